@@ -12,7 +12,7 @@ use holochain_core_types::{
 };
 
 /// Object holding an Agent's identity.
-#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
 pub struct Identity(Content);
 
 impl Identity {
@@ -34,7 +34,7 @@ impl From<String> for Identity {
 }
 
 /// Object holding all Agent's data.
-#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
 pub struct Agent(Identity);
 
 impl Agent {
@@ -1,7 +1,7 @@
 use futures::executor::block_on;
 use holochain_core_types::{
     cas::content::{Address, Content},
-    eav::{Attribute, Entity, EntityAttributeValue, Value},
+    eav::{Attribute, EavQuery, Entity, EntityAttributeValue, QueryPlan, Value},
     error::HolochainError,
 };
 use riker::actors::*;
@@ -23,11 +23,26 @@ pub enum Protocol {
     CasContains(Address),
     CasContainsResult(Result<bool, HolochainError>),
 
+    CasAddresses,
+    CasAddressesResult(Result<Vec<Address>, HolochainError>),
+
+    CasRemove(Address),
+    CasRemoveResult(Result<(), HolochainError>),
+
     EavAdd(EntityAttributeValue),
     EavAddResult(Result<(), HolochainError>),
 
     EavFetch(Option<Entity>, Option<Attribute>, Option<Value>),
     EavFetchResult(Result<HashSet<EntityAttributeValue>, HolochainError>),
+
+    EavExplain(EavQuery),
+    EavExplainResult(Result<QueryPlan, HolochainError>),
+
+    EavAddAttributeIndex(Attribute),
+    EavAddAttributeIndexResult(Result<(), HolochainError>),
+
+    EavRemove(EntityAttributeValue),
+    EavRemoveResult(Result<(), HolochainError>),
 }
 
 /// required by riker
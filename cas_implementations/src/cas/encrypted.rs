@@ -0,0 +1,269 @@
+//! Encryption at rest for the content of any storage.
+//!
+//! Wrapping a storage, e.g. a FilesystemStorage, in an EncryptedStorage seals the content with
+//! ChaCha20-Poly1305 before it reaches the storage and opens it again when it is fetched.
+//! Addresses are computed over the plaintext, so they are the same as without encryption.
+//! The address is authenticated along with the content, so sealed content moved to another
+//! address doesn't open. Keeping the key is up to the caller.
+
+use hex;
+use holochain_core_types::{
+    cas::{
+        content::{Address, AddressableContent, Content},
+        storage::ContentAddressableStorage,
+    },
+    error::HolochainError,
+};
+use ring::{
+    aead::{self, OpeningKey, SealingKey, CHACHA20_POLY1305},
+    rand::{SecureRandom, SystemRandom},
+};
+use std::fmt;
+
+/// number of bytes of a StorageKey
+pub const STORAGE_KEY_LEN: usize = 32;
+
+/// Secret key the content of an EncryptedStorage is sealed with
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct StorageKey([u8; STORAGE_KEY_LEN]);
+
+impl StorageKey {
+    pub fn new(bytes: [u8; STORAGE_KEY_LEN]) -> Self {
+        StorageKey(bytes)
+    }
+}
+
+/// keeps the key out of logs
+impl fmt::Debug for StorageKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "StorageKey(..)")
+    }
+}
+
+/// sealed content stored at the address of its plaintext
+struct Sealed {
+    address: Address,
+    content: Content,
+}
+
+impl AddressableContent for Sealed {
+    fn address(&self) -> Address {
+        self.address.clone()
+    }
+
+    fn content(&self) -> Content {
+        self.content.clone()
+    }
+
+    fn from_content(content: &Content) -> Self {
+        Sealed {
+            address: content.address(),
+            content: content.clone(),
+        }
+    }
+}
+
+fn encryption_error(reason: &str) -> HolochainError {
+    HolochainError::ErrorGeneric(format!("content encryption failed: {}", reason))
+}
+
+/// the content sealed with the key under a random nonce, as hex of the nonce and the ciphertext
+/// the address the content is stored at is authenticated with it, @see open()
+pub fn seal(
+    key: &StorageKey,
+    address: &Address,
+    content: &Content,
+) -> Result<Content, HolochainError> {
+    let sealing_key =
+        SealingKey::new(&CHACHA20_POLY1305, &key.0).map_err(|_| encryption_error("invalid key"))?;
+    let mut nonce = vec![0; CHACHA20_POLY1305.nonce_len()];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| encryption_error("no nonce"))?;
+    let tag_len = CHACHA20_POLY1305.tag_len();
+    let mut in_out = content.as_bytes().to_vec();
+    in_out.extend(vec![0; tag_len]);
+    let sealed_len = aead::seal_in_place(
+        &sealing_key,
+        &nonce,
+        address.to_string().as_bytes(),
+        &mut in_out,
+        tag_len,
+    )
+    .map_err(|_| encryption_error("sealing failed"))?;
+    in_out.truncate(sealed_len);
+    nonce.extend(in_out);
+    Ok(hex::encode(&nonce))
+}
+
+/// the plaintext of content sealed with seal() for the address
+/// fails if the content wasn't sealed with the key for the address or was tampered with
+pub fn open(
+    key: &StorageKey,
+    address: &Address,
+    sealed: &Content,
+) -> Result<Content, HolochainError> {
+    let opening_key =
+        OpeningKey::new(&CHACHA20_POLY1305, &key.0).map_err(|_| encryption_error("invalid key"))?;
+    let nonce_len = CHACHA20_POLY1305.nonce_len();
+    let mut bytes = hex::decode(sealed)
+        .ok()
+        .filter(|bytes| bytes.len() >= nonce_len)
+        .ok_or_else(|| encryption_error("content is not sealed"))?;
+    let mut in_out = bytes.split_off(nonce_len);
+    let plaintext = aead::open_in_place(
+        &opening_key,
+        &bytes,
+        address.to_string().as_bytes(),
+        0,
+        &mut in_out,
+    )
+    .map_err(|_| encryption_error("wrong key, address or tampered content"))?;
+    String::from_utf8(plaintext.to_vec()).map_err(|_| encryption_error("content is not UTF-8"))
+}
+
+/// A storage holding the content of another sealed with a key
+#[derive(Clone)]
+pub struct EncryptedStorage<CAS>
+where
+    CAS: ContentAddressableStorage,
+{
+    storage: CAS,
+    key: StorageKey,
+}
+
+impl<CAS> EncryptedStorage<CAS>
+where
+    CAS: ContentAddressableStorage,
+{
+    pub fn new(storage: CAS, key: StorageKey) -> Self {
+        EncryptedStorage { storage, key }
+    }
+
+    /// the wrapped storage, holding the sealed content
+    pub fn inner(&self) -> &CAS {
+        &self.storage
+    }
+}
+
+impl<CAS> ContentAddressableStorage for EncryptedStorage<CAS>
+where
+    CAS: ContentAddressableStorage,
+{
+    fn add(&mut self, content: &AddressableContent) -> Result<(), HolochainError> {
+        let address = content.address();
+        let sealed = Sealed {
+            content: seal(&self.key, &address, &content.content())?,
+            address,
+        };
+        self.storage.add(&sealed)
+    }
+
+    fn contains(&self, address: &Address) -> Result<bool, HolochainError> {
+        self.storage.contains(address)
+    }
+
+    fn fetch<AC: AddressableContent>(
+        &self,
+        address: &Address,
+    ) -> Result<Option<AC>, HolochainError> {
+        match self.storage.fetch::<Content>(address)? {
+            Some(sealed) => Ok(Some(AC::from_content(&open(&self.key, address, &sealed)?))),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use cas::file::tests::test_file_cas;
+    use holochain_core_types::cas::content::ExampleAddressableContent;
+    use std::fs::read_to_string;
+    use tempfile::TempDir;
+    use walkdir::WalkDir;
+
+    fn test_key(byte: u8) -> StorageKey {
+        StorageKey::new([byte; STORAGE_KEY_LEN])
+    }
+
+    #[test]
+    /// the files only hold ciphertext, fetches give back the plaintext at its usual address
+    fn file_content_is_encrypted_at_rest() {
+        let (file_cas, dir) = test_file_cas();
+        let mut cas = EncryptedStorage::new(file_cas.clone(), test_key(7));
+        let content = ExampleAddressableContent::from_content(&"top secret".to_string());
+        cas.add(&content).unwrap();
+
+        assert_eq!(Ok(true), cas.contains(&content.address()));
+        assert_eq!(Ok(Some(content.clone())), cas.fetch(&content.address()));
+        // stored under the address the plaintext has without encryption
+        let (mut plain_cas, plain_dir) = test_file_cas();
+        plain_cas.add(&content).unwrap();
+        let files = |dir: &TempDir| -> Vec<(String, String)> {
+            WalkDir::new(dir.path())
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_file())
+                .map(|entry| {
+                    (
+                        entry.file_name().to_string_lossy().to_string(),
+                        read_to_string(entry.path()).unwrap(),
+                    )
+                })
+                .collect()
+        };
+        let encrypted_files = files(&dir);
+        let plain_files = files(&plain_dir);
+        assert_eq!(1, encrypted_files.len());
+        assert_eq!(plain_files[0].0, encrypted_files[0].0);
+        assert_eq!("top secret", plain_files[0].1);
+        assert!(!encrypted_files[0].1.contains("top secret"));
+        assert_ne!(
+            Ok(Some(content.clone())),
+            file_cas.fetch::<ExampleAddressableContent>(&content.address())
+        );
+
+        let wrong_key = EncryptedStorage::new(file_cas, test_key(8));
+        assert!(wrong_key
+            .fetch::<ExampleAddressableContent>(&content.address())
+            .is_err());
+    }
+
+    #[test]
+    /// sealing the same content twice gives different ciphertexts that open the same
+    fn sealing_uses_fresh_nonces() {
+        let content = "top secret".to_string();
+        let address = content.address();
+        let first = seal(&test_key(7), &address, &content).unwrap();
+        let second = seal(&test_key(7), &address, &content).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(Ok(content.clone()), open(&test_key(7), &address, &first));
+        assert_eq!(Ok(content), open(&test_key(7), &address, &second));
+        assert!(open(&test_key(7), &address, &"not sealed".to_string()).is_err());
+    }
+
+    #[test]
+    /// sealed content copied to the address of other content doesn't open there
+    fn sealed_content_is_bound_to_its_address() {
+        let (file_cas, _dir) = test_file_cas();
+        let mut cas = EncryptedStorage::new(file_cas.clone(), test_key(7));
+        let secret = ExampleAddressableContent::from_content(&"top secret".to_string());
+        let public = ExampleAddressableContent::from_content(&"public".to_string());
+        cas.add(&secret).unwrap();
+        cas.add(&public).unwrap();
+
+        let sealed: Content = file_cas.fetch(&secret.address()).unwrap().unwrap();
+        file_cas
+            .clone()
+            .add(&Sealed {
+                address: public.address(),
+                content: sealed,
+            })
+            .unwrap();
+        assert!(cas
+            .fetch::<ExampleAddressableContent>(&public.address())
+            .is_err());
+        assert_eq!(Ok(Some(secret.clone())), cas.fetch(&secret.address()));
+    }
+}
@@ -65,6 +65,17 @@ impl MemoryStorageActor {
     fn unthreadable_fetch(&self, address: &Address) -> Result<Option<Content>, HolochainError> {
         Ok(self.storage.get(address).cloned())
     }
+
+    fn unthreadable_remove(&mut self, address: &Address) -> Result<(), HolochainError> {
+        self.storage.remove(address);
+        Ok(())
+    }
+
+    fn unthreadable_addresses(&self) -> Result<Vec<Address>, HolochainError> {
+        let mut addresses: Vec<Address> = self.storage.keys().cloned().collect();
+        addresses.sort();
+        Ok(addresses)
+    }
 }
 
 impl Actor for MemoryStorageActor {
@@ -88,6 +99,12 @@ impl Actor for MemoryStorageActor {
                     Protocol::CasFetch(address) => {
                         Protocol::CasFetchResult(self.unthreadable_fetch(&address))
                     }
+                    Protocol::CasAddresses => {
+                        Protocol::CasAddressesResult(self.unthreadable_addresses())
+                    }
+                    Protocol::CasRemove(address) => {
+                        Protocol::CasRemoveResult(self.unthreadable_remove(&address))
+                    }
                     _ => unreachable!(),
                 },
                 Some(context.myself()),
@@ -1,33 +1,98 @@
 mod actor;
 use actor::{AskSelf, Protocol};
-use cas::memory::actor::MemoryStorageActor;
+use cas::{
+    encrypted::{open, seal, StorageKey},
+    memory::actor::MemoryStorageActor,
+};
 use holochain_core_types::{
     cas::{
-        content::{Address, AddressableContent},
+        content::{Address, AddressableContent, Content},
         storage::ContentAddressableStorage,
     },
     error::HolochainError,
 };
 use riker::actors::*;
 
+/// content storage in memory, its clones share their content
+/// a storage made with new_sealed() holds its content sealed with the key, like an
+/// EncryptedStorage, @see cas::encrypted
 #[derive(Clone, Debug, PartialEq)]
 pub struct MemoryStorage {
     actor: ActorRef<Protocol>,
+    key: Option<StorageKey>,
 }
 
 impl MemoryStorage {
     pub fn new() -> Result<MemoryStorage, HolochainError> {
         Ok(MemoryStorage {
             actor: MemoryStorageActor::new_ref()?,
+            key: None,
+        })
+    }
+
+    /// an empty storage sealing what it is given with the key
+    pub fn new_sealed(key: StorageKey) -> Result<MemoryStorage, HolochainError> {
+        Ok(MemoryStorage {
+            actor: MemoryStorageActor::new_ref()?,
+            key: Some(key),
         })
     }
+
+    /// the key the content is sealed with, None if it is held in plaintext
+    pub fn storage_key(&self) -> Option<StorageKey> {
+        self.key
+    }
+
+    /// the addresses of all the content in the storage, sorted
+    pub fn addresses(&self) -> Result<Vec<Address>, HolochainError> {
+        let response = self.actor.block_on_ask(Protocol::CasAddresses)?;
+        unwrap_to!(response => Protocol::CasAddressesResult).clone()
+    }
+
+    /// the content at the address as it is held, sealed if the storage has a key
+    /// @see MemoryStorage::add_sealed() to put it back
+    pub fn fetch_sealed(&self, address: &Address) -> Result<Option<Content>, HolochainError> {
+        let response = self
+            .actor
+            .block_on_ask(Protocol::CasFetch(address.clone()))?;
+        unwrap_to!(response => Protocol::CasFetchResult).clone()
+    }
+
+    /// holds the content at the address as it is, without sealing it again
+    /// the content has to be sealed with the key of the storage for the address, as
+    /// fetch_sealed() gives it, or fetches fail
+    pub fn add_sealed(
+        &mut self,
+        address: &Address,
+        content: &Content,
+    ) -> Result<(), HolochainError> {
+        let response = self
+            .actor
+            .block_on_ask(Protocol::CasAdd(address.clone(), content.clone()))?;
+        unwrap_to!(response => Protocol::CasAddResult).clone()
+    }
+
+    /// takes the content at the address out of the storage, e.g. to undo adding it
+    /// content addressed storages keep content forever otherwise, removing content other
+    /// content refers to leaves that content dangling
+    pub fn remove(&mut self, address: &Address) -> Result<(), HolochainError> {
+        let response = self
+            .actor
+            .block_on_ask(Protocol::CasRemove(address.clone()))?;
+        unwrap_to!(response => Protocol::CasRemoveResult).clone()
+    }
 }
 
 impl ContentAddressableStorage for MemoryStorage {
     fn add(&mut self, content: &AddressableContent) -> Result<(), HolochainError> {
+        let address = content.address();
+        let content = match self.key {
+            Some(ref key) => seal(key, &address, &content.content())?,
+            None => content.content(),
+        };
         let response = self
             .actor
-            .block_on_ask(Protocol::CasAdd(content.address(), content.content()))?;
+            .block_on_ask(Protocol::CasAdd(address, content))?;
         unwrap_to!(response => Protocol::CasAddResult).clone()
     }
 
@@ -46,19 +111,23 @@ impl ContentAddressableStorage for MemoryStorage {
             .actor
             .block_on_ask(Protocol::CasFetch(address.clone()))?;
         let content = unwrap_to!(response => Protocol::CasFetchResult).clone()?;
-        Ok(match content {
-            Some(c) => Some(AC::from_content(&c)),
-            None => None,
+        Ok(match (content, self.key) {
+            (Some(sealed), Some(ref key)) => Some(AC::from_content(&open(key, address, &sealed)?)),
+            (Some(c), None) => Some(AC::from_content(&c)),
+            (None, _) => None,
         })
     }
 }
 
 #[cfg(test)]
 pub mod tests {
-    use cas::memory::MemoryStorage;
+    use cas::{
+        encrypted::{StorageKey, STORAGE_KEY_LEN},
+        memory::MemoryStorage,
+    };
     use holochain_core_types::cas::{
-        content::{ExampleAddressableContent, OtherExampleAddressableContent},
-        storage::StorageTestSuite,
+        content::{AddressableContent, ExampleAddressableContent, OtherExampleAddressableContent},
+        storage::{ContentAddressableStorage, StorageTestSuite},
     };
 
     pub fn test_memory_storage() -> MemoryStorage {
@@ -74,4 +143,58 @@ pub mod tests {
         );
     }
 
+    #[test]
+    /// all the stored addresses are listed, shared by clones of the storage
+    fn memory_addresses() {
+        let mut storage = test_memory_storage();
+        assert_eq!(Ok(vec![]), storage.addresses());
+
+        let foo = ExampleAddressableContent::from_content(&String::from("foo"));
+        let bar = ExampleAddressableContent::from_content(&String::from("bar"));
+        storage.add(&foo).expect("could not add to storage");
+        storage.clone().add(&bar).expect("could not add to storage");
+
+        let mut expected = vec![foo.address(), bar.address()];
+        expected.sort();
+        assert_eq!(Ok(expected), storage.addresses());
+    }
+
+    #[test]
+    /// a sealed storage holds ciphertext at the addresses of the plaintext
+    fn memory_sealed_round_trip() {
+        let key = StorageKey::new([7; STORAGE_KEY_LEN]);
+        let mut storage = MemoryStorage::new_sealed(key).unwrap();
+        let content = ExampleAddressableContent::from_content(&String::from("top secret"));
+        storage.add(&content).unwrap();
+
+        assert_eq!(Ok(vec![content.address()]), storage.addresses());
+        assert_eq!(Ok(Some(content.clone())), storage.fetch(&content.address()));
+        let plain = MemoryStorage {
+            key: None,
+            ..storage.clone()
+        };
+        let sealed: Option<String> = plain.fetch(&content.address()).unwrap();
+        assert!(!sealed.clone().unwrap().contains("top secret"));
+
+        // the sealed content moves to another storage with the key as it is
+        assert_eq!(Ok(sealed.clone()), storage.fetch_sealed(&content.address()));
+        let mut copy = MemoryStorage::new_sealed(key).unwrap();
+        copy.add_sealed(&content.address(), &sealed.unwrap())
+            .unwrap();
+        assert_eq!(Ok(Some(content.clone())), copy.fetch(&content.address()));
+    }
+
+    #[test]
+    /// removed content is gone for the clones of the storage, too
+    fn memory_remove() {
+        let mut storage = MemoryStorage::new().unwrap();
+        let content = ExampleAddressableContent::from_content(&String::from("foo"));
+        storage.add(&content).unwrap();
+        let clone = storage.clone();
+
+        storage.remove(&content.address()).unwrap();
+        assert_eq!(Ok(false), clone.contains(&content.address()));
+        assert_eq!(Ok(Vec::new()), clone.addresses());
+        assert_eq!(Ok(()), storage.remove(&content.address()));
+    }
 }
@@ -1,2 +1,3 @@
+pub mod encrypted;
 pub mod file;
 pub mod memory;
@@ -1,11 +1,11 @@
 use actor::{Protocol, SYS};
 use holochain_core_types::{
-    eav::{Attribute, Entity, EntityAttributeValue, Value},
+    eav::{Attribute, EavQuery, Entity, EntityAttributeValue, QueryPlan, Value},
     error::{HcResult, HolochainError},
 };
 use riker::actors::*;
 use snowflake;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 const ACTOR_ID_ROOT: &'static str = "/eav_memory_actor/";
 
@@ -19,12 +19,20 @@ fn actor_id() -> String {
 
 pub struct EavMemoryStorageActor {
     storage: HashSet<EntityAttributeValue>,
+    /// the EAVs of the indexed attributes, by attribute
+    attribute_indexes: HashMap<Attribute, HashSet<EntityAttributeValue>>,
+}
+
+/// name of the index of the attribute in query plans
+fn attribute_index_name(attribute: &Attribute) -> String {
+    format!("attribute:{}", attribute)
 }
 
 impl EavMemoryStorageActor {
     pub fn new() -> EavMemoryStorageActor {
         EavMemoryStorageActor {
             storage: HashSet::new(),
+            attribute_indexes: HashMap::new(),
         }
     }
 
@@ -44,7 +52,8 @@ impl EavMemoryStorageActor {
             // always return the same reference to the same actor for the same path
             // consistency here provides safety for CAS methods
             &actor_id(),
-        ).map_err(|actor_create_error| {
+        )
+        .map_err(|actor_create_error| {
             HolochainError::ErrorGeneric(format!(
                 "Failed to create actor in system: {:?}",
                 actor_create_error
@@ -53,10 +62,53 @@ impl EavMemoryStorageActor {
     }
 
     fn unthreadable_add_eav(&mut self, eav: &EntityAttributeValue) -> HcResult<()> {
+        if let Some(index) = self.attribute_indexes.get_mut(&eav.attribute()) {
+            index.insert(eav.clone());
+        }
         self.storage.insert(eav.clone());
         Ok(())
     }
 
+    fn unthreadable_remove_eav(&mut self, eav: &EntityAttributeValue) -> HcResult<()> {
+        if let Some(index) = self.attribute_indexes.get_mut(&eav.attribute()) {
+            index.remove(eav);
+        }
+        self.storage.remove(eav);
+        Ok(())
+    }
+
+    fn unthreadable_add_attribute_index(&mut self, attribute: &Attribute) -> HcResult<()> {
+        let index: HashSet<EntityAttributeValue> = self
+            .storage
+            .iter()
+            .filter(|eav| &eav.attribute() == attribute)
+            .cloned()
+            .collect();
+        self.attribute_indexes.insert(attribute.clone(), index);
+        Ok(())
+    }
+
+    /// the EAVs a query constrained to the attribute has to look at, all of them unless
+    /// the attribute is indexed
+    fn candidates(&self, attribute: &Option<Attribute>) -> &HashSet<EntityAttributeValue> {
+        attribute
+            .as_ref()
+            .and_then(|attribute| self.attribute_indexes.get(attribute))
+            .unwrap_or(&self.storage)
+    }
+
+    fn unthreadable_explain_eav(&self, query: &EavQuery) -> HcResult<QueryPlan> {
+        let plan = match query.attribute {
+            Some(ref attribute) if self.attribute_indexes.contains_key(attribute) => QueryPlan {
+                index: Some(attribute_index_name(attribute)),
+                estimated_rows: self.attribute_indexes[attribute].len(),
+                full_scan: false,
+            },
+            _ => QueryPlan::full_scan(self.storage.len()),
+        };
+        Ok(plan)
+    }
+
     fn unthreadable_fetch_eav(
         &self,
         entity: Option<Entity>,
@@ -64,7 +116,7 @@ impl EavMemoryStorageActor {
         value: Option<Value>,
     ) -> Result<HashSet<EntityAttributeValue>, HolochainError> {
         Ok(self
-            .storage
+            .candidates(&attribute)
             .iter()
             .cloned()
             .filter(|e| EntityAttributeValue::filter_on_eav::<Entity>(e.entity(), &entity))
@@ -92,6 +144,17 @@ impl Actor for EavMemoryStorageActor {
                     Protocol::EavFetch(e, a, v) => {
                         Protocol::EavFetchResult(self.unthreadable_fetch_eav(e, a, v))
                     }
+                    Protocol::EavExplain(query) => {
+                        Protocol::EavExplainResult(self.unthreadable_explain_eav(&query))
+                    }
+                    Protocol::EavAddAttributeIndex(attribute) => {
+                        Protocol::EavAddAttributeIndexResult(
+                            self.unthreadable_add_attribute_index(&attribute),
+                        )
+                    }
+                    Protocol::EavRemove(eav) => {
+                        Protocol::EavRemoveResult(self.unthreadable_remove_eav(&eav))
+                    }
                     _ => unreachable!(),
                 },
                 Some(context.myself()),
@@ -2,7 +2,10 @@ pub mod actor;
 use actor::{AskSelf, Protocol};
 use eav::memory::actor::EavMemoryStorageActor;
 use holochain_core_types::{
-    eav::{Attribute, Entity, EntityAttributeValue, EntityAttributeValueStorage, Value},
+    eav::{
+        Attribute, EavQuery, Entity, EntityAttributeValue, EntityAttributeValueStorage, QueryPlan,
+        Value,
+    },
     error::HolochainError,
 };
 use riker::actors::*;
@@ -37,15 +40,38 @@ impl EntityAttributeValueStorage for EavMemoryStorage {
             .block_on_ask(Protocol::EavFetch(entity, attribute, value))?;
         unwrap_to!(response => Protocol::EavFetchResult).clone()
     }
+
+    fn explain_eav(&self, query: &EavQuery) -> Result<QueryPlan, HolochainError> {
+        let response = self
+            .actor
+            .block_on_ask(Protocol::EavExplain(query.clone()))?;
+        unwrap_to!(response => Protocol::EavExplainResult).clone()
+    }
+
+    fn add_attribute_index(&mut self, attribute: &Attribute) -> Result<(), HolochainError> {
+        let response = self
+            .actor
+            .block_on_ask(Protocol::EavAddAttributeIndex(attribute.clone()))?;
+        unwrap_to!(response => Protocol::EavAddAttributeIndexResult).clone()
+    }
+
+    fn remove_eav(&mut self, eav: &EntityAttributeValue) -> Result<(), HolochainError> {
+        let response = self.actor.block_on_ask(Protocol::EavRemove(eav.clone()))?;
+        unwrap_to!(response => Protocol::EavRemoveResult).clone()
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
     use eav::memory::EavMemoryStorage;
-    use holochain_core_types::cas::{
-        content::{AddressableContent, ExampleAddressableContent},
-        storage::EavTestSuite,
+    use holochain_core_types::{
+        cas::{
+            content::{AddressableContent, ExampleAddressableContent},
+            storage::EavTestSuite,
+        },
+        eav::{EavQuery, EntityAttributeValue, EntityAttributeValueStorage, QueryPlan},
     };
+    use std::collections::HashSet;
 
     #[test]
     fn memory_eav_round_trip() {
@@ -60,6 +86,74 @@ pub mod tests {
         )
     }
 
+    #[test]
+    /// queries constrained to an indexed attribute only look at its EAVs
+    fn memory_eav_attribute_index() {
+        let mut eav_storage =
+            EavMemoryStorage::new().expect("could not construct new eav memory storage");
+        let entity = ExampleAddressableContent::from_content(&"foo".to_string()).address();
+        let value = ExampleAddressableContent::from_content(&"blue".to_string()).address();
+        for attribute in vec!["link__tag", "colour", "size"] {
+            let eav = EntityAttributeValue::new(&entity, &attribute.to_string(), &value);
+            eav_storage.add_eav(&eav).unwrap();
+        }
+        let query = EavQuery {
+            attribute: Some("link__tag".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(Ok(QueryPlan::full_scan(3)), eav_storage.explain_eav(&query));
+
+        eav_storage
+            .add_attribute_index(&"link__tag".to_string())
+            .unwrap();
+        let eav = EntityAttributeValue::new(&value, &"link__tag".to_string(), &entity);
+        eav_storage.add_eav(&eav).unwrap();
+        assert_eq!(
+            Ok(QueryPlan {
+                index: Some("attribute:link__tag".to_string()),
+                estimated_rows: 2,
+                full_scan: false,
+            }),
+            eav_storage.explain_eav(&query)
+        );
+        assert_eq!(
+            2,
+            eav_storage
+                .fetch_eav(None, Some("link__tag".to_string()), None)
+                .unwrap()
+                .len()
+        );
+        assert_eq!(
+            1,
+            eav_storage
+                .fetch_eav(Some(entity), Some("link__tag".to_string()), None)
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    /// removed EAVs are gone from the storage and from the indexes
+    fn memory_eav_remove() {
+        let mut eav_storage =
+            EavMemoryStorage::new().expect("could not construct new eav memory storage");
+        let entity = ExampleAddressableContent::from_content(&"foo".to_string()).address();
+        let value = ExampleAddressableContent::from_content(&"blue".to_string()).address();
+        let attribute = "link__tag".to_string();
+        eav_storage.add_attribute_index(&attribute).unwrap();
+        let eav = EntityAttributeValue::new(&entity, &attribute, &value);
+        eav_storage.add_eav(&eav).unwrap();
+
+        eav_storage.remove_eav(&eav).unwrap();
+        assert_eq!(Ok(HashSet::new()), eav_storage.fetch_eav(None, None, None));
+        assert_eq!(
+            Ok(HashSet::new()),
+            eav_storage.fetch_eav(None, Some(attribute), None)
+        );
+        // removing what isn't there is fine
+        assert_eq!(Ok(()), eav_storage.remove_eav(&eav));
+    }
+
     #[test]
     fn memory_eav_one_to_many() {
         let eav_storage =
@@ -6,12 +6,14 @@
 //! which are defined but not implemented in the core_types crate.
 
 extern crate futures;
+extern crate hex;
 extern crate holochain_core_types;
 #[macro_use]
 extern crate lazy_static;
 extern crate riker;
 extern crate riker_default;
 extern crate riker_patterns;
+extern crate ring;
 #[macro_use]
 extern crate unwrap_to;
 extern crate snowflake;
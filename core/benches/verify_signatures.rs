@@ -0,0 +1,43 @@
+//! Serial against parallel verification of entry signatures, run with `cargo bench`
+
+#![feature(test)]
+
+extern crate holochain_core;
+extern crate holochain_core_types;
+extern crate test;
+
+use holochain_core::agent::{
+    keys::KeyPair,
+    signatures::{entry_signature, verify_entry_signatures, DEFAULT_VERIFY_WORKERS},
+};
+use holochain_core_types::{
+    cas::content::Address,
+    entry::{test_entry_unique, Entry},
+    signature::Signature,
+};
+use test::Bencher;
+
+const ENTRIES: usize = 2_000;
+
+fn signed_entries() -> Vec<(Entry, Signature, Address)> {
+    let key_pair = KeyPair::generate().unwrap();
+    (0..ENTRIES)
+        .map(|_| {
+            let entry = test_entry_unique();
+            let signature = entry_signature(&key_pair, &entry);
+            (entry, signature, Address::from(key_pair.public_key()))
+        })
+        .collect()
+}
+
+#[bench]
+fn verify_serially(bencher: &mut Bencher) {
+    let items = signed_entries();
+    bencher.iter(|| verify_entry_signatures(items.clone(), 1));
+}
+
+#[bench]
+fn verify_in_parallel(bencher: &mut Bencher) {
+    let items = signed_entries();
+    bencher.iter(|| verify_entry_signatures(items.clone(), DEFAULT_VERIFY_WORKERS));
+}
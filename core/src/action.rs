@@ -1,17 +1,30 @@
-use agent::state::AgentState;
+use agent::{
+    actions::{commit::CommitOptions, transaction::Transaction},
+    state::AgentState,
+};
 use context::Context;
+use dht::{
+    link_export::{LinkExport, LinkImportMode},
+    metadata::EntryMetadata,
+};
 use holochain_core_types::{
-    cas::content::Address, entry::Entry, get_links_args::GetLinksArgs, links_entry::Link,
+    cas::content::{Address, Content},
+    entry::Entry,
+    entry_type::EntryType,
+    error::HolochainError,
+    get_links_args::GetLinksArgs,
+    links_entry::Link,
 };
 use holochain_dna::Dna;
 use nucleus::{
     state::{NucleusState, ValidationResult},
-    ZomeFnCall, ZomeFnResult,
+    ProgressUpdate, ZomeFnCall, ZomeFnResult,
 };
 use snowflake;
 use std::{
     hash::{Hash, Hasher},
     sync::Arc,
+    time::Duration,
 };
 
 /// Wrapper for actions that provides a unique ID
@@ -71,11 +84,50 @@ pub enum Action {
     /// entry to Commit
     /// MUST already have passed all callback checks
     Commit(Entry),
+    /// entry to Commit with a delegate agent and/or content type
+    /// the delegation proof MUST already have been verified
+    CommitWithOptions(Entry, CommitOptions),
+    /// take the entry at the address, committed last, back off the source chain and reject
+    /// it in the DHT, e.g. because it violates an invariant of the DNA
+    RollBackCommit(Address),
     /// GetEntry by address
     GetEntry(Address),
+    /// ask peers for the addresses of the entries of the given type
+    GetEntriesByType(EntryType),
+    /// drop the ephemeral entries committed longer ago than the given duration
+    ExpireEphemeral(Duration),
+    /// mark the entries whose time to live has passed as deleted
+    ExpireEntries,
+    /// exempt the address from eviction from the network cache
+    PinEntry(Address),
+    /// make the address evictable from the network cache again
+    UnpinEntry(Address),
+    /// forget which addresses the network didn't have, so they are fetched again
+    ClearNegativeCache,
+    /// store the entries of a peer's snapshot of the DHT in bulk
+    /// the snapshot MUST already have been verified, @see DhtExport::verify()
+    LoadDhtSnapshot(Vec<Entry>),
+    /// store the links of a link graph snapshot, @see DhtStore::import_links()
+    ImportLinks(LinkExport, LinkImportMode),
+
+    /// publish all the entries queued while auto publish was disabled
+    FlushPublishes,
+    /// return how the publish of the entry at the address ended
+    /// @see NetworkBackend::publish_async()
+    ReturnPublishResult(Address, Result<(), HolochainError>),
+    /// return what the peers answered to the GetEntry action, None if they don't hold it
+    /// @see NetworkBackend::get_async()
+    ReturnGetResult(Box<ActionWrapper>, Result<Option<Content>, HolochainError>),
 
     /// link to add
     AddLink(Link),
+    /// links to mark as removed, e.g. orphaned links, @see DhtStore::orphaned_links()
+    RemoveLinks(Vec<Link>),
+    /// signed metadata to attach to its entry, @see DhtStore::attach_metadata()
+    AttachMetadata(EntryMetadata),
+    /// entries to commit and links to add as one unit
+    /// the entries MUST already have passed all callback checks
+    Transaction(Transaction),
     /// get links from entry address and attribute-name
     GetLinks(GetLinksArgs),
 
@@ -83,6 +135,8 @@ pub enum Action {
     ExecuteZomeFunction(ZomeFnCall),
     /// return the result of a zome WASM function call
     ReturnZomeFunctionResult(ZomeFnResult),
+    /// progress reported by a running zome function call
+    ReportProgress(ZomeFnCall, ProgressUpdate),
 
     /// initialize an application from a Dna
     /// not the same as genesis
@@ -101,6 +155,77 @@ pub enum Action {
     ReturnValidationResult(((snowflake::ProcessUniqueId, Address), ValidationResult)),
 }
 
+bitflags! {
+    /// selects Action variants, e.g. to subscribe to only some of the actions
+    pub struct ActionFilter: u32 {
+        /// Commit and CommitWithOptions
+        const COMMIT = 1 << 0;
+        const GET_ENTRY = 1 << 1;
+        const FLUSH_PUBLISHES = 1 << 2;
+        const ADD_LINK = 1 << 3;
+        const GET_LINKS = 1 << 4;
+        const EXECUTE_ZOME_FUNCTION = 1 << 5;
+        const RETURN_ZOME_FUNCTION_RESULT = 1 << 6;
+        const INIT_APPLICATION = 1 << 7;
+        const RETURN_INITIALIZATION_RESULT = 1 << 8;
+        const CALL = 1 << 9;
+        const RETURN_VALIDATION_RESULT = 1 << 10;
+        const EXPIRE_EPHEMERAL = 1 << 11;
+        const REPORT_PROGRESS = 1 << 12;
+        /// PinEntry and UnpinEntry
+        const PIN_ENTRY = 1 << 13;
+        const GET_ENTRIES_BY_TYPE = 1 << 14;
+        const EXPIRE_ENTRIES = 1 << 15;
+        const TRANSACTION = 1 << 16;
+        const CLEAR_NEGATIVE_CACHE = 1 << 17;
+        const LOAD_DHT_SNAPSHOT = 1 << 18;
+        const RETURN_PUBLISH_RESULT = 1 << 19;
+        const ROLL_BACK_COMMIT = 1 << 20;
+        const IMPORT_LINKS = 1 << 21;
+        const REMOVE_LINKS = 1 << 22;
+        const ATTACH_METADATA = 1 << 23;
+        const RETURN_GET_RESULT = 1 << 24;
+    }
+}
+
+impl ActionFilter {
+    /// the filter selecting only the variant of the given action
+    pub fn of(action: &Action) -> ActionFilter {
+        match action {
+            Action::Commit(_) | Action::CommitWithOptions(_, _) => ActionFilter::COMMIT,
+            Action::RollBackCommit(_) => ActionFilter::ROLL_BACK_COMMIT,
+            Action::GetEntry(_) => ActionFilter::GET_ENTRY,
+            Action::GetEntriesByType(_) => ActionFilter::GET_ENTRIES_BY_TYPE,
+            Action::ExpireEphemeral(_) => ActionFilter::EXPIRE_EPHEMERAL,
+            Action::ExpireEntries => ActionFilter::EXPIRE_ENTRIES,
+            Action::PinEntry(_) | Action::UnpinEntry(_) => ActionFilter::PIN_ENTRY,
+            Action::ClearNegativeCache => ActionFilter::CLEAR_NEGATIVE_CACHE,
+            Action::LoadDhtSnapshot(_) => ActionFilter::LOAD_DHT_SNAPSHOT,
+            Action::ImportLinks(_, _) => ActionFilter::IMPORT_LINKS,
+            Action::FlushPublishes => ActionFilter::FLUSH_PUBLISHES,
+            Action::ReturnPublishResult(_, _) => ActionFilter::RETURN_PUBLISH_RESULT,
+            Action::ReturnGetResult(_, _) => ActionFilter::RETURN_GET_RESULT,
+            Action::AddLink(_) => ActionFilter::ADD_LINK,
+            Action::RemoveLinks(_) => ActionFilter::REMOVE_LINKS,
+            Action::AttachMetadata(_) => ActionFilter::ATTACH_METADATA,
+            Action::Transaction(_) => ActionFilter::TRANSACTION,
+            Action::GetLinks(_) => ActionFilter::GET_LINKS,
+            Action::ExecuteZomeFunction(_) => ActionFilter::EXECUTE_ZOME_FUNCTION,
+            Action::ReturnZomeFunctionResult(_) => ActionFilter::RETURN_ZOME_FUNCTION_RESULT,
+            Action::ReportProgress(_, _) => ActionFilter::REPORT_PROGRESS,
+            Action::InitApplication(_) => ActionFilter::INIT_APPLICATION,
+            Action::ReturnInitializationResult(_) => ActionFilter::RETURN_INITIALIZATION_RESULT,
+            Action::Call(_) => ActionFilter::CALL,
+            Action::ReturnValidationResult(_) => ActionFilter::RETURN_VALIDATION_RESULT,
+        }
+    }
+
+    /// true if the filter selects the variant of the action
+    pub fn matches(&self, action: &Action) -> bool {
+        self.intersects(ActionFilter::of(action))
+    }
+}
+
 /// function signature for action handler functions
 // @TODO merge these into a single signature
 // @see https://github.com/holochain/holochain-rust/issues/194
@@ -111,7 +236,7 @@ pub type ReduceFn<S> = fn(Arc<Context>, &mut S, &ActionWrapper);
 #[cfg(test)]
 pub mod tests {
 
-    use action::{Action, ActionWrapper};
+    use action::{Action, ActionFilter, ActionWrapper};
     use holochain_core_types::entry::{test_entry, test_entry_address};
     use nucleus::tests::test_call_result;
     use test_utils::calculate_hash;
@@ -191,4 +316,16 @@ pub mod tests {
         assert_ne!(calculate_hash(&aw1), calculate_hash(&aw2));
     }
 
+    #[test]
+    /// tests that filters select actions by variant
+    fn action_filter_matches() {
+        let commit = test_action_wrapper_commit();
+        let get = test_action_wrapper_get();
+
+        assert!(ActionFilter::COMMIT.matches(commit.action()));
+        assert!(!ActionFilter::COMMIT.matches(get.action()));
+        assert!((ActionFilter::COMMIT | ActionFilter::GET_ENTRY).matches(get.action()));
+        assert!(ActionFilter::all().matches(get.action()));
+        assert!(!ActionFilter::empty().matches(commit.action()));
+    }
 }
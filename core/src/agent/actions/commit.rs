@@ -1,10 +1,10 @@
 extern crate futures;
 use action::{Action, ActionWrapper};
-use agent::state::ActionResponse;
+use agent::{delegation::Delegation, state::ActionResponse};
 use context::Context;
 use futures::Future;
 use holochain_core_types::{cas::content::Address, entry::Entry, error::HolochainError};
-use instance::dispatch_action;
+use instance::{dispatch_action, dispatch_action_and_wait};
 use std::sync::{mpsc::SyncSender, Arc};
 
 /// Commit Action Creator
@@ -17,7 +17,45 @@ pub fn commit_entry(
     action_channel: &SyncSender<ActionWrapper>,
     context: &Arc<Context>,
 ) -> CommitFuture {
-    let action_wrapper = ActionWrapper::new(Action::Commit(entry));
+    commit_entry_with_options(entry, CommitOptions::default(), action_channel, context)
+}
+
+/// What a commit records in the chain header besides the entry
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommitOptions {
+    /// agent on whose behalf the commit is made with the agent's proof, if any
+    /// both are recorded in the chain header
+    pub delegation: Option<Delegation>,
+    /// MIME-like type of the entry's content
+    /// None for the one declared for the entry type in the DNA
+    pub content_type: Option<String>,
+    /// addresses of the agents allowed to get the entry besides its author
+    /// None for an entry everyone can get
+    pub acl: Option<Vec<Address>>,
+    /// namespace of the zome committing the entry, which entries of types that aren't
+    /// shared are kept to, @see dht_store::zome_namespace()
+    pub namespace: Option<Address>,
+    /// keep the entry in the publish queue until the next FlushPublishes even with auto
+    /// publish, e.g. until the invariants of the DNA were checked
+    pub hold_publish: bool,
+}
+
+/// Commit Action Creator for commits with options
+/// Same as commit_entry() but e.g. attributes the commit to the delegate if there is one.
+///
+/// Returns a future that resolves to an ActionResponse.
+pub fn commit_entry_with_options(
+    entry: Entry,
+    options: CommitOptions,
+    action_channel: &SyncSender<ActionWrapper>,
+    context: &Arc<Context>,
+) -> CommitFuture {
+    let action = if options == CommitOptions::default() {
+        Action::Commit(entry)
+    } else {
+        Action::CommitWithOptions(entry, options)
+    };
+    let action_wrapper = ActionWrapper::new(action);
     dispatch_action(action_channel, action_wrapper.clone());
     CommitFuture {
         context: context.clone(),
@@ -25,6 +63,24 @@ pub fn commit_entry(
     }
 }
 
+/// Roll Back Commit Action Creator
+/// Takes the entry, committed last, back off the source chain and rejects it in the DHT,
+/// e.g. when it violates an invariant of the DNA. Blocks until the action was reduced.
+/// Fails if the entry wasn't the one committed last anymore, it is left as it is then.
+pub fn roll_back_commit(address: Address, context: &Arc<Context>) -> Result<(), HolochainError> {
+    let action_wrapper = ActionWrapper::new(Action::RollBackCommit(address));
+    dispatch_action_and_wait(
+        &context.action_channel,
+        &context.observer_channel,
+        action_wrapper.clone(),
+    );
+    let agent = context.state().expect("context must have a State.").agent();
+    match agent.action_response(&action_wrapper) {
+        Some(ActionResponse::RollBackCommit(result)) => result.clone(),
+        _ => unreachable!(),
+    }
+}
+
 /// CommitFuture resolves to ActionResponse
 /// Tracks the state for a response to its ActionWrapper
 pub struct CommitFuture {
@@ -1 +1,2 @@
 pub mod commit;
+pub mod transaction;
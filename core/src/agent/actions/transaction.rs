@@ -0,0 +1,347 @@
+extern crate futures;
+use action::{Action, ActionWrapper};
+use agent::state::{is_own_entry, unique_fields, ActionResponse};
+use context::Context;
+use dht::dht_store::{indexed_field_values, DhtStore};
+use futures::{future, Future, FutureExt};
+use holochain_core_types::{
+    cas::{
+        content::{Address, AddressableContent, Content},
+        storage::ContentAddressableStorage,
+    },
+    eav::EntityAttributeValueStorage,
+    entry::Entry,
+    error::HolochainError,
+    links_entry::Link,
+};
+use instance::dispatch_action;
+use nucleus::{
+    actions::validate::validate_entry, ribosome::api::commit::build_validation_data_commit,
+};
+use std::{
+    collections::HashMap,
+    sync::{mpsc::SyncSender, Arc},
+};
+
+/// an operation staged in a Transaction
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransactionOp {
+    Commit(Entry),
+    AddLink(Link),
+}
+
+/// Commits and links applied as one unit: either all of them end up in the chain and the DHT,
+/// or none of them do
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Transaction {
+    ops: Vec<TransactionOp>,
+}
+
+impl Transaction {
+    pub fn new() -> Self {
+        Transaction::default()
+    }
+
+    /// stages the entry to be committed
+    pub fn commit(&mut self, entry: Entry) -> &mut Self {
+        self.ops.push(TransactionOp::Commit(entry));
+        self
+    }
+
+    /// stages the link to be added
+    pub fn add_link(&mut self, link: Link) -> &mut Self {
+        self.ops.push(TransactionOp::AddLink(link));
+        self
+    }
+
+    /// the staged operations, in the order they are applied
+    pub fn ops(&self) -> &[TransactionOp] {
+        &self.ops
+    }
+
+    /// the staged entries to commit
+    pub fn entries(&self) -> Vec<&Entry> {
+        self.ops
+            .iter()
+            .filter_map(|op| match op {
+                TransactionOp::Commit(entry) => Some(entry),
+                TransactionOp::AddLink(_) => None,
+            })
+            .collect()
+    }
+
+    /// the staged links to add
+    pub fn links(&self) -> Vec<&Link> {
+        self.ops
+            .iter()
+            .filter_map(|op| match op {
+                TransactionOp::AddLink(link) => Some(link),
+                TransactionOp::Commit(_) => None,
+            })
+            .collect()
+    }
+
+    /// fails if any of the operations would fail against the store, or against the operations
+    /// staged before them, without applying any
+    /// the agent and the DHT reducers both check against the same store before applying
+    /// anything, so they agree on whether the transaction is applied
+    pub(crate) fn check<CAS, EAVS>(
+        &self,
+        context: &Arc<Context>,
+        store: &DhtStore<CAS, EAVS>,
+    ) -> Result<(), HolochainError>
+    where
+        CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+        EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+    {
+        if context.read_only {
+            return Err(HolochainError::ReadOnlyInstance);
+        }
+        store.check_links(&self.links(), context.max_links_per_base)?;
+
+        // unique values and keyed addresses taken by a staged entry are taken for the entries
+        // staged after it as well
+        let mut unique_values: HashMap<(String, String, String), Address> = HashMap::new();
+        let mut keyed_contents: HashMap<Address, Content> = HashMap::new();
+        for entry in self.entries() {
+            let fields = unique_fields(context, entry);
+            store.check_unique(entry, &fields)?;
+            context.verify_address_key(entry)?;
+            store.check_address_key(entry, is_own_entry(context, &entry.address()))?;
+            for (field, value) in indexed_field_values(entry, &fields) {
+                let key = (entry.entry_type().to_string(), field.clone(), value.clone());
+                if unique_values.entry(key).or_insert_with(|| entry.address()) != &entry.address() {
+                    return Err(HolochainError::UniquenessViolation { field, value });
+                }
+            }
+            if entry.address_key().is_some()
+                && keyed_contents
+                    .entry(entry.address())
+                    .or_insert_with(|| entry.content())
+                    != &entry.content()
+            {
+                return Err(HolochainError::AddressTaken);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How a batch commit handles entries that can't be committed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatchMode {
+    /// commit none of the entries if any of them fails, like a Transaction
+    AllOrNothing,
+    /// commit the entries passing validation and report the others, e.g. for bulk imports
+    BestEffort,
+}
+
+/// What a batch commit did
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchReport {
+    /// addresses of the committed entries, in batch order
+    pub committed: Vec<Address>,
+    /// positions in the batch of the entries that weren't committed, with the reason
+    pub failed: Vec<(usize, HolochainError)>,
+}
+
+/// validates the entries as if they were committed
+fn validate_entries(
+    entries: Vec<&Entry>,
+    context: &Arc<Context>,
+) -> Vec<Box<dyn Future<Item = Address, Error = HolochainError>>> {
+    let state = context.state().expect("context must have a State.");
+    entries
+        .into_iter()
+        .map(|entry| {
+            let validation_data = build_validation_data_commit(
+                entry.clone(),
+                entry.entry_type().clone(),
+                &state.agent(),
+            );
+            validate_entry(
+                entry.entry_type().clone(),
+                entry.clone(),
+                validation_data,
+                context,
+            )
+        })
+        .collect()
+}
+
+/// dispatches the transaction without validating its entries
+fn apply_transaction(
+    transaction: Transaction,
+    action_channel: &SyncSender<ActionWrapper>,
+    context: Arc<Context>,
+) -> TransactionFuture {
+    let action_wrapper = ActionWrapper::new(Action::Transaction(transaction));
+    dispatch_action(action_channel, action_wrapper.clone());
+    TransactionFuture {
+        context,
+        action: action_wrapper,
+    }
+}
+
+/// Transaction Action Creator
+/// Validates every staged entry, then applies the whole transaction in a single action.
+/// Nothing is dispatched if any entry is invalid.
+///
+/// Returns a future that resolves to the addresses of the committed entries.
+pub fn commit_transaction(
+    transaction: Transaction,
+    action_channel: &SyncSender<ActionWrapper>,
+    context: &Arc<Context>,
+) -> Box<dyn Future<Item = Vec<Address>, Error = HolochainError>> {
+    let validations = validate_entries(transaction.entries(), context);
+    let action_channel = action_channel.clone();
+    let context = context.clone();
+    Box::new(
+        future::join_all(validations)
+            .and_then(move |_| apply_transaction(transaction, &action_channel, context)),
+    )
+}
+
+/// Batch Commit Action Creator
+/// AllOrNothing commits the entries as a Transaction and fails if any of them is invalid.
+/// BestEffort validates each entry on its own and commits the valid ones in one action,
+/// the invalid ones end up in the report instead of failing the batch.
+///
+/// Returns a future that resolves to the report of the batch.
+pub fn commit_batch(
+    entries: Vec<Entry>,
+    mode: BatchMode,
+    action_channel: &SyncSender<ActionWrapper>,
+    context: &Arc<Context>,
+) -> Box<dyn Future<Item = BatchReport, Error = HolochainError>> {
+    if mode == BatchMode::AllOrNothing {
+        let mut transaction = Transaction::new();
+        for entry in entries {
+            transaction.commit(entry);
+        }
+        return Box::new(
+            commit_transaction(transaction, action_channel, context).map(|committed| BatchReport {
+                committed,
+                failed: Vec::new(),
+            }),
+        );
+    }
+
+    let validations: Vec<_> = validate_entries(entries.iter().collect(), context)
+        .into_iter()
+        .map(|validation| validation.then(|result| Ok::<_, HolochainError>(result)))
+        .collect();
+    let action_channel = action_channel.clone();
+    let context = context.clone();
+    Box::new(future::join_all(validations).and_then(move |results| {
+        let mut transaction = Transaction::new();
+        let mut failed = Vec::new();
+        for (index, (entry, result)) in entries.into_iter().zip(results).enumerate() {
+            match result {
+                Ok(_) => {
+                    transaction.commit(entry);
+                }
+                Err(error) => failed.push((index, error)),
+            }
+        }
+        let committed: Box<dyn Future<Item = Vec<Address>, Error = HolochainError>> =
+            if transaction.ops().is_empty() {
+                Box::new(future::ok(Vec::new()))
+            } else {
+                Box::new(apply_transaction(transaction, &action_channel, context))
+            };
+        committed.map(move |committed| BatchReport { committed, failed })
+    }))
+}
+
+/// TransactionFuture resolves to the addresses of the committed entries
+/// Tracks the state for a response to its ActionWrapper
+pub struct TransactionFuture {
+    context: Arc<Context>,
+    action: ActionWrapper,
+}
+
+impl Future for TransactionFuture {
+    type Item = Vec<Address>;
+    type Error = HolochainError;
+
+    fn poll(
+        &mut self,
+        cx: &mut futures::task::Context<'_>,
+    ) -> Result<futures::Async<Vec<Address>>, Self::Error> {
+        //
+        // TODO: connect the waker to state updates for performance reasons
+        // See: https://github.com/holochain/holochain-rust/issues/314
+        //
+        cx.waker().wake();
+        match self
+            .context
+            .state()
+            .unwrap()
+            .agent()
+            .actions()
+            .get(&self.action)
+        {
+            Some(ActionResponse::Transaction(result)) => match result {
+                Ok(addresses) => Ok(futures::Async::Ready(addresses.clone())),
+                Err(error) => Err(error.clone()),
+            },
+            Some(_) => unreachable!(),
+            None => Ok(futures::Async::Pending),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_cas_implementations::{
+        cas::memory::MemoryStorage, eav::memory::EavMemoryStorage,
+    };
+    use holochain_core_types::{
+        cas::content::AddressableContent,
+        entry::{test_entry, test_entry_a, test_entry_b},
+    };
+    use instance::tests::test_context;
+
+    #[test]
+    /// the staged operations are kept in order
+    fn transaction_stages_ops_in_order() {
+        let link = Link::new(&test_entry().address(), &test_entry_b().address(), "tag");
+        let mut transaction = Transaction::new();
+        transaction.commit(test_entry()).add_link(link.clone());
+
+        assert_eq!(
+            &[
+                TransactionOp::Commit(test_entry()),
+                TransactionOp::AddLink(link.clone())
+            ],
+            transaction.ops()
+        );
+        assert_eq!(vec![&test_entry()], transaction.entries());
+        assert_eq!(vec![&link], transaction.links());
+    }
+
+    #[test]
+    /// links staged together count towards the limit of their base
+    fn check_counts_staged_links() {
+        let mut context = (*test_context("bob")).clone();
+        context.max_links_per_base = Some(1);
+        let context = Arc::new(context);
+        let store = DhtStore::new(
+            MemoryStorage::new().unwrap(),
+            EavMemoryStorage::new().unwrap(),
+        );
+        let base = test_entry().address();
+
+        let mut transaction = Transaction::new();
+        transaction.add_link(Link::new(&base, &test_entry_a().address(), "tag"));
+        assert_eq!(Ok(()), transaction.check(&context, &store));
+
+        transaction.add_link(Link::new(&base, &test_entry_b().address(), "tag"));
+        assert_eq!(
+            Err(HolochainError::LinkLimitExceeded),
+            transaction.check(&context, &store)
+        );
+    }
+}
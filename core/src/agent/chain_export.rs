@@ -0,0 +1,237 @@
+//! Moving an agent's source chain to another device.
+//!
+//! The export holds every header of the chain with its entry and the exporting agent's
+//! Ed25519 signature of the header. Importing verifies all of them against the agent's public
+//! key, so a chain that was tampered with, truncated or forged on the way is refused instead
+//! of becoming the agent's history.
+//! Headers are signed with the key active at their position, @see key_rotation
+
+use agent::{
+    delegation::verify_chain_header_delegation,
+    key_rotation::{signing_keys, ActiveKey, KeyRotation},
+    keys::{verify_signature, KeyPair, KeyStore, PublicKey},
+    state::AgentState,
+};
+use holochain_agent::Agent;
+use holochain_cas_implementations::cas::memory::MemoryStorage;
+use holochain_core_types::{
+    cas::{
+        content::{Address, AddressableContent},
+        storage::ContentAddressableStorage,
+    },
+    chain_header::ChainHeader,
+    entry::Entry,
+    error::HolochainError,
+    json::{FromJson, ToJson},
+    signature::Signature,
+};
+use serde_json;
+
+/// Signature of the header with the key pair.
+pub fn header_signature(key_pair: &KeyPair, chain_header: &ChainHeader) -> Signature {
+    key_pair.sign(chain_header.address().to_string().as_bytes())
+}
+
+/// true if the signature of the header was made with the key pair of the public key
+pub fn verify_header_signature(
+    public_key: &PublicKey,
+    chain_header: &ChainHeader,
+    signature: &Signature,
+) -> bool {
+    verify_signature(
+        public_key,
+        chain_header.address().to_string().as_bytes(),
+        signature,
+    )
+}
+
+/// a header of the exported chain with its entry
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportedHeader {
+    pub header: ChainHeader,
+    pub entry: Entry,
+    pub signature: Signature,
+}
+
+/// An agent's whole source chain in a portable format, oldest header first
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AgentChainExport {
+    agent: String,
+    headers: Vec<ExportedHeader>,
+}
+
+impl AgentChainExport {
+    /// exports the source chain of the state, signing every header with the key pair the
+    /// keystore has for the key of the agent active at its position
+    /// fails with InconsistentStorage if the entry of a header is missing
+    pub fn new(
+        keystore: &KeyStore,
+        initial: &ActiveKey,
+        state: &AgentState,
+    ) -> Result<Self, HolochainError> {
+        let mut headers = Vec::new();
+        for (header, entry, key) in
+            signing_keys(initial, &state.chain(), &state.top_chain_header())?
+        {
+            headers.push(ExportedHeader {
+                signature: header_signature(&keystore.key_pair(&key.key)?, &header),
+                header,
+                entry,
+            });
+        }
+        Ok(AgentChainExport {
+            agent: initial.key.to_string(),
+            headers,
+        })
+    }
+
+    /// the agent whose chain this is
+    pub fn agent(&self) -> Agent {
+        Agent::from(self.agent.clone())
+    }
+
+    /// the headers of the chain, oldest first
+    pub fn headers(&self) -> &[ExportedHeader] {
+        &self.headers
+    }
+
+    /// the address of the latest header of the chain, None for an empty chain
+    pub fn chain_top(&self) -> Option<Address> {
+        self.headers
+            .last()
+            .map(|exported| exported.header.address())
+    }
+
+    /// checks that every entry matches its header, every header links to the one before it,
+    /// every delegation was allowed by the agent and every signature was made with the key
+    /// pair of the agent active at its position, starting with the one of the public key
+    /// fails with InvalidChainExport saying what doesn't match
+    pub fn verify(&self, public_key: &PublicKey) -> Result<(), HolochainError> {
+        let mut key = ActiveKey::new(&self.agent(), public_key);
+        let mut previous = None;
+        for exported in self.headers.iter() {
+            let header = &exported.header;
+            let invalid = |reason: &str| {
+                Err(HolochainError::InvalidChainExport(format!(
+                    "chain header {} {}",
+                    header.address(),
+                    reason
+                )))
+            };
+            if header.entry_address() != &exported.entry.address()
+                || header.entry_type() != exported.entry.entry_type()
+            {
+                return invalid("doesn't match its entry");
+            }
+            if header.link() != previous {
+                return invalid("doesn't link to the header before it");
+            }
+            if !verify_header_signature(&key.public_key, header, &exported.signature) {
+                return invalid("is not signed by the agent");
+            }
+            if !verify_chain_header_delegation(&key.public_key, header) {
+                return invalid("has a delegation the agent didn't sign");
+            }
+            key = KeyRotation::key_after(&key, &exported.entry);
+            previous = Some(header.address());
+        }
+        Ok(())
+    }
+
+    /// a storage holding the exported headers and entries
+    /// @see State::rebuild_from_content_storage()
+    pub fn to_storage(&self) -> Result<MemoryStorage, HolochainError> {
+        let mut storage = MemoryStorage::new()?;
+        for exported in self.headers.iter() {
+            storage.add(&exported.entry)?;
+            storage.add(&exported.header)?;
+        }
+        Ok(storage)
+    }
+}
+
+impl ToJson for AgentChainExport {
+    fn to_json(&self) -> Result<String, HolochainError> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+impl FromJson for AgentChainExport {
+    fn from_json(s: &str) -> Result<Self, HolochainError> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use action::{Action, ActionWrapper};
+    use agent::{chain_store::tests::test_chain_store, state::reduce};
+    use holochain_core_types::entry::{test_entry, test_entry_b, test_entry_unique};
+    use instance::tests::test_context;
+    use std::sync::Arc;
+
+    /// export of a chain of three entries committed by bob, and bob's public key
+    fn test_export() -> (AgentChainExport, PublicKey) {
+        let context = test_context("bob");
+        let mut state = Arc::new(AgentState::new(test_chain_store()));
+        for entry in vec![test_entry(), test_entry_b(), test_entry_unique()] {
+            let action_wrapper = ActionWrapper::new(Action::Commit(entry));
+            state = reduce(context.clone(), state, &action_wrapper);
+        }
+        let public_key = context.keystore.public_key(&context.agent).unwrap();
+        let initial = ActiveKey::new(&context.agent, &public_key);
+        let export = AgentChainExport::new(&context.keystore, &initial, &state).unwrap();
+        (export, public_key)
+    }
+
+    #[test]
+    /// the export holds the chain oldest first and survives serialization
+    fn export_round_trips_through_json() {
+        let (export, public_key) = test_export();
+        assert_eq!(3, export.headers().len());
+        assert_eq!(test_entry(), export.headers()[0].entry);
+        assert_eq!(None, export.headers()[0].header.link());
+        assert_eq!(Ok(()), export.verify(&public_key));
+
+        let imported = AgentChainExport::from_json(&export.to_json().unwrap()).unwrap();
+        assert_eq!(export, imported);
+        assert_eq!(Ok(()), imported.verify(&public_key));
+    }
+
+    #[test]
+    /// forged signatures, swapped entries and missing headers are refused
+    fn verify_refuses_tampered_exports() {
+        // the signatures only verify with bob's public key
+        let (export, _) = test_export();
+        assert!(export
+            .verify(&KeyPair::generate().unwrap().public_key())
+            .is_err());
+
+        let (mut swapped, public_key) = test_export();
+        swapped.headers[1].entry = test_entry();
+        assert!(swapped.verify(&public_key).is_err());
+
+        let (mut truncated, public_key) = test_export();
+        truncated.headers.remove(1);
+        assert!(truncated.verify(&public_key).is_err());
+    }
+
+    #[test]
+    /// an export signed by anyone but the agent doesn't verify with the agent's public key,
+    /// even if it is otherwise a valid chain in the agent's name
+    fn verify_refuses_forged_exports() {
+        let (export, public_key) = test_export();
+        let mallory = KeyPair::generate().unwrap();
+        let mut forged = export.clone();
+        for exported in forged.headers.iter_mut() {
+            exported.signature = header_signature(&mallory, &exported.header);
+        }
+        assert_eq!(export.agent(), forged.agent());
+        assert_eq!(Ok(()), forged.verify(&mallory.public_key()));
+        match forged.verify(&public_key) {
+            Err(HolochainError::InvalidChainExport(_)) => (),
+            result => panic!("a forged export should not verify: {:?}", result),
+        }
+    }
+}
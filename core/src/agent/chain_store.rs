@@ -1,5 +1,10 @@
 use holochain_core_types::{
-    cas::storage::ContentAddressableStorage, chain_header::ChainHeader, entry_type::EntryType,
+    cas::{
+        content::{Address, AddressableContent},
+        storage::ContentAddressableStorage,
+    },
+    chain_header::ChainHeader,
+    entry_type::EntryType,
 };
 
 #[derive(Debug, PartialEq, Clone)]
@@ -38,6 +43,29 @@ where
                 .find(|chain_header| chain_header.entry_type() == entry_type),
         )
     }
+
+    /// the headers of the chain ending in the top that come after the header at the address,
+    /// oldest first; the whole chain for None
+    /// None if the address isn't on the chain, e.g. the top of a chain that diverged
+    pub fn headers_since(
+        &self,
+        top: &Option<ChainHeader>,
+        since: Option<&Address>,
+    ) -> Option<Vec<ChainHeader>> {
+        let mut headers = Vec::new();
+        for chain_header in self.iter(top) {
+            if Some(&chain_header.address()) == since {
+                headers.reverse();
+                return Some(headers);
+            }
+            headers.push(chain_header);
+        }
+        if since.is_some() {
+            return None;
+        }
+        headers.reverse();
+        Some(headers)
+    }
 }
 
 pub struct ChainStoreIterator<CAS>
@@ -187,6 +215,46 @@ pub mod tests {
         assert_eq!(expected, found);
     }
 
+    #[test]
+    /// the headers after a header of the chain come oldest first, unknown headers give None
+    fn headers_since_test() {
+        let chain_store = test_chain_store();
+        let chain_header_a = test_chain_header();
+        let entry = test_entry_b();
+        let chain_header_b = ChainHeader::new(
+            &entry.entry_type(),
+            &entry.address(),
+            &test_signature_b(),
+            &Some(chain_header_a.address()),
+            &None,
+            &test_iso_8601(),
+        );
+        for chain_header in vec![&chain_header_a, &chain_header_b] {
+            chain_store
+                .content_storage()
+                .add(chain_header)
+                .expect("could not add header to cas");
+        }
+        let top = Some(chain_header_b.clone());
+
+        assert_eq!(
+            Some(vec![chain_header_a.clone(), chain_header_b.clone()]),
+            chain_store.headers_since(&top, None)
+        );
+        assert_eq!(
+            Some(vec![chain_header_b.clone()]),
+            chain_store.headers_since(&top, Some(&chain_header_a.address()))
+        );
+        assert_eq!(
+            Some(Vec::new()),
+            chain_store.headers_since(&top, Some(&chain_header_b.address()))
+        );
+        assert_eq!(
+            None,
+            chain_store.headers_since(&top, Some(&entry.address()))
+        );
+    }
+
     #[test]
     /// show entry typed Iterator implementation for chain store
     fn type_iterator_test() {
@@ -0,0 +1,304 @@
+//! Delegation lets an instance's agent allow another agent identity to make zome calls
+//! on its behalf, e.g. the end-users of a multi-user container.
+//! The agent signs what it allows the delegate: one function of a capability of a zome, in
+//! instances of one DNA, until an expiry, so a proof can't be replayed for other calls or forever.
+//! Commits made during a delegated call are attributed to the delegate: their chain header
+//! names the delegate and carries the scope and the proof, so the chain alone shows who
+//! allowed them.
+
+use agent::keys::{verify_signature, KeyPair, PublicKey};
+use holochain_agent::Agent;
+use holochain_core_types::{
+    cas::content::Address, chain_header::ChainHeader, error::HolochainError, json::ToJson,
+    signature::Signature,
+};
+use serde_json;
+use std::time::Duration;
+
+/// What the agent allows a delegate to call
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DelegationScope {
+    pub zome: String,
+    pub capability: String,
+    pub function: String,
+    /// address of the DNA of the instances the delegate may call, @see Dna::to_entry()
+    pub dna: Address,
+    /// time since the UNIX epoch from which the delegation no longer holds, as read from the
+    /// clock of the instance called
+    pub expires: Duration,
+}
+
+impl DelegationScope {
+    pub fn new(
+        zome: &str,
+        capability: &str,
+        function: &str,
+        dna: &Address,
+        expires: Duration,
+    ) -> Self {
+        DelegationScope {
+            zome: zome.to_string(),
+            capability: capability.to_string(),
+            function: function.to_string(),
+            dna: dna.clone(),
+            expires,
+        }
+    }
+
+    /// true if the scope covers calling the function of the capability of the zome in an
+    /// instance of the DNA, whatever the time
+    pub fn covers(&self, zome: &str, capability: &str, function: &str, dna: &Address) -> bool {
+        self.zome == zome
+            && self.capability == capability
+            && self.function == function
+            && &self.dna == dna
+    }
+
+    /// true if the delegation no longer holds at the time
+    pub fn is_expired(&self, now: Duration) -> bool {
+        now >= self.expires
+    }
+}
+
+impl ToJson for DelegationScope {
+    fn to_json(&self) -> Result<String, HolochainError> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+/// A delegate with the agent's proof that it may act on the agent's behalf within the scope
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Delegation {
+    pub delegate: Agent,
+    pub scope: DelegationScope,
+    pub proof: Signature,
+}
+
+impl Delegation {
+    pub fn new(delegate: &Agent, scope: &DelegationScope, proof: &Signature) -> Self {
+        Delegation {
+            delegate: delegate.clone(),
+            scope: scope.clone(),
+            proof: proof.clone(),
+        }
+    }
+
+    /// the delegation recorded in the chain header, None if the agent committed for itself
+    /// or the header doesn't record a whole delegation
+    pub fn from_chain_header(chain_header: &ChainHeader) -> Option<Self> {
+        match (
+            chain_header.delegate(),
+            chain_header.delegation_scope(),
+            chain_header.delegation_proof(),
+        ) {
+            (Some(delegate), Some(scope), Some(proof)) => {
+                serde_json::from_str(&scope).ok().map(|scope| Delegation {
+                    delegate: Agent::from(delegate),
+                    scope,
+                    proof,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// what the agent signs to delegate the scope to the delegate
+fn delegation_message(delegate: &Agent, scope: &DelegationScope) -> String {
+    json!({"delegate": delegate.to_string(), "scope": scope}).to_string()
+}
+
+/// Builds the proof that the agent of the key pair allows `delegate` to act on its behalf
+/// within the scope.
+pub fn delegation_proof(
+    key_pair: &KeyPair,
+    delegate: &Agent,
+    scope: &DelegationScope,
+) -> Signature {
+    key_pair.sign(delegation_message(delegate, scope).as_bytes())
+}
+
+/// true if the proof was signed for `delegate` and the scope by the agent with the public key
+pub fn verify_delegation_proof(
+    public_key: &PublicKey,
+    delegate: &Agent,
+    scope: &DelegationScope,
+    proof: &Signature,
+) -> bool {
+    verify_signature(
+        public_key,
+        delegation_message(delegate, scope).as_bytes(),
+        proof,
+    )
+}
+
+/// true if the chain header was committed by the agent with the public key for itself, or
+/// for a delegate it signed a proof for
+pub fn verify_chain_header_delegation(public_key: &PublicKey, chain_header: &ChainHeader) -> bool {
+    match Delegation::from_chain_header(chain_header) {
+        Some(delegation) => verify_delegation_proof(
+            public_key,
+            &delegation.delegate,
+            &delegation.scope,
+            &delegation.proof,
+        ),
+        None => {
+            chain_header.delegate().is_none()
+                && chain_header.delegation_scope().is_none()
+                && chain_header.delegation_proof().is_none()
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core_types::chain_header::test_chain_header;
+
+    /// scope of a delegation to call the "test" function of "test_cap" of "test_zome" in
+    /// instances of a DNA
+    pub fn test_scope() -> DelegationScope {
+        DelegationScope::new(
+            "test_zome",
+            "test_cap",
+            "test",
+            &Address::from("dna".to_string()),
+            Duration::from_secs(2000),
+        )
+    }
+
+    #[test]
+    /// a proof only verifies for the agent, delegate and scope it was built for
+    fn verify_delegation_proof_test() {
+        let agent = KeyPair::generate().unwrap();
+        let other_agent = KeyPair::generate().unwrap();
+        let delegate = Agent::from("bob".to_string());
+        let other = Agent::from("carol".to_string());
+        let scope = test_scope();
+
+        let proof = delegation_proof(&agent, &delegate, &scope);
+
+        assert!(verify_delegation_proof(
+            &agent.public_key(),
+            &delegate,
+            &scope,
+            &proof
+        ));
+        assert!(!verify_delegation_proof(
+            &agent.public_key(),
+            &other,
+            &scope,
+            &proof
+        ));
+        assert!(!verify_delegation_proof(
+            &other_agent.public_key(),
+            &delegate,
+            &scope,
+            &proof
+        ));
+        assert!(!verify_delegation_proof(
+            &agent.public_key(),
+            &delegate,
+            &scope,
+            &Signature::from("fake-signature")
+        ));
+        // widening any part of the scope voids the proof
+        let scopes = vec![
+            DelegationScope {
+                zome: "other_zome".to_string(),
+                ..scope.clone()
+            },
+            DelegationScope {
+                capability: "other_cap".to_string(),
+                ..scope.clone()
+            },
+            DelegationScope {
+                function: "other".to_string(),
+                ..scope.clone()
+            },
+            DelegationScope {
+                dna: Address::from("other dna".to_string()),
+                ..scope.clone()
+            },
+            DelegationScope {
+                expires: Duration::from_secs(3000),
+                ..scope.clone()
+            },
+        ];
+        for widened in scopes {
+            assert!(!verify_delegation_proof(
+                &agent.public_key(),
+                &delegate,
+                &widened,
+                &proof
+            ));
+        }
+    }
+
+    #[test]
+    /// a scope covers its own function only, until it expires
+    fn scope_covers_test() {
+        let scope = test_scope();
+        assert!(scope.covers("test_zome", "test_cap", "test", &scope.dna));
+        assert!(!scope.covers("test_zome", "test_cap", "other", &scope.dna));
+        assert!(!scope.covers("test_zome", "other_cap", "test", &scope.dna));
+        assert!(!scope.covers(
+            "test_zome",
+            "test_cap",
+            "test",
+            &Address::from("other".to_string())
+        ));
+        // the same capability and function in another zome is out of scope
+        assert!(!scope.covers("other_zome", "test_cap", "test", &scope.dna));
+        assert!(!scope.is_expired(Duration::from_secs(1999)));
+        assert!(scope.is_expired(Duration::from_secs(2000)));
+    }
+
+    #[test]
+    /// the delegation recorded in a chain header verifies with the public key of the author
+    fn verify_chain_header_delegation_test() {
+        let agent = KeyPair::generate().unwrap();
+        let delegate = Agent::from("bob".to_string());
+        let scope = test_scope();
+        let proof = delegation_proof(&agent, &delegate, &scope);
+
+        let own = test_chain_header();
+        assert_eq!(None, Delegation::from_chain_header(&own));
+        assert!(verify_chain_header_delegation(&agent.public_key(), &own));
+
+        let delegated = test_chain_header().with_delegation(Some((
+            delegate.to_string(),
+            scope.to_json().unwrap(),
+            proof.clone(),
+        )));
+        assert_eq!(
+            Some(Delegation::new(&delegate, &scope, &proof)),
+            Delegation::from_chain_header(&delegated)
+        );
+        assert!(verify_chain_header_delegation(
+            &agent.public_key(),
+            &delegated
+        ));
+
+        let forged = test_chain_header().with_delegation(Some((
+            delegate.to_string(),
+            scope.to_json().unwrap(),
+            delegation_proof(&KeyPair::generate().unwrap(), &delegate, &scope),
+        )));
+        assert!(!verify_chain_header_delegation(
+            &agent.public_key(),
+            &forged
+        ));
+
+        let unreadable = test_chain_header().with_delegation(Some((
+            delegate.to_string(),
+            "not a scope".to_string(),
+            proof,
+        )));
+        assert_eq!(None, Delegation::from_chain_header(&unreadable));
+        assert!(!verify_chain_header_delegation(
+            &agent.public_key(),
+            &unreadable
+        ));
+    }
+}
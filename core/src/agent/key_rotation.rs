@@ -0,0 +1,231 @@
+//! Rotating the key an agent signs its source chain with.
+//!
+//! A rotation is a system entry on the chain naming the old and the new key and binding the
+//! public key of the new one, signed with the old key pair. Headers up to and including the
+//! rotation are signed with the old key, the ones after it with the new key, so the chain
+//! stays verifiable from its start after a key was compromised and replaced. A rotation that
+//! wasn't signed by the key active at its position is refused when it is committed and
+//! ignored when a chain is verified, so nobody but the holder of the active key can rotate it.
+
+use agent::{
+    chain_store::ChainStore,
+    keys::{verify_signature, KeyPair, PublicKey},
+    state::AgentState,
+};
+use holochain_agent::Agent;
+use holochain_cas_implementations::cas::memory::MemoryStorage;
+use holochain_core_types::{
+    cas::storage::ContentAddressableStorage,
+    chain_header::ChainHeader,
+    entry::{Entry, ToEntry},
+    entry_type::EntryType,
+    error::HolochainError,
+    signature::Signature,
+};
+use serde_json;
+
+/// A key an agent signs with and the public key its signatures verify with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActiveKey {
+    pub key: Agent,
+    pub public_key: PublicKey,
+}
+
+impl ActiveKey {
+    pub fn new(key: &Agent, public_key: &PublicKey) -> Self {
+        ActiveKey {
+            key: key.clone(),
+            public_key: public_key.clone(),
+        }
+    }
+}
+
+/// The switch from the old key to the new one, committed by the agent
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KeyRotation {
+    old: String,
+    new: String,
+    new_public_key: PublicKey,
+    /// the old key pair's signature of the rotation
+    signature: Signature,
+}
+
+/// what the old key pair signs to rotate to the new key
+fn rotation_message(old: &Agent, new: &Agent, new_public_key: &PublicKey) -> String {
+    format!(
+        "rotate:{}:{}:{}",
+        old.to_string(),
+        new.to_string(),
+        new_public_key.to_string()
+    )
+}
+
+impl KeyRotation {
+    /// the rotation from the old key to the new one, signed with the key pair of the old key
+    pub fn new(old: &Agent, old_key_pair: &KeyPair, new: &ActiveKey) -> Self {
+        let message = rotation_message(old, &new.key, &new.public_key);
+        KeyRotation {
+            old: old.to_string(),
+            new: new.key.to_string(),
+            new_public_key: new.public_key.clone(),
+            signature: old_key_pair.sign(message.as_bytes()),
+        }
+    }
+
+    pub fn old(&self) -> Agent {
+        Agent::from(self.old.clone())
+    }
+
+    /// the key active after the rotation
+    pub fn new_key(&self) -> ActiveKey {
+        ActiveKey::new(&Agent::from(self.new.clone()), &self.new_public_key)
+    }
+
+    /// true if the rotation away from the active key was signed with its key pair
+    pub fn verify(&self, active: &ActiveKey) -> bool {
+        if self.old() != active.key {
+            return false;
+        }
+        let message = rotation_message(&self.old(), &self.new_key().key, &self.new_public_key);
+        verify_signature(&active.public_key, message.as_bytes(), &self.signature)
+    }
+
+    /// the rotation the entry holds, None if it is no rotation
+    pub fn from_chain_entry(entry: &Entry) -> Option<Self> {
+        if entry.entry_type() != &EntryType::Key {
+            return None;
+        }
+        serde_json::from_str(entry.value()).ok()
+    }
+
+    /// the key active after the entry, given the key active before it
+    /// only rotations signed by the active key count, others can't have been made by it
+    pub fn key_after(active: &ActiveKey, entry: &Entry) -> ActiveKey {
+        match KeyRotation::from_chain_entry(entry) {
+            Some(ref rotation) if rotation.verify(active) => rotation.new_key(),
+            _ => active.clone(),
+        }
+    }
+}
+
+impl ToEntry for KeyRotation {
+    fn to_entry(&self) -> Entry {
+        let json = serde_json::to_string(self).expect("KeyRotation should serialize");
+        Entry::new(&EntryType::Key, &json)
+    }
+
+    fn from_entry(entry: &Entry) -> Self {
+        KeyRotation::from_chain_entry(entry).expect("entry is not a valid KeyRotation")
+    }
+}
+
+/// the headers of the chain, oldest first, each with its entry and the key that signs it
+/// fails with InconsistentStorage if the entry of a header is missing
+pub fn signing_keys(
+    initial: &ActiveKey,
+    chain: &ChainStore<MemoryStorage>,
+    top: &Option<ChainHeader>,
+) -> Result<Vec<(ChainHeader, Entry, ActiveKey)>, HolochainError> {
+    let mut headers: Vec<ChainHeader> = chain.iter(top).collect();
+    headers.reverse();
+    let mut active = initial.clone();
+    let mut signed = Vec::new();
+    for header in headers {
+        let entry: Entry = chain
+            .content_storage()
+            .fetch(header.entry_address())?
+            .ok_or_else(|| {
+                HolochainError::InconsistentStorage(format!(
+                    "the entry of chain header {} is missing",
+                    header.entry_address()
+                ))
+            })?;
+        let next = KeyRotation::key_after(&active, &entry);
+        signed.push((header, entry, active));
+        active = next;
+    }
+    Ok(signed)
+}
+
+/// the key the next commit of the agent is signed with
+pub fn active_key(initial: &ActiveKey, state: &AgentState) -> Result<ActiveKey, HolochainError> {
+    let chain = state.chain();
+    let mut active = initial.clone();
+    for (_, entry, _) in signing_keys(initial, &chain, &state.top_chain_header())? {
+        active = KeyRotation::key_after(&active, &entry);
+    }
+    Ok(active)
+}
+
+/// fails with InvalidKeyRotation if the entry is a key entry that isn't a rotation signed by
+/// the active key
+pub fn check_rotation(active: &ActiveKey, entry: &Entry) -> Result<(), HolochainError> {
+    if entry.entry_type() != &EntryType::Key {
+        return Ok(());
+    }
+    match KeyRotation::from_chain_entry(entry) {
+        Some(ref rotation) if rotation.verify(active) => Ok(()),
+        _ => Err(HolochainError::InvalidKeyRotation),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use action::{Action, ActionWrapper};
+    use agent::{chain_store::tests::test_chain_store, state::reduce};
+    use holochain_core_types::entry::{test_entry, test_entry_b};
+    use instance::tests::test_context;
+    use std::sync::Arc;
+
+    #[test]
+    /// headers after a rotation are signed with the new key, a rotation has to be signed by
+    /// the active key
+    fn rotation_changes_signing_key() {
+        let context = test_context("bob");
+        let bob = context.agent.clone();
+        let bob_key = context.keystore.key_pair(&bob).unwrap();
+        let initial = ActiveKey::new(&bob, &bob_key.public_key());
+        let bob2_key = KeyPair::generate().unwrap();
+        let bob2 = ActiveKey::new(&Agent::from("bob2".to_string()), &bob2_key.public_key());
+        let mallory_key = KeyPair::generate().unwrap();
+        let mallory = ActiveKey::new(
+            &Agent::from("mallory".to_string()),
+            &mallory_key.public_key(),
+        );
+
+        let mut state = Arc::new(AgentState::new(test_chain_store()));
+        for entry in vec![
+            test_entry(),
+            KeyRotation::new(&bob, &bob_key, &bob2).to_entry(),
+            test_entry_b(),
+        ] {
+            let action_wrapper = ActionWrapper::new(Action::Commit(entry));
+            state = reduce(context.clone(), state, &action_wrapper);
+        }
+
+        let chain = state.chain();
+        let keys: Vec<ActiveKey> = signing_keys(&initial, &chain, &state.top_chain_header())
+            .unwrap()
+            .into_iter()
+            .map(|(_, _, key)| key)
+            .collect();
+        assert_eq!(vec![initial.clone(), initial.clone(), bob2.clone()], keys);
+        assert_eq!(Ok(bob2.clone()), active_key(&initial, &state));
+
+        // bob's key isn't active anymore, and mallory's never was
+        for rotation in vec![
+            KeyRotation::new(&bob, &bob_key, &mallory),
+            KeyRotation::new(&bob2.key, &mallory_key, &mallory),
+        ] {
+            assert_eq!(
+                Err(HolochainError::InvalidKeyRotation),
+                check_rotation(&bob2, &rotation.to_entry())
+            );
+            assert_eq!(bob2, KeyRotation::key_after(&bob2, &rotation.to_entry()));
+        }
+        let rotation = KeyRotation::new(&bob2.key, &bob2_key, &mallory);
+        assert_eq!(Ok(()), check_rotation(&bob2, &rotation.to_entry()));
+        assert_eq!(mallory, KeyRotation::key_after(&bob2, &rotation.to_entry()));
+    }
+}
@@ -0,0 +1,368 @@
+//! The Ed25519 key pairs agents sign with.
+//!
+//! Only the holder of a key pair can make a signature that verifies under its public key, so
+//! anything signed here, e.g. a delegation proof or a chain export, proves who made it as far
+//! as the verifier knows the public key. Key pairs never leave the KeyStore of an instance, signed data
+//! carries the public key and verifiers decide which public keys they trust.
+//! A KeyStore made with KeyStore::new() forgets its key pairs when it is dropped, use
+//! KeyStore::open() or KeyStore::from_seed() for keys that survive a restart.
+//! A store only makes a key pair when KeyStore::generate() asks it to, looking up a key it
+//! doesn't hold fails, so verifying against an unknown key never makes one up.
+
+use hex;
+use holochain_agent::Agent;
+use holochain_core_types::{cas::content::Address, error::HolochainError, signature::Signature};
+use persister::write_atomically;
+use ring::{
+    digest::{digest, SHA256},
+    rand::{SecureRandom, SystemRandom},
+    signature::{self, Ed25519KeyPair, ED25519},
+};
+use serde_json;
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+use untrusted::Input;
+
+/// number of bytes of the seed a key pair is made from
+pub const KEY_SEED_LEN: usize = 32;
+
+/// The public half of a key pair, as hex
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct PublicKey(String);
+
+impl ToString for PublicKey {
+    fn to_string(&self) -> String {
+        self.0.clone()
+    }
+}
+
+/// the public key as an address, to name the signer where addresses are expected
+impl From<PublicKey> for Address {
+    fn from(public_key: PublicKey) -> Address {
+        Address::from(public_key.0)
+    }
+}
+
+impl<'a> From<&'a Address> for PublicKey {
+    fn from(address: &'a Address) -> PublicKey {
+        PublicKey(address.to_string())
+    }
+}
+
+/// An Ed25519 key pair, clones share the secret
+#[derive(Clone)]
+pub struct KeyPair {
+    key_pair: Arc<Ed25519KeyPair>,
+    seed: [u8; KEY_SEED_LEN],
+}
+
+/// keeps the secret out of logs
+impl fmt::Debug for KeyPair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "KeyPair({})", self.public_key().to_string())
+    }
+}
+
+fn key_error(reason: &str) -> HolochainError {
+    HolochainError::ErrorGeneric(format!("key pair failed: {}", reason))
+}
+
+impl KeyPair {
+    /// a key pair made from a fresh random seed of the operating system
+    pub fn generate() -> Result<Self, HolochainError> {
+        let mut seed = [0; KEY_SEED_LEN];
+        SystemRandom::new()
+            .fill(&mut seed)
+            .map_err(|_| key_error("no seed"))?;
+        KeyPair::from_seed(&seed)
+    }
+
+    /// the key pair made from the seed, the same for the same seed
+    pub fn from_seed(seed: &[u8; KEY_SEED_LEN]) -> Result<Self, HolochainError> {
+        let key_pair = Ed25519KeyPair::from_seed_unchecked(Input::from(&seed[..]))
+            .map_err(|_| key_error("invalid seed"))?;
+        Ok(KeyPair {
+            key_pair: Arc::new(key_pair),
+            seed: *seed,
+        })
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey(hex::encode(self.key_pair.public_key_bytes()))
+    }
+
+    /// the signature of the message, as hex
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        Signature::from(hex::encode(self.key_pair.sign(message).as_ref()))
+    }
+}
+
+/// true if the signature of the message was made with the key pair of the public key
+pub fn verify_signature(public_key: &PublicKey, message: &[u8], signature: &Signature) -> bool {
+    match (
+        hex::decode(&public_key.0).ok(),
+        hex::decode(signature.to_string()).ok(),
+    ) {
+        (Some(public_key), Some(signature)) => signature::verify(
+            &ED25519,
+            Input::from(&public_key[..]),
+            Input::from(message),
+            Input::from(&signature[..]),
+        )
+        .is_ok(),
+        _ => false,
+    }
+}
+
+/// where a KeyStore gets the key pairs it doesn't hold yet from
+#[derive(Clone)]
+enum KeySource {
+    /// fresh random seeds, forgotten with the store
+    Random,
+    /// seeds derived from the master seed and the key, the same for every store with that seed
+    Seeded([u8; KEY_SEED_LEN]),
+    /// fresh random seeds, saved to the file so a store opened on it later has the same keys
+    File(PathBuf),
+}
+
+/// keeps the master seed out of logs
+impl fmt::Debug for KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeySource::Random => write!(f, "Random"),
+            KeySource::Seeded(_) => write!(f, "Seeded"),
+            KeySource::File(path) => write!(f, "File({:?})", path),
+        }
+    }
+}
+
+impl Default for KeySource {
+    fn default() -> Self {
+        KeySource::Random
+    }
+}
+
+/// The key pairs an instance signs with, by the key they stand for, shared by the clones
+#[derive(Clone, Debug, Default)]
+pub struct KeyStore {
+    key_pairs: Arc<RwLock<HashMap<String, KeyPair>>>,
+    source: KeySource,
+}
+
+impl KeyStore {
+    /// a store generating random key pairs, which are lost when it is dropped
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// a store deriving the key pair of every key from the master seed, so a store made
+    /// from the same seed after a restart signs with the same key pairs
+    /// the master seed has to be kept as secret as the key pairs it stands for
+    pub fn from_seed(master_seed: &[u8; KEY_SEED_LEN]) -> Self {
+        KeyStore {
+            source: KeySource::Seeded(*master_seed),
+            ..Default::default()
+        }
+    }
+
+    /// a store keeping the seeds of its key pairs in the file, loading the ones saved there
+    /// the file is created with the first key pair if it doesn't exist, readable by its owner
+    /// only and replaced in one go whenever a key pair is added
+    /// fails with IoError if the file can't be read and ErrorGeneric if it isn't a key file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, HolochainError> {
+        let path = path.as_ref().to_path_buf();
+        let mut key_pairs = HashMap::new();
+        if path.exists() {
+            let seeds: HashMap<String, String> = serde_json::from_str(&fs::read_to_string(&path)?)
+                .map_err(|_| key_error("not a key file"))?;
+            for (key, seed) in seeds {
+                let seed = hex::decode(&seed)
+                    .ok()
+                    .filter(|seed| seed.len() == KEY_SEED_LEN)
+                    .ok_or_else(|| key_error("invalid seed in key file"))?;
+                let mut fixed = [0; KEY_SEED_LEN];
+                fixed.copy_from_slice(&seed);
+                key_pairs.insert(key, KeyPair::from_seed(&fixed)?);
+            }
+        }
+        Ok(KeyStore {
+            key_pairs: Arc::new(RwLock::new(key_pairs)),
+            source: KeySource::File(path),
+        })
+    }
+
+    /// the key pair a key the store doesn't hold yet gets, @see KeyStore::generate()
+    fn new_key_pair(&self, key: &Agent) -> Result<KeyPair, HolochainError> {
+        match self.source {
+            KeySource::Seeded(ref master_seed) => {
+                let mut message = master_seed.to_vec();
+                message.extend_from_slice(key.to_string().as_bytes());
+                let mut seed = [0; KEY_SEED_LEN];
+                seed.copy_from_slice(digest(&SHA256, &message).as_ref());
+                KeyPair::from_seed(&seed)
+            }
+            _ => KeyPair::generate(),
+        }
+    }
+
+    /// writes the seeds of the key pairs to the file of the store, if it has one
+    fn save(&self, key_pairs: &HashMap<String, KeyPair>) -> Result<(), HolochainError> {
+        if let KeySource::File(ref path) = self.source {
+            let seeds: HashMap<&String, String> = key_pairs
+                .iter()
+                .map(|(key, key_pair)| (key, hex::encode(&key_pair.seed)))
+                .collect();
+            write_atomically(path, serde_json::to_string(&seeds)?.as_bytes(), true)?;
+        }
+        Ok(())
+    }
+
+    /// the key pair of the key
+    /// fails with UnknownKey if the store doesn't hold one, @see KeyStore::generate()
+    pub fn key_pair(&self, key: &Agent) -> Result<KeyPair, HolochainError> {
+        self.key_pairs
+            .read()
+            .map_err(|_| key_error("key store poisoned"))?
+            .get(&key.to_string())
+            .cloned()
+            .ok_or_else(|| HolochainError::UnknownKey(key.to_string()))
+    }
+
+    /// the key pair of the key, made now if the store doesn't hold one yet
+    /// only for keys the instance signs with itself, e.g. those of its own agent
+    pub fn generate(&self, key: &Agent) -> Result<KeyPair, HolochainError> {
+        let mut key_pairs = self
+            .key_pairs
+            .write()
+            .map_err(|_| key_error("key store poisoned"))?;
+        if !key_pairs.contains_key(&key.to_string()) {
+            key_pairs.insert(key.to_string(), self.new_key_pair(key)?);
+            self.save(&key_pairs)?;
+        }
+        Ok(key_pairs[&key.to_string()].clone())
+    }
+
+    /// replaces the key pair of the key, e.g. with one moved from another device
+    pub fn insert(&self, key: &Agent, key_pair: KeyPair) -> Result<(), HolochainError> {
+        let mut key_pairs = self
+            .key_pairs
+            .write()
+            .map_err(|_| key_error("key store poisoned"))?;
+        key_pairs.insert(key.to_string(), key_pair);
+        self.save(&key_pairs)
+    }
+
+    /// the public key of the key pair of the key, failing like key_pair()
+    pub fn public_key(&self, key: &Agent) -> Result<PublicKey, HolochainError> {
+        Ok(self.key_pair(key)?.public_key())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    /// a signature only verifies for the message and the public key of the key pair that made
+    /// it, and can't be made from the public key alone
+    fn signatures_verify_with_the_public_key() {
+        let alex = KeyPair::generate().unwrap();
+        let eve = KeyPair::generate().unwrap();
+        let signature = alex.sign(b"message");
+
+        assert!(verify_signature(&alex.public_key(), b"message", &signature));
+        assert!(!verify_signature(
+            &alex.public_key(),
+            b"other message",
+            &signature
+        ));
+        assert!(!verify_signature(&eve.public_key(), b"message", &signature));
+        assert!(!verify_signature(
+            &alex.public_key(),
+            b"message",
+            &eve.sign(b"message")
+        ));
+        assert!(!verify_signature(
+            &alex.public_key(),
+            b"message",
+            &Signature::from("fake-signature")
+        ));
+
+        let seed = [7; KEY_SEED_LEN];
+        let same = KeyPair::from_seed(&seed).unwrap();
+        assert_eq!(
+            same.public_key(),
+            KeyPair::from_seed(&seed).unwrap().public_key()
+        );
+        assert_ne!(alex.public_key(), eve.public_key());
+    }
+
+    #[test]
+    /// the key store keeps the key pair it generated for a key and makes up none for the
+    /// keys it wasn't asked to generate
+    fn key_store_keeps_key_pairs() {
+        let store = KeyStore::new();
+        let alex = Agent::from("alex".to_string());
+        let billie = Agent::from("billie".to_string());
+        assert_eq!(
+            Err(HolochainError::UnknownKey("alex".to_string())),
+            store.public_key(&alex)
+        );
+        let public_key = store.generate(&alex).unwrap().public_key();
+        assert_eq!(Ok(public_key.clone()), store.clone().public_key(&alex));
+        assert_eq!(public_key, store.generate(&alex).unwrap().public_key());
+        assert!(store.key_pair(&billie).is_err());
+        assert_ne!(public_key, store.generate(&billie).unwrap().public_key());
+    }
+
+    #[test]
+    /// seeded and file backed stores give a key the same key pair after a restart
+    fn key_stores_survive_restarts() {
+        let alex = Agent::from("alex".to_string());
+        let billie = Agent::from("billie".to_string());
+        let seeded = |seed: u8, agent: &Agent| {
+            KeyStore::from_seed(&[seed; KEY_SEED_LEN])
+                .generate(agent)
+                .unwrap()
+                .public_key()
+        };
+
+        assert_eq!(seeded(3, &alex), seeded(3, &alex));
+        assert_ne!(seeded(3, &alex), seeded(4, &alex));
+        assert_ne!(seeded(3, &alex), seeded(3, &billie));
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("keys.json");
+        let saved = KeyStore::open(&path)
+            .unwrap()
+            .generate(&alex)
+            .unwrap()
+            .public_key();
+        let reopened = KeyStore::open(&path).unwrap();
+        assert_eq!(Ok(saved.clone()), reopened.public_key(&alex));
+        assert_ne!(seeded(3, &alex), saved);
+
+        fs::write(&path, "not keys").unwrap();
+        assert!(KeyStore::open(&path).is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    /// the key file is only readable by its owner and replaced in one go
+    fn key_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("keys.json");
+        let store = KeyStore::open(&path).unwrap();
+        store.generate(&Agent::from("alex".to_string())).unwrap();
+        store.generate(&Agent::from("billie".to_string())).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(0o600, mode & 0o777);
+        assert_eq!(1, fs::read_dir(dir.path()).unwrap().count());
+    }
+}
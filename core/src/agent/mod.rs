@@ -1,5 +1,10 @@
 /// Agent is the module that handles the user’s identity and source chain for every Phenotype.
 ///
 pub mod actions;
+pub mod chain_export;
 pub mod chain_store;
+pub mod delegation;
+pub mod key_rotation;
+pub mod keys;
+pub mod signatures;
 pub mod state;
@@ -0,0 +1,130 @@
+//! Verifying the signatures of many entries at once.
+//!
+//! Importing a chain or warm starting from a snapshot verifies an Ed25519 signature per entry,
+//! which is bound by the CPU. The entries are split into contiguous runs that worker threads
+//! verify side by side, the results are put back together in the input order.
+//! Each item names its signer by the address of the signer's public key, @see PublicKey
+
+use agent::keys::{verify_signature, KeyPair, PublicKey};
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    entry::Entry,
+    signature::Signature,
+};
+use std::{sync::Arc, thread};
+
+/// default number of threads verifying signatures
+pub const DEFAULT_VERIFY_WORKERS: usize = 4;
+
+/// Signature of the entry with the key pair.
+pub fn entry_signature(key_pair: &KeyPair, entry: &Entry) -> Signature {
+    key_pair.sign(entry.address().to_string().as_bytes())
+}
+
+/// true if the signature of the entry was made with the key pair of the public key at the
+/// signer address
+pub fn verify_entry_signature(signer: &Address, entry: &Entry, signature: &Signature) -> bool {
+    verify_signature(
+        &PublicKey::from(signer),
+        entry.address().to_string().as_bytes(),
+        signature,
+    )
+}
+
+/// whether each entry was signed by its signer, in the order of the items
+/// verified by up to the given number of threads, at least one
+pub fn verify_entry_signatures(
+    items: Vec<(Entry, Signature, Address)>,
+    workers: usize,
+) -> Vec<bool> {
+    let workers = workers.max(1);
+    if workers == 1 || items.len() < 2 {
+        return items
+            .iter()
+            .map(|(entry, signature, signer)| verify_entry_signature(signer, entry, signature))
+            .collect();
+    }
+    let items = Arc::new(items);
+    let run_length = (items.len() + workers - 1) / workers;
+    let runs: Vec<thread::JoinHandle<Vec<bool>>> = (0..items.len())
+        .step_by(run_length)
+        .map(|start| {
+            let items = items.clone();
+            thread::spawn(move || {
+                let end = (start + run_length).min(items.len());
+                items[start..end]
+                    .iter()
+                    .map(|(entry, signature, signer)| {
+                        verify_entry_signature(signer, entry, signature)
+                    })
+                    .collect()
+            })
+        })
+        .collect();
+    runs.into_iter()
+        .flat_map(|run| run.join().expect("signature verification shouldn't panic"))
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core_types::entry::test_entry_unique;
+
+    /// entries signed with alex's key pair, every third signature made with another key pair
+    /// or over another entry
+    pub fn signed_entries(count: usize) -> Vec<(Entry, Signature, Address)> {
+        let alex = KeyPair::generate().unwrap();
+        let mallory = KeyPair::generate().unwrap();
+        (0..count)
+            .map(|index| {
+                let entry = test_entry_unique();
+                let signature = match index % 6 {
+                    2 => entry_signature(&mallory, &entry),
+                    5 => entry_signature(&alex, &test_entry_unique()),
+                    _ => entry_signature(&alex, &entry),
+                };
+                (entry, signature, Address::from(alex.public_key()))
+            })
+            .collect()
+    }
+
+    #[test]
+    /// results come back in input order, valid and invalid signatures alike
+    fn verifies_in_input_order() {
+        let items = signed_entries(10);
+        let expected: Vec<bool> = (0..10).map(|index| index % 3 != 2).collect();
+        assert_eq!(expected, verify_entry_signatures(items.clone(), 1));
+        assert_eq!(expected, verify_entry_signatures(items.clone(), 3));
+        assert_eq!(expected, verify_entry_signatures(items, 20));
+        assert!(verify_entry_signatures(Vec::new(), 4).is_empty());
+    }
+
+    #[test]
+    /// a signer address that isn't a public key verifies nothing
+    fn unknown_signer_test() {
+        let key_pair = KeyPair::generate().unwrap();
+        let entry = test_entry_unique();
+        let signature = entry_signature(&key_pair, &entry);
+        assert!(verify_entry_signature(
+            &Address::from(key_pair.public_key()),
+            &entry,
+            &signature
+        ));
+        assert!(!verify_entry_signature(
+            &Address::from("alex".to_string()),
+            &entry,
+            &signature
+        ));
+    }
+
+    #[test]
+    /// parallel verification agrees with serial verification
+    fn parallel_verification_matches_serial() {
+        let items = signed_entries(50);
+        assert_eq!(
+            verify_entry_signatures(items.clone(), 1),
+            verify_entry_signatures(items, DEFAULT_VERIFY_WORKERS)
+        );
+    }
+}
@@ -1,6 +1,13 @@
 use action::{Action, ActionWrapper, AgentReduceFn};
-use agent::chain_store::ChainStore;
+use agent::{
+    actions::commit::CommitOptions,
+    chain_store::ChainStore,
+    delegation::Delegation,
+    key_rotation::{active_key, check_rotation, ActiveKey},
+};
 use context::Context;
+use dht::network_worker::entry_from_peer;
+use holochain_agent::Agent;
 use holochain_cas_implementations::cas::memory::MemoryStorage;
 use holochain_core_types::{
     cas::{
@@ -9,13 +16,19 @@ use holochain_core_types::{
     },
     chain_header::ChainHeader,
     entry::Entry,
+    entry_type::EntryType,
     error::HolochainError,
     json::ToJson,
     keys::Keys,
     signature::Signature,
     time::Iso8601,
 };
-use std::{collections::HashMap, sync::Arc};
+use holochain_dna::zome::entry_types::Sharing;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
 
 /// The state-slice for the Agent.
 /// Holds the agent's source chain and keys.
@@ -28,6 +41,69 @@ pub struct AgentState {
     actions: HashMap<ActionWrapper, ActionResponse>,
     chain: ChainStore<MemoryStorage>,
     top_chain_header: Option<ChainHeader>,
+    /// committed entries of ephemeral types and when they were committed, as read from the
+    /// context's clock
+    /// they are kept here instead of on the chain
+    ephemeral: HashMap<Address, (Entry, Duration)>,
+    /// when the entries committed within the growth window were committed, oldest first,
+    /// as read from the context's clock
+    commit_times: VecDeque<Duration>,
+    /// entries committed to the chain, genesis left out
+    commit_count: u64,
+    /// commits rejected with ChainRateLimited
+    rate_limited_count: u64,
+    /// the entries on the chain by entry type, each distinct entry counted once
+    storage_by_type: HashMap<String, StorageStats>,
+}
+
+/// How much of the content storage the entries of a type take
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StorageStats {
+    /// number of distinct entries
+    pub count: usize,
+    /// bytes of their content
+    pub bytes: usize,
+}
+
+/// Bound on how many entries the agent commits to its source chain within any window of the
+/// given length, genesis left out
+/// commits over the limit fail with ChainRateLimited
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChainRateLimit {
+    pub max_commits: usize,
+    pub window: Duration,
+}
+
+impl ChainRateLimit {
+    pub fn new(max_commits: usize, window: Duration) -> Self {
+        ChainRateLimit {
+            max_commits,
+            window,
+        }
+    }
+}
+
+/// seconds of the window recent commits are counted in when the chain is not rate limited
+pub const DEFAULT_GROWTH_WINDOW: u64 = 60;
+
+/// the window recent commits are counted in, the window of the limit if there is one
+pub fn growth_window(limit: Option<ChainRateLimit>) -> Duration {
+    limit
+        .map(|limit| limit.window)
+        .unwrap_or_else(|| Duration::from_secs(DEFAULT_GROWTH_WINDOW))
+}
+
+/// How fast the source chain grows
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChainGrowthStats {
+    /// entries committed to the chain, genesis left out
+    pub commits: u64,
+    /// commits rejected with ChainRateLimited
+    pub rate_limited: u64,
+    /// length of the window recent commits are counted in, @see growth_window()
+    pub window: Duration,
+    /// commits within the window up to now
+    pub commits_in_window: usize,
 }
 
 impl AgentState {
@@ -38,6 +114,33 @@ impl AgentState {
             actions: HashMap::new(),
             chain,
             top_chain_header: None,
+            ephemeral: HashMap::new(),
+            commit_times: VecDeque::new(),
+            commit_count: 0,
+            rate_limited_count: 0,
+            storage_by_type: HashMap::new(),
+        }
+    }
+
+    /// copy of the state without its ephemeral parts, for persistence
+    /// the source chain and keys are kept, the action results, ephemeral
+    /// entries and growth counts start fresh
+    pub fn durable(&self) -> AgentState {
+        AgentState {
+            actions: HashMap::new(),
+            ephemeral: HashMap::new(),
+            commit_times: VecDeque::new(),
+            commit_count: 0,
+            rate_limited_count: 0,
+            ..self.clone()
+        }
+    }
+
+    /// the same state with its chain in the given storage, @see State::detached()
+    pub(crate) fn with_chain(&self, chain: ChainStore<MemoryStorage>) -> AgentState {
+        AgentState {
+            chain,
+            ..self.clone()
         }
     }
 
@@ -52,6 +155,11 @@ impl AgentState {
         self.actions.clone()
     }
 
+    /// the result of the action, None if the agent didn't reduce it
+    pub fn action_response(&self, action_wrapper: &ActionWrapper) -> Option<&ActionResponse> {
+        self.actions.get(action_wrapper)
+    }
+
     pub fn chain(&self) -> ChainStore<MemoryStorage> {
         self.chain.clone()
     }
@@ -59,6 +167,103 @@ impl AgentState {
     pub fn top_chain_header(&self) -> Option<ChainHeader> {
         self.top_chain_header.clone()
     }
+
+    /// the delegate a commit was made on behalf of, as recorded in its chain header
+    /// None if the agent committed for itself or the header is not in the content storage
+    pub fn delegate(&self, chain_header_address: &Address) -> Option<Agent> {
+        self.chain
+            .content_storage()
+            .fetch::<ChainHeader>(chain_header_address)
+            .ok()
+            .and_then(|chain_header| chain_header)
+            .and_then(|chain_header| Delegation::from_chain_header(&chain_header))
+            .map(|delegation| delegation.delegate)
+    }
+
+    /// a committed entry of an ephemeral type, if it hasn't expired
+    pub fn ephemeral_entry(&self, address: &Address) -> Option<Entry> {
+        self.ephemeral.get(address).map(|(entry, _)| entry.clone())
+    }
+
+    /// true if an ephemeral entry was committed at least ttl before now
+    /// @see Action::ExpireEphemeral
+    pub fn has_expired_ephemeral(&self, now: Duration, ttl: Duration) -> bool {
+        self.ephemeral
+            .values()
+            .any(|(_, committed)| now >= *committed + ttl)
+    }
+
+    /// the content type recorded in the chain header of the committed entry
+    /// None if the entry is not on the chain or was committed without a content type
+    pub fn content_type(&self, entry_address: &Address) -> Option<String> {
+        self.chain_header_of(entry_address)
+            .and_then(|chain_header| chain_header.content_type())
+    }
+
+    /// points the source chain at the header, e.g. one recovered from the content storage
+    /// the storage stats are counted again from the chain
+    pub(crate) fn set_top_chain_header(&mut self, top_chain_header: Option<ChainHeader>) {
+        self.top_chain_header = top_chain_header;
+        self.storage_by_type = HashMap::new();
+        let mut counted = HashSet::new();
+        let chain_headers: Vec<ChainHeader> = self.chain.iter(&self.top_chain_header).collect();
+        for chain_header in chain_headers {
+            if !counted.insert(chain_header.entry_address().clone()) {
+                continue;
+            }
+            if let Ok(Some(entry)) = self
+                .chain
+                .content_storage()
+                .fetch::<Entry>(chain_header.entry_address())
+            {
+                self.count_stored(&entry);
+            }
+        }
+    }
+
+    /// the number and size of the entries on the chain, by entry type
+    /// the same entry committed more than once is stored, and counted, once
+    pub fn storage_by_type(&self) -> &HashMap<String, StorageStats> {
+        &self.storage_by_type
+    }
+
+    fn count_stored(&mut self, entry: &Entry) {
+        let stats = self
+            .storage_by_type
+            .entry(entry.entry_type().to_string())
+            .or_insert_with(StorageStats::default);
+        stats.count += 1;
+        stats.bytes += entry.content().len();
+    }
+
+    /// how fast the chain grew, counting the commits within the window up to now
+    pub fn chain_growth_stats(&self, window: Duration, now: Duration) -> ChainGrowthStats {
+        ChainGrowthStats {
+            commits: self.commit_count,
+            rate_limited: self.rate_limited_count,
+            window,
+            commits_in_window: self
+                .commit_times
+                .iter()
+                .filter(|committed| now < **committed + window)
+                .count(),
+        }
+    }
+
+    /// the chain header the entry was committed with, if it is on the source chain
+    pub fn chain_header_of(&self, entry_address: &Address) -> Option<ChainHeader> {
+        self.chain
+            .iter(&self.top_chain_header)
+            .find(|chain_header| chain_header.entry_address() == entry_address)
+    }
+}
+
+/// true if the agent of the context committed the entry at the address to its source chain,
+/// so committing another entry at the address is an update of it
+pub(crate) fn is_own_entry(context: &Context, address: &Address) -> bool {
+    context.state().map_or(false, |state| {
+        state.agent().chain_header_of(address).is_some()
+    })
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -69,9 +274,11 @@ impl AgentState {
 // @see https://github.com/holochain/holochain-rust/issues/196
 pub enum ActionResponse {
     Commit(Result<Address, HolochainError>),
-    GetEntry(Option<Entry>),
+    GetEntry(Result<Option<Entry>, HolochainError>),
     GetLinks(Result<Vec<Address>, HolochainError>),
     LinkEntries(Result<Entry, HolochainError>),
+    Transaction(Result<Vec<Address>, HolochainError>),
+    RollBackCommit(Result<(), HolochainError>),
 }
 
 impl ToJson for ActionResponse {
@@ -82,8 +289,9 @@ impl ToJson for ActionResponse {
                 Err(err) => Ok((*err).to_json()?),
             },
             ActionResponse::GetEntry(result) => match result {
-                Some(entry) => Ok(entry.to_json()?),
-                None => Ok("".to_string()),
+                Ok(Some(entry)) => Ok(entry.to_json()?),
+                Ok(None) => Ok("".to_string()),
+                Err(err) => Ok((*err).to_json()?),
             },
             ActionResponse::GetLinks(result) => match result {
                 Ok(hash_list) => Ok(json!(hash_list).to_string()),
@@ -93,23 +301,170 @@ impl ToJson for ActionResponse {
                 Ok(entry) => Ok(format!("{{\"address\":\"{}\"}}", entry.address())),
                 Err(err) => Ok((*err).to_json()?),
             },
+            ActionResponse::Transaction(result) => match result {
+                Ok(addresses) => Ok(json!({ "addresses": addresses }).to_string()),
+                Err(err) => Ok((*err).to_json()?),
+            },
+            ActionResponse::RollBackCommit(result) => match result {
+                Ok(()) => Ok("".to_string()),
+                Err(err) => Ok((*err).to_json()?),
+            },
         }
     }
 }
 
+/// the content type the DNA declares for the entry's type, if any
+fn declared_content_type(context: &Arc<Context>, entry: &Entry) -> Option<String> {
+    if !entry.entry_type().to_owned().is_app() {
+        return None;
+    }
+    context
+        .state()
+        .and_then(|state| state.nucleus().dna())
+        .and_then(|dna| {
+            dna.get_entry_type_def(&entry.entry_type().to_string())
+                .cloned()
+        })
+        .and_then(|entry_type_def| entry_type_def.content_type)
+}
+
+/// the fields the DNA declares unique for the entry's type
+pub(crate) fn unique_fields(context: &Arc<Context>, entry: &Entry) -> Vec<String> {
+    if !entry.entry_type().to_owned().is_app() {
+        return Vec::new();
+    }
+    context
+        .state()
+        .and_then(|state| state.nucleus().dna())
+        .and_then(|dna| {
+            dna.get_entry_type_def(&entry.entry_type().to_string())
+                .cloned()
+        })
+        .map(|entry_type_def| entry_type_def.unique_fields)
+        .unwrap_or_default()
+}
+
+/// true if committing the entry counts towards the growth of the chain, genesis doesn't
+fn counts_towards_growth(entry: &Entry) -> bool {
+    match entry.entry_type() {
+        EntryType::Dna | EntryType::AgentId => false,
+        _ => true,
+    }
+}
+
+/// true if the DNA declares the entry's type as ephemeral
+fn is_ephemeral(context: &Arc<Context>, entry: &Entry) -> bool {
+    if !entry.entry_type().to_owned().is_app() {
+        return false;
+    }
+    context
+        .state()
+        .and_then(|state| state.nucleus().dna())
+        .and_then(|dna| {
+            dna.get_entry_type_def(&entry.entry_type().to_string())
+                .cloned()
+        })
+        .map(|entry_type_def| entry_type_def.sharing == Sharing::Ephemeral)
+        .unwrap_or(false)
+}
+
 /// Do a Commit Action against an agent state.
+/// Entries of ephemeral types are kept aside instead of being added to the chain.
 /// Intended for use inside the reducer, isolated for unit testing.
 /// callback checks (e.g. validate_commit) happen elsewhere because callback functions cause
 /// action reduction to hang
 /// @TODO is there a way to reduce that doesn't block indefinitely on callback fns?
 /// @see https://github.com/holochain/holochain-rust/issues/222
 fn reduce_commit_entry(
-    _context: Arc<Context>,
+    context: Arc<Context>,
     state: &mut AgentState,
     action_wrapper: &ActionWrapper,
 ) {
-    let action = action_wrapper.action();
-    let entry = unwrap_to!(action => Action::Commit);
+    let (entry, options) = match action_wrapper.action() {
+        Action::Commit(entry) => (entry, CommitOptions::default()),
+        Action::CommitWithOptions(entry, options) => (entry, options.clone()),
+        _ => unreachable!(),
+    };
+
+    let res = if context.read_only {
+        Err(HolochainError::ReadOnlyInstance)
+    } else {
+        commit(&context, state, entry, options)
+    };
+
+    state
+        .actions
+        .insert(action_wrapper.clone(), ActionResponse::Commit(res));
+}
+
+/// adds the entry to the chain, or keeps it aside if its type is ephemeral
+fn commit(
+    context: &Arc<Context>,
+    state: &mut AgentState,
+    entry: &Entry,
+    options: CommitOptions,
+) -> Result<Address, HolochainError> {
+    if is_ephemeral(context, entry) {
+        state
+            .ephemeral
+            .insert(entry.address(), (entry.clone(), context.clock.now()));
+        return Ok(entry.address());
+    }
+
+    // commits are counted in a window sliding with the context's clock
+    let growth = counts_towards_growth(entry);
+    let now = context.clock.now();
+    if growth {
+        let window = growth_window(context.chain_rate_limit);
+        while state
+            .commit_times
+            .front()
+            .map_or(false, |committed| now >= *committed + window)
+        {
+            state.commit_times.pop_front();
+        }
+        if let Some(limit) = context.chain_rate_limit {
+            if state.commit_times.len() >= limit.max_commits {
+                state.rate_limited_count += 1;
+                return Err(HolochainError::ChainRateLimited);
+            }
+        }
+    }
+
+    check_key_rotation(context, state, entry)?;
+    // an entry only takes the address its content derives
+    context.verify_address_key(entry)?;
+
+    // commits are reduced one at a time, so of two commits with the same value in a unique
+    // field the one reduced first wins
+    let unique_fields = unique_fields(context, entry);
+    if !unique_fields.is_empty() {
+        if let Some(state) = context.state() {
+            state.dht().check_unique(entry, &unique_fields)?;
+        }
+    }
+    // the same goes for entries addressed by a key, e.g. a username, unless the agent
+    // updates an entry it committed itself
+    if entry.address_key().is_some() {
+        let update = state.chain_header_of(&entry.address()).is_some();
+        if let Some(state) = context.state() {
+            state.dht().check_address_key(entry, update)?;
+        }
+    }
+
+    let content_type = options
+        .content_type
+        .or_else(|| declared_content_type(context, entry));
+    // the scope and proof are recorded with the delegate, so the chain alone shows who allowed
+    // the commit
+    let delegation = match options.delegation {
+        Some(delegation) => Some((
+            delegation.delegate.to_string(),
+            delegation.scope.to_json()?,
+            delegation.proof,
+        )),
+        None => None,
+    };
 
     // @TODO validation dispatch should go here rather than upstream in invoke_commit
     // @see https://github.com/holochain/holochain-rust/issues/256
@@ -130,7 +485,9 @@ fn reduce_commit_entry(
             .and_then(|chain_header| Some(chain_header.address())),
         // @TODO timestamp
         &Iso8601::from(""),
-    );
+    )
+    .with_content_type(content_type)
+    .with_delegation(delegation);
 
     // @TODO adding the entry to the CAS should happen elsewhere.
     fn response(
@@ -138,36 +495,217 @@ fn reduce_commit_entry(
         entry: &Entry,
         chain_header: &ChainHeader,
     ) -> Result<Address, HolochainError> {
+        let is_new = !state.chain.content_storage().contains(&entry.address())?;
         state.chain.content_storage().add(entry)?;
         state.chain.content_storage().add(chain_header)?;
+        if is_new {
+            state.count_stored(entry);
+        }
         Ok(entry.address())
     }
     let res = response(state, &entry, &chain_header);
+    if growth && res.is_ok() {
+        state.commit_times.push_back(now);
+        state.commit_count += 1;
+    }
     state.top_chain_header = Some(chain_header);
+    res
+}
 
+/// only the holder of the active key can rotate it
+fn check_key_rotation(
+    context: &Arc<Context>,
+    state: &AgentState,
+    entry: &Entry,
+) -> Result<(), HolochainError> {
+    if entry.entry_type() != &EntryType::Key {
+        return Ok(());
+    }
+    let public_key = context.keystore.public_key(&context.agent)?;
+    let initial = ActiveKey::new(&context.agent, &public_key);
+    check_rotation(&active_key(&initial, state)?, entry)
+}
+
+/// fails if committing the entries one after the other would fail for the agent, without
+/// committing any
+/// the checks against the DHT are left to Transaction::check(), key rotations are checked
+/// against the key active before the entries, so the key is rotated at most once
+fn check_commits(
+    context: &Arc<Context>,
+    state: &AgentState,
+    entries: &[&Entry],
+) -> Result<(), HolochainError> {
+    if let Some(limit) = context.chain_rate_limit {
+        let growth = entries
+            .iter()
+            .filter(|entry| !is_ephemeral(context, entry) && counts_towards_growth(entry))
+            .count();
+        let now = context.clock.now();
+        let recent = state
+            .commit_times
+            .iter()
+            .filter(|committed| now < **committed + limit.window)
+            .count();
+        if growth > 0 && recent + growth > limit.max_commits {
+            return Err(HolochainError::ChainRateLimited);
+        }
+    }
+    for entry in entries {
+        check_key_rotation(context, state, entry)?;
+    }
+    Ok(())
+}
+
+/// commit the entries one after the other
+/// if one of them fails, what the ones before it added to the content storage is taken out
+/// again, so the storage holds none of the entries nor headers of a failed transaction
+/// entries the storage held before are left in it
+fn commit_all(
+    context: &Arc<Context>,
+    state: &mut AgentState,
+    entries: &[&Entry],
+) -> Result<Vec<Address>, HolochainError> {
+    let top_before = state
+        .top_chain_header
+        .as_ref()
+        .map(|header| header.address());
+    let mut storage = state.chain.content_storage();
+    let mut new_entries = Vec::new();
+    for entry in entries {
+        if !storage.contains(&entry.address())? {
+            new_entries.push(entry.address());
+        }
+    }
+    let mut committed = Vec::new();
+    for entry in entries {
+        match commit(context, state, entry, CommitOptions::default()) {
+            Ok(address) => committed.push(address),
+            Err(error) => {
+                // the headers of the transaction are the ones on top of the header it found
+                let mut header = state.top_chain_header.clone();
+                while let Some(added) = header {
+                    if Some(added.address()) == top_before {
+                        break;
+                    }
+                    storage.remove(&added.address())?;
+                    header = match added.link() {
+                        Some(link) => storage.fetch(&link)?,
+                        None => None,
+                    };
+                }
+                for address in new_entries {
+                    storage.remove(&address)?;
+                }
+                return Err(error);
+            }
+        }
+    }
+    Ok(committed)
+}
+
+/// take the entry back off the source chain if it was committed last
+/// a commit made on top of it since isn't undone, so the entry is left as it is and the
+/// response says it couldn't be rolled back
+fn reduce_roll_back_commit(
+    _context: Arc<Context>,
+    state: &mut AgentState,
+    action_wrapper: &ActionWrapper,
+) {
+    let address = unwrap_to!(action_wrapper.action() => Action::RollBackCommit);
+    let res = roll_back_commit(state, address);
     state
         .actions
-        .insert(action_wrapper.clone(), ActionResponse::Commit(res));
+        .insert(action_wrapper.clone(), ActionResponse::RollBackCommit(res));
 }
 
-/// do a get action against an agent state
-/// intended for use inside the reducer, isolated for unit testing
-fn reduce_get_entry(
-    _context: Arc<Context>,
+fn roll_back_commit(state: &mut AgentState, address: &Address) -> Result<(), HolochainError> {
+    if state.ephemeral.remove(address).is_some() {
+        return Ok(());
+    }
+    let top_chain_header = match state.top_chain_header.clone() {
+        Some(ref chain_header) if chain_header.entry_address() == address => chain_header.clone(),
+        _ => {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "{} is not the entry committed last",
+                address
+            )))
+        }
+    };
+    let previous = match top_chain_header.link() {
+        Some(link) => state.chain.content_storage().fetch::<ChainHeader>(&link)?,
+        None => None,
+    };
+    state.set_top_chain_header(previous);
+    Ok(())
+}
+
+/// commit the entries of the transaction, unless any of its operations would fail
+/// every check runs before the first entry is committed, the entries are committed to a copy
+/// of the state that only replaces the state if all of them were, so a failing storage
+/// doesn't leave part of the transaction on the chain either, @see commit_all()
+/// the links are added by the DHT reducer, which runs the same checks and only applies the
+/// transaction if the agent committed it, @see State::reduce()
+fn reduce_transaction(
+    context: Arc<Context>,
     state: &mut AgentState,
     action_wrapper: &ActionWrapper,
 ) {
+    let transaction = unwrap_to!(action_wrapper.action() => Action::Transaction);
+    let dht = context.state().expect("context must have a State.").dht();
+    let entries = transaction.entries();
+    let mut committed = state.clone();
+    let res = transaction
+        .check(&context, &dht)
+        .and_then(|_| check_commits(&context, state, &entries))
+        .and_then(|_| commit_all(&context, &mut committed, &entries));
+    match res {
+        Ok(_) => *state = committed,
+        Err(HolochainError::ChainRateLimited) => state.rate_limited_count += 1,
+        Err(_) => (),
+    }
+
+    state
+        .actions
+        .insert(action_wrapper.clone(), ActionResponse::Transaction(res));
+}
+
+/// do a get action against an agent state
+/// intended for use inside the reducer, isolated for unit testing
+fn reduce_get_entry(context: Arc<Context>, state: &mut AgentState, action_wrapper: &ActionWrapper) {
     let action = action_wrapper.action();
     let address = unwrap_to!(action => Action::GetEntry);
 
-    let result = state
+    let local = state
         .chain
         .content_storage()
         .fetch(&address)
-        .expect("could not fetch from CAS");
-
-    // @TODO if the get fails local, do a network get
-    // @see https://github.com/holochain/holochain-rust/issues/167
+        .expect("could not fetch from CAS")
+        .or_else(|| state.ephemeral_entry(&address));
+    // entries the peers are asked for are answered once they did, @see reduce_return_get_result
+    // the peers decide whether the agent may have entries with an access control list
+    let result = match local {
+        Some(entry) => Ok(Some(entry)),
+        None => match context.state() {
+            Some(state) => {
+                let dht = state.dht();
+                match dht.cache().peek(&address) {
+                    Some(entry) => Ok(Some(entry)),
+                    None if dht.network().backend().is_none() => Ok(None),
+                    None => match context.negative_cache_ttl {
+                        Some(ttl)
+                            if dht
+                                .cache()
+                                .is_known_missing(&address, ttl, context.clock.now()) =>
+                        {
+                            Ok(None)
+                        }
+                        _ => return,
+                    },
+                }
+            }
+            None => Ok(None),
+        },
+    };
 
     state.actions.insert(
         action_wrapper.clone(),
@@ -175,11 +713,53 @@ fn reduce_get_entry(
     );
 }
 
+/// answer the GetEntry action the peers were asked for with what they answered
+/// content that isn't the entry asked for is no entry, entries the peers refused the agent
+/// are AccessDenied
+fn reduce_return_get_result(
+    context: Arc<Context>,
+    state: &mut AgentState,
+    action_wrapper: &ActionWrapper,
+) {
+    let (get, content) = match action_wrapper.action() {
+        Action::ReturnGetResult(get, content) => (get, content),
+        _ => unreachable!(),
+    };
+    if state.actions.contains_key(get) {
+        return;
+    }
+    let address = unwrap_to!(get.action() => Action::GetEntry);
+    let result = content.clone().map(|content| {
+        content.and_then(|content| entry_from_peer(&context, address, &content).ok())
+    });
+    state
+        .actions
+        .insert((**get).clone(), ActionResponse::GetEntry(result));
+}
+
+/// drop the ephemeral entries older than the duration of the action, by the context's clock
+fn reduce_expire_ephemeral(
+    context: Arc<Context>,
+    state: &mut AgentState,
+    action_wrapper: &ActionWrapper,
+) {
+    let ttl = unwrap_to!(action_wrapper.action() => Action::ExpireEphemeral);
+    let now = context.clock.now();
+    state
+        .ephemeral
+        .retain(|_, (_, committed)| now < *committed + *ttl);
+}
+
 /// maps incoming action to the correct handler
 fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<AgentReduceFn> {
     match action_wrapper.action() {
         Action::Commit(_) => Some(reduce_commit_entry),
+        Action::CommitWithOptions(_, _) => Some(reduce_commit_entry),
+        Action::RollBackCommit(_) => Some(reduce_roll_back_commit),
         Action::GetEntry(_) => Some(reduce_get_entry),
+        Action::ReturnGetResult(_, _) => Some(reduce_return_get_result),
+        Action::ExpireEphemeral(_) => Some(reduce_expire_ephemeral),
+        Action::Transaction(_) => Some(reduce_transaction),
         _ => None,
     }
 }
@@ -203,17 +783,45 @@ pub fn reduce(
 
 #[cfg(test)]
 pub mod tests {
-    use super::{reduce_commit_entry, reduce_get_entry, ActionResponse, AgentState};
-    use action::tests::{test_action_wrapper_commit, test_action_wrapper_get};
-    use agent::chain_store::tests::test_chain_store;
+    use super::{
+        reduce_commit_entry, reduce_get_entry, reduce_roll_back_commit, reduce_transaction,
+        ActionResponse, AgentState, ChainGrowthStats, ChainRateLimit, StorageStats,
+    };
+    use action::{
+        tests::{test_action_wrapper_commit, test_action_wrapper_get},
+        Action, ActionWrapper,
+    };
+    use agent::{
+        actions::{commit::CommitOptions, transaction::Transaction},
+        chain_store::tests::test_chain_store,
+        delegation::{tests::test_scope, Delegation},
+        key_rotation::{ActiveKey, KeyRotation},
+        keys::KeyPair,
+    };
+    use clock::{Clock, ManualClock};
+    use holochain_agent::Agent;
     use holochain_core_types::{
-        cas::content::AddressableContent,
-        entry::{test_entry, test_entry_address},
+        cas::{content::AddressableContent, storage::ContentAddressableStorage},
+        entry::{test_entry, test_entry_address, test_entry_b, test_entry_unique, ToEntry},
         error::HolochainError,
         json::ToJson,
+        signature::test_signature,
+    };
+    use holochain_dna::{
+        zome::{
+            entry_types::{EntryTypeDef, Sharing},
+            Zome,
+        },
+        Dna,
     };
     use instance::tests::test_context;
-    use std::{collections::HashMap, sync::Arc};
+    use persister::{Persister, SimplePersister};
+    use state::State;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, RwLock},
+        time::Duration,
+    };
 
     /// dummy agent state
     pub fn test_agent_state() -> AgentState {
@@ -227,7 +835,7 @@ pub mod tests {
 
     /// dummy action response for a successful get as test_entry()
     pub fn test_action_response_get() -> ActionResponse {
-        ActionResponse::GetEntry(Some(test_entry()))
+        ActionResponse::GetEntry(Ok(Some(test_entry())))
     }
 
     #[test]
@@ -262,6 +870,330 @@ pub mod tests {
         );
     }
 
+    #[test]
+    /// commits past the limit within the window are rejected until the window slides on
+    fn test_reduce_commit_entry_rate_limited() {
+        let clock = ManualClock::new(Duration::from_secs(1000));
+        let mut context = (*test_context("bob")).clone();
+        context.clock = Arc::new(clock.clone());
+        context.chain_rate_limit = Some(ChainRateLimit::new(2, Duration::from_secs(60)));
+        let context = Arc::new(context);
+        let mut state = test_agent_state();
+
+        let commit = |state: &mut AgentState| {
+            let action_wrapper = ActionWrapper::new(Action::Commit(test_entry_unique()));
+            reduce_commit_entry(context.clone(), state, &action_wrapper);
+            state.actions().get(&action_wrapper).cloned()
+        };
+        for _ in 0..2 {
+            match commit(&mut state) {
+                Some(ActionResponse::Commit(Ok(_))) => (),
+                response => panic!("the commit should be allowed: {:?}", response),
+            }
+        }
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(
+            Some(ActionResponse::Commit(Err(
+                HolochainError::ChainRateLimited
+            ))),
+            commit(&mut state)
+        );
+        assert_eq!(
+            ChainGrowthStats {
+                commits: 2,
+                rate_limited: 1,
+                window: Duration::from_secs(60),
+                commits_in_window: 2,
+            },
+            state.chain_growth_stats(Duration::from_secs(60), clock.now())
+        );
+
+        clock.advance(Duration::from_secs(31));
+        match commit(&mut state) {
+            Some(ActionResponse::Commit(Ok(_))) => (),
+            response => panic!("the window should have slid on: {:?}", response),
+        }
+        let stats = state.chain_growth_stats(Duration::from_secs(60), clock.now());
+        assert_eq!(
+            (3, 1, 1),
+            (stats.commits, stats.rate_limited, stats.commits_in_window)
+        );
+    }
+
+    #[test]
+    /// entries are counted by type, an entry committed twice is stored once
+    fn test_storage_by_type() {
+        let mut state = test_agent_state();
+        let context = test_context("bob");
+        for entry in vec![test_entry(), test_entry_b(), test_entry()] {
+            let action_wrapper = ActionWrapper::new(Action::Commit(entry));
+            reduce_commit_entry(context.clone(), &mut state, &action_wrapper);
+        }
+
+        let stats = state.storage_by_type().clone();
+        assert_eq!(2, stats.len());
+        assert_eq!(
+            Some(&StorageStats {
+                count: 1,
+                bytes: test_entry().content().len(),
+            }),
+            stats.get("testEntryType")
+        );
+        assert_eq!(
+            Some(&StorageStats {
+                count: 1,
+                bytes: test_entry_b().content().len(),
+            }),
+            stats.get("testEntryTypeB")
+        );
+
+        // counted again from the chain, e.g. when it is recovered from storage
+        let top_chain_header = state.top_chain_header();
+        state.set_top_chain_header(top_chain_header);
+        assert_eq!(&stats, state.storage_by_type());
+    }
+
+    #[test]
+    /// only the entry committed last is taken back off the chain
+    fn test_reduce_roll_back_commit() {
+        let mut state = test_agent_state();
+        let context = test_context("bob");
+        for entry in vec![test_entry(), test_entry_b()] {
+            let action_wrapper = ActionWrapper::new(Action::Commit(entry));
+            reduce_commit_entry(context.clone(), &mut state, &action_wrapper);
+        }
+        let committed = state.top_chain_header();
+
+        let action_wrapper = ActionWrapper::new(Action::RollBackCommit(test_entry().address()));
+        reduce_roll_back_commit(context.clone(), &mut state, &action_wrapper);
+        assert_eq!(committed, state.top_chain_header());
+        assert!(match state.action_response(&action_wrapper) {
+            Some(ActionResponse::RollBackCommit(Err(_))) => true,
+            _ => false,
+        });
+
+        let action_wrapper = ActionWrapper::new(Action::RollBackCommit(test_entry_b().address()));
+        reduce_roll_back_commit(context.clone(), &mut state, &action_wrapper);
+        assert_eq!(
+            Some(&ActionResponse::RollBackCommit(Ok(()))),
+            state.action_response(&action_wrapper)
+        );
+        assert_eq!(None, state.chain_header_of(&test_entry_b().address()));
+        assert_eq!(
+            state.chain_header_of(&test_entry().address()),
+            state.top_chain_header()
+        );
+        assert_eq!(None, state.storage_by_type().get("testEntryTypeB"));
+    }
+
+    #[test]
+    /// test that delegated commits are attributed to the delegate
+    fn test_reduce_commit_delegated_entry() {
+        let mut state = test_agent_state();
+        let delegate = Agent::from("bob".to_string());
+        let action_wrapper = ActionWrapper::new(Action::CommitWithOptions(
+            test_entry(),
+            CommitOptions {
+                delegation: Some(Delegation::new(&delegate, &test_scope(), &test_signature())),
+                content_type: None,
+                acl: None,
+                namespace: None,
+                hold_publish: false,
+            },
+        ));
+
+        reduce_commit_entry(test_context("alex"), &mut state, &action_wrapper);
+
+        assert_eq!(
+            state.actions().get(&action_wrapper),
+            Some(&test_action_response_commit()),
+        );
+        let top_chain_header = state
+            .top_chain_header()
+            .expect("commit should add a header");
+        assert_eq!(Some(delegate), state.delegate(&top_chain_header.address()));
+        assert_eq!(Some(test_signature()), top_chain_header.delegation_proof());
+
+        reduce_commit_entry(
+            test_context("alex"),
+            &mut state,
+            &test_action_wrapper_commit(),
+        );
+        let top_chain_header = state
+            .top_chain_header()
+            .expect("commit should add a header");
+        assert_eq!(None, state.delegate(&top_chain_header.address()));
+    }
+
+    #[test]
+    /// test that the content type given to the commit is recorded in the header
+    fn test_reduce_commit_entry_with_content_type() {
+        let mut state = test_agent_state();
+        let content_type = Some("text/markdown".to_string());
+        let action_wrapper = ActionWrapper::new(Action::CommitWithOptions(
+            test_entry(),
+            CommitOptions {
+                delegation: None,
+                content_type: content_type.clone(),
+                acl: None,
+                namespace: None,
+                hold_publish: false,
+            },
+        ));
+
+        reduce_commit_entry(test_context("alex"), &mut state, &action_wrapper);
+
+        assert_eq!(content_type, state.content_type(&test_entry().address()));
+        assert_eq!(
+            content_type,
+            state
+                .top_chain_header()
+                .and_then(|header| header.content_type())
+        );
+        assert_eq!(None, state.content_type(&test_entry_b().address()));
+    }
+
+    #[test]
+    /// a transaction failing halfway takes what its earlier commits added back out of the
+    /// content storage
+    fn failed_transaction_leaves_no_content() {
+        let mut context = (*test_context("bob")).clone();
+        context.set_state(Arc::new(RwLock::new(State::new())));
+        let context = Arc::new(context);
+        let bob = context.agent.clone();
+        let bob_key = context.keystore.key_pair(&bob).unwrap();
+        let rotation_to = |name: &str| {
+            let key = ActiveKey::new(
+                &Agent::from(name.to_string()),
+                &KeyPair::generate().unwrap().public_key(),
+            );
+            KeyRotation::new(&bob, &bob_key, &key).to_entry()
+        };
+        // both rotations are signed by the key active before the transaction, so it only
+        // fails at the second, once the first rotated the key
+        let (bob2, mallory) = (rotation_to("bob2"), rotation_to("mallory"));
+        let mut transaction = Transaction::new();
+        transaction
+            .commit(test_entry())
+            .commit(bob2.clone())
+            .commit(mallory.clone());
+        let action_wrapper = ActionWrapper::new(Action::Transaction(transaction));
+
+        let mut state = test_agent_state();
+        reduce_transaction(context.clone(), &mut state, &action_wrapper);
+
+        assert_eq!(
+            Some(&ActionResponse::Transaction(Err(
+                HolochainError::InvalidKeyRotation
+            ))),
+            state.actions().get(&action_wrapper)
+        );
+        assert_eq!(None, state.top_chain_header());
+        let storage = state.chain().content_storage();
+        for entry in vec![test_entry(), bob2, mallory] {
+            assert_eq!(Ok(false), storage.contains(&entry.address()));
+        }
+        assert_eq!(Ok(vec![]), storage.addresses());
+    }
+
+    #[test]
+    /// test that commits without a content type get the one declared in the DNA
+    fn test_reduce_commit_entry_with_declared_content_type() {
+        let mut entry_type_def = EntryTypeDef::new();
+        entry_type_def.content_type = Some("application/json".to_string());
+        let mut zome = Zome::default();
+        zome.entry_types
+            .insert(test_entry().entry_type().to_string(), entry_type_def);
+        let mut dna = Dna::new();
+        dna.zomes.insert("zome".to_string(), zome);
+
+        let mut context = (*test_context("alex")).clone();
+        let app_state = State::new().reduce(
+            test_context("alex"),
+            ActionWrapper::new(Action::InitApplication(dna)),
+        );
+        context.set_state(Arc::new(RwLock::new(app_state)));
+
+        let mut state = test_agent_state();
+        reduce_commit_entry(Arc::new(context), &mut state, &test_action_wrapper_commit());
+
+        assert_eq!(
+            Some("application/json".to_string()),
+            state.content_type(&test_entry().address())
+        );
+    }
+
+    #[test]
+    /// ephemeral entries can be fetched locally but are neither chained, published nor persisted,
+    /// and expire by the context's clock
+    fn test_reduce_commit_ephemeral_entry() {
+        let mut entry_type_def = EntryTypeDef::new();
+        entry_type_def.sharing = Sharing::Ephemeral;
+        let mut zome = Zome::default();
+        zome.entry_types
+            .insert(test_entry().entry_type().to_string(), entry_type_def);
+        let mut dna = Dna::new();
+        dna.zomes.insert("zome".to_string(), zome);
+
+        let clock = ManualClock::new(Duration::from_secs(1000));
+        let mut context = (*test_context("alex")).clone();
+        context.clock = Arc::new(clock.clone());
+        let app_state = State::new().reduce(
+            test_context("alex"),
+            ActionWrapper::new(Action::InitApplication(dna)),
+        );
+        context.set_state(Arc::new(RwLock::new(app_state.clone())));
+        let context = Arc::new(context);
+
+        let commit = test_action_wrapper_commit();
+        let get = test_action_wrapper_get();
+        let state = app_state
+            .reduce(context.clone(), commit.clone())
+            .reduce(context.clone(), get.clone());
+
+        assert_eq!(
+            Some(&test_action_response_commit()),
+            state.agent().actions().get(&commit)
+        );
+        assert_eq!(
+            Some(&test_action_response_get()),
+            state.agent().actions().get(&get)
+        );
+        assert_eq!(None, state.agent().top_chain_header());
+        assert!(state.dht().network().published().is_empty());
+
+        let mut persister = SimplePersister::new();
+        persister.save(state.clone());
+        let loaded = persister
+            .load()
+            .unwrap()
+            .expect("state should have been saved");
+        assert_eq!(
+            None,
+            loaded.agent().ephemeral_entry(&test_entry().address())
+        );
+
+        let ttl = Duration::from_secs(60);
+        clock.advance(Duration::from_secs(59));
+        assert!(!state.agent().has_expired_ephemeral(clock.now(), ttl));
+        let state = state.reduce(
+            context.clone(),
+            ActionWrapper::new(Action::ExpireEphemeral(ttl)),
+        );
+        assert_eq!(
+            Some(test_entry()),
+            state.agent().ephemeral_entry(&test_entry().address())
+        );
+        clock.advance(Duration::from_secs(1));
+        assert!(state.agent().has_expired_ephemeral(clock.now(), ttl));
+        let state = state.reduce(
+            context.clone(),
+            ActionWrapper::new(Action::ExpireEphemeral(ttl)),
+        );
+        assert_eq!(None, state.agent().ephemeral_entry(&test_entry().address()));
+        assert!(!state.agent().has_expired_ephemeral(clock.now(), ttl));
+    }
+
     #[test]
     /// test for reducing get entry
     fn test_reduce_get_entry() {
@@ -274,7 +1206,7 @@ pub mod tests {
         // nothing has been committed so the get must be None
         assert_eq!(
             state.actions().get(&aw1),
-            Some(&ActionResponse::GetEntry(None)),
+            Some(&ActionResponse::GetEntry(Ok(None))),
         );
 
         // do a round trip
@@ -311,11 +1243,11 @@ pub mod tests {
     fn test_get_response_to_json() {
         assert_eq!(
             "{\"value\":\"test entry value\",\"entry_type\":{\"App\":\"testEntryType\"}}",
-            ActionResponse::GetEntry(Some(test_entry().clone()))
+            ActionResponse::GetEntry(Ok(Some(test_entry().clone())))
                 .to_json()
                 .unwrap(),
         );
-        assert_eq!("", ActionResponse::GetEntry(None).to_json().unwrap());
+        assert_eq!("", ActionResponse::GetEntry(Ok(None)).to_json().unwrap());
     }
 
     #[test]
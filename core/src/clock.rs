@@ -0,0 +1,68 @@
+//! Where an instance reads the current time from.
+//!
+//! Time dependent behavior, like the expiry of entries, asks the clock of the context instead
+//! of the system, so tests can inject a ManualClock and move time forward deterministically.
+
+use instance::PoisonPolicy;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+pub trait Clock: Send + Sync {
+    /// time elapsed since the UNIX epoch
+    fn now(&self) -> Duration;
+}
+
+/// Clock reading the system time
+#[derive(Clone, Debug, Default)]
+pub struct SystemClock {}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time should be after the UNIX epoch")
+    }
+}
+
+/// Clock whose time only changes when it is advanced
+/// clones share the same time
+/// shared by instances whatever their poison policy, its lock follows the default one
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    now: Arc<Mutex<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new(now: Duration) -> Self {
+        ManualClock {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *PoisonPolicy::default().unpoison(self.now.lock(), "clock Mutex") += by;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        *PoisonPolicy::default().unpoison(self.now.lock(), "clock Mutex")
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    /// clones of a manual clock advance together
+    fn manual_clock_test() {
+        let clock = ManualClock::new(Duration::from_secs(10));
+        let clone = clock.clone();
+        clone.advance(Duration::from_secs(5));
+        assert_eq!(Duration::from_secs(15), clock.now());
+        assert_eq!(Duration::from_secs(15), clone.now());
+    }
+}
@@ -1,15 +1,54 @@
 use action::ActionWrapper;
+use agent::{keys::KeyStore, state::ChainRateLimit};
+use clock::{Clock, SystemClock};
+use dht::{
+    dht_store::{indexed_field_values, PublishOrder, PublishRateLimit},
+    get_limit::NetworkGetLimit,
+    routing::{AnyPeer, RoutingStrategy},
+    storage_routes::StorageRoutes,
+};
 use holochain_agent::Agent;
-use holochain_core_types::error::HolochainError;
-use instance::Observer;
+use holochain_cas_implementations::cas::encrypted::{EncryptedStorage, StorageKey};
+use holochain_core_types::{
+    cas::{
+        content::{Address, Content},
+        storage::ContentAddressableStorage,
+    },
+    entry::Entry,
+    entry_type::EntryType,
+    error::HolochainError,
+    json::sort_json_keys,
+};
+use holochain_dna::zome::entry_types::AddressDerivation;
+use instance::{Observer, PoisonPolicy};
 use logger::Logger;
+use nucleus::{
+    actions::validate::ValidationLevel,
+    ribosome::{callback::post_commit::PostCommitWorker, modules::ZomeModules},
+};
 use persister::Persister;
+use random::{CallIdSeed, SeedSource};
+use scheduler::SchedulerPriorities;
 use state::State;
-use std::sync::{
-    mpsc::{sync_channel, SyncSender},
-    Arc, Mutex, RwLock, RwLockReadGuard,
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{sync_channel, SyncSender},
+        Arc, Mutex, RwLock, RwLockReadGuard,
+    },
+    time::Duration,
 };
 
+/// renders addresses for export surfaces
+pub type AddressFormatter = Arc<Fn(&Address) -> String + Send + Sync>;
+
+/// rewrites the content of app entries before they are committed, and therefore addressed
+pub type ContentCanonicalizer = Arc<Fn(&EntryType, &Content) -> Content + Send + Sync>;
+
+/// makes the key the address of an entry is derived from out of its content
+/// @see AddressDerivation::Custom
+pub type AddressDeriver = Arc<Fn(&Content) -> Result<String, HolochainError> + Send + Sync>;
+
 /// Context holds the components that parts of a Holochain instance need in order to operate.
 /// This includes components that are injected from the outside like logger and persister
 /// but also the store of the instance that gets injected before passing on the context
@@ -17,11 +56,76 @@ use std::sync::{
 #[derive(Clone)]
 pub struct Context {
     pub agent: Agent,
+    /// the key pairs the agent signs with, shared by the clones of the context
+    /// random ones lost with the context by default, set a KeyStore::open() or
+    /// KeyStore::from_seed() one for signatures that still verify after a restart
+    pub keystore: KeyStore,
     pub logger: Arc<Mutex<Logger>>,
     pub persister: Arc<Mutex<Persister>>,
     state: Option<Arc<RwLock<State>>>,
     pub action_channel: SyncSender<ActionWrapper>,
     pub observer_channel: SyncSender<Observer>,
+    /// publish public entries to the network as soon as they are committed
+    /// if false they are queued until Action::FlushPublishes
+    pub auto_publish: bool,
+    /// maximum number of links with the same tag on a single base, None for unlimited
+    pub max_links_per_base: Option<usize>,
+    /// order in which Action::FlushPublishes publishes the queued entries
+    pub publish_order: PublishOrder,
+    /// bound on the rate entries are sent to the network at, None for unlimited
+    /// entries over the limit are queued until a later commit or Action::FlushPublishes
+    pub publish_rate_limit: Option<PublishRateLimit>,
+    /// bound on the rate entries are committed to the source chain at, None for unlimited
+    /// commits over the limit fail with ChainRateLimited
+    pub chain_rate_limit: Option<ChainRateLimit>,
+    /// whether entries failing validation are rejected, committed with a warning, or
+    /// committed without validation
+    pub validation_level: ValidationLevel,
+    /// where time dependent behavior, e.g. the expiry of entries, reads the time from
+    pub clock: Arc<Clock>,
+    /// which peers the network asks for an entry
+    pub routing_strategy: Arc<RoutingStrategy>,
+    /// bound on the gets in flight on the network at the same time, shared by the clones
+    /// of the context; gets over it wait for one in flight to return
+    pub network_get_limit: NetworkGetLimit,
+    /// which actions the event loop reduces ahead of the others queued with them
+    pub scheduler_priorities: SchedulerPriorities,
+    /// where the DHT stores the entries of the routed types, instead of its content storage
+    pub storage_routes: StorageRoutes,
+    /// key the content of the instance and the entries routed with Context::route_storage()
+    /// are encrypted at rest with, None to store them in plaintext
+    /// @see Context::set_storage_key()
+    storage_key: Option<StorageKey>,
+    /// how long an address the network didn't have isn't asked for again, None to always ask
+    pub negative_cache_ttl: Option<Duration>,
+    /// bytes of WASM memory a zome call may use before it is aborted, None for unlimited
+    pub max_call_memory: Option<usize>,
+    /// where zome calls get the seed of their random numbers from
+    pub seed_source: Arc<SeedSource>,
+    /// the zome modules loaded so far, shared by the clones of the context
+    pub zome_modules: ZomeModules,
+    /// runs the post commit hooks in the order of the commits, shared by the clones
+    pub post_commit_worker: PostCommitWorker,
+    /// reject commits and links with ReadOnlyInstance, e.g. for read replicas sharing the
+    /// storage of another instance
+    pub read_only: bool,
+    /// log actions the DHT store is expected to reduce but has no reducer for,
+    /// instead of silently ignoring them
+    pub strict_reducers: bool,
+    /// whether the state lock is used anyway once a panic poisoned it, or panics as well
+    pub poison_policy: PoisonPolicy,
+    /// id of the zome call this context was handed to, lines it logs are tagged with it
+    /// None outside of zome calls
+    pub call_id: Option<String>,
+    /// how addresses are rendered for external systems, e.g. in exports
+    /// @see Context::set_address_formatter()
+    address_formatter: AddressFormatter,
+    /// how the content of app entries is normalized before they are committed
+    /// @see Context::set_content_canonicalizer()
+    content_canonicalizer: ContentCanonicalizer,
+    /// the address derivers the DNA can name, by name
+    /// @see Context::set_address_deriver()
+    address_derivers: HashMap<String, AddressDeriver>,
 }
 
 impl Context {
@@ -38,11 +142,38 @@ impl Context {
         let (tx_observer, _) = sync_channel(Self::default_channel_buffer_size());
         Context {
             agent,
+            keystore: KeyStore::new(),
             logger,
             persister,
             state: None,
             action_channel: tx_action,
             observer_channel: tx_observer,
+            auto_publish: true,
+            max_links_per_base: None,
+            publish_order: PublishOrder::default(),
+            publish_rate_limit: None,
+            chain_rate_limit: None,
+            validation_level: ValidationLevel::default(),
+            clock: Arc::new(SystemClock {}),
+            routing_strategy: Arc::new(AnyPeer {}),
+            network_get_limit: NetworkGetLimit::default(),
+            scheduler_priorities: SchedulerPriorities::default(),
+            storage_routes: StorageRoutes::new(),
+            storage_key: None,
+            negative_cache_ttl: None,
+            max_call_memory: None,
+            seed_source: Arc::new(CallIdSeed {}),
+            zome_modules: ZomeModules::new(),
+            post_commit_worker: PostCommitWorker::new(),
+            read_only: false,
+            strict_reducers: false,
+            poison_policy: PoisonPolicy::default(),
+            call_id: None,
+            address_formatter: Arc::new(|address: &Address| address.to_string()),
+            content_canonicalizer: Arc::new(|_: &EntryType, content: &Content| {
+                sort_json_keys(content)
+            }),
+            address_derivers: HashMap::new(),
         }
     }
 
@@ -55,17 +186,184 @@ impl Context {
     ) -> Context {
         Context {
             agent,
+            keystore: KeyStore::new(),
             logger,
             persister,
             state: None,
             action_channel,
             observer_channel,
+            auto_publish: true,
+            max_links_per_base: None,
+            publish_order: PublishOrder::default(),
+            publish_rate_limit: None,
+            chain_rate_limit: None,
+            validation_level: ValidationLevel::default(),
+            clock: Arc::new(SystemClock {}),
+            routing_strategy: Arc::new(AnyPeer {}),
+            network_get_limit: NetworkGetLimit::default(),
+            scheduler_priorities: SchedulerPriorities::default(),
+            storage_routes: StorageRoutes::new(),
+            storage_key: None,
+            negative_cache_ttl: None,
+            max_call_memory: None,
+            seed_source: Arc::new(CallIdSeed {}),
+            zome_modules: ZomeModules::new(),
+            post_commit_worker: PostCommitWorker::new(),
+            read_only: false,
+            strict_reducers: false,
+            poison_policy: PoisonPolicy::default(),
+            call_id: None,
+            address_formatter: Arc::new(|address: &Address| address.to_string()),
+            content_canonicalizer: Arc::new(|_: &EntryType, content: &Content| {
+                sort_json_keys(content)
+            }),
+            address_derivers: HashMap::new(),
         }
     }
+
+    /// render addresses given to external systems with the formatter, e.g. as URLs
+    /// addresses are stored and used internally in their canonical encoding either way
+    pub fn set_address_formatter<F>(&mut self, formatter: F)
+    where
+        F: 'static + Fn(&Address) -> String + Send + Sync,
+    {
+        self.address_formatter = Arc::new(formatter);
+    }
+
+    /// the address as it should be given to external systems
+    /// the canonical encoding unless a formatter was set
+    pub fn format_address(&self, address: &Address) -> String {
+        (self.address_formatter)(address)
+    }
+
+    /// encrypts the content at rest with the key from now on, the primary content storage
+    /// when the instance is initialized and every storage routed afterwards
+    /// the meta data (links, CRUD status) stays in plaintext, a read replica serves the
+    /// storage shared with its writer as it is
+    /// fails with StorageAlreadyRouted if a storage was routed already, as it would stay in
+    /// plaintext
+    pub fn set_storage_key(&mut self, key: StorageKey) -> Result<(), HolochainError> {
+        if !self.storage_routes.is_empty() {
+            return Err(HolochainError::StorageAlreadyRouted);
+        }
+        self.storage_key = Some(key);
+        Ok(())
+    }
+
+    /// the key the content is encrypted at rest with, None if it is stored in plaintext
+    pub fn storage_key(&self) -> Option<StorageKey> {
+        self.storage_key
+    }
+
+    /// stores the entries of the type in the storage from now on, encrypted with the storage
+    /// key if there is one, @see EncryptedStorage
+    pub fn route_storage<CAS>(&mut self, entry_type: &EntryType, storage: CAS)
+    where
+        CAS: 'static + ContentAddressableStorage,
+    {
+        match self.storage_key {
+            Some(key) => self
+                .storage_routes
+                .route(entry_type, EncryptedStorage::new(storage, key)),
+            None => self.storage_routes.route(entry_type, storage),
+        }
+    }
+
+    /// commit app entries with the content the canonicalizer makes of what the zome passed,
+    /// e.g. to reproduce the addresses a legacy system gave its data
+    /// instances canonicalizing differently address the same entries differently
+    /// replaces the default canonicalizer, call sort_json_keys() to keep sorting JSON keys
+    pub fn set_content_canonicalizer<F>(&mut self, canonicalizer: F)
+    where
+        F: 'static + Fn(&EntryType, &Content) -> Content + Send + Sync,
+    {
+        self.content_canonicalizer = Arc::new(canonicalizer);
+    }
+
+    /// the content an app entry of the type is committed with
+    /// unless a canonicalizer was set, the content as passed with the keys of JSON objects
+    /// sorted, @see sort_json_keys()
+    pub fn canonicalize_content(&self, entry_type: &EntryType, content: &Content) -> Content {
+        (self.content_canonicalizer)(entry_type, content)
+    }
+
+    /// derive the addresses of the entries of types the DNA declares with
+    /// AddressDerivation::Custom(name) from the key the deriver makes of their content
+    pub fn set_address_deriver<F>(&mut self, name: &str, deriver: F)
+    where
+        F: 'static + Fn(&Content) -> Result<String, HolochainError> + Send + Sync,
+    {
+        self.address_derivers
+            .insert(name.to_string(), Arc::new(deriver));
+    }
+
+    /// the key the deriver registered under the name makes of the content
+    /// fails with AddressDerivationFailed if there is no such deriver
+    pub fn derive_address_key(
+        &self,
+        name: &str,
+        content: &Content,
+    ) -> Result<String, HolochainError> {
+        let deriver = self.address_derivers.get(name).ok_or_else(|| {
+            HolochainError::AddressDerivationFailed(format!("no address deriver named {}", name))
+        })?;
+        deriver(content)
+    }
+
+    /// the key the DNA of the state derives the address of the entry from, None for entries
+    /// addressed by their whole content
+    /// fails with AddressDerivationFailed if there is no key to derive it from
+    pub fn address_key(&self, entry: &Entry) -> Result<Option<String>, HolochainError> {
+        if !entry.entry_type().to_owned().is_app() {
+            return Ok(None);
+        }
+        let address_derivation = self
+            .state()
+            .and_then(|state| state.nucleus().dna())
+            .and_then(|dna| {
+                dna.get_entry_type_def(&entry.entry_type().to_string())
+                    .cloned()
+            })
+            .map(|entry_type_def| entry_type_def.address_derivation)
+            .unwrap_or_default();
+        match address_derivation {
+            AddressDerivation::WholeContent => Ok(None),
+            AddressDerivation::ByField(field) => indexed_field_values(entry, &[field.clone()])
+                .pop()
+                .map(|(_, value)| Some(value))
+                .ok_or_else(|| {
+                    HolochainError::AddressDerivationFailed(format!("the entry has no {}", field))
+                }),
+            AddressDerivation::Custom(name) => {
+                self.derive_address_key(&name, entry.value()).map(Some)
+            }
+        }
+    }
+
+    /// fails with AddressDerivationFailed unless the entry is addressed by the key derived
+    /// from its content, or by its whole content if its type derives no key
+    /// entries from peers, imports and commits are checked, so no entry takes an address it
+    /// doesn't derive
+    pub fn verify_address_key(&self, entry: &Entry) -> Result<(), HolochainError> {
+        let derived = self.address_key(entry)?;
+        if derived.as_ref() == entry.address_key() {
+            Ok(())
+        } else {
+            Err(HolochainError::AddressDerivationFailed(format!(
+                "the entry is addressed by {:?} instead of {:?}",
+                entry.address_key(),
+                derived
+            )))
+        }
+    }
+
     // helper function to make it easier to call the logger
     pub fn log(&self, msg: &str) -> Result<(), HolochainError> {
         let mut logger = self.logger.lock().or(Err(HolochainError::LoggingError))?;
-        logger.log(msg.to_string());
+        match self.call_id {
+            Some(ref call_id) => logger.log_for_call(call_id, msg.to_string()),
+            None => logger.log(msg.to_string()),
+        }
         Ok(())
     }
 
@@ -76,7 +374,7 @@ impl Context {
     pub fn state(&self) -> Option<RwLockReadGuard<State>> {
         match self.state {
             None => None,
-            Some(ref s) => Some(s.read().unwrap()),
+            Some(ref s) => Some(self.poison_policy.unpoison(s.read(), "state RwLock")),
         }
     }
 }
@@ -86,6 +384,8 @@ mod tests {
     extern crate holochain_agent;
     extern crate test_utils;
     use super::*;
+    use holochain_cas_implementations::cas::{encrypted::STORAGE_KEY_LEN, memory::MemoryStorage};
+    use holochain_core_types::{cas::content::AddressableContent, entry::test_entry};
     use instance::tests::test_logger;
     use persister::SimplePersister;
     use state::State;
@@ -96,6 +396,65 @@ mod tests {
         assert_eq!(Context::default_channel_buffer_size(), 100);
     }
 
+    #[test]
+    fn address_formatter_test() {
+        let mut context = Context::new(
+            holochain_agent::Agent::from("Terence".to_string()),
+            test_logger(),
+            Arc::new(Mutex::new(SimplePersister::new())),
+        );
+        let address = Address::from("QmAddress".to_string());
+        assert_eq!("QmAddress", context.format_address(&address));
+
+        context.set_address_formatter(|address| format!("hc://{}", address));
+        assert_eq!("hc://QmAddress", context.format_address(&address));
+        assert_eq!("hc://QmAddress", context.clone().format_address(&address));
+    }
+
+    #[test]
+    /// entries routed with a storage key are stored sealed and fetched in plaintext
+    fn routed_storage_is_encrypted_with_storage_key() {
+        let mut context = Context::new(
+            holochain_agent::Agent::from("Terence".to_string()),
+            test_logger(),
+            Arc::new(Mutex::new(SimplePersister::new())),
+        );
+        context
+            .set_storage_key(StorageKey::new([7; STORAGE_KEY_LEN]))
+            .unwrap();
+        let storage = MemoryStorage::new().unwrap();
+        context.route_storage(test_entry().entry_type(), storage.clone());
+
+        let routed = context
+            .storage_routes
+            .storage_for(test_entry().entry_type())
+            .unwrap();
+        routed.add(&test_entry()).unwrap();
+        assert_eq!(
+            Ok(Some(test_entry())),
+            context.storage_routes.fetch(&test_entry().address())
+        );
+        let stored: Option<Content> = storage.fetch(&test_entry().address()).unwrap();
+        assert!(!stored.unwrap().contains(&test_entry().value().to_string()));
+    }
+
+    #[test]
+    /// a key set after a storage was routed would leave that storage in plaintext
+    fn storage_key_has_to_be_set_before_routing() {
+        let mut context = Context::new(
+            holochain_agent::Agent::from("Terence".to_string()),
+            test_logger(),
+            Arc::new(Mutex::new(SimplePersister::new())),
+        );
+        context.route_storage(test_entry().entry_type(), MemoryStorage::new().unwrap());
+
+        assert_eq!(
+            Err(HolochainError::StorageAlreadyRouted),
+            context.set_storage_key(StorageKey::new([7; STORAGE_KEY_LEN])),
+        );
+        assert_eq!(None, context.storage_key());
+    }
+
     #[test]
     fn test_state() {
         let mut context = Context::new(
@@ -0,0 +1,274 @@
+//! Bounded cache of the entries fetched from the network.
+//!
+//! Unpinned entries are evicted least recently used first once there are more than the
+//! capacity. Pinned entries are never evicted; they don't count toward the capacity but
+//! toward a separate pin budget, so pinning can't grow the cache without bounds.
+//! Addresses the network didn't have can be remembered for a while too, so repeated gets of
+//! a missing entry don't all go to the network, @see Context::negative_cache_ttl. As many of
+//! them as entries are remembered, the ones missed longest ago are forgotten first.
+
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    entry::Entry,
+    error::HolochainError,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::Duration,
+};
+
+/// number of unpinned entries the cache of a new DHT store holds
+pub const DEFAULT_CACHE_CAPACITY: usize = 1000;
+/// number of addresses that can be pinned in the cache of a new DHT store
+pub const DEFAULT_PIN_BUDGET: usize = 100;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntryCache {
+    capacity: usize,
+    pin_budget: usize,
+    entries: HashMap<Address, Entry>,
+    // cached addresses, least recently used first
+    recency: VecDeque<Address>,
+    pinned: HashSet<Address>,
+    // addresses the network didn't have and when it was asked, as read from the context's clock
+    misses: HashMap<Address, Duration>,
+}
+
+impl EntryCache {
+    pub fn new(capacity: usize, pin_budget: usize) -> Self {
+        EntryCache {
+            capacity,
+            pin_budget,
+            entries: HashMap::new(),
+            recency: VecDeque::new(),
+            pinned: HashSet::new(),
+            misses: HashMap::new(),
+        }
+    }
+
+    /// caches the entry as the most recently used one, evicting unpinned entries if needed
+    pub fn insert(&mut self, entry: Entry) {
+        let address = entry.address();
+        self.misses.remove(&address);
+        self.entries.insert(address.clone(), entry);
+        self.touch(&address);
+        self.evict();
+    }
+
+    /// marks a cached entry as the most recently used one
+    pub fn touch(&mut self, address: &Address) {
+        if !self.entries.contains_key(address) {
+            return;
+        }
+        self.recency.retain(|cached| cached != address);
+        self.recency.push_back(address.clone());
+    }
+
+    /// the cached entry, without changing how recently it was used
+    pub fn peek(&self, address: &Address) -> Option<Entry> {
+        self.entries.get(address).cloned()
+    }
+
+    pub fn contains(&self, address: &Address) -> bool {
+        self.entries.contains_key(address)
+    }
+
+    /// exempts the address from eviction, whether it is cached yet or not
+    /// fails with PinBudgetExceeded if as many addresses as the budget are already pinned
+    pub fn pin(&mut self, address: &Address) -> Result<(), HolochainError> {
+        if self.pinned.contains(address) {
+            return Ok(());
+        }
+        if self.pinned.len() >= self.pin_budget {
+            return Err(HolochainError::PinBudgetExceeded);
+        }
+        self.pinned.insert(address.clone());
+        Ok(())
+    }
+
+    /// makes the address evictable again, which may evict the least recently used entries
+    pub fn unpin(&mut self, address: &Address) {
+        self.pinned.remove(address);
+        self.evict();
+    }
+
+    pub fn is_pinned(&self, address: &Address) -> bool {
+        self.pinned.contains(address)
+    }
+
+    /// the cached addresses, sorted
+    pub fn addresses(&self) -> Vec<Address> {
+        let mut addresses: Vec<Address> = self.entries.keys().cloned().collect();
+        addresses.sort();
+        addresses
+    }
+
+    /// the pinned addresses, sorted
+    pub fn pinned(&self) -> Vec<Address> {
+        let mut pinned: Vec<Address> = self.pinned.iter().cloned().collect();
+        pinned.sort();
+        pinned
+    }
+
+    /// remembers that the network didn't have the address at the given time, forgetting the
+    /// oldest miss if as many as the capacity are remembered already
+    pub fn insert_miss(&mut self, address: &Address, now: Duration) {
+        self.misses.insert(address.clone(), now);
+        while self.misses.len() > self.capacity {
+            let oldest = self
+                .misses
+                .iter()
+                .min_by_key(|&(_, missed)| *missed)
+                .map(|(address, _)| address.clone())
+                .expect("there are more misses than the capacity");
+            self.misses.remove(&oldest);
+        }
+    }
+
+    /// true if the network didn't have the address less than ttl before the given time
+    pub fn is_known_missing(&self, address: &Address, ttl: Duration, now: Duration) -> bool {
+        self.misses
+            .get(address)
+            .map(|missed| {
+                now.checked_sub(*missed)
+                    .map_or(true, |elapsed| elapsed < ttl)
+            })
+            .unwrap_or(false)
+    }
+
+    /// the addresses the network didn't have and when it was asked, sorted by address
+    /// expired misses are listed until the address is asked for again
+    pub fn misses(&self) -> Vec<(Address, Duration)> {
+        let mut misses: Vec<(Address, Duration)> = self
+            .misses
+            .iter()
+            .map(|(address, missed)| (address.clone(), *missed))
+            .collect();
+        misses.sort_by(|a, b| a.0.cmp(&b.0));
+        misses
+    }
+
+    /// forgets the misses, so the addresses are asked for on the network again
+    pub fn clear_misses(&mut self) {
+        self.misses.clear();
+    }
+
+    fn evict(&mut self) {
+        let mut unpinned = self
+            .recency
+            .iter()
+            .filter(|address| !self.pinned.contains(*address))
+            .count();
+        while unpinned > self.capacity {
+            let position = self
+                .recency
+                .iter()
+                .position(|address| !self.pinned.contains(address))
+                .expect("there are more unpinned entries than the capacity");
+            if let Some(address) = self.recency.remove(position) {
+                self.entries.remove(&address);
+            }
+            unpinned -= 1;
+        }
+    }
+}
+
+impl Default for EntryCache {
+    fn default() -> Self {
+        EntryCache::new(DEFAULT_CACHE_CAPACITY, DEFAULT_PIN_BUDGET)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core_types::entry_type::test_entry_type;
+
+    /// distinct dummy entries
+    fn test_cache_entry(n: usize) -> Entry {
+        Entry::new(&test_entry_type(), &format!("cached entry {}", n))
+    }
+
+    #[test]
+    /// under cache pressure the pinned entry survives while the unpinned one is dropped
+    fn pinned_entry_survives_eviction() {
+        let mut cache = EntryCache::new(2, 1);
+        cache.insert(test_cache_entry(0));
+        cache.insert(test_cache_entry(1));
+        cache
+            .pin(&test_cache_entry(0).address())
+            .expect("pin budget should be left");
+
+        cache.insert(test_cache_entry(2));
+        cache.insert(test_cache_entry(3));
+
+        assert!(cache.contains(&test_cache_entry(0).address()));
+        assert!(!cache.contains(&test_cache_entry(1).address()));
+        assert!(cache.contains(&test_cache_entry(2).address()));
+        assert!(cache.contains(&test_cache_entry(3).address()));
+
+        // once unpinned it is the least recently used entry
+        cache.unpin(&test_cache_entry(0).address());
+        assert!(!cache.contains(&test_cache_entry(0).address()));
+        assert!(cache.contains(&test_cache_entry(2).address()));
+    }
+
+    #[test]
+    /// pinning beyond the budget fails, pinning an address twice doesn't use the budget
+    fn pin_budget_test() {
+        let mut cache = EntryCache::new(2, 1);
+        let first = test_cache_entry(0).address();
+        let second = test_cache_entry(1).address();
+        assert_eq!(Ok(()), cache.pin(&first));
+        assert_eq!(Ok(()), cache.pin(&first));
+        assert_eq!(Err(HolochainError::PinBudgetExceeded), cache.pin(&second));
+        assert_eq!(vec![first.clone()], cache.pinned());
+
+        cache.unpin(&first);
+        assert_eq!(Ok(()), cache.pin(&second));
+    }
+
+    #[test]
+    /// a miss is remembered until the entry is cached or the misses are cleared
+    fn misses_test() {
+        let mut cache = EntryCache::new(2, 1);
+        let address = test_cache_entry(0).address();
+        let ttl = Duration::from_secs(60);
+        let now = Duration::from_secs(1000);
+        assert!(!cache.is_known_missing(&address, ttl, now));
+
+        cache.insert_miss(&address, now);
+        assert!(cache.is_known_missing(&address, ttl, now + Duration::from_secs(59)));
+        assert!(!cache.is_known_missing(&address, ttl, now + ttl));
+        assert_eq!(vec![(address.clone(), now)], cache.misses());
+
+        cache.insert(test_cache_entry(0));
+        assert!(!cache.is_known_missing(&address, ttl, now));
+
+        cache.insert_miss(&test_cache_entry(1).address(), now);
+        cache.clear_misses();
+        assert!(cache.misses().is_empty());
+    }
+
+    #[test]
+    /// no more misses than the capacity are remembered, the oldest ones are forgotten first
+    fn misses_are_bounded() {
+        let mut cache = EntryCache::new(2, 1);
+        for n in 0..3 {
+            cache.insert_miss(
+                &test_cache_entry(n).address(),
+                Duration::from_secs(n as u64),
+            );
+        }
+        let mut remembered = vec![test_cache_entry(1).address(), test_cache_entry(2).address()];
+        remembered.sort();
+        assert_eq!(
+            remembered,
+            cache
+                .misses()
+                .into_iter()
+                .map(|(address, _)| address)
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,316 @@
+//! Snapshots of what the DHT of an instance holds, to warm start new peers with.
+//!
+//! A peer joining the network would otherwise fetch every entry one get at a time. A trusted
+//! peer exports the public app entries and the links it holds, never those with an access
+//! control list, which the peers loading them couldn't enforce, each signed with the Ed25519
+//! key pair of its agent, and the new peer loads them in bulk after verifying every address
+//! and every signature against the public key of the peer it trusts.
+//!
+//! Stores too large to export in one go are exported as a stream of chunks instead, each
+//! verified and loaded on its own, @see DhtExportStream.
+
+use agent::keys::{verify_signature, KeyPair, PublicKey};
+use dht::dht_store::DhtStore;
+use holochain_agent::Agent;
+use holochain_cas_implementations::{cas::memory::MemoryStorage, eav::memory::EavMemoryStorage};
+use holochain_core_types::{
+    cas::{
+        content::{Address, AddressableContent, Content},
+        storage::ContentAddressableStorage,
+    },
+    chain_header::ChainHeader,
+    entry::Entry,
+    entry_type::EntryType,
+    error::HolochainError,
+    json::{FromJson, ToJson},
+    signature::Signature,
+};
+use holochain_dna::Dna;
+use serde_json;
+use std::vec;
+
+/// an entry of the snapshot with the exporting agent's signature of its address
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportedEntry {
+    pub address: Address,
+    pub entry: Entry,
+    pub signature: Signature,
+}
+
+/// The entries a peer shares with the network, in address order
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DhtExport {
+    exporter: String,
+    entries: Vec<ExportedEntry>,
+}
+
+/// What loading a snapshot did
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BootstrapReport {
+    /// number of entries stored
+    pub loaded: usize,
+    /// number of entries that were held already
+    pub already_held: usize,
+}
+
+/// the entry stored at the address as the agent exports it, signed with its key pair
+/// None for chain headers, system entries, private entries and entries with an access control
+/// list, which aren't exported
+fn exported_entry(
+    key_pair: &KeyPair,
+    dna: &Dna,
+    dht: &DhtStore<MemoryStorage, EavMemoryStorage>,
+    address: Address,
+) -> Result<Option<ExportedEntry>, HolochainError> {
+    if !dht.readers(&address)?.is_empty() {
+        return Ok(None);
+    }
+    let content: Content = match dht.content_storage().fetch(&address)? {
+        Some(content) => content,
+        None => return Ok(None),
+    };
+    if ChainHeader::from_json_str(&content).is_ok() {
+        return Ok(None);
+    }
+    let entry = match Entry::from_json(&content) {
+        Ok(entry) => entry,
+        Err(_) => return Ok(None),
+    };
+    let is_shared = match entry.entry_type() {
+        EntryType::Link => true,
+        EntryType::App(name) => dna
+            .get_entry_type_def(name)
+            .map(|def| def.sharing.clone().can_publish())
+            .unwrap_or(false),
+        _ => false,
+    };
+    if !is_shared {
+        return Ok(None);
+    }
+    Ok(Some(ExportedEntry {
+        signature: key_pair.sign(address.to_string().as_bytes()),
+        address,
+        entry,
+    }))
+}
+
+/// checks that every entry has the address it is listed under and that every address
+/// was signed with the key pair of the public key
+fn verify_entries(public_key: &PublicKey, entries: &[ExportedEntry]) -> Result<(), HolochainError> {
+    for exported in entries.iter() {
+        let invalid = |reason: &str| {
+            Err(HolochainError::InvalidDhtExport(format!(
+                "entry {} {}",
+                exported.address, reason
+            )))
+        };
+        if exported.entry.address() != exported.address {
+            return invalid("doesn't match its address");
+        }
+        if !verify_signature(
+            public_key,
+            exported.address.to_string().as_bytes(),
+            &exported.signature,
+        ) {
+            return invalid("is not signed by the exporter");
+        }
+    }
+    Ok(())
+}
+
+impl DhtExport {
+    /// exports the links and the app entries of types the DNA shares that the DHT holds,
+    /// signing every address with the key pair of the agent
+    /// chain headers, system entries, private entries and entries with an access control list
+    /// are left out
+    pub fn new(
+        agent: &Agent,
+        key_pair: &KeyPair,
+        dna: &Dna,
+        dht: &DhtStore<MemoryStorage, EavMemoryStorage>,
+    ) -> Result<Self, HolochainError> {
+        let mut entries = Vec::new();
+        for address in dht.content_storage().addresses()? {
+            if let Some(exported) = exported_entry(key_pair, dna, dht, address)? {
+                entries.push(exported);
+            }
+        }
+        Ok(DhtExport {
+            exporter: agent.to_string(),
+            entries,
+        })
+    }
+
+    /// the agent that exported and signed the snapshot
+    pub fn exporter(&self) -> Agent {
+        Agent::from(self.exporter.clone())
+    }
+
+    pub fn entries(&self) -> &[ExportedEntry] {
+        &self.entries
+    }
+
+    /// checks that every entry has the address it is listed under and that every address
+    /// was signed with the key pair of the public key, the one of the exporter if the
+    /// verifier trusts it
+    /// fails with InvalidDhtExport saying which entry doesn't match
+    pub fn verify(&self, public_key: &PublicKey) -> Result<(), HolochainError> {
+        verify_entries(public_key, &self.entries)
+    }
+}
+
+/// default number of entries in a chunk of a DhtExportStream
+pub const DEFAULT_EXPORT_CHUNK_SIZE: usize = 100;
+
+/// Part of a streamed export, with the entries of up to a chunk size of addresses
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportChunk {
+    exporter: String,
+    entries: Vec<ExportedEntry>,
+}
+
+impl ExportChunk {
+    /// the agent that exported and signed the chunk
+    pub fn exporter(&self) -> Agent {
+        Agent::from(self.exporter.clone())
+    }
+
+    pub fn entries(&self) -> &[ExportedEntry] {
+        &self.entries
+    }
+
+    /// like DhtExport::verify() for the entries of the chunk
+    pub fn verify(&self, public_key: &PublicKey) -> Result<(), HolochainError> {
+        verify_entries(public_key, &self.entries)
+    }
+}
+
+/// The entries a DhtExport would hold, fetched from the storage and signed one chunk at a
+/// time as the stream is read
+/// only the addresses are listed up front, entries stored afterwards aren't exported, and
+/// entries restricted by the time their chunk is read are left out
+pub struct DhtExportStream {
+    agent: Agent,
+    key_pair: KeyPair,
+    dna: Dna,
+    dht: DhtStore<MemoryStorage, EavMemoryStorage>,
+    addresses: vec::IntoIter<Address>,
+    chunk_size: usize,
+}
+
+impl DhtExportStream {
+    /// streams the entries DhtExport::new() would export in chunks of the entries of
+    /// chunk_size addresses, a chunk_size of 0 is taken as 1
+    pub fn new(
+        agent: &Agent,
+        key_pair: &KeyPair,
+        dna: &Dna,
+        dht: &DhtStore<MemoryStorage, EavMemoryStorage>,
+        chunk_size: usize,
+    ) -> Result<Self, HolochainError> {
+        Ok(DhtExportStream {
+            agent: agent.clone(),
+            key_pair: key_pair.clone(),
+            dna: dna.clone(),
+            dht: dht.clone(),
+            addresses: dht.content_storage().addresses()?.into_iter(),
+            chunk_size: chunk_size.max(1),
+        })
+    }
+}
+
+impl Iterator for DhtExportStream {
+    type Item = Result<ExportChunk, HolochainError>;
+
+    /// the next chunk, None once every address was exported
+    /// chunks can be empty if none of their addresses hold exported entries
+    fn next(&mut self) -> Option<Self::Item> {
+        let addresses: Vec<Address> = self.addresses.by_ref().take(self.chunk_size).collect();
+        if addresses.is_empty() {
+            return None;
+        }
+        let mut entries = Vec::new();
+        for address in addresses {
+            match exported_entry(&self.key_pair, &self.dna, &self.dht, address) {
+                Ok(Some(exported)) => entries.push(exported),
+                Ok(None) => (),
+                Err(error) => return Some(Err(error)),
+            }
+        }
+        Some(Ok(ExportChunk {
+            exporter: self.agent.to_string(),
+            entries,
+        }))
+    }
+}
+
+impl ToJson for DhtExport {
+    fn to_json(&self) -> Result<String, HolochainError> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+impl FromJson for DhtExport {
+    fn from_json(s: &str) -> Result<Self, HolochainError> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+impl ToJson for ExportChunk {
+    fn to_json(&self) -> Result<String, HolochainError> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+impl FromJson for ExportChunk {
+    fn from_json(s: &str) -> Result<Self, HolochainError> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core_types::entry::{test_entry, test_entry_b};
+
+    /// a snapshot of the entries signed with the key pair
+    fn signed_export(key_pair: &KeyPair, entries: Vec<Entry>) -> DhtExport {
+        DhtExport {
+            exporter: "alex".to_string(),
+            entries: entries
+                .into_iter()
+                .map(|entry| ExportedEntry {
+                    address: entry.address(),
+                    signature: key_pair.sign(entry.address().to_string().as_bytes()),
+                    entry,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    /// a tampered snapshot re-signed by anyone but the exporter doesn't verify with the
+    /// exporter's public key, whoever it claims to be exported by
+    fn verify_refuses_resigned_snapshots() {
+        let alex = KeyPair::generate().unwrap();
+        let mallory = KeyPair::generate().unwrap();
+        let snapshot = signed_export(&alex, vec![test_entry()]);
+        assert_eq!(Ok(()), snapshot.verify(&alex.public_key()));
+
+        let resigned = signed_export(&mallory, vec![test_entry(), test_entry_b()]);
+        assert_eq!(snapshot.exporter(), resigned.exporter());
+        assert_eq!(Ok(()), resigned.verify(&mallory.public_key()));
+        assert_eq!(
+            Err(HolochainError::InvalidDhtExport(format!(
+                "entry {} is not signed by the exporter",
+                test_entry().address()
+            ))),
+            resigned.verify(&alex.public_key())
+        );
+
+        // signatures can't be moved to other entries either
+        let mut swapped = signed_export(&alex, vec![test_entry(), test_entry_b()]);
+        swapped.entries[1].signature = swapped.entries[0].signature.clone();
+        assert!(swapped.verify(&alex.public_key()).is_err());
+    }
+}
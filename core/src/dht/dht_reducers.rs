@@ -1,14 +1,30 @@
 //! all DHT reducers
 
-use action::{Action, ActionWrapper};
+use action::{Action, ActionFilter, ActionWrapper};
+use agent::{
+    actions::{commit::CommitOptions, transaction::TransactionOp},
+    state::is_own_entry,
+};
 use context::Context;
-use dht::dht_store::DhtStore;
+use dht::{
+    dht_store::{dependency_order, DhtStore, PublishOrder},
+    network::AclGrant,
+    network_worker::{entry_from_peer, NetworkRequest},
+    staging::{StagedEavStorage, StagedStorage},
+};
 use holochain_core_types::{
-    cas::{content::AddressableContent, storage::ContentAddressableStorage},
+    cas::{
+        content::{Address, AddressableContent},
+        storage::ContentAddressableStorage,
+    },
+    crud_status::CrudStatus,
     eav::EntityAttributeValueStorage,
-    entry::Entry,
+    entry::{Entry, ToEntry},
+    entry_type::EntryType,
+    error::HolochainError,
+    links_entry::{LinkActionKind, LinkEntry},
 };
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 // A function that might return a mutated DhtStore
 type DhtReducer<CAS, EAVS> =
@@ -28,6 +44,14 @@ where
     // Get reducer
     let maybe_reducer = resolve_reducer(action_wrapper);
     if maybe_reducer.is_none() {
+        if context.strict_reducers && dht_actions().matches(action_wrapper.action()) {
+            context
+                .log(&format!(
+                    "DHT store has no reducer for action: {:?}",
+                    action_wrapper.action()
+                ))
+                .expect("Logger should work");
+        }
         return old_store;
     }
     let reducer = maybe_reducer.unwrap();
@@ -39,6 +63,29 @@ where
     }
 }
 
+/// the actions the DHT store is expected to reduce
+/// with Context::strict_reducers, those without a reducer are logged instead of ignored
+/// GetLinks has none yet: links are read straight from the store, @see DhtStore::link_tags()
+pub fn dht_actions() -> ActionFilter {
+    ActionFilter::COMMIT
+        | ActionFilter::GET_ENTRY
+        | ActionFilter::GET_ENTRIES_BY_TYPE
+        | ActionFilter::FLUSH_PUBLISHES
+        | ActionFilter::RETURN_PUBLISH_RESULT
+        | ActionFilter::RETURN_GET_RESULT
+        | ActionFilter::ROLL_BACK_COMMIT
+        | ActionFilter::ADD_LINK
+        | ActionFilter::REMOVE_LINKS
+        | ActionFilter::ATTACH_METADATA
+        | ActionFilter::GET_LINKS
+        | ActionFilter::PIN_ENTRY
+        | ActionFilter::EXPIRE_ENTRIES
+        | ActionFilter::TRANSACTION
+        | ActionFilter::CLEAR_NEGATIVE_CACHE
+        | ActionFilter::LOAD_DHT_SNAPSHOT
+        | ActionFilter::IMPORT_LINKS
+}
+
 /// Maps incoming action to the correct reducer
 fn resolve_reducer<CAS, EAVS>(action_wrapper: &ActionWrapper) -> Option<DhtReducer<CAS, EAVS>>
 where
@@ -47,9 +94,23 @@ where
 {
     match action_wrapper.action() {
         Action::Commit(_) => Some(reduce_commit_entry),
+        Action::CommitWithOptions(_, _) => Some(reduce_commit_entry),
         Action::GetEntry(_) => Some(reduce_get_entry_from_network),
+        Action::GetEntriesByType(_) => Some(reduce_get_entries_by_type),
+        Action::FlushPublishes => Some(reduce_flush_publishes),
+        Action::ReturnPublishResult(_, _) => Some(reduce_return_publish_result),
+        Action::ReturnGetResult(_, _) => Some(reduce_return_get_result),
+        Action::RollBackCommit(_) => Some(reduce_roll_back_commit),
         Action::AddLink(_) => Some(reduce_add_link),
-        Action::GetLinks(_) => Some(reduce_get_links),
+        Action::RemoveLinks(_) => Some(reduce_remove_links),
+        Action::AttachMetadata(_) => Some(reduce_attach_metadata),
+        Action::Transaction(_) => Some(reduce_transaction),
+        Action::PinEntry(_) => Some(reduce_pin_entry),
+        Action::UnpinEntry(_) => Some(reduce_unpin_entry),
+        Action::ClearNegativeCache => Some(reduce_clear_negative_cache),
+        Action::LoadDhtSnapshot(_) => Some(reduce_load_dht_snapshot),
+        Action::ImportLinks(_, _) => Some(reduce_import_links),
+        Action::ExpireEntries => Some(reduce_expire_entries),
         _ => None,
     }
 }
@@ -60,23 +121,32 @@ pub(crate) fn commit_sys_entry<CAS, EAVS>(
     old_store: &DhtStore<CAS, EAVS>,
     entry: &Entry,
 ) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    // TODO #439 - Log the error. Once we have better logging.
+    store_sys_entry(old_store, entry).ok()?
+}
+
+/// stores the entry, Ok(None) if its type isn't publishable
+fn store_sys_entry<CAS, EAVS>(
+    old_store: &DhtStore<CAS, EAVS>,
+    entry: &Entry,
+) -> Result<Option<DhtStore<CAS, EAVS>>, HolochainError>
 where
     CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
     EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
 {
     // system entry type must be publishable
     if !entry.entry_type().to_owned().can_publish() {
-        return None;
+        return Ok(None);
     }
     // Add it local storage
     let mut new_store = (*old_store).clone();
-    let res = new_store.content_storage_mut().add(entry);
-    if res.is_err() {
-        // TODO #439 - Log the error. Once we have better logging.
-        return None;
-    }
+    new_store.content_storage_mut().add(entry)?;
     // Note: System entry types are not published to the network
-    Some(new_store)
+    Ok(Some(new_store))
 }
 
 //
@@ -84,7 +154,30 @@ pub(crate) fn commit_app_entry<CAS, EAVS>(
     context: Arc<Context>,
     old_store: &DhtStore<CAS, EAVS>,
     entry: &Entry,
+    options: &CommitOptions,
 ) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    // TODO #439 - Log the error. Once we have better logging.
+    let mut new_store = store_app_entry(&context, old_store, entry, options).ok()??;
+    // the queue is sent right away unless auto publish is disabled, within the rate limit
+    if context.auto_publish {
+        publish_queued(&context, &mut new_store);
+    }
+    Some(new_store)
+}
+
+/// stores the entry and queues it for the network if its type is public, without sending the
+/// queue
+/// Ok(None) if there is nothing to store, e.g. a private entry committed outside of a zome
+fn store_app_entry<CAS, EAVS>(
+    context: &Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    entry: &Entry,
+    options: &CommitOptions,
+) -> Result<Option<DhtStore<CAS, EAVS>>, HolochainError>
 where
     CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
     EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
@@ -97,29 +190,62 @@ where
         .nucleus()
         .dna()
         .expect("context.state must hold DNA in order to commit an app entry.");
-    let maybe_def = dna.get_entry_type_def(&entry.entry_type().to_string());
-    if maybe_def.is_none() {
-        // TODO #439 - Log the error. Once we have better logging.
-        return None;
-    }
-    let entry_type_def = maybe_def.unwrap();
+    let entry_type_def = dna
+        .get_entry_type_def(&entry.entry_type().to_string())
+        .ok_or_else(|| {
+            HolochainError::ErrorGeneric(format!("unknown entry type {}", entry.entry_type()))
+        })?;
 
-    // app entry type must be publishable
+    // app entry type must be publishable, entries of other types only get the namespace of
+    // the zome committing them, so other zomes can't get them
+    let mut new_store = (*old_store).clone();
     if !entry_type_def.sharing.clone().can_publish() {
-        return None;
+        let namespace = match options.namespace {
+            Some(ref namespace) => namespace,
+            None => return Ok(None),
+        };
+        new_store.add_to_namespace(&entry.address(), namespace)?;
+        return Ok(Some(new_store));
     }
 
-    // Add it to local storage...
-    let mut new_store = (*old_store).clone();
-    let res = new_store.content_storage_mut().add(entry);
-    if res.is_err() {
-        // TODO #439 - Log the error. Once we have better logging.
-        return None;
+    // no other entry may have the values of its unique fields, @see AgentState::commit()
+    // nor its address if it is addressed by a key
+    new_store.check_unique(entry, &entry_type_def.unique_fields)?;
+    new_store.check_address_key(entry, is_own_entry(context, &entry.address()))?;
+
+    // Restrict who can get it before anyone can, its author always can, so nothing is
+    // stored if the peers refuse the restriction...
+    if let Some(ref readers) = options.acl {
+        let mut readers = readers.clone();
+        readers.push(context.agent.address());
+        let key_pair = context.keystore.key_pair(&context.agent)?;
+        new_store.restrict(&AclGrant::new(
+            &entry.address(),
+            &readers,
+            &context.agent.address(),
+            &key_pair,
+        ))?;
     }
-    // ...and publish to the network if its not private
-    new_store.network_mut().publish(entry);
+    // ...add it to local storage, or the backend its type is routed to...
+    match context.storage_routes.storage_for(entry.entry_type()) {
+        Some(storage) => storage.add(entry)?,
+        None => new_store.content_storage_mut().add(entry)?,
+    };
+    // ...maintain the secondary indexes declared for its type...
+    new_store.add_field_indexes(entry, &entry_type_def.index_fields())?;
+    // ...remember what it references...
+    new_store.add_references(entry)?;
+    // ...remember when it expires if its type has a time to live...
+    if let Some(ttl) = entry_type_def.ttl {
+        new_store.expiries_mut().insert(
+            entry.address(),
+            context.clock.now() + Duration::from_secs(ttl),
+        );
+    }
+    // ...and queue it for the network since it's not private
+    new_store.publish_queue_mut().push(entry.address());
     // Done
-    Some(new_store)
+    Ok(Some(new_store))
 }
 
 //
@@ -132,32 +258,208 @@ where
     CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
     EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
 {
-    let action = action_wrapper.action();
-    let entry = unwrap_to!(action => Action::Commit);
+    let (entry, options) = match action_wrapper.action() {
+        Action::Commit(entry) => (entry, CommitOptions::default()),
+        Action::CommitWithOptions(entry, options) => (entry, options.clone()),
+        _ => unreachable!(),
+    };
 
-    // pre-condition: Must not already have entry in local storage
-    if old_store
-        .content_storage()
-        .contains(&entry.address())
-        .unwrap()
-    {
-        // TODO #439 - Log a warning saying this should not happen. Once we have better logging.
+    // read replicas don't write to the storage they share
+    if context.read_only {
         return None;
     }
+    commit_entry(context, old_store, entry, &options)
+}
+
+/// stores the entry and queues it for the network unless it was already
+fn commit_entry<CAS, EAVS>(
+    context: Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    entry: &Entry,
+    options: &CommitOptions,
+) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    // TODO #439 - Log the error. Once we have better logging.
+    let mut new_store = store_entry(&context, old_store, entry, options).ok()??;
+    if !entry.entry_type().to_owned().is_sys() && context.auto_publish && !options.hold_publish {
+        publish_queued(&context, &mut new_store);
+    }
+    Some(new_store)
+}
+
+/// like commit_entry() but without sending the publish queue, failing if the entry couldn't
+/// be stored
+fn store_entry<CAS, EAVS>(
+    context: &Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    entry: &Entry,
+    options: &CommitOptions,
+) -> Result<Option<DhtStore<CAS, EAVS>>, HolochainError>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    // pre-condition: Must not already have published or queued the entry
+    // the agent's chain shares the content storage, so the entry is usually stored already
+    let address = entry.address();
+    if old_store.network().published().contains(&address)
+        || old_store.publish_queue().contains(&address)
+    {
+        return Ok(None);
+    }
 
     // Handle sys entries and app entries differently
     if entry.entry_type().to_owned().is_sys() {
-        return commit_sys_entry(context, old_store, entry);
+        return store_sys_entry(old_store, entry);
     }
-    return commit_app_entry(context, old_store, entry);
+    store_app_entry(context, old_store, entry, options)
 }
 
 //
-pub(crate) fn reduce_get_entry_from_network<CAS, EAVS>(
+pub(crate) fn reduce_flush_publishes<CAS, EAVS>(
+    context: Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    _action_wrapper: &ActionWrapper,
+) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    if old_store.publish_queue().is_empty() {
+        return None;
+    }
+    let mut new_store = (*old_store).clone();
+    publish_queued(&context, &mut new_store);
+    Some(new_store)
+}
+
+/// sends the queued entries to the network in the publish order of the context
+/// entries over the publish rate limit stay queued
+fn publish_queued<CAS, EAVS>(context: &Arc<Context>, store: &mut DhtStore<CAS, EAVS>)
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    let queue = store.publish_queue_mut().split_off(0);
+    let mut entries = Vec::new();
+    for address in queue {
+        match store.content_storage().fetch::<Entry>(&address) {
+            Ok(Some(entry)) => entries.push(entry),
+            // TODO #439 - Log the error. Once we have better logging.
+            _ => store.publish_queue_mut().push(address),
+        }
+    }
+    if context.publish_order == PublishOrder::Dependencies {
+        entries = dependency_order(entries);
+    }
+    let now = context.clock.now();
+    let mut sent = Vec::new();
+    for entry in entries {
+        if store.publish_within(&entry, context.publish_rate_limit, now) {
+            sent.push(entry);
+        } else {
+            store.publish_queue_mut().push(entry.address());
+        }
+    }
+    // the network worker of the instance hands them to the peers in this order
+    // how every publish ended comes back in a ReturnPublishResult action
+    if store.network().backend().is_none() {
+        return;
+    }
+    store
+        .network_requests_mut()
+        .extend(sent.into_iter().map(NetworkRequest::Publish));
+}
+
+//
+pub(crate) fn reduce_return_publish_result<CAS, EAVS>(
+    context: Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    let (address, result) = match action_wrapper.action() {
+        Action::ReturnPublishResult(address, result) => (address, result),
+        _ => unreachable!(),
+    };
+    // the peers took it, nothing to record
+    let error = result.clone().err()?;
+    context
+        .log(&format!("dht: publishing {} failed: {}", address, error))
+        .expect("Logger should work");
+    let mut new_store = (*old_store).clone();
+    new_store.network_mut().publish_failed(address);
+    Some(new_store)
+}
+
+/// a rolled back entry isn't published, the content storage keeps it tombstoned as rejected
+/// if it was published already, peers keep their copy
+pub(crate) fn reduce_roll_back_commit<CAS, EAVS>(
     _context: Arc<Context>,
     old_store: &DhtStore<CAS, EAVS>,
     action_wrapper: &ActionWrapper,
 ) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    let address = unwrap_to!(action_wrapper.action() => Action::RollBackCommit);
+    if !old_store.content_storage().contains(address).ok()? {
+        return None;
+    }
+    let mut new_store = (*old_store).clone();
+    new_store
+        .publish_queue_mut()
+        .retain(|queued| queued != address);
+    new_store
+        .add_crud_status(address, CrudStatus::REJECTED)
+        .ok()?;
+    Some(new_store)
+}
+
+//
+pub(crate) fn reduce_expire_entries<CAS, EAVS>(
+    context: Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    _action_wrapper: &ActionWrapper,
+) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    let due = old_store.due_expiries(context.clock.now());
+    if due.is_empty() {
+        return None;
+    }
+    let mut new_store = (*old_store).clone();
+    for address in due {
+        // tombstoned in the meta storage, the entry itself stays in the content storage
+        if new_store
+            .add_crud_status(&address, CrudStatus::DELETED)
+            .is_err()
+        {
+            // TODO #439 - Log the error. Once we have better logging.
+            continue;
+        }
+        new_store.expiries_mut().remove(&address);
+        new_store.expired_mut().insert(address);
+    }
+    Some(new_store)
+}
+
+//
+/// every get is counted, whether or not the entry is fetched, @see DhtStore::access_counts()
+pub(crate) fn reduce_get_entry_from_network<CAS, EAVS>(
+    context: Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore<CAS, EAVS>>
 where
     CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
     EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
@@ -165,67 +467,427 @@ where
     // Get Action's input data
     let action = action_wrapper.action();
     let address = unwrap_to!(action => Action::GetEntry);
+    let mut new_store = fetch_entry_from_network(&context, old_store, action_wrapper, address)
+        .unwrap_or_else(|| (*old_store).clone());
+    new_store.count_access(address);
+    Some(new_store)
+}
+
+/// the store asking the network worker for the entry at the address, or with the cached entry
+/// touched, None if it has nothing to fetch
+/// what the peers answered comes back in a ReturnGetResult action
+fn fetch_entry_from_network<CAS, EAVS>(
+    context: &Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    action_wrapper: &ActionWrapper,
+    address: &Address,
+) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
     // pre-condition check: Look in local storage if it already has it.
     if old_store.content_storage().contains(address).unwrap() {
         // TODO #439 - Log a warning saying this should not happen. Once we have better logging.
         return None;
     }
-    // Retrieve it from the network...
-    old_store
-        .network()
-        .clone()
-        .get(address)
-        .and_then(|content| {
-            let entry = Entry::from_content(&content);
-            let mut new_store = (*old_store).clone();
-            // ...and add it to the local storage
-            let res = new_store.content_storage_mut().add(&entry);
-            match res {
-                Err(_) => None,
-                Ok(()) => Some(new_store),
+    // Cached entries were fetched from the network already
+    if old_store.cache().contains(address) {
+        let mut new_store = (*old_store).clone();
+        new_store.cache_mut().touch(address);
+        return Some(new_store);
+    }
+    // The network didn't have it a moment ago
+    if let Some(ttl) = context.negative_cache_ttl {
+        if old_store
+            .cache()
+            .is_known_missing(address, ttl, context.clock.now())
+        {
+            return None;
+        }
+    }
+    // There is nobody to ask
+    old_store.network().backend()?;
+    let mut new_store = (*old_store).clone();
+    new_store
+        .network_requests_mut()
+        .push(NetworkRequest::Get(action_wrapper.clone(), address.clone()));
+    Some(new_store)
+}
+
+//
+pub(crate) fn reduce_return_get_result<CAS, EAVS>(
+    context: Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    let (get, content) = match action_wrapper.action() {
+        Action::ReturnGetResult(get, content) => (get, content),
+        _ => unreachable!(),
+    };
+    let address = unwrap_to!(get.action() => Action::GetEntry);
+    // it was committed while the peers were asked
+    if old_store.content_storage().contains(address).ok()? {
+        return None;
+    }
+    // the peers refused the agent the entry
+    let content = content.as_ref().ok()?;
+    let mut new_store = (*old_store).clone();
+    match content {
+        // add it to the cache if it is the entry asked for
+        // a buggy or hostile peer must not take down the reduce loop
+        Some(content) => match entry_from_peer(&context, address, content) {
+            Ok(entry) => new_store.cache_mut().insert(entry),
+            Err(error) => {
+                context
+                    .log(&format!(
+                        "dht: skipping the content fetched for {}: {}",
+                        address, error
+                    ))
+                    .expect("Logger should work");
+                return None;
             }
-        })
+        },
+        // or remember it's missing
+        None if context.negative_cache_ttl.is_some() => new_store
+            .cache_mut()
+            .insert_miss(address, context.clock.now()),
+        None => return None,
+    }
+    Some(new_store)
 }
 
 //
-pub(crate) fn reduce_add_link<CAS, EAVS>(
+pub(crate) fn reduce_clear_negative_cache<CAS, EAVS>(
     _context: Arc<Context>,
-    _old_store: &DhtStore<CAS, EAVS>,
+    old_store: &DhtStore<CAS, EAVS>,
     _action_wrapper: &ActionWrapper,
 ) -> Option<DhtStore<CAS, EAVS>>
 where
     CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
     EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
 {
-    // FIXME
-    None
+    let mut new_store = (*old_store).clone();
+    new_store.cache_mut().clear_misses();
+    Some(new_store)
+}
+
+//
+pub(crate) fn reduce_load_dht_snapshot<CAS, EAVS>(
+    context: Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    let entries = unwrap_to!(action_wrapper.action() => Action::LoadDhtSnapshot);
+    // read replicas don't write to the storage they share
+    if context.read_only {
+        return None;
+    }
+    let mut new_store = (*old_store).clone();
+    for entry in entries {
+        // TODO #439 - Log the errors. Once we have better logging.
+        // an entry only takes the address its content derives
+        if context.verify_address_key(entry).is_err()
+            || new_store.content_storage_mut().add(entry).is_err()
+        {
+            continue;
+        }
+        // the peer held them already, they are not published again
+        if *entry.entry_type() == EntryType::Link {
+            let link_entry = LinkEntry::from_entry(entry);
+            let _ = match link_entry.action_kind() {
+                LinkActionKind::ADD => new_store.add_link(link_entry.link(), None),
+                LinkActionKind::DELETE => new_store.remove_link(link_entry.link()),
+            };
+        } else {
+            let _ = new_store.add_references(entry);
+            new_store
+                .known_addresses_mut(entry.entry_type())
+                .insert(entry.address());
+        }
+    }
+    Some(new_store)
+}
+
+//
+pub(crate) fn reduce_remove_links<CAS, EAVS>(
+    context: Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    let links = unwrap_to!(action_wrapper.action() => Action::RemoveLinks);
+    // read replicas don't write to the storage they share
+    if context.read_only {
+        return None;
+    }
+    let mut new_store = (*old_store).clone();
+    for link in links {
+        // TODO #439 - Log the error. Once we have better logging.
+        let _ = new_store.remove_link(link);
+    }
+    Some(new_store)
+}
+
+//
+pub(crate) fn reduce_attach_metadata<CAS, EAVS>(
+    context: Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    let metadata = unwrap_to!(action_wrapper.action() => Action::AttachMetadata);
+    // read replicas don't write to the storage they share
+    if context.read_only {
+        return None;
+    }
+    let mut new_store = (*old_store).clone();
+    match new_store.attach_metadata(metadata) {
+        Ok(()) => Some(new_store),
+        // TODO #439 - Log the error. Once we have better logging.
+        Err(_) => None,
+    }
+}
+
+//
+pub(crate) fn reduce_import_links<CAS, EAVS>(
+    context: Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    let (export, mode) = match action_wrapper.action() {
+        Action::ImportLinks(export, mode) => (export, mode),
+        _ => unreachable!(),
+    };
+    // read replicas don't write to the storage they share
+    if context.read_only {
+        return None;
+    }
+    let mut new_store = (*old_store).clone();
+    // TODO #439 - Log the error. Once we have better logging.
+    let _ = new_store.import_links(export.links(), mode);
+    Some(new_store)
+}
+
+//
+pub(crate) fn reduce_get_entries_by_type<CAS, EAVS>(
+    _context: Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    let entry_type = unwrap_to!(action_wrapper.action() => Action::GetEntriesByType);
+    let addresses = old_store.network().addresses_of_type(entry_type);
+    let mut new_store = (*old_store).clone();
+    // merged with what peers told about before
+    new_store
+        .known_addresses_mut(entry_type)
+        .extend(addresses.into_iter());
+    Some(new_store)
+}
+
+//
+pub(crate) fn reduce_add_link<CAS, EAVS>(
+    context: Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    let link = unwrap_to!(action_wrapper.action() => Action::AddLink);
+    let mut new_store = (*old_store).clone();
+    let result = if context.read_only {
+        Err(HolochainError::ReadOnlyInstance)
+    } else {
+        new_store.add_link(link, context.max_links_per_base)
+    };
+    new_store
+        .add_link_results_mut()
+        .insert(action_wrapper.clone(), result);
+    Some(new_store)
 }
 
 //
-pub(crate) fn reduce_get_links<CAS, EAVS>(
+pub(crate) fn reduce_transaction<CAS, EAVS>(
+    context: Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    let transaction = unwrap_to!(action_wrapper.action() => Action::Transaction);
+    // nothing is applied if any of the operations would fail, the agent reducer reports why
+    // State::reduce() doesn't even get here unless the agent committed the entries
+    if transaction.check(&context, old_store).is_err() {
+        return None;
+    }
+    // the operations are applied to a copy of the store over staged storages, which is dropped
+    // if any of them fails, and nothing is sent to the network before all of them were applied
+    // entries of types routed to another storage are stored there right away
+    let mut new_store = old_store.over_storages(
+        StagedStorage::new(old_store.content_storage()),
+        StagedEavStorage::new(old_store.meta_storage()),
+    );
+    for op in transaction.ops() {
+        let applied = match op {
+            TransactionOp::Commit(entry) => {
+                match store_entry(&context, &new_store, entry, &CommitOptions::default()) {
+                    Ok(Some(store)) => {
+                        new_store = store;
+                        Ok(())
+                    }
+                    Ok(None) => Ok(()),
+                    Err(error) => Err(error),
+                }
+            }
+            TransactionOp::AddLink(link) => new_store.add_link(link, context.max_links_per_base),
+        };
+        if applied.is_err() {
+            // TODO #439 - Log the error. Once we have better logging.
+            return None;
+        }
+    }
+    let content_storage = new_store.content_storage().commit().ok()?;
+    let meta_storage = new_store.meta_storage().commit().ok()?;
+    let mut new_store = new_store.over_storages(content_storage, meta_storage);
+    if context.auto_publish {
+        publish_queued(&context, &mut new_store);
+    }
+    Some(new_store)
+}
+
+//
+pub(crate) fn reduce_pin_entry<CAS, EAVS>(
     _context: Arc<Context>,
-    _old_store: &DhtStore<CAS, EAVS>,
-    _action_wrapper: &ActionWrapper,
+    old_store: &DhtStore<CAS, EAVS>,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore<CAS, EAVS>>
+where
+    CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+    EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+{
+    let address = unwrap_to!(action_wrapper.action() => Action::PinEntry);
+    let mut new_store = (*old_store).clone();
+    let result = new_store.cache_mut().pin(address);
+    new_store
+        .pin_results_mut()
+        .insert(action_wrapper.clone(), result);
+    Some(new_store)
+}
+
+//
+pub(crate) fn reduce_unpin_entry<CAS, EAVS>(
+    _context: Arc<Context>,
+    old_store: &DhtStore<CAS, EAVS>,
+    action_wrapper: &ActionWrapper,
 ) -> Option<DhtStore<CAS, EAVS>>
 where
     CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
     EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
 {
-    // FIXME
-    None
+    let address = unwrap_to!(action_wrapper.action() => Action::UnpinEntry);
+    let mut new_store = (*old_store).clone();
+    new_store.cache_mut().unpin(address);
+    Some(new_store)
 }
 
 #[cfg(test)]
 pub mod tests {
 
-    use dht::dht_reducers::commit_sys_entry;
+    use action::{Action, ActionWrapper};
+    use agent::actions::transaction::Transaction;
+    use clock::ManualClock;
+    use context::Context;
+    use dht::{
+        dht_reducers::{
+            commit_app_entry, commit_sys_entry, reduce, reduce_add_link, reduce_flush_publishes,
+            reduce_get_entries_by_type, reduce_transaction,
+        },
+        dht_store::{DhtStore, Network, PublishOrder, PublishRateLimit},
+        network::NetworkBackend,
+        network_worker::{entry_from_peer, NetworkRequest},
+    };
+    use holochain_agent::Agent;
+    use holochain_cas_implementations::{
+        cas::memory::MemoryStorage, eav::memory::EavMemoryStorage,
+    };
     use holochain_core_types::{
-        cas::{content::AddressableContent, storage::ContentAddressableStorage},
-        entry::{test_entry, test_sys_entry, test_unpublishable_entry, Entry},
+        cas::{
+            content::{Address, AddressableContent},
+            storage::ContentAddressableStorage,
+        },
+        entry::{test_entry, test_entry_b, test_sys_entry, test_unpublishable_entry, Entry},
+        entry_type::{test_entry_type, test_entry_type_b, EntryType},
+        error::HolochainError,
+        get_links_args::GetLinksArgs,
+        links_entry::Link,
+    };
+    use holochain_dna::{
+        zome::{
+            entry_types::{AddressDerivation, EntryTypeDef},
+            Zome,
+        },
+        Dna,
     };
-    use instance::tests::test_context;
-    use state::test_store;
-    use std::sync::Arc;
+    use instance::tests::{test_context, test_context_and_logger, test_logger};
+    use persister::SimplePersister;
+    use state::{test_store, State};
+    use std::{
+        sync::{Arc, Mutex, RwLock},
+        time::Duration,
+    };
+
+    /// a context whose state holds a DNA with a "post" entry type indexed by author
+    fn test_context_with_indexed_posts() -> Arc<Context> {
+        let mut post_def = EntryTypeDef::new();
+        post_def.indexed_fields.push("author".to_string());
+        test_context_with_posts(post_def)
+    }
+
+    /// a context whose state holds a DNA with a "post" entry type of the given definition
+    fn test_context_with_posts(post_def: EntryTypeDef) -> Arc<Context> {
+        let mut zome = Zome::default();
+        zome.entry_types.insert("post".to_string(), post_def);
+        let mut dna = Dna::new();
+        dna.zomes.insert("blog".to_string(), zome);
+
+        let mut context = Context::new(
+            Agent::from("alex".to_string()),
+            test_logger(),
+            Arc::new(Mutex::new(SimplePersister::new())),
+        );
+        let state = State::new().reduce(
+            test_context("alex"),
+            ActionWrapper::new(Action::InitApplication(dna)),
+        );
+        context.set_state(Arc::new(RwLock::new(state)));
+        Arc::new(context)
+    }
 
     #[test]
     fn commit_sys_entry_test() {
@@ -271,4 +933,609 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn find_posts_by_author_test() {
+        let context = test_context_with_indexed_posts();
+        let post_type = EntryType::App("post".to_string());
+        let post_a = Entry::new(&post_type, &r#"{"author":"alex","title":"a"}"#.to_string());
+        let post_b = Entry::new(&post_type, &r#"{"author":"bob","title":"b"}"#.to_string());
+        let post_c = Entry::new(&post_type, &r#"{"author":"alex","title":"c"}"#.to_string());
+
+        let mut store = (*context.state().unwrap().dht()).clone();
+        for post in vec![post_a.clone(), post_b.clone(), post_c.clone()] {
+            store = commit_app_entry(Arc::clone(&context), &store, &post, &Default::default())
+                .expect("there should be a new store for committing a post");
+        }
+
+        let mut expected = vec![post_a.address(), post_c.address()];
+        expected.sort();
+        assert_eq!(
+            Ok(expected),
+            store.find_by_field(&post_type, "author", "alex")
+        );
+        assert_eq!(
+            Ok(vec![post_b.address()]),
+            store.find_by_field(&post_type, "author", "bob")
+        );
+        assert_eq!(
+            Ok(vec![]),
+            store.find_by_field(&post_type, "author", "carol")
+        );
+        // only declared fields are indexed
+        assert_eq!(Ok(vec![]), store.find_by_field(&post_type, "title", "a"));
+        // other entry types are filtered out
+        assert_eq!(
+            Ok(vec![]),
+            store.find_by_field(&EntryType::App("comment".to_string()), "author", "alex")
+        );
+    }
+
+    #[test]
+    /// an operation failing halfway through a transaction leaves nothing of the ones before
+    /// it in the storages
+    fn failed_transaction_leaves_storages_alone_test() {
+        let context = test_context_with_indexed_posts();
+        let post_type = EntryType::App("post".to_string());
+        let post = Entry::new(&post_type, &r#"{"author":"alex"}"#.to_string());
+        // passes the checks, but the DNA doesn't know its type when it is stored
+        let comment = Entry::new(
+            &EntryType::App("comment".to_string()),
+            &r#"{"author":"alex"}"#.to_string(),
+        );
+        let mut transaction = Transaction::new();
+        transaction
+            .commit(post.clone())
+            .add_link(Link::new(&post.address(), &test_entry().address(), "tag"))
+            .commit(comment);
+        let store = (*context.state().unwrap().dht()).clone();
+        let eavs_before = store.meta_storage().fetch_eav(None, None, None).unwrap();
+
+        assert_eq!(
+            None,
+            reduce_transaction(
+                Arc::clone(&context),
+                &store,
+                &ActionWrapper::new(Action::Transaction(transaction)),
+            )
+        );
+        assert_eq!(Ok(false), store.content_storage().contains(&post.address()));
+        assert_eq!(
+            Ok(eavs_before),
+            store.meta_storage().fetch_eav(None, None, None)
+        );
+        assert_eq!(
+            Ok(vec![]),
+            store.find_by_field(&post_type, "author", "alex")
+        );
+    }
+
+    #[test]
+    /// a post with the title of a held post is rejected, the held post can be committed again
+    fn commit_rejects_duplicate_unique_field_test() {
+        let mut post_def = EntryTypeDef::new();
+        post_def.unique_fields.push("title".to_string());
+        let context = test_context_with_posts(post_def);
+        let post_type = EntryType::App("post".to_string());
+        let post_a = Entry::new(&post_type, &r#"{"author":"alex","title":"a"}"#.to_string());
+        let post_b = Entry::new(&post_type, &r#"{"author":"bob","title":"a"}"#.to_string());
+        let post_c = Entry::new(&post_type, &r#"{"author":"bob","title":"c"}"#.to_string());
+
+        let store = (*context.state().unwrap().dht()).clone();
+        let store = commit_app_entry(Arc::clone(&context), &store, &post_a, &Default::default())
+            .expect("there should be a new store for committing the first post");
+        assert_eq!(
+            Err(HolochainError::UniquenessViolation {
+                field: "title".to_string(),
+                value: "a".to_string(),
+            }),
+            store.check_unique(&post_b, &["title".to_string()])
+        );
+        assert_eq!(
+            None,
+            commit_app_entry(Arc::clone(&context), &store, &post_b, &Default::default())
+        );
+        assert_eq!(
+            Ok(vec![post_a.address()]),
+            store.find_by_field(&post_type, "title", "a")
+        );
+
+        assert!(store.check_unique(&post_a, &["title".to_string()]).is_ok());
+        assert!(
+            commit_app_entry(Arc::clone(&context), &store, &post_c, &Default::default()).is_some()
+        );
+    }
+
+    /// a context whose state holds a DNA with a "post" entry type addressed by its title
+    fn test_context_with_titled_posts() -> Arc<Context> {
+        let mut post_def = EntryTypeDef::new();
+        post_def.address_derivation = AddressDerivation::ByField("title".to_string());
+        test_context_with_posts(post_def)
+    }
+
+    /// a post addressed by its title
+    fn titled_post(title: &str, author: &str) -> Entry {
+        Entry::new(
+            &EntryType::App("post".to_string()),
+            &format!(r#"{{"author":"{}","title":"{}"}}"#, author, title),
+        )
+        .with_address_key(title)
+    }
+
+    /// the context with its state reduced with the action
+    fn reduced(context: &Arc<Context>, action: Action) -> Arc<Context> {
+        let state = context
+            .state()
+            .unwrap()
+            .reduce(Arc::clone(context), ActionWrapper::new(action));
+        let mut context = (**context).clone();
+        context.set_state(Arc::new(RwLock::new(state)));
+        Arc::new(context)
+    }
+
+    #[test]
+    /// an entry addressed by a key doesn't take the address of an entry another agent holds
+    fn commit_rejects_taken_address_key_test() {
+        let context = test_context_with_titled_posts();
+        let store = (*context.state().unwrap().dht()).clone();
+        let post = titled_post("a", "alex");
+        let store = commit_app_entry(Arc::clone(&context), &store, &post, &Default::default())
+            .expect("there should be a new store for committing the first post");
+
+        let other = titled_post("a", "bob");
+        assert_eq!(other.address(), post.address());
+        assert_eq!(
+            Err(HolochainError::AddressTaken),
+            store.check_address_key(&other, false)
+        );
+        assert_eq!(
+            None,
+            commit_app_entry(Arc::clone(&context), &store, &other, &Default::default())
+        );
+        assert_eq!(Ok(()), store.check_address_key(&other, true));
+    }
+
+    #[test]
+    /// the agent that committed an entry addressed by a key updates it by committing another
+    /// entry at its address
+    fn author_updates_keyed_entry_test() {
+        let context = test_context_with_titled_posts();
+        let first = titled_post("a", "alex");
+        let second = titled_post("a", "alex and bob");
+
+        let context = reduced(&context, Action::Commit(first.clone()));
+        let context = reduced(&context, Action::Commit(second.clone()));
+
+        let state = context.state().unwrap();
+        assert!(state.agent().chain_header_of(&first.address()).is_some());
+        let held: Option<Entry> = state
+            .dht()
+            .content_storage()
+            .fetch(&first.address())
+            .unwrap();
+        assert_eq!(
+            Some(second.value()),
+            held.as_ref().map(|entry| entry.value())
+        );
+    }
+
+    #[test]
+    /// entries taking an address their content doesn't derive are refused, whether peers
+    /// answered with them or they are loaded from a snapshot
+    fn forged_address_keys_are_refused_test() {
+        let context = test_context_with_titled_posts();
+        let post = titled_post("a", "alex");
+        // the content of post "b" claiming the address of post "a"
+        let forged = Entry::new(post.entry_type(), titled_post("b", "mallory").value())
+            .with_address_key("a");
+        // an address key on a type addressed by its whole content
+        let unkeyed_type = test_entry().with_address_key("a");
+
+        assert_eq!(Ok(()), context.verify_address_key(&post));
+        for entry in vec![forged.clone(), unkeyed_type.clone()] {
+            match entry_from_peer(&context, &entry.address(), &entry.content()) {
+                Err(HolochainError::AddressDerivationFailed(_)) => (),
+                result => panic!("the entry should be refused: {:?}", result),
+            }
+        }
+
+        let context = reduced(
+            &context,
+            Action::LoadDhtSnapshot(vec![forged, unkeyed_type]),
+        );
+        let storage = context.state().unwrap().dht().content_storage();
+        assert_eq!(Ok(false), storage.contains(&post.address()));
+        assert_eq!(
+            Ok(false),
+            storage.contains(&test_entry().with_address_key("a").address())
+        );
+    }
+
+    #[test]
+    /// entries of routed types are stored in the backend of their type, not the content storage
+    fn commit_routes_entries_by_type_test() {
+        let mut zome = Zome::default();
+        zome.entry_types
+            .insert("post".to_string(), EntryTypeDef::new());
+        zome.entry_types
+            .insert("session".to_string(), EntryTypeDef::new());
+        let mut dna = Dna::new();
+        dna.zomes.insert("blog".to_string(), zome);
+        let post_type = EntryType::App("post".to_string());
+        let session_type = EntryType::App("session".to_string());
+        let posts = MemoryStorage::new().unwrap();
+        let sessions = MemoryStorage::new().unwrap();
+
+        let mut context = (*test_context("alex")).clone();
+        context.storage_routes.route(&post_type, posts.clone());
+        context
+            .storage_routes
+            .route(&session_type, sessions.clone());
+        let state = State::new().reduce(
+            test_context("alex"),
+            ActionWrapper::new(Action::InitApplication(dna)),
+        );
+        context.set_state(Arc::new(RwLock::new(state)));
+        let context = Arc::new(context);
+        let post = Entry::new(&post_type, &r#"{"title":"hello"}"#.to_string());
+        let session = Entry::new(&session_type, &r#"{"token":"abc"}"#.to_string());
+
+        let store = (*context.state().unwrap().dht()).clone();
+        let store = commit_app_entry(Arc::clone(&context), &store, &post, &Default::default())
+            .expect("there should be a new store for committing a post");
+        let store = commit_app_entry(Arc::clone(&context), &store, &session, &Default::default())
+            .expect("there should be a new store for committing a session");
+
+        assert_eq!(Ok(true), posts.contains(&post.address()));
+        assert_eq!(Ok(false), posts.contains(&session.address()));
+        assert_eq!(Ok(true), sessions.contains(&session.address()));
+        assert_eq!(Ok(false), sessions.contains(&post.address()));
+        assert_eq!(Ok(false), store.content_storage().contains(&post.address()));
+        assert_eq!(
+            Ok(false),
+            store.content_storage().contains(&session.address())
+        );
+    }
+
+    #[test]
+    fn commit_without_auto_publish_test() {
+        let mut context = (*test_context_with_indexed_posts()).clone();
+        context.auto_publish = false;
+        let context = Arc::new(context);
+        let post_type = EntryType::App("post".to_string());
+        let post = Entry::new(&post_type, &r#"{"author":"alex"}"#.to_string());
+
+        let store = (*context.state().unwrap().dht()).clone();
+        let store = commit_app_entry(Arc::clone(&context), &store, &post, &Default::default())
+            .expect("there should be a new store for committing a post");
+
+        // in local storage and queued, but not published
+        assert_eq!(
+            Some(post.clone()),
+            store
+                .content_storage()
+                .fetch(&post.address())
+                .expect("could not fetch from cas")
+        );
+        assert_eq!(&vec![post.address()], store.publish_queue());
+        assert!(store.network().published().is_empty());
+
+        let store = reduce_flush_publishes(
+            Arc::clone(&context),
+            &store,
+            &ActionWrapper::new(Action::FlushPublishes),
+        )
+        .expect("flushing a non empty queue should return a new store");
+
+        assert!(store.publish_queue().is_empty());
+        assert_eq!(&vec![post.address()], store.network().published());
+    }
+
+    #[test]
+    fn flush_publishes_referenced_entries_first_test() {
+        let mut context = (*test_context_with_indexed_posts()).clone();
+        context.auto_publish = false;
+        let context = Arc::new(context);
+        let post_type = EntryType::App("post".to_string());
+        let post = Entry::new(&post_type, &r#"{"author":"alex"}"#.to_string());
+        let reply = Entry::new(
+            &post_type,
+            &format!(r#"{{"author":"bob","reply_to":"{}"}}"#, post.address()),
+        );
+
+        // the reply is queued before the post it references
+        let store = (*context.state().unwrap().dht()).clone();
+        let store =
+            commit_app_entry(Arc::clone(&context), &store, &reply, &Default::default()).unwrap();
+        let store =
+            commit_app_entry(Arc::clone(&context), &store, &post, &Default::default()).unwrap();
+        assert_eq!(
+            &vec![reply.address(), post.address()],
+            store.publish_queue()
+        );
+
+        let flushed = reduce_flush_publishes(
+            Arc::clone(&context),
+            &store,
+            &ActionWrapper::new(Action::FlushPublishes),
+        )
+        .unwrap();
+        assert_eq!(
+            &vec![post.address(), reply.address()],
+            flushed.network().published()
+        );
+
+        // queue order is kept if asked for
+        let mut context = (*context).clone();
+        context.publish_order = PublishOrder::Queue;
+        let flushed = reduce_flush_publishes(
+            Arc::new(context),
+            &store,
+            &ActionWrapper::new(Action::FlushPublishes),
+        )
+        .unwrap();
+        assert_eq!(
+            &vec![reply.address(), post.address()],
+            flushed.network().published()
+        );
+    }
+
+    #[test]
+    /// entries are referrers of the addresses in their fields and bases of links are
+    /// referrers of the targets, until the links are removed
+    fn commit_indexes_referrers_test() {
+        let context = test_context_with_indexed_posts();
+        let post_type = EntryType::App("post".to_string());
+        let post = Entry::new(&post_type, &r#"{"author":"alex"}"#.to_string());
+        let reply = Entry::new(
+            &post_type,
+            &format!(r#"{{"author":"bob","reply_to":"{}"}}"#, post.address()),
+        );
+
+        let store = (*context.state().unwrap().dht()).clone();
+        let store =
+            commit_app_entry(Arc::clone(&context), &store, &post, &Default::default()).unwrap();
+        let mut store =
+            commit_app_entry(Arc::clone(&context), &store, &reply, &Default::default()).unwrap();
+        assert_eq!(Ok(vec![reply.address()]), store.referrers(&post.address()));
+        assert_eq!(Ok(vec![]), store.referrers(&reply.address()));
+
+        let link = Link::new(&test_entry().address(), &post.address(), "likes");
+        store.add_link(&link, None).unwrap();
+        let mut referrers = vec![reply.address(), test_entry().address()];
+        referrers.sort();
+        assert_eq!(Ok(referrers), store.referrers(&post.address()));
+
+        store.remove_link(&link).unwrap();
+        assert_eq!(Ok(vec![reply.address()]), store.referrers(&post.address()));
+    }
+
+    #[test]
+    /// a burst of commits is sent to the network a few entries per interval, not all at once
+    fn publish_rate_limit_test() {
+        let mut context = (*test_context_with_indexed_posts()).clone();
+        context.publish_order = PublishOrder::Queue;
+        context.publish_rate_limit = Some(PublishRateLimit::new(2, Duration::from_millis(300)));
+        let clock = ManualClock::new(Duration::from_secs(0));
+        context.clock = Arc::new(clock.clone());
+        let context = Arc::new(context);
+        let post_type = EntryType::App("post".to_string());
+        let posts: Vec<Entry> = (0..5)
+            .map(|n| Entry::new(&post_type, &format!(r#"{{"author":"alex","n":{}}}"#, n)))
+            .collect();
+        let addresses: Vec<Address> = posts.iter().map(|post| post.address()).collect();
+
+        let mut store = (*context.state().unwrap().dht()).clone();
+        for post in posts.iter() {
+            store =
+                commit_app_entry(Arc::clone(&context), &store, post, &Default::default()).unwrap();
+        }
+        assert_eq!(&addresses[..2].to_vec(), store.network().published());
+        assert_eq!(&addresses[2..].to_vec(), store.publish_queue());
+
+        // flushing within the same interval sends nothing more
+        let flush = ActionWrapper::new(Action::FlushPublishes);
+        let store = reduce_flush_publishes(Arc::clone(&context), &store, &flush).unwrap();
+        assert_eq!(2, store.network().published().len());
+
+        clock.advance(Duration::from_millis(300));
+        let store = reduce_flush_publishes(Arc::clone(&context), &store, &flush).unwrap();
+        assert_eq!(&addresses[..4].to_vec(), store.network().published());
+        assert_eq!(&addresses[4..].to_vec(), store.publish_queue());
+
+        clock.advance(Duration::from_millis(300));
+        let store = reduce_flush_publishes(Arc::clone(&context), &store, &flush).unwrap();
+        assert_eq!(&addresses, store.network().published());
+        assert!(store.publish_queue().is_empty());
+    }
+
+    #[test]
+    fn add_link_up_to_limit_test() {
+        let mut context = (*test_context("bob")).clone();
+        context.max_links_per_base = Some(2);
+        let context = Arc::new(context);
+        let base = test_entry().address();
+        let targets: Vec<_> = vec!["a", "b", "c"]
+            .iter()
+            .map(|value| Entry::new(&EntryType::App("post".to_string()), &value.to_string()))
+            .map(|entry| entry.address())
+            .collect();
+
+        let mut store = (*test_store().dht()).clone();
+        let mut results = Vec::new();
+        for target in targets.iter() {
+            let action_wrapper =
+                ActionWrapper::new(Action::AddLink(Link::new(&base, target, "tag")));
+            store = reduce_add_link(Arc::clone(&context), &store, &action_wrapper)
+                .expect("adding a link should return a new store");
+            results.push(store.add_link_results()[&action_wrapper].clone());
+        }
+
+        assert_eq!(
+            vec![Ok(()), Ok(()), Err(HolochainError::LinkLimitExceeded)],
+            results
+        );
+
+        // other tags on the same base have their own limit
+        let action_wrapper =
+            ActionWrapper::new(Action::AddLink(Link::new(&base, &targets[2], "other")));
+        let store = reduce_add_link(Arc::clone(&context), &store, &action_wrapper)
+            .expect("adding a link should return a new store");
+        assert_eq!(Ok(()), store.add_link_results()[&action_wrapper]);
+    }
+
+    #[test]
+    /// in strict mode unhandled DHT actions are logged, other actions pass through silently
+    fn strict_reducers_report_unhandled_dht_actions() {
+        let (context, logger) = test_context_and_logger("bob");
+        let mut context = (*context).clone();
+        context.strict_reducers = true;
+        let context = Arc::new(context);
+        let store = Arc::new((*test_store().dht()).clone());
+
+        let get_links = ActionWrapper::new(Action::GetLinks(GetLinksArgs {
+            entry_address: test_entry().address(),
+            tag: "tag".to_string(),
+        }));
+        let new_store = reduce(Arc::clone(&context), Arc::clone(&store), &get_links);
+        assert_eq!(store, new_store);
+        assert_eq!(1, logger.lock().unwrap().log.len());
+        assert!(logger.lock().unwrap().log[0].contains("GetLinks"));
+
+        let not_for_dht = ActionWrapper::new(Action::ReturnInitializationResult(None));
+        reduce(Arc::clone(&context), Arc::clone(&store), &not_for_dht);
+        assert_eq!(1, logger.lock().unwrap().log.len());
+
+        // without strict mode the unhandled action is ignored
+        let (context, logger) = test_context_and_logger("bob");
+        reduce(context, store, &get_links);
+        assert!(logger.lock().unwrap().log.is_empty());
+    }
+
+    /// network whose peers hold the given addresses of the standard test entry type
+    struct MockNetwork {
+        addresses: Vec<Address>,
+    }
+
+    impl NetworkBackend for MockNetwork {
+        fn publish(&self, _entry: &Entry) {}
+
+        fn get(&self, _address: &Address) -> Option<Entry> {
+            None
+        }
+
+        fn addresses_of_type(&self, entry_type: &EntryType) -> Vec<Address> {
+            if entry_type == &test_entry_type() {
+                self.addresses.clone()
+            } else {
+                Vec::new()
+            }
+        }
+    }
+
+    /// network answering every get with the same content
+    struct ContentNetwork {
+        content: String,
+    }
+
+    impl NetworkBackend for ContentNetwork {
+        fn publish(&self, _entry: &Entry) {}
+
+        fn get(&self, _address: &Address) -> Option<Entry> {
+            None
+        }
+
+        fn get_content(&self, _address: &Address) -> Option<String> {
+            Some(self.content.clone())
+        }
+
+        fn addresses_of_type(&self, _entry_type: &EntryType) -> Vec<Address> {
+            Vec::new()
+        }
+    }
+
+    #[test]
+    /// a get only asks the network worker, the store doesn't wait for the peers
+    fn get_entry_asks_the_network_worker() {
+        let context = test_context("bob");
+        let get = ActionWrapper::new(Action::GetEntry(test_entry().address()));
+        let store = Arc::new(DhtStore::new_with_network(
+            MemoryStorage::new().unwrap(),
+            EavMemoryStorage::new().unwrap(),
+            Network::new_on(Arc::new(ContentNetwork {
+                content: test_entry().content(),
+            })),
+        ));
+        let new_store = reduce(Arc::clone(&context), store, &get);
+        assert_eq!(
+            &vec![NetworkRequest::Get(get.clone(), test_entry().address())],
+            new_store.network_requests()
+        );
+        assert!(!new_store.cache().contains(&test_entry().address()));
+
+        // without a network there is nobody to ask
+        let store = Arc::new(DhtStore::new(
+            MemoryStorage::new().unwrap(),
+            EavMemoryStorage::new().unwrap(),
+        ));
+        let new_store = reduce(Arc::clone(&context), store, &get);
+        assert!(new_store.network_requests().is_empty());
+    }
+
+    #[test]
+    /// content that isn't the entry asked for is logged and skipped instead of panicking
+    fn malformed_network_content_is_skipped() {
+        let (context, logger) = test_context_and_logger("bob");
+        let get = ActionWrapper::new(Action::GetEntry(test_entry().address()));
+        let store = Arc::new(DhtStore::new(
+            MemoryStorage::new().unwrap(),
+            EavMemoryStorage::new().unwrap(),
+        ));
+        for content in vec!["{not an entry".to_string(), test_entry_b().content()] {
+            let result = ActionWrapper::new(Action::ReturnGetResult(
+                Box::new(get.clone()),
+                Ok(Some(content)),
+            ));
+            let new_store = reduce(Arc::clone(&context), Arc::clone(&store), &result);
+            assert_eq!(store, new_store);
+            assert!(!new_store.cache().contains(&test_entry().address()));
+        }
+        let log = logger.lock().unwrap().log.clone();
+        assert_eq!(2, log.len());
+        assert!(log
+            .iter()
+            .all(|line| line.contains("skipping the content fetched")));
+
+        // the reduce loop keeps going with well-formed content
+        let result = ActionWrapper::new(Action::ReturnGetResult(
+            Box::new(get),
+            Ok(Some(test_entry().content())),
+        ));
+        let new_store = reduce(Arc::clone(&context), store, &result);
+        assert!(new_store.cache().contains(&test_entry().address()));
+    }
+
+    #[test]
+    /// addresses returned by peers are merged with those known before
+    fn get_entries_by_type_merges_peer_addresses() {
+        let network = Network::new_on(Arc::new(MockNetwork {
+            addresses: vec![test_entry().address()],
+        }));
+        let mut store = DhtStore::new_with_network(
+            MemoryStorage::new().unwrap(),
+            EavMemoryStorage::new().unwrap(),
+            network,
+        );
+        store
+            .known_addresses_mut(&test_entry_type())
+            .insert(test_entry_b().address());
+
+        let action_wrapper = ActionWrapper::new(Action::GetEntriesByType(test_entry_type()));
+        let store = reduce_get_entries_by_type(test_context("bob"), &store, &action_wrapper)
+            .expect("querying the network should return a new store");
+
+        let mut expected = vec![test_entry().address(), test_entry_b().address()];
+        expected.sort();
+        assert_eq!(expected, store.known_addresses(&test_entry_type()));
+        assert!(store.known_addresses(&test_entry_type_b()).is_empty());
+    }
 }
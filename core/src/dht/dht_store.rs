@@ -1,34 +1,409 @@
+use action::ActionWrapper;
+use dht::{
+    cache::EntryCache,
+    get_limit::NetworkGetLimit,
+    link_export::LinkImportMode,
+    metadata::{metadata_attribute, EntryMetadata},
+    network::{AclGrant, GetRequest, NetworkBackend, PublishAck, PublishStatus},
+    network_worker::NetworkRequest,
+    routing::RoutingStrategy,
+};
+use futures::executor::block_on;
 use holochain_core_types::{
     cas::{
         content::{Address, AddressableContent, Content},
         storage::ContentAddressableStorage,
     },
-    eav::{EntityAttributeValue, EntityAttributeValueStorage},
+    crud_status::{CrudStatus, STATUS_NAME},
+    eav::{
+        Attribute, EavQuery, Entity, EntityAttributeValue, EntityAttributeValueStorage, QueryPlan,
+    },
+    entry::{Entry, ToEntry},
+    entry_type::EntryType,
     error::HolochainError,
     hash::HashString,
-    links_entry::Link,
+    json::FromJson,
+    links_entry::{Link, LinkActionKind, LinkEntry},
 };
-use std::collections::HashSet;
+use multihash::Hash;
+use serde_json::{self, Value};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    fmt,
+    sync::Arc,
+    time::Duration,
+};
+
+/// EAV attribute under which the links with the given tag are stored
+pub fn link_attribute(tag: &str) -> Attribute {
+    format!("link:{}", tag)
+}
+
+/// EAV attribute under which the removed links with the given tag are stored
+pub fn link_tombstone_attribute(tag: &str) -> Attribute {
+    format!("link_tombstone:{}", tag)
+}
+
+/// EAV attribute under which the addresses an entry references are stored
+/// @see publish_dependencies()
+pub const REFERENCES_NAME: &str = "references";
+
+/// EAV attribute under which the agents allowed to get an entry are stored
+pub const ACL_NAME: &str = "acl";
+
+/// EAV attribute under which the namespace of the zome that committed an entry is stored
+/// @see zome_namespace()
+pub const NAMESPACE_NAME: &str = "namespace";
+
+/// namespace of the entries a zome commits to types that aren't shared, only the zome itself
+/// gets them
+pub fn zome_namespace(zome_name: &str) -> Address {
+    HashString::from(format!("zome:{}", zome_name))
+}
+
+/// EAV attribute under which the secondary index of an entry field is stored
+/// namespaced so indexes can't be confused with other meta data such as links
+pub fn index_attribute(field_name: &str) -> Attribute {
+    format!("index:{}", field_name)
+}
+
+/// EAV entity standing for a value of an indexed field
+/// strings are indexed as is, any other JSON value by its JSON representation
+pub fn index_entity(field_value: &str) -> Entity {
+    HashString::encode_from_str(field_value, Hash::SHA2256)
+}
+
+/// the values of the given fields in an entry whose value is a JSON object
+/// fields that are missing are skipped
+pub fn indexed_field_values(entry: &Entry, field_names: &[String]) -> Vec<(String, String)> {
+    let json: Value = match serde_json::from_str(entry.value()) {
+        Ok(json) => json,
+        Err(_) => return Vec::new(),
+    };
+    field_names
+        .iter()
+        .filter_map(|field_name| {
+            json.get(field_name).map(|field_value| {
+                let field_value = match field_value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                (field_name.clone(), field_value)
+            })
+        })
+        .collect()
+}
+
+/// What collecting the orphaned links did, @see DhtStore::orphaned_links()
+/// only links are collected, no other meta data is checked or counted
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GcReport {
+    /// number of links looked at
+    pub links_checked: usize,
+    /// number of orphaned links marked as removed
+    pub links_removed: usize,
+}
+
+/// Order in which queued entries are published
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PublishOrder {
+    /// the order they were committed in
+    Queue,
+    /// entries after the queued entries they reference, otherwise in queue order
+    /// @see publish_dependencies()
+    Dependencies,
+}
+
+impl Default for PublishOrder {
+    fn default() -> Self {
+        PublishOrder::Dependencies
+    }
+}
+
+/// Bound on how many entries are sent to the network within any interval of the given length
+/// entries over the limit wait in the publish queue
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PublishRateLimit {
+    pub max_publishes: usize,
+    pub interval: Duration,
+}
+
+impl PublishRateLimit {
+    pub fn new(max_publishes: usize, interval: Duration) -> Self {
+        PublishRateLimit {
+            max_publishes,
+            interval,
+        }
+    }
+}
+
+/// the addresses an entry references, which peers should receive before the entry:
+/// the base and target of a link entry, or the top-level string fields of a JSON entry
+/// that are valid addresses
+pub fn publish_dependencies(entry: &Entry) -> Vec<Address> {
+    if entry.entry_type() == &EntryType::Link {
+        let link_entry = LinkEntry::from_entry(entry);
+        return vec![
+            link_entry.link().base().clone(),
+            link_entry.link().target().clone(),
+        ];
+    }
+    match serde_json::from_str(entry.value()) {
+        Ok(Value::Object(fields)) => fields
+            .values()
+            .filter_map(|value| value.as_str())
+            .filter_map(|value| value.parse::<Address>().ok())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// sorts the entries so every entry comes after the entries it references,
+/// keeping the given order otherwise
+/// references to entries that are not given are ignored
+pub fn dependency_order(entries: Vec<Entry>) -> Vec<Entry> {
+    fn visit(
+        index: usize,
+        entries: &[Entry],
+        indexes: &HashMap<Address, usize>,
+        visited: &mut HashSet<usize>,
+        ordered: &mut Vec<usize>,
+    ) {
+        // content addressing rules out cycles, this only skips entries already placed
+        if !visited.insert(index) {
+            return;
+        }
+        for dependency in publish_dependencies(&entries[index]) {
+            if let Some(dependency_index) = indexes.get(&dependency) {
+                visit(*dependency_index, entries, indexes, visited, ordered);
+            }
+        }
+        ordered.push(index);
+    }
+
+    let indexes: HashMap<Address, usize> = entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| (entry.address(), index))
+        .collect();
+    let mut visited = HashSet::new();
+    let mut ordered = Vec::new();
+    for index in 0..entries.len() {
+        visit(index, &entries, &indexes, &mut visited, &mut ordered);
+    }
+    ordered
+        .into_iter()
+        .map(|index| entries[index].clone())
+        .collect()
+}
 
 // Placeholder network module
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Default)]
 pub struct Network {
     // FIXME
+    // addresses of everything published so far
+    published: Vec<Address>,
+    // addresses of the published entries the peers didn't take
+    failed: BTreeSet<Address>,
+    // the peers, shared by the instances on the same network
+    backend: Option<Arc<NetworkBackend>>,
+}
+/// what a peer answered to a get, failing only if it refused the requester the entry
+fn answer(
+    result: Result<Option<Content>, HolochainError>,
+) -> Result<Option<Content>, HolochainError> {
+    match result {
+        Err(HolochainError::AccessDenied) => Err(HolochainError::AccessDenied),
+        Err(_) => Ok(None),
+        Ok(content) => Ok(content),
+    }
 }
+
 impl Network {
-    pub fn publish(&mut self, _content: &AddressableContent) {
-        // FIXME
+    /// a network on its own, nothing published can be fetched back
+    pub fn new() -> Self {
+        Default::default()
     }
-    pub fn publish_meta(&mut self, _meta: &EntityAttributeValue) {
-        // FIXME
+
+    /// a network reaching the peers of the backend
+    /// e.g. an InMemoryNetwork whose clones are given to several instances
+    pub fn new_on(backend: Arc<NetworkBackend>) -> Self {
+        Network {
+            published: Vec::new(),
+            failed: BTreeSet::new(),
+            backend: Some(backend),
+        }
+    }
+
+    /// the same network, without the record of what was published
+    pub fn reconnected(&self) -> Self {
+        Network {
+            published: Vec::new(),
+            failed: BTreeSet::new(),
+            backend: self.backend.clone(),
+        }
     }
 
-    pub fn get(&mut self, _address: &Address) -> Option<Content> {
+    /// records the entry as published
+    /// the peers get it from the network worker, outside of the reduce loop
+    /// @see NetworkBackend::publish_async()
+    pub fn publish(&mut self, entry: &Entry) {
         // FIXME
-        None
+        self.published.push(entry.address());
+    }
+
+    /// the peers, None for a network on its own
+    pub(crate) fn backend(&self) -> Option<Arc<NetworkBackend>> {
+        self.backend.clone()
+    }
+
+    /// records that the peers didn't take the published entry at the address
+    pub(crate) fn publish_failed(&mut self, address: &Address) {
+        self.failed.insert(address.clone());
+    }
+    pub fn published(&self) -> &Vec<Address> {
+        &self.published
+    }
+
+    /// how far the publish of the entry at the address got, as acknowledged by the peers
+    /// entries that weren't sent to the peers yet are Pending
+    pub fn publish_status(&self, address: &Address) -> PublishStatus {
+        if !self.published.contains(address) {
+            return PublishStatus::Pending;
+        }
+        if self.failed.contains(address) {
+            return PublishStatus::Failed;
+        }
+        match self
+            .backend
+            .as_ref()
+            .and_then(|backend| backend.publish_ack(address))
+        {
+            Some(PublishAck::Stored(replicas)) => PublishStatus::Acknowledged(replicas),
+            Some(PublishAck::Rejected) => PublishStatus::Failed,
+            None => PublishStatus::Pending,
+        }
+    }
+    pub fn publish_meta(&mut self, meta: &EntityAttributeValue) {
+        if let Some(ref backend) = self.backend {
+            backend.publish_meta(meta);
+        }
+    }
+
+    /// has the peers restrict the entry of the grant, a network on its own has nobody to
+    /// restrict it from
+    pub fn restrict(&mut self, grant: &AclGrant) -> Result<(), HolochainError> {
+        match self.backend {
+            Some(ref backend) => backend.restrict(grant),
+            None => Ok(()),
+        }
+    }
+
+    /// hands content that isn't an entry to the peers
+    pub fn publish_content(&mut self, content: &Content) {
+        if let Some(ref backend) = self.backend {
+            backend.publish_content(content);
+        }
+    }
+
+    /// the content at the address held by peers, entry or not
+    pub fn get_content(&self, address: &Address) -> Option<Content> {
+        self.backend
+            .as_ref()
+            .and_then(|backend| backend.get_content(address))
+    }
+
+    /// the values of the attribute of the entity held by peers, sorted
+    pub fn meta_values(&self, entity: &Address, attribute: &Attribute) -> Vec<Address> {
+        self.backend
+            .as_ref()
+            .map(|backend| backend.meta_values(entity, attribute))
+            .unwrap_or_default()
+    }
+
+    /// asks the peers picked by the routing strategy for the address of the request, in order
+    /// backends without peers answer themselves, with whatever content they hold
+    /// fails with AccessDenied if the peers refuse the requester the entry, peers failing
+    /// otherwise count as not holding it
+    /// waits while the limit has as many gets in flight as it allows, and until the peers
+    /// answered, so reducers never call it: the DHT gets through the network worker
+    /// @see NetworkBackend::get_async()
+    pub fn get(
+        &self,
+        request: &GetRequest,
+        routing: &RoutingStrategy,
+        limit: &NetworkGetLimit,
+    ) -> Result<Option<Content>, HolochainError> {
+        let backend = match self.backend {
+            Some(ref backend) => backend,
+            None => return Ok(None),
+        };
+        let _permit = limit.acquire();
+        let peers = backend.peers();
+        if peers.is_empty() {
+            return answer(block_on(backend.get_async(request.clone())));
+        }
+        for peer in routing.route(request.address(), &peers) {
+            let answered = block_on(backend.get_from_async(peer, request.clone()));
+            if let Some(content) = answer(answered)? {
+                return Ok(Some(content));
+            }
+        }
+        Ok(None)
+    }
+
+    /// whether there are peers, reachable or not, false for a network on its own
+    pub fn has_peers(&self) -> bool {
+        self.backend.is_some()
+    }
+
+    /// false if there are no peers or none of them can be reached
+    pub fn is_reachable(&self) -> bool {
+        self.backend
+            .as_ref()
+            .map(|backend| backend.is_reachable())
+            .unwrap_or(false)
+    }
+
+    /// addresses of the entries of the type held by peers, sorted
+    pub fn addresses_of_type(&self, entry_type: &EntryType) -> Vec<Address> {
+        self.backend
+            .as_ref()
+            .map(|backend| backend.addresses_of_type(entry_type))
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Network")
+            .field("published", &self.published)
+            .field("connected", &self.backend.is_some())
+            .finish()
+    }
+}
+
+impl PartialEq for Network {
+    /// networks are equal if they published the same and reach the same peers
+    fn eq(&self, other: &Network) -> bool {
+        self.published == other.published
+            && match (&self.backend, &other.backend) {
+                (Some(backend), Some(other_backend)) => Arc::ptr_eq(backend, other_backend),
+                (None, None) => true,
+                _ => false,
+            }
     }
 }
 
+/// The part of the link graph reached by DhtStore::traverse()
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GraphResult {
+    /// the addresses reached, in the order they were reached, the base left out
+    pub reachable: Vec<Address>,
+    /// the links walked, as (base, target)
+    pub edges: Vec<(Address, Address)>,
+}
+
 /// The state-slice for the DHT.
 /// Holds the agent's local shard and interacts with the network module
 #[derive(Clone, Debug, PartialEq)]
@@ -42,6 +417,29 @@ where
     meta_storage: EAVS,
     // Placeholder network module
     network: Network,
+    // addresses of committed entries waiting to be published
+    publish_queue: Vec<Address>,
+    // results of the AddLink actions
+    add_link_results: HashMap<ActionWrapper, Result<(), HolochainError>>,
+    // bounded cache of the entries fetched from the network
+    // shared with the clones of the store until one of them changes it, so reducing actions
+    // that leave it alone doesn't copy it
+    cache: Arc<EntryCache>,
+    // results of the PinEntry actions
+    pin_results: HashMap<ActionWrapper, Result<(), HolochainError>>,
+    // addresses of entries held by peers, by entry type, as learned from GetEntriesByType
+    known_addresses: HashMap<String, BTreeSet<Address>>,
+    // when the entries published within the last rate limit interval were sent, oldest first,
+    // as read from the context's clock
+    publish_times: VecDeque<Duration>,
+    // when the entries of types with a time to live expire, as read from the context's clock
+    expiries: BTreeMap<Address, Duration>,
+    // addresses of the entries marked as deleted when their time to live passed
+    expired: BTreeSet<Address>,
+    // how often each address was asked for with GetEntry
+    access_counts: HashMap<Address, u64>,
+    // what the reducers asked of the peers since the instance last took it, @see NetworkWorker
+    network_requests: Vec<NetworkRequest>,
 }
 
 impl<CAS, EAVS> DhtStore<CAS, EAVS>
@@ -52,23 +450,339 @@ where
     // LifeCycle
     // =========
     pub fn new(content_storage: CAS, meta_storage: EAVS) -> Self {
-        let network = Network {};
+        DhtStore::new_with_network(content_storage, meta_storage, Network::new())
+    }
+
+    pub fn new_with_network(content_storage: CAS, meta_storage: EAVS, network: Network) -> Self {
         DhtStore {
             content_storage,
             meta_storage,
             network,
+            publish_queue: Vec::new(),
+            add_link_results: HashMap::new(),
+            cache: Arc::new(EntryCache::default()),
+            pin_results: HashMap::new(),
+            known_addresses: HashMap::new(),
+            publish_times: VecDeque::new(),
+            expiries: BTreeMap::new(),
+            expired: BTreeSet::new(),
+            access_counts: HashMap::new(),
+            network_requests: Vec::new(),
+        }
+    }
+
+    /// copy of the store without its ephemeral parts, for persistence
+    /// the local shard, the publish queue and the expiries are kept, what the network was sent,
+    /// the cache of network entries, the addresses learned from peers, the access counts and
+    /// the results of reduced actions start fresh
+    pub fn durable(&self) -> Self {
+        let mut durable = DhtStore::new_with_network(
+            self.content_storage.clone(),
+            self.meta_storage.clone(),
+            self.network.reconnected(),
+        );
+        durable.publish_queue = self.publish_queue.clone();
+        durable.expiries = self.expiries.clone();
+        durable.expired = self.expired.clone();
+        durable
+    }
+
+    /// the same store reading and writing the given storages instead, @see State::detached()
+    pub(crate) fn with_storages(&self, content_storage: CAS, meta_storage: EAVS) -> Self {
+        DhtStore {
+            content_storage,
+            meta_storage,
+            ..self.clone()
+        }
+    }
+
+    /// copy of the store over storages of another type, e.g. staged ones
+    /// @see dht::staging
+    pub(crate) fn over_storages<C, E>(&self, content_storage: C, meta_storage: E) -> DhtStore<C, E>
+    where
+        C: ContentAddressableStorage + Sized + Clone + PartialEq,
+        E: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+    {
+        DhtStore {
+            content_storage,
+            meta_storage,
+            network: self.network.clone(),
+            publish_queue: self.publish_queue.clone(),
+            add_link_results: self.add_link_results.clone(),
+            cache: self.cache.clone(),
+            pin_results: self.pin_results.clone(),
+            known_addresses: self.known_addresses.clone(),
+            publish_times: self.publish_times.clone(),
+            expiries: self.expiries.clone(),
+            expired: self.expired.clone(),
+            access_counts: self.access_counts.clone(),
+            network_requests: self.network_requests.clone(),
         }
     }
 
     // Linking
     // =======
-    pub fn add_link(&mut self, _link: &Link) -> Result<(), HolochainError> {
-        // FIXME
-        Err(HolochainError::NotImplemented)
+    /// stores the link in the meta storage
+    /// fails with LinkLimitExceeded if the base already has max_links links with the same tag
+    pub fn add_link(
+        &mut self,
+        link: &Link,
+        max_links: Option<usize>,
+    ) -> Result<(), HolochainError> {
+        let attribute = link_attribute(link.tag());
+        if let Some(max_links) = max_links {
+            let links = self.meta_storage.fetch_eav(
+                Some(link.base().clone()),
+                Some(attribute.clone()),
+                None,
+            )?;
+            let is_new = !links.iter().any(|eav| &eav.value() == link.target());
+            if is_new && links.len() >= max_links {
+                return Err(HolochainError::LinkLimitExceeded);
+            }
+        }
+        self.meta_storage.add_eav(&EntityAttributeValue::new(
+            link.base(),
+            &attribute,
+            link.target(),
+        ))?;
+        // kept in the content storage as well so the links can be recovered from it
+        self.content_storage
+            .add(&LinkEntry::from_link(LinkActionKind::ADD, link).to_entry())
     }
 
-    pub fn remove_link(&mut self) {
-        // FIXME
+    /// fails like add_link would if the links were added one after the other, without adding
+    /// any of them
+    pub fn check_links(
+        &self,
+        links: &[&Link],
+        max_links: Option<usize>,
+    ) -> Result<(), HolochainError> {
+        let max_links = match max_links {
+            Some(max_links) => max_links,
+            None => return Ok(()),
+        };
+        let mut targets: HashMap<(Address, Attribute), HashSet<Address>> = HashMap::new();
+        for link in links {
+            let key = (link.base().clone(), link_attribute(link.tag()));
+            if !targets.contains_key(&key) {
+                let stored = self
+                    .meta_storage
+                    .fetch_eav(Some(key.0.clone()), Some(key.1.clone()), None)?
+                    .iter()
+                    .map(|eav| eav.value())
+                    .collect();
+                targets.insert(key.clone(), stored);
+            }
+            let base_targets = targets.get_mut(&key).expect("targets were just fetched");
+            if !base_targets.contains(link.target()) && base_targets.len() >= max_links {
+                return Err(HolochainError::LinkLimitExceeded);
+            }
+            base_targets.insert(link.target().clone());
+        }
+        Ok(())
+    }
+
+    /// marks the link as removed
+    /// the EAV storage is append only so a tombstone is stored next to the link
+    pub fn remove_link(&mut self, link: &Link) -> Result<(), HolochainError> {
+        self.meta_storage.add_eav(&EntityAttributeValue::new(
+            link.base(),
+            &link_tombstone_attribute(link.tag()),
+            link.target(),
+        ))?;
+        self.content_storage
+            .add(&LinkEntry::from_link(LinkActionKind::DELETE, link).to_entry())
+    }
+
+    /// the distinct tags of the links from the base, sorted
+    /// tags whose links have all been removed are left out
+    pub fn link_tags(&self, base: &Address) -> Result<Vec<String>, HolochainError> {
+        let mut targets_by_tag: BTreeMap<String, HashSet<Address>> = BTreeMap::new();
+        let mut tombstones = HashSet::new();
+        for eav in self
+            .meta_storage
+            .fetch_eav(Some(base.clone()), None, None)?
+        {
+            let attribute = eav.attribute();
+            if attribute.starts_with(&link_attribute("")) {
+                targets_by_tag
+                    .entry(attribute[link_attribute("").len()..].to_string())
+                    .or_insert_with(HashSet::new)
+                    .insert(eav.value());
+            } else if attribute.starts_with(&link_tombstone_attribute("")) {
+                tombstones.insert((
+                    attribute[link_tombstone_attribute("").len()..].to_string(),
+                    eav.value(),
+                ));
+            }
+        }
+        Ok(targets_by_tag
+            .into_iter()
+            .filter(|(tag, targets)| {
+                targets
+                    .iter()
+                    .any(|target| !tombstones.contains(&(tag.clone(), target.clone())))
+            })
+            .map(|(tag, _)| tag)
+            .collect())
+    }
+
+    /// the targets of the links with the tag from the base, sorted
+    /// removed links are left out
+    pub fn link_targets(
+        &self,
+        base: &Address,
+        tag: &str,
+    ) -> Result<BTreeSet<Address>, HolochainError> {
+        let mut targets = BTreeSet::new();
+        for eav in
+            self.meta_storage
+                .fetch_eav(Some(base.clone()), Some(link_attribute(tag)), None)?
+        {
+            targets.insert(eav.value());
+        }
+        for eav in self.meta_storage.fetch_eav(
+            Some(base.clone()),
+            Some(link_tombstone_attribute(tag)),
+            None,
+        )? {
+            targets.remove(&eav.value());
+        }
+        Ok(targets)
+    }
+
+    /// walks the links with the tag breadth first from the base, up to depth links away
+    /// addresses reached before are not walked again, so cycles end the walk
+    pub fn traverse(
+        &self,
+        base: &Address,
+        tag: &str,
+        depth: usize,
+    ) -> Result<GraphResult, HolochainError> {
+        let mut result = GraphResult::default();
+        let mut visited = HashSet::new();
+        visited.insert(base.clone());
+        let mut frontier = vec![base.clone()];
+        for _ in 0..depth {
+            let mut next = Vec::new();
+            for source in frontier.iter() {
+                for target in self.link_targets(source, tag)? {
+                    result.edges.push((source.clone(), target.clone()));
+                    if visited.insert(target.clone()) {
+                        result.reachable.push(target.clone());
+                        next.push(target);
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+        Ok(result)
+    }
+
+    /// how the meta storage answers the query, e.g. to find out why link queries are slow
+    pub fn explain_meta_query(&self, query: &EavQuery) -> Result<QueryPlan, HolochainError> {
+        self.meta_storage.explain_eav(query)
+    }
+
+    /// indexes the meta storage by the attribute, e.g. the link_attribute() of a busy tag
+    /// clones of the meta storage share their content, and so the index
+    pub fn add_meta_index(&self, attribute: &Attribute) -> Result<(), HolochainError> {
+        self.meta_storage.clone().add_attribute_index(attribute)
+    }
+
+    /// the links the meta storage holds, by base, tag and target
+    /// removed links are left out
+    pub fn links(&self) -> Result<Vec<Link>, HolochainError> {
+        let mut links = HashSet::new();
+        let mut tombstones = HashSet::new();
+        for eav in self.meta_storage.fetch_eav(None, None, None)? {
+            let attribute = eav.attribute();
+            if attribute.starts_with(&link_attribute("")) {
+                links.insert((
+                    eav.entity(),
+                    attribute[link_attribute("").len()..].to_string(),
+                    eav.value(),
+                ));
+            } else if attribute.starts_with(&link_tombstone_attribute("")) {
+                tombstones.insert((
+                    eav.entity(),
+                    attribute[link_tombstone_attribute("").len()..].to_string(),
+                    eav.value(),
+                ));
+            }
+        }
+        let mut live: Vec<(Address, String, Address)> =
+            links.difference(&tombstones).cloned().collect();
+        live.sort();
+        Ok(live
+            .iter()
+            .map(|(base, tag, target)| Link::new(base, target, tag))
+            .collect())
+    }
+
+    /// the links whose base or target was removed, i.e. is marked as DELETED or REJECTED
+    /// links to entries the DHT doesn't hold are not orphaned, peers may hold the entries
+    pub fn orphaned_links(&self) -> Result<Vec<Link>, HolochainError> {
+        let removed = CrudStatus::DELETED | CrudStatus::REJECTED;
+        let mut orphaned = Vec::new();
+        for link in self.links()? {
+            if self.crud_status(link.base())?.intersects(removed)
+                || self.crud_status(link.target())?.intersects(removed)
+            {
+                orphaned.push(link);
+            }
+        }
+        Ok(orphaned)
+    }
+
+    /// the number of links from each base that has any, whatever their tags
+    /// removed links are left out
+    pub fn link_counts(&self) -> Result<HashMap<Address, usize>, HolochainError> {
+        let mut counts = HashMap::new();
+        for link in self.links()? {
+            *counts.entry(link.base().clone()).or_insert(0) += 1;
+        }
+        Ok(counts)
+    }
+
+    /// stores the links of a link graph snapshot
+    /// Replace takes every link and tombstone out of the meta storage first, Merge keeps them
+    /// and revives the links of the snapshot that were removed
+    /// the LinkEntries in the content storage are kept either way
+    pub fn import_links(
+        &mut self,
+        links: &[Link],
+        mode: &LinkImportMode,
+    ) -> Result<(), HolochainError> {
+        match mode {
+            LinkImportMode::Replace => {
+                for eav in self.meta_storage.fetch_eav(None, None, None)? {
+                    let attribute = eav.attribute();
+                    if attribute.starts_with(&link_attribute(""))
+                        || attribute.starts_with(&link_tombstone_attribute(""))
+                    {
+                        self.meta_storage.remove_eav(&eav)?;
+                    }
+                }
+            }
+            LinkImportMode::Merge => {
+                for link in links {
+                    self.meta_storage.remove_eav(&EntityAttributeValue::new(
+                        link.base(),
+                        &link_tombstone_attribute(link.tag()),
+                        link.target(),
+                    ))?;
+                }
+            }
+        }
+        for link in links {
+            self.add_link(link, None)?;
+        }
+        Ok(())
     }
 
     pub fn get_links(
@@ -80,6 +794,287 @@ where
         Err(HolochainError::NotImplemented)
     }
 
+    // CRUD status
+    // ===========
+    /// the statuses recorded in the meta storage for the entry, LIVE if none was recorded
+    pub fn crud_status(&self, address: &Address) -> Result<CrudStatus, HolochainError> {
+        let mut crud_status = CrudStatus::default();
+        for eav in self.meta_storage.fetch_eav(
+            Some(address.clone()),
+            Some(STATUS_NAME.to_string()),
+            None,
+        )? {
+            let maybe_status: Option<CrudStatus> = self.content_storage.fetch(&eav.value())?;
+            if let Some(status) = maybe_status {
+                crud_status |= status;
+            }
+        }
+        if crud_status.is_empty() {
+            crud_status = CrudStatus::LIVE;
+        }
+        Ok(crud_status)
+    }
+
+    /// records the status for the entry in the meta storage
+    pub fn add_crud_status(
+        &mut self,
+        address: &Address,
+        crud_status: CrudStatus,
+    ) -> Result<(), HolochainError> {
+        self.content_storage.add(&crud_status)?;
+        self.meta_storage.add_eav(&EntityAttributeValue::new(
+            address,
+            &STATUS_NAME.to_string(),
+            &crud_status.address(),
+        ))
+    }
+
+    // Access control
+    // ==============
+    /// restricts getting the entry to the readers of the grant, published right away so peers
+    /// enforce the restriction as soon as they can get the entry
+    /// fails with AccessDenied if the peers refuse the grant, @see NetworkBackend::restrict()
+    pub fn restrict(&mut self, grant: &AclGrant) -> Result<(), HolochainError> {
+        self.network.restrict(grant)?;
+        for reader in grant.readers() {
+            self.meta_storage.add_eav(&EntityAttributeValue::new(
+                grant.address(),
+                &ACL_NAME.to_string(),
+                reader,
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// the agents allowed to get the entry, known locally or to peers, sorted
+    /// empty for an entry everyone can get
+    /// the peers serving a get check it, @see NetworkBackend::authorize()
+    pub fn readers(&self, address: &Address) -> Result<Vec<Address>, HolochainError> {
+        let mut readers: BTreeSet<Address> = self
+            .meta_storage
+            .fetch_eav(Some(address.clone()), Some(ACL_NAME.to_string()), None)?
+            .into_iter()
+            .map(|eav| eav.value())
+            .collect();
+        readers.extend(self.network.meta_values(address, &ACL_NAME.to_string()));
+        Ok(readers.into_iter().collect())
+    }
+
+    /// keeps the entry to the zome of the namespace, @see zome_namespace()
+    pub fn add_to_namespace(
+        &mut self,
+        address: &Address,
+        namespace: &Address,
+    ) -> Result<(), HolochainError> {
+        self.meta_storage.add_eav(&EntityAttributeValue::new(
+            address,
+            &NAMESPACE_NAME.to_string(),
+            namespace,
+        ))
+    }
+
+    /// true if the entry is in the namespace or in none, i.e. shared by all zomes
+    pub fn is_in_namespace(
+        &self,
+        address: &Address,
+        namespace: &Address,
+    ) -> Result<bool, HolochainError> {
+        let namespaces = self.meta_storage.fetch_eav(
+            Some(address.clone()),
+            Some(NAMESPACE_NAME.to_string()),
+            None,
+        )?;
+        Ok(namespaces.is_empty() || namespaces.iter().any(|eav| &eav.value() == namespace))
+    }
+
+    // Metadata
+    // ========
+    /// stores the metadata and attaches it to its entry under its key, published right away
+    /// fails with InvalidMetadataSignature if it wasn't signed by its author
+    pub fn attach_metadata(&mut self, metadata: &EntryMetadata) -> Result<(), HolochainError> {
+        metadata.verify()?;
+        self.content_storage.add(metadata)?;
+        let eav = EntityAttributeValue::new(
+            &metadata.address,
+            &metadata_attribute(&metadata.key),
+            &metadata.address(),
+        );
+        self.meta_storage.add_eav(&eav)?;
+        self.network.publish_content(&metadata.content());
+        self.network.publish_meta(&eav);
+        Ok(())
+    }
+
+    /// the metadata attached to the entry under the key, known locally or to peers, in the
+    /// order of their addresses
+    /// metadata that can't be fetched or whose signature doesn't verify is left out
+    pub fn metadata(
+        &self,
+        address: &Address,
+        key: &str,
+    ) -> Result<Vec<EntryMetadata>, HolochainError> {
+        let attribute = metadata_attribute(key);
+        let mut metadata_addresses: BTreeSet<Address> = self
+            .meta_storage
+            .fetch_eav(Some(address.clone()), Some(attribute.clone()), None)?
+            .into_iter()
+            .map(|eav| eav.value())
+            .collect();
+        metadata_addresses.extend(self.network.meta_values(address, &attribute));
+        let mut metadata = Vec::new();
+        for metadata_address in metadata_addresses {
+            let content = match self.content_storage.fetch::<Content>(&metadata_address)? {
+                Some(content) => Some(content),
+                None => self.network.get_content(&metadata_address),
+            };
+            let attached = match content.map(|content| EntryMetadata::from_json(&content)) {
+                Some(Ok(attached)) => attached,
+                _ => continue,
+            };
+            if &attached.address == address && attached.key == key && attached.verify().is_ok() {
+                metadata.push(attached);
+            }
+        }
+        Ok(metadata)
+    }
+
+    // Expiry
+    // ======
+    /// when the entries that will expire do, by address
+    pub fn expiries(&self) -> &BTreeMap<Address, Duration> {
+        &self.expiries
+    }
+    /// the entries whose time to live passed at the given time, not yet marked as deleted
+    pub fn due_expiries(&self, now: Duration) -> Vec<Address> {
+        self.expiries
+            .iter()
+            .filter(|(_, expiry)| **expiry <= now)
+            .map(|(address, _)| address.clone())
+            .collect()
+    }
+    pub(crate) fn expiries_mut(&mut self) -> &mut BTreeMap<Address, Duration> {
+        &mut self.expiries
+    }
+
+    /// the entries marked as deleted when their time to live passed, sorted
+    pub fn expired(&self) -> Vec<Address> {
+        self.expired.iter().cloned().collect()
+    }
+    pub(crate) fn expired_mut(&mut self) -> &mut BTreeSet<Address> {
+        &mut self.expired
+    }
+
+    // Secondary indexes
+    // =================
+    /// indexes the entry by the values of the given fields
+    pub fn add_field_indexes(
+        &mut self,
+        entry: &Entry,
+        field_names: &[String],
+    ) -> Result<(), HolochainError> {
+        for (field_name, field_value) in indexed_field_values(entry, field_names) {
+            self.meta_storage.add_eav(&EntityAttributeValue::new(
+                &index_entity(&field_value),
+                &index_attribute(&field_name),
+                &entry.address(),
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// fails with UniquenessViolation if another entry of the entry's type has the same value
+    /// in one of the given fields
+    /// only the entries the DHT indexed by the fields count
+    pub fn check_unique(
+        &self,
+        entry: &Entry,
+        field_names: &[String],
+    ) -> Result<(), HolochainError> {
+        for (field, value) in indexed_field_values(entry, field_names) {
+            let holders = self.find_by_field(entry.entry_type(), &field, &value)?;
+            if holders.iter().any(|holder| holder != &entry.address()) {
+                return Err(HolochainError::UniquenessViolation { field, value });
+            }
+        }
+        Ok(())
+    }
+
+    /// fails with AddressTaken if the entry is addressed by a key and another entry is held
+    /// at its address, e.g. another user with the same username
+    /// an update, by the author of the entry held, takes the address over
+    pub fn check_address_key(&self, entry: &Entry, update: bool) -> Result<(), HolochainError> {
+        if entry.address_key().is_none() || update {
+            return Ok(());
+        }
+        match self.content_storage.fetch::<Content>(&entry.address())? {
+            Some(ref held) if held != &entry.content() => Err(HolochainError::AddressTaken),
+            _ => Ok(()),
+        }
+    }
+
+    /// addresses of the entries of the given type whose indexed field has the given value
+    pub fn find_by_field(
+        &self,
+        entry_type: &EntryType,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<Vec<Address>, HolochainError> {
+        let mut addresses = Vec::new();
+        for eav in self.meta_storage.fetch_eav(
+            Some(index_entity(field_value)),
+            Some(index_attribute(field_name)),
+            None,
+        )? {
+            let maybe_entry: Option<Entry> = self.content_storage.fetch(&eav.value())?;
+            if let Some(entry) = maybe_entry {
+                if entry.entry_type() == entry_type {
+                    addresses.push(eav.value());
+                }
+            }
+        }
+        addresses.sort();
+        Ok(addresses)
+    }
+
+    // References
+    // ==========
+    /// remembers the addresses the entry references so it is found among their referrers
+    pub fn add_references(&mut self, entry: &Entry) -> Result<(), HolochainError> {
+        for referenced in publish_dependencies(entry) {
+            self.meta_storage.add_eav(&EntityAttributeValue::new(
+                &entry.address(),
+                &REFERENCES_NAME.to_string(),
+                &referenced,
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// the entries referencing the address in their content, @see publish_dependencies(),
+    /// and the bases of the links to it, sorted
+    /// removed links are left out
+    pub fn referrers(&self, address: &Address) -> Result<Vec<Address>, HolochainError> {
+        let mut referrers = BTreeSet::new();
+        for eav in self
+            .meta_storage
+            .fetch_eav(None, None, Some(address.clone()))?
+        {
+            let attribute = eav.attribute();
+            let is_referrer = if attribute == REFERENCES_NAME {
+                true
+            } else if attribute.starts_with(&link_attribute("")) {
+                let tag = &attribute[link_attribute("").len()..];
+                self.link_targets(&eav.entity(), tag)?.contains(address)
+            } else {
+                false
+            };
+            if is_referrer {
+                referrers.insert(eav.entity());
+            }
+        }
+        Ok(referrers.into_iter().collect())
+    }
+
     // Getters (for reducers)
     // =======
     pub(crate) fn content_storage(&self) -> CAS {
@@ -88,10 +1083,240 @@ where
     pub(crate) fn content_storage_mut(&mut self) -> &mut CAS {
         &mut self.content_storage
     }
-    pub(crate) fn network(&self) -> &Network {
+    /// the storage of the links and other metadata, its clones share its content
+    pub fn meta_storage(&self) -> EAVS {
+        self.meta_storage.clone()
+    }
+    pub fn network(&self) -> &Network {
         &self.network
     }
     pub(crate) fn network_mut(&mut self) -> &mut Network {
         &mut self.network
     }
+    /// sends the entry to the network unless the limit of publishes within its interval was
+    /// reached at the given time, returns whether the entry was sent
+    pub(crate) fn publish_within(
+        &mut self,
+        entry: &Entry,
+        limit: Option<PublishRateLimit>,
+        now: Duration,
+    ) -> bool {
+        if let Some(limit) = limit {
+            // a clock set back doesn't let anything out of the interval early
+            while self.publish_times.front().map_or(false, |sent| {
+                now.checked_sub(*sent)
+                    .map_or(false, |elapsed| elapsed >= limit.interval)
+            }) {
+                self.publish_times.pop_front();
+            }
+            if self.publish_times.len() >= limit.max_publishes {
+                return false;
+            }
+            self.publish_times.push_back(now);
+        }
+        self.network.publish(entry);
+        true
+    }
+
+    /// addresses of committed entries waiting for Action::FlushPublishes
+    pub fn publish_queue(&self) -> &Vec<Address> {
+        &self.publish_queue
+    }
+    pub(crate) fn publish_queue_mut(&mut self) -> &mut Vec<Address> {
+        &mut self.publish_queue
+    }
+    /// the publishes and gets the reducers asked for, in order, that the instance hasn't
+    /// handed to its network worker yet
+    pub fn network_requests(&self) -> &Vec<NetworkRequest> {
+        &self.network_requests
+    }
+    pub(crate) fn network_requests_mut(&mut self) -> &mut Vec<NetworkRequest> {
+        &mut self.network_requests
+    }
+    /// results of the AddLink actions reduced so far
+    pub fn add_link_results(&self) -> &HashMap<ActionWrapper, Result<(), HolochainError>> {
+        &self.add_link_results
+    }
+    pub(crate) fn add_link_results_mut(
+        &mut self,
+    ) -> &mut HashMap<ActionWrapper, Result<(), HolochainError>> {
+        &mut self.add_link_results
+    }
+    /// entries fetched from the network, and which of their addresses are pinned
+    pub fn cache(&self) -> &EntryCache {
+        &self.cache
+    }
+    /// the cache of this store only, copied from the other stores sharing it if needed
+    pub(crate) fn cache_mut(&mut self) -> &mut EntryCache {
+        Arc::make_mut(&mut self.cache)
+    }
+    /// how often each address was asked for with GetEntry since the instance started
+    pub fn access_counts(&self) -> &HashMap<Address, u64> {
+        &self.access_counts
+    }
+    pub(crate) fn count_access(&mut self, address: &Address) {
+        *self.access_counts.entry(address.clone()).or_insert(0) += 1;
+    }
+    /// results of the PinEntry actions reduced so far
+    pub fn pin_results(&self) -> &HashMap<ActionWrapper, Result<(), HolochainError>> {
+        &self.pin_results
+    }
+    pub(crate) fn pin_results_mut(
+        &mut self,
+    ) -> &mut HashMap<ActionWrapper, Result<(), HolochainError>> {
+        &mut self.pin_results
+    }
+    /// addresses of the entries of the type peers told about so far, sorted
+    pub fn known_addresses(&self, entry_type: &EntryType) -> Vec<Address> {
+        self.known_addresses
+            .get(&entry_type.to_string())
+            .map(|addresses| addresses.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+    /// addresses of the DHT entries this store knows of: those it published and the peers
+    /// took, those it still has to publish and those peers told about
+    pub fn held_addresses(&self) -> BTreeSet<Address> {
+        let mut held: BTreeSet<Address> = self
+            .network
+            .published()
+            .iter()
+            .filter(|address| !self.network.failed.contains(*address))
+            .cloned()
+            .collect();
+        held.extend(self.publish_queue.iter().cloned());
+        for addresses in self.known_addresses.values() {
+            held.extend(addresses.iter().cloned());
+        }
+        held
+    }
+    pub(crate) fn known_addresses_mut(&mut self, entry_type: &EntryType) -> &mut BTreeSet<Address> {
+        self.known_addresses
+            .entry(entry_type.to_string())
+            .or_insert_with(BTreeSet::new)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_cas_implementations::{
+        cas::memory::MemoryStorage, eav::memory::EavMemoryStorage,
+    };
+    use holochain_core_types::{
+        entry::{test_entry, test_entry_a, test_entry_b},
+        links_entry::LinkActionKind,
+    };
+    use state::test_store;
+
+    #[test]
+    /// link entries depend on their base and target
+    fn publish_dependencies_test() {
+        let base = test_entry_a().address();
+        let target = test_entry_b().address();
+        let link_entry = LinkEntry::new(LinkActionKind::ADD, &base, &target, "tag").to_entry();
+
+        assert_eq!(vec![base, target], publish_dependencies(&link_entry));
+        assert!(publish_dependencies(&test_entry()).is_empty());
+        assert_eq!(
+            vec![test_entry_a(), test_entry_b(), link_entry.clone()],
+            dependency_order(vec![link_entry.clone(), test_entry_a(), test_entry_b()])
+        );
+    }
+
+    #[test]
+    /// clones of the store share its cache until one of them changes it
+    fn cache_is_copied_on_write() {
+        let store = (*test_store().dht()).clone();
+        let mut clone = store.clone();
+        assert!(Arc::ptr_eq(&store.cache, &clone.cache));
+
+        clone.cache_mut().insert(test_entry());
+        assert!(!Arc::ptr_eq(&store.cache, &clone.cache));
+        assert!(clone.cache().contains(&test_entry().address()));
+        assert!(!store.cache().contains(&test_entry().address()));
+    }
+
+    /// a store with a link tagged "follows" for each pair of names
+    fn follows_store(follows: Vec<(&str, &str)>) -> DhtStore<MemoryStorage, EavMemoryStorage> {
+        let mut store = (*test_store().dht()).clone();
+        for (base, target) in follows {
+            let link = Link::new(&Address::from(base), &Address::from(target), "follows");
+            store.add_link(&link, None).expect("could not add link");
+        }
+        store
+    }
+
+    #[test]
+    /// the walk stops at the given depth and only follows links with the tag
+    fn traverse_test() {
+        let mut store = follows_store(vec![("A", "B"), ("B", "C"), ("C", "D")]);
+        store
+            .add_link(
+                &Link::new(&Address::from("A"), &Address::from("E"), "likes"),
+                None,
+            )
+            .expect("could not add link");
+        let address = |name: &str| Address::from(name);
+
+        let result = store.traverse(&address("A"), "follows", 2).unwrap();
+        assert_eq!(vec![address("B"), address("C")], result.reachable);
+        assert_eq!(
+            vec![(address("A"), address("B")), (address("B"), address("C"))],
+            result.edges
+        );
+        assert_eq!(
+            GraphResult::default(),
+            store.traverse(&address("A"), "follows", 0).unwrap()
+        );
+    }
+
+    #[test]
+    /// addresses reached before are not walked again, however deep the walk may go
+    fn traverse_cycle_test() {
+        let store = follows_store(vec![("A", "B"), ("B", "C"), ("C", "A")]);
+        let address = |name: &str| Address::from(name);
+
+        let result = store.traverse(&address("A"), "follows", 100).unwrap();
+        assert_eq!(vec![address("B"), address("C")], result.reachable);
+        assert_eq!(3, result.edges.len());
+        assert!(result.edges.contains(&(address("C"), address("A"))));
+    }
+
+    #[test]
+    /// tags are distinct, sorted and leave out removed links
+    fn link_tags_test() {
+        let mut store = (*test_store().dht()).clone();
+        let base = test_entry().address();
+        let target_a = test_entry_a().address();
+        let target_b = test_entry_b().address();
+
+        for (target, tag) in vec![
+            (&target_a, "comments"),
+            (&target_b, "comments"),
+            (&target_a, "authors"),
+            (&target_b, "likes"),
+            (&target_a, "drafts"),
+        ] {
+            store
+                .add_link(&Link::new(&base, target, tag), None)
+                .expect("could not add link");
+        }
+        store
+            .remove_link(&Link::new(&base, &target_a, "drafts"))
+            .expect("could not remove link");
+        // removing one of two links keeps the tag
+        store
+            .remove_link(&Link::new(&base, &target_a, "comments"))
+            .expect("could not remove link");
+
+        assert_eq!(
+            Ok(vec![
+                "authors".to_string(),
+                "comments".to_string(),
+                "likes".to_string()
+            ]),
+            store.link_tags(&base)
+        );
+        assert_eq!(Ok(vec![]), store.link_tags(&target_a));
+    }
 }
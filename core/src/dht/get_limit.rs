@@ -0,0 +1,216 @@
+//! A bound on the gets an instance has in flight on the network at the same time.
+//!
+//! Every GetEntry the DHT can't answer from its own storage fetches from the network, so a
+//! busy instance could open as many connections as it has pending gets. Gets over the bound
+//! wait until one of the gets in flight returns.
+//!
+//! The limits of instances hosted side by side can count their gets against a global limit
+//! as well, so that together they don't exhaust the resources of the host.
+
+use instance::PoisonPolicy;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+/// default number of gets in flight at the same time
+pub const DEFAULT_MAX_CONCURRENT_GETS: usize = 64;
+
+/// Counting semaphore shared by the clones of a limit
+#[derive(Clone, Debug)]
+pub struct NetworkGetLimit {
+    bound: Option<usize>,
+    in_flight: Arc<(Mutex<usize>, Condvar)>,
+    /// limit shared with the limits of other instances, @see NetworkGetLimit::within()
+    global: Option<Box<NetworkGetLimit>>,
+}
+
+impl Default for NetworkGetLimit {
+    fn default() -> Self {
+        NetworkGetLimit::new(Some(DEFAULT_MAX_CONCURRENT_GETS))
+    }
+}
+
+/// A get in flight, counted until it is dropped
+pub struct GetPermit<'a> {
+    limit: &'a NetworkGetLimit,
+    /// the permit of the global limit, released after this one
+    _global: Option<Box<GetPermit<'a>>>,
+}
+
+impl<'a> Drop for GetPermit<'a> {
+    fn drop(&mut self) {
+        let (_, ref returned) = *self.limit.in_flight;
+        *self.limit.lock() -= 1;
+        returned.notify_one();
+    }
+}
+
+impl NetworkGetLimit {
+    /// at most bound gets in flight, None for unlimited
+    /// a bound of 0 is taken as 1, gets would wait forever otherwise
+    pub fn new(bound: Option<usize>) -> Self {
+        NetworkGetLimit {
+            bound: bound.map(|bound| bound.max(1)),
+            in_flight: Arc::new((Mutex::new(0), Condvar::new())),
+            global: None,
+        }
+    }
+
+    /// the same limit counting its gets against the global limit as well, e.g. the limit a
+    /// conductor shares between its instances
+    /// gets wait for the instance's limit first and then for the global one
+    pub fn within(mut self, global: &NetworkGetLimit) -> Self {
+        self.global = Some(Box::new(global.clone()));
+        self
+    }
+
+    pub fn global(&self) -> Option<&NetworkGetLimit> {
+        self.global.as_ref().map(|global| &**global)
+    }
+
+    pub fn bound(&self) -> Option<usize> {
+        self.bound
+    }
+
+    /// number of gets in flight right now
+    pub fn in_flight(&self) -> usize {
+        *self.lock()
+    }
+
+    /// waits until the get can be made without going over the bound
+    pub fn acquire(&self) -> GetPermit {
+        let (_, ref returned) = *self.in_flight;
+        let mut in_flight = self.lock();
+        if let Some(bound) = self.bound {
+            while *in_flight >= bound {
+                in_flight =
+                    PoisonPolicy::default().unpoison(returned.wait(in_flight), "get limit Mutex");
+            }
+        }
+        *in_flight += 1;
+        drop(in_flight);
+        GetPermit {
+            limit: self,
+            _global: self
+                .global
+                .as_ref()
+                .map(|global| Box::new(global.acquire())),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<usize> {
+        let (ref in_flight, _) = *self.in_flight;
+        PoisonPolicy::default().unpoison(in_flight.lock(), "get limit Mutex")
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use agent::keys::KeyPair;
+    use dht::{
+        dht_store::Network,
+        network::{GetRequest, InMemoryNetwork, NetworkBackend},
+        routing::AnyPeer,
+    };
+    use holochain_core_types::{
+        cas::content::{Address, AddressableContent},
+        entry::{test_entry, Entry},
+        entry_type::EntryType,
+    };
+    use std::{thread, time::Duration};
+
+    /// the get of the test entry by bob
+    fn test_get_request() -> GetRequest {
+        GetRequest::new(
+            &test_entry().address(),
+            &Address::from("bob".to_string()),
+            &KeyPair::generate().unwrap(),
+        )
+    }
+
+    /// network taking a while to answer, recording how many gets it had at the same time
+    struct SlowNetwork {
+        peers: InMemoryNetwork,
+        /// gets in flight and the most there were at the same time
+        in_flight: Mutex<(usize, usize)>,
+    }
+
+    impl NetworkBackend for SlowNetwork {
+        fn publish(&self, entry: &Entry) {
+            self.peers.publish(entry)
+        }
+
+        fn get(&self, address: &Address) -> Option<Entry> {
+            {
+                let mut in_flight = self.in_flight.lock().unwrap();
+                in_flight.0 += 1;
+                in_flight.1 = in_flight.1.max(in_flight.0);
+            }
+            thread::sleep(Duration::from_millis(20));
+            self.in_flight.lock().unwrap().0 -= 1;
+            self.peers.get(address)
+        }
+
+        fn addresses_of_type(&self, entry_type: &EntryType) -> Vec<Address> {
+            self.peers.addresses_of_type(entry_type)
+        }
+    }
+
+    #[test]
+    /// gets over the bound queue until a get in flight returns
+    fn gets_over_bound_queue() {
+        let backend = Arc::new(SlowNetwork {
+            peers: InMemoryNetwork::new().unwrap(),
+            in_flight: Mutex::new((0, 0)),
+        });
+        backend.publish(&test_entry());
+        let network = Network::new_on(backend.clone());
+        let limit = NetworkGetLimit::new(Some(2));
+
+        let gets: Vec<_> = (0..6)
+            .map(|_| {
+                let network = network.clone();
+                let limit = limit.clone();
+                thread::spawn(move || network.get(&test_get_request(), &AnyPeer {}, &limit))
+            })
+            .collect();
+        for get in gets {
+            assert_eq!(Ok(Some(test_entry().content())), get.join().unwrap());
+        }
+
+        assert_eq!(2, backend.in_flight.lock().unwrap().1);
+        assert_eq!(0, limit.in_flight());
+    }
+
+    #[test]
+    /// the gets of two instances together stay within the global limit they share
+    fn gets_over_global_bound_queue() {
+        let backend = Arc::new(SlowNetwork {
+            peers: InMemoryNetwork::new().unwrap(),
+            in_flight: Mutex::new((0, 0)),
+        });
+        backend.publish(&test_entry());
+        let global = NetworkGetLimit::new(Some(3));
+        let instance_limits = vec![
+            NetworkGetLimit::new(Some(2)).within(&global),
+            NetworkGetLimit::new(Some(2)).within(&global),
+        ];
+
+        let gets: Vec<_> = (0..8)
+            .map(|index| {
+                let network = Network::new_on(backend.clone());
+                let limit = instance_limits[index % 2].clone();
+                thread::spawn(move || network.get(&test_get_request(), &AnyPeer {}, &limit))
+            })
+            .collect();
+        for get in gets {
+            assert_eq!(Ok(Some(test_entry().content())), get.join().unwrap());
+        }
+
+        assert_eq!(3, backend.in_flight.lock().unwrap().1);
+        assert_eq!(0, global.in_flight());
+        assert_eq!(
+            Some(Some(3)),
+            instance_limits[0].global().map(|global| global.bound())
+        );
+    }
+}
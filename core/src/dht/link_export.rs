@@ -0,0 +1,64 @@
+//! Snapshots of the link graph alone.
+//!
+//! Rebuilding the links of an instance, e.g. after fixing a bug in how they were added, only
+//! needs the links, not every entry the DHT holds as a DhtExport carries. A LinkExport holds
+//! the links of an instance and is imported again either in place of the links held or on top
+//! of them.
+
+use dht::dht_store::DhtStore;
+use holochain_core_types::{
+    cas::storage::ContentAddressableStorage,
+    eav::EntityAttributeValueStorage,
+    error::HolochainError,
+    json::{FromJson, ToJson},
+    links_entry::Link,
+};
+use serde_json;
+
+/// The links an instance held, by base, tag and target
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LinkExport {
+    links: Vec<Link>,
+}
+
+/// How importing a LinkExport treats the links held already
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LinkImportMode {
+    /// the links held are dropped, only the links of the export are left
+    Replace,
+    /// the links of the export are added to the links held
+    Merge,
+}
+
+impl LinkExport {
+    /// the links the DHT holds, removed links left out
+    pub fn of<CAS, EAVS>(dht: &DhtStore<CAS, EAVS>) -> Result<Self, HolochainError>
+    where
+        CAS: ContentAddressableStorage + Sized + Clone + PartialEq,
+        EAVS: EntityAttributeValueStorage + Sized + Clone + PartialEq,
+    {
+        Ok(LinkExport {
+            links: dht.links()?,
+        })
+    }
+
+    pub fn new(links: Vec<Link>) -> Self {
+        LinkExport { links }
+    }
+
+    pub fn links(&self) -> &[Link] {
+        &self.links
+    }
+}
+
+impl ToJson for LinkExport {
+    fn to_json(&self) -> Result<String, HolochainError> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+impl FromJson for LinkExport {
+    fn from_json(s: &str) -> Result<Self, HolochainError> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
@@ -0,0 +1,213 @@
+//! Metadata agents attach to entries, e.g. reactions, ratings or moderation flags.
+//!
+//! Attaching metadata leaves the entry as it is. Each value is stored as content of its own,
+//! signed with the Ed25519 key pair of the agent that attached it, and the entry points at it
+//! in the meta storage under the key, so any number of agents can attach values under the
+//! same key and anyone reading them can tell who attached what.
+//! The author's public key comes with the metadata; only a reader that knows which public key
+//! an agent has can tell metadata in the agent's name from metadata someone else signed and
+//! put the agent's name on, @see EntryMetadata::signed_by()
+
+use agent::keys::{verify_signature, KeyPair, PublicKey};
+use holochain_agent::Agent;
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent, Content},
+    eav::Attribute,
+    error::HolochainError,
+    json::{FromJson, ToJson},
+    signature::Signature,
+};
+use serde_json;
+
+/// EAV attribute under which the metadata with the given key is stored
+pub fn metadata_attribute(key: &str) -> Attribute {
+    format!("metadata:{}", key)
+}
+
+/// A value attached to an entry under a key, signed by the agent that attached it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EntryMetadata {
+    /// address of the entry the metadata is attached to
+    pub address: Address,
+    pub key: String,
+    pub value: String,
+    author: String,
+    author_key: PublicKey,
+    pub signature: Signature,
+}
+
+impl EntryMetadata {
+    /// the value under the key for the entry at the address, signed with the key pair of the
+    /// agent
+    pub fn new(
+        agent: &Agent,
+        key_pair: &KeyPair,
+        address: &Address,
+        key: &str,
+        value: &str,
+    ) -> Self {
+        let message = signed_message(address, key, value, agent);
+        EntryMetadata {
+            address: address.clone(),
+            key: key.to_string(),
+            value: value.to_string(),
+            author: agent.to_string(),
+            author_key: key_pair.public_key(),
+            signature: key_pair.sign(message.as_bytes()),
+        }
+    }
+
+    /// the agent that attached the metadata, as it named itself
+    pub fn author(&self) -> Agent {
+        Agent::from(self.author.clone())
+    }
+
+    /// the public key of the key pair the metadata was signed with
+    pub fn author_key(&self) -> &PublicKey {
+        &self.author_key
+    }
+
+    /// fails with InvalidMetadataSignature unless the entry address, key, value and author
+    /// were signed with the key pair of the author key
+    pub fn verify(&self) -> Result<(), HolochainError> {
+        let message = signed_message(&self.address, &self.key, &self.value, &self.author());
+        if verify_signature(&self.author_key, message.as_bytes(), &self.signature) {
+            Ok(())
+        } else {
+            Err(HolochainError::InvalidMetadataSignature)
+        }
+    }
+
+    /// true if the metadata verifies and was signed with the key pair of the public key,
+    /// i.e. was attached by the agent the reader knows to have it
+    pub fn signed_by(&self, public_key: &PublicKey) -> bool {
+        &self.author_key == public_key && self.verify().is_ok()
+    }
+}
+
+/// what the author of metadata signs, binding the value to the entry, the key and the author
+/// each is a field of its own so no value or author can be split into another key or value
+fn signed_message(address: &Address, key: &str, value: &str, author: &Agent) -> String {
+    json!({
+        "address": address.to_string(),
+        "key": key,
+        "value": value,
+        "author": author.to_string(),
+    })
+    .to_string()
+}
+
+impl AddressableContent for EntryMetadata {
+    fn content(&self) -> Content {
+        self.to_json()
+            .expect("could not convert EntryMetadata to Json Content")
+    }
+
+    fn from_content(content: &Content) -> Self {
+        EntryMetadata::from_json(content).expect("could not convert Json Content to EntryMetadata")
+    }
+}
+
+impl ToJson for EntryMetadata {
+    fn to_json(&self) -> Result<String, HolochainError> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+impl FromJson for EntryMetadata {
+    fn from_json(s: &str) -> Result<Self, HolochainError> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core_types::entry::test_entry_address;
+
+    #[test]
+    /// metadata only verifies with the entry, key, value and author it was signed for
+    fn metadata_signature_test() {
+        let alex = Agent::from("alex".to_string());
+        let alex_key_pair = KeyPair::generate().unwrap();
+        let metadata =
+            EntryMetadata::new(&alex, &alex_key_pair, &test_entry_address(), "rating", "5");
+        assert_eq!(Ok(()), metadata.verify());
+        assert!(metadata.signed_by(&alex_key_pair.public_key()));
+        assert_eq!(alex, metadata.author());
+        assert_eq!(
+            Ok(metadata.clone()),
+            EntryMetadata::from_json(&metadata.to_json().unwrap())
+        );
+
+        let mut tampered = metadata.clone();
+        tampered.value = "1".to_string();
+        assert_eq!(
+            Err(HolochainError::InvalidMetadataSignature),
+            tampered.verify()
+        );
+        let mut forged = metadata.clone();
+        forged.author = "billie".to_string();
+        assert_eq!(
+            Err(HolochainError::InvalidMetadataSignature),
+            forged.verify()
+        );
+    }
+
+    #[test]
+    /// a signature doesn't carry over to another split of the key, value and author
+    fn metadata_signature_resplit_test() {
+        let alex = Agent::from("alex".to_string());
+        let alex_key_pair = KeyPair::generate().unwrap();
+        let metadata = EntryMetadata::new(
+            &alex,
+            &alex_key_pair,
+            &test_entry_address(),
+            "rating",
+            "5:x",
+        );
+        assert_eq!(Ok(()), metadata.verify());
+
+        // key "rating:5" with value "x", and value "5" attached by "x:alex"
+        let mut other_key = metadata.clone();
+        other_key.key = "rating:5".to_string();
+        other_key.value = "x".to_string();
+        let mut other_author = metadata.clone();
+        other_author.value = "5".to_string();
+        other_author.author = "x:alex".to_string();
+        for resplit in vec![other_key, other_author] {
+            assert_eq!(
+                Err(HolochainError::InvalidMetadataSignature),
+                resplit.verify()
+            );
+        }
+    }
+
+    #[test]
+    /// metadata someone else signed in alex's name isn't taken as alex's
+    fn metadata_forged_in_another_name_test() {
+        let alex = Agent::from("alex".to_string());
+        let alex_key = KeyPair::generate().unwrap().public_key();
+        let mallory_key_pair = KeyPair::generate().unwrap();
+
+        // signed with mallory's key pair, carrying mallory's public key
+        let forged = EntryMetadata::new(
+            &alex,
+            &mallory_key_pair,
+            &test_entry_address(),
+            "rating",
+            "1",
+        );
+        assert_eq!(alex, forged.author());
+        assert!(!forged.signed_by(&alex_key));
+
+        // or claiming alex's public key, which mallory can't sign for
+        let mut forged = forged.clone();
+        forged.author_key = alex_key.clone();
+        assert_eq!(
+            Err(HolochainError::InvalidMetadataSignature),
+            forged.verify()
+        );
+        assert!(!forged.signed_by(&alex_key));
+    }
+}
@@ -1,4 +1,14 @@
 //! DHT is the module that handles the agent's local shard of data and p2p communications
 
+pub mod cache;
+pub mod dht_export;
 pub mod dht_reducers;
 pub mod dht_store;
+pub mod get_limit;
+pub mod link_export;
+pub mod metadata;
+pub mod network;
+pub mod network_worker;
+pub mod routing;
+pub mod staging;
+pub mod storage_routes;
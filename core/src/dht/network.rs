@@ -0,0 +1,674 @@
+//! The contract between an instance and the peers it publishes to and queries.
+//!
+//! There is no peer to peer transport yet: InMemoryNetwork stands in for one in tests,
+//! with every instance holding a clone of it seeing what the others published.
+//!
+//! Reducers run inside the reduce loop and must not wait on peers. They only record the
+//! entries to publish and the entries to get in the store, the instance hands those of the
+//! state it keeps to its network worker, which drives the futures of publish_async() and
+//! get_async() and reports the outcomes back with ReturnPublishResult and ReturnGetResult
+//! actions, @see NetworkWorker
+//!
+//! The peers serving a get decide whether the agent asking may have the entry. A get is
+//! signed by the agent asking and only answered with an entry that has an access control list
+//! if the signature verifies with the key the peers trust for the agent and the agent is on
+//! the list. The address of an agent doesn't derive from its key, so a key pair of its own
+//! proves nothing about who holds it: the peers only trust a key for an agent that was
+//! vouched for out of band, e.g. by whoever admits agents to the network, or that was
+//! registered with a signature of the key they trusted for the agent before.
+//! The access control list of an entry is the author's alone: the peers only take one signed
+//! by the first agent to restrict the entry, and only before they hold the entry unrestricted.
+
+use agent::keys::{verify_signature, KeyPair, PublicKey};
+use dht::dht_store::ACL_NAME;
+use futures::future::{self, Future};
+use holochain_cas_implementations::{cas::memory::MemoryStorage, eav::memory::EavMemoryStorage};
+use holochain_core_types::{
+    cas::{
+        content::{Address, AddressableContent, Content},
+        storage::ContentAddressableStorage,
+    },
+    eav::{Attribute, EntityAttributeValue, EntityAttributeValueStorage},
+    entry::Entry,
+    entry_type::EntryType,
+    error::HolochainError,
+    hash::HashString,
+    signature::Signature,
+};
+use instance::PoisonPolicy;
+use multihash::Hash;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// What the peers answered to the publish of an entry
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PublishAck {
+    /// stored by the given number of peers
+    Stored(usize),
+    /// refused or lost by the peers
+    Rejected,
+}
+
+/// How far the publish of an entry got, @see Network::publish_status()
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PublishStatus {
+    /// not sent yet, or the peers haven't answered
+    Pending,
+    /// held by the given number of peers
+    Acknowledged(usize),
+    /// the peers didn't store the entry
+    Failed,
+}
+
+/// the address identifying the DHT the instances of the DNA share on the named network
+/// instances with different ids don't see each other's data
+pub fn network_id(dna_address: &Address, network_name: &str) -> Address {
+    HashString::encode_from_serializable((dna_address, network_name), Hash::SHA2256)
+}
+
+/// A network operation in flight, resolving once the peers answered
+pub type NetworkFuture<T> = Box<Future<Item = T, Error = HolochainError> + Send>;
+
+/// A get of the entry at an address, signed by the agent asking for it
+#[derive(Clone, Debug, PartialEq)]
+pub struct GetRequest {
+    address: Address,
+    requester: Address,
+    signature: Signature,
+}
+
+impl GetRequest {
+    /// the get of the address by the agent at the requester address, signed with its key pair
+    pub fn new(address: &Address, requester: &Address, key_pair: &KeyPair) -> Self {
+        GetRequest {
+            address: address.clone(),
+            requester: requester.clone(),
+            signature: key_pair.sign(GetRequest::signed(address, requester).as_bytes()),
+        }
+    }
+
+    fn signed(address: &Address, requester: &Address) -> String {
+        format!("get:{}:{}", address, requester)
+    }
+
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    pub fn requester(&self) -> &Address {
+        &self.requester
+    }
+
+    /// true if the request was signed with the key pair of the public key
+    pub fn signed_by(&self, public_key: &PublicKey) -> bool {
+        verify_signature(
+            public_key,
+            GetRequest::signed(&self.address, &self.requester).as_bytes(),
+            &self.signature,
+        )
+    }
+}
+
+/// The claim of the agent at an address to sign with a public key from now on, signed with
+/// the key pair the peers trust for the agent so far, e.g. to rotate to a new key pair
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyRegistration {
+    agent: Address,
+    public_key: PublicKey,
+    signer: PublicKey,
+    signature: Signature,
+}
+
+impl KeyRegistration {
+    /// the registration of the public key for the agent at the address, signed with the key
+    /// pair of the signer
+    pub fn new(agent: &Address, public_key: &PublicKey, signer: &KeyPair) -> Self {
+        KeyRegistration {
+            agent: agent.clone(),
+            public_key: public_key.clone(),
+            signer: signer.public_key(),
+            signature: signer.sign(KeyRegistration::signed(agent, public_key).as_bytes()),
+        }
+    }
+
+    fn signed(agent: &Address, public_key: &PublicKey) -> String {
+        format!("register:{}:{}", agent, public_key.to_string())
+    }
+
+    pub fn agent(&self) -> &Address {
+        &self.agent
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// the public key of the key pair the registration claims to be signed with
+    pub fn signer(&self) -> &PublicKey {
+        &self.signer
+    }
+
+    /// true if the registration was signed with the key pair of its signer
+    pub fn is_signed(&self) -> bool {
+        verify_signature(
+            &self.signer,
+            KeyRegistration::signed(&self.agent, &self.public_key).as_bytes(),
+            &self.signature,
+        )
+    }
+}
+
+/// The access control list the author of the entry at an address gives it, signed with the
+/// author's key pair
+#[derive(Clone, Debug, PartialEq)]
+pub struct AclGrant {
+    address: Address,
+    readers: Vec<Address>,
+    author: Address,
+    signature: Signature,
+}
+
+impl AclGrant {
+    /// the grant of the entry at the address to the readers by the agent at the author
+    /// address, signed with its key pair
+    pub fn new(
+        address: &Address,
+        readers: &[Address],
+        author: &Address,
+        key_pair: &KeyPair,
+    ) -> Self {
+        AclGrant {
+            address: address.clone(),
+            readers: readers.to_vec(),
+            author: author.clone(),
+            signature: key_pair.sign(AclGrant::signed(address, readers, author).as_bytes()),
+        }
+    }
+
+    fn signed(address: &Address, readers: &[Address], author: &Address) -> String {
+        let readers: Vec<String> = readers.iter().map(|reader| reader.to_string()).collect();
+        json!({
+            "address": address.to_string(),
+            "readers": readers,
+            "author": author.to_string(),
+        })
+        .to_string()
+    }
+
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    pub fn readers(&self) -> &Vec<Address> {
+        &self.readers
+    }
+
+    pub fn author(&self) -> &Address {
+        &self.author
+    }
+
+    /// true if the grant was signed with the key pair of the public key
+    pub fn signed_by(&self, public_key: &PublicKey) -> bool {
+        verify_signature(
+            public_key,
+            AclGrant::signed(&self.address, &self.readers, &self.author).as_bytes(),
+            &self.signature,
+        )
+    }
+}
+
+/// What an instance can ask of its peers.
+/// One backend is shared by all the instances on the same network.
+pub trait NetworkBackend: Send + Sync {
+    /// hands the entry to the peers
+    fn publish(&self, entry: &Entry);
+    /// hands the entry to the peers, resolving once they took it
+    /// this is what the DHT publishes with, never from the reduce loop; backends doing real
+    /// I/O return a future that doesn't block, the default runs the blocking publish()
+    fn publish_async(&self, entry: Entry) -> NetworkFuture<()> {
+        self.publish(&entry);
+        Box::new(future::ok(()))
+    }
+    /// the entry at the address, if a peer holds it
+    fn get(&self, address: &Address) -> Option<Entry>;
+    /// the content a peer answers with for the address, which isn't necessarily an entry
+    fn get_content(&self, address: &Address) -> Option<Content> {
+        self.get(address).map(|entry| entry.content())
+    }
+    /// the content a peer answers with for the request, resolving once it answered
+    /// this is what the DHT gets with, never from the reduce loop; backends doing real I/O
+    /// return a future that doesn't block, the default runs the blocking get_content() once
+    /// authorize() let the request through
+    fn get_async(&self, request: GetRequest) -> NetworkFuture<Option<Content>> {
+        Box::new(future::result(
+            self.authorize(&request)
+                .map(|_| self.get_content(request.address())),
+        ))
+    }
+    /// fails with AccessDenied unless the requester may have the entry of the request: it has
+    /// no access control list or the requester is on it and signed the request with the key
+    /// the peers trust for it
+    /// this is what the peers serving a get check, the requester can't skip it
+    fn authorize(&self, request: &GetRequest) -> Result<(), HolochainError> {
+        let readers = self.meta_values(request.address(), &ACL_NAME.to_string());
+        if readers.is_empty() {
+            return Ok(());
+        }
+        let signed = self
+            .public_key_of(request.requester())
+            .map(|public_key| request.signed_by(&public_key))
+            .unwrap_or(false);
+        if signed && readers.contains(request.requester()) {
+            Ok(())
+        } else {
+            Err(HolochainError::AccessDenied)
+        }
+    }
+    /// has the peers trust the public key of the registration for its agent from now on
+    /// registrations that aren't signed with the key the peers trust for the agent so far are
+    /// refused with AccessDenied, as are those of agents the peers trust no key of: how the
+    /// first key of an agent comes to be trusted is up to the backend, e.g.
+    /// InMemoryNetwork::trust_key()
+    fn register_key(&self, _registration: &KeyRegistration) -> Result<(), HolochainError> {
+        Err(HolochainError::AccessDenied)
+    }
+    /// has the peers restrict getting the entry of the grant to its readers
+    /// grants that aren't signed with the key the peers trust for their author are refused
+    /// with AccessDenied, as are those for an entry another author restricted already or the
+    /// peers hold unrestricted; backends that can't enforce access control refuse every grant,
+    /// so no entry meant to be restricted is published to them
+    fn restrict(&self, _grant: &AclGrant) -> Result<(), HolochainError> {
+        Err(HolochainError::AccessDenied)
+    }
+    /// the public key the peers trust for the agent at the address, None if they trust none
+    /// backends without keys only answer gets of entries without an access control list
+    fn public_key_of(&self, _agent: &Address) -> Option<PublicKey> {
+        None
+    }
+    /// addresses of the entries of the type the peers hold, sorted
+    fn addresses_of_type(&self, entry_type: &EntryType) -> Vec<Address>;
+    /// the peers gets can be routed to, empty if the backend answers gets itself
+    fn peers(&self) -> Vec<Address> {
+        Vec::new()
+    }
+    /// the entry at the address, if the given peer holds it
+    fn get_from(&self, _peer: &Address, address: &Address) -> Option<Entry> {
+        self.get(address)
+    }
+    /// the content the given peer answers with for the request, like get_async()
+    fn get_from_async(&self, peer: Address, request: GetRequest) -> NetworkFuture<Option<Content>> {
+        Box::new(future::result(self.authorize(&request).map(|_| {
+            self.get_from(&peer, request.address())
+                .map(|entry| entry.content())
+        })))
+    }
+    /// hands the meta data to the peers
+    /// access control lists are only taken through restrict()
+    fn publish_meta(&self, _meta: &EntityAttributeValue) {}
+    /// hands content that isn't an entry to the peers, e.g. the metadata attached to an entry
+    /// peers answer gets of its address with get_content()
+    fn publish_content(&self, _content: &Content) {}
+    /// the values of the attribute of the entity the peers hold, sorted
+    fn meta_values(&self, _entity: &Address, _attribute: &Attribute) -> Vec<Address> {
+        Vec::new()
+    }
+    /// false while no peer can be reached, e.g. during a network partition
+    fn is_reachable(&self) -> bool {
+        true
+    }
+    /// the peers' answer to the publish of the entry at the address, None while they
+    /// haven't answered or if the backend doesn't acknowledge publishes
+    fn publish_ack(&self, _address: &Address) -> Option<PublishAck> {
+        None
+    }
+}
+
+/// Network where peers are the clones of an in-memory storage
+/// gets without a request only find the entries everyone may have
+#[derive(Clone)]
+pub struct InMemoryNetwork {
+    storage: MemoryStorage,
+    meta_storage: EavMemoryStorage,
+    addresses_by_type: Arc<Mutex<HashMap<String, BTreeSet<Address>>>>,
+    partitioned: Arc<AtomicBool>,
+    keys: Arc<Mutex<HashMap<Address, PublicKey>>>,
+    // the author of the access control list of each restricted entry
+    acl_authors: Arc<Mutex<HashMap<Address, Address>>>,
+}
+
+impl InMemoryNetwork {
+    pub fn new() -> Result<Self, HolochainError> {
+        Ok(InMemoryNetwork {
+            storage: MemoryStorage::new()?,
+            meta_storage: EavMemoryStorage::new()?,
+            addresses_by_type: Arc::new(Mutex::new(HashMap::new())),
+            partitioned: Arc::new(AtomicBool::new(false)),
+            keys: Arc::new(Mutex::new(HashMap::new())),
+            acl_authors: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// true if the entry at the address has an access control list
+    fn is_restricted(&self, address: &Address) -> bool {
+        !self.meta_values(address, &ACL_NAME.to_string()).is_empty()
+    }
+
+    /// the content at the address, restricted or not
+    fn fetch_content(&self, address: &Address) -> Option<Content> {
+        if !self.is_reachable() {
+            return None;
+        }
+        self.storage.fetch(address).ok().and_then(|content| content)
+    }
+
+    /// vouches for the public key of the agent at the address, for every clone, e.g. on behalf
+    /// of whoever admits agents to the network: gets signed with it are answered as the
+    /// agent's, and it signs the registrations of the agent's next keys
+    /// replaces the key trusted for the agent so far
+    pub fn trust_key(&self, agent: &Address, public_key: PublicKey) {
+        PoisonPolicy::default()
+            .unpoison(self.keys.lock(), "keys Mutex")
+            .insert(agent.clone(), public_key);
+    }
+
+    /// simulates a network partition: while partitioned, gets find nothing and the network
+    /// reports itself unreachable, for every clone
+    pub fn partition(&self, partitioned: bool) {
+        self.partitioned.store(partitioned, Ordering::SeqCst);
+    }
+}
+
+impl NetworkBackend for InMemoryNetwork {
+    fn publish(&self, entry: &Entry) {
+        self.storage
+            .clone()
+            .add(entry)
+            .expect("in-memory network storage should accept content");
+        PoisonPolicy::default()
+            .unpoison(self.addresses_by_type.lock(), "type index Mutex")
+            .entry(entry.entry_type().to_string())
+            .or_insert_with(BTreeSet::new)
+            .insert(entry.address());
+    }
+
+    /// the peers take the entry when the future is first polled
+    fn publish_async(&self, entry: Entry) -> NetworkFuture<()> {
+        let network = self.clone();
+        Box::new(future::lazy(move |_| -> Result<(), HolochainError> {
+            network.publish(&entry);
+            Ok(())
+        }))
+    }
+
+    fn get(&self, address: &Address) -> Option<Entry> {
+        if !self.is_reachable() || self.is_restricted(address) {
+            return None;
+        }
+        self.storage.fetch(address).ok().and_then(|entry| entry)
+    }
+
+    fn get_content(&self, address: &Address) -> Option<Content> {
+        if self.is_restricted(address) {
+            return None;
+        }
+        self.fetch_content(address)
+    }
+
+    /// the peers are asked when the future is first polled
+    fn get_async(&self, request: GetRequest) -> NetworkFuture<Option<Content>> {
+        let network = self.clone();
+        Box::new(future::lazy(
+            move |_| -> Result<Option<Content>, HolochainError> {
+                network.authorize(&request)?;
+                Ok(network.fetch_content(request.address()))
+            },
+        ))
+    }
+
+    fn register_key(&self, registration: &KeyRegistration) -> Result<(), HolochainError> {
+        let agent = registration.agent();
+        let mut keys = PoisonPolicy::default().unpoison(self.keys.lock(), "keys Mutex");
+        if keys.get(agent) != Some(registration.signer()) || !registration.is_signed() {
+            return Err(HolochainError::AccessDenied);
+        }
+        keys.insert(agent.clone(), registration.public_key().clone());
+        Ok(())
+    }
+
+    /// the first author restricting an entry owns its access control list from then on
+    fn restrict(&self, grant: &AclGrant) -> Result<(), HolochainError> {
+        let signed = self
+            .public_key_of(grant.author())
+            .map(|public_key| grant.signed_by(&public_key))
+            .unwrap_or(false);
+        if !signed {
+            return Err(HolochainError::AccessDenied);
+        }
+        let mut acl_authors =
+            PoisonPolicy::default().unpoison(self.acl_authors.lock(), "ACL authors Mutex");
+        match acl_authors.get(grant.address()) {
+            Some(author) if author != grant.author() => return Err(HolochainError::AccessDenied),
+            Some(_) => (),
+            None => {
+                if self.storage.contains(grant.address())? {
+                    return Err(HolochainError::AccessDenied);
+                }
+            }
+        }
+        acl_authors.insert(grant.address().clone(), grant.author().clone());
+        for reader in grant.readers() {
+            self.meta_storage
+                .clone()
+                .add_eav(&EntityAttributeValue::new(
+                    grant.address(),
+                    &ACL_NAME.to_string(),
+                    reader,
+                ))?;
+        }
+        Ok(())
+    }
+
+    fn public_key_of(&self, agent: &Address) -> Option<PublicKey> {
+        PoisonPolicy::default()
+            .unpoison(self.keys.lock(), "keys Mutex")
+            .get(agent)
+            .cloned()
+    }
+
+    fn addresses_of_type(&self, entry_type: &EntryType) -> Vec<Address> {
+        PoisonPolicy::default()
+            .unpoison(self.addresses_by_type.lock(), "type index Mutex")
+            .get(&entry_type.to_string())
+            .map(|addresses| addresses.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn publish_meta(&self, meta: &EntityAttributeValue) {
+        if meta.attribute() == ACL_NAME {
+            return;
+        }
+        self.meta_storage
+            .clone()
+            .add_eav(meta)
+            .expect("in-memory network meta storage should accept meta data");
+    }
+
+    fn publish_content(&self, content: &Content) {
+        self.storage
+            .clone()
+            .add(content)
+            .expect("in-memory network storage should accept content");
+    }
+
+    fn meta_values(&self, entity: &Address, attribute: &Attribute) -> Vec<Address> {
+        let values: BTreeSet<Address> = self
+            .meta_storage
+            .fetch_eav(Some(entity.clone()), Some(attribute.clone()), None)
+            .map(|eavs| eavs.into_iter().map(|eav| eav.value()).collect())
+            .unwrap_or_default();
+        values.into_iter().collect()
+    }
+
+    fn is_reachable(&self) -> bool {
+        !self.partitioned.load(Ordering::SeqCst)
+    }
+
+    /// the shared storage is the only replica
+    fn publish_ack(&self, address: &Address) -> Option<PublishAck> {
+        match self.storage.contains(address) {
+            Ok(true) => Some(PublishAck::Stored(1)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use holochain_core_types::entry::{test_entry, test_entry_type};
+
+    #[test]
+    /// the peers only hand an entry with an access control list to the agents on it, as
+    /// proven by the signature of their get
+    fn serving_peers_enforce_access_control() {
+        let network = InMemoryNetwork::new().unwrap();
+        let address = test_entry().address();
+        let alex = Address::from("alex".to_string());
+        let mallory = Address::from("mallory".to_string());
+        let alex_keys = KeyPair::generate().unwrap();
+        let mallory_keys = KeyPair::generate().unwrap();
+        network.trust_key(&alex, alex_keys.public_key());
+        network.trust_key(&mallory, mallory_keys.public_key());
+        assert_eq!(
+            Ok(()),
+            network.restrict(&AclGrant::new(&address, &[alex.clone()], &alex, &alex_keys))
+        );
+        network.publish(&test_entry());
+
+        assert_eq!(
+            Ok(Some(test_entry().content())),
+            block_on(network.get_async(GetRequest::new(&address, &alex, &alex_keys)))
+        );
+        // gets without a request don't find it
+        assert_eq!(None, network.get(&address));
+        assert_eq!(None, network.get_content(&address));
+        // nor do agents that aren't on the list, or claim to be one that is
+        assert_eq!(
+            Err(HolochainError::AccessDenied),
+            block_on(network.get_async(GetRequest::new(&address, &mallory, &mallory_keys)))
+        );
+        assert_eq!(
+            Err(HolochainError::AccessDenied),
+            block_on(network.get_async(GetRequest::new(&address, &alex, &mallory_keys)))
+        );
+    }
+
+    #[test]
+    /// only the author of an entry's access control list changes it, and an entry the peers
+    /// hold unrestricted can't be restricted, so nobody locks others out of public content
+    fn acl_belongs_to_its_author() {
+        let network = InMemoryNetwork::new().unwrap();
+        let address = test_entry().address();
+        let alex = Address::from("alex".to_string());
+        let mallory = Address::from("mallory".to_string());
+        let alex_keys = KeyPair::generate().unwrap();
+        let mallory_keys = KeyPair::generate().unwrap();
+        network.trust_key(&alex, alex_keys.public_key());
+        network.trust_key(&mallory, mallory_keys.public_key());
+        let readers = |address: &Address| network.meta_values(address, &ACL_NAME.to_string());
+
+        // grants not signed by their author's trusted key are refused
+        let forged = AclGrant::new(&address, &[mallory.clone()], &alex, &mallory_keys);
+        assert_eq!(Err(HolochainError::AccessDenied), network.restrict(&forged));
+        // nor are meta data standing in for a grant taken
+        network.publish_meta(&EntityAttributeValue::new(
+            &address,
+            &ACL_NAME.to_string(),
+            &mallory,
+        ));
+        assert!(readers(&address).is_empty());
+
+        // once alex restricted the entry, mallory can't add itself to the list
+        let grant = AclGrant::new(&address, &[alex.clone()], &alex, &alex_keys);
+        assert_eq!(Ok(()), network.restrict(&grant));
+        network.publish(&test_entry());
+        let squatting = AclGrant::new(&address, &[mallory.clone()], &mallory, &mallory_keys);
+        assert_eq!(
+            Err(HolochainError::AccessDenied),
+            network.restrict(&squatting)
+        );
+        assert_eq!(vec![alex.clone()], readers(&address));
+
+        // nor restrict an entry the peers hold unrestricted
+        let public = Entry::new(&test_entry_type(), &"public".to_string());
+        network.publish(&public);
+        let squatting = AclGrant::new(
+            &public.address(),
+            &[mallory.clone()],
+            &mallory,
+            &mallory_keys,
+        );
+        assert_eq!(
+            Err(HolochainError::AccessDenied),
+            network.restrict(&squatting)
+        );
+        assert_eq!(Some(public.clone()), network.get(&public.address()));
+    }
+
+    #[test]
+    /// a key is only registered for an agent with a signature of the key trusted for it, so
+    /// no other key pair takes over the agent, whether the peers trust a key for it yet or not
+    fn key_registration_needs_trusted_key() {
+        let network = InMemoryNetwork::new().unwrap();
+        let alex = Address::from("alex".to_string());
+        let billie = Address::from("billie".to_string());
+        let alex_keys = KeyPair::generate().unwrap();
+        let mallory_keys = KeyPair::generate().unwrap();
+        network.trust_key(&alex, alex_keys.public_key());
+
+        // mallory registers a key pair of its own for alex, or for billie before billie does
+        for agent in vec![&alex, &billie] {
+            let squatting = KeyRegistration::new(agent, &mallory_keys.public_key(), &mallory_keys);
+            assert!(squatting.is_signed());
+            assert_eq!(
+                Err(HolochainError::AccessDenied),
+                network.register_key(&squatting)
+            );
+        }
+        assert_eq!(Some(alex_keys.public_key()), network.public_key_of(&alex));
+        assert_eq!(None, network.public_key_of(&billie));
+
+        // nor does claiming to be signed by the trusted key do
+        let mut forged = KeyRegistration::new(&alex, &mallory_keys.public_key(), &mallory_keys);
+        forged.signer = alex_keys.public_key();
+        assert!(!forged.is_signed());
+        assert_eq!(
+            Err(HolochainError::AccessDenied),
+            network.register_key(&forged)
+        );
+        // and a registration signed for another agent doesn't carry over
+        network.trust_key(&billie, alex_keys.public_key());
+        let mut moved = KeyRegistration::new(&alex, &mallory_keys.public_key(), &alex_keys);
+        moved.agent = billie.clone();
+        assert_eq!(
+            Err(HolochainError::AccessDenied),
+            network.register_key(&moved)
+        );
+
+        // the trusted key rotates to the next one, which then is the only one trusted
+        let next_keys = KeyPair::generate().unwrap();
+        let rotation = KeyRegistration::new(&alex, &next_keys.public_key(), &alex_keys);
+        assert_eq!(Ok(()), network.register_key(&rotation));
+        assert_eq!(Some(next_keys.public_key()), network.public_key_of(&alex));
+        assert_eq!(
+            Err(HolochainError::AccessDenied),
+            network.register_key(&rotation)
+        );
+    }
+}
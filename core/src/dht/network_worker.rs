@@ -0,0 +1,136 @@
+//! The threads an instance talks to its peers on.
+//!
+//! Reducers never wait on the network, they record what they want from the peers in the DHT
+//! store as NetworkRequests. Once a reduced state has become the state of the instance, the
+//! instance takes them out of it and hands them to its worker, so the requests of states that
+//! are thrown away, e.g. by the check of a transaction or a replay, never reach the peers.
+//! One thread publishes the entries one after the other, in the order they were requested,
+//! another one gets, so a slow publish doesn't hold up gets. Gets are signed by the agent
+//! of the instance, the peers decide whether it may have the entry. The outcomes come back as
+//! ReturnPublishResult and ReturnGetResult actions.
+
+use action::{Action, ActionWrapper};
+use context::Context;
+use dht::{dht_store::Network, network::GetRequest};
+use futures::executor::block_on;
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent, Content},
+    entry::Entry,
+    error::HolochainError,
+};
+use std::{
+    sync::{
+        mpsc::{channel, Sender},
+        Arc,
+    },
+    thread,
+};
+
+/// What a reducer asks of the peers
+#[derive(Clone, Debug, PartialEq)]
+pub enum NetworkRequest {
+    /// hand the entry to the peers
+    Publish(Entry),
+    /// get the content at the address for the GetEntry action
+    Get(ActionWrapper, Address),
+}
+
+/// asks the peers of the network for the address in the name of the agent of the context,
+/// with a request signed with its key pair, @see Network::get()
+/// the peers only answer it with entries that have an access control list if they trust the
+/// public key of the key pair for the agent, @see NetworkBackend::register_key()
+pub fn signed_get(
+    context: &Context,
+    network: &Network,
+    address: &Address,
+) -> Result<Option<Content>, HolochainError> {
+    if network.backend().is_none() {
+        return Ok(None);
+    }
+    let key_pair = context.keystore.key_pair(&context.agent)?;
+    let requester = context.agent.address();
+    network.get(
+        &GetRequest::new(address, &requester, &key_pair),
+        &*context.routing_strategy,
+        &context.network_get_limit,
+    )
+}
+
+/// the entry in the content a peer answered with, if it is the entry at the address
+/// peers can be buggy or hostile, what they answer is never trusted: the address key of the
+/// entry has to be the one its content derives, @see Context::verify_address_key()
+pub fn entry_from_peer(
+    context: &Context,
+    address: &Address,
+    content: &Content,
+) -> Result<Entry, HolochainError> {
+    let entry = Entry::try_from_content(content)?;
+    context.verify_address_key(&entry)?;
+    if &entry.address() == address {
+        Ok(entry)
+    } else {
+        Err(HolochainError::ErrorGeneric(format!(
+            "got entry {} instead",
+            entry.address()
+        )))
+    }
+}
+
+/// The threads of an instance publishing to and getting from its peers
+/// dropping it lets them finish what they were handed and end
+pub struct NetworkWorker {
+    publishes: Sender<(Network, Entry)>,
+    gets: Sender<(Network, ActionWrapper, Address)>,
+}
+
+impl NetworkWorker {
+    /// starts the threads, which dispatch the outcomes to the action channel of the context
+    pub fn start(context: Arc<Context>) -> Self {
+        let (publishes, publish_receiver) = channel::<(Network, Entry)>();
+        let action_channel = context.action_channel.clone();
+        thread::spawn(move || {
+            for (network, entry) in publish_receiver {
+                let backend = match network.backend() {
+                    Some(backend) => backend,
+                    None => continue,
+                };
+                let result = block_on(backend.publish_async(entry.clone()));
+                // nobody records the result once the instance is gone
+                let _ = action_channel.send(ActionWrapper::new(Action::ReturnPublishResult(
+                    entry.address(),
+                    result,
+                )));
+            }
+        });
+
+        let (gets, get_receiver) = channel::<(Network, ActionWrapper, Address)>();
+        thread::spawn(move || {
+            for (network, get, address) in get_receiver {
+                let content = signed_get(&context, &network, &address);
+                let _ = context
+                    .action_channel
+                    .send(ActionWrapper::new(Action::ReturnGetResult(
+                        Box::new(get),
+                        content,
+                    )));
+            }
+        });
+
+        NetworkWorker { publishes, gets }
+    }
+
+    /// hands the requests to the threads, in order
+    pub fn send(&self, network: &Network, requests: Vec<NetworkRequest>) {
+        for request in requests {
+            // the threads only end once the worker is dropped
+            match request {
+                NetworkRequest::Publish(entry) => {
+                    let _ = self.publishes.send((network.clone(), entry));
+                }
+                NetworkRequest::Get(get, address) => {
+                    let _ = self.gets.send((network.clone(), get, address));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Which peers are asked for an address.
+//!
+//! The network consults the RoutingStrategy of the context before a get, so lookup behavior
+//! can be tried out and tuned without touching the reducers. Backends without peers of their
+//! own, like InMemoryNetwork, answer every get themselves and are not routed.
+
+use holochain_core_types::cas::content::Address;
+
+pub trait RoutingStrategy: Send + Sync {
+    /// the peers to ask for the address, in the order they are asked
+    /// the first one holding the entry answers
+    fn route(&self, address: &Address, peers: &[Address]) -> Vec<Address>;
+}
+
+/// Asks every peer, in the order the backend lists them
+#[derive(Clone, Debug, Default)]
+pub struct AnyPeer {}
+
+impl RoutingStrategy for AnyPeer {
+    fn route(&self, _address: &Address, peers: &[Address]) -> Vec<Address> {
+        peers.to_vec()
+    }
+}
+
+/// Asks a single peer for every address, e.g. to test lookups against a known peer
+/// nothing is asked if the peer is not on the network
+#[derive(Clone, Debug)]
+pub struct FixedPeer {
+    peer: Address,
+}
+
+impl FixedPeer {
+    pub fn new(peer: Address) -> Self {
+        FixedPeer { peer }
+    }
+}
+
+impl RoutingStrategy for FixedPeer {
+    fn route(&self, _address: &Address, peers: &[Address]) -> Vec<Address> {
+        peers
+            .iter()
+            .filter(|peer| **peer == self.peer)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use dht::{dht_store::Network, network::NetworkBackend, network_worker::signed_get};
+    use holochain_core_types::{
+        cas::content::AddressableContent,
+        entry::{test_entry_a, test_entry_b, Entry},
+        entry_type::EntryType,
+    };
+    use instance::tests::test_context;
+    use std::sync::{Arc, Mutex};
+
+    /// backend with three peers holding everything, recording which peer was asked
+    #[derive(Default)]
+    struct RecordingBackend {
+        asked: Mutex<Vec<Address>>,
+    }
+
+    impl NetworkBackend for RecordingBackend {
+        fn publish(&self, _entry: &Entry) {}
+
+        fn get(&self, _address: &Address) -> Option<Entry> {
+            None
+        }
+
+        fn addresses_of_type(&self, _entry_type: &EntryType) -> Vec<Address> {
+            Vec::new()
+        }
+
+        fn peers(&self) -> Vec<Address> {
+            vec![
+                Address::from("peer-a".to_string()),
+                Address::from("peer-b".to_string()),
+                Address::from("peer-c".to_string()),
+            ]
+        }
+
+        fn get_from(&self, peer: &Address, _address: &Address) -> Option<Entry> {
+            self.asked.lock().unwrap().push(peer.clone());
+            Some(test_entry_a())
+        }
+    }
+
+    #[test]
+    /// with a fixed peer strategy every get goes to the designated peer
+    fn fixed_peer_routes_all_gets() {
+        let backend = Arc::new(RecordingBackend::default());
+        let peer = Address::from("peer-b".to_string());
+        let mut context = (*test_context("bob")).clone();
+        context.routing_strategy = Arc::new(FixedPeer::new(peer.clone()));
+        let context = Arc::new(context);
+        let network = Network::new_on(backend.clone());
+
+        for address in vec![test_entry_a().address(), test_entry_b().address()] {
+            signed_get(&context, &network, &address)
+                .unwrap()
+                .expect("the designated peer should answer");
+        }
+
+        assert_eq!(vec![peer.clone(), peer], *backend.asked.lock().unwrap());
+    }
+
+    #[test]
+    /// a fixed peer that is not on the network is not asked
+    fn fixed_peer_off_network() {
+        let peers = vec![Address::from("peer-a".to_string())];
+        let strategy = FixedPeer::new(Address::from("peer-z".to_string()));
+        assert!(strategy.route(&test_entry_a().address(), &peers).is_empty());
+        assert_eq!(peers, AnyPeer {}.route(&test_entry_a().address(), &peers));
+    }
+}
@@ -0,0 +1,267 @@
+//! Storages holding writes apart from the storage they read through.
+//!
+//! Operations that have to be applied as one unit, e.g. a Transaction, are applied to staged
+//! storages first. Only once all of them were applied are the staged writes committed to the
+//! storages underneath, so an operation failing halfway leaves nothing behind.
+
+use holochain_core_types::{
+    cas::{
+        content::{Address, AddressableContent, Content},
+        storage::ContentAddressableStorage,
+    },
+    eav::{Attribute, Entity, EntityAttributeValue, EntityAttributeValueStorage, Value},
+    error::HolochainError,
+};
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// the staged writes are plain collections, a panic while they were locked leaves them whole
+fn lock<T>(staged: &Mutex<T>) -> MutexGuard<T> {
+    staged
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// writes staged by a StagedStorage and its clones
+#[derive(Debug, Default)]
+struct StagedContent {
+    added: BTreeMap<Address, Content>,
+}
+
+/// Content storage staging what is added to it until commit()
+/// reads see the staged content on top of the storage, clones share the staged content
+#[derive(Clone, Debug)]
+pub(crate) struct StagedStorage<CAS> {
+    storage: CAS,
+    staged: Arc<Mutex<StagedContent>>,
+}
+
+impl<CAS> StagedStorage<CAS>
+where
+    CAS: ContentAddressableStorage,
+{
+    pub(crate) fn new(storage: CAS) -> Self {
+        StagedStorage {
+            storage,
+            staged: Arc::new(Mutex::new(StagedContent::default())),
+        }
+    }
+
+    /// adds the staged content to the storage, which is given back
+    pub(crate) fn commit(self) -> Result<CAS, HolochainError> {
+        let mut storage = self.storage;
+        let staged = lock(&self.staged);
+        for (address, content) in staged.added.iter() {
+            storage.add(&Staged {
+                address: address.clone(),
+                content: content.clone(),
+            })?;
+        }
+        Ok(storage)
+    }
+}
+
+impl<CAS> PartialEq for StagedStorage<CAS>
+where
+    CAS: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.storage == other.storage && Arc::ptr_eq(&self.staged, &other.staged)
+    }
+}
+
+impl<CAS> ContentAddressableStorage for StagedStorage<CAS>
+where
+    CAS: ContentAddressableStorage,
+{
+    fn add(&mut self, content: &AddressableContent) -> Result<(), HolochainError> {
+        lock(&self.staged)
+            .added
+            .insert(content.address(), content.content());
+        Ok(())
+    }
+
+    fn contains(&self, address: &Address) -> Result<bool, HolochainError> {
+        if lock(&self.staged).added.contains_key(address) {
+            return Ok(true);
+        }
+        self.storage.contains(address)
+    }
+
+    fn fetch<C: AddressableContent>(&self, address: &Address) -> Result<Option<C>, HolochainError> {
+        if let Some(content) = lock(&self.staged).added.get(address) {
+            return Ok(Some(C::from_content(content)));
+        }
+        self.storage.fetch(address)
+    }
+}
+
+/// staged content, added at the address it was staged at
+struct Staged {
+    address: Address,
+    content: Content,
+}
+
+impl AddressableContent for Staged {
+    fn address(&self) -> Address {
+        self.address.clone()
+    }
+
+    fn content(&self) -> Content {
+        self.content.clone()
+    }
+
+    fn from_content(content: &Content) -> Self {
+        Staged {
+            address: Address::from(""),
+            content: content.clone(),
+        }
+    }
+}
+
+/// writes staged by a StagedEavStorage and its clones
+#[derive(Debug, Default)]
+struct StagedEavs {
+    added: HashSet<EntityAttributeValue>,
+    removed: HashSet<EntityAttributeValue>,
+}
+
+/// EAV storage staging what is added to and removed from it until commit()
+/// reads see the staged changes on top of the storage, clones share the staged changes
+#[derive(Clone, Debug)]
+pub(crate) struct StagedEavStorage<EAVS> {
+    storage: EAVS,
+    staged: Arc<Mutex<StagedEavs>>,
+}
+
+impl<EAVS> StagedEavStorage<EAVS>
+where
+    EAVS: EntityAttributeValueStorage,
+{
+    pub(crate) fn new(storage: EAVS) -> Self {
+        StagedEavStorage {
+            storage,
+            staged: Arc::new(Mutex::new(StagedEavs::default())),
+        }
+    }
+
+    /// removes and adds the staged EAVs in the storage, which is given back
+    pub(crate) fn commit(self) -> Result<EAVS, HolochainError> {
+        let mut storage = self.storage;
+        let staged = lock(&self.staged);
+        for eav in staged.removed.iter() {
+            storage.remove_eav(eav)?;
+        }
+        for eav in staged.added.iter() {
+            storage.add_eav(eav)?;
+        }
+        Ok(storage)
+    }
+}
+
+impl<EAVS> PartialEq for StagedEavStorage<EAVS>
+where
+    EAVS: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.storage == other.storage && Arc::ptr_eq(&self.staged, &other.staged)
+    }
+}
+
+impl<EAVS> EntityAttributeValueStorage for StagedEavStorage<EAVS>
+where
+    EAVS: EntityAttributeValueStorage,
+{
+    fn add_eav(&mut self, eav: &EntityAttributeValue) -> Result<(), HolochainError> {
+        let mut staged = lock(&self.staged);
+        staged.removed.remove(eav);
+        staged.added.insert(eav.clone());
+        Ok(())
+    }
+
+    fn fetch_eav(
+        &self,
+        entity: Option<Entity>,
+        attribute: Option<Attribute>,
+        value: Option<Value>,
+    ) -> Result<HashSet<EntityAttributeValue>, HolochainError> {
+        let staged = lock(&self.staged);
+        let mut eavs = self
+            .storage
+            .fetch_eav(entity.clone(), attribute.clone(), value.clone())?;
+        eavs.retain(|eav| !staged.removed.contains(eav));
+        eavs.extend(
+            staged
+                .added
+                .iter()
+                .filter(|eav| EntityAttributeValue::filter_on_eav(eav.entity(), &entity))
+                .filter(|eav| EntityAttributeValue::filter_on_eav(eav.attribute(), &attribute))
+                .filter(|eav| EntityAttributeValue::filter_on_eav(eav.value(), &value))
+                .cloned(),
+        );
+        Ok(eavs)
+    }
+
+    fn add_attribute_index(&mut self, attribute: &Attribute) -> Result<(), HolochainError> {
+        self.storage.add_attribute_index(attribute)
+    }
+
+    fn remove_eav(&mut self, eav: &EntityAttributeValue) -> Result<(), HolochainError> {
+        let mut staged = lock(&self.staged);
+        staged.added.remove(eav);
+        staged.removed.insert(eav.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_cas_implementations::{
+        cas::memory::MemoryStorage, eav::memory::EavMemoryStorage,
+    };
+    use holochain_core_types::{
+        eav::test_eav,
+        entry::{test_entry, Entry},
+    };
+
+    #[test]
+    /// staged content is seen through the staged storage but only reaches the storage when
+    /// committed
+    fn staged_content_is_committed_at_once() {
+        let storage = MemoryStorage::new().unwrap();
+        let mut staged = StagedStorage::new(storage.clone());
+        staged.add(&test_entry()).unwrap();
+
+        assert_eq!(
+            Ok(Some(test_entry())),
+            staged.fetch(&test_entry().address())
+        );
+        assert_eq!(Ok(false), storage.contains(&test_entry().address()));
+        staged.commit().unwrap();
+        assert_eq!(
+            Ok(Some(test_entry())),
+            storage.fetch::<Entry>(&test_entry().address())
+        );
+    }
+
+    #[test]
+    /// staged EAVs are seen through the staged storage but only reach the storage when
+    /// committed
+    fn staged_eavs_are_committed_at_once() {
+        let storage = EavMemoryStorage::new().unwrap();
+        let mut staged = StagedEavStorage::new(storage.clone());
+        staged.add_eav(&test_eav()).unwrap();
+
+        let expected: HashSet<_> = vec![test_eav()].into_iter().collect();
+        assert_eq!(
+            Ok(expected.clone()),
+            staged.fetch_eav(Some(test_eav().entity()), None, None)
+        );
+        assert_eq!(Ok(HashSet::new()), storage.fetch_eav(None, None, None));
+        staged.commit().unwrap();
+        assert_eq!(Ok(expected), storage.fetch_eav(None, None, None));
+    }
+}
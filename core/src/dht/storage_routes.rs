@@ -0,0 +1,111 @@
+//! Which storage the DHT keeps the entries of a type in.
+//!
+//! Entry types have different needs, e.g. session data is fine in memory while records have
+//! to survive restarts. The DHT stores the entries of the types routed in the context in the
+//! backend given for them, all others in its own content storage.
+
+use holochain_core_types::{
+    cas::{content::Address, storage::ContentAddressableStorage},
+    entry::Entry,
+    entry_type::EntryType,
+    error::HolochainError,
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// Storage backend for entries
+/// implemented by every ContentAddressableStorage whose clones share their content
+pub trait EntryStorage: Send + Sync {
+    fn add(&self, entry: &Entry) -> Result<(), HolochainError>;
+    fn contains(&self, address: &Address) -> Result<bool, HolochainError>;
+    fn fetch(&self, address: &Address) -> Result<Option<Entry>, HolochainError>;
+}
+
+impl<CAS> EntryStorage for CAS
+where
+    CAS: ContentAddressableStorage,
+{
+    fn add(&self, entry: &Entry) -> Result<(), HolochainError> {
+        // clones share their content, so the entry ends up in self
+        ContentAddressableStorage::add(&mut self.clone(), entry)
+    }
+
+    fn contains(&self, address: &Address) -> Result<bool, HolochainError> {
+        ContentAddressableStorage::contains(self, address)
+    }
+
+    fn fetch(&self, address: &Address) -> Result<Option<Entry>, HolochainError> {
+        ContentAddressableStorage::fetch(self, address)
+    }
+}
+
+/// The backends of the routed entry types, by entry type name
+#[derive(Clone, Default)]
+pub struct StorageRoutes {
+    routes: HashMap<String, Arc<EntryStorage>>,
+}
+
+impl StorageRoutes {
+    pub fn new() -> Self {
+        StorageRoutes::default()
+    }
+
+    /// stores the entries of the type in the backend from now on
+    pub fn route<S>(&mut self, entry_type: &EntryType, storage: S)
+    where
+        S: 'static + EntryStorage,
+    {
+        self.routes
+            .insert(entry_type.to_string(), Arc::new(storage));
+    }
+
+    /// true if the entries of every type stay in the DHT's content storage
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// the backend of the entry type, None if its entries stay in the DHT's content storage
+    pub fn storage_for(&self, entry_type: &EntryType) -> Option<Arc<EntryStorage>> {
+        self.routes.get(&entry_type.to_string()).cloned()
+    }
+
+    /// the entry at the address in any of the backends
+    pub fn fetch(&self, address: &Address) -> Result<Option<Entry>, HolochainError> {
+        for storage in self.routes.values() {
+            if let Some(entry) = storage.fetch(address)? {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_cas_implementations::cas::memory::MemoryStorage;
+    use holochain_core_types::{
+        cas::content::AddressableContent,
+        entry::{test_entry, test_entry_b},
+    };
+
+    #[test]
+    /// entries are only found in the backend of their type
+    fn routes_by_entry_type() {
+        let storage = MemoryStorage::new().unwrap();
+        let mut routes = StorageRoutes::new();
+        routes.route(test_entry().entry_type(), storage.clone());
+        assert!(routes.storage_for(test_entry_b().entry_type()).is_none());
+
+        let routed = routes.storage_for(test_entry().entry_type()).unwrap();
+        routed.add(&test_entry()).unwrap();
+        assert_eq!(
+            Ok(true),
+            EntryStorage::contains(&storage, &test_entry().address())
+        );
+        assert_eq!(
+            Ok(Some(test_entry())),
+            routes.fetch(&test_entry().address())
+        );
+        assert_eq!(Ok(None), routes.fetch(&test_entry_b().address()));
+    }
+}
@@ -0,0 +1,226 @@
+//! A self-contained snapshot of an instance to attach to bug reports.
+//!
+//! The bundle aggregates what the introspection functions of an instance tell into a single
+//! serializable structure. Zome call parameters and results are left out and entries only
+//! appear as addresses, so it can be shared without disclosing the agent's data.
+
+use action::ActionFilter;
+use holochain_core_types::{
+    cas::content::Address,
+    error::HolochainError,
+    json::{FromJson, ToJson},
+};
+use instance::LockStats;
+use nucleus::ZomeFnCall;
+use serde_json;
+use state::State;
+use std::collections::BTreeMap;
+
+/// number of log lines a bundle holds
+pub const DIAGNOSTIC_LOG_LINES: usize = 100;
+
+/// number of entries in each ranking of a hotspot report
+pub const HOTSPOT_REPORT_LENGTH: usize = 20;
+
+/// A zome call without its parameters and result
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CallSummary {
+    pub zome: String,
+    pub capability: String,
+    pub function: String,
+    /// the error the call failed with, None if it succeeded
+    pub error: Option<String>,
+}
+
+impl CallSummary {
+    pub fn new(call: &ZomeFnCall, result: &Result<String, HolochainError>) -> Self {
+        CallSummary {
+            zome: call.zome_name.clone(),
+            capability: call.cap_name.clone(),
+            function: call.fn_name.clone(),
+            error: result.as_ref().err().map(|error| error.to_string()),
+        }
+    }
+}
+
+/// How many actions were reduced, in total and by kind
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActionStats {
+    pub reduced: usize,
+    /// counts by the name of the ActionFilter selecting the kind of action
+    pub by_kind: BTreeMap<String, usize>,
+}
+
+impl ActionStats {
+    /// the stats of the actions in the history of the state
+    pub fn of(state: &State) -> Self {
+        let mut stats = ActionStats::default();
+        for action_wrapper in state.history.iter() {
+            stats.reduced += 1;
+            let kind = format!("{:?}", ActionFilter::of(action_wrapper.action()));
+            *stats.by_kind.entry(kind).or_insert(0) += 1;
+        }
+        stats
+    }
+}
+
+/// An entry with how many links there are from it and how often it was asked for
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Hotspot {
+    pub address: Address,
+    pub links: usize,
+    /// gets since the instance started
+    pub gets: u64,
+}
+
+/// The entries most likely to slow the instance down, e.g. anchors that might need sharding
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HotspotReport {
+    /// the entries with links from them, most links first
+    pub by_links: Vec<Hotspot>,
+    /// the entries that were asked for, most gets first
+    pub by_gets: Vec<Hotspot>,
+}
+
+impl HotspotReport {
+    /// the report for the state, each ranking holding up to limit entries
+    /// entries ranking equal are ordered by address
+    pub fn of(state: &State, limit: usize) -> Result<Self, HolochainError> {
+        let dht = state.dht();
+        let link_counts = dht.link_counts()?;
+        let access_counts = dht.access_counts();
+        let hotspot = |address: &Address| Hotspot {
+            address: address.clone(),
+            links: link_counts.get(address).cloned().unwrap_or(0),
+            gets: access_counts.get(address).cloned().unwrap_or(0),
+        };
+
+        let mut by_links: Vec<Hotspot> = link_counts.keys().map(&hotspot).collect();
+        by_links.sort_by(|a, b| b.links.cmp(&a.links).then(a.address.cmp(&b.address)));
+        by_links.truncate(limit);
+        let mut by_gets: Vec<Hotspot> = access_counts.keys().map(&hotspot).collect();
+        by_gets.sort_by(|a, b| b.gets.cmp(&a.gets).then(a.address.cmp(&b.address)));
+        by_gets.truncate(limit);
+        Ok(HotspotReport { by_links, by_gets })
+    }
+}
+
+/// The addresses an instance has, e.g. to debug replication
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct KnownAddresses {
+    /// held in the content storage, authored or published to the instance, sorted
+    pub local: Vec<Address>,
+    /// fetched from the network and cached, sorted
+    pub cached: Vec<Address>,
+}
+
+impl KnownAddresses {
+    pub fn of(state: &State) -> Result<Self, HolochainError> {
+        Ok(KnownAddresses {
+            local: state.dht().content_storage().addresses()?,
+            cached: state.dht().cache().addresses(),
+        })
+    }
+}
+
+/// Whether the instance is up and what it still has to do
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Health {
+    pub initialized: bool,
+    pub active: bool,
+    pub paused: bool,
+    /// number of entries waiting to be published to the network
+    pub queued_publishes: usize,
+}
+
+/// Everything needed to understand the situation of an instance in a bug report
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticBundle {
+    /// address of the DNA entry, None before genesis
+    pub dna_address: Option<Address>,
+    pub agent: String,
+    pub agent_address: Address,
+    /// @see State::fingerprint()
+    pub state_fingerprint: Address,
+    /// @see State::dht_fingerprint()
+    pub dht_fingerprint: Address,
+    pub action_stats: ActionStats,
+    pub health: Health,
+    pub lock_stats: LockStats,
+    /// the recorded zome calls, oldest first
+    pub calls: Vec<CallSummary>,
+    /// the last lines of the log, if the logger keeps them
+    pub log_lines: Vec<String>,
+}
+
+impl ToJson for DiagnosticBundle {
+    fn to_json(&self) -> Result<String, HolochainError> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+impl FromJson for DiagnosticBundle {
+    fn from_json(s: &str) -> Result<Self, HolochainError> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use action::{Action, ActionWrapper};
+    use holochain_core_types::{
+        cas::content::AddressableContent,
+        entry::{test_entry_address, test_entry_b},
+    };
+    use instance::tests::test_context;
+
+    #[test]
+    /// actions are counted by the name of their filter
+    fn action_stats_count_by_kind() {
+        let context = test_context("bob");
+        let mut state = State::new();
+        for action in vec![
+            Action::GetEntry(test_entry_address()),
+            Action::GetEntry(test_entry_address()),
+            Action::ClearNegativeCache,
+        ] {
+            state = state.reduce(context.clone(), ActionWrapper::new(action));
+        }
+
+        let stats = ActionStats::of(&state);
+        assert_eq!(3, stats.reduced);
+        assert_eq!(Some(&2), stats.by_kind.get("GET_ENTRY"));
+        assert_eq!(Some(&1), stats.by_kind.get("CLEAR_NEGATIVE_CACHE"));
+    }
+
+    #[test]
+    /// every get is counted, whether the entry was found or not
+    fn hotspots_rank_by_gets() {
+        let context = test_context("bob");
+        let mut state = State::new();
+        for address in vec![
+            test_entry_b().address(),
+            test_entry_address(),
+            test_entry_address(),
+        ] {
+            state = state.reduce(
+                context.clone(),
+                ActionWrapper::new(Action::GetEntry(address)),
+            );
+        }
+
+        let report = HotspotReport::of(&state, 10).unwrap();
+        assert!(report.by_links.is_empty());
+        let gets: Vec<(Address, u64)> = report
+            .by_gets
+            .iter()
+            .map(|hotspot| (hotspot.address.clone(), hotspot.gets))
+            .collect();
+        assert_eq!(
+            vec![(test_entry_address(), 2), (test_entry_b().address(), 1)],
+            gets
+        );
+        assert_eq!(1, HotspotReport::of(&state, 1).unwrap().by_gets.len());
+    }
+}
@@ -0,0 +1,95 @@
+//! Positions in the action history of a State.
+//!
+//! A HistoryCursor counts the actions reduced into a State since it was created.
+//! Every reduced action advances the cursor by exactly one, so a cursor taken before some
+//! operation is always less than a cursor taken after it, no matter how many internal
+//! actions the operation dispatched. Callers should compare cursors rather than assert on
+//! exact history lengths, which change whenever internals change.
+//!
+//! Cursors do not depend on the size of State.history, so they stay meaningful if stale
+//! history is ever dropped.
+//! Advancing saturates at u64::MAX rather than overflowing.
+
+use std::fmt;
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct HistoryCursor(u64);
+
+impl HistoryCursor {
+    /// cursor positioned before any action has been reduced
+    pub fn new() -> Self {
+        HistoryCursor(0)
+    }
+
+    /// the cursor one action further along
+    pub fn next(&self) -> Self {
+        self.advance(1)
+    }
+
+    /// the cursor the given number of actions further along
+    pub fn advance(&self, actions: u64) -> Self {
+        HistoryCursor(self.0.saturating_add(actions))
+    }
+
+    /// number of actions reduced between an earlier cursor and this one
+    /// zero if the other cursor is not earlier
+    pub fn actions_since(&self, earlier: &HistoryCursor) -> u64 {
+        self.0.saturating_sub(earlier.0)
+    }
+
+    /// true if at least one action was reduced between the other cursor and this one
+    pub fn is_after(&self, other: &HistoryCursor) -> bool {
+        self > other
+    }
+}
+
+impl From<HistoryCursor> for u64 {
+    fn from(cursor: HistoryCursor) -> u64 {
+        cursor.0
+    }
+}
+
+impl fmt::Display for HistoryCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::u64;
+
+    #[test]
+    /// cursors order by position
+    fn cursor_ordering() {
+        let start = HistoryCursor::new();
+        let next = start.next();
+
+        assert!(next.is_after(&start));
+        assert!(!start.is_after(&next));
+        assert!(!start.is_after(&start));
+        assert_eq!(next, start.advance(1));
+    }
+
+    #[test]
+    /// distance between cursors
+    fn cursor_actions_since() {
+        let start = HistoryCursor::new();
+        let later = start.advance(3);
+
+        assert_eq!(3, later.actions_since(&start));
+        assert_eq!(0, start.actions_since(&later));
+    }
+
+    #[test]
+    /// advancing never overflows
+    fn cursor_saturates() {
+        let end = HistoryCursor::new().advance(u64::MAX);
+
+        assert_eq!(end, end.next());
+        assert_eq!(u64::MAX, u64::from(end.advance(10)));
+    }
+}
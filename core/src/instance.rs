@@ -1,17 +1,26 @@
-use action::ActionWrapper;
+use action::{ActionFilter, ActionWrapper};
 use context::Context;
+use dht::network_worker::NetworkWorker;
+use history::HistoryCursor;
+use holochain_core_types::error::HolochainError;
+use scheduler::{record_wait, ActionQueue, SchedulerStats};
 use state::State;
 use std::{
+    collections::HashMap,
     sync::{
-        mpsc::{sync_channel, Receiver, SyncSender},
-        Arc, RwLock, RwLockReadGuard,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender},
+        Arc, LockResult, Mutex, MutexGuard, RwLock, RwLockReadGuard,
     },
-    thread,
-    time::Duration,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
 pub const RECV_DEFAULT_TIMEOUT_MS: Duration = Duration::from_millis(10000);
 
+/// how long the idle event loop waits for an action before checking whether it was stopped
+pub const ACTION_LOOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 /// Object representing a Holochain instance, i.e. a running holochain (DNA + DHT + source-chain)
 /// Holds the Event loop and processes it with the redux pattern.
 #[derive(Clone)]
@@ -20,6 +29,103 @@ pub struct Instance {
     state: Arc<RwLock<State>>,
     action_channel: SyncSender<ActionWrapper>,
     observer_channel: SyncSender<Observer>,
+    /// Timings of the state lock taken by the event loop
+    lock_stats: Arc<Mutex<LockStats>>,
+    /// Queue waits of the actions reduced by the event loop, by priority class
+    scheduler_stats: Arc<Mutex<SchedulerStats>>,
+    /// Callbacks for reduced actions
+    action_subscriptions: Arc<Mutex<Vec<ActionSubscription>>>,
+    /// Callbacks for every new state
+    state_subscriptions: Arc<Mutex<Vec<StateCallback>>>,
+    /// Every reduced action in order, None unless kept with keep_action_log()
+    action_log: Option<Arc<Mutex<Vec<ActionWrapper>>>>,
+    /// Cursor of the initial state, where the action log starts
+    log_start: HistoryCursor,
+    /// Held from reading the state for a reduction until the new state is written,
+    /// so a state restored in between isn't overwritten
+    reduction: Arc<Mutex<()>>,
+    /// set to end the event loop, @see Instance::stop_action_loop()
+    stopping: Arc<AtomicBool>,
+    /// thread of the event loop, None until it is started and once it is stopped
+    action_loop: Arc<Mutex<Option<JoinHandle<()>>>>,
+    /// publishes and gets for the event loop, None while it isn't running
+    network_worker: Arc<Mutex<Option<NetworkWorker>>>,
+    /// Deadlines of the queued actions dispatched with dispatch_with_deadline()
+    deadlines: Arc<Mutex<HashMap<ActionWrapper, Deadline>>>,
+    /// what using the locks of the instance does once a panic poisoned them
+    /// taken from the context the event loop is started with
+    poison_policy: PoisonPolicy,
+    /// number of times a poisoned lock was used anyway, @see PoisonPolicy::Recover
+    poison_recoveries: Arc<AtomicUsize>,
+    /// set once the first recovery from a poisoned lock was logged
+    poison_logged: Arc<AtomicBool>,
+}
+
+/// What the instance does with a lock a thread panicked while holding, e.g. the reduction
+/// lock left poisoned by a panicking reducer
+/// a poisoned lock stays poisoned, so the policy applies to every later use of it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoisonPolicy {
+    /// panic as well, every later use of the instance fails
+    Panic,
+    /// carry on with the data as the panicking thread left it, logging the first recovery
+    /// the state is only ever replaced as a whole, so it isn't left half written
+    Recover,
+}
+
+impl Default for PoisonPolicy {
+    fn default() -> Self {
+        PoisonPolicy::Recover
+    }
+}
+
+impl PoisonPolicy {
+    /// the guard of the lock, or the guard of what a panicking owner left in it as the policy
+    /// says
+    pub fn unpoison<G>(self, result: LockResult<G>, lock_name: &str) -> G {
+        result.unwrap_or_else(|poisoned| match self {
+            PoisonPolicy::Panic => panic!("owners of the {} shouldn't panic", lock_name),
+            PoisonPolicy::Recover => poisoned.into_inner(),
+        })
+    }
+}
+
+/// When a queued action is dropped instead of reduced, and where its dispatcher learns which
+struct Deadline {
+    at: Instant,
+    outcome: SyncSender<Result<(), HolochainError>>,
+}
+
+type ActionCallback = Box<Fn(&ActionWrapper) + Send>;
+type StateCallback = Box<Fn(&State) -> bool + Send>;
+
+/// Callback for the reduced actions selected by the filter
+struct ActionSubscription {
+    filter: ActionFilter,
+    callback: ActionCallback,
+}
+
+/// Timings of the state lock taken by the event loop while reducing actions.
+/// Reducers can hold the lock for a long time, e.g. while waiting on the network,
+/// which blocks everything else that needs the state.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LockStats {
+    /// number of times the lock was acquired
+    pub acquisitions: u64,
+    /// total time spent waiting to acquire the lock
+    pub total_wait: Duration,
+    /// longest time the lock was held by a single acquisition
+    pub max_held_duration: Duration,
+}
+
+impl LockStats {
+    fn record(&mut self, wait: Duration, held: Duration) {
+        self.acquisitions += 1;
+        self.total_wait += wait;
+        if held > self.max_held_duration {
+            self.max_held_duration = held;
+        }
+    }
 }
 
 type ClosureType = Box<FnMut(&State) -> bool + Send>;
@@ -81,6 +187,52 @@ impl Instance {
         )
     }
 
+    /// Stack an Action in the Event Queue and block until it has been reduced, unless it is
+    /// still queued at the deadline: then it is dropped from the queue and this fails with
+    /// DeadlineExceeded as soon as the deadline passes
+    ///
+    /// # Panics
+    ///
+    /// Panics if called before `start_action_loop`.
+    pub fn dispatch_with_deadline(
+        &mut self,
+        action_wrapper: ActionWrapper,
+        deadline: Instant,
+    ) -> Result<(), HolochainError> {
+        let (sender, receiver) = sync_channel(1);
+        self.lock_deadlines().insert(
+            action_wrapper.clone(),
+            Deadline {
+                at: deadline,
+                outcome: sender,
+            },
+        );
+        dispatch_action(&self.action_channel, action_wrapper.clone());
+        let now = Instant::now();
+        let timeout = if deadline > now {
+            deadline - now
+        } else {
+            Duration::from_secs(0)
+        };
+        match receiver.recv_timeout(timeout) {
+            Ok(outcome) => outcome,
+            Err(_) => {
+                // still queued, the event loop drops it when it gets to it
+                if self.lock_deadlines().contains_key(&action_wrapper) {
+                    return Err(HolochainError::DeadlineExceeded);
+                }
+                // taken from the queue just in time, or just too late
+                receiver
+                    .recv()
+                    .unwrap_or(Err(HolochainError::DeadlineExceeded))
+            }
+        }
+    }
+
+    fn lock_deadlines(&self) -> MutexGuard<HashMap<ActionWrapper, Deadline>> {
+        self.unpoison(self.deadlines.lock(), "deadlines Mutex")
+    }
+
     /// Returns recievers for actions and observers that get added to this instance
     fn initialize_channels(&mut self) -> (Receiver<ActionWrapper>, Receiver<Observer>) {
         let (tx_action, rx_action) =
@@ -102,23 +254,77 @@ impl Instance {
     }
 
     /// Start the Event Loop on a seperate thread
+    /// Of the actions queued, those of the most urgent priority class are reduced first
+    /// @see Context::scheduler_priorities
     pub fn start_action_loop(&mut self, context: Arc<Context>) {
         let (rx_action, rx_observer) = self.initialize_channels();
+        // a fresh flag, so a loop stopped before doesn't stop this one
+        self.stopping = Arc::new(AtomicBool::new(false));
+        self.poison_policy = context.poison_policy;
+        let stopping = self.stopping.clone();
 
         let sync_self = self.clone();
         let sub_context = self.initialize_context(context);
+        *self.unpoison(self.network_worker.lock(), "network worker Mutex") =
+            Some(NetworkWorker::start(sub_context.clone()));
 
-        thread::spawn(move || {
+        let action_loop = thread::spawn(move || {
             let mut state_observers: Vec<Observer> = Vec::new();
-            for action_wrapper in rx_action {
+            let mut queue = ActionQueue::new(sub_context.scheduler_priorities);
+            while !stopping.load(Ordering::SeqCst) {
+                if queue.is_empty() {
+                    match rx_action.recv_timeout(ACTION_LOOP_POLL_INTERVAL) {
+                        Ok(action_wrapper) => queue.push(action_wrapper),
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                for action_wrapper in rx_action.try_iter() {
+                    queue.push(action_wrapper);
+                }
+                let (action_wrapper, class, wait) = queue.pop().expect("queue isn't empty");
+                record_wait(
+                    &mut sync_self
+                        .unpoison(sync_self.scheduler_stats.lock(), "scheduler stats Mutex"),
+                    class,
+                    wait,
+                );
+                let deadline = sync_self.lock_deadlines().remove(&action_wrapper);
+                if let Some(ref deadline) = deadline {
+                    if Instant::now() > deadline.at {
+                        let _ = deadline.outcome.send(Err(HolochainError::DeadlineExceeded));
+                        continue;
+                    }
+                }
                 state_observers = sync_self.process_action(
                     action_wrapper,
                     state_observers,
                     &rx_observer,
                     &sub_context,
                 );
+                if let Some(deadline) = deadline {
+                    let _ = deadline.outcome.send(Ok(()));
+                }
             }
         });
+        *self.unpoison(self.action_loop.lock(), "action loop Mutex") = Some(action_loop);
+    }
+
+    /// Ends the event loop once the action it is reducing is reduced and waits for its thread
+    /// to finish, the actions still queued are dropped
+    /// dispatching to the instance fails from then on
+    pub fn stop_action_loop(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        let action_loop = self
+            .unpoison(self.action_loop.lock(), "action loop Mutex")
+            .take();
+        if let Some(action_loop) = action_loop {
+            // a loop a panicking reducer ended is over already
+            let _ = action_loop.join();
+        }
+        // its threads end once they handed what they have to the peers
+        self.unpoison(self.network_worker.lock(), "network worker Mutex")
+            .take();
     }
 
     /// Calls the reducers for an action and calls the observers with the new state
@@ -131,28 +337,53 @@ impl Instance {
         context: &Arc<Context>,
     ) -> Vec<Observer> {
         // Mutate state
-        {
-            let new_state: State;
-
-            {
-                // Only get a read lock first so code in reducers can read state as well
-                let state = self
-                    .state
-                    .read()
-                    .expect("owners of the state RwLock shouldn't panic");
-
-                // Create new state by reducing the action on old state
-                new_state = state.reduce(context.clone(), action_wrapper);
-            }
+        let (network, network_requests) = {
+            let _reduction = self.unpoison(self.reduction.lock(), "reduction Mutex");
+            // Create new state by reducing the action on old state
+            let mut new_state =
+                self.reduce_locked(|state| state.reduce(context.clone(), action_wrapper.clone()));
+            // only the state kept asks the peers, states thrown away never do
+            let network_requests = new_state.take_network_requests();
 
             // Get write lock
-            let mut state = self
-                .state
-                .write()
-                .expect("owners of the state RwLock shouldn't panic");
+            let requested = Instant::now();
+            let mut state = self.unpoison(self.state.write(), "state RwLock");
+            let acquired = Instant::now();
 
             // Change the state
             *state = new_state;
+            // logged before the lock is released, so the log covers the cursor of any state read
+            if let Some(ref action_log) = self.action_log {
+                self.unpoison(action_log.lock(), "action log Mutex")
+                    .push(action_wrapper.clone());
+            }
+            self.record_lock(acquired.duration_since(requested), acquired.elapsed());
+            network_requests
+        };
+        if !network_requests.is_empty() {
+            // without a running event loop nobody would record the outcomes
+            if let Some(ref network_worker) =
+                *self.unpoison(self.network_worker.lock(), "network worker Mutex")
+            {
+                network_worker.send(&network, network_requests);
+            }
+        }
+        if self.poison_recoveries() > 0 && !self.poison_logged.swap(true, Ordering::SeqCst) {
+            // the logger may have been poisoned by the same panic
+            let _ = context.log("instance recovered a lock poisoned by a panic, carrying on");
+        }
+
+        // Run the callbacks subscribed to this kind of action
+        {
+            let subscriptions = self.unpoison(
+                self.action_subscriptions.lock(),
+                "action subscriptions Mutex",
+            );
+            for subscription in subscriptions.iter() {
+                if subscription.filter.matches(action_wrapper.action()) {
+                    (subscription.callback)(&action_wrapper);
+                }
+            }
         }
 
         // Add new observers
@@ -160,10 +391,9 @@ impl Instance {
 
         // Run all observer closures
         {
-            let state = self
-                .state
-                .read()
-                .expect("owners of the state RwLock shouldn't panic");
+            let state = self.unpoison(self.state.read(), "state RwLock");
+            self.unpoison(self.state_subscriptions.lock(), "state subscriptions Mutex")
+                .retain(|callback| callback(&state));
             let mut i = 0;
             while i != state_observers.len() {
                 if (&mut state_observers[i].sensor)(&state) {
@@ -176,21 +406,197 @@ impl Instance {
         state_observers
     }
 
+    /// Builds a new state while holding a read lock on the current one
+    /// Only a read lock is taken so code in reducers can read state as well
+    fn reduce_locked<F>(&self, reduce: F) -> State
+    where
+        F: FnOnce(&State) -> State,
+    {
+        let requested = Instant::now();
+        let state = self.unpoison(self.state.read(), "state RwLock");
+        let acquired = Instant::now();
+        let new_state = reduce(&state);
+        drop(state);
+        self.record_lock(acquired.duration_since(requested), acquired.elapsed());
+        new_state
+    }
+
+    /// the guard of the lock, or the guard of what a panicking owner left in it as the poison
+    /// policy says
+    fn unpoison<G>(&self, result: LockResult<G>, lock_name: &str) -> G {
+        if result.is_err() {
+            self.poison_recoveries.fetch_add(1, Ordering::SeqCst);
+        }
+        self.poison_policy.unpoison(result, lock_name)
+    }
+
+    /// number of times a lock poisoned by a panic was used anyway, @see PoisonPolicy
+    pub fn poison_recoveries(&self) -> usize {
+        self.poison_recoveries.load(Ordering::SeqCst)
+    }
+
+    fn record_lock(&self, wait: Duration, held: Duration) {
+        self.unpoison(self.lock_stats.lock(), "lock stats Mutex")
+            .record(wait, held);
+    }
+
+    /// Timings of the state lock taken so far by the event loop
+    pub fn lock_stats(&self) -> LockStats {
+        self.unpoison(self.lock_stats.lock(), "lock stats Mutex")
+            .clone()
+    }
+
+    /// How long the actions reduced so far waited in the queue, by priority class
+    pub fn scheduler_stats(&self) -> SchedulerStats {
+        self.unpoison(self.scheduler_stats.lock(), "scheduler stats Mutex")
+            .clone()
+    }
+
+    /// Calls the callback with every action selected by the filter once it has been reduced
+    /// The callback runs in the event loop, so it must not block or dispatch and wait
+    pub fn subscribe_actions<F>(&self, filter: ActionFilter, callback: F)
+    where
+        F: 'static + Fn(&ActionWrapper) + Send,
+    {
+        self.unpoison(
+            self.action_subscriptions.lock(),
+            "action subscriptions Mutex",
+        )
+        .push(ActionSubscription {
+            filter,
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Calls the callback with the new state after every reduced action
+    /// The callback runs in the event loop, so it must not block or dispatch and wait
+    pub fn subscribe_state<F>(&self, callback: F)
+    where
+        F: 'static + Fn(&State) + Send,
+    {
+        self.subscribe_state_while(move |state| {
+            callback(state);
+            true
+        });
+    }
+
+    /// Calls the callback with the new state after every reduced action until it returns
+    /// false, e.g. once whoever it passes the states on to is gone
+    /// The callback runs in the event loop, so it must not block or dispatch and wait
+    pub fn subscribe_state_while<F>(&self, callback: F)
+    where
+        F: 'static + Fn(&State) -> bool + Send,
+    {
+        self.unpoison(self.state_subscriptions.lock(), "state subscriptions Mutex")
+            .push(Box::new(callback));
+    }
+
     /// Creates a new Instance with disconnected channels.
     pub fn new() -> Self {
+        Instance::from_state(State::new())
+    }
+
+    /// Creates a new Instance with disconnected channels, starting from the given state.
+    pub fn from_state(state: State) -> Self {
         let (tx_action, _) = sync_channel(1);
         let (tx_observer, _) = sync_channel(1);
         Instance {
-            state: Arc::new(RwLock::new(State::new())),
+            action_log: None,
+            log_start: state.history_cursor(),
+            state: Arc::new(RwLock::new(state)),
             action_channel: tx_action,
             observer_channel: tx_observer,
+            lock_stats: Arc::new(Mutex::new(LockStats::default())),
+            scheduler_stats: Arc::new(Mutex::new(SchedulerStats::new())),
+            action_subscriptions: Arc::new(Mutex::new(Vec::new())),
+            state_subscriptions: Arc::new(Mutex::new(Vec::new())),
+            reduction: Arc::new(Mutex::new(())),
+            stopping: Arc::new(AtomicBool::new(false)),
+            action_loop: Arc::new(Mutex::new(None)),
+            network_worker: Arc::new(Mutex::new(None)),
+            deadlines: Arc::new(Mutex::new(HashMap::new())),
+            poison_policy: PoisonPolicy::default(),
+            poison_recoveries: Arc::new(AtomicUsize::new(0)),
+            poison_logged: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// keeps every action reduced from now on, so past states can be reconstructed with
+    /// state_at() and replay_actions()
+    /// the log grows for the lifetime of the instance, so it is only kept if asked for,
+    /// before the event loop is started
+    /// does nothing if the current state has content in its storage, as it can't be
+    /// replayed from then
+    pub fn keep_action_log(&mut self) {
+        let (replayable, cursor) = {
+            let state = self.state();
+            let replayable = state
+                .dht()
+                .content_storage()
+                .addresses()
+                .map(|addresses| addresses.is_empty())
+                .unwrap_or(false);
+            (replayable, state.history_cursor())
+        };
+        if replayable && self.action_log.is_none() {
+            self.action_log = Some(Arc::new(Mutex::new(Vec::new())));
+            self.log_start = cursor;
         }
     }
 
     pub fn state(&self) -> RwLockReadGuard<State> {
-        self.state
-            .read()
-            .expect("owners of the state RwLock shouldn't panic")
+        self.unpoison(self.state.read(), "state RwLock")
+    }
+
+    /// replaces the state in one go, between the reduction of two actions
+    /// the actions logged after the cursor of the new state are dropped, so the history
+    /// continues from the new state
+    pub fn restore_state(&self, state: State) {
+        let _reduction = self.unpoison(self.reduction.lock(), "reduction Mutex");
+        let mut current = self.unpoison(self.state.write(), "state RwLock");
+        if let Some(ref action_log) = self.action_log {
+            let kept = state.history_cursor().actions_since(&self.log_start) as usize;
+            self.unpoison(action_log.lock(), "action log Mutex")
+                .truncate(kept);
+        }
+        *current = state;
+    }
+
+    /// the state as it was at the cursor, reconstructed by replaying the action log
+    /// fails with HistoryUnavailable for a cursor after the current state or before the
+    /// initial one, or if the action log isn't kept, @see Instance::keep_action_log()
+    /// @see State::replay()
+    pub fn state_at(
+        &self,
+        context: Arc<Context>,
+        cursor: HistoryCursor,
+    ) -> Result<State, HolochainError> {
+        self.replay_actions(context, cursor, ActionFilter::all())
+    }
+
+    /// the partial state the actions of the action log up to the cursor that match the
+    /// filter make, failing like state_at()
+    /// @see State::replay_filtered()
+    pub fn replay_actions(
+        &self,
+        context: Arc<Context>,
+        cursor: HistoryCursor,
+        filter: ActionFilter,
+    ) -> Result<State, HolochainError> {
+        let action_log = match self.action_log {
+            Some(ref action_log) => self.unpoison(action_log.lock(), "action log Mutex"),
+            None => return Err(HolochainError::HistoryUnavailable),
+        };
+        let actions = cursor.actions_since(&self.log_start) as usize;
+        if cursor < self.log_start || actions > action_log.len() {
+            return Err(HolochainError::HistoryUnavailable);
+        }
+        Ok(State::replay_filtered(
+            context,
+            self.log_start,
+            &action_log[..actions],
+            filter,
+        ))
     }
 }
 
@@ -272,15 +678,24 @@ pub fn dispatch_action(action_channel: &SyncSender<ActionWrapper>, action_wrappe
 pub mod tests {
     extern crate test_utils;
     use super::*;
-    use action::{tests::test_action_wrapper_get, Action, ActionWrapper};
-    use agent::state::ActionResponse;
+    use action::{
+        tests::{test_action_wrapper_commit, test_action_wrapper_get},
+        Action, ActionWrapper,
+    };
+    use agent::state::{ActionResponse, ChainRateLimit};
     use context::Context;
     use futures::executor::block_on;
     use holochain_agent::Agent;
     use holochain_core_types::{
-        cas::content::AddressableContent, entry::ToEntry, entry_type::EntryType,
+        cas::{content::AddressableContent, storage::ContentAddressableStorage},
+        entry::{test_entry_a, test_entry_b, ToEntry},
+        entry_type::EntryType,
+        error::HolochainError,
+    };
+    use holochain_dna::{
+        zome::{entry_types::EntryTypeDef, Zome},
+        Dna,
     };
-    use holochain_dna::{zome::Zome, Dna};
     use logger::Logger;
     use nucleus::{
         actions::initialize::initialize_application,
@@ -317,14 +732,13 @@ pub mod tests {
     pub fn test_context_and_logger(agent_name: &str) -> (Arc<Context>, Arc<Mutex<TestLogger>>) {
         let agent = Agent::from(agent_name.to_owned());
         let logger = test_logger();
-        (
-            Arc::new(Context::new(
-                agent,
-                logger.clone(),
-                Arc::new(Mutex::new(SimplePersister::new())),
-            )),
-            logger,
-        )
+        let context = Context::new(
+            agent.clone(),
+            logger.clone(),
+            Arc::new(Mutex::new(SimplePersister::new())),
+        );
+        context.keystore.generate(&agent).unwrap();
+        (Arc::new(context), logger)
     }
 
     /// create a test context
@@ -341,13 +755,15 @@ pub mod tests {
     ) -> Arc<Context> {
         let agent = Agent::from(agent_name.to_owned());
         let logger = test_logger();
-        Arc::new(Context::new_with_channels(
-            agent,
+        let context = Context::new_with_channels(
+            agent.clone(),
             logger.clone(),
             Arc::new(Mutex::new(SimplePersister::new())),
             action_channel.clone(),
             observer_channel.clone(),
-        ))
+        );
+        context.keystore.generate(&agent).unwrap();
+        Arc::new(context)
     }
 
     pub fn test_context_with_state() -> Arc<Context> {
@@ -356,6 +772,7 @@ pub mod tests {
             test_logger(),
             Arc::new(Mutex::new(SimplePersister::new())),
         );
+        context.keystore.generate(&context.agent).unwrap();
         let global_state = Arc::new(RwLock::new(State::new()));
         context.set_state(global_state.clone());
         Arc::new(context)
@@ -487,7 +904,83 @@ pub mod tests {
             .get(&action_wrapper)
             .expect("action and reponse should be added after Get action dispatch");
 
-        assert_eq!(response, &ActionResponse::GetEntry(None));
+        assert_eq!(response, &ActionResponse::GetEntry(Ok(None)));
+    }
+
+    #[test]
+    /// tests that action subscriptions are only called for the actions they select
+    fn commit_subscription_ignores_get_entry() {
+        let mut instance = Instance::new();
+        let context = test_context("jane");
+        let (_rx_action, rx_observer) = instance.initialize_channels();
+
+        let (sender, receiver) = channel();
+        instance.subscribe_actions(ActionFilter::COMMIT, move |action_wrapper| {
+            sender.send(action_wrapper.clone()).unwrap()
+        });
+
+        let get = test_action_wrapper_get();
+        let commit = test_action_wrapper_commit();
+        instance.process_action(get, Vec::new(), &rx_observer, &context);
+        instance.process_action(commit.clone(), Vec::new(), &rx_observer, &context);
+
+        assert_eq!(vec![commit], receiver.try_iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    /// tests that state subscriptions see every new state
+    fn state_subscription_sees_every_reduce() {
+        let mut instance = Instance::new();
+        let context = test_context("jane");
+        let (_rx_action, rx_observer) = instance.initialize_channels();
+
+        let (sender, receiver) = channel();
+        instance.subscribe_state(move |state| sender.send(state.history_cursor()).unwrap());
+
+        instance.process_action(
+            test_action_wrapper_get(),
+            Vec::new(),
+            &rx_observer,
+            &context,
+        );
+        instance.process_action(
+            test_action_wrapper_get(),
+            Vec::new(),
+            &rx_observer,
+            &context,
+        );
+
+        let cursors: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(2, cursors.len());
+        assert!(cursors[1].is_after(&cursors[0]));
+    }
+
+    #[test]
+    /// tests that a state subscription is dropped once its callback returns false
+    fn state_subscription_ends_when_callback_returns_false() {
+        let mut instance = Instance::new();
+        let context = test_context("jane");
+        let (_rx_action, rx_observer) = instance.initialize_channels();
+
+        let (sender, receiver) = channel();
+        instance.subscribe_state_while(move |state| sender.send(state.history_cursor()).is_ok());
+
+        instance.process_action(
+            test_action_wrapper_get(),
+            Vec::new(),
+            &rx_observer,
+            &context,
+        );
+        assert_eq!(1, receiver.try_iter().count());
+        assert_eq!(1, instance.state_subscriptions.lock().unwrap().len());
+        drop(receiver);
+        instance.process_action(
+            test_action_wrapper_get(),
+            Vec::new(),
+            &rx_observer,
+            &context,
+        );
+        assert!(instance.state_subscriptions.lock().unwrap().is_empty());
     }
 
     #[test]
@@ -526,6 +1019,22 @@ pub mod tests {
         assert_eq!(dna, stored_dna);
     }
 
+    #[test]
+    /// tests that a slow reduce shows up in the lock stats
+    fn slow_reduce_shows_in_lock_stats() {
+        let instance = Instance::new();
+        assert_eq!(LockStats::default(), instance.lock_stats());
+
+        instance.reduce_locked(|state| {
+            sleep(Duration::from_millis(50));
+            state.clone()
+        });
+
+        let stats = instance.lock_stats();
+        assert_eq!(1, stats.acquisitions);
+        assert!(stats.max_held_duration >= Duration::from_millis(50));
+    }
+
     #[test]
     /// tests that we can dispatch an action and block until it completes
     fn can_dispatch_and_wait() {
@@ -555,6 +1064,127 @@ pub mod tests {
         );
     }
 
+    #[test]
+    /// a stopped event loop ends its thread, so nothing can be dispatched anymore
+    fn stopped_action_loop_releases_its_thread() {
+        let mut instance = Instance::new();
+        instance.start_action_loop(test_context("jane"));
+        instance.dispatch_and_wait(ActionWrapper::new(Action::InitApplication(Dna::new())));
+
+        let started = Instant::now();
+        instance.stop_action_loop();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(instance
+            .action_channel()
+            .try_send(test_action_wrapper_get())
+            .is_err());
+        // stopping again is harmless
+        instance.stop_action_loop();
+    }
+
+    /// leaves the locks of the instance poisoned, as a reducer panicking while the new state
+    /// is written and a subscription callback panicking would
+    fn poison_locks(instance: &Instance) {
+        let poisoner = instance.clone();
+        let result = thread::spawn(move || {
+            let _reduction = poisoner.reduction.lock().unwrap();
+            let _state = poisoner.state.write().unwrap();
+            let _action_subscriptions = poisoner.action_subscriptions.lock().unwrap();
+            let _state_subscriptions = poisoner.state_subscriptions.lock().unwrap();
+            let _lock_stats = poisoner.lock_stats.lock().unwrap();
+            let _scheduler_stats = poisoner.scheduler_stats.lock().unwrap();
+            let _deadlines = poisoner.deadlines.lock().unwrap();
+            panic!("simulated panic while reducing");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(instance.reduction.is_poisoned());
+        assert!(instance.state.is_poisoned());
+        assert!(instance.action_subscriptions.is_poisoned());
+    }
+
+    #[test]
+    /// after a panic poisoned its locks the instance still serves reads and reduces actions,
+    /// unless its policy is to panic as well
+    fn instance_recovers_from_poisoned_locks() {
+        let mut instance = Instance::new();
+        let (context, logger) = test_context_and_logger("jane");
+        let (_rx_action, rx_observer) = instance.initialize_channels();
+        poison_locks(&instance);
+
+        assert_eq!(None, instance.state().nucleus().dna());
+        let get = test_action_wrapper_get();
+        instance.process_action(get.clone(), Vec::new(), &rx_observer, &context);
+        assert!(instance.state().history.contains(&get));
+        assert!(instance.poison_recoveries() > 0);
+        // the other locks of the instance are recovered too
+        instance.subscribe_actions(ActionFilter::all(), |_| ());
+        instance.subscribe_state(|_| ());
+        assert!(instance.lock_stats().acquisitions > 0);
+        instance.scheduler_stats();
+        assert!(instance.lock_deadlines().is_empty());
+        let recovered = |logger: &Arc<Mutex<TestLogger>>| {
+            logger
+                .lock()
+                .unwrap()
+                .log
+                .iter()
+                .filter(|line| line.contains("poisoned"))
+                .count()
+        };
+        assert_eq!(1, recovered(&logger));
+        // logged once, not for every later use of the poisoned locks
+        instance.process_action(
+            test_action_wrapper_get(),
+            Vec::new(),
+            &rx_observer,
+            &context,
+        );
+        assert_eq!(1, recovered(&logger));
+
+        let mut context = (*test_context("jane")).clone();
+        context.poison_policy = PoisonPolicy::Panic;
+        let mut strict = Instance::new();
+        strict.start_action_loop(Arc::new(context));
+        poison_locks(&strict);
+        let reader = strict.clone();
+        assert!(thread::spawn(move || reader.state().history.len())
+            .join()
+            .is_err());
+        strict.stop_action_loop();
+    }
+
+    #[test]
+    /// an action still queued behind slow reductions at its deadline is dropped, not reduced late
+    fn late_action_is_dropped_at_its_deadline() {
+        let mut instance = Instance::new();
+        instance.start_action_loop(test_context("jane"));
+        let on_time = ActionWrapper::new(Action::ClearNegativeCache);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        assert_eq!(
+            Ok(()),
+            instance.dispatch_with_deadline(on_time.clone(), deadline)
+        );
+        assert!(instance.state().history.contains(&on_time));
+
+        // every reduction from now on takes a while
+        instance.subscribe_state(|_| sleep(Duration::from_millis(100)));
+        for _ in 0..5 {
+            instance.dispatch(ActionWrapper::new(Action::ClearNegativeCache));
+        }
+        let late = ActionWrapper::new(Action::ClearNegativeCache);
+        let dispatched = Instant::now();
+        assert_eq!(
+            Err(HolochainError::DeadlineExceeded),
+            instance.dispatch_with_deadline(late.clone(), dispatched + Duration::from_millis(50))
+        );
+        assert!(dispatched.elapsed() < Duration::from_millis(400));
+
+        // once the queue drained the late action still wasn't reduced
+        instance.dispatch_and_wait(ActionWrapper::new(Action::ClearNegativeCache));
+        assert!(!instance.state().history.contains(&late));
+    }
+
     #[test]
     /// tests that an unimplemented genesis allows the nucleus to initialize
     /// @TODO is this right? should return unimplemented?
@@ -688,4 +1318,41 @@ pub mod tests {
                 _ => false,
             });
     }
+
+    #[test]
+    /// a commit the agent refused neither reaches the DHT storage nor the publish queue
+    fn refused_commit_stays_out_of_dht() {
+        let mut context = (*test_context("alex")).clone();
+        context.auto_publish = false;
+        context.chain_rate_limit = Some(ChainRateLimit::new(1, Duration::from_secs(3600)));
+        let mut instance = Instance::new();
+        let (_rx_action, rx_observer) = instance.initialize_channels();
+        let context = instance.initialize_context(Arc::new(context));
+        let mut zome = Zome::default();
+        for entry_type in vec![test_entry_a().entry_type(), test_entry_b().entry_type()] {
+            zome.entry_types
+                .insert(entry_type.to_string(), EntryTypeDef::new());
+        }
+        let mut dna = Dna::new();
+        dna.zomes.insert("test_zome".to_string(), zome);
+        let init = ActionWrapper::new(Action::InitApplication(dna));
+        instance.process_action(init, Vec::new(), &rx_observer, &context);
+
+        let accepted = ActionWrapper::new(Action::Commit(test_entry_a()));
+        let refused = ActionWrapper::new(Action::Commit(test_entry_b()));
+        instance.process_action(accepted.clone(), Vec::new(), &rx_observer, &context);
+        instance.process_action(refused.clone(), Vec::new(), &rx_observer, &context);
+
+        let state = instance.state();
+        assert_eq!(
+            Some(&ActionResponse::Commit(Err(
+                HolochainError::ChainRateLimited
+            ))),
+            state.agent().actions().get(&refused)
+        );
+        let storage = state.dht().content_storage();
+        assert_eq!(Ok(true), storage.contains(&test_entry_a().address()));
+        assert_eq!(Ok(false), storage.contains(&test_entry_b().address()));
+        assert_eq!(&vec![test_entry_a().address()], state.dht().publish_queue());
+    }
 }
@@ -2,6 +2,8 @@
 
 #[macro_use]
 extern crate serde_derive;
+#[macro_use]
+extern crate bitflags;
 extern crate chrono;
 extern crate multihash;
 extern crate rust_base58;
@@ -17,8 +19,14 @@ extern crate wasmi;
 extern crate unwrap_to;
 #[macro_use]
 extern crate num_derive;
+extern crate flate2;
+extern crate hex;
 extern crate num_traits;
 extern crate regex;
+extern crate ring;
+#[cfg(test)]
+extern crate tempfile;
+extern crate untrusted;
 
 extern crate config;
 extern crate holochain_agent;
@@ -31,12 +39,18 @@ extern crate holochain_core_types;
 
 pub mod action;
 pub mod agent;
+pub mod clock;
 pub mod context;
 pub mod dht;
+pub mod diagnostics;
+pub mod history;
 pub mod instance;
 #[cfg(test)]
 pub mod link_tests;
 pub mod logger;
 pub mod nucleus;
 pub mod persister;
+pub mod random;
+pub mod recovery;
+pub mod scheduler;
 pub mod state;
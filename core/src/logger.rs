@@ -7,6 +7,18 @@ use chrono::Local;
 /// trait that defines the logging functionality that holochain_core requires
 pub trait Logger: Send {
     fn log(&mut self, msg: String);
+
+    /// logs a line emitted during the zome call with the id
+    /// loggers that don't render the id log the bare message
+    fn log_for_call(&mut self, _call_id: &str, msg: String) {
+        self.log(msg);
+    }
+
+    /// the last count lines logged, oldest first
+    /// loggers that don't keep the lines they logged have none
+    fn recent_lines(&self, _count: usize) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 #[derive(Clone)]
@@ -21,7 +33,32 @@ impl Logger for SimpleLogger {
         let date = Local::now();
         println!("{}:{}", date.format("%Y-%m-%d %H:%M:%S"), msg);
     }
+
+    fn log_for_call(&mut self, call_id: &str, msg: String) {
+        let date = Local::now();
+        println!("{}:[{}] {}", date.format("%Y-%m-%d %H:%M:%S"), call_id, msg);
+    }
     // fn new() -> SimpleLogger {
     //      SimpleLogger {}
     // }
 }
+
+/// Logger printing each line as a JSON object, for log collectors
+/// lines emitted during a zome call have a "call" field with the id of the call
+#[derive(Clone)]
+pub struct JsonLogger {}
+
+// ignore this in test coverage as it is only side effects
+#[cfg_attr(tarpaulin, skip)]
+impl Logger for JsonLogger {
+    fn log(&mut self, msg: String) {
+        println!("{}", json!({"time": Local::now().to_rfc3339(), "msg": msg}));
+    }
+
+    fn log_for_call(&mut self, call_id: &str, msg: String) {
+        println!(
+            "{}",
+            json!({"time": Local::now().to_rfc3339(), "call": call_id, "msg": msg})
+        );
+    }
+}
@@ -1,19 +1,75 @@
 extern crate serde_json;
 use context::Context;
+use dht::{
+    dht_store::zome_namespace,
+    network_worker::{entry_from_peer, signed_get},
+};
 use futures::{future, Future};
 use holochain_core_types::{
-    cas::{content::Address, storage::ContentAddressableStorage},
+    cas::{
+        content::{Address, AddressableContent},
+        storage::ContentAddressableStorage,
+    },
     entry::Entry,
     error::HolochainError,
 };
 use std::sync::Arc;
 
+/// the entry at the address held locally, None if it has an access control list the agent
+/// isn't on, whoever it was got from, e.g. a snapshot of another instance's DHT
 fn get_entry_from_dht_cas(
     context: &Arc<Context>,
     address: Address,
 ) -> Result<Option<Entry>, HolochainError> {
-    let dht = context.state().unwrap().dht().content_storage();
-    dht.fetch(&address)
+    let state = context.state().unwrap();
+    let readers = state.dht().readers(&address)?;
+    if !readers.is_empty() && !readers.contains(&context.agent.address()) {
+        return Ok(None);
+    }
+    let dht = state.dht().content_storage();
+    match dht.fetch(&address)? {
+        Some(entry) => Ok(Some(entry)),
+        None => context.storage_routes.fetch(&address),
+    }
+}
+
+/// How fresh the answer to a get of an entry held by peers has to be
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Consistency {
+    /// ask the network, fail with NetworkUnreachable if it can't be reached
+    Strong,
+    /// answer with the copy cached from an earlier get if there is one, even if it may be
+    /// stale, and ask the network otherwise
+    Eventual,
+}
+
+/// the entry at the address as held by this instance or else, skipping the cache, by the
+/// network, @see Consistency::Strong
+/// fails with NetworkUnreachable if the entry isn't held locally and the network can't be
+/// reached
+pub fn get_entry_strong(
+    context: &Arc<Context>,
+    address: &Address,
+) -> Result<Option<Entry>, HolochainError> {
+    let state = context.state().expect("context must have a State.");
+    let agent = state.agent();
+    let local = agent
+        .chain()
+        .content_storage()
+        .fetch(address)?
+        .or_else(|| agent.ephemeral_entry(address));
+    if local.is_some() {
+        return Ok(local);
+    }
+    let dht = state.dht();
+    if !dht.network().is_reachable() {
+        return Err(HolochainError::NetworkUnreachable);
+    }
+    // the peers refuse entries with an access control list the agent isn't on
+    match signed_get(context, dht.network(), address)? {
+        Some(content) => entry_from_peer(context, address, &content).map(Some),
+        None => Ok(None),
+    }
 }
 
 /// GetEntry Action Creator
@@ -29,11 +85,31 @@ pub fn get_entry(
     }
 }
 
+/// GetEntry Action Creator for the calls of a zome
+/// entries another zome keeps to its namespace aren't found, @see zome_namespace()
+pub fn get_entry_in_zome(
+    context: &Arc<Context>,
+    address: Address,
+    zome_name: &str,
+) -> Box<dyn Future<Item = Option<Entry>, Error = HolochainError>> {
+    let dht = context.state().expect("context must have a State.").dht();
+    match dht.is_in_namespace(&address, &zome_namespace(zome_name)) {
+        Err(err) => Box::new(future::err(err)),
+        Ok(false) => Box::new(future::ok(None)),
+        Ok(true) => get_entry(context, address),
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
+    use dht::dht_store::ACL_NAME;
     use futures::executor::block_on;
     use holochain_core_types::{
-        cas::{content::AddressableContent, storage::ContentAddressableStorage},
+        cas::{
+            content::{Address, AddressableContent},
+            storage::ContentAddressableStorage,
+        },
+        eav::{EntityAttributeValue, EntityAttributeValueStorage},
         entry::test_entry,
     };
     use instance::tests::test_context_with_state;
@@ -72,4 +148,31 @@ pub mod tests {
         assert_eq!(Ok(Some(entry.clone())), block_on(future));
     }
 
+    #[test]
+    /// an entry held locally isn't found by agents that aren't on its access control list
+    fn get_entry_from_dht_cas_checks_acl() {
+        let entry = test_entry();
+        let context = test_context_with_state();
+        let dht = context.state().unwrap().dht();
+        dht.content_storage().add(&entry).unwrap();
+        dht.meta_storage()
+            .add_eav(&EntityAttributeValue::new(
+                &entry.address(),
+                &ACL_NAME.to_string(),
+                &Address::from("someone else".to_string()),
+            ))
+            .unwrap();
+        let result = super::get_entry_from_dht_cas(&context, entry.address());
+        assert_eq!(Ok(None), result);
+
+        dht.meta_storage()
+            .add_eav(&EntityAttributeValue::new(
+                &entry.address(),
+                &ACL_NAME.to_string(),
+                &context.agent.address(),
+            ))
+            .unwrap();
+        let result = super::get_entry_from_dht_cas(&context, entry.address());
+        assert_eq!(Ok(Some(entry)), result);
+    }
 }
@@ -7,14 +7,24 @@ use holochain_core_types::entry::ToEntry;
 use holochain_dna::Dna;
 use instance::dispatch_action_and_wait;
 use nucleus::{
-    ribosome::callback::{genesis::genesis, CallbackParams, CallbackResult},
+    ribosome::callback::{genesis::genesis_unless_cancelled, CallbackParams, CallbackResult},
     state::NucleusStatus,
 };
-use std::{sync::Arc, thread, time::*};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::*,
+};
 
-/// Timeout in seconds for initialization process.
+/// Default timeout in seconds for initialization process.
 /// Future will resolve to an error after this duration.
-const INITIALIZATION_TIMEOUT: u64 = 30;
+pub const INITIALIZATION_TIMEOUT: u64 = 30;
+
+/// Error initialization fails with once it was cancelled
+pub const INITIALIZATION_CANCELLED: &str = "Initialization cancelled";
 
 /// Initialize Application, Action Creator
 /// This is the high-level initialization function that wraps the whole process of initializing an
@@ -27,6 +37,31 @@ const INITIALIZATION_TIMEOUT: u64 = 30;
 pub fn initialize_application(
     dna: Dna,
     context: Arc<Context>,
+) -> Box<dyn Future<Item = NucleusStatus, Error = String>> {
+    initialize_application_with_timeout(dna, context, Duration::from_secs(INITIALIZATION_TIMEOUT))
+}
+
+/// Same as initialize_application() but the returned future resolves to an error
+/// if initialization takes longer than the given timeout.
+pub fn initialize_application_with_timeout(
+    dna: Dna,
+    context: Arc<Context>,
+    timeout: Duration,
+) -> Box<dyn Future<Item = NucleusStatus, Error = String>> {
+    initialize_application_cancellable(dna, context, timeout, Arc::new(AtomicBool::new(false)))
+}
+
+/// Same as initialize_application_with_timeout() but initialization stops once cancelled is
+/// set, before the DNA is committed, before the wasm of the genesis of a zome starts or once
+/// the genesis of the last zome returned.
+/// The returned future then resolves to INITIALIZATION_CANCELLED.
+/// The instance may be stopped before a cancelled or timed out initialization ends, what it
+/// would report then is dropped.
+pub fn initialize_application_cancellable(
+    dna: Dna,
+    context: Arc<Context>,
+    timeout: Duration,
+    cancelled: Arc<AtomicBool>,
 ) -> Box<dyn Future<Item = NucleusStatus, Error = String>> {
     if context.state().unwrap().nucleus().status != NucleusStatus::New {
         return Box::new(future::err(
@@ -44,6 +79,32 @@ pub fn initialize_application(
             action_wrapper.clone(),
         );
 
+        // Read replicas share the chain of the instance that ran genesis,
+        // instances rebuilt from their storage ran it before
+        let has_chain = context_clone
+            .state()
+            .expect("context must have a State.")
+            .agent()
+            .top_chain_header()
+            .is_some();
+        if context_clone.read_only || has_chain {
+            // nobody waits for the result any more if the instance was stopped meanwhile
+            let _ = context_clone
+                .action_channel
+                .send(ActionWrapper::new(Action::ReturnInitializationResult(None)));
+            return;
+        }
+
+        let return_cancelled = || {
+            let _ = context_clone.action_channel.send(ActionWrapper::new(
+                Action::ReturnInitializationResult(Some(INITIALIZATION_CANCELLED.to_string())),
+            ));
+        };
+        if cancelled.load(Ordering::SeqCst) {
+            return_cancelled();
+            return;
+        }
+
         // Commit DNA to chain
         let dna_entry = dna.to_entry();
         let dna_commit = block_on(commit_entry(
@@ -59,22 +120,35 @@ pub fn initialize_application(
         #[cfg_attr(tarpaulin, skip)]
         {
             if dna_commit.is_err() {
-                context_clone
-                    .action_channel
-                    .send(ActionWrapper::new(Action::ReturnInitializationResult(
-                        Some(dna_commit.map_err(|e| e.to_string()).err().unwrap()),
-                    )))
-                    .expect("Action channel not usable in initialize_application()");
+                let _ = context_clone.action_channel.send(ActionWrapper::new(
+                    Action::ReturnInitializationResult(Some(
+                        dna_commit.map_err(|e| e.to_string()).err().unwrap(),
+                    )),
+                ));
                 return;
             };
         }
 
-        // map genesis across every zome
-        let results: Vec<_> = dna
-            .zomes
-            .keys()
-            .map(|zome_name| genesis(context_clone.clone(), zome_name, &CallbackParams::Genesis))
-            .collect();
+        // map genesis across every zome, unless cancelled meanwhile
+        let mut results = Vec::new();
+        for zome_name in dna.zomes.keys() {
+            match genesis_unless_cancelled(
+                context_clone.clone(),
+                zome_name,
+                &CallbackParams::Genesis,
+                &cancelled,
+            ) {
+                Some(result) => results.push(result),
+                None => {
+                    return_cancelled();
+                    return;
+                }
+            }
+        }
+        if cancelled.load(Ordering::SeqCst) {
+            return_cancelled();
+            return;
+        }
 
         let fail_result = results.iter().find(|ref r| match r {
             CallbackResult::Fail(_) => true,
@@ -89,17 +163,15 @@ pub fn initialize_application(
             None => None,
         };
 
-        context_clone
-            .action_channel
-            .send(ActionWrapper::new(Action::ReturnInitializationResult(
-                maybe_error,
-            )))
-            .expect("Action channel not usable in initialize_application()");
+        let _ = context_clone.action_channel.send(ActionWrapper::new(
+            Action::ReturnInitializationResult(maybe_error),
+        ));
     });
 
     Box::new(InitializationFuture {
         context: context.clone(),
         created_at: Instant::now(),
+        timeout,
     })
 }
 
@@ -108,6 +180,7 @@ pub fn initialize_application(
 pub struct InitializationFuture {
     context: Arc<Context>,
     created_at: Instant,
+    timeout: Duration,
 }
 
 impl Future for InitializationFuture {
@@ -124,9 +197,7 @@ impl Future for InitializationFuture {
         //
         cx.waker().wake();
 
-        if Instant::now().duration_since(self.created_at)
-            > Duration::from_secs(INITIALIZATION_TIMEOUT)
-        {
+        if Instant::now().duration_since(self.created_at) > self.timeout {
             return Err("Timeout while initializing".to_string());
         }
         if let Some(state) = self.context.state() {
@@ -12,11 +12,50 @@ use nucleus::ribosome::callback::{self, CallbackResult};
 use snowflake;
 use std::{sync::Arc, thread};
 
+/// How validation failures of committed entries are handled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationLevel {
+    /// entries failing validation are rejected
+    Strict,
+    /// entries failing validation are committed, the failure is logged as a warning
+    Lenient,
+    /// entries are committed without running validation
+    Off,
+}
+
+impl Default for ValidationLevel {
+    fn default() -> Self {
+        ValidationLevel::Strict
+    }
+}
+
+/// the result of validation under the validation level of the context
+/// failures pass under Lenient, after being logged
+fn apply_validation_level(
+    context: &Arc<Context>,
+    address: &HashString,
+    result: Result<(), String>,
+) -> Result<(), String> {
+    match result {
+        Err(error) if context.validation_level == ValidationLevel::Lenient => {
+            context
+                .log(&format!(
+                    "warning: committing entry {} despite failed validation: {}",
+                    address, error
+                ))
+                .expect("Logger should work");
+            Ok(())
+        }
+        result => result,
+    }
+}
+
 /// ValidateEntry Action Creator
 /// This is the high-level validate function that wraps the whole validation process and is what should
 /// be called from zome api functions and other contexts that don't care about implementation details.
 ///
 /// Returns a future that resolves to an Ok(ActionWrapper) or an Err(error_message:String).
+/// How failures are handled depends on the validation level of the context.
 pub fn validate_entry(
     entry_type: EntryType,
     entry: Entry,
@@ -26,6 +65,10 @@ pub fn validate_entry(
     let id = snowflake::ProcessUniqueId::new();
     let address = entry.address();
 
+    if context.validation_level == ValidationLevel::Off {
+        return Box::new(future::ok(address));
+    }
+
     match context
         .state()
         .unwrap()
@@ -35,10 +78,12 @@ pub fn validate_entry(
         .get_zome_name_for_entry_type(entry_type.as_str())
     {
         None => {
-            return Box::new(future::err(HolochainError::ValidationFailed(format!(
-                "Unknown entry type: '{}'",
-                entry_type.as_str()
-            ))));;
+            let unknown_type = Err(format!("Unknown entry type: '{}'", entry_type.as_str()));
+            return Box::new(future::result(
+                apply_validation_level(context, &address, unknown_type)
+                    .map(|_| address)
+                    .map_err(HolochainError::ValidationFailed),
+            ));
         }
         Some(_) => {
             let id = id.clone();
@@ -64,6 +109,7 @@ pub fn validate_entry(
                     },
                     Err(error) => Err(error.to_string()),
                 };
+                let result = apply_validation_level(&context, &address, result);
 
                 context
                     .action_channel
@@ -1,26 +1,31 @@
 /// Nucleus is the module that handles DNA, including the Ribosome.
 ///
 pub mod actions;
+pub mod revalidation;
 pub mod ribosome;
 pub mod state;
 
 use action::{Action, ActionWrapper, NucleusReduceFn};
+use agent::delegation::Delegation;
 use context::Context;
-use holochain_core_types::error::{DnaError, HolochainError};
+use holochain_core_types::error::{DnaError, HolochainError, ZomeCallError};
 use holochain_dna::{wasm::DnaWasm, zome::capabilities::Capability, Dna};
 use instance::{dispatch_action_with_observer, Observer};
 use nucleus::{
-    ribosome::api::call::reduce_call,
+    ribosome::{api::call::reduce_call, memory::MemoryLimitExceeded},
     state::{NucleusState, NucleusStatus},
 };
 use snowflake;
 use std::{
     sync::{
+        atomic::{AtomicBool, Ordering},
         mpsc::{sync_channel, SyncSender},
         Arc,
     },
     thread,
+    time::Duration,
 };
+use wasmi;
 
 /// Struct holding data for requesting the execution of a Zome function (ExecutionZomeFunction Action)
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -30,6 +35,8 @@ pub struct ZomeFnCall {
     pub cap_name: String,
     pub fn_name: String,
     pub parameters: String,
+    /// agent on whose behalf the call is made with the agent's proof, if any
+    pub delegation: Option<Delegation>,
 }
 
 impl ZomeFnCall {
@@ -42,9 +49,30 @@ impl ZomeFnCall {
             cap_name: capability.to_string(),
             fn_name: function.to_string(),
             parameters: parameters.to_string(),
+            delegation: None,
         }
     }
 
+    /// builds a call made on behalf of the delegate of the delegation
+    /// the delegation proof must be verified before dispatching the call
+    pub fn new_delegated(
+        delegation: &Delegation,
+        zome: &str,
+        capability: &str,
+        function: &str,
+        parameters: &str,
+    ) -> Self {
+        ZomeFnCall {
+            delegation: Some(delegation.clone()),
+            ..ZomeFnCall::new(zome, capability, function, parameters)
+        }
+    }
+
+    /// process unique id of the call, distinguishing concurrent calls to the same function
+    pub fn id(&self) -> snowflake::ProcessUniqueId {
+        self.id
+    }
+
     pub fn same_fn_as(&self, fn_call: &ZomeFnCall) -> bool {
         self.zome_name == fn_call.zome_name
             && self.cap_name == fn_call.cap_name
@@ -124,6 +152,70 @@ pub fn call_and_wait_for_result(
     receiver.recv().expect("local channel to work")
 }
 
+/// Dispatch ExecuteZomeFunction to Instance and block until call has finished,
+/// passing the progress the zome function reports to on_progress in the meantime.
+/// on_progress runs on the instance's thread and must not block
+/// Fails with CallTimedOut if there is no result within the timeout, on_progress isn't
+/// called anymore from then on.
+pub fn call_with_progress<F>(
+    call: ZomeFnCall,
+    instance: &mut super::instance::Instance,
+    on_progress: F,
+    timeout: Duration,
+) -> Result<String, HolochainError>
+where
+    F: 'static + Fn(ProgressUpdate) + Send,
+{
+    let call_action = ActionWrapper::new(Action::ExecuteZomeFunction(call.clone()));
+
+    // Dispatch action with observer closure that passes on new progress and waits for a result
+    // the observer is dropped with the result, or once the caller stopped waiting for it
+    let (sender, receiver) = sync_channel(1);
+    let abandoned = Arc::new(AtomicBool::new(false));
+    let observer_abandoned = abandoned.clone();
+    let mut reported = 0;
+    instance.dispatch_with_observer(call_action, move |state: &super::state::State| {
+        if observer_abandoned.load(Ordering::SeqCst) {
+            return true;
+        }
+        let nucleus = state.nucleus();
+        let progress = nucleus.progress(&call);
+        for update in progress.into_iter().skip(reported) {
+            on_progress(update);
+            reported += 1;
+        }
+        if let Some(result) = nucleus.zome_call_result(&call) {
+            // fails only if the caller timed out in the meantime
+            let _ = sender.send(result.clone());
+            true
+        } else {
+            false
+        }
+    });
+
+    // Block until we got that result through the channel:
+    receiver.recv_timeout(timeout).unwrap_or_else(|_| {
+        abandoned.store(true, Ordering::SeqCst);
+        Err(HolochainError::CallTimedOut)
+    })
+}
+
+/// Progress a running zome function reported through the hc_progress API function,
+/// e.g. for a progress bar
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ProgressUpdate {
+    /// whatever the zome function passed to hc_progress
+    pub message: String,
+}
+
+impl ProgressUpdate {
+    pub fn new(message: &str) -> Self {
+        ProgressUpdate {
+            message: message.to_string(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Hash)]
 pub struct ZomeFnResult {
     call: ZomeFnCall,
@@ -200,6 +292,14 @@ fn reduce_init_application(
     }
 }
 
+/// true if the call was aborted for using more memory than the context allows
+fn is_memory_limit_exceeded(error: &wasmi::Error) -> bool {
+    error
+        .as_host_error()
+        .and_then(|host_error| host_error.downcast_ref::<MemoryLimitExceeded>())
+        .is_some()
+}
+
 pub(crate) fn launch_zome_fn_call(
     context: Arc<Context>,
     fc: ZomeFnCall,
@@ -218,14 +318,30 @@ pub(crate) fn launch_zome_fn_call(
             Some(fc.clone().parameters.into_bytes()),
         ) {
             Ok(runtime) => {
-                result = ZomeFnResult::new(fc.clone(), Ok(runtime.result.to_string()));
+                // a function returning a serialized Err failed as well
+                let returned_error =
+                    ZomeCallError::returned(&fc.zome_name, &fc.fn_name, &runtime.result);
+                result = ZomeFnResult::new(
+                    fc.clone(),
+                    match returned_error {
+                        Some(error) => Err(HolochainError::ZomeCallFailed(error)),
+                        None => Ok(runtime.result.to_string()),
+                    },
+                );
+            }
+
+            Err(ref error) if is_memory_limit_exceeded(error) => {
+                result = ZomeFnResult::new(fc.clone(), Err(HolochainError::MemoryLimitExceeded));
             }
 
             Err(ref error) => {
-                result = ZomeFnResult::new(
-                    fc.clone(),
-                    Err(HolochainError::ErrorGeneric(format!("{}", error))),
+                let error = ZomeCallError::trapped(
+                    &fc.zome_name,
+                    &fc.fn_name,
+                    &format!("{}", error),
+                    &format!("{:?}", error),
                 );
+                result = ZomeFnResult::new(fc.clone(), Err(HolochainError::ZomeCallFailed(error)));
             }
         }
         // Send ReturnResult Action
@@ -362,6 +478,26 @@ fn reduce_return_zome_function_result(
     state.zome_calls.insert(fr.call(), Some(fr.result()));
 }
 
+/// Reduce ReportProgress Action
+/// Append the progress to the progress of its zome call
+#[allow(unknown_lints)]
+#[allow(needless_pass_by_value)]
+fn reduce_report_progress(
+    _context: Arc<Context>,
+    state: &mut NucleusState,
+    action_wrapper: &ActionWrapper,
+) {
+    let (call, update) = match action_wrapper.action() {
+        Action::ReportProgress(call, update) => (call, update),
+        _ => unreachable!(),
+    };
+    state
+        .progress
+        .entry(call.clone())
+        .or_insert_with(Vec::new)
+        .push(update.clone());
+}
+
 /// Maps incoming action to the correct reducer
 fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<NucleusReduceFn> {
     match action_wrapper.action() {
@@ -369,6 +505,7 @@ fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<NucleusReduceFn> {
         Action::InitApplication(_) => Some(reduce_init_application),
         Action::ExecuteZomeFunction(_) => Some(reduce_execute_zome_function),
         Action::ReturnZomeFunctionResult(_) => Some(reduce_return_zome_function_result),
+        Action::ReportProgress(_, _) => Some(reduce_report_progress),
         Action::Call(_) => Some(reduce_call),
         Action::ReturnValidationResult(_) => Some(reduce_return_validation_result),
         _ => None,
@@ -600,6 +737,69 @@ pub mod tests {
         }
     }
 
+    #[test]
+    /// tests that progress reported by a zome function reaches the caller before the result
+    fn call_zome_function_with_progress() {
+        let messages = vec!["half way", "almost done"];
+        let mut data = String::new();
+        let mut calls = String::new();
+        let mut offset: u32 = 4096;
+        for message in messages.iter() {
+            data.push_str(&format!("(data (i32.const {}) \"{}\")\n", offset, message));
+            calls.push_str(&format!(
+                "(drop (call $progress (i32.const {})))\n",
+                ((offset << 16) | message.len() as u32) as i32
+            ));
+            offset += 256;
+        }
+        let wat = format!(
+            r#"
+(module
+    (import "env" "hc_progress"
+        (func $progress
+            (param i32)
+            (result i32)
+        )
+    )
+
+    (memory 1)
+    (export "memory" (memory 0))
+    {}
+    (func
+        (export "main")
+        (param $allocation i32)
+        (result i32)
+
+        {}
+        (i32.const 0)
+    )
+)
+            "#,
+            data, calls
+        );
+        let dna = test_utils::create_test_dna_with_wat("test_zome", "test_cap", Some(&wat));
+        let mut instance = test_instance(dna).expect("Could not initialize test instance");
+
+        let (sender, receiver) = sync_channel(10);
+        let zome_call = ZomeFnCall::new("test_zome", "test_cap", "main", "");
+        let result = super::call_with_progress(
+            zome_call,
+            &mut instance,
+            move |update| sender.send(update).unwrap(),
+            Duration::from_secs(10),
+        );
+
+        assert_eq!(Ok(String::new()), result);
+        let updates: Vec<ProgressUpdate> = receiver.try_iter().collect();
+        assert_eq!(
+            messages
+                .iter()
+                .map(|message| ProgressUpdate::new(message))
+                .collect::<Vec<_>>(),
+            updates
+        );
+    }
+
     #[test]
     /// smoke test reducing over a nucleus
     fn can_reduce_execfn_action() {
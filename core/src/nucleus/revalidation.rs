@@ -0,0 +1,225 @@
+//! Checking the entries already on the source chain against the current validation rules.
+//!
+//! Revalidation only reports: nothing is removed from the chain or the DHT, so an operator
+//! can look at which entries became invalid after the validation callbacks of a DNA changed.
+//! Simulation does the same against a candidate DNA that isn't installed, so the outcome of an
+//! upgrade can be checked before it is made.
+
+use context::Context;
+use holochain_core_types::{
+    cas::{content::Address, storage::ContentAddressableStorage},
+    entry::Entry,
+    hash::HashString,
+};
+use holochain_dna::Dna;
+use holochain_wasm_utils::api_serialization::validation::{
+    EntryAction, EntryLifecycle, ValidationData,
+};
+use nucleus::ribosome::{
+    callback::{validate_entry::validate_entry, CallbackResult},
+    modules::ZomeModules,
+};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+};
+
+/// Outcome of running the local app entries through the current validation callbacks
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RevalidationReport {
+    results: BTreeMap<Address, Result<(), String>>,
+}
+
+impl RevalidationReport {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// the result of revalidating each entry, by address
+    pub fn results(&self) -> &BTreeMap<Address, Result<(), String>> {
+        &self.results
+    }
+
+    /// addresses of the entries that still pass validation, sorted
+    pub fn passed(&self) -> Vec<Address> {
+        self.results
+            .iter()
+            .filter(|(_, result)| result.is_ok())
+            .map(|(address, _)| address.clone())
+            .collect()
+    }
+
+    /// addresses of the entries that fail validation under the current rules, sorted
+    pub fn failed(&self) -> Vec<Address> {
+        self.results
+            .iter()
+            .filter(|(_, result)| result.is_err())
+            .map(|(address, _)| address.clone())
+            .collect()
+    }
+
+    /// true if every revalidated entry passed
+    pub fn all_passed(&self) -> bool {
+        self.results.values().all(|result| result.is_ok())
+    }
+}
+
+/// runs every app entry on the source chain through the validation callback of the DNA
+/// currently held by the context's state and reports the outcome per address
+/// entries whose type has no validation callback any more are reported as failed
+pub fn revalidate_chain(context: &Arc<Context>) -> RevalidationReport {
+    let agent = context.state().expect("context must have a State.").agent();
+    let chain = agent.chain();
+    let mut report = RevalidationReport::new();
+
+    for chain_header in chain.iter(&agent.top_chain_header()) {
+        if !chain_header.entry_type().is_app() {
+            continue;
+        }
+        let address = chain_header.entry_address().clone();
+        let maybe_entry: Option<Entry> = match chain.content_storage().fetch(&address) {
+            Ok(maybe_entry) => maybe_entry,
+            Err(error) => {
+                report.results.insert(address, Err(error.to_string()));
+                continue;
+            }
+        };
+        let entry = match maybe_entry {
+            Some(entry) => entry,
+            None => {
+                report
+                    .results
+                    .insert(address, Err("entry is missing from the chain".to_string()));
+                continue;
+            }
+        };
+
+        let result = match validate_entry(
+            entry,
+            chain_header.entry_type().clone(),
+            revalidation_data(),
+            context.clone(),
+        ) {
+            Ok(CallbackResult::Pass) => Ok(()),
+            Ok(CallbackResult::Fail(error_string)) => Err(error_string),
+            Ok(CallbackResult::NotImplemented) => Err(format!(
+                "Validation callback not implemented for {:?}",
+                chain_header.entry_type()
+            )),
+            Err(error) => Err(error.to_string()),
+        };
+        report.results.insert(address, result);
+    }
+
+    report
+}
+
+/// Outcome of running the local app entries through the validation callbacks of a candidate DNA
+pub type SimulationReport = RevalidationReport;
+
+/// runs every app entry on the source chain through the validation callbacks of the candidate
+/// DNA instead of the installed one and reports the outcome per address, @see revalidate_chain()
+/// the callbacks run against a copy of the state holding the candidate, with their own loaded
+/// modules, so neither the state nor the modules of the context change
+pub fn simulate_validation(context: &Arc<Context>, candidate: Dna) -> SimulationReport {
+    let state = context
+        .state()
+        .expect("context must have a State.")
+        .with_dna(candidate);
+    let mut simulation_context = (**context).clone();
+    simulation_context.set_state(Arc::new(RwLock::new(state)));
+    simulation_context.zome_modules = ZomeModules::new();
+    revalidate_chain(&Arc::new(simulation_context))
+}
+
+fn revalidation_data() -> ValidationData {
+    // TODO: populate with chain content, same as the validation data of a commit
+    // @see nucleus::ribosome::api::commit::build_validation_data_commit
+    ValidationData {
+        chain_header: None,
+        sources: vec![HashString::from("<insert your agent key here>")],
+        source_chain_entries: None,
+        source_chain_headers: None,
+        custom: None,
+        lifecycle: EntryLifecycle::Chain,
+        action: EntryAction::Commit,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate test_utils;
+    use super::*;
+    use action::{Action, ActionWrapper};
+    use holochain_core_types::{
+        cas::content::AddressableContent,
+        entry::{test_entry, test_entry_b},
+    };
+    use instance::tests::{test_context, test_instance};
+
+    /// wat validating entries of testEntryType and failing those of testEntryTypeB
+    fn test_updated_validator_wat() -> String {
+        // the failure message lives far above the stack the ribosome writes parameters to
+        let offset: u32 = 4096;
+        let message = "entry type B is not allowed any more";
+        format!(
+            r#"
+(module
+    (memory 1)
+    (export "memory" (memory 0))
+    (data (i32.const {}) "{}")
+
+    (func
+        (export "validate_testEntryType")
+        (param $allocation i32)
+        (result i32)
+
+        (i32.const 0)
+    )
+
+    (func
+        (export "validate_testEntryTypeB")
+        (param $allocation i32)
+        (result i32)
+
+        (i32.const {})
+    )
+)
+            "#,
+            offset,
+            message,
+            ((offset << 16) | message.len() as u32) as i32,
+        )
+    }
+
+    #[test]
+    /// only the entry failing the updated validator is flagged, and nothing is removed
+    fn revalidation_flags_entries_failing_updated_rules() {
+        let dna = test_utils::create_test_dna_with_wat(
+            "test_zome",
+            "test_cap",
+            Some(&test_updated_validator_wat()),
+        );
+        let mut instance = test_instance(dna).expect("Could not create test instance");
+        // committed directly, as if they had been validated under the old rules
+        for entry in vec![test_entry(), test_entry_b()] {
+            instance.dispatch_and_wait(ActionWrapper::new(Action::Commit(entry)));
+        }
+        let context = instance.initialize_context(test_context("jane"));
+        let top_chain_header = instance.state().agent().top_chain_header();
+
+        let report = revalidate_chain(&context);
+
+        assert_eq!(vec![test_entry_b().address()], report.failed());
+        assert_eq!(vec![test_entry().address()], report.passed());
+        assert_eq!(
+            Some(&Err("entry type B is not allowed any more".to_string())),
+            report.results().get(&test_entry_b().address())
+        );
+        assert!(!report.all_passed());
+        assert_eq!(
+            top_chain_header,
+            instance.state().agent().top_chain_header()
+        );
+    }
+}
@@ -1,5 +1,8 @@
 extern crate futures;
+use action::{Action, ActionWrapper};
 use agent::{actions::commit::*, state::AgentState};
+use context::Context;
+use dht::dht_store::zome_namespace;
 use futures::{executor::block_on, FutureExt};
 use holochain_core_types::{
     cas::content::Address, entry::Entry, entry_type::EntryType, error::HolochainError,
@@ -9,12 +12,22 @@ use holochain_wasm_utils::api_serialization::{
     commit::{CommitEntryArgs, CommitEntryResult},
     validation::{EntryAction, EntryLifecycle, ValidationData},
 };
-use nucleus::{actions::validate::*, ribosome::api::Runtime};
+use instance::dispatch_action;
+use nucleus::{
+    actions::validate::*,
+    ribosome::{
+        api::Runtime,
+        callback::{
+            invariant::{check_invariants, has_invariants},
+            post_commit::queue_post_commit,
+        },
+    },
+};
 use serde_json;
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 use wasmi::{RuntimeArgs, RuntimeValue, Trap};
 
-fn build_validation_data_commit(
+pub(crate) fn build_validation_data_commit(
     _entry: Entry,
     _entry_type: EntryType,
     _state: &AgentState,
@@ -40,6 +53,18 @@ fn build_validation_data_commit(
     }
 }
 
+/// whether the DNA gives committed entries of the type a nonce
+fn is_nonced(context: &Arc<Context>, entry_type: &EntryType) -> bool {
+    if !entry_type.to_owned().is_app() {
+        return false;
+    }
+    context
+        .state()
+        .and_then(|state| state.nucleus().dna())
+        .and_then(|dna| dna.get_entry_type_def(&entry_type.to_string()).cloned())
+        .map_or(false, |entry_type_def| entry_type_def.nonced)
+}
+
 /// ZomeApiFunction::CommitAppEntry function code
 /// args: [0] encoded MemoryAllocation as u32
 /// Expected complex argument: CommitArgs
@@ -59,13 +84,37 @@ pub fn invoke_commit_app_entry(
     // Create Chain Entry
     let entry_type =
         EntryType::from_str(&input.entry_type_name).expect("could not create EntryType from str");
-    let entry = Entry::new(&entry_type, &input.entry_value);
+    let content = runtime
+        .context
+        .canonicalize_content(&entry_type, &input.entry_value);
+    let mut entry = Entry::new(&entry_type, &content);
+    // taken from the generator of the call so replays give the same addresses
+    if is_nonced(&runtime.context, &entry_type) {
+        entry = entry.with_nonce(&format!("{:016x}", runtime.rng.next_u64()));
+    }
+    // entries of types addressed by a key can be got by it without an index
+    match runtime.context.address_key(&entry) {
+        Ok(Some(key)) => entry = entry.with_address_key(&key),
+        Ok(None) => (),
+        Err(error) => {
+            let error_report =
+                ribosome_error_report!(format!("Call to `hc_commit_entry()` failed: {}", error));
+            return match serde_json::to_string(&error_report.to_string()) {
+                Ok(json) => runtime.store_utf8(&json),
+                Err(_) => ribosome_error_code!(ResponseSerializationFailed),
+            };
+        }
+    }
     let validation_data = build_validation_data_commit(
         entry.clone(),
         entry_type.clone(),
         &runtime.context.state().unwrap().agent(),
     );
 
+    // entries an invariant watches stay in the publish queue until the invariants were checked,
+    // so the network never gets a commit that is rolled back
+    let checks_invariants = has_invariants(&runtime.context, &entry_type);
+
     // Wait for future to be resolved
     let task_result: Result<Address, HolochainError> = block_on(
         // First validate entry:
@@ -73,13 +122,57 @@ pub fn invoke_commit_app_entry(
             entry_type.clone(),
             entry.clone(),
             validation_data,
-            &runtime.context)
-            // if successful, commit entry:
-            .and_then(|_| commit_entry(entry.clone(), &runtime.context.action_channel, &runtime.context)),
+            &runtime.context,
+        )
+        // if successful, commit entry:
+        .and_then(|_| {
+            commit_entry_with_options(
+                entry.clone(),
+                CommitOptions {
+                    delegation: runtime.zome_call.delegation.clone(),
+                    content_type: input.content_type.clone(),
+                    acl: input.acl.clone(),
+                    namespace: Some(zome_namespace(&runtime.zome_call.zome_name)),
+                    hold_publish: checks_invariants,
+                },
+                &runtime.context.action_channel,
+                &runtime.context,
+            )
+        }),
     );
 
+    // a commit breaking an invariant of the DNA is taken back before anyone is told about it
+    // if another commit was made on top of it meanwhile it can't be, which is an error of its
+    // own rather than a failed validation: the entry stays on the chain
+    let task_result = task_result.and_then(|address| {
+        if !checks_invariants {
+            return Ok(address);
+        }
+        match check_invariants(runtime.context.clone(), &entry_type, &address) {
+            Ok(()) => {
+                if runtime.context.auto_publish {
+                    dispatch_action(
+                        &runtime.context.action_channel,
+                        ActionWrapper::new(Action::FlushPublishes),
+                    );
+                }
+                Ok(address)
+            }
+            Err(error) => match roll_back_commit(address, &runtime.context) {
+                Ok(()) => Err(error),
+                Err(roll_back_error) => Err(HolochainError::ErrorGeneric(format!(
+                    "{}, but the commit could not be rolled back: {}",
+                    error, roll_back_error
+                ))),
+            },
+        }
+    });
+
     let maybe_json = match task_result {
-        Ok(address) => serde_json::to_string(&CommitEntryResult::success(address)),
+        Ok(address) => {
+            queue_post_commit(runtime.context.clone(), entry_type, address.clone());
+            serde_json::to_string(&CommitEntryResult::success(address))
+        }
         Err(HolochainError::ValidationFailed(fail_string)) => {
             serde_json::to_string(&CommitEntryResult::failure(fail_string))
         }
@@ -123,6 +216,8 @@ pub mod tests {
         let args = CommitEntryArgs {
             entry_type_name: entry_type.to_string(),
             entry_value: entry.value().to_owned(),
+            content_type: None,
+            acl: None,
         };
         serde_json::to_string(&args)
             .expect("args should serialize")
@@ -1,6 +1,6 @@
 use futures::executor::block_on;
 use holochain_wasm_utils::api_serialization::get_entry::{GetEntryArgs, GetEntryResult};
-use nucleus::{actions::get_entry::get_entry, ribosome::api::Runtime};
+use nucleus::{actions::get_entry::get_entry_in_zome, ribosome::api::Runtime};
 use serde_json;
 use wasmi::{RuntimeArgs, RuntimeValue, Trap};
 
@@ -21,7 +21,11 @@ pub fn invoke_get_entry(
     }
     let input = res_entry.unwrap();
 
-    let future = get_entry(&runtime.context, input.address);
+    let future = get_entry_in_zome(
+        &runtime.context,
+        input.address,
+        &runtime.zome_call.zome_name,
+    );
     let result = block_on(future);
     match result {
         Err(_) => ribosome_error_code!(Unspecified),
@@ -0,0 +1,104 @@
+use action::{Action, ActionWrapper};
+use holochain_core_types::links_entry::Link;
+use nucleus::ribosome::api::Runtime;
+use serde_json;
+use std::sync::mpsc::channel;
+use wasmi::{RuntimeArgs, RuntimeValue, Trap};
+
+/// ZomeApiFunction::LinkEntries function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expected complex argument: Link
+/// Returns an HcApiReturnCode as I32
+pub fn invoke_link_entries(
+    runtime: &mut Runtime,
+    args: &RuntimeArgs,
+) -> Result<Option<RuntimeValue>, Trap> {
+    // deserialize args
+    let args_str = runtime.load_utf8_from_args(&args);
+    let link: Link = match serde_json::from_str(&args_str) {
+        Ok(link) => link,
+        // Exit on error
+        Err(_) => return ribosome_error_code!(ArgumentDeserializationFailed),
+    };
+
+    let action_wrapper = ActionWrapper::new(Action::AddLink(link));
+    // Send Action and block for result
+    let (sender, receiver) = channel();
+    ::instance::dispatch_action_with_observer(
+        &runtime.context.action_channel,
+        &runtime.context.observer_channel,
+        action_wrapper.clone(),
+        move |state: &::state::State| match state.dht().add_link_results().get(&action_wrapper) {
+            Some(result) => {
+                // @TODO never panic in wasm
+                // @see https://github.com/holochain/holochain-rust/issues/159
+                sender
+                    .send(result.clone())
+                    // the channel stays connected until the first message has been sent
+                    // if this fails that means that it was called after having returned done=true
+                    .expect("observer called after done");
+                true
+            }
+            None => false,
+        },
+    );
+
+    match receiver.recv().expect("observer dropped before done") {
+        // Return Ribosome Success Code
+        Ok(()) => Ok(Some(RuntimeValue::I32(0 as i32))),
+        Err(error) => {
+            let error_report =
+                ribosome_error_report!(format!("Call to `hc_link_entries()` failed: {}", error));
+            match serde_json::to_string(&error_report) {
+                Ok(json) => runtime.store_utf8(&json),
+                Err(_) => ribosome_error_code!(ResponseSerializationFailed),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use holochain_core_types::{
+        cas::content::AddressableContent,
+        entry::{test_entry, test_entry_b},
+        links_entry::Link,
+    };
+    use nucleus::ribosome::{
+        api::{tests::test_zome_api_function_runtime, ZomeApiFunction},
+        Defn,
+    };
+    use serde_json;
+
+    /// dummy link args between the two standard test entries
+    pub fn test_link_args_bytes() -> Vec<u8> {
+        let link = Link::new(
+            &test_entry().address(),
+            &test_entry_b().address(),
+            "test-tag",
+        );
+        serde_json::to_string(&link)
+            .expect("link should serialize")
+            .into_bytes()
+    }
+
+    #[test]
+    /// test that a link added through the zome API is stored in the DHT
+    fn test_link_entries() {
+        let (runtime, _) = test_zome_api_function_runtime(
+            ZomeApiFunction::LinkEntries.as_str(),
+            test_link_args_bytes(),
+        );
+
+        assert_eq!(runtime.result, String::new());
+        assert_eq!(
+            Ok(vec!["test-tag".to_string()]),
+            runtime
+                .context
+                .state()
+                .unwrap()
+                .dht()
+                .link_tags(&test_entry().address()),
+        );
+    }
+}
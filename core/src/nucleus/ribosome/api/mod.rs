@@ -7,6 +7,9 @@ pub mod debug;
 pub mod get_entry;
 pub mod get_links;
 pub mod init_globals;
+pub mod link_entries;
+pub mod progress;
+pub mod random;
 use context::Context;
 use holochain_dna::zome::capabilities::ReservedCapabilityNames;
 use holochain_wasm_utils::{
@@ -18,13 +21,15 @@ use nucleus::{
         api::{
             call::invoke_call, commit::invoke_commit_app_entry, debug::invoke_debug,
             get_entry::invoke_get_entry, init_globals::invoke_init_globals,
+            link_entries::invoke_link_entries, progress::invoke_progress, random::invoke_random,
         },
-        memory::SinglePageManager,
+        memory::{MemoryLimitExceeded, SinglePageManager},
         Defn,
     },
     ZomeFnCall,
 };
 use num_traits::FromPrimitive;
+use random::SeededRng;
 use std::{str::FromStr, sync::Arc};
 use wasmi::{
     self, Error as InterpreterError, Externals, FuncInstance, FuncRef, ImportsBuilder,
@@ -73,6 +78,18 @@ pub enum ZomeApiFunction {
     /// Call a zome function in a different capability or zome
     /// hc_call(zome_name: String, cap_name: String, fn_name: String, args: String);
     Call,
+
+    /// Add a tagged link from a base entry to a target entry
+    /// hc_link_entries(base: Address, target: Address, tag: String)
+    LinkEntries,
+
+    /// Report progress of the running zome function to its caller
+    /// hc_progress(message: String)
+    Progress,
+
+    /// Get the next number of the deterministic random generator of the call
+    /// hc_random() -> u64
+    Random,
 }
 
 impl Defn for ZomeApiFunction {
@@ -85,6 +102,9 @@ impl Defn for ZomeApiFunction {
             ZomeApiFunction::GetAppEntry => "hc_get_entry",
             ZomeApiFunction::InitGlobals => "hc_init_globals",
             ZomeApiFunction::Call => "hc_call",
+            ZomeApiFunction::LinkEntries => "hc_link_entries",
+            ZomeApiFunction::Progress => "hc_progress",
+            ZomeApiFunction::Random => "hc_random",
         }
     }
 
@@ -120,6 +140,9 @@ impl FromStr for ZomeApiFunction {
             "hc_get_entry" => Ok(ZomeApiFunction::GetAppEntry),
             "hc_init_globals" => Ok(ZomeApiFunction::InitGlobals),
             "hc_call" => Ok(ZomeApiFunction::Call),
+            "hc_link_entries" => Ok(ZomeApiFunction::LinkEntries),
+            "hc_progress" => Ok(ZomeApiFunction::Progress),
+            "hc_random" => Ok(ZomeApiFunction::Random),
             _ => Err("Cannot convert string to ZomeApiFunction"),
         }
     }
@@ -146,6 +169,9 @@ impl ZomeApiFunction {
             ZomeApiFunction::GetAppEntry => invoke_get_entry,
             ZomeApiFunction::InitGlobals => invoke_init_globals,
             ZomeApiFunction::Call => invoke_call,
+            ZomeApiFunction::LinkEntries => invoke_link_entries,
+            ZomeApiFunction::Progress => invoke_progress,
+            ZomeApiFunction::Random => invoke_random,
         }
     }
 }
@@ -162,9 +188,28 @@ pub struct Runtime {
     memory_manager: SinglePageManager,
     zome_call: ZomeFnCall,
     pub app_name: String,
+    /// most bytes of WASM memory the call had at any check
+    pub peak_memory: usize,
+    /// seed of the random numbers of the call, from the seed source of the context
+    pub seed: u64,
+    rng: SeededRng,
 }
 
 impl Runtime {
+    /// records the current WASM memory size in the peak, and fails with MemoryLimitExceeded
+    /// if the memory of the call is over the cap of the context
+    /// memory is checked before the call, at every Zome API function call and after the call
+    pub fn track_memory(&mut self) -> Result<(), MemoryLimitExceeded> {
+        let used = self.memory_manager.size();
+        if used > self.peak_memory {
+            self.peak_memory = used;
+        }
+        match self.context.max_call_memory {
+            Some(limit) if used > limit => Err(MemoryLimitExceeded { used, limit }),
+            _ => Ok(()),
+        }
+    }
+
     /// Load a string stored in wasm memory.
     /// Input RuntimeArgs should only have one input which is the encoded allocation holding
     /// the complex data as an utf8 string.
@@ -228,8 +273,11 @@ pub fn call(
     zome_call: &ZomeFnCall,
     parameters: Option<Vec<u8>>,
 ) -> Result<Runtime, InterpreterError> {
-    // Create wasm module from wasm binary
-    let module = wasmi::Module::from_buffer(wasm).expect("wasm should be valid");
+    // Load the wasm module of the zome, unless an earlier call did
+    let module = context
+        .zome_modules
+        .load(&zome_call.zome_name, &wasm)
+        .expect("wasm should be valid");
 
     // invoke_index and resolve_func work together to enable callable host functions
     // within WASM modules, which is how the core API functions
@@ -243,6 +291,8 @@ pub fn call(
             index: usize,
             args: RuntimeArgs,
         ) -> Result<Option<RuntimeValue>, Trap> {
+            self.track_memory()
+                .map_err(|error| Trap::new(TrapKind::Host(Box::new(error))))?;
             let zf = ZomeApiFunction::from_index(index);
             match zf {
                 ZomeApiFunction::MissingNo => panic!("unknown function index"),
@@ -307,14 +357,27 @@ pub fn call(
     // write input arguments for module call in memory Buffer
     let input_parameters: Vec<_> = parameters.unwrap_or_default();
 
+    // lines logged during the call are tagged with its id
+    let mut call_context = (*context).clone();
+    call_context.call_id = Some(zome_call.id().to_string());
+
+    let seed = context.seed_source.seed(zome_call);
+
     // instantiate runtime struct for passing external state data over wasm but not to wasm
     let mut runtime = Runtime {
-        context,
+        context: Arc::new(call_context),
         result: String::new(),
         memory_manager: SinglePageManager::new(&wasm_instance),
         zome_call: zome_call.clone(),
         app_name: app_name.to_string(),
+        peak_memory: 0,
+        seed,
+        rng: SeededRng::new(seed),
     };
+    // the memory the module declares counts toward the cap too
+    runtime
+        .track_memory()
+        .map_err(|error| InterpreterError::Host(Box::new(error)))?;
 
     // Write input arguments in wasm memory
     // scope for mutable borrow of runtime
@@ -354,6 +417,10 @@ pub fn call(
             .try_into()
             .unwrap();
     }
+    // growing memory doesn't call into the host, so it is only caught once the call returns
+    runtime
+        .track_memory()
+        .map_err(|error| InterpreterError::Host(Box::new(error)))?;
 
     // Handle result returned by invoked function
     let maybe_allocation = decode_encoded_allocation(returned_encoded_allocation);
@@ -390,18 +457,38 @@ pub mod tests {
         tests::{test_context_and_logger, test_instance, TestLogger},
         Instance,
     };
+    use logger::Logger;
     use nucleus::{
         ribosome::{
             api::{call, Runtime},
+            memory::MemoryLimitExceeded,
             Defn,
         },
         ZomeFnCall,
     };
+    use persister::SimplePersister;
     use std::{
         str::FromStr,
         sync::{Arc, Mutex},
+        thread,
     };
 
+    /// logger keeping the id of the call each line was logged for
+    #[derive(Clone, Debug, Default)]
+    pub struct CallTagLogger {
+        pub log: Vec<(Option<String>, String)>,
+    }
+
+    impl Logger for CallTagLogger {
+        fn log(&mut self, msg: String) {
+            self.log.push((None, msg));
+        }
+
+        fn log_for_call(&mut self, call_id: &str, msg: String) {
+            self.log.push((Some(call_id.to_string()), msg));
+        }
+    }
+
     use holochain_dna::zome::capabilities::ReservedCapabilityNames;
 
     /// generates the wasm to dispatch any zome API function with a single memomry managed runtime
@@ -539,7 +626,8 @@ pub mod tests {
                 wasm.clone(),
                 &zome_call,
                 Some(args_bytes),
-            ).expect("test should be callable"),
+            )
+            .expect("test should be callable"),
             logger,
         )
     }
@@ -579,6 +667,9 @@ pub mod tests {
             ("hc_get_entry", ZomeApiFunction::GetAppEntry),
             ("hc_init_globals", ZomeApiFunction::InitGlobals),
             ("hc_call", ZomeApiFunction::Call),
+            ("hc_link_entries", ZomeApiFunction::LinkEntries),
+            ("hc_progress", ZomeApiFunction::Progress),
+            ("hc_random", ZomeApiFunction::Random),
         ] {
             assert_eq!(ZomeApiFunction::from_str(input).unwrap(), output);
         }
@@ -601,6 +692,9 @@ pub mod tests {
             (ZomeApiFunction::GetAppEntry, "hc_get_entry"),
             (ZomeApiFunction::InitGlobals, "hc_init_globals"),
             (ZomeApiFunction::Call, "hc_call"),
+            (ZomeApiFunction::LinkEntries, "hc_link_entries"),
+            (ZomeApiFunction::Progress, "hc_progress"),
+            (ZomeApiFunction::Random, "hc_random"),
         ] {
             assert_eq!(output, input.as_str());
         }
@@ -614,6 +708,9 @@ pub mod tests {
             ("hc_get_entry", 4),
             ("hc_init_globals", 5),
             ("hc_call", 6),
+            ("hc_link_entries", 7),
+            ("hc_progress", 8),
+            ("hc_random", 9),
         ] {
             assert_eq!(output, ZomeApiFunction::str_to_index(input));
         }
@@ -627,9 +724,132 @@ pub mod tests {
             (4, ZomeApiFunction::GetAppEntry),
             (5, ZomeApiFunction::InitGlobals),
             (6, ZomeApiFunction::Call),
+            (7, ZomeApiFunction::LinkEntries),
+            (8, ZomeApiFunction::Progress),
+            (9, ZomeApiFunction::Random),
         ] {
             assert_eq!(output, ZomeApiFunction::from_index(input));
         }
     }
 
+    #[test]
+    /// lines logged by concurrent zome calls are tagged with the id of the call logging them
+    fn concurrent_calls_tag_their_log_lines() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::Debug.as_str());
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            wasm.clone(),
+        );
+        let app_name = dna.name.to_string();
+        let instance = test_instance(dna).expect("Could not create test instance");
+        let logger = Arc::new(Mutex::new(CallTagLogger::default()));
+        let context = instance.initialize_context(Arc::new(Context::new(
+            holochain_agent::Agent::from("joan".to_string()),
+            logger.clone(),
+            Arc::new(Mutex::new(SimplePersister::new())),
+        )));
+
+        let zome_calls: Vec<ZomeFnCall> = (0..2)
+            .map(|_| {
+                ZomeFnCall::new(
+                    &test_zome_name(),
+                    &test_capability(),
+                    &test_function_name(),
+                    &test_parameters(),
+                )
+            })
+            .collect();
+        let handles: Vec<_> = zome_calls
+            .iter()
+            .cloned()
+            .map(|zome_call| {
+                let (app_name, context, wasm) = (app_name.clone(), context.clone(), wasm.clone());
+                thread::spawn(move || {
+                    call(&app_name, context, wasm, &zome_call, Some(b"foo".to_vec()))
+                        .expect("test should be callable");
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().expect("zome call thread shouldn't panic");
+        }
+
+        let log = logger.lock().unwrap().log.clone();
+        assert_eq!(2, log.len());
+        for zome_call in zome_calls {
+            let call_id = Some(zome_call.id().to_string());
+            let lines: Vec<String> = log
+                .iter()
+                .filter(|(tag, _)| *tag == call_id)
+                .map(|(_, msg)| msg.clone())
+                .collect();
+            assert_eq!(
+                vec!["Zome Function 'test' returned: Success".to_string()],
+                lines
+            );
+        }
+        // the context the calls were given is not tagged
+        assert_eq!(None, context.call_id);
+    }
+
+    /// WASM whose "test" function grows its memory by the given number of pages
+    fn test_memory_hungry_wasm(pages: u32) -> Vec<u8> {
+        Wat2Wasm::new()
+            .canonicalize_lebs(false)
+            .write_debug_names(true)
+            .convert(format!(
+                r#"
+(module
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func
+        (export "test")
+        (param $allocation i32)
+        (result i32)
+
+        (drop (grow_memory (i32.const {})))
+        (i32.const 0)
+    )
+)
+                "#,
+                pages
+            ))
+            .unwrap()
+            .as_ref()
+            .to_vec()
+    }
+
+    #[test]
+    /// a call growing its memory past the cap of the context is aborted, the peak is measured
+    fn memory_hungry_call_is_aborted() {
+        let wasm = test_memory_hungry_wasm(9);
+        let zome_call = ZomeFnCall::new(
+            &test_zome_name(),
+            &test_capability(),
+            &test_function_name(),
+            &test_parameters(),
+        );
+        let (context, _) = test_context_and_logger("joan");
+
+        let runtime = call("test", context.clone(), wasm.clone(), &zome_call, None)
+            .expect("uncapped call should succeed");
+        assert_eq!(10 * 65536, runtime.peak_memory);
+
+        let mut capped = (*context).clone();
+        capped.max_call_memory = Some(2 * 65536);
+        let error = call("test", Arc::new(capped), wasm, &zome_call, None)
+            .err()
+            .expect("capped call should be aborted");
+        assert_eq!(
+            Some(&MemoryLimitExceeded {
+                used: 10 * 65536,
+                limit: 2 * 65536,
+            }),
+            error
+                .as_host_error()
+                .and_then(|host_error| host_error.downcast_ref::<MemoryLimitExceeded>())
+        );
+    }
 }
@@ -0,0 +1,25 @@
+use action::{Action, ActionWrapper};
+use instance::dispatch_action;
+use nucleus::{ribosome::api::Runtime, ProgressUpdate};
+use wasmi::{RuntimeArgs, RuntimeValue, Trap};
+
+/// ZomeApiFunction::Progress function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expecting a string as complex input argument
+/// Returns an HcApiReturnCode as I32
+/// Does not wait for the progress to reach the caller
+pub fn invoke_progress(
+    runtime: &mut Runtime,
+    args: &RuntimeArgs,
+) -> Result<Option<RuntimeValue>, Trap> {
+    let message = runtime.load_utf8_from_args(args);
+    dispatch_action(
+        &runtime.context.action_channel,
+        ActionWrapper::new(Action::ReportProgress(
+            runtime.zome_call.clone(),
+            ProgressUpdate::new(&message),
+        )),
+    );
+    // Return Ribosome Success Code
+    Ok(Some(RuntimeValue::I32(0 as i32)))
+}
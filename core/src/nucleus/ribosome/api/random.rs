@@ -0,0 +1,33 @@
+use nucleus::ribosome::api::Runtime;
+use wasmi::{RuntimeArgs, RuntimeValue, Trap};
+
+/// ZomeApiFunction::Random function code
+/// args: [0] encoded MemoryAllocation as u32, ignored
+/// Returns an HcApiReturnCode as I32, the next random number of the call in JSON
+/// The numbers only depend on the seed of the call, so replaying the call gives the same ones
+pub fn invoke_random(
+    runtime: &mut Runtime,
+    _args: &RuntimeArgs,
+) -> Result<Option<RuntimeValue>, Trap> {
+    let number = runtime.rng.next_u64();
+    runtime.store_utf8(&number.to_string())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use nucleus::ribosome::{
+        api::{tests::test_zome_api_function_runtime, ZomeApiFunction},
+        Defn,
+    };
+    use random::{CallIdSeed, SeedSource, SeededRng};
+
+    #[test]
+    /// the call gets the first number of the generator seeded for it
+    fn test_random() {
+        let (runtime, _) =
+            test_zome_api_function_runtime(ZomeApiFunction::Random.as_str(), Vec::new());
+        let expected = SeededRng::new(runtime.seed).next_u64();
+        assert_eq!(format!("{}\u{0}", expected), runtime.result);
+        assert_eq!(CallIdSeed {}.seed(&runtime.zome_call), runtime.seed);
+    }
+}
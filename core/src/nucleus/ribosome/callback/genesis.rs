@@ -1,7 +1,7 @@
-use super::call;
+use super::{call, call_unless_cancelled};
 use context::Context;
 use nucleus::ribosome::callback::{Callback, CallbackParams, CallbackResult};
-use std::sync::Arc;
+use std::sync::{atomic::AtomicBool, Arc};
 
 pub fn genesis(
     context: Arc<Context>,
@@ -12,15 +12,27 @@ pub fn genesis(
     call(context, zome, &Callback::Genesis, params)
 }
 
+/// the genesis of the zome, None if cancelled was set before its wasm started
+/// @see initialize_application_cancellable()
+pub fn genesis_unless_cancelled(
+    context: Arc<Context>,
+    zome: &str,
+    params: &CallbackParams,
+    cancelled: &AtomicBool,
+) -> Option<CallbackResult> {
+    call_unless_cancelled(context, zome, &Callback::Genesis, params, cancelled)
+}
+
 #[cfg(test)]
 pub mod tests {
 
-    use super::genesis;
+    use super::{genesis, genesis_unless_cancelled};
     use instance::tests::test_context;
     use nucleus::ribosome::{
         callback::{tests::test_callback_instance, Callback, CallbackParams, CallbackResult},
         Defn,
     };
+    use std::sync::atomic::AtomicBool;
 
     #[test]
     fn pass() {
@@ -34,6 +46,34 @@ pub mod tests {
         assert_eq!(CallbackResult::Pass, result);
     }
 
+    #[test]
+    /// a cancelled genesis doesn't run
+    fn cancelled() {
+        let zome = "test_zome";
+        let instance = test_callback_instance(zome, Callback::Genesis.as_str(), 0)
+            .expect("Test callback instance could not be initialized");
+        let context = instance.initialize_context(test_context("test"));
+
+        assert_eq!(
+            None,
+            genesis_unless_cancelled(
+                context.clone(),
+                zome,
+                &CallbackParams::Genesis,
+                &AtomicBool::new(true)
+            )
+        );
+        assert_eq!(
+            Some(CallbackResult::Pass),
+            genesis_unless_cancelled(
+                context,
+                zome,
+                &CallbackParams::Genesis,
+                &AtomicBool::new(false)
+            )
+        );
+    }
+
     #[test]
     fn not_implemented() {
         let zome = "test_zome";
@@ -42,7 +82,8 @@ pub mod tests {
             // anything other than Genesis is fine here
             Callback::Receive.as_str(),
             0,
-        ).expect("Test callback instance could not be initialized");
+        )
+        .expect("Test callback instance could not be initialized");
 
         let context = instance.initialize_context(test_context("test"));
 
@@ -0,0 +1,263 @@
+use context::Context;
+use holochain_core_types::{cas::content::Address, entry_type::EntryType, error::HolochainError};
+use nucleus::{
+    ribosome::callback::{get_dna, run_callback, CallbackResult},
+    ZomeFnCall,
+};
+use std::sync::Arc;
+
+/// true if the DNA declares an invariant for the entry type
+pub fn has_invariants(context: &Arc<Context>, entry_type: &EntryType) -> bool {
+    entry_type.is_app()
+        && get_dna(context).map_or(false, |dna| {
+            dna.invariants
+                .iter()
+                .any(|invariant| invariant.watches(entry_type.as_str()))
+        })
+}
+
+/// Checks the invariants the DNA declares for the entry type, in the order they are declared,
+/// after the entry at the address was committed. Stops at the first one that doesn't hold.
+/// An invariant whose function can't be run doesn't hold either: the commit can't be shown
+/// to keep it.
+pub fn check_invariants(
+    context: Arc<Context>,
+    entry_type: &EntryType,
+    address: &Address,
+) -> Result<(), HolochainError> {
+    if !entry_type.is_app() {
+        return Ok(());
+    }
+    let dna = get_dna(&context).expect("Callback called without DNA set!");
+    let params = json!({ "entry_address": address.to_string() }).to_string();
+    for invariant in dna
+        .invariants
+        .iter()
+        .filter(|invariant| invariant.watches(entry_type.as_str()))
+    {
+        let result = match dna.get_wasm_from_zome_name(&invariant.zome) {
+            Some(wasm) if !wasm.code.is_empty() => {
+                let invariant_call = ZomeFnCall::new(
+                    &invariant.zome,
+                    "no capability, since this is an invariant",
+                    &invariant.function,
+                    &params,
+                );
+                run_callback(context.clone(), invariant_call, wasm, dna.name.clone())
+            }
+            _ => CallbackResult::NotImplemented,
+        };
+        match result {
+            CallbackResult::Pass => continue,
+            CallbackResult::Fail(error) => {
+                return Err(HolochainError::ValidationFailed(format!(
+                    "invariant {}::{} violated by {}: {}",
+                    invariant.zome, invariant.function, address, error
+                )))
+            }
+            CallbackResult::NotImplemented => {
+                return Err(HolochainError::ValidationFailed(format!(
+                    "invariant {}::{} could not be checked for {}",
+                    invariant.zome, invariant.function, address
+                )))
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate test_utils;
+    extern crate wabt;
+
+    use self::wabt::Wat2Wasm;
+    use super::*;
+    use holochain_core_types::{
+        cas::content::AddressableContent, crud_status::CrudStatus, entry::Entry,
+        entry_type::test_entry_type,
+    };
+    use holochain_dna::invariant::Invariant;
+    use holochain_wasm_utils::api_serialization::{
+        commit::CommitEntryArgs,
+        get_entry::{GetEntryArgs, GetEntryResult},
+    };
+    use instance::tests::{test_context, test_instance};
+    use nucleus::ribosome::api::{
+        self,
+        tests::{test_capability, test_zome_name},
+    };
+    use serde_json;
+
+    /// the entry of the test entry type with the value, as the test zome commits it
+    fn test_state_entry(value: &str) -> Entry {
+        Entry::new(&test_entry_type(), &value.to_string())
+    }
+
+    fn test_state_commit_args(value: &str) -> Vec<u8> {
+        let args = CommitEntryArgs {
+            entry_type_name: test_entry_type().to_string(),
+            entry_value: value.to_string(),
+            content_type: None,
+            acl: None,
+        };
+        serde_json::to_string(&args)
+            .expect("args should serialize")
+            .into_bytes()
+    }
+
+    /// wasm exporting "commit", which passes its argument to hc_commit_entry, and the
+    /// "no_conflict" invariant, which fails if both the "open" and the "closed" entries exist
+    fn test_invariant_wasm() -> Vec<u8> {
+        let get_args = |value: &str| {
+            serde_json::to_string(&GetEntryArgs {
+                address: test_state_entry(value).address(),
+            })
+            .expect("args should serialize")
+        };
+        let open_args = get_args("open");
+        let closed_args = get_args("closed");
+        // the get arguments live far above the stack the ribosome writes parameters to
+        let open_offset: u32 = 1024;
+        let closed_offset: u32 = 2048;
+        let not_found_length = serde_json::to_string(&GetEntryResult::not_found())
+            .expect("result should serialize")
+            .len();
+
+        Wat2Wasm::new()
+            .canonicalize_lebs(false)
+            .write_debug_names(true)
+            .convert(format!(
+                r#"
+(module
+    (import "env" "hc_commit_entry"
+        (func $commit_entry
+            (param i32)
+            (result i32)
+        )
+    )
+    (import "env" "hc_get_entry"
+        (func $get_entry
+            (param i32)
+            (result i32)
+        )
+    )
+
+    (memory 1)
+    (export "memory" (memory 0))
+    (data (i32.const {}) "{}")
+    (data (i32.const {}) "{}")
+
+    (func
+        (export "commit")
+        (param $allocation i32)
+        (result i32)
+
+        (call
+            $commit_entry
+            (get_local $allocation)
+        )
+    )
+
+    (func
+        (export "validate_testEntryType")
+        (param $allocation i32)
+        (result i32)
+
+        (i32.const 0)
+    )
+
+    (func
+        (export "no_conflict")
+        (param $allocation i32)
+        (result i32)
+        (local $open i32)
+        (local $closed i32)
+
+        (set_local $open (call $get_entry (i32.const {})))
+        (set_local $closed (call $get_entry (i32.const {})))
+        ;; found if the length of the result isn't the length of the not found result,
+        ;; the result of the second get is returned as the reason the invariant failed
+        (select
+            (get_local $closed)
+            (i32.const 0)
+            (i32.and
+                (i32.ne (i32.and (get_local $open) (i32.const 65535)) (i32.const {}))
+                (i32.ne (i32.and (get_local $closed) (i32.const 65535)) (i32.const {}))
+            )
+        )
+    )
+)
+                "#,
+                open_offset,
+                open_args.replace("\"", "\\\""),
+                closed_offset,
+                closed_args.replace("\"", "\\\""),
+                ((open_offset << 16) | open_args.len() as u32) as i32,
+                ((closed_offset << 16) | closed_args.len() as u32) as i32,
+                not_found_length,
+                not_found_length,
+            ))
+            .expect("string literal should be valid WAT")
+            .as_ref()
+            .to_vec()
+    }
+
+    #[test]
+    /// of two entries in conflicting states, the one committed second is rolled back
+    fn commit_breaking_invariant_is_rolled_back() {
+        let wasm = test_invariant_wasm();
+        let mut dna =
+            test_utils::create_test_dna_with_wasm(&test_zome_name(), &test_capability(), wasm);
+        dna.invariants.push(Invariant {
+            zome: test_zome_name(),
+            function: "no_conflict".to_string(),
+            entry_types: vec![test_entry_type().to_string()],
+        });
+        let app_name = dna.name.clone();
+        let wasm = dna
+            .get_wasm_from_zome_name(&test_zome_name())
+            .expect("test zome should have wasm")
+            .code
+            .clone();
+
+        let instance = test_instance(dna).expect("Could not create test instance");
+        let context = instance.initialize_context(test_context("jane"));
+        let commit = |value: &str| {
+            let commit_call = ZomeFnCall::new(&test_zome_name(), &test_capability(), "commit", "");
+            let runtime = api::call(
+                &app_name,
+                context.clone(),
+                wasm.clone(),
+                &commit_call,
+                Some(test_state_commit_args(value)),
+            )
+            .expect("commit should be callable");
+            runtime.result
+        };
+
+        let open = test_state_entry("open");
+        let closed = test_state_entry("closed");
+        assert_eq!(
+            format!(
+                r#"{{"address":"{}","validation_failure":""}}"#,
+                open.address()
+            ),
+            commit("open")
+        );
+        assert!(commit("closed").contains("invariant test_zome::no_conflict violated"));
+
+        let state = context.state().unwrap();
+        assert!(state.agent().chain_header_of(&open.address()).is_some());
+        assert_eq!(None, state.agent().chain_header_of(&closed.address()));
+        assert_eq!(
+            Ok(CrudStatus::LIVE),
+            state.dht().crud_status(&open.address())
+        );
+        assert_eq!(
+            Ok(CrudStatus::REJECTED),
+            state.dht().crud_status(&closed.address())
+        );
+        assert!(!state.dht().publish_queue().contains(&closed.address()));
+    }
+}
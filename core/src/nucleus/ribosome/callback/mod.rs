@@ -2,6 +2,8 @@
 //! ZomeCallbacks are functions in a Zome that are callable by the ribosome.
 
 pub mod genesis;
+pub mod invariant;
+pub mod post_commit;
 pub mod receive;
 pub mod validate_entry;
 
@@ -17,7 +19,15 @@ use nucleus::{
     ZomeFnCall,
 };
 use num_traits::FromPrimitive;
-use std::{str::FromStr, sync::Arc, thread::sleep, time::Duration};
+use std::{
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::sleep,
+    time::Duration,
+};
 
 /// Enumeration of all Zome Callbacks known and used by Holochain
 /// Enumeration can convert to str
@@ -192,6 +202,19 @@ pub fn call(
     function: &Callback,
     params: &CallbackParams,
 ) -> CallbackResult {
+    call_unless_cancelled(context, zome, function, params, &AtomicBool::new(false))
+        .expect("a callback nobody can cancel should run")
+}
+
+/// like call() but None if cancelled is set once the DNA is there, before the wasm runs
+/// a running callback isn't interrupted
+pub fn call_unless_cancelled(
+    context: Arc<Context>,
+    zome: &str,
+    function: &Callback,
+    params: &CallbackParams,
+    cancelled: &AtomicBool,
+) -> Option<CallbackResult> {
     let zome_call = ZomeFnCall::new(
         zome,
         &function.capability().as_str().to_string(),
@@ -200,17 +223,25 @@ pub fn call(
     );
 
     let dna = get_dna(&context).expect("Callback called without DNA set!");
+    // waiting for the DNA can take a while
+    if cancelled.load(Ordering::SeqCst) {
+        return None;
+    }
 
-    match dna.get_wasm_from_zome_name(zome) {
+    Some(match dna.get_wasm_from_zome_name(zome) {
         None => CallbackResult::NotImplemented,
         Some(wasm) => {
-            if wasm.code.is_empty() {
+            // zomes that don't define the callback aren't loaded for it
+            let defines_callback = wasm.exports().map_or(true, |exports| {
+                exports.iter().any(|export| export == function.as_str())
+            });
+            if wasm.code.is_empty() || !defines_callback {
                 CallbackResult::NotImplemented
             } else {
                 run_callback(context.clone(), zome_call, wasm, dna.name.clone())
             }
         }
-    }
+    })
 }
 
 #[cfg(test)]
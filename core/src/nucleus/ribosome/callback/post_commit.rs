@@ -0,0 +1,273 @@
+use context::Context;
+use holochain_core_types::{cas::content::Address, entry_type::EntryType};
+use nucleus::{
+    ribosome::callback::{get_dna, run_callback, CallbackResult},
+    ZomeFnCall,
+};
+use std::{
+    sync::{
+        mpsc::{channel, SendError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+/// a post commit hook waiting to run: the context of the commit, and what it committed
+type QueuedHook = (Arc<Context>, EntryType, Address);
+
+/// The thread running the post commit hooks of an instance one at a time, in the order the
+/// entries were committed, shared by the clones of the context
+/// It is started by the first hook and ends once the last clone of the context is gone.
+#[derive(Clone, Default)]
+pub struct PostCommitWorker {
+    sender: Arc<Mutex<Option<Sender<QueuedHook>>>>,
+}
+
+impl PostCommitWorker {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn queue(&self, hook: QueuedHook) {
+        let mut sender = self
+            .sender
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // the worker thread only goes away if a hook panicked, the next hook starts a new one
+        let hook = match *sender {
+            Some(ref worker) => match worker.send(hook) {
+                Ok(()) => return,
+                Err(SendError(hook)) => hook,
+            },
+            None => hook,
+        };
+        let (worker, receiver) = channel::<QueuedHook>();
+        thread::spawn(move || {
+            for (context, entry_type, address) in receiver {
+                run_post_commit(context, &entry_type, &address);
+            }
+        });
+        worker.send(hook).expect("receiver was just created");
+        *sender = Some(worker);
+    }
+}
+
+/// Queues the post commit hook the DNA declares for the entry type, if any, to be called with
+/// the address of the committed entry.
+/// Hooks run on the worker thread of the instance: the commit that triggered them has already
+/// returned, so a hook can dispatch actions (e.g. further commits or links) and wait for them
+/// without blocking the action loop or the zome call that committed.
+pub fn queue_post_commit(context: Arc<Context>, entry_type: EntryType, address: Address) {
+    if !entry_type.is_app() {
+        return;
+    }
+    let worker = context.post_commit_worker.clone();
+    worker.queue((context, entry_type, address));
+}
+
+fn run_post_commit(context: Arc<Context>, entry_type: &EntryType, address: &Address) {
+    if let CallbackResult::Fail(error) = post_commit(context.clone(), entry_type, address) {
+        context
+            .log(&format!(
+                "Post commit hook for '{}' failed on {}: {}",
+                entry_type, address, error
+            ))
+            .expect("Logger should work");
+    }
+}
+
+/// Runs the post commit hook for the entry type and waits for it to return
+pub fn post_commit(
+    context: Arc<Context>,
+    entry_type: &EntryType,
+    address: &Address,
+) -> CallbackResult {
+    if !entry_type.is_app() {
+        return CallbackResult::NotImplemented;
+    }
+    let dna = get_dna(&context).expect("Callback called without DNA set!");
+    let hook = dna
+        .get_entry_type_def(entry_type.as_str())
+        .and_then(|entry_type_def| entry_type_def.post_commit.clone());
+    let zome_name = dna.get_zome_name_for_entry_type(entry_type.as_str());
+
+    match (hook, zome_name) {
+        (Some(hook), Some(zome_name)) => match dna.get_wasm_from_zome_name(&zome_name) {
+            Some(wasm) if !wasm.code.is_empty() => {
+                let params = json!({ "entry_address": address.to_string() }).to_string();
+                let hook_call = ZomeFnCall::new(
+                    &zome_name,
+                    "no capability, since this is a post commit hook",
+                    &hook,
+                    &params,
+                );
+                run_callback(context.clone(), hook_call, wasm, dna.name.clone())
+            }
+            _ => CallbackResult::NotImplemented,
+        },
+        _ => CallbackResult::NotImplemented,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate test_utils;
+    extern crate wabt;
+
+    use self::wabt::Wat2Wasm;
+    use super::*;
+    use holochain_core_types::{
+        cas::content::AddressableContent,
+        entry::{test_entry, test_entry_b},
+        entry_type::test_entry_type,
+        links_entry::Link,
+    };
+    use instance::tests::{test_context, test_instance};
+    use nucleus::ribosome::api::{
+        self,
+        commit::tests::test_commit_args_bytes,
+        tests::{test_capability, test_zome_name},
+    };
+    use serde_json;
+    use std::time::{Duration, Instant};
+
+    /// wasm exporting "commit", which passes its argument to hc_commit_entry, and the
+    /// "link_test_entries" hook, which links the two standard test entries with the given tag
+    fn test_post_commit_wasm(tag: &str) -> Vec<u8> {
+        let link = Link::new(&test_entry().address(), &test_entry_b().address(), tag);
+        let link_json = serde_json::to_string(&link).expect("link should serialize");
+        // the link argument lives far above the stack the ribosome writes parameters to
+        let offset: u32 = 1024;
+        let encoded_allocation = (offset << 16) | link_json.len() as u32;
+
+        Wat2Wasm::new()
+            .canonicalize_lebs(false)
+            .write_debug_names(true)
+            .convert(format!(
+                r#"
+(module
+    (import "env" "hc_commit_entry"
+        (func $commit_entry
+            (param i32)
+            (result i32)
+        )
+    )
+    (import "env" "hc_link_entries"
+        (func $link_entries
+            (param i32)
+            (result i32)
+        )
+    )
+
+    (memory 1)
+    (export "memory" (memory 0))
+    (data (i32.const {}) "{}")
+
+    (func
+        (export "commit")
+        (param $allocation i32)
+        (result i32)
+
+        (call
+            $commit_entry
+            (get_local $allocation)
+        )
+    )
+
+    (func
+        (export "validate_testEntryType")
+        (param $allocation i32)
+        (result i32)
+
+        (i32.const 0)
+    )
+
+    (func
+        (export "link_test_entries")
+        (param $allocation i32)
+        (result i32)
+
+        (drop
+            (call
+                $link_entries
+                (i32.const {})
+            )
+        )
+        (i32.const 0)
+    )
+)
+                "#,
+                offset,
+                link_json.replace("\"", "\\\""),
+                encoded_allocation as i32,
+            ))
+            .expect("string literal should be valid WAT")
+            .as_ref()
+            .to_vec()
+    }
+
+    #[test]
+    /// entry types without a declared hook have nothing to run
+    fn post_commit_not_declared() {
+        let wasm = test_post_commit_wasm("derived");
+        let dna =
+            test_utils::create_test_dna_with_wasm(&test_zome_name(), &test_capability(), wasm);
+        let instance = test_instance(dna).expect("Could not create test instance");
+        let context = instance.initialize_context(test_context("jane"));
+
+        assert_eq!(
+            CallbackResult::NotImplemented,
+            post_commit(context, &test_entry_type(), &test_entry().address()),
+        );
+    }
+
+    #[test]
+    /// a hook adding a link after each commit leaves the link in the DHT
+    fn post_commit_hook_adds_link() {
+        let wasm = test_post_commit_wasm("derived");
+        let mut dna =
+            test_utils::create_test_dna_with_wasm(&test_zome_name(), &test_capability(), wasm);
+        dna.zomes
+            .get_mut(&test_zome_name())
+            .expect("test zome should exist")
+            .entry_types
+            .get_mut(&test_entry_type().to_string())
+            .expect("test entry type should exist")
+            .post_commit = Some("link_test_entries".to_string());
+        let app_name = dna.name.clone();
+        let wasm = dna
+            .get_wasm_from_zome_name(&test_zome_name())
+            .expect("test zome should have wasm")
+            .code
+            .clone();
+
+        let instance = test_instance(dna).expect("Could not create test instance");
+        let context = instance.initialize_context(test_context("jane"));
+
+        let commit_call = ZomeFnCall::new(&test_zome_name(), &test_capability(), "commit", "");
+        api::call(
+            &app_name,
+            context.clone(),
+            wasm,
+            &commit_call,
+            Some(test_commit_args_bytes()),
+        )
+        .expect("commit should be callable");
+
+        // the hook runs after the commit returned
+        let timeout = Instant::now() + Duration::from_secs(5);
+        let mut tags = Ok(Vec::new());
+        while Instant::now() < timeout {
+            tags = context
+                .state()
+                .unwrap()
+                .dht()
+                .link_tags(&test_entry().address());
+            if tags != Ok(Vec::new()) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(Ok(vec!["derived".to_string()]), tags);
+    }
+}
@@ -2,8 +2,32 @@ use holochain_wasm_utils::{
     error::RibosomeErrorCode,
     memory_allocation::{SinglePageAllocation, SinglePageStack, U16_MAX},
 };
+use std::fmt;
+use wasmi::{
+    memory_units::{Bytes, Pages},
+    HostError, MemoryRef, ModuleRef,
+};
+
+/// Host error aborting a zome call whose WASM memory grew past the cap of the context
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryLimitExceeded {
+    /// bytes of WASM memory the call had when it was aborted
+    pub used: usize,
+    /// bytes of WASM memory a call may have
+    pub limit: usize,
+}
+
+impl fmt::Display for MemoryLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "zome call used {} bytes of memory, more than its limit of {}",
+            self.used, self.limit
+        )
+    }
+}
 
-use wasmi::{MemoryRef, ModuleRef};
+impl HostError for MemoryLimitExceeded {}
 
 //--------------------------------------------------------------------------------------------------
 // WASM Memory Manager
@@ -78,6 +102,12 @@ impl SinglePageManager {
         Ok(mem_buf)
     }
 
+    /// bytes of WASM memory currently allocated to the module, all pages included
+    pub fn size(&self) -> usize {
+        let Bytes(size) = Pages::from(self.wasm_memory.current_size()).into();
+        size
+    }
+
     /// Read data somewhere in stack
     pub fn read(&self, allocation: SinglePageAllocation) -> Vec<u8> {
         return self
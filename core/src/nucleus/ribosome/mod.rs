@@ -3,6 +3,7 @@
 pub mod api;
 pub mod callback;
 pub mod memory;
+pub mod modules;
 
 use holochain_dna::zome::capabilities::ReservedCapabilityNames;
 
@@ -0,0 +1,83 @@
+//! The zome WASM modules an instance has loaded.
+//!
+//! Parsing and validating its WASM is the expensive part of running a zome, and a DNA with
+//! many zomes shouldn't pay for all of them when an app only uses a few. The module of a zome
+//! is loaded on the first call into it, callbacks included, and kept for the calls after.
+//! ZomeModules::load() loads a module ahead of its first call.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use wasmi::{self, Module};
+
+/// A loaded module with the code it was loaded from
+struct LoadedModule {
+    code: Vec<u8>,
+    module: Arc<Module>,
+}
+
+/// The loaded modules by zome name, shared by the clones of a context
+#[derive(Clone, Default)]
+pub struct ZomeModules {
+    modules: Arc<Mutex<HashMap<String, LoadedModule>>>,
+}
+
+impl ZomeModules {
+    pub fn new() -> Self {
+        ZomeModules::default()
+    }
+
+    /// the module of the zome's code, loaded now unless it was loaded from the same code before
+    pub fn load(&self, zome_name: &str, code: &[u8]) -> Result<Arc<Module>, wasmi::Error> {
+        let mut modules = self.modules.lock().unwrap();
+        if let Some(loaded) = modules.get(zome_name) {
+            if loaded.code.as_slice() == code {
+                return Ok(loaded.module.clone());
+            }
+        }
+        let module = Arc::new(Module::from_buffer(code)?);
+        modules.insert(
+            zome_name.to_string(),
+            LoadedModule {
+                code: code.to_vec(),
+                module: module.clone(),
+            },
+        );
+        Ok(module)
+    }
+
+    /// names of the zomes whose module is loaded, sorted
+    pub fn loaded(&self) -> Vec<String> {
+        let mut zome_names: Vec<String> = self.modules.lock().unwrap().keys().cloned().collect();
+        zome_names.sort();
+        zome_names
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use nucleus::ribosome::callback::tests::test_callback_wasm;
+
+    #[test]
+    /// a module is loaded once per code, other zomes stay unloaded
+    fn modules_are_loaded_once() {
+        let modules = ZomeModules::new();
+        let code = test_callback_wasm("genesis", 0);
+        assert!(modules.loaded().is_empty());
+
+        let first = modules.load("zome_a", &code).unwrap();
+        let second = modules.load("zome_a", &code).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(vec!["zome_a".to_string()], modules.loaded());
+
+        // new code for the zome replaces its module
+        let third = modules
+            .load("zome_a", &test_callback_wasm("genesis", 1))
+            .unwrap();
+        assert!(!Arc::ptr_eq(&first, &third));
+        assert!(modules.clone().load("zome_b", b"not wasm").is_err());
+        assert_eq!(vec!["zome_a".to_string()], modules.loaded());
+    }
+}
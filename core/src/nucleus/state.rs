@@ -1,10 +1,10 @@
 use holochain_core_types::{cas::content::Address, error::HolochainError};
 use holochain_dna::Dna;
-use nucleus::ZomeFnCall;
+use nucleus::{ProgressUpdate, ZomeFnCall};
 use snowflake;
 use std::collections::HashMap;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum NucleusStatus {
     New,
     Initializing,
@@ -32,6 +32,8 @@ pub struct NucleusState {
     // @see https://github.com/holochain/holochain-rust/issues/196
     pub zome_calls: HashMap<ZomeFnCall, Option<Result<String, HolochainError>>>,
     pub validation_results: HashMap<(snowflake::ProcessUniqueId, Address), ValidationResult>,
+    /// progress reported by running zome calls, in the order it was reported
+    pub progress: HashMap<ZomeFnCall, Vec<ProgressUpdate>>,
 }
 
 impl NucleusState {
@@ -41,6 +43,18 @@ impl NucleusState {
             status: NucleusStatus::New,
             zome_calls: HashMap::new(),
             validation_results: HashMap::new(),
+            progress: HashMap::new(),
+        }
+    }
+
+    /// copy of the state without its ephemeral parts, for persistence
+    /// the DNA and status are kept, in-flight zome calls and validations are dropped
+    pub fn durable(&self) -> Self {
+        NucleusState {
+            zome_calls: HashMap::new(),
+            validation_results: HashMap::new(),
+            progress: HashMap::new(),
+            ..self.clone()
         }
     }
 
@@ -54,6 +68,11 @@ impl NucleusState {
         }
     }
 
+    /// the progress the zome call reported so far, oldest first
+    pub fn progress(&self, zome_call: &ZomeFnCall) -> Vec<ProgressUpdate> {
+        self.progress.get(zome_call).cloned().unwrap_or_default()
+    }
+
     pub fn has_initialized(&self) -> bool {
         self.status == NucleusStatus::Initialized
     }
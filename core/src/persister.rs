@@ -1,7 +1,20 @@
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use holochain_cas_implementations::cas::encrypted::StorageKey;
 use holochain_core_types::error::HolochainError;
-use state::State;
+use logger::{Logger, SimpleLogger};
+use serde_json;
+use state::{State, StateSnapshot};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::{
+    fs::{self, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
 
 /// trait that defines the persistence functionality that holochain_core requires
+/// implementations should only persist State::durable(), ephemeral state is rebuilt on load
 pub trait Persister: Send {
     // @TODO how does save/load work with snowflake IDs?
     // snowflake is only unique across a single process, not a reboot save/load round trip
@@ -18,7 +31,7 @@ pub struct SimplePersister {
 
 impl Persister for SimplePersister {
     fn save(&mut self, state: State) {
-        self.state = Some(state);
+        self.state = Some(state.durable());
     }
     fn load(&self) -> Result<Option<State>, HolochainError> {
         Ok(self.state.clone())
@@ -31,9 +44,147 @@ impl SimplePersister {
     }
 }
 
+/// replaces the file with the bytes in one go: they are written to a file next to it first,
+/// which is then renamed to it, so a crash leaves either the old or the new file
+/// a private file is only readable and writable by its owner, e.g. one holding key seeds
+pub fn write_atomically(path: &Path, bytes: &[u8], private: bool) -> Result<(), HolochainError> {
+    let mut temp_name = path
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    temp_name.push(".tmp");
+    let temp = path.with_file_name(temp_name);
+    // left over by a crash, possibly with other permissions
+    let _ = fs::remove_file(&temp);
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        if private {
+            options.mode(0o600);
+        }
+    }
+    {
+        let mut file = options.open(&temp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+    }
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+/// first bytes of a gzip stream, a JSON snapshot can't start with them
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Persister writing the durable state to a file as a JSON StateSnapshot, gzip compressed if
+/// configured to. Loading tells a compressed file from a plain one by its first bytes, so a
+/// file loads whichever way it was written.
+/// Content sealed with a storage key is written sealed, a persister made with new_sealed()
+/// seals the content of states that aren't sealed with its key before writing them and opens
+/// it again on load.
+#[derive(Clone)]
+pub struct FilePersister {
+    path: PathBuf,
+    compress: bool,
+    storage_key: Option<StorageKey>,
+    /// where save() reports the states it couldn't write
+    logger: Arc<Mutex<Logger>>,
+}
+
+impl Persister for FilePersister {
+    /// logs the error and leaves the file as it was if it can't be written,
+    /// @see FilePersister::write() to handle the error
+    fn save(&mut self, state: State) {
+        if let Err(error) = self.write(&state) {
+            if let Ok(mut logger) = self.logger.lock() {
+                logger.log(format!(
+                    "could not write the state to {}: {}",
+                    self.path.display(),
+                    error
+                ));
+            }
+        }
+    }
+    /// None if nothing was saved to the file yet
+    fn load(&self) -> Result<Option<State>, HolochainError> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let bytes = fs::read(&self.path)?;
+        let json = if bytes.starts_with(&GZIP_MAGIC) {
+            let mut json = Vec::new();
+            GzDecoder::new(&bytes[..]).read_to_end(&mut json)?;
+            json
+        } else {
+            bytes
+        };
+        let snapshot: StateSnapshot = serde_json::from_slice(&json)?;
+        State::from_snapshot(snapshot, self.storage_key).map(Some)
+    }
+}
+
+impl FilePersister {
+    pub fn new<P: Into<PathBuf>>(path: P, compress: bool) -> Self {
+        FilePersister {
+            path: path.into(),
+            compress,
+            storage_key: None,
+            logger: Arc::new(Mutex::new(SimpleLogger {})),
+        }
+    }
+
+    /// a persister keeping the content in the file sealed with the key, e.g. the storage key
+    /// of the instance it persists, @see Context::set_storage_key()
+    pub fn new_sealed<P: Into<PathBuf>>(path: P, compress: bool, key: StorageKey) -> Self {
+        FilePersister {
+            storage_key: Some(key),
+            ..FilePersister::new(path, compress)
+        }
+    }
+
+    /// logs the errors of save() with the logger instead of printing them
+    pub fn set_logger(&mut self, logger: Arc<Mutex<Logger>>) {
+        self.logger = logger;
+    }
+
+    /// writes the durable parts of the state to the file, replacing what it held in one go
+    /// @see write_atomically()
+    pub fn write(&self, state: &State) -> Result<(), HolochainError> {
+        let durable = match self.storage_key {
+            Some(key) => state.durable().sealed(key)?,
+            None => state.durable(),
+        };
+        let json = serde_json::to_vec(&durable.snapshot()?)?;
+        let bytes = if self.compress {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&json)?;
+            encoder.finish()?
+        } else {
+            json
+        };
+        write_atomically(&self.path, &bytes, false)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use action::{Action, ActionWrapper};
+    use context::Context;
+    use holochain_agent::Agent;
+    use holochain_cas_implementations::cas::encrypted::STORAGE_KEY_LEN;
+    use holochain_core_types::{
+        cas::{content::AddressableContent, storage::ContentAddressableStorage},
+        entry::{test_entry, Entry},
+        entry_type::test_entry_type,
+    };
+    use holochain_dna::{
+        zome::{entry_types::EntryTypeDef, Zome},
+        Dna,
+    };
+    use instance::tests::{test_context, test_logger};
+    use std::sync::RwLock;
+    use tempfile::tempdir;
 
     #[test]
     fn can_instantiate() {
@@ -42,5 +193,158 @@ mod tests {
         assert_eq!(store.load(), Ok(None));
     }
 
-    // TODO write a persister.save() test
+    /// state of an instance of a DNA with the test entry type that committed the entries
+    fn test_state_with_commits(entries: Vec<Entry>) -> State {
+        let mut zome = Zome::default();
+        zome.entry_types
+            .insert(test_entry_type().to_string(), EntryTypeDef::new());
+        let mut dna = Dna::new();
+        dna.zomes.insert("zome".to_string(), zome);
+        let mut context = Context::new(
+            Agent::from("jane".to_string()),
+            test_logger(),
+            Arc::new(Mutex::new(SimplePersister::new())),
+        );
+        let state = State::new().reduce(
+            test_context("jane"),
+            ActionWrapper::new(Action::InitApplication(dna)),
+        );
+        context.set_state(Arc::new(RwLock::new(state.clone())));
+        let context = Arc::new(context);
+
+        entries.into_iter().fold(state, |state, entry| {
+            state.reduce(context.clone(), ActionWrapper::new(Action::Commit(entry)))
+        })
+    }
+
+    #[test]
+    /// authored entries survive a save/load round trip, ephemeral state doesn't
+    fn save_keeps_durable_state_only() {
+        let state = test_state_with_commits(vec![test_entry()]);
+        assert!(!state.history.is_empty());
+        assert!(!state.agent().actions().is_empty());
+        assert!(!state.dht().network().published().is_empty());
+
+        let mut store = SimplePersister::new();
+        store.save(state.clone());
+        let loaded = store.load().unwrap().expect("state should have been saved");
+
+        assert_eq!(
+            state.agent().top_chain_header(),
+            loaded.agent().top_chain_header()
+        );
+        assert_eq!(
+            Some(test_entry()),
+            loaded
+                .dht()
+                .content_storage()
+                .fetch(&test_entry().address())
+                .expect("could not fetch from cas")
+        );
+        assert_eq!(state.nucleus().dna(), loaded.nucleus().dna());
+        assert_eq!(state.history_cursor(), loaded.history_cursor());
+        assert!(loaded.history.is_empty());
+        assert!(loaded.agent().actions().is_empty());
+        assert!(loaded.dht().network().published().is_empty());
+    }
+
+    #[test]
+    /// a compressed file restores the same durable state in a smaller file, and a persister
+    /// loads files whichever way they were written
+    fn compressed_round_trip() {
+        let entries = (0..20)
+            .map(|index| Entry::new(&test_entry_type(), &format!("entry number {}", index)))
+            .collect();
+        let state = test_state_with_commits(entries);
+        let dir = tempdir().unwrap();
+        let plain_path = dir.path().join("state.json");
+        let compressed_path = dir.path().join("state.json.gz");
+        let mut plain = FilePersister::new(plain_path.clone(), false);
+        let mut compressed = FilePersister::new(compressed_path.clone(), true);
+        assert_eq!(Ok(None), compressed.load());
+
+        plain.save(state.clone());
+        compressed.save(state.clone());
+        let expected = state.durable().snapshot();
+        let loaded = compressed
+            .load()
+            .unwrap()
+            .expect("state should have been saved");
+        assert_eq!(expected, loaded.snapshot());
+        assert_eq!(state.fingerprint(), loaded.fingerprint());
+        assert_eq!(
+            state.agent().top_chain_header(),
+            loaded.agent().top_chain_header()
+        );
+
+        let plain_size = fs::metadata(&plain_path).unwrap().len();
+        let compressed_size = fs::metadata(&compressed_path).unwrap().len();
+        assert!(compressed_size < plain_size);
+
+        let from_plain = FilePersister::new(plain_path, true)
+            .load()
+            .unwrap()
+            .expect("state should have been saved");
+        assert_eq!(expected, from_plain.snapshot());
+    }
+
+    #[test]
+    /// the content of a sealed state stays sealed in the file and only loads with its key
+    fn sealed_content_stays_sealed_in_the_file() {
+        let secret = Entry::new(&test_entry_type(), &"top secret value".to_string());
+        let key = StorageKey::new([7; STORAGE_KEY_LEN]);
+        let state = test_state_with_commits(vec![secret.clone()])
+            .sealed(key)
+            .unwrap();
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let mut plain = FilePersister::new(path.clone(), false);
+        plain.save(state);
+
+        assert!(!fs::read_to_string(&path)
+            .unwrap()
+            .contains("top secret value"));
+        assert_eq!(Err(HolochainError::StorageKeyMissing), plain.load());
+        let wrong_key = StorageKey::new([8; STORAGE_KEY_LEN]);
+        assert!(FilePersister::new_sealed(path.clone(), false, wrong_key)
+            .load()
+            .is_err());
+        let loaded = FilePersister::new_sealed(path.clone(), false, key)
+            .load()
+            .unwrap()
+            .expect("state should have been saved");
+        assert_eq!(Some(key), loaded.dht().content_storage().storage_key());
+        assert_eq!(
+            Ok(Some(secret.clone())),
+            loaded.dht().content_storage().fetch(&secret.address())
+        );
+
+        // a persister with a key seals the content of plain states
+        let mut sealing = FilePersister::new_sealed(path.clone(), false, key);
+        sealing.save(test_state_with_commits(vec![secret]));
+        assert!(!fs::read_to_string(&path)
+            .unwrap()
+            .contains("top secret value"));
+    }
+
+    #[test]
+    /// the file is replaced without leaving anything next to it, and a state that can't be
+    /// written is logged instead of ending the instance
+    fn failed_save_is_logged() {
+        let dir = tempdir().unwrap();
+        let state = test_state_with_commits(vec![test_entry()]);
+        let logger = test_logger();
+        let mut store = FilePersister::new(dir.path().join("state.json"), false);
+        store.set_logger(logger.clone());
+        store.save(state.clone());
+        store.save(state.clone());
+        assert!(logger.lock().unwrap().log.is_empty());
+        assert_eq!(1, fs::read_dir(dir.path()).unwrap().count());
+
+        let mut missing = FilePersister::new(dir.path().join("missing").join("state.json"), false);
+        missing.set_logger(logger.clone());
+        missing.save(state);
+        assert_eq!(1, logger.lock().unwrap().log.len());
+        assert_eq!(Ok(None), missing.load());
+    }
 }
@@ -0,0 +1,97 @@
+//! Where zome calls get their randomness from.
+//!
+//! Zome functions can't use true randomness without breaking replays and agreement between
+//! nodes, so every call gets a generator seeded by the seed source of the context. The default
+//! derives the seed from the id of the call; replays inject the seed the recorded call had.
+
+use nucleus::ZomeFnCall;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+pub trait SeedSource: Send + Sync {
+    /// seed of the random numbers the call gets
+    fn seed(&self, call: &ZomeFnCall) -> u64;
+}
+
+/// Seeds every call with a hash of its id
+#[derive(Clone, Debug, Default)]
+pub struct CallIdSeed {}
+
+impl SeedSource for CallIdSeed {
+    fn seed(&self, call: &ZomeFnCall) -> u64 {
+        // DefaultHasher::new() always uses the same keys
+        let mut hasher = DefaultHasher::new();
+        call.id().to_string().hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Seeds every call with the same seed, e.g. to replay a recorded call
+#[derive(Clone, Debug)]
+pub struct FixedSeed {
+    seed: u64,
+}
+
+impl FixedSeed {
+    pub fn new(seed: u64) -> Self {
+        FixedSeed { seed }
+    }
+}
+
+impl SeedSource for FixedSeed {
+    fn seed(&self, _call: &ZomeFnCall) -> u64 {
+        self.seed
+    }
+}
+
+/// Deterministic generator of the random numbers of a call (SplitMix64)
+/// not suitable for cryptography
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        SeededRng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    /// generators with the same seed give the same numbers
+    fn seeded_rng_test() {
+        let mut first = SeededRng::new(42);
+        let mut second = SeededRng::new(42);
+        let numbers: Vec<u64> = (0..3).map(|_| first.next_u64()).collect();
+        assert_eq!(
+            numbers,
+            (0..3).map(|_| second.next_u64()).collect::<Vec<u64>>()
+        );
+        assert_ne!(numbers[0], numbers[1]);
+        assert_ne!(numbers[0], SeededRng::new(43).next_u64());
+    }
+
+    #[test]
+    /// the seed of a call depends on its id only
+    fn call_id_seed_test() {
+        let call = ZomeFnCall::new("zome", "cap", "fn", "{}");
+        let other = ZomeFnCall::new("zome", "cap", "fn", "{}");
+        assert_eq!(CallIdSeed {}.seed(&call), CallIdSeed {}.seed(&call.clone()));
+        assert_ne!(CallIdSeed {}.seed(&call), CallIdSeed {}.seed(&other));
+        assert_eq!(7, FixedSeed::new(7).seed(&call));
+    }
+}
@@ -0,0 +1,267 @@
+//! Recovering the source chain and links of an instance from its content storage alone.
+//!
+//! Chain headers link to the previous header, so the chain is recovered by finding the one
+//! header no other header links to and following the links from there. Links are recovered
+//! from the link entries the DHT store keeps in the content storage next to its meta data.
+
+use holochain_cas_implementations::cas::memory::MemoryStorage;
+use holochain_core_types::{
+    cas::{
+        content::{Address, AddressableContent, Content},
+        storage::ContentAddressableStorage,
+    },
+    chain_header::ChainHeader,
+    entry::{Entry, ToEntry},
+    entry_type::EntryType,
+    error::HolochainError,
+    json::FromJson,
+    links_entry::{LinkActionKind, LinkEntry},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// Something in a content storage that doesn't add up to a single source chain
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Inconsistency {
+    /// an entry that no chain header refers to
+    OrphanEntry(Address),
+    /// a chain header whose entry is not in the storage
+    MissingEntry(Address),
+    /// a chain header linking to a previous header that is not in the storage
+    BrokenChain(Address),
+    /// several chain headers that no other header links to, sorted
+    ForkedChain(Vec<Address>),
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Inconsistency::OrphanEntry(address) => {
+                write!(f, "entry {} is not on the chain", address)
+            }
+            Inconsistency::MissingEntry(address) => {
+                write!(f, "the entry of chain header {} is missing", address)
+            }
+            Inconsistency::BrokenChain(address) => {
+                write!(
+                    f,
+                    "the previous header of chain header {} is missing",
+                    address
+                )
+            }
+            Inconsistency::ForkedChain(addresses) => write!(
+                f,
+                "the chain has several tops: {}",
+                addresses
+                    .iter()
+                    .map(|address| address.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// What a scan of a content storage found
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StorageScan {
+    /// the newest chain header, None if the storage holds no chain
+    pub top_chain_header: Option<ChainHeader>,
+    /// the link entries, the additions before the removals, each in address order
+    /// a removal tombstones the link for good, so replaying them in this order gives the links
+    /// of the DHT store whatever order they were made in
+    pub links: Vec<LinkEntry>,
+    /// everything that doesn't add up, empty for a consistent storage
+    pub inconsistencies: Vec<Inconsistency>,
+}
+
+impl StorageScan {
+    /// the inconsistencies as a single error, Ok if there are none
+    pub fn check(&self) -> Result<(), HolochainError> {
+        if self.inconsistencies.is_empty() {
+            return Ok(());
+        }
+        Err(HolochainError::InconsistentStorage(
+            self.inconsistencies
+                .iter()
+                .map(|inconsistency| inconsistency.to_string())
+                .collect::<Vec<String>>()
+                .join("; "),
+        ))
+    }
+}
+
+/// sorts all the content of the storage into chain headers, chain entries and links
+/// content that is neither a chain header nor an entry, e.g. a CRUD status, is skipped
+pub fn scan_storage(storage: &MemoryStorage) -> Result<StorageScan, HolochainError> {
+    let mut headers = BTreeMap::new();
+    let mut entries = BTreeMap::new();
+    for address in storage.addresses()? {
+        let content: Content = match storage.fetch(&address)? {
+            Some(content) => content,
+            None => continue,
+        };
+        if let Ok(chain_header) = ChainHeader::from_json_str(&content) {
+            headers.insert(address, chain_header);
+        } else if let Ok(entry) = Entry::from_json(&content) {
+            entries.insert(address, entry);
+        }
+    }
+
+    let mut scan = StorageScan::default();
+    let linked: BTreeSet<Address> = headers
+        .values()
+        .filter_map(|chain_header| chain_header.link())
+        .collect();
+    let tops: Vec<Address> = headers
+        .keys()
+        .filter(|address| !linked.contains(*address))
+        .cloned()
+        .collect();
+    if tops.len() > 1 {
+        scan.inconsistencies
+            .push(Inconsistency::ForkedChain(tops.clone()));
+    }
+
+    for (address, chain_header) in headers.iter() {
+        if !entries.contains_key(chain_header.entry_address()) {
+            scan.inconsistencies
+                .push(Inconsistency::MissingEntry(address.clone()));
+        }
+        if let Some(previous) = chain_header.link() {
+            if !headers.contains_key(&previous) {
+                scan.inconsistencies
+                    .push(Inconsistency::BrokenChain(address.clone()));
+            }
+        }
+    }
+
+    let chained: BTreeSet<&Address> = headers
+        .values()
+        .map(|chain_header| chain_header.entry_address())
+        .collect();
+    for (address, entry) in entries.iter() {
+        if *entry.entry_type() == EntryType::Link {
+            scan.links.push(LinkEntry::from_entry(entry));
+        } else if !chained.contains(address) {
+            scan.inconsistencies
+                .push(Inconsistency::OrphanEntry(address.clone()));
+        }
+    }
+
+    scan.links
+        .sort_by_key(|link_entry| match link_entry.action_kind() {
+            LinkActionKind::ADD => 0,
+            LinkActionKind::DELETE => 1,
+        });
+
+    if tops.len() == 1 {
+        scan.top_chain_header = headers.get(&tops[0]).cloned();
+    }
+    Ok(scan)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core_types::{
+        entry::{test_entry, test_entry_b},
+        links_entry::Link,
+        signature::Signature,
+        time::Iso8601,
+    };
+
+    /// header of the entry, linking to the previous header if any
+    fn test_header(entry: &Entry, previous: Option<&ChainHeader>) -> ChainHeader {
+        ChainHeader::new(
+            entry.entry_type(),
+            &entry.address(),
+            &Signature::from(""),
+            &previous.map(|chain_header| chain_header.address()),
+            &None,
+            &Iso8601::from(""),
+        )
+    }
+
+    #[test]
+    /// the chain and links of a consistent storage are found, whatever the address order
+    fn scan_consistent_storage() {
+        let mut storage = MemoryStorage::new().unwrap();
+        let first = test_header(&test_entry(), None);
+        let second = test_header(&test_entry_b(), Some(&first));
+        let link = Link::new(&test_entry().address(), &test_entry_b().address(), "tag");
+        let link_entry = LinkEntry::from_link(LinkActionKind::ADD, &link);
+        storage.add(&test_entry()).unwrap();
+        storage.add(&test_entry_b()).unwrap();
+        storage.add(&first).unwrap();
+        storage.add(&second).unwrap();
+        storage.add(&link_entry.to_entry()).unwrap();
+
+        let scan = scan_storage(&storage).unwrap();
+        assert_eq!(Some(second), scan.top_chain_header);
+        assert_eq!(vec![link_entry], scan.links);
+        assert_eq!(Ok(()), scan.check());
+    }
+
+    #[test]
+    /// removals of links are replayed after all the additions, whatever their addresses
+    fn scan_orders_link_removals_last() {
+        let mut storage = MemoryStorage::new().unwrap();
+        let mut additions = Vec::new();
+        let mut removals = Vec::new();
+        for tag in &["a", "b", "c", "d"] {
+            let link = Link::new(&test_entry().address(), &test_entry_b().address(), tag);
+            let addition = LinkEntry::from_link(LinkActionKind::ADD, &link);
+            let removal = LinkEntry::from_link(LinkActionKind::DELETE, &link);
+            storage.add(&addition.to_entry()).unwrap();
+            storage.add(&removal.to_entry()).unwrap();
+            additions.push(addition);
+            removals.push(removal);
+        }
+
+        let scan = scan_storage(&storage).unwrap();
+        assert!(scan.links[..4]
+            .iter()
+            .all(|link_entry| additions.contains(link_entry)));
+        assert!(scan.links[4..]
+            .iter()
+            .all(|link_entry| removals.contains(link_entry)));
+    }
+
+    #[test]
+    /// orphan entries, missing entries and broken links between headers are all reported
+    fn scan_reports_inconsistencies() {
+        let mut storage = MemoryStorage::new().unwrap();
+        let orphan = Entry::new(test_entry().entry_type(), &"orphan".to_string());
+        let unknown = Entry::new(test_entry().entry_type(), &"unknown".to_string());
+        // linking to the header of an entry that was never stored
+        let dangling = test_header(&test_entry_b(), Some(&test_header(&unknown, None)));
+        // the header of an entry that is not in the storage
+        let lonely = test_header(&test_entry(), None);
+        storage.add(&orphan).unwrap();
+        storage.add(&test_entry_b()).unwrap();
+        storage.add(&dangling).unwrap();
+        storage.add(&lonely).unwrap();
+
+        let scan = scan_storage(&storage).unwrap();
+        let mut tops = vec![dangling.address(), lonely.address()];
+        tops.sort();
+        assert_eq!(None, scan.top_chain_header);
+        assert!(scan
+            .inconsistencies
+            .contains(&Inconsistency::ForkedChain(tops)));
+        assert!(scan
+            .inconsistencies
+            .contains(&Inconsistency::BrokenChain(dangling.address())));
+        assert!(scan
+            .inconsistencies
+            .contains(&Inconsistency::MissingEntry(lonely.address())));
+        assert!(scan
+            .inconsistencies
+            .contains(&Inconsistency::OrphanEntry(orphan.address())));
+        assert_eq!(4, scan.inconsistencies.len());
+        assert!(scan.check().is_err());
+    }
+}
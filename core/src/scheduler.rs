@@ -0,0 +1,178 @@
+//! The order the event loop reduces the queued actions in.
+//!
+//! Actions come from zome calls, the network and timers alike. Reducing them strictly in the
+//! order they were dispatched lets a burst of background work, e.g. network gets, delay the
+//! calls a user is waiting on. The event loop takes the queued actions of the most urgent
+//! priority class first, and the actions of the same class in the order they were dispatched.
+
+use action::{ActionFilter, ActionWrapper};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// How urgently an action is reduced, most urgent first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PriorityClass {
+    /// actions someone is waiting on, e.g. zome calls
+    Interactive,
+    Normal,
+    /// maintenance and network traffic nobody waits on directly
+    Background,
+}
+
+/// Which actions are in which priority class, all others are Normal
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SchedulerPriorities {
+    pub interactive: ActionFilter,
+    pub background: ActionFilter,
+}
+
+impl Default for SchedulerPriorities {
+    /// zome calls ahead of everything, network gets and maintenance behind everything
+    fn default() -> Self {
+        SchedulerPriorities {
+            interactive: ActionFilter::EXECUTE_ZOME_FUNCTION | ActionFilter::CALL,
+            background: ActionFilter::GET_ENTRY
+                | ActionFilter::GET_ENTRIES_BY_TYPE
+                | ActionFilter::FLUSH_PUBLISHES
+                | ActionFilter::RETURN_PUBLISH_RESULT
+                | ActionFilter::RETURN_GET_RESULT
+                | ActionFilter::EXPIRE_EPHEMERAL
+                | ActionFilter::EXPIRE_ENTRIES
+                | ActionFilter::CLEAR_NEGATIVE_CACHE
+                | ActionFilter::LOAD_DHT_SNAPSHOT,
+        }
+    }
+}
+
+impl SchedulerPriorities {
+    /// every action in the same class, so actions are reduced in the order they were dispatched
+    pub fn fifo() -> Self {
+        SchedulerPriorities {
+            interactive: ActionFilter::empty(),
+            background: ActionFilter::empty(),
+        }
+    }
+
+    pub fn class_of(&self, action_wrapper: &ActionWrapper) -> PriorityClass {
+        if self.interactive.matches(action_wrapper.action()) {
+            PriorityClass::Interactive
+        } else if self.background.matches(action_wrapper.action()) {
+            PriorityClass::Background
+        } else {
+            PriorityClass::Normal
+        }
+    }
+}
+
+/// How long the actions of a class waited in the queue
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClassStats {
+    /// number of actions taken from the queue
+    pub reduced: u64,
+    pub total_wait: Duration,
+    pub max_wait: Duration,
+}
+
+impl ClassStats {
+    /// mean time the actions waited, zero before any was reduced
+    pub fn mean_wait(&self) -> Duration {
+        if self.reduced == 0 {
+            return Duration::from_secs(0);
+        }
+        self.total_wait / self.reduced as u32
+    }
+}
+
+/// Queue waits of the event loop by priority class
+pub type SchedulerStats = BTreeMap<PriorityClass, ClassStats>;
+
+/// The actions received by the event loop and not reduced yet
+pub struct ActionQueue {
+    priorities: SchedulerPriorities,
+    queues: BTreeMap<PriorityClass, VecDeque<(ActionWrapper, Instant)>>,
+}
+
+impl ActionQueue {
+    pub fn new(priorities: SchedulerPriorities) -> Self {
+        ActionQueue {
+            priorities,
+            queues: BTreeMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, action_wrapper: ActionWrapper) {
+        let class = self.priorities.class_of(&action_wrapper);
+        self.queues
+            .entry(class)
+            .or_insert_with(VecDeque::new)
+            .push_back((action_wrapper, Instant::now()));
+    }
+
+    /// the first queued action of the most urgent class, with its class and how long it waited
+    pub fn pop(&mut self) -> Option<(ActionWrapper, PriorityClass, Duration)> {
+        for (class, queue) in self.queues.iter_mut() {
+            if let Some((action_wrapper, queued)) = queue.pop_front() {
+                return Some((action_wrapper, *class, queued.elapsed()));
+            }
+        }
+        None
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queues.values().all(|queue| queue.is_empty())
+    }
+}
+
+/// counts an action of the class taken from the queue after the wait
+pub fn record_wait(stats: &mut SchedulerStats, class: PriorityClass, wait: Duration) {
+    let class_stats = stats.entry(class).or_insert_with(ClassStats::default);
+    class_stats.reduced += 1;
+    class_stats.total_wait += wait;
+    if wait > class_stats.max_wait {
+        class_stats.max_wait = wait;
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use action::Action;
+    use holochain_core_types::entry::test_entry_address;
+    use nucleus::tests::test_zome_call;
+
+    #[test]
+    /// a call queued behind network gets is taken first, the gets keep their order
+    fn interactive_actions_overtake_background_ones() {
+        let mut queue = ActionQueue::new(SchedulerPriorities::default());
+        let gets: Vec<ActionWrapper> = (0..3)
+            .map(|_| ActionWrapper::new(Action::GetEntry(test_entry_address())))
+            .collect();
+        for get in gets.iter() {
+            queue.push(get.clone());
+        }
+        let flush = ActionWrapper::new(Action::FlushPublishes);
+        queue.push(flush.clone());
+        let call = ActionWrapper::new(Action::ExecuteZomeFunction(test_zome_call()));
+        queue.push(call.clone());
+
+        let mut popped = Vec::new();
+        while let Some((action_wrapper, class, _)) = queue.pop() {
+            popped.push((action_wrapper, class));
+        }
+        assert_eq!((call, PriorityClass::Interactive), popped[0]);
+        let background: Vec<ActionWrapper> = popped[1..].iter().map(|(aw, _)| aw.clone()).collect();
+        let mut expected = gets.clone();
+        expected.push(flush);
+        assert_eq!(expected, background);
+        assert!(queue.is_empty());
+
+        let mut fifo = ActionQueue::new(SchedulerPriorities::fifo());
+        fifo.push(gets[0].clone());
+        fifo.push(ActionWrapper::new(Action::ExecuteZomeFunction(
+            test_zome_call(),
+        )));
+        assert_eq!(Some(gets[0].clone()), fifo.pop().map(|(aw, _, _)| aw));
+    }
+}
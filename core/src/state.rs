@@ -1,10 +1,42 @@
-use action::ActionWrapper;
-use agent::{chain_store::ChainStore, state::AgentState};
+use action::{ActionFilter, ActionWrapper};
+use agent::{
+    chain_store::ChainStore,
+    state::{ActionResponse, AgentState},
+};
 use context::Context;
-use dht::dht_store::DhtStore;
-use holochain_cas_implementations::{cas::memory::MemoryStorage, eav::memory::EavMemoryStorage};
-use nucleus::state::NucleusState;
-use std::{collections::HashSet, sync::Arc};
+use dht::{
+    dht_store::{DhtStore, Network},
+    network_worker::NetworkRequest,
+};
+use history::HistoryCursor;
+use holochain_cas_implementations::{
+    cas::{encrypted::StorageKey, memory::MemoryStorage},
+    eav::memory::EavMemoryStorage,
+};
+use holochain_core_types::{
+    cas::{
+        content::{Address, AddressableContent, Content},
+        storage::ContentAddressableStorage,
+    },
+    chain_header::ChainHeader,
+    eav::{EntityAttributeValue, EntityAttributeValueStorage},
+    entry::Entry,
+    entry_type::EntryType,
+    error::HolochainError,
+    hash::HashString,
+    json::ToJson,
+    links_entry::{Link, LinkActionKind, LinkEntry},
+};
+use holochain_dna::Dna;
+use multihash::Hash;
+use nucleus::state::{NucleusState, NucleusStatus};
+use recovery::scan_storage;
+use serde_json;
+use snowflake::ProcessUniqueId;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::Arc,
+};
 
 /// The Store of the Holochain instance Object, according to Redux pattern.
 /// It's composed of all sub-module's state slices.
@@ -17,6 +49,14 @@ pub struct State {
     // @TODO eventually drop stale history
     // @see https://github.com/holochain/holochain-rust/issues/166
     pub history: HashSet<ActionWrapper>,
+    /// the position of each action of the history, by action id, so the history can be told
+    /// in the order it was reduced in
+    history_positions: HashMap<ProcessUniqueId, HistoryCursor>,
+    /// position after the last reduced action
+    /// @see history::HistoryCursor
+    history_cursor: HistoryCursor,
+    /// replayed from a subset of the actions, @see State::replay_filtered()
+    partial: bool,
 }
 
 impl State {
@@ -24,42 +64,177 @@ impl State {
         // @TODO file table
         // @see https://github.com/holochain/holochain-rust/pull/246
 
+        let content_storage =
+            MemoryStorage::new().expect("could not create new cas memory storage");
+        State::new_with_content_storage(content_storage)
+    }
+
+    /// new state whose chain and DHT read and write the given content storage
+    /// clones of a MemoryStorage share their content, so states can share a storage
+    pub fn new_with_content_storage(content_storage: MemoryStorage) -> Self {
+        let eav_storage = EavMemoryStorage::new().expect("could not create new eav memory storage");
+        State::new_with_storages(content_storage, eav_storage)
+    }
+
+    /// new state whose chain and DHT read and write the given content storage, and whose DHT
+    /// keeps its links and other metadata in the given EAV storage
+    /// like content storages, clones of an EavMemoryStorage share their content
+    pub fn new_with_storages(
+        content_storage: MemoryStorage,
+        eav_storage: EavMemoryStorage,
+    ) -> Self {
+        State::new_with_storages_and_network(content_storage, eav_storage, Network::new())
+    }
+
+    /// new state whose source chain and links are recovered from the given content storage
+    /// fails with InconsistentStorage if the storage doesn't hold a single intact chain
+    /// @see recovery::scan_storage()
+    pub fn rebuild_from_content_storage(
+        content_storage: MemoryStorage,
+    ) -> Result<Self, HolochainError> {
+        let scan = scan_storage(&content_storage)?;
+        scan.check()?;
+        let mut state = State::new_with_content_storage(content_storage);
+
+        let mut agent = (*state.agent).clone();
+        agent.set_top_chain_header(scan.top_chain_header);
+        state.agent = Arc::new(agent);
+
+        let mut dht = (*state.dht).clone();
+        for link_entry in scan.links.iter() {
+            match link_entry.action_kind() {
+                LinkActionKind::ADD => dht.add_link(link_entry.link(), None)?,
+                LinkActionKind::DELETE => dht.remove_link(link_entry.link())?,
+            }
+        }
+        state.dht = Arc::new(dht);
+        Ok(state)
+    }
+
+    /// new state publishing to and fetching from the given network
+    /// @see Network::new_on()
+    pub fn new_on_network(network: Network) -> Self {
         let content_storage =
             MemoryStorage::new().expect("could not create new cas memory storage");
         let eav_storage = EavMemoryStorage::new().expect("could not create new eav memory storage");
+        State::new_with_storages_and_network(content_storage, eav_storage, network)
+    }
 
+    fn new_with_storages_and_network(
+        content_storage: MemoryStorage,
+        eav_storage: EavMemoryStorage,
+        network: Network,
+    ) -> Self {
         State {
             nucleus: Arc::new(NucleusState::new()),
             agent: Arc::new(AgentState::new(ChainStore::new(content_storage.clone()))),
-            dht: Arc::new(DhtStore::new(content_storage.clone(), eav_storage.clone())),
+            dht: Arc::new(DhtStore::new_with_network(
+                content_storage.clone(),
+                eav_storage.clone(),
+                network,
+            )),
             history: HashSet::new(),
+            history_positions: HashMap::new(),
+            history_cursor: HistoryCursor::new(),
+            partial: false,
         }
     }
 
     pub fn reduce(&self, context: Arc<Context>, action_wrapper: ActionWrapper) -> Self {
+        let agent = ::agent::state::reduce(
+            Arc::clone(&context),
+            Arc::clone(&self.agent),
+            &action_wrapper,
+        );
+        // the DHT only applies a commit or a transaction the agent committed, so the links and
+        // the public entries are stored together with the entries on the chain or not at all,
+        // and only rejects a commit the agent took back off the chain
+        let dht = if agent_refused(&agent, &action_wrapper) {
+            Arc::clone(&self.dht)
+        } else {
+            ::dht::dht_reducers::reduce(
+                Arc::clone(&context),
+                Arc::clone(&self.dht),
+                &action_wrapper,
+            )
+        };
         let mut new_state = State {
             nucleus: ::nucleus::reduce(
                 Arc::clone(&context),
                 Arc::clone(&self.nucleus),
                 &action_wrapper,
             ),
-            agent: ::agent::state::reduce(
-                Arc::clone(&context),
-                Arc::clone(&self.agent),
-                &action_wrapper,
-            ),
-            dht: ::dht::dht_reducers::reduce(
-                Arc::clone(&context),
-                Arc::clone(&self.dht),
-                &action_wrapper,
-            ),
+            agent,
+            dht,
             history: self.history.clone(),
+            history_positions: self.history_positions.clone(),
+            history_cursor: self.history_cursor.next(),
+            partial: self.partial,
         };
 
+        new_state
+            .history_positions
+            .insert(action_wrapper.id().clone(), new_state.history_cursor);
         new_state.history.insert(action_wrapper);
         new_state
     }
 
+    /// takes what the reducers asked of the peers out of the state, with the network to ask
+    /// only the instance does, once the state is its state, @see NetworkWorker
+    pub(crate) fn take_network_requests(&mut self) -> (Network, Vec<NetworkRequest>) {
+        if self.dht.network_requests().is_empty() {
+            return (self.dht.network().clone(), Vec::new());
+        }
+        let mut dht = (*self.dht).clone();
+        let requests = dht.network_requests_mut().split_off(0);
+        let network = dht.network().clone();
+        self.dht = Arc::new(dht);
+        (network, requests)
+    }
+
+    /// state reconstructed by reducing the actions, in order, into a new state with empty
+    /// storage and no network, whose cursor starts at the given one
+    /// actions starting work outside the reducers, i.e. zome calls, only advance the cursor:
+    /// the actions that work dispatched are among the replayed ones
+    pub fn replay(context: Arc<Context>, start: HistoryCursor, actions: &[ActionWrapper]) -> Self {
+        State::replay_filtered(context, start, actions, ActionFilter::all())
+    }
+
+    /// like replay() but only reducing the actions matching the filter, e.g. only the commits
+    /// to quickly reconstruct the authored data
+    /// the other actions only advance the cursor, so unless the filter matches every action
+    /// the state is partial: it lacks their effects, e.g. the entries got from the network
+    pub fn replay_filtered(
+        context: Arc<Context>,
+        start: HistoryCursor,
+        actions: &[ActionWrapper],
+        filter: ActionFilter,
+    ) -> Self {
+        let skipped = ActionFilter::EXECUTE_ZOME_FUNCTION | ActionFilter::CALL;
+        let mut state = State::new();
+        state.history_cursor = start;
+        state.partial = !filter.is_all();
+        for action_wrapper in actions {
+            let action = action_wrapper.action();
+            if skipped.matches(action) || !filter.matches(action) {
+                state.history_cursor = state.history_cursor.next();
+                state
+                    .history_positions
+                    .insert(action_wrapper.id().clone(), state.history_cursor);
+                state.history.insert(action_wrapper.clone());
+            } else {
+                state = state.reduce(context.clone(), action_wrapper.clone());
+            }
+        }
+        state
+    }
+
+    /// true if the state was replayed from a subset of the actions
+    /// @see State::replay_filtered()
+    pub fn is_partial(&self) -> bool {
+        self.partial
+    }
+
     pub fn nucleus(&self) -> Arc<NucleusState> {
         Arc::clone(&self.nucleus)
     }
@@ -71,8 +246,679 @@ impl State {
     pub fn dht(&self) -> Arc<DhtStore<MemoryStorage, EavMemoryStorage>> {
         Arc::clone(&self.dht)
     }
+
+    /// copy of this state holding only what must survive a restart, for persisters to save
+    /// durable: the authoritative CAS and EAV storage, the source chain, the DNA and queued
+    /// publishes
+    /// ephemeral: the action history, results of reduced actions and in-flight calls,
+    /// and what was sent to the network; these start fresh in the copy
+    /// the history cursor is kept so cursors taken before the copy are still comparable
+    pub fn durable(&self) -> Self {
+        State {
+            nucleus: Arc::new(self.nucleus.durable()),
+            agent: Arc::new(self.agent.durable()),
+            dht: Arc::new(self.dht.durable()),
+            history: HashSet::new(),
+            history_positions: HashMap::new(),
+            history_cursor: self.history_cursor,
+            partial: self.partial,
+        }
+    }
+
+    /// copy of this state with its own copy of the content and meta storage, so content added
+    /// to either state afterwards doesn't show in the other
+    /// clones of a state share their storage, @see State::new_with_content_storage()
+    /// the network is shared, content sealed with a key stays sealed with it
+    pub fn detached(&self) -> Result<Self, HolochainError> {
+        let content_storage = match self.dht.content_storage().storage_key() {
+            Some(key) => MemoryStorage::new_sealed(key)?,
+            None => MemoryStorage::new()?,
+        };
+        self.copied_to(content_storage)
+    }
+
+    /// the same state on a network of its own, nothing it publishes or gets reaches the peers
+    /// @see Network::new()
+    pub fn disconnected(&self) -> Self {
+        let mut dht = (*self.dht).clone();
+        *dht.network_mut() = Network::new();
+        State {
+            dht: Arc::new(dht),
+            ..self.clone()
+        }
+    }
+
+    /// copy of this state with its content sealed with the key, like detached()
+    /// a state whose content is sealed with the key already is returned as is, so storages
+    /// shared with other states stay shared, @see Context::set_storage_key()
+    pub fn sealed(&self, key: StorageKey) -> Result<Self, HolochainError> {
+        if self.dht.content_storage().storage_key() == Some(key) {
+            return Ok(self.clone());
+        }
+        self.copied_to(MemoryStorage::new_sealed(key)?)
+    }
+
+    /// copy of this state with the content copied to the given storage and its meta data to
+    /// a storage of its own
+    fn copied_to(&self, mut content_storage: MemoryStorage) -> Result<Self, HolochainError> {
+        let source = self.dht.content_storage();
+        for address in source.addresses()? {
+            if let Some(content) = source.fetch::<Content>(&address)? {
+                content_storage.add(&StoredContent { address, content })?;
+            }
+        }
+        let mut meta_storage = EavMemoryStorage::new()?;
+        for eav in self.dht.meta_storage().fetch_eav(None, None, None)? {
+            meta_storage.add_eav(&eav)?;
+        }
+        Ok(State {
+            nucleus: self.nucleus.clone(),
+            agent: Arc::new(
+                self.agent
+                    .with_chain(ChainStore::new(content_storage.clone())),
+            ),
+            dht: Arc::new(self.dht.with_storages(content_storage, meta_storage)),
+            history: self.history.clone(),
+            history_positions: self.history_positions.clone(),
+            history_cursor: self.history_cursor,
+            partial: self.partial,
+        })
+    }
+
+    /// the same state with the nucleus holding the given DNA instead
+    /// the storages are shared, @see nucleus::revalidation::simulate_validation()
+    pub(crate) fn with_dna(&self, dna: Dna) -> Self {
+        State {
+            nucleus: Arc::new(NucleusState {
+                dna: Some(dna),
+                ..(*self.nucleus).clone()
+            }),
+            ..self.clone()
+        }
+    }
+
+    /// the durable parts of this state as plain data, for persisters writing it out
+    /// content sealed with a storage key stays sealed in the snapshot
+    /// @see State::durable()
+    pub fn snapshot(&self) -> Result<StateSnapshot, HolochainError> {
+        let content_storage = self.dht.content_storage();
+        let mut contents = Vec::new();
+        for address in content_storage.addresses()? {
+            if let Some(content) = content_storage.fetch_sealed(&address)? {
+                contents.push((address, content));
+            }
+        }
+        let mut meta: Vec<EntityAttributeValue> = self
+            .dht
+            .meta_storage()
+            .fetch_eav(None, None, None)?
+            .into_iter()
+            .collect();
+        meta.sort_by_key(|eav| (eav.entity(), eav.attribute(), eav.value()));
+        Ok(StateSnapshot {
+            dna: self.nucleus.dna.clone(),
+            status: self.nucleus.status.clone(),
+            contents,
+            meta,
+            top_chain_header: self
+                .agent
+                .top_chain_header()
+                .map(|chain_header| chain_header.address()),
+            publish_queue: self.dht.publish_queue().clone(),
+            history_cursor: self.history_cursor,
+            sealed: content_storage.storage_key().is_some(),
+        })
+    }
+
+    /// new state with its own storage holding what the snapshot was taken of, sealed with
+    /// the storage key if one is given
+    /// the key has to be the one the content of a sealed snapshot is sealed with, content of
+    /// a plain snapshot is sealed with it on load
+    /// fails with StorageKeyMissing if the snapshot is sealed and no key is given, with
+    /// ErrorGeneric if it is sealed with another key and with InconsistentStorage if the top
+    /// chain header isn't in the snapshot
+    pub fn from_snapshot(
+        snapshot: StateSnapshot,
+        storage_key: Option<StorageKey>,
+    ) -> Result<Self, HolochainError> {
+        let mut content_storage = match storage_key {
+            Some(key) => MemoryStorage::new_sealed(key)?,
+            None if snapshot.sealed => return Err(HolochainError::StorageKeyMissing),
+            None => MemoryStorage::new()?,
+        };
+        for (address, content) in snapshot.contents {
+            if snapshot.sealed {
+                content_storage.add_sealed(&address, &content)?;
+                // fails unless the content opens with the key
+                content_storage.fetch::<Content>(&address)?;
+            } else {
+                content_storage.add(&StoredContent { address, content })?;
+            }
+        }
+        let mut meta_storage = EavMemoryStorage::new()?;
+        for eav in snapshot.meta.iter() {
+            meta_storage.add_eav(eav)?;
+        }
+        let top_chain_header = match snapshot.top_chain_header {
+            Some(address) => Some(content_storage.fetch::<ChainHeader>(&address)?.ok_or_else(
+                || {
+                    HolochainError::InconsistentStorage(format!(
+                        "top chain header {} is missing",
+                        address
+                    ))
+                },
+            )?),
+            None => None,
+        };
+
+        let mut state = State::new_with_content_storage(content_storage.clone());
+        let mut nucleus = NucleusState::new();
+        nucleus.dna = snapshot.dna;
+        nucleus.status = snapshot.status;
+        state.nucleus = Arc::new(nucleus);
+
+        let mut agent = (*state.agent).clone();
+        agent.set_top_chain_header(top_chain_header);
+        state.agent = Arc::new(agent);
+
+        let mut dht = state.dht.with_storages(content_storage, meta_storage);
+        *dht.publish_queue_mut() = snapshot.publish_queue;
+        state.dht = Arc::new(dht);
+        state.history_cursor = snapshot.history_cursor;
+        Ok(state)
+    }
+
+    /// cursor positioned after the last action reduced into this state
+    /// compare cursors taken before and after an operation instead of history lengths
+    pub fn history_cursor(&self) -> HistoryCursor {
+        self.history_cursor
+    }
+
+    /// deterministic hash over the entries on the source chain and the DHT meta data
+    /// it doesn't depend on the order things were added in, so instances holding the same
+    /// data have the same fingerprint
+    /// chain headers are left out because they record the commit order
+    pub fn fingerprint(&self) -> Result<Address, HolochainError> {
+        let entries: BTreeSet<Address> = self.chain_entry_addresses().into_iter().collect();
+        let meta: BTreeSet<(Address, String, Address)> = self
+            .dht
+            .meta_storage()
+            .fetch_eav(None, None, None)?
+            .into_iter()
+            .map(|eav| (eav.entity(), eav.attribute(), eav.value()))
+            .collect();
+        Ok(HashString::encode_from_serializable(
+            (entries, meta),
+            Hash::SHA2256,
+        ))
+    }
+
+    /// deterministic hash over the DHT entries this instance knows of, @see
+    /// DhtStore::held_addresses()
+    /// unlike fingerprint() it leaves out the source chain, so instances of different agents
+    /// have the same DHT fingerprint once each learned of what the others published
+    pub fn dht_fingerprint(&self) -> Address {
+        HashString::encode_from_serializable(self.dht.held_addresses(), Hash::SHA2256)
+    }
+
+    /// addresses of all the entries on the source chain of this snapshot, newest first
+    fn chain_entry_addresses(&self) -> Vec<Address> {
+        self.agent
+            .chain()
+            .iter(&self.agent.top_chain_header())
+            .map(|chain_header| chain_header.entry_address().clone())
+            .collect()
+    }
+
+    /// the entries on the source chain of this snapshot as a JSON array, newest first
+    /// addresses are rendered by the context's address formatter for external systems
+    pub fn export_chain(&self, context: &Context) -> Result<String, HolochainError> {
+        let chain = self.agent.chain();
+        let mut exported = Vec::new();
+        for chain_header in chain.iter(&self.agent.top_chain_header()) {
+            let maybe_entry: Option<Entry> = chain
+                .content_storage()
+                .fetch(chain_header.entry_address())?;
+            if let Some(entry) = maybe_entry {
+                exported.push(json!({
+                    "address": context.format_address(chain_header.entry_address()),
+                    "entry_type": entry.entry_type().to_string(),
+                    "value": entry.value(),
+                }));
+            }
+        }
+        Ok(serde_json::to_string(&exported)?)
+    }
+
+    /// the links the source chain of this snapshot adds and doesn't delete again, newest first
+    /// the newest link entry committed for a link decides whether it is there
+    /// fails with InconsistentStorage if the entry of a link's chain header is missing
+    fn chain_links(&self) -> Result<Vec<Link>, HolochainError> {
+        let chain = self.agent.chain();
+        let mut links = Vec::new();
+        let mut seen = HashSet::new();
+        for chain_header in chain
+            .iter(&self.agent.top_chain_header())
+            .filter(|chain_header| chain_header.entry_type() == &EntryType::Link)
+        {
+            let entry: Entry = chain
+                .content_storage()
+                .fetch(chain_header.entry_address())?
+                .ok_or_else(|| {
+                    HolochainError::InconsistentStorage(format!(
+                        "the entry of chain header {} is missing",
+                        chain_header.entry_address()
+                    ))
+                })?;
+            let link_entry: LinkEntry = serde_json::from_str(entry.value())?;
+            if seen.insert(link_entry.link().clone())
+                && link_entry.action_kind() == &LinkActionKind::ADD
+            {
+                links.push(link_entry.link().clone());
+            }
+        }
+        Ok(links)
+    }
+
+    /// describes what changed between this snapshot and a later one
+    /// entries and links are compared through the source chains of both snapshots as the CAS
+    /// itself is shared between snapshots, each list keeps the order of its source chain
+    /// new actions are in the order they were reduced in
+    /// fails if the link entries on either chain can't be read
+    pub fn diff(&self, other: &State) -> Result<StateDiff, HolochainError> {
+        let before_entries = self.chain_entry_addresses();
+        let after_entries = other.chain_entry_addresses();
+        let before_links = self.chain_links()?;
+        let after_links = other.chain_links()?;
+        let mut new_actions: Vec<&ActionWrapper> =
+            other.history.difference(&self.history).collect();
+        new_actions.sort_by_key(|action_wrapper| {
+            other.history_positions.get(action_wrapper.id()).cloned()
+        });
+
+        Ok(StateDiff {
+            added_entries: not_in(&after_entries, &before_entries),
+            removed_entries: not_in(&before_entries, &after_entries),
+            added_links: not_in(&after_links, &before_links),
+            removed_links: not_in(&before_links, &after_links),
+            new_actions: new_actions
+                .into_iter()
+                .map(|action_wrapper| format!("{:?}", action_wrapper.action()))
+                .collect(),
+        })
+    }
+}
+
+/// The durable parts of a state as plain data, @see State::snapshot()
+/// expiries aren't kept, entries due to expire are kept until they are committed again
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub dna: Option<Dna>,
+    pub status: NucleusStatus,
+    /// everything in the content storage with the address it is stored at, which isn't the
+    /// address of the content for entries addressed by a key
+    pub contents: Vec<(Address, Content)>,
+    /// everything in the meta storage, sorted
+    pub meta: Vec<EntityAttributeValue>,
+    /// address of the chain header at the top of the source chain
+    pub top_chain_header: Option<Address>,
+    pub publish_queue: Vec<Address>,
+    pub history_cursor: HistoryCursor,
+    /// true if the contents are sealed with the storage key of the state, @see cas::encrypted
+    #[serde(default)]
+    pub sealed: bool,
+}
+
+/// The changes between two State snapshots, as returned by State::diff().
+/// Actions are described by their debug representation so the whole diff can be serialized
+/// and sent to a client.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StateDiff {
+    pub added_entries: Vec<Address>,
+    pub removed_entries: Vec<Address>,
+    pub added_links: Vec<Link>,
+    pub removed_links: Vec<Link>,
+    pub new_actions: Vec<String>,
+}
+
+impl StateDiff {
+    /// true if nothing changed between the two snapshots
+    pub fn is_empty(&self) -> bool {
+        self.added_entries.is_empty()
+            && self.removed_entries.is_empty()
+            && self.added_links.is_empty()
+            && self.removed_links.is_empty()
+            && self.new_actions.is_empty()
+    }
+}
+
+impl ToJson for StateDiff {
+    fn to_json(&self) -> Result<String, HolochainError> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+/// content stored at the address it was stored at before, e.g. an entry addressed by a key
+struct StoredContent {
+    address: Address,
+    content: Content,
+}
+
+impl AddressableContent for StoredContent {
+    fn address(&self) -> Address {
+        self.address.clone()
+    }
+
+    fn content(&self) -> Content {
+        self.content.clone()
+    }
+
+    fn from_content(content: &Content) -> Self {
+        StoredContent {
+            address: content.address(),
+            content: content.clone(),
+        }
+    }
+}
+
+/// the items that aren't among the others, in their order
+fn not_in<T: Clone + Eq + ::std::hash::Hash>(items: &[T], others: &[T]) -> Vec<T> {
+    let others: HashSet<&T> = others.iter().collect();
+    items
+        .iter()
+        .filter(|item| !others.contains(item))
+        .cloned()
+        .collect()
+}
+
+/// true if the action is a commit or a transaction the agent didn't commit, or a commit it
+/// didn't roll back
+fn agent_refused(agent: &AgentState, action_wrapper: &ActionWrapper) -> bool {
+    match agent.action_response(action_wrapper) {
+        Some(ActionResponse::Commit(result)) => result.is_err(),
+        Some(ActionResponse::Transaction(result)) => result.is_err(),
+        Some(ActionResponse::RollBackCommit(result)) => result.is_err(),
+        _ => false,
+    }
 }
 
 pub fn test_store() -> State {
     State::new()
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use action::{Action, ActionWrapper};
+    use dht::network::{InMemoryNetwork, NetworkBackend};
+    use holochain_core_types::{
+        cas::content::AddressableContent,
+        entry::{test_entry_a, test_entry_b, test_sys_entry, ToEntry},
+        links_entry::LinkActionKind,
+    };
+    use instance::tests::test_context;
+    use nucleus::ZomeFnCall;
+
+    /// a state with the entries committed in the given order
+    fn committed(entries: Vec<Entry>) -> State {
+        entries.into_iter().fold(test_store(), |state, entry| {
+            state.reduce(
+                test_context("bob"),
+                ActionWrapper::new(Action::Commit(entry)),
+            )
+        })
+    }
+
+    #[test]
+    /// replaying a prefix of the actions reconstructs the state at that point of the history
+    fn replay_reconstructs_past_states() {
+        let actions = vec![
+            ActionWrapper::new(Action::Commit(test_entry_a())),
+            ActionWrapper::new(Action::ExecuteZomeFunction(ZomeFnCall::new(
+                "test_zome",
+                "test_cap",
+                "test",
+                "{}",
+            ))),
+            ActionWrapper::new(Action::Commit(test_entry_b())),
+        ];
+        let start = HistoryCursor::new().advance(5);
+
+        let past = State::replay(test_context("bob"), start, &actions[..1]);
+        assert_eq!(start.advance(1), past.history_cursor());
+        assert_eq!(vec![test_entry_a().address()], past.chain_entry_addresses());
+
+        // the zome call is not run again but still counts toward the cursor
+        let present = State::replay(test_context("bob"), start, &actions);
+        assert_eq!(start.advance(3), present.history_cursor());
+        assert_eq!(
+            committed(vec![test_entry_a(), test_entry_b()]).chain_entry_addresses(),
+            present.chain_entry_addresses()
+        );
+    }
+
+    #[test]
+    /// detached states and snapshots keep content at the address it was stored at, which
+    /// isn't the address of the content for entries addressed by a key
+    fn copies_keep_keyed_addresses() {
+        let entry = test_entry_a().with_address_key("alice");
+        assert_ne!(entry.content().address(), entry.address());
+        let state = test_store();
+        state.dht().content_storage().add(&entry).unwrap();
+
+        let detached = state.detached().unwrap();
+        assert_eq!(
+            Ok(Some(entry.clone())),
+            detached
+                .dht()
+                .content_storage()
+                .fetch::<Entry>(&entry.address())
+        );
+        let restored = State::from_snapshot(state.snapshot().unwrap(), None).unwrap();
+        assert_eq!(
+            Ok(Some(entry.clone())),
+            restored
+                .dht()
+                .content_storage()
+                .fetch::<Entry>(&entry.address())
+        );
+    }
+
+    #[test]
+    /// a diff between a pre-commit and post-commit state only reports the committed entry
+    fn diff_reports_committed_entry() {
+        let context = test_context("bob");
+        let before = test_store();
+        let entry = test_sys_entry();
+
+        let after = before.reduce(context, ActionWrapper::new(Action::Commit(entry.clone())));
+        let diff = before.diff(&after).unwrap();
+
+        assert_eq!(vec![entry.address()], diff.added_entries);
+        assert!(diff.removed_entries.is_empty());
+        assert!(diff.added_links.is_empty());
+        assert!(diff.removed_links.is_empty());
+        assert_eq!(1, diff.new_actions.len());
+
+        assert!(after.diff(&after).unwrap().is_empty());
+    }
+
+    #[test]
+    /// a diff lists the new actions in the order they were reduced in
+    fn diff_orders_new_actions() {
+        let before = test_store();
+        let actions: Vec<ActionWrapper> = (0..20)
+            .map(|index| {
+                let entry = Entry::new(test_entry_a().entry_type(), &index.to_string());
+                ActionWrapper::new(Action::Commit(entry))
+            })
+            .collect();
+        let after = actions
+            .iter()
+            .fold(before.clone(), |state, action_wrapper| {
+                state.reduce(test_context("bob"), action_wrapper.clone())
+            });
+
+        let expected: Vec<String> = actions
+            .iter()
+            .map(|action_wrapper| format!("{:?}", action_wrapper.action()))
+            .collect();
+        assert_eq!(expected, before.diff(&after).unwrap().new_actions);
+    }
+
+    #[test]
+    /// every reduced action advances the history cursor by one
+    fn reduce_advances_history_cursor() {
+        let context = test_context("bob");
+        let before = test_store();
+
+        let after = before.reduce(
+            context,
+            ActionWrapper::new(Action::Commit(test_sys_entry())),
+        );
+
+        assert!(after.history_cursor().is_after(&before.history_cursor()));
+        assert_eq!(
+            1,
+            after
+                .history_cursor()
+                .actions_since(&before.history_cursor())
+        );
+    }
+
+    #[test]
+    /// a diff reports the committed links and serializes to JSON for transmission to a client
+    fn diff_to_json() {
+        let link_entry = LinkEntry::new(
+            LinkActionKind::ADD,
+            &test_entry_a().address(),
+            &test_entry_b().address(),
+            "tag",
+        );
+        let commit = Action::Commit(link_entry.to_entry());
+        let before = committed(vec![test_entry_a()]);
+        let after = before.reduce(test_context("bob"), ActionWrapper::new(commit.clone()));
+        let diff = before.diff(&after).unwrap();
+        assert_eq!(vec![link_entry.link().clone()], diff.added_links);
+
+        let json: serde_json::Value = serde_json::from_str(&diff.to_json().unwrap()).unwrap();
+        assert_eq!(
+            json!([link_entry.to_entry().address()]),
+            json["added_entries"]
+        );
+        assert_eq!(json!([]), json["removed_entries"]);
+        assert_eq!(json!([link_entry.link()]), json["added_links"]);
+        assert_eq!(json!([]), json["removed_links"]);
+        assert_eq!(json!([format!("{:?}", commit)]), json["new_actions"]);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&after.diff(&before).unwrap().to_json().unwrap()).unwrap();
+        assert_eq!(
+            json!([link_entry.to_entry().address()]),
+            json["removed_entries"]
+        );
+        assert_eq!(json!([link_entry.link()]), json["removed_links"]);
+        assert_eq!(json!([]), json["new_actions"]);
+    }
+
+    #[test]
+    /// a link deleted by a later link entry is reported removed by a forward diff
+    fn diff_reports_deleted_link() {
+        let link_entry = |action_kind| {
+            LinkEntry::new(
+                action_kind,
+                &test_entry_a().address(),
+                &test_entry_b().address(),
+                "tag",
+            )
+        };
+        let added = link_entry(LinkActionKind::ADD);
+        let deleted = link_entry(LinkActionKind::DELETE);
+        let before = committed(vec![test_entry_a(), test_entry_b(), added.to_entry()]);
+        let after = before.reduce(
+            test_context("bob"),
+            ActionWrapper::new(Action::Commit(deleted.to_entry())),
+        );
+        let diff = before.diff(&after).unwrap();
+
+        assert_eq!(vec![deleted.to_entry().address()], diff.added_entries);
+        assert!(diff.added_links.is_empty());
+        assert_eq!(vec![added.link().clone()], diff.removed_links);
+        // and added again by the diff the other way round
+        let diff = after.diff(&before).unwrap();
+        assert_eq!(vec![added.link().clone()], diff.added_links);
+        assert!(diff.removed_links.is_empty());
+    }
+
+    #[test]
+    /// exports render addresses with the context's formatter
+    fn export_chain_formats_addresses() {
+        let entry = test_sys_entry();
+        let state = committed(vec![entry.clone()]);
+        let mut context = (*test_context("bob")).clone();
+        context.set_address_formatter(|address| format!("hc://{}", address));
+
+        let exported: serde_json::Value =
+            serde_json::from_str(&state.export_chain(&context).unwrap()).unwrap();
+        assert_eq!(
+            json!([{
+                "address": format!("hc://{}", entry.address()),
+                "entry_type": entry.entry_type().to_string(),
+                "value": entry.value(),
+            }]),
+            exported
+        );
+        // the chain itself keeps the canonical address
+        assert_eq!(
+            &entry.address(),
+            state.agent().top_chain_header().unwrap().entry_address()
+        );
+    }
+
+    #[test]
+    /// the fingerprint depends on the data but not on the order it was committed in
+    fn fingerprint_ignores_commit_order() {
+        let base = test_entry_a().address();
+        let target = test_entry_b().address();
+        let link_a = LinkEntry::new(LinkActionKind::ADD, &base, &target, "a").to_entry();
+        let link_b = LinkEntry::new(LinkActionKind::ADD, &base, &target, "b").to_entry();
+
+        let state_ab = committed(vec![link_a.clone(), link_b.clone()]);
+        let state_ba = committed(vec![link_b.clone(), link_a.clone()]);
+        let state_a = committed(vec![link_a.clone()]);
+
+        assert_ne!(
+            state_ab.agent().top_chain_header(),
+            state_ba.agent().top_chain_header()
+        );
+        assert_eq!(state_ab.fingerprint(), state_ba.fingerprint());
+        assert_ne!(state_ab.fingerprint(), state_a.fingerprint());
+    }
+
+    #[test]
+    /// instances on the same network only agree on the DHT once each learned of the entries
+    /// the others published
+    fn dht_fingerprint_is_what_the_instance_knows() {
+        let backend = Arc::new(InMemoryNetwork::new().unwrap());
+        let mut state = State::new_on_network(Network::new_on(backend.clone()));
+        let mut publisher = State::new_on_network(Network::new_on(backend.clone()));
+        assert_eq!(state.dht_fingerprint(), publisher.dht_fingerprint());
+
+        let mut dht = (*publisher.dht).clone();
+        dht.publish_queue_mut().push(test_entry_a().address());
+        publisher.dht = Arc::new(dht);
+        assert_ne!(state.dht_fingerprint(), publisher.dht_fingerprint());
+
+        // the peers holding the entry doesn't change what the instance knows of
+        backend.publish(&test_entry_a());
+        assert_ne!(state.dht_fingerprint(), publisher.dht_fingerprint());
+
+        state = state.reduce(
+            test_context("bob"),
+            ActionWrapper::new(Action::GetEntriesByType(
+                test_entry_a().entry_type().clone(),
+            )),
+        );
+        assert_eq!(state.dht_fingerprint(), publisher.dht_fingerprint());
+    }
+}
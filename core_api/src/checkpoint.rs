@@ -0,0 +1,62 @@
+//! Checkpoints of the state of an instance to roll back to, e.g. when a migration or a bulk
+//! import goes wrong.
+//!
+//! A checkpoint holds its own copy of the content and meta storage, so what is committed after
+//! it was taken doesn't show in it. Rolling back hands out another copy, so the same
+//! checkpoint can be rolled back to more than once.
+//!
+//! Everything else the state holds is plain data and comes back as it was: the chain and its
+//! delegations, the ephemeral entries, the publish queue and the record of what was published.
+//! What an instance keeps outside of its state can't be copied, so instances storing entries
+//! of routed types in other storages, or handing entries to peers, can't take checkpoints.
+
+use holochain_core::state::State;
+use holochain_core_types::error::HolochainError;
+use std::collections::BTreeMap;
+
+/// Id of a checkpoint, later checkpoints have greater ids
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CheckpointId(u64);
+
+/// The checkpoints taken of an instance, by id
+#[derive(Default)]
+pub struct Checkpoints {
+    next: u64,
+    states: BTreeMap<CheckpointId, State>,
+}
+
+impl Checkpoints {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// keeps a copy of the state, returning the id to roll back to it with
+    pub fn take(&mut self, state: &State) -> Result<CheckpointId, HolochainError> {
+        let id = CheckpointId(self.next);
+        self.states.insert(id, state.detached()?);
+        self.next += 1;
+        Ok(id)
+    }
+
+    /// a copy of the state of the checkpoint, discarding the checkpoints taken after it
+    /// fails with UnknownCheckpoint if there is no checkpoint with the id
+    pub fn roll_back(&mut self, id: CheckpointId) -> Result<State, HolochainError> {
+        let state = self
+            .states
+            .get(&id)
+            .ok_or(HolochainError::UnknownCheckpoint)?
+            .detached()?;
+        self.states.split_off(&CheckpointId(id.0 + 1));
+        Ok(state)
+    }
+
+    /// forgets the checkpoint, returning whether there was one with the id
+    pub fn discard(&mut self, id: CheckpointId) -> bool {
+        self.states.remove(&id).is_some()
+    }
+
+    /// ids of the checkpoints there are, oldest first
+    pub fn ids(&self) -> Vec<CheckpointId> {
+        self.states.keys().cloned().collect()
+    }
+}
@@ -0,0 +1,376 @@
+//! Several Holochain instances on a shared in-memory network, for multi-node tests.
+//!
+//! Whatever a node publishes is put in the InMemoryNetwork standing in for the peers, where
+//! every node can fetch it. settle() waits until no node reduces actions any more, so
+//! assertions run against a quiescent cluster, and await_consistency() until all nodes see the
+//! same DHT. Nodes don't read the wall clock: they share a logical clock that only advances
+//! when a test says so, e.g. to expire entries.
+
+use holochain_agent::Agent;
+use holochain_core::{
+    clock::{Clock, ManualClock},
+    context::Context,
+    dht::{dht_store::Network, network::InMemoryNetwork},
+    history::HistoryCursor,
+    logger::SimpleLogger,
+    persister::SimplePersister,
+};
+use holochain_core_types::{cas::content::AddressableContent, error::HolochainError};
+use holochain_dna::Dna;
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use Holochain;
+
+/// how long every node must go without reducing an action for the cluster to be settled
+pub const SETTLE_INTERVAL_MS: u64 = 50;
+/// how many intervals settle() waits for the cluster to become quiescent
+pub const MAX_SETTLE_ROUNDS: usize = 100;
+
+/// dropping the cluster drops its nodes, which ends their event loops and other threads
+pub struct TestNetworkCluster {
+    nodes: Vec<Holochain>,
+    /// the clock of every node
+    clock: ManualClock,
+    /// the app entry types of the DNA, which nodes ask the peers about to learn of entries
+    entry_types: Vec<String>,
+}
+
+impl TestNetworkCluster {
+    /// starts size instances of the DNA on the same in-memory network
+    /// the agent of node i is "node-i"
+    pub fn new(dna: &Dna, size: usize) -> Result<Self, HolochainError> {
+        let network = Arc::new(InMemoryNetwork::new()?);
+        let clock = ManualClock::new(Duration::from_secs(0));
+        let mut nodes = Vec::new();
+        for index in 0..size {
+            let agent = Agent::from(format!("node-{}", index));
+            let mut context = Context::new(
+                agent.clone(),
+                Arc::new(Mutex::new(SimpleLogger {})),
+                Arc::new(Mutex::new(SimplePersister::new())),
+            );
+            context.clock = Arc::new(clock.clone());
+            let mut node = Holochain::new_on_network(
+                dna.clone(),
+                Arc::new(context),
+                Network::new_on(network.clone()),
+                HolochainConfig::default(),
+            )?;
+            node.start()?;
+            // whoever runs the network admits the agent with the key it signs with
+            network.trust_key(&agent.address(), node.public_key()?);
+            nodes.push(node);
+        }
+        let mut entry_types: Vec<String> = dna
+            .zomes
+            .values()
+            .flat_map(|zome| zome.entry_types.keys().cloned())
+            .collect();
+        entry_types.sort();
+        Ok(TestNetworkCluster {
+            nodes,
+            clock,
+            entry_types,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// the node at the index
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are not more nodes than the index.
+    pub fn node(&mut self, index: usize) -> &mut Holochain {
+        &mut self.nodes[index]
+    }
+
+    /// waits until no node reduced an action for a whole settle interval
+    /// fails if the cluster is still busy after MAX_SETTLE_ROUNDS intervals
+    pub fn settle(&mut self) -> Result<(), HolochainError> {
+        let mut cursors = self.cursors();
+        for _ in 0..MAX_SETTLE_ROUNDS {
+            thread::sleep(Duration::from_millis(SETTLE_INTERVAL_MS));
+            let next_cursors = self.cursors();
+            if next_cursors == cursors {
+                return Ok(());
+            }
+            cursors = next_cursors;
+        }
+        Err(HolochainError::ErrorGeneric(format!(
+            "cluster didn't settle within {} rounds",
+            MAX_SETTLE_ROUNDS
+        )))
+    }
+
+    /// waits until every node sees the same DHT as node 0, each node asking the peers about
+    /// every entry type of the DNA on every round
+    /// fails if they still disagree after the timeout
+    /// @see Holochain::is_consistent_with()
+    pub fn await_consistency(&mut self, timeout: Duration) -> Result<(), HolochainError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            for node in self.nodes.iter_mut() {
+                for entry_type in self.entry_types.iter() {
+                    node.query_network(entry_type)?;
+                }
+            }
+            if let Some((first, others)) = self.nodes.split_first() {
+                let fingerprint = first.dht_fingerprint();
+                if others
+                    .iter()
+                    .all(|node| node.is_consistent_with(&fingerprint))
+                {
+                    return Ok(());
+                }
+            } else {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(HolochainError::ErrorGeneric(format!(
+                    "cluster didn't reach consistency within {:?}",
+                    timeout
+                )));
+            }
+            thread::sleep(Duration::from_millis(SETTLE_INTERVAL_MS));
+        }
+    }
+
+    /// the logical time elapsed since the cluster was created, as every node reads it
+    pub fn clock(&self) -> Duration {
+        self.clock.now()
+    }
+
+    /// moves the logical clock of every node forward, independently of how long the scenario
+    /// took
+    pub fn advance_clock(&mut self, by: Duration) {
+        self.clock.advance(by);
+    }
+
+    fn cursors(&mut self) -> Vec<Option<HistoryCursor>> {
+        self.nodes
+            .iter_mut()
+            .map(|node| node.state().ok().map(|state| state.history_cursor()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core::action::{Action, ActionWrapper};
+    use holochain_core_types::{
+        entry::{test_entry, Entry},
+        entry_type::test_entry_type,
+    };
+    use test_utils::create_test_dna_with_wat;
+
+    /// DNA whose "main" function commits the entry described by its parameters
+    fn test_commit_dna() -> Dna {
+        let wat = r#"
+(module
+    (import "env" "hc_commit_entry"
+        (func $commit_entry
+            (param i32)
+            (result i32)
+        )
+    )
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func
+        (export "main")
+        (param $allocation i32)
+        (result i32)
+
+        (call
+            $commit_entry
+            (get_local $allocation)
+        )
+    )
+
+    (func
+        (export "validate_testEntryType")
+        (param $allocation i32)
+        (result i32)
+
+        (i32.const 0)
+    )
+)
+"#;
+        create_test_dna_with_wat("test_zome", "test_cap", Some(wat))
+    }
+
+    #[test]
+    /// an entry committed on node 0 can be fetched on node 2 once the cluster settled
+    fn commit_is_visible_on_other_node_after_settle() {
+        let mut cluster = TestNetworkCluster::new(&test_commit_dna(), 3).unwrap();
+        assert_eq!(3, cluster.size());
+        let address = test_entry().address();
+        assert_eq!(Ok(None), cluster.node(2).get_entry(&address));
+
+        let commit_args = format!(
+            r#"{{"entry_type_name":"testEntryType","entry_value":"{}","content_type":null}}"#,
+            test_entry().value()
+        );
+        let result = cluster
+            .node(0)
+            .call("test_zome", "test_cap", "main", &commit_args);
+        assert!(result.is_ok(), "result = {:?}", result);
+        cluster.settle().expect("cluster should settle");
+
+        assert_eq!(Ok(Some(test_entry())), cluster.node(2).get_entry(&address));
+        assert_eq!(
+            Ok(vec![address]),
+            cluster.node(2).query_network("testEntryType")
+        );
+    }
+
+    #[test]
+    /// after a commit on node 0 every node holds the entry once the cluster is consistent
+    fn await_consistency_after_commit() {
+        let mut cluster = TestNetworkCluster::new(&test_commit_dna(), 3).unwrap();
+        let address = test_entry().address();
+        let commit_args = format!(
+            r#"{{"entry_type_name":"testEntryType","entry_value":"{}"}}"#,
+            test_entry().value()
+        );
+        let result = cluster
+            .node(0)
+            .call("test_zome", "test_cap", "main", &commit_args);
+        assert!(result.is_ok(), "result = {:?}", result);
+
+        cluster
+            .await_consistency(Duration::from_secs(5))
+            .expect("cluster should become consistent");
+        let fingerprint = cluster.node(0).dht_fingerprint();
+        for index in 0..cluster.size() {
+            assert!(cluster.node(index).is_consistent_with(&fingerprint));
+            assert_eq!(
+                Ok(Some(test_entry())),
+                cluster.node(index).get_entry(&address)
+            );
+        }
+    }
+
+    #[test]
+    /// an entry with an access control list can be got by the listed agents only
+    fn acl_restricts_who_gets_entry() {
+        let mut cluster = TestNetworkCluster::new(&test_commit_dna(), 3).unwrap();
+        let address = test_entry().address();
+        let reader = Agent::from("node-1".to_string()).address();
+        let commit_args = format!(
+            r#"{{"entry_type_name":"testEntryType","entry_value":"{}","acl":["{}"]}}"#,
+            test_entry().value(),
+            reader
+        );
+        let result = cluster
+            .node(0)
+            .call("test_zome", "test_cap", "main", &commit_args);
+        assert!(result.is_ok(), "result = {:?}", result);
+        cluster.settle().expect("cluster should settle");
+
+        assert_eq!(Ok(Some(test_entry())), cluster.node(0).get_entry(&address));
+        assert_eq!(Ok(Some(test_entry())), cluster.node(1).get_entry(&address));
+        assert_eq!(
+            Err(HolochainError::AccessDenied),
+            cluster.node(2).get_entry(&address)
+        );
+    }
+
+    #[test]
+    /// committing the same content with an access control list of its own neither adds an
+    /// agent to the list of a restricted entry nor restricts a public one
+    fn acl_is_only_set_by_author() {
+        let mut cluster = TestNetworkCluster::new(&test_commit_dna(), 3).unwrap();
+        let commit = |value: &str, reader: &str| {
+            format!(
+                r#"{{"entry_type_name":"testEntryType","entry_value":"{}","acl":["{}"]}}"#,
+                value,
+                Agent::from(reader.to_string()).address()
+            )
+        };
+        let restricted = test_entry();
+        let public = Entry::new(&test_entry_type(), &"public".to_string());
+
+        let result = cluster.node(0).call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &commit(restricted.value(), "node-1"),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        let result = cluster.node(0).call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &format!(
+                r#"{{"entry_type_name":"testEntryType","entry_value":"{}"}}"#,
+                public.value()
+            ),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        cluster.settle().expect("cluster should settle");
+
+        // node 2 commits both with itself as the only reader
+        for entry in vec![&restricted, &public] {
+            let _ = cluster.node(2).call(
+                "test_zome",
+                "test_cap",
+                "main",
+                &commit(entry.value(), "node-2"),
+            );
+        }
+        cluster.settle().expect("cluster should settle");
+
+        assert_eq!(
+            Err(HolochainError::AccessDenied),
+            cluster.node(2).get_entry(&restricted.address())
+        );
+        assert_eq!(
+            Ok(Some(restricted.clone())),
+            cluster.node(1).get_entry(&restricted.address())
+        );
+        assert_eq!(
+            Ok(Some(public.clone())),
+            cluster.node(1).get_entry(&public.address())
+        );
+    }
+
+    #[test]
+    /// the logical clock only moves when advanced, and is the clock of every node
+    fn clock_is_deterministic() {
+        let mut cluster = TestNetworkCluster::new(&Dna::new(), 2).unwrap();
+        cluster.settle().expect("cluster should settle");
+        assert_eq!(Duration::from_secs(0), cluster.clock());
+        cluster.advance_clock(Duration::from_secs(5));
+        assert_eq!(Duration::from_secs(5), cluster.clock());
+        for index in 0..cluster.size() {
+            assert_eq!(
+                Duration::from_secs(5),
+                cluster.node(index).context.clock.now()
+            );
+        }
+    }
+    #[test]
+    /// dropping the cluster ends the event loops of its nodes, stopped or not
+    fn drop_ends_node_action_loops() {
+        let mut cluster = TestNetworkCluster::new(&Dna::new(), 2).unwrap();
+        cluster.node(1).stop().expect("couldn't stop");
+        let action_channels: Vec<_> = (0..cluster.size())
+            .map(|index| cluster.node(index).instance.action_channel())
+            .collect();
+        drop(cluster);
+        for action_channel in action_channels {
+            assert!(action_channel
+                .send(ActionWrapper::new(Action::ExpireEntries))
+                .is_err());
+        }
+    }
+}
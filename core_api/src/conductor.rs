@@ -0,0 +1,329 @@
+//! Hosting of several Holochain instances side by side, so their bridges can be wired up.
+//!
+//! Instances are registered under the id that the "target" of a DNA's bridges refers to.
+//! A bridge is resolved once an instance with its target id is registered and running.
+//!
+//! The conductor also holds a network get limit for the instances to share, so a busy
+//! instance can't take all the network resources of the host from the others.
+
+use holochain_core::dht::get_limit::NetworkGetLimit;
+use holochain_dna::bridge::Bridge;
+use std::collections::{BTreeMap, HashMap};
+use Holochain;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BridgeStatus {
+    /// the target instance is registered and running
+    Resolved,
+    /// the target instance is not registered, or not running
+    Unresolved,
+}
+
+/// A bridge declared in an instance's DNA, and whether calls through it can reach the target
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BridgeInfo {
+    pub handle: String,
+    pub target: String,
+    pub status: BridgeStatus,
+}
+
+impl BridgeInfo {
+    pub fn new(bridge: &Bridge, status: BridgeStatus) -> Self {
+        BridgeInfo {
+            handle: bridge.handle.clone(),
+            target: bridge.target.clone(),
+            status,
+        }
+    }
+}
+
+/// Network gets in flight across the instances of a conductor
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NetworkConcurrencyStats {
+    /// gets in flight counted against the shared limit
+    pub in_flight: usize,
+    /// bound of the shared limit, None for unlimited
+    pub bound: Option<usize>,
+    /// gets in flight by instance id, whether their limit is within the shared one or not
+    pub by_instance: BTreeMap<String, usize>,
+}
+
+/// A set of Holochain instances hosted by the same container, by instance id
+#[derive(Default)]
+pub struct Conductor {
+    instances: HashMap<String, Holochain>,
+    network_limit: NetworkGetLimit,
+}
+
+impl Conductor {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// a conductor whose instances share a limit of bound network gets in flight, None for
+    /// unlimited
+    pub fn new_with_network_limit(bound: Option<usize>) -> Self {
+        Conductor {
+            instances: HashMap::new(),
+            network_limit: NetworkGetLimit::new(bound),
+        }
+    }
+
+    /// the limit the network gets of the instances count against, on top of their own
+    /// the contexts of the instances opt in with NetworkGetLimit::within()
+    pub fn network_limit(&self) -> &NetworkGetLimit {
+        &self.network_limit
+    }
+
+    /// the network gets in flight right now, in total and by instance
+    pub fn network_concurrency_stats(&self) -> NetworkConcurrencyStats {
+        NetworkConcurrencyStats {
+            in_flight: self.network_limit.in_flight(),
+            bound: self.network_limit.bound(),
+            by_instance: self
+                .instances
+                .iter()
+                .map(|(id, instance)| (id.clone(), instance.network_gets_in_flight()))
+                .collect(),
+        }
+    }
+
+    /// register the instance under the id, returning the instance it replaces if any
+    pub fn add_instance(&mut self, id: &str, instance: Holochain) -> Option<Holochain> {
+        self.instances.insert(id.to_string(), instance)
+    }
+
+    /// unregister the instance, bridges targeting it become unresolved
+    pub fn remove_instance(&mut self, id: &str) -> Option<Holochain> {
+        self.instances.remove(id)
+    }
+
+    pub fn instance(&self, id: &str) -> Option<&Holochain> {
+        self.instances.get(id)
+    }
+
+    pub fn instance_mut(&mut self, id: &str) -> Option<&mut Holochain> {
+        self.instances.get_mut(id)
+    }
+
+    /// the bridges declared by the instance's DNA, resolved against the registered instances
+    /// None if no instance is registered under the id
+    pub fn bridges(&self, id: &str) -> Option<Vec<BridgeInfo>> {
+        self.instances.get(id).map(|instance| {
+            instance
+                .declared_bridges()
+                .iter()
+                .map(|bridge| BridgeInfo::new(bridge, self.status(bridge)))
+                .collect()
+        })
+    }
+
+    /// the unresolved bridges of all registered instances, by id of the declaring instance
+    /// sorted by instance id
+    pub fn unresolved_bridges(&self) -> Vec<(String, BridgeInfo)> {
+        let mut ids: Vec<&String> = self.instances.keys().collect();
+        ids.sort();
+        ids.into_iter()
+            .flat_map(|id| {
+                self.bridges(id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|info| info.status == BridgeStatus::Unresolved)
+                    .map(move |info| (id.clone(), info))
+            })
+            .collect()
+    }
+
+    /// ids of the registered instances labeled with the value under the key, sorted
+    /// @see Holochain::set_label()
+    pub fn instances_with_label(&self, key: &str, value: &str) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .instances
+            .iter()
+            .filter(|(_, instance)| instance.label(key) == Some(value))
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// true if both instances are registered and on the same DHT, e.g. before wiring them
+    /// up with a bridge, @see Holochain::network_id()
+    pub fn same_network(&self, id_a: &str, id_b: &str) -> bool {
+        let network_id = |id: &str| {
+            self.instances
+                .get(id)
+                .and_then(|instance| instance.network_id().ok())
+        };
+        match (network_id(id_a), network_id(id_b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    fn status(&self, bridge: &Bridge) -> BridgeStatus {
+        match self.instances.get(&bridge.target) {
+            Some(target) if target.active() => BridgeStatus::Resolved,
+            _ => BridgeStatus::Unresolved,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate holochain_agent;
+    use super::*;
+    use config::HolochainConfig;
+    use holochain_core::{context::Context, persister::SimplePersister};
+    use holochain_dna::Dna;
+    use std::sync::{Arc, Mutex};
+    use test_utils;
+
+    fn test_holochain(dna: Dna) -> Holochain {
+        let context = Arc::new(Context::new(
+            holochain_agent::Agent::from("bob".to_string()),
+            test_utils::test_logger(),
+            Arc::new(Mutex::new(SimplePersister::new())),
+        ));
+        Holochain::new(dna, context).expect("instance should initialize")
+    }
+
+    fn test_bridge() -> Bridge {
+        let mut bridge = Bridge::new();
+        bridge.handle = "accounts".to_string();
+        bridge.target = "accounts-instance".to_string();
+        bridge
+    }
+
+    #[test]
+    /// a declared bridge is unresolved until its target instance is registered and running
+    fn bridge_resolves_once_target_runs() {
+        let mut dna = Dna::new();
+        dna.name = "TestApp".to_string();
+        dna.bridges.push(test_bridge());
+
+        let mut conductor = Conductor::new();
+        conductor.add_instance("app", test_holochain(dna));
+        let unresolved = BridgeInfo::new(&test_bridge(), BridgeStatus::Unresolved);
+        assert_eq!(Some(vec![unresolved.clone()]), conductor.bridges("app"));
+        assert_eq!(
+            vec![unresolved.clone()],
+            conductor.instance("app").unwrap().bridges()
+        );
+        assert_eq!(
+            vec![("app".to_string(), unresolved.clone())],
+            conductor.unresolved_bridges()
+        );
+
+        conductor.add_instance("accounts-instance", test_holochain(Dna::new()));
+        assert_eq!(Some(vec![unresolved]), conductor.bridges("app"));
+
+        conductor
+            .instance_mut("accounts-instance")
+            .unwrap()
+            .start()
+            .unwrap();
+        assert_eq!(
+            Some(vec![BridgeInfo::new(
+                &test_bridge(),
+                BridgeStatus::Resolved
+            )]),
+            conductor.bridges("app")
+        );
+        assert!(conductor.unresolved_bridges().is_empty());
+        assert_eq!(None, conductor.bridges("unknown"));
+    }
+
+    #[test]
+    /// only the instances with the label's value are listed
+    fn filters_instances_by_label() {
+        let mut conductor = Conductor::new();
+        for (id, env) in vec![("a", "prod"), ("b", "staging"), ("c", "prod")] {
+            let mut instance = test_holochain(Dna::new());
+            assert_eq!(None, instance.set_label("env", env));
+            instance.set_label("owner", "ops");
+            conductor.add_instance(id, instance);
+        }
+        conductor.add_instance("unlabeled", test_holochain(Dna::new()));
+
+        assert_eq!(
+            vec!["a".to_string(), "c".to_string()],
+            conductor.instances_with_label("env", "prod")
+        );
+        assert_eq!(
+            vec!["b".to_string()],
+            conductor.instances_with_label("env", "staging")
+        );
+        assert!(conductor.instances_with_label("env", "dev").is_empty());
+
+        {
+            let b = conductor.instance_mut("b").unwrap();
+            assert_eq!(Some("staging".to_string()), b.set_label("env", "prod"));
+            assert_eq!(Some("prod"), b.label("env"));
+            assert_eq!(Some("ops".to_string()), b.remove_label("owner"));
+            assert_eq!(1, b.labels().len());
+        }
+        assert_eq!(3, conductor.instances_with_label("env", "prod").len());
+    }
+
+    #[test]
+    /// instances of the same DNA share a network unless their network names differ
+    fn same_dna_means_same_network() {
+        let mut dna = Dna::new();
+        dna.name = "TestApp".to_string();
+        let mut other_dna = Dna::new();
+        other_dna.name = "OtherApp".to_string();
+        let mut config = HolochainConfig::default();
+        config.network_name = "staging".to_string();
+        let context = Arc::new(Context::new(
+            holochain_agent::Agent::from("bob".to_string()),
+            test_utils::test_logger(),
+            Arc::new(Mutex::new(SimplePersister::new())),
+        ));
+        let staging = Holochain::new_with_config(dna.clone(), context, config).unwrap();
+
+        let mut conductor = Conductor::new();
+        conductor.add_instance("a", test_holochain(dna.clone()));
+        conductor.add_instance("b", test_holochain(dna));
+        conductor.add_instance("other", test_holochain(other_dna));
+        conductor.add_instance("staging", staging);
+
+        assert_eq!(
+            conductor.instance("a").unwrap().network_id(),
+            conductor.instance("b").unwrap().network_id()
+        );
+        assert!(conductor.same_network("a", "b"));
+        assert!(!conductor.same_network("a", "other"));
+        assert!(!conductor.same_network("a", "staging"));
+        assert!(!conductor.same_network("a", "unknown"));
+    }
+
+    #[test]
+    /// the gets of the instances within the shared limit count towards its stats
+    fn network_concurrency_stats_add_up_instances() {
+        let mut conductor = Conductor::new_with_network_limit(Some(3));
+        for id in vec!["a", "b"] {
+            let mut context = Context::new(
+                holochain_agent::Agent::from("bob".to_string()),
+                test_utils::test_logger(),
+                Arc::new(Mutex::new(SimplePersister::new())),
+            );
+            context.network_get_limit =
+                NetworkGetLimit::new(Some(2)).within(conductor.network_limit());
+            let instance = Holochain::new(Dna::new(), Arc::new(context)).unwrap();
+            conductor.add_instance(id, instance);
+        }
+        assert_eq!(0, conductor.network_concurrency_stats().in_flight);
+
+        let a = &conductor.instance("a").unwrap().context.network_get_limit;
+        let b = &conductor.instance("b").unwrap().context.network_get_limit;
+        let _permits = vec![a.acquire(), a.acquire(), b.acquire()];
+        let stats = conductor.network_concurrency_stats();
+        assert_eq!(3, stats.in_flight);
+        assert_eq!(Some(3), stats.bound);
+        let mut by_instance = BTreeMap::new();
+        by_instance.insert("a".to_string(), 2);
+        by_instance.insert("b".to_string(), 1);
+        assert_eq!(by_instance, stats.by_instance);
+    }
+}
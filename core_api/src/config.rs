@@ -0,0 +1,97 @@
+//! Instance-level configuration for a Holochain instance.
+//! New tunables should be added here rather than as new constructors on Holochain.
+
+use holochain_core::{
+    agent::state::ChainRateLimit,
+    dht::dht_store::PublishRateLimit,
+    nucleus::actions::{initialize::INITIALIZATION_TIMEOUT, validate::ValidationLevel},
+};
+use std::time::Duration;
+use subscription::TailPolicy;
+
+/// Options for Holochain::new_with_config() and the other constructors of Holochain
+/// Default::default() reproduces the behavior of Holochain::new()
+#[derive(Clone, Debug, PartialEq)]
+pub struct HolochainConfig {
+    /// how long genesis may take before instantiation fails
+    pub initialization_timeout: Duration,
+    /// publish public entries to the network as soon as they are committed
+    /// if false they are queued until Holochain::flush_publishes()
+    pub auto_publish: bool,
+    /// bound on the rate entries are sent to the network at, None for unlimited
+    /// entries over the limit are published by later commits or Holochain::flush_publishes()
+    pub publish_rate_limit: Option<PublishRateLimit>,
+    /// bound on the rate entries are committed to the source chain at, None for unlimited
+    /// guards against zomes committing in a loop; commits over it fail with ChainRateLimited
+    pub chain_rate_limit: Option<ChainRateLimit>,
+    /// whether commits failing validation are rejected, committed with a logged warning,
+    /// or committed without running validation
+    pub validation_level: ValidationLevel,
+    /// keep a CallRecord of every zome call for Holochain::replay_call()
+    pub record_calls: bool,
+    /// keep every reduced action for Holochain::state_at() and Holochain::replay_actions()
+    /// the log grows for the whole lifetime of the instance
+    pub record_history: bool,
+    /// how often a started instance checks for entries whose time to live passed
+    pub expiry_sweep_interval: Duration,
+    /// how long entries of ephemeral types are kept after they are committed
+    /// they are dropped by the same sweeps as the entries whose time to live passed
+    pub ephemeral_ttl: Duration,
+    /// how often a started instance checks whether scheduled functions are due
+    /// @see Dna::scheduled_functions
+    pub schedule_check_interval: Duration,
+    /// how actions are buffered for streams from Holochain::tail_actions() lagging behind
+    pub action_tail_policy: TailPolicy,
+    /// the network among those of the DNA the instance is meant for, e.g. to keep a staging
+    /// deployment apart from production, @see Holochain::network_id()
+    pub network_name: String,
+}
+
+impl Default for HolochainConfig {
+    fn default() -> Self {
+        HolochainConfig {
+            initialization_timeout: Duration::from_secs(INITIALIZATION_TIMEOUT),
+            auto_publish: true,
+            publish_rate_limit: None,
+            chain_rate_limit: None,
+            validation_level: ValidationLevel::Strict,
+            record_calls: false,
+            record_history: false,
+            expiry_sweep_interval: Duration::from_secs(1),
+            ephemeral_ttl: Duration::from_secs(3600),
+            schedule_check_interval: Duration::from_secs(1),
+            action_tail_policy: TailPolicy::DropNewest(1024),
+            network_name: String::new(),
+        }
+    }
+}
+
+impl HolochainConfig {
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    /// defaults match the behavior of Holochain::new()
+    fn default_config() {
+        let config = HolochainConfig::default();
+        assert_eq!(Duration::from_secs(30), config.initialization_timeout);
+        assert!(config.auto_publish);
+        assert_eq!(None, config.publish_rate_limit);
+        assert_eq!(None, config.chain_rate_limit);
+        assert_eq!(ValidationLevel::Strict, config.validation_level);
+        assert!(!config.record_calls);
+        assert!(!config.record_history);
+        assert_eq!(Duration::from_secs(1), config.expiry_sweep_interval);
+        assert_eq!(Duration::from_secs(3600), config.ephemeral_ttl);
+        assert_eq!(Duration::from_secs(1), config.schedule_check_interval);
+        assert_eq!(TailPolicy::DropNewest(1024), config.action_tail_policy);
+        assert_eq!("", config.network_name);
+        assert_eq!(config, HolochainConfig::new());
+    }
+}
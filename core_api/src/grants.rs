@@ -0,0 +1,198 @@
+//! Grants of access to capabilities whose membrane requires a token.
+//!
+//! The instance hands out a token per grant; calls to a capability with the api-key membrane
+//! must present a token granted for it. Tokens are random, so holding one is the proof of the
+//! grant. Grants can be limited to a number of uses and to a
+//! point in time read from Context::clock, e.g. for one-time invite links or time-boxed access.
+//! Exhausted and expired grants are revoked when they are next presented.
+
+use holochain_core_types::error::HolochainError;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::{collections::HashMap, time::Duration};
+
+/// number of random bytes of a token
+const TOKEN_LEN: usize = 32;
+
+/// a fresh token of random bytes of the operating system, as hex
+fn new_token() -> Result<String, HolochainError> {
+    let mut bytes = [0; TOKEN_LEN];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| HolochainError::ErrorGeneric("no random bytes for a token".to_string()))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Limits of a grant, unlimited by default
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GrantOptions {
+    /// time of the context clock from which on the grant is expired
+    pub expires_at: Option<Duration>,
+    /// number of calls the grant can be used for
+    pub max_uses: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapabilityGrant {
+    pub zome: String,
+    pub cap: String,
+    pub options: GrantOptions,
+    /// number of calls the grant was used for so far
+    pub uses: u32,
+}
+
+impl CapabilityGrant {
+    fn is_expired(&self, now: Duration) -> bool {
+        self.options
+            .expires_at
+            .map(|expires_at| now >= expires_at)
+            .unwrap_or(false)
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.options
+            .max_uses
+            .map(|max_uses| self.uses >= max_uses)
+            .unwrap_or(false)
+    }
+}
+
+/// The grants handed out by an instance, by token
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CapabilityGrants {
+    grants: HashMap<String, CapabilityGrant>,
+}
+
+impl CapabilityGrants {
+    pub fn new() -> Self {
+        CapabilityGrants::default()
+    }
+
+    /// grants access to the capability, returns the token to call it with
+    /// fails if the operating system has no random bytes for the token
+    pub fn grant(
+        &mut self,
+        zome: &str,
+        cap: &str,
+        options: GrantOptions,
+    ) -> Result<String, HolochainError> {
+        let token = new_token()?;
+        self.grants.insert(
+            token.clone(),
+            CapabilityGrant {
+                zome: zome.to_string(),
+                cap: cap.to_string(),
+                options,
+                uses: 0,
+            },
+        );
+        Ok(token)
+    }
+
+    /// the grant of the token, None if it was never granted or was revoked
+    pub fn get(&self, token: &str) -> Option<&CapabilityGrant> {
+        self.grants.get(token)
+    }
+
+    pub fn revoke(&mut self, token: &str) {
+        self.grants.remove(token);
+    }
+
+    /// checks that the token opens the capability without counting a call
+    /// fails like use_grant()
+    pub fn check(
+        &self,
+        token: &str,
+        zome: &str,
+        cap: &str,
+        now: Duration,
+    ) -> Result<(), HolochainError> {
+        match self.grants.get(token) {
+            Some(ref grant)
+                if grant.zome == zome
+                    && grant.cap == cap
+                    && !grant.is_expired(now)
+                    && !grant.is_exhausted() =>
+            {
+                Ok(())
+            }
+            _ => Err(HolochainError::DoesNotHaveCapabilityToken),
+        }
+    }
+
+    /// counts a call to the capability made with the token
+    /// fails with DoesNotHaveCapabilityToken if the token wasn't granted for the capability
+    /// or its grant expired; grants that expired or are used up are revoked
+    pub fn use_grant(
+        &mut self,
+        token: &str,
+        zome: &str,
+        cap: &str,
+        now: Duration,
+    ) -> Result<(), HolochainError> {
+        let (result, revoke) = match self.grants.get_mut(token) {
+            Some(ref grant) if grant.zome != zome || grant.cap != cap => (Err(()), false),
+            Some(ref grant) if grant.is_expired(now) || grant.is_exhausted() => (Err(()), true),
+            Some(grant) => {
+                grant.uses += 1;
+                (Ok(()), grant.is_exhausted())
+            }
+            None => (Err(()), false),
+        };
+        if revoke {
+            self.grants.remove(token);
+        }
+        result.map_err(|_| HolochainError::DoesNotHaveCapabilityToken)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    /// a token only opens the capability it was granted for, until it is revoked
+    fn grant_is_bound_to_capability() {
+        let mut grants = CapabilityGrants::new();
+        let token = grants
+            .grant("zome", "cap", GrantOptions::default())
+            .unwrap();
+        let now = Duration::from_secs(0);
+
+        assert_eq!(Ok(()), grants.check(&token, "zome", "cap", now));
+        assert_eq!(0, grants.get(&token).unwrap().uses);
+        assert_eq!(Ok(()), grants.use_grant(&token, "zome", "cap", now));
+        assert_eq!(Ok(()), grants.use_grant(&token, "zome", "cap", now));
+        assert_eq!(2, grants.get(&token).unwrap().uses);
+        assert_eq!(
+            Err(HolochainError::DoesNotHaveCapabilityToken),
+            grants.use_grant(&token, "zome", "other_cap", now)
+        );
+        assert_eq!(
+            Err(HolochainError::DoesNotHaveCapabilityToken),
+            grants.check(&token, "zome", "other_cap", now)
+        );
+
+        grants.revoke(&token);
+        assert_eq!(None, grants.get(&token));
+        assert_eq!(
+            Err(HolochainError::DoesNotHaveCapabilityToken),
+            grants.use_grant(&token, "zome", "cap", now)
+        );
+    }
+
+    #[test]
+    /// tokens are 32 random bytes as hex, so they can't be guessed from earlier ones
+    fn tokens_are_random() {
+        let mut grants = CapabilityGrants::new();
+        let first = grants
+            .grant("zome", "cap", GrantOptions::default())
+            .unwrap();
+        let second = grants
+            .grant("zome", "cap", GrantOptions::default())
+            .unwrap();
+
+        assert_eq!(2 * TOKEN_LEN, first.len());
+        assert!(first.chars().all(|c| c.is_digit(16)));
+        assert_ne!(first, second);
+    }
+}
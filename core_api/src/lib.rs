@@ -51,22 +51,122 @@
 //!```
 
 extern crate futures;
+extern crate holochain_agent;
+extern crate holochain_cas_implementations;
 extern crate holochain_core;
 extern crate holochain_core_types;
 extern crate holochain_dna;
+extern crate ring;
 #[cfg(test)]
 extern crate test_utils;
 
-use futures::executor::block_on;
+pub mod checkpoint;
+pub mod cluster;
+pub mod conductor;
+pub mod config;
+pub mod grants;
+pub mod lifecycle;
+pub mod middleware;
+pub mod pending;
+pub mod policy;
+pub mod rate_limit;
+pub mod replay;
+pub mod schedule;
+pub mod subscription;
+
+use checkpoint::{CheckpointId, Checkpoints};
+use conductor::{BridgeInfo, BridgeStatus};
+use config::HolochainConfig;
+use futures::{executor::block_on, Future};
+use grants::{CapabilityGrants, GrantOptions};
+use holochain_agent::Agent;
+use holochain_cas_implementations::{cas::memory::MemoryStorage, eav::memory::EavMemoryStorage};
 use holochain_core::{
+    action::{Action, ActionFilter, ActionWrapper},
+    agent::{
+        actions::{
+            commit::commit_entry,
+            transaction::{commit_batch, commit_transaction, BatchMode, BatchReport, Transaction},
+        },
+        chain_export::AgentChainExport,
+        delegation::{verify_delegation_proof, Delegation},
+        key_rotation::{self, ActiveKey, KeyRotation},
+        keys::PublicKey,
+        signatures,
+        state::{growth_window, ActionResponse, ChainGrowthStats, StorageStats},
+    },
+    clock::ManualClock,
     context::Context,
-    instance::Instance,
-    nucleus::{actions::initialize::initialize_application, call_and_wait_for_result, ZomeFnCall},
+    dht::{
+        dht_export::{BootstrapReport, DhtExport, DhtExportStream, ExportChunk, ExportedEntry},
+        dht_store::{GcReport, GraphResult, Network},
+        link_export::{LinkExport, LinkImportMode},
+        metadata::EntryMetadata,
+        network::{network_id, PublishStatus},
+        storage_routes::StorageRoutes,
+    },
+    diagnostics::{
+        ActionStats, CallSummary, DiagnosticBundle, Health, HotspotReport, KnownAddresses,
+        DIAGNOSTIC_LOG_LINES, HOTSPOT_REPORT_LENGTH,
+    },
+    history::HistoryCursor,
+    instance::{Instance, LockStats},
+    logger::SimpleLogger,
+    nucleus::{
+        actions::{
+            get_entry::{get_entry_strong, Consistency},
+            initialize::{initialize_application_cancellable, INITIALIZATION_CANCELLED},
+        },
+        call_and_wait_for_result, call_with_progress,
+        revalidation::{
+            revalidate_chain, simulate_validation, RevalidationReport, SimulationReport,
+        },
+        state::NucleusStatus,
+        ProgressUpdate, ZomeFnCall,
+    },
+    persister::SimplePersister,
+    random::FixedSeed,
+    scheduler::SchedulerStats,
     state::State,
 };
-use holochain_core_types::error::HolochainError;
-use holochain_dna::Dna;
-use std::sync::Arc;
+use holochain_core_types::{
+    cas::{
+        content::{Address, AddressableContent},
+        storage::ContentAddressableStorage,
+    },
+    eav::{Attribute, EavQuery, QueryPlan},
+    entry::{Entry, EntryWithMeta, ToEntry},
+    entry_type::EntryType,
+    error::{DnaError, HolochainError},
+    signature::Signature,
+};
+use holochain_dna::{
+    bridge::Bridge,
+    schedule::ScheduledFunction,
+    zome::{
+        call_params::CallParams,
+        capabilities::{FnDeclaration, Membrane},
+    },
+    Dna,
+};
+use lifecycle::{LifecycleEvent, LifecycleNotifier};
+use middleware::CallMiddleware;
+use pending::{PendingEntries, PendingEntry};
+use policy::{FunctionPolicies, FunctionPolicy};
+use rate_limit::{CallRateLimit, CapabilityRateLimits};
+use replay::CallRecord;
+use schedule::Schedule;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::channel,
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use subscription::ActionTail;
 
 /// contains a Holochain application instance
 pub struct Holochain {
@@ -74,27 +174,301 @@ pub struct Holochain {
     #[allow(dead_code)]
     context: Arc<Context>,
     active: bool,
+    paused: bool,
+    config: HolochainConfig,
+    lifecycle: LifecycleNotifier,
+    call_records: Vec<CallRecord>,
+    // cleared to stop the thread sweeping expired entries
+    sweeping: Arc<AtomicBool>,
+    // cleared to stop the thread calling the scheduled functions
+    scheduling: Arc<AtomicBool>,
+    // set while paused, so scheduled functions aren't called
+    scheduling_paused: Arc<AtomicBool>,
+    function_policies: FunctionPolicies,
+    capability_grants: CapabilityGrants,
+    capability_rate_limits: CapabilityRateLimits,
+    // run on every call after the checks above, in the order they were added
+    call_middleware: Vec<Box<CallMiddleware>>,
+    // operator metadata grouping instances, @see Conductor::instances_with_label()
+    labels: BTreeMap<String, String>,
+    checkpoints: Checkpoints,
+    // entries committed with commit_deferred() before what they reference
+    pending: PendingEntries,
+    // tops of other agents' chains, from the exports learned, @see Holochain::learn_chain_top()
+    chain_tops: HashMap<Agent, Address>,
+}
+
+/// A Holochain instance whose genesis is still running, @see Holochain::new_cancellable()
+pub struct PendingHolochain {
+    instance: Instance,
+    context: Arc<Context>,
+    config: HolochainConfig,
+    name: String,
+    initialization: Box<Future<Item = NucleusStatus, Error = String>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl PendingHolochain {
+    /// blocks until genesis is done, fails with InitializationCancelled if it was cancelled
+    /// meanwhile
+    /// whenever it fails, the event loop of the instance is stopped
+    pub fn wait(self) -> Result<Holochain, HolochainError> {
+        let PendingHolochain {
+            instance,
+            context,
+            config,
+            name,
+            initialization,
+            cancelled,
+        } = self;
+        let result = block_on(initialization)
+            .map_err(|initialization_error| {
+                if initialization_error == INITIALIZATION_CANCELLED {
+                    HolochainError::InitializationCancelled
+                } else {
+                    HolochainError::ErrorGeneric(initialization_error)
+                }
+            })
+            .and_then(|_| {
+                if cancelled.load(Ordering::SeqCst) {
+                    Err(HolochainError::InitializationCancelled)
+                } else {
+                    Ok(())
+                }
+            })
+            .and_then(|_| context.log(&format!("{} instantiated", name)));
+        if let Err(error) = result {
+            instance.stop_action_loop();
+            return Err(error);
+        }
+        let lifecycle = LifecycleNotifier::new();
+        lifecycle.notify(LifecycleEvent::InitializationComplete);
+        Ok(Holochain {
+            instance,
+            context,
+            active: false,
+            paused: false,
+            config,
+            lifecycle,
+            call_records: Vec::new(),
+            sweeping: Arc::new(AtomicBool::new(false)),
+            scheduling: Arc::new(AtomicBool::new(false)),
+            scheduling_paused: Arc::new(AtomicBool::new(false)),
+            function_policies: FunctionPolicies::new(),
+            capability_grants: CapabilityGrants::new(),
+            capability_rate_limits: CapabilityRateLimits::new(),
+            call_middleware: Vec::new(),
+            labels: BTreeMap::new(),
+            checkpoints: Checkpoints::new(),
+            pending: PendingEntries::new(),
+            chain_tops: HashMap::new(),
+        })
+    }
+}
+
+/// Cancels the genesis of a PendingHolochain when cancel() is called or when it is dropped
+pub struct CancelHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    /// genesis stops before the wasm of the next zome runs, the instance is released once it
+    /// did
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for CancelHandle {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// ends the threads of the instance, started or not: its event loop with its network worker,
+/// the expiry sweeper and the scheduler
+impl Drop for Holochain {
+    fn drop(&mut self) {
+        self.sweeping.store(false, Ordering::SeqCst);
+        self.scheduling.store(false, Ordering::SeqCst);
+        self.instance.stop_action_loop();
+    }
 }
 
 impl Holochain {
-    /// create a new Holochain instance
+    /// create a new Holochain instance with the default configuration
+    /// the agent of the context signs with the key pair its keystore holds for it, one is
+    /// generated if it holds none, @see KeyStore::generate()
     pub fn new(dna: Dna, context: Arc<Context>) -> Result<Self, HolochainError> {
-        let mut instance = Instance::new();
+        Holochain::new_with_config(dna, context, Default::default())
+    }
+
+    /// start creating a new Holochain instance with the given configuration, returning
+    /// before genesis is done
+    /// PendingHolochain::wait() gives the instance, the CancelHandle aborts genesis, e.g. when
+    /// the user navigates away, so it has to be kept until the instance is there
+    pub fn new_cancellable(
+        dna: Dna,
+        context: Arc<Context>,
+        config: HolochainConfig,
+    ) -> Result<(PendingHolochain, CancelHandle), HolochainError> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let pending = Holochain::begin_initialization(
+            Instance::new(),
+            dna,
+            context,
+            config,
+            cancelled.clone(),
+        )?;
+        Ok((pending, CancelHandle { cancelled }))
+    }
+
+    /// create a new Holochain instance with the given configuration
+    pub fn new_with_config(
+        dna: Dna,
+        context: Arc<Context>,
+        config: HolochainConfig,
+    ) -> Result<Self, HolochainError> {
+        Holochain::initialize(Instance::new(), dna, context, config)
+    }
+
+    /// create a read-only Holochain instance serving the entries and links of the shared
+    /// storages, e.g. those of a writing instance, @see Holochain::content_storage() and
+    /// Holochain::meta_storage()
+    /// genesis is left to the writer, commits and links fail with ReadOnlyInstance
+    pub fn new_read_replica(
+        dna: Dna,
+        context: Arc<Context>,
+        shared_cas: MemoryStorage,
+        shared_eav: EavMemoryStorage,
+        config: HolochainConfig,
+    ) -> Result<Self, HolochainError> {
+        let mut read_only_context = (*context).clone();
+        read_only_context.read_only = true;
+        Holochain::initialize(
+            Instance::from_state(State::new_with_storages(shared_cas, shared_eav)),
+            dna,
+            Arc::new(read_only_context),
+            config,
+        )
+    }
+
+    /// create a Holochain instance from the source chain and links found in the storage,
+    /// e.g. a snapshot of the content storage of a lost instance
+    /// genesis is not run again; fails with InconsistentStorage if the storage holds orphan
+    /// entries, a broken chain or the chain of another DNA
+    pub fn rebuild_from_cas(
+        dna: Dna,
+        context: Arc<Context>,
+        cas: MemoryStorage,
+        config: HolochainConfig,
+    ) -> Result<Self, HolochainError> {
+        let state = State::rebuild_from_content_storage(cas)?;
+        let agent = state.agent();
+        if let Some(dna_header) = agent
+            .chain()
+            .iter_type(&agent.top_chain_header(), &EntryType::Dna)
+            .next()
+        {
+            if dna_header.entry_address() != &dna.to_entry().address() {
+                return Err(HolochainError::InconsistentStorage(
+                    "the chain was started with another DNA".to_string(),
+                ));
+            }
+        }
+        Holochain::initialize(Instance::from_state(state), dna, context, config)
+    }
+
+    /// create a Holochain instance continuing the exported source chain of the context's agent
+    /// every entry, chain link and header signature is verified first, against the public key
+    /// the keystore of the context has for the agent; fails with InvalidChainExport if any
+    /// doesn't match or the chain is another agent's
+    /// the key pairs of the agent have to be moved to the keystore first, @see KeyStore::insert()
+    pub fn import_agent_chain(
+        dna: Dna,
+        context: Arc<Context>,
+        export: &AgentChainExport,
+        config: HolochainConfig,
+    ) -> Result<Self, HolochainError> {
+        export.verify(&context.keystore.public_key(&context.agent)?)?;
+        if export.agent() != context.agent {
+            return Err(HolochainError::InvalidChainExport(
+                "the chain was exported by another agent".to_string(),
+            ));
+        }
+        Holochain::rebuild_from_cas(dna, context, export.to_storage()?, config)
+    }
+
+    /// create a Holochain instance publishing to and fetching from the given network
+    /// @see InMemoryNetwork to connect instances with each other
+    pub fn new_on_network(
+        dna: Dna,
+        context: Arc<Context>,
+        network: Network,
+        config: HolochainConfig,
+    ) -> Result<Self, HolochainError> {
+        Holochain::initialize(
+            Instance::from_state(State::new_on_network(network)),
+            dna,
+            context,
+            config,
+        )
+    }
+
+    fn initialize(
+        instance: Instance,
+        dna: Dna,
+        context: Arc<Context>,
+        config: HolochainConfig,
+    ) -> Result<Self, HolochainError> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        Holochain::begin_initialization(instance, dna, context, config, cancelled)?.wait()
+    }
+
+    fn begin_initialization(
+        mut instance: Instance,
+        dna: Dna,
+        context: Arc<Context>,
+        config: HolochainConfig,
+        cancelled: Arc<AtomicBool>,
+    ) -> Result<PendingHolochain, HolochainError> {
+        dna.validate_determinism()
+            .map_err(HolochainError::DnaError)?;
         let name = dna.name.clone();
+        let mut configured_context = (*context).clone();
+        configured_context.auto_publish = config.auto_publish;
+        configured_context.publish_rate_limit = config.publish_rate_limit;
+        configured_context.chain_rate_limit = config.chain_rate_limit;
+        configured_context.validation_level = config.validation_level;
+        // the key pair the agent signs its chain with from now on, unless it already has one
+        configured_context
+            .keystore
+            .generate(&configured_context.agent)?;
+        let context = Arc::new(configured_context);
+        // a read replica serves the storage shared with the writer as it is
+        if let (Some(key), false) = (context.storage_key(), context.read_only) {
+            let sealed = instance.state().sealed(key)?;
+            instance = Instance::from_state(sealed);
+        }
+        if config.record_history {
+            instance.keep_action_log();
+        }
         instance.start_action_loop(context.clone());
         let context = instance.initialize_context(context);
-        match block_on(initialize_application(dna, context.clone())) {
-            Ok(_) => {
-                context.log(&format!("{} instantiated", name))?;
-                let app = Holochain {
-                    instance,
-                    context,
-                    active: false,
-                };
-                Ok(app)
-            }
-            Err(initialization_error) => Err(HolochainError::ErrorGeneric(initialization_error)),
-        }
+        let initialization = initialize_application_cancellable(
+            dna,
+            context.clone(),
+            config.initialization_timeout,
+            cancelled.clone(),
+        );
+        Ok(PendingHolochain {
+            instance,
+            context,
+            config,
+            name,
+            initialization,
+            cancelled,
+        })
     }
 
     /// activate the Holochain instance
@@ -103,18 +477,192 @@ impl Holochain {
             return Err(HolochainError::InstanceActive);
         }
         self.active = true;
+        self.start_expiry_sweeper();
+        self.start_scheduler();
+        self.lifecycle.notify(LifecycleEvent::Started);
         Ok(())
     }
 
     /// deactivate the Holochain instance
+    /// its event loop keeps running so it can be started again, until the instance is dropped
     pub fn stop(&mut self) -> Result<(), HolochainError> {
         if !self.active {
             return Err(HolochainError::InstanceNotActive);
         }
         self.active = false;
+        self.paused = false;
+        self.sweeping.store(false, Ordering::SeqCst);
+        self.scheduling.store(false, Ordering::SeqCst);
+        self.scheduling_paused.store(false, Ordering::SeqCst);
+        self.lifecycle.notify(LifecycleEvent::Stopped);
+        Ok(())
+    }
+
+    /// periodically asks the DHT to mark the entries whose time to live passed as deleted,
+    /// and the agent to drop the entries of ephemeral types kept for longer than
+    /// HolochainConfig::ephemeral_ttl
+    /// the thread ends once the instance is stopped
+    fn start_expiry_sweeper(&mut self) {
+        // a fresh flag, so a sweeper left from before a quick restart still stops
+        self.sweeping = Arc::new(AtomicBool::new(true));
+        let sweeping = self.sweeping.clone();
+        let action_channel = self.instance.action_channel();
+        let context = self.context.clone();
+        let interval = self.config.expiry_sweep_interval;
+        let ephemeral_ttl = self.config.ephemeral_ttl;
+        thread::spawn(move || {
+            while sweeping.load(Ordering::SeqCst) {
+                // only dispatch when something is due, so idle instances don't grow their history
+                let now = context.clock.now();
+                let (entries_due, ephemeral_due) = context
+                    .state()
+                    .map(|state| {
+                        (
+                            !state.dht().due_expiries(now).is_empty(),
+                            state.agent().has_expired_ephemeral(now, ephemeral_ttl),
+                        )
+                    })
+                    .unwrap_or((false, false));
+                if entries_due
+                    && action_channel
+                        .send(ActionWrapper::new(Action::ExpireEntries))
+                        .is_err()
+                {
+                    break;
+                }
+                if ephemeral_due
+                    && action_channel
+                        .send(ActionWrapper::new(Action::ExpireEphemeral(ephemeral_ttl)))
+                        .is_err()
+                {
+                    break;
+                }
+                thread::sleep(interval);
+            }
+        });
+    }
+
+    /// calls the functions the DNA schedules whenever they are due, @see Schedule
+    /// the thread ends once the instance is stopped, nothing is called while it is paused
+    fn start_scheduler(&mut self) {
+        let mut schedule = Schedule::new(self.scheduled_functions(), self.context.clock.now());
+        if schedule.is_empty() {
+            return;
+        }
+        // fresh flags, like the expiry sweeper's
+        self.scheduling = Arc::new(AtomicBool::new(true));
+        self.scheduling_paused = Arc::new(AtomicBool::new(false));
+        let scheduling = self.scheduling.clone();
+        let paused = self.scheduling_paused.clone();
+        let action_channel = self.instance.action_channel();
+        let context = self.context.clone();
+        let interval = self.config.schedule_check_interval;
+        thread::spawn(move || {
+            while scheduling.load(Ordering::SeqCst) {
+                // runs missed while paused collapse into one once resumed
+                if !paused.load(Ordering::SeqCst) {
+                    for function in schedule.take_due(context.clock.now()) {
+                        let call = ZomeFnCall::new(
+                            &function.zome,
+                            &function.capability,
+                            &function.function,
+                            "",
+                        );
+                        if action_channel
+                            .send(ActionWrapper::new(Action::ExecuteZomeFunction(call)))
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                }
+                thread::sleep(interval);
+            }
+        });
+    }
+
+    /// temporarily reject zome calls without deactivating the Holochain instance
+    pub fn pause(&mut self) -> Result<(), HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        if self.paused {
+            return Err(HolochainError::InstancePaused);
+        }
+        self.paused = true;
+        self.scheduling_paused.store(true, Ordering::SeqCst);
+        self.lifecycle.notify(LifecycleEvent::Paused);
+        Ok(())
+    }
+
+    /// accept zome calls again after a pause
+    pub fn resume(&mut self) -> Result<(), HolochainError> {
+        if !self.paused {
+            return Err(HolochainError::InstanceNotPaused);
+        }
+        self.paused = false;
+        self.scheduling_paused.store(false, Ordering::SeqCst);
+        self.lifecycle.notify(LifecycleEvent::Resumed);
         Ok(())
     }
 
+    /// checks to see if an instance is paused
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// register a callback for lifecycle events
+    /// callbacks run on a separate thread, in the order the events happened
+    /// the first registered callback also receives the events that happened before it,
+    /// e.g. InitializationComplete
+    pub fn on_lifecycle<F>(&mut self, callback: F)
+    where
+        F: 'static + Fn(LifecycleEvent) + Send,
+    {
+        self.lifecycle.register(Box::new(callback));
+    }
+
+    /// register a callback for the reduced actions selected by the filter
+    /// callbacks run in the instance's event loop so they should return quickly
+    pub fn subscribe_actions<F>(&mut self, filter: ActionFilter, callback: F)
+    where
+        F: 'static + Fn(&ActionWrapper) + Send,
+    {
+        self.instance.subscribe_actions(filter, callback);
+    }
+
+    /// register a callback for every new state
+    /// callbacks run in the instance's event loop so they should return quickly
+    pub fn subscribe_state<F>(&mut self, callback: F)
+    where
+        F: 'static + Fn(&State) + Send,
+    {
+        self.instance.subscribe_state(callback);
+    }
+
+    /// register a callback for new states, called at most once per window with the latest
+    /// state, dropping the ones in between
+    /// callbacks run on a separate thread so slow ones don't hold up the instance
+    pub fn subscribe_coalesced<F>(&mut self, window: Duration, callback: F)
+    where
+        F: 'static + Fn(&State) + Send,
+    {
+        let sender = subscription::coalesce(window, Box::new(callback));
+        // the coalescing thread only ends early if the callback panicked, the subscription
+        // ends with it instead of failing the event loop
+        self.instance
+            .subscribe_state_while(move |state| sender.send(state.clone()).is_ok());
+    }
+
+    /// stream of every action reduced from now on, e.g. to follow a running instance live
+    /// the instance doesn't wait for a slow consumer, actions it lags behind by are buffered
+    /// according to HolochainConfig::action_tail_policy
+    pub fn tail_actions(&mut self) -> ActionTail {
+        let (feed, action_tail) = subscription::tail(self.config.action_tail_policy);
+        self.instance.subscribe_actions(ActionFilter::all(), feed);
+        action_tail
+    }
+
     /// call a function in a zome
     pub fn call(
         &mut self,
@@ -126,279 +674,4881 @@ impl Holochain {
         if !self.active {
             return Err(HolochainError::InstanceNotActive);
         }
+        if self.paused {
+            return Err(HolochainError::InstancePaused);
+        }
 
         let zome_call = ZomeFnCall::new(&zome, &cap, &fn_name, &params);
 
-        call_and_wait_for_result(zome_call, &mut self.instance)
+        self.call_and_record(zome_call, None)
     }
 
-    /// checks to see if an instance is active
-    pub fn active(&self) -> bool {
-        self.active
+    /// call a function in a zome with the arguments encoded as the function declares in the DNA
+    /// functions that aren't declared get the arguments as a JSON object
+    /// fails with InvalidCallParams if the arguments don't fit the declared inputs
+    pub fn call_with_params(
+        &mut self,
+        zome: &str,
+        cap: &str,
+        fn_name: &str,
+        params: &CallParams,
+    ) -> Result<String, HolochainError> {
+        let dna = self
+            .instance
+            .state()
+            .nucleus()
+            .dna()
+            .ok_or(HolochainError::DnaMissing)?;
+        let capability = dna
+            .get_capability_with_zome_name(zome, cap)
+            .map_err(HolochainError::DnaError)?;
+        let fn_declaration = capability
+            .functions
+            .iter()
+            .find(|fn_declaration| fn_declaration.name == fn_name)
+            .cloned()
+            .unwrap_or_else(|| FnDeclaration {
+                name: fn_name.to_string(),
+                ..FnDeclaration::new()
+            });
+        let params = params
+            .encode(&fn_declaration)
+            .map_err(HolochainError::DnaError)?;
+        self.call(zome, cap, fn_name, &params)
     }
 
-    /// return
-    pub fn state(&mut self) -> Result<State, HolochainError> {
-        Ok(self.instance.state().clone())
+    /// call a function in a zome with a token granted through grant_capability()
+    /// fails with DoesNotHaveCapabilityToken if the token doesn't grant the capability
+    pub fn call_with_grant(
+        &mut self,
+        token: &str,
+        zome: &str,
+        cap: &str,
+        fn_name: &str,
+        params: &str,
+    ) -> Result<String, HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        if self.paused {
+            return Err(HolochainError::InstancePaused);
+        }
+
+        let zome_call = ZomeFnCall::new(&zome, &cap, &fn_name, &params);
+
+        self.call_and_record(zome_call, Some(token))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    extern crate holochain_agent;
-    use super::*;
-    use holochain_core::{
-        context::Context,
-        nucleus::ribosome::{callback::Callback, Defn},
-        persister::SimplePersister,
-    };
-    use holochain_dna::Dna;
-    use std::sync::{Arc, Mutex};
-    use test_utils::{
-        create_test_cap_with_fn_name, create_test_dna_with_cap, create_test_dna_with_wat,
-        create_wasm_from_file,
-    };
+    /// call a function in a zome on behalf of a delegate agent
+    /// the proof must have been signed for the delegate and the scope with the key of this
+    /// instance's agent, @see holochain_core::agent::delegation::delegation_proof
+    /// fails with DelegationOutOfScope if the scope is for another zome, function or DNA, and
+    /// with DelegationExpired once the clock of the context reached its expiry
+    pub fn call_as(
+        &mut self,
+        delegation: &Delegation,
+        zome: &str,
+        cap: &str,
+        fn_name: &str,
+        params: &str,
+    ) -> Result<String, HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        if self.paused {
+            return Err(HolochainError::InstancePaused);
+        }
 
-    // TODO: TestLogger duplicated in test_utils because:
-    //  use holochain_core::{instance::tests::TestLogger};
-    // doesn't work.
-    // @see https://github.com/holochain/holochain-rust/issues/185
-    fn test_context(agent_name: &str) -> (Arc<Context>, Arc<Mutex<test_utils::TestLogger>>) {
-        let agent = holochain_agent::Agent::from(agent_name.to_string());
-        let logger = test_utils::test_logger();
-        (
-            Arc::new(Context::new(
-                agent,
-                logger.clone(),
-                Arc::new(Mutex::new(SimplePersister::new())),
-            )),
-            logger,
-        )
+        let public_key = self.context.keystore.public_key(&self.context.agent)?;
+        if !verify_delegation_proof(
+            &public_key,
+            &delegation.delegate,
+            &delegation.scope,
+            &delegation.proof,
+        ) {
+            return Err(HolochainError::InvalidDelegationProof);
+        }
+        let dna = self
+            .instance
+            .state()
+            .nucleus()
+            .dna()
+            .ok_or(HolochainError::DnaMissing)?;
+        if !delegation
+            .scope
+            .covers(zome, cap, fn_name, &dna.to_entry().address())
+        {
+            return Err(HolochainError::DelegationOutOfScope);
+        }
+        if delegation.scope.is_expired(self.context.clock.now()) {
+            return Err(HolochainError::DelegationExpired);
+        }
+
+        let zome_call = ZomeFnCall::new_delegated(delegation, &zome, &cap, &fn_name, &params);
+
+        self.call_and_record(zome_call, None)
     }
 
-    #[test]
-    fn can_instantiate() {
-        let mut dna = Dna::new();
-        dna.name = "TestApp".to_string();
-        let (context, test_logger) = test_context("bob");
-        let result = Holochain::new(dna.clone(), context.clone());
+    /// call a function in a zome, passing the progress it reports through hc_progress to
+    /// on_progress while waiting for its result
+    /// on_progress runs on the instance's thread and must not block
+    /// fails with CallTimedOut if there is no result within the timeout
+    pub fn call_with_progress<F>(
+        &mut self,
+        zome: &str,
+        cap: &str,
+        fn_name: &str,
+        params: &str,
+        on_progress: F,
+        timeout: Duration,
+    ) -> Result<String, HolochainError>
+    where
+        F: 'static + Fn(ProgressUpdate) + Send,
+    {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        if self.paused {
+            return Err(HolochainError::InstancePaused);
+        }
 
-        match result {
-            Ok(hc) => {
-                assert_eq!(hc.instance.state().nucleus().dna(), Some(dna));
-                assert!(!hc.active);
-                assert_eq!(hc.context.agent.to_string(), "bob".to_string());
-                assert!(hc.instance.state().nucleus().has_initialized());
-                let test_logger = test_logger.lock().unwrap();
-                assert_eq!(format!("{:?}", *test_logger), "[\"TestApp instantiated\"]");
-            }
-            Err(_) => assert!(false),
+        let zome_call = ZomeFnCall::new(&zome, &cap, &fn_name, &params);
+        self.check_call(&zome_call, None)?;
+        let time = self.context.clock.now();
+        let result =
+            call_with_progress(zome_call.clone(), &mut self.instance, on_progress, timeout);
+        self.record(zome_call, &result, time);
+        result
+    }
+
+    /// allows or denies calls to the function, whatever its capability in the DNA says
+    /// denied functions fail with FunctionDisabled before anything is dispatched
+    pub fn set_function_policy(
+        &mut self,
+        zome: &str,
+        cap: &str,
+        fn_name: &str,
+        policy: FunctionPolicy,
+    ) {
+        self.function_policies.set(zome, cap, fn_name, policy);
+    }
+
+    /// limits how many calls the capability takes within a window of the context clock,
+    /// independently of the limits of other capabilities
+    /// calls over the limit fail with CallRateLimited before anything is dispatched
+    pub fn set_capability_rate_limit(&mut self, zome: &str, cap: &str, limit: CallRateLimit) {
+        self.capability_rate_limits.set(zome, cap, limit);
+    }
+
+    /// lets the capability take any number of calls again
+    pub fn remove_capability_rate_limit(&mut self, zome: &str, cap: &str) {
+        self.capability_rate_limits.remove(zome, cap);
+    }
+
+    /// runs the middleware on every call before it is dispatched, after the middleware added
+    /// before; calls it rejects fail with its error
+    pub fn add_call_middleware(&mut self, middleware: Box<CallMiddleware>) {
+        self.call_middleware.push(middleware);
+    }
+
+    /// grants access to a capability with the api-key membrane, which can't be called otherwise
+    /// returns the token to pass to call_with_grant()
+    /// the grant is revoked once it is used up or expired, @see GrantOptions
+    pub fn grant_capability(
+        &mut self,
+        zome: &str,
+        cap: &str,
+        options: GrantOptions,
+    ) -> Result<String, HolochainError> {
+        self.capability_grants.grant(zome, cap, options)
+    }
+
+    /// revokes the grant of the token, calls with it fail from now on
+    pub fn revoke_capability(&mut self, token: &str) {
+        self.capability_grants.revoke(token);
+    }
+
+    /// checks the function policies, the grant of the token for capabilities with the api-key
+    /// membrane and the call middleware, then counts the call against the rate limits and
+    /// uses up one call of the grant last, so a call rejected by any check costs no use
+    fn check_call(
+        &mut self,
+        zome_call: &ZomeFnCall,
+        grant_token: Option<&str>,
+    ) -> Result<(), HolochainError> {
+        self.function_policies.check(zome_call)?;
+        let needs_grant = self
+            .instance
+            .state()
+            .nucleus()
+            .dna()
+            .and_then(|dna| {
+                dna.get_capability_with_zome_name(&zome_call.zome_name, &zome_call.cap_name)
+                    .ok()
+                    .map(|capability| capability.cap_type.membrane == Membrane::ApiKey)
+            })
+            .unwrap_or(false);
+        let now = self.context.clock.now();
+        let token = if needs_grant {
+            let token = grant_token.ok_or(HolochainError::DoesNotHaveCapabilityToken)?;
+            self.capability_grants
+                .check(token, &zome_call.zome_name, &zome_call.cap_name, now)?;
+            Some(token)
+        } else {
+            None
         };
+        for middleware in self.call_middleware.iter_mut() {
+            middleware.before_call(zome_call)?;
+        }
+        self.capability_rate_limits.check(zome_call, now)?;
+        if let Some(token) = token {
+            self.capability_grants.use_grant(
+                token,
+                &zome_call.zome_name,
+                &zome_call.cap_name,
+                now,
+            )?;
+        }
+        Ok(())
     }
 
-    #[test]
-    fn fails_instantiate_if_genesis_fails() {
-        let dna = create_test_dna_with_wat(
-            "test_zome",
-            Callback::Genesis.capability().as_str(),
-            Some(
-                r#"
-            (module
-                (memory (;0;) 17)
-                (func (export "genesis") (param $p0 i32) (result i32)
-                    i32.const 4
-                )
-                (data (i32.const 0)
-                    "fail"
-                )
-                (export "memory" (memory 0))
-            )
-        "#,
-            ),
+    fn call_and_record(
+        &mut self,
+        zome_call: ZomeFnCall,
+        grant_token: Option<&str>,
+    ) -> Result<String, HolochainError> {
+        self.check_call(&zome_call, grant_token)?;
+        let time = self.context.clock.now();
+        let result = call_and_wait_for_result(zome_call.clone(), &mut self.instance);
+        self.record(zome_call, &result, time);
+        result
+    }
+
+    /// records the call made at the time, if HolochainConfig::record_calls is set
+    fn record(
+        &mut self,
+        zome_call: ZomeFnCall,
+        result: &Result<String, HolochainError>,
+        time: Duration,
+    ) {
+        if self.config.record_calls {
+            let seed = self.context.seed_source.seed(&zome_call);
+            self.call_records
+                .push(CallRecord::new(zome_call, result.clone(), seed, time));
+        }
+    }
+
+    /// the calls made so far, if HolochainConfig::record_calls is set
+    pub fn call_records(&self) -> &Vec<CallRecord> {
+        &self.call_records
+    }
+
+    /// re-run a recorded call on a separate instance starting from base_state,
+    /// e.g. the state before the call was made
+    /// the separate instance works on its own copy of the storages and on a network of its
+    /// own, so the live instance's chain, storages and peers are left untouched; entries of
+    /// routed types are kept in the copy too, the replay doesn't see those in routed storages
+    /// the replay reads the time the recorded call was made at from its clock and gets the
+    /// same random numbers
+    /// the event loop of the separate instance ends with the replay
+    pub fn replay_call(
+        &self,
+        record: &CallRecord,
+        base_state: State,
+    ) -> Result<String, HolochainError> {
+        let mut context = (*self.context).clone();
+        context.seed_source = Arc::new(FixedSeed::new(record.seed()));
+        context.clock = Arc::new(ManualClock::new(record.time()));
+        context.storage_routes = StorageRoutes::new();
+        let mut instance = Instance::from_state(base_state.detached()?.disconnected());
+        instance.start_action_loop(Arc::new(context));
+        let result = call_and_wait_for_result(record.replay_call(), &mut instance);
+        instance.stop_action_loop();
+        result
+    }
+
+    /// publish the entries queued while auto publish is disabled or over the rate limit
+    /// @see HolochainConfig::auto_publish
+    /// @see HolochainConfig::publish_rate_limit
+    pub fn flush_publishes(&mut self) -> Result<(), HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        self.instance
+            .dispatch_and_wait(ActionWrapper::new(Action::FlushPublishes));
+        Ok(())
+    }
+
+    /// how far the publish of the entry at the address got, as acknowledged by the peers
+    /// Pending while the entry waits in the publish queue or the peers haven't answered
+    pub fn publish_status(&self, address: &Address) -> PublishStatus {
+        self.instance
+            .state()
+            .dht()
+            .network()
+            .publish_status(address)
+    }
+
+    /// keeps the entry at the address in the network cache regardless of how recently it
+    /// was used, also if it only gets cached later
+    /// fails with PinBudgetExceeded if the cache's pin budget is used up, with ErrorGeneric if
+    /// the state the result was recorded in was replaced meanwhile
+    pub fn pin_entry(&mut self, address: &Address) -> Result<(), HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        let action_wrapper = ActionWrapper::new(Action::PinEntry(address.clone()));
+        self.instance.dispatch_and_wait(action_wrapper.clone());
+        self.instance
+            .state()
+            .dht()
+            .pin_results()
+            .get(&action_wrapper)
+            .cloned()
+            .unwrap_or_else(|| {
+                Err(HolochainError::ErrorGeneric(
+                    "the PinEntry action has no result".to_string(),
+                ))
+            })
+    }
+
+    /// lets the entry at the address be evicted from the network cache again
+    pub fn unpin_entry(&mut self, address: &Address) -> Result<(), HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        self.instance
+            .dispatch_and_wait(ActionWrapper::new(Action::UnpinEntry(address.clone())));
+        Ok(())
+    }
+
+    /// commits the entries and adds the links staged by build as one unit
+    /// nothing is written if any entry is invalid or any operation fails
+    /// returns the addresses of the committed entries, in the order they were staged
+    pub fn transaction<F>(&mut self, build: F) -> Result<Vec<Address>, HolochainError>
+    where
+        F: FnOnce(&mut Transaction),
+    {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        if self.paused {
+            return Err(HolochainError::InstancePaused);
+        }
+        let mut transaction = Transaction::new();
+        build(&mut transaction);
+        block_on(commit_transaction(
+            transaction,
+            &self.context.action_channel,
+            &self.context,
+        ))
+    }
+
+    /// commits the entries, either all of them or, in BatchMode::BestEffort, the valid ones
+    /// the report lists the addresses of the committed entries and why the others failed
+    pub fn commit_batch(
+        &mut self,
+        entries: Vec<Entry>,
+        mode: BatchMode,
+    ) -> Result<BatchReport, HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        if self.paused {
+            return Err(HolochainError::InstancePaused);
+        }
+        block_on(commit_batch(
+            entries,
+            mode,
+            &self.context.action_channel,
+            &self.context,
+        ))
+    }
+
+    /// commits the entry once everything it references is held, e.g. the base and target of
+    /// a link, so graphs can be imported out of order
+    /// until then it waits in the pending area, @see Holochain::pending_entries()
+    /// returns the address of the entry, whether it was committed or deferred
+    /// fails with the validation error if the entry was committed right away and isn't valid
+    pub fn commit_deferred(&mut self, entry: Entry) -> Result<Address, HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        if self.paused {
+            return Err(HolochainError::InstancePaused);
+        }
+        let address = entry.address();
+        self.pending.defer(entry);
+        let (_, failed) = self.promote_ready()?;
+        let mut own_error = None;
+        for (failed_address, error) in failed {
+            if failed_address == address {
+                own_error = Some(error);
+            } else {
+                self.log_dropped(&failed_address, &error);
+            }
+        }
+        match own_error {
+            Some(error) => Err(error),
+            None => Ok(address),
+        }
+    }
+
+    /// commits the pending entries whose dependencies arrived, including those that arrived
+    /// with the entries promoted before them
+    /// pending entries failing validation are logged and dropped
+    /// returns the addresses of the committed entries, in the order they were committed
+    /// commit_deferred() promotes by itself, this is for dependencies arriving another way,
+    /// e.g. committed by a zome call
+    pub fn promote_pending(&mut self) -> Result<Vec<Address>, HolochainError> {
+        let (promoted, failed) = self.promote_ready()?;
+        for (address, error) in failed {
+            self.log_dropped(&address, &error);
+        }
+        Ok(promoted)
+    }
+
+    /// commits the pending entries whose dependencies arrived until none are left ready
+    /// returns the addresses of the committed entries and of the dropped ones with why they
+    /// failed validation
+    /// fails if the storage can't tell whether a dependency is held, or if a batch can't be
+    /// committed at all, leaving the entries of that batch pending
+    fn promote_ready(
+        &mut self,
+    ) -> Result<(Vec<Address>, Vec<(Address, HolochainError)>), HolochainError> {
+        let mut promoted = Vec::new();
+        let mut failed = Vec::new();
+        loop {
+            let storage = self.content_storage();
+            let ready = self
+                .pending
+                .take_ready(|address| storage.contains(address))?;
+            if ready.is_empty() {
+                return Ok((promoted, failed));
+            }
+            let result = block_on(commit_batch(
+                ready.clone(),
+                BatchMode::BestEffort,
+                &self.context.action_channel,
+                &self.context,
+            ));
+            let report = match result {
+                Ok(report) => report,
+                Err(error) => {
+                    for entry in ready {
+                        self.pending.defer(entry);
+                    }
+                    return Err(error);
+                }
+            };
+            for (index, error) in report.failed {
+                failed.push((ready[index].address(), error));
+            }
+            if report.committed.is_empty() {
+                return Ok((promoted, failed));
+            }
+            promoted.extend(report.committed);
+        }
+    }
+
+    /// logs that the pending entry was dropped, a failing logger doesn't stop the promotion
+    fn log_dropped(&self, address: &Address, error: &HolochainError) {
+        let _ = self
+            .context
+            .log(&format!("dropping pending entry {}: {}", address, error));
+    }
+
+    /// the entries waiting for what they reference, in the order they were deferred, with the
+    /// addresses that aren't held yet
+    /// fails if the storage can't tell whether an address is held
+    pub fn pending_entries(&self) -> Result<Vec<PendingEntry>, HolochainError> {
+        let storage = self.content_storage();
+        self.pending.list(|address| storage.contains(address))
+    }
+
+    /// the addresses the network didn't have and when it was asked, as read from the clock of
+    /// the context, sorted by address
+    /// they are not asked for again for Context::negative_cache_ttl
+    pub fn negative_cache_entries(&self) -> Vec<(Address, Duration)> {
+        self.instance.state().dht().cache().misses()
+    }
+
+    /// forgets which addresses the network didn't have, so the next gets ask it again
+    pub fn clear_negative_cache(&mut self) -> Result<(), HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        self.instance
+            .dispatch_and_wait(ActionWrapper::new(Action::ClearNegativeCache));
+        Ok(())
+    }
+
+    /// checks to see if an instance is active
+    pub fn active(&self) -> bool {
+        self.active
+    }
+
+    /// labels the instance with the value under the key, e.g. "env" = "staging", returning
+    /// the value it replaces if any
+    /// labels are metadata for operators, they don't change how the instance behaves
+    pub fn set_label(&mut self, key: &str, value: &str) -> Option<String> {
+        self.labels.insert(key.to_string(), value.to_string())
+    }
+
+    /// removes the label under the key, returning its value if any
+    pub fn remove_label(&mut self, key: &str) -> Option<String> {
+        self.labels.remove(key)
+    }
+
+    pub fn label(&self, key: &str) -> Option<&str> {
+        self.labels.get(key).map(|value| value.as_str())
+    }
+
+    /// the labels of the instance, sorted by key
+    pub fn labels(&self) -> &BTreeMap<String, String> {
+        &self.labels
+    }
+
+    /// the configuration this instance was created with
+    pub fn config(&self) -> &HolochainConfig {
+        &self.config
+    }
+
+    /// timings of the state lock taken while reducing actions
+    /// a high max_held_duration means reduces are blocking the instance, e.g. on the network
+    pub fn lock_stats(&self) -> LockStats {
+        self.instance.lock_stats()
+    }
+
+    /// how long the reduced actions waited in the queue of the event loop, by priority class
+    /// long waits of interactive actions mean the priorities need tuning,
+    /// @see Context::scheduler_priorities
+    pub fn scheduler_stats(&self) -> SchedulerStats {
+        self.instance.scheduler_stats()
+    }
+
+    /// how many entries the agent committed, in total and within the window of the chain
+    /// rate limit up to now, and how many commits the limit rejected
+    /// @see HolochainConfig::chain_rate_limit
+    pub fn chain_growth_stats(&self) -> ChainGrowthStats {
+        self.instance.state().agent().chain_growth_stats(
+            growth_window(self.context.chain_rate_limit),
+            self.context.clock.now(),
+        )
+    }
+
+    /// the number and bytes of the distinct entries on the source chain, by entry type name
+    /// e.g. to see which types take the most storage
+    pub fn storage_by_type(&self) -> HashMap<String, StorageStats> {
+        self.instance.state().agent().storage_by_type().clone()
+    }
+
+    /// the entries with the most links from them and the entries asked for most often with
+    /// get_entry(), e.g. to find anchors that might need sharding
+    pub fn hotspots(&self) -> Result<HotspotReport, HolochainError> {
+        HotspotReport::of(&self.instance.state(), HOTSPOT_REPORT_LENGTH)
+    }
+
+    /// how the DHT's EAV storage answers the query: the index it uses, how many EAVs it looks
+    /// at and whether it scans them all, e.g. to diagnose slow link queries
+    pub fn explain_eav_query(&self, query: &EavQuery) -> Result<QueryPlan, HolochainError> {
+        self.instance.state().dht().explain_meta_query(query)
+    }
+
+    /// indexes the DHT's EAV storage by the attribute, so queries constrained to it don't scan
+    /// every EAV, e.g. link_attribute() of a tag with many links
+    pub fn add_eav_attribute_index(&self, attribute: &Attribute) -> Result<(), HolochainError> {
+        self.instance.state().dht().add_meta_index(attribute)
+    }
+
+    /// the addresses held locally and the addresses cached from the network, e.g. to debug
+    /// replication
+    pub fn known_addresses(&self) -> Result<KnownAddresses, HolochainError> {
+        KnownAddresses::of(&self.instance.state())
+    }
+
+    /// loads the WASM of the zome now instead of on the first call into it, e.g. to keep the
+    /// load out of the latency of that call
+    pub fn preload_zome(&self, zome_name: &str) -> Result<(), HolochainError> {
+        let dna = self
+            .instance
+            .state()
+            .nucleus()
+            .dna()
+            .ok_or(HolochainError::DnaMissing)?;
+        let wasm = dna.get_wasm_from_zome_name(zome_name).ok_or_else(|| {
+            HolochainError::DnaError(DnaError::ZomeNotFound(format!(
+                "Zome '{}' not found",
+                zome_name
+            )))
+        })?;
+        self.context
+            .zome_modules
+            .load(zome_name, &wasm.code)
+            .map(|_| ())
+            .map_err(|error| HolochainError::ErrorGeneric(error.to_string()))
+    }
+
+    /// names of the zomes whose WASM is loaded, sorted
+    /// zomes are loaded on the first call into them, callbacks included, or by preload_zome()
+    pub fn loaded_zomes(&self) -> Vec<String> {
+        self.context.zome_modules.loaded()
+    }
+
+    /// everything needed to understand the situation of the instance, for bug reports
+    /// holds no zome call parameters or results and no entry contents, only addresses
+    /// calls are only listed if HolochainConfig::record_calls is set
+    pub fn diagnostic_dump(&self) -> Result<DiagnosticBundle, HolochainError> {
+        let state = self.instance.state();
+        let log_lines = self
+            .context
+            .logger
+            .lock()
+            .map_err(|_| HolochainError::LoggingError)?
+            .recent_lines(DIAGNOSTIC_LOG_LINES);
+        Ok(DiagnosticBundle {
+            dna_address: state.nucleus().dna().map(|dna| dna.to_entry().address()),
+            agent: self.context.agent.to_string(),
+            agent_address: self.context.agent.to_entry().address(),
+            state_fingerprint: state.fingerprint()?,
+            dht_fingerprint: state.dht_fingerprint(),
+            action_stats: ActionStats::of(&state),
+            health: Health {
+                initialized: state.nucleus().has_initialized(),
+                active: self.active,
+                paused: self.paused,
+                queued_publishes: state.dht().publish_queue().len(),
+            },
+            lock_stats: self.instance.lock_stats(),
+            calls: self
+                .call_records
+                .iter()
+                .map(|record| CallSummary::new(record.call(), record.result()))
+                .collect(),
+            log_lines,
+        })
+    }
+
+    /// return
+    pub fn state(&mut self) -> Result<State, HolochainError> {
+        Ok(self.instance.state().clone())
+    }
+
+    /// UNSTABLE: the instance this Holochain wraps, to dispatch raw actions to and inspect its
+    /// internals, e.g. for containers and tests doing what the API doesn't cover yet
+    /// only built with the "unstable" feature; it bypasses the checks of the API, e.g. whether
+    /// the instance is active, and may change or go away without notice
+    #[cfg(feature = "unstable")]
+    pub fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+
+    /// the entry at the address, looked up locally and then on the network
+    pub fn get_entry(&mut self, address: &Address) -> Result<Option<Entry>, HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        let action_wrapper = ActionWrapper::new(Action::GetEntry(address.clone()));
+        let awaited = action_wrapper.clone();
+        let (sender, receiver) = channel();
+        // entries the peers are asked for are answered by a later ReturnGetResult action,
+        // the reduce loop doesn't wait for the peers
+        self.instance
+            .dispatch_with_observer(action_wrapper, move |state: &State| {
+                match state.agent().action_response(&awaited) {
+                    Some(ActionResponse::GetEntry(result)) => {
+                        let _ = sender.send(result.clone());
+                        true
+                    }
+                    _ => false,
+                }
+            });
+        receiver.recv().map_err(|_| {
+            HolochainError::ErrorGeneric(
+                "the instance stopped before the get was answered".to_string(),
+            )
+        })?
+    }
+
+    /// the entry at the address, like get_entry() for Consistency::Eventual
+    /// Consistency::Strong skips the copies cached from the network and fails with
+    /// NetworkUnreachable if an entry held by peers can't be fetched
+    pub fn get_entry_with_consistency(
+        &mut self,
+        address: &Address,
+        consistency: Consistency,
+    ) -> Result<Option<Entry>, HolochainError> {
+        match consistency {
+            Consistency::Eventual => self.get_entry(address),
+            Consistency::Strong => {
+                if !self.active {
+                    return Err(HolochainError::InstanceNotActive);
+                }
+                get_entry_strong(&self.context, address)
+            }
+        }
+    }
+
+    /// addresses of the entries marked as deleted because their time to live passed, sorted
+    pub fn expired_entries(&self) -> Result<Vec<Address>, HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        Ok(self.instance.state().dht().expired())
+    }
+
+    /// the state as it was when the history was at the cursor, e.g. to audit past data
+    /// fails with HistoryUnavailable unless HolochainConfig::record_history is set, and for
+    /// instances started from storage with content, like read replicas and instances rebuilt
+    /// from their storage
+    pub fn state_at(&self, cursor: HistoryCursor) -> Result<State, HolochainError> {
+        self.instance.state_at(self.context.clone(), cursor)
+    }
+
+    /// the partial state replaying only the actions matching the filter up to the cursor
+    /// makes, e.g. ActionFilter::COMMIT to reconstruct just the authored data
+    /// @see State::replay_filtered()
+    pub fn replay_actions(
+        &self,
+        cursor: HistoryCursor,
+        filter: ActionFilter,
+    ) -> Result<State, HolochainError> {
+        self.instance
+            .replay_actions(self.context.clone(), cursor, filter)
+    }
+
+    /// keeps a copy of the current state, chain and storage included, to roll back to
+    /// fails with CheckpointUnsupported if entries are routed to other storages or handed to
+    /// peers, neither of which a rollback could take back
+    pub fn checkpoint(&mut self) -> Result<CheckpointId, HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        if !self.context.storage_routes.is_empty() {
+            return Err(HolochainError::CheckpointUnsupported(
+                "entries of routed types are stored outside of the state".to_string(),
+            ));
+        }
+        let state = self.instance.state().clone();
+        if state.dht().network().has_peers() {
+            return Err(HolochainError::CheckpointUnsupported(
+                "entries handed to peers can't be taken back".to_string(),
+            ));
+        }
+        self.checkpoints.take(&state)
+    }
+
+    /// restores the state of the checkpoint, discarding everything committed and every
+    /// checkpoint taken since
+    /// the state is replaced in one go, between the reduction of two actions
+    /// fails with UnknownCheckpoint if the checkpoint was discarded
+    pub fn rollback(&mut self, id: CheckpointId) -> Result<(), HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        let state = self.checkpoints.roll_back(id)?;
+        self.instance.restore_state(state);
+        Ok(())
+    }
+
+    /// forgets the checkpoint and its copy of the storage
+    pub fn discard_checkpoint(&mut self, id: CheckpointId) -> Result<(), HolochainError> {
+        if self.checkpoints.discard(id) {
+            Ok(())
+        } else {
+            Err(HolochainError::UnknownCheckpoint)
+        }
+    }
+
+    /// the entry at the address as it was stored when the history was at the cursor
+    /// only the local storage is read, the network has no history
+    pub fn get_entry_at(
+        &self,
+        cursor: HistoryCursor,
+        address: &Address,
+    ) -> Result<Option<Entry>, HolochainError> {
+        let state = self.state_at(cursor)?;
+        let content_storage = state.agent().chain().content_storage();
+        content_storage.fetch(address)
+    }
+
+    /// the entry on the source chain together with its header, signature and CRUD status
+    /// None if no entry with the address was committed by this instance
+    pub fn get_entry_full(
+        &self,
+        address: &Address,
+    ) -> Result<Option<EntryWithMeta>, HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        let state = self.instance.state();
+        let header = match state.agent().chain_header_of(address) {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let maybe_entry: Option<Entry> = state.agent().chain().content_storage().fetch(address)?;
+        let crud_status = state.dht().crud_status(address)?;
+        Ok(maybe_entry.map(|entry| EntryWithMeta {
+            entry,
+            signature: header.entry_signature().clone(),
+            header,
+            crud_status,
+        }))
+    }
+
+    /// addresses of the entries of the app entry type held by peers, sorted
+    /// includes the addresses learned from earlier queries
+    pub fn query_network(&mut self, entry_type: &str) -> Result<Vec<Address>, HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        let entry_type = EntryType::App(entry_type.to_string());
+        self.instance
+            .dispatch_and_wait(ActionWrapper::new(Action::GetEntriesByType(
+                entry_type.clone(),
+            )));
+        Ok(self.instance.state().dht().known_addresses(&entry_type))
+    }
+
+    /// the content storage holding the chain and DHT entries of this instance
+    /// @see Holochain::new_read_replica()
+    pub fn content_storage(&self) -> MemoryStorage {
+        self.instance.state().agent().chain().content_storage()
+    }
+
+    /// the EAV storage holding the links and other metadata of this instance
+    /// @see Holochain::new_read_replica()
+    pub fn meta_storage(&self) -> EavMemoryStorage {
+        self.instance.state().dht().meta_storage()
+    }
+
+    /// the content type recorded in the chain header of a committed entry, if any
+    pub fn entry_content_type(&self, address: &Address) -> Option<String> {
+        self.instance.state().agent().content_type(address)
+    }
+
+    /// fingerprint of the data held by this instance, independent of the order it was added in
+    /// instances holding the same data have the same fingerprint
+    pub fn state_fingerprint(&self) -> Result<Address, HolochainError> {
+        self.instance.state().fingerprint()
+    }
+
+    /// fingerprint of the entries this instance sees in the DHT, published or still queued
+    /// @see State::dht_fingerprint()
+    pub fn dht_fingerprint(&self) -> Address {
+        self.instance.state().dht_fingerprint()
+    }
+
+    /// true if this instance sees the same DHT as the instance with the given DHT fingerprint
+    /// state fingerprints can't be compared across agents since each has its own source chain
+    pub fn is_consistent_with(&self, other_fingerprint: &Address) -> bool {
+        &self.dht_fingerprint() == other_fingerprint
+    }
+
+    /// runs every app entry on the source chain through the current validation callbacks
+    /// nothing is modified, the report lists which entries would fail under the current rules
+    pub fn revalidate_all(&self) -> RevalidationReport {
+        revalidate_chain(&self.context)
+    }
+
+    /// runs every app entry on the source chain through the validation callbacks of a candidate
+    /// DNA, e.g. an upgrade, without installing it
+    /// nothing is modified, the report lists which entries would fail under the candidate
+    pub fn simulate_validation(&self, candidate_dna: Dna) -> SimulationReport {
+        simulate_validation(&self.context, candidate_dna)
+    }
+
+    /// the bridges declared by the DNA
+    pub fn declared_bridges(&self) -> Vec<Bridge> {
+        self.instance
+            .state()
+            .nucleus()
+            .dna()
+            .map(|dna| dna.bridges)
+            .unwrap_or_default()
+    }
+
+    /// the address identifying the DHT the instance is on, derived from the address of its DNA
+    /// and HolochainConfig::network_name
+    /// instances with the same id share their data, @see Conductor::same_network()
+    pub fn network_id(&self) -> Result<Address, HolochainError> {
+        let dna = self
+            .instance
+            .state()
+            .nucleus()
+            .dna()
+            .ok_or(HolochainError::DnaMissing)?;
+        Ok(network_id(
+            &dna.to_entry().address(),
+            &self.config.network_name,
+        ))
+    }
+
+    /// number of network gets the instance has in flight right now
+    pub fn network_gets_in_flight(&self) -> usize {
+        self.context.network_get_limit.in_flight()
+    }
+
+    /// the functions the DNA schedules, @see HolochainConfig::schedule_check_interval
+    pub fn scheduled_functions(&self) -> Vec<ScheduledFunction> {
+        self.instance
+            .state()
+            .nucleus()
+            .dna()
+            .map(|dna| dna.scheduled_functions)
+            .unwrap_or_default()
+    }
+
+    /// call a scheduled function now, without waiting for it to be due
+    /// fails with ZomeFunctionNotFound if the DNA schedules no function with the name
+    pub fn trigger_scheduled(&mut self, name: &str) -> Result<String, HolochainError> {
+        let scheduled = self
+            .scheduled_functions()
+            .into_iter()
+            .find(|scheduled| scheduled.function == name)
+            .ok_or_else(|| {
+                HolochainError::DnaError(DnaError::ZomeFunctionNotFound(format!(
+                    "no scheduled function {}",
+                    name
+                )))
+            })?;
+        self.call(
+            &scheduled.zome,
+            &scheduled.capability,
+            &scheduled.function,
+            "",
+        )
+    }
+
+    /// the bridges declared by the DNA, all unresolved since a lone instance has no targets
+    /// @see Conductor::bridges() to resolve them against other instances
+    pub fn bridges(&self) -> Vec<BridgeInfo> {
+        self.declared_bridges()
+            .iter()
+            .map(|bridge| BridgeInfo::new(bridge, BridgeStatus::Unresolved))
+            .collect()
+    }
+
+    /// the entries on the source chain as a JSON array, newest first
+    /// addresses are rendered by the address formatter of the context the instance was
+    /// created with, @see Context::set_address_formatter()
+    pub fn export_chain(&self) -> Result<String, HolochainError> {
+        self.instance.state().export_chain(&self.context)
+    }
+
+    /// the whole source chain with its entries and the agent's signatures of its headers,
+    /// e.g. to move the agent to another device
+    /// @see Holochain::import_agent_chain()
+    pub fn export_agent_chain(&self) -> Result<AgentChainExport, HolochainError> {
+        let public_key = self.context.keystore.public_key(&self.context.agent)?;
+        AgentChainExport::new(
+            &self.context.keystore,
+            &ActiveKey::new(&self.context.agent, &public_key),
+            &self.instance.state().agent(),
+        )
+    }
+
+    /// the address of the latest header of the agent's chain as this instance knows it
+    /// for other agents that is the top of the latest of their chain exports learned, None
+    /// for an empty chain and for agents none was learned of
+    /// @see Holochain::learn_chain_top()
+    pub fn chain_top(&self, agent: &Agent) -> Option<Address> {
+        if agent != &self.context.agent {
+            return self.chain_tops.get(agent).cloned();
+        }
+        self.instance
+            .state()
+            .agent()
+            .top_chain_header()
+            .map(|chain_header| chain_header.address())
+    }
+
+    /// remembers the top of another agent's chain export, replacing the one learned before
+    /// the export is verified first, against the public key of its agent; fails with
+    /// InvalidChainExport if it doesn't match or the chain is the instance's own
+    /// @see Holochain::chain_top()
+    pub fn learn_chain_top(
+        &mut self,
+        export: &AgentChainExport,
+        public_key: &PublicKey,
+    ) -> Result<(), HolochainError> {
+        export.verify(public_key)?;
+        let agent = export.agent();
+        if agent == self.context.agent {
+            return Err(HolochainError::InvalidChainExport(
+                "the chain is the instance's own".to_string(),
+            ));
+        }
+        match export.chain_top() {
+            Some(top) => self.chain_tops.insert(agent, top),
+            None => self.chain_tops.remove(&agent),
+        };
+        Ok(())
+    }
+
+    /// the addresses of the headers of the source chain a node whose copy of it ends in the
+    /// given top is missing, oldest first; all of them for None
+    /// fails with InvalidAddress if the top isn't on the chain, e.g. because the chains
+    /// diverged
+    pub fn chain_delta(&self, their_top: Option<&Address>) -> Result<Vec<Address>, HolochainError> {
+        let agent = self.instance.state().agent();
+        agent
+            .chain()
+            .headers_since(&agent.top_chain_header(), their_top)
+            .map(|headers| headers.iter().map(|header| header.address()).collect())
+            .ok_or_else(|| {
+                HolochainError::InvalidAddress(format!(
+                    "{} is not on the source chain",
+                    their_top.map(|top| top.to_string()).unwrap_or_default()
+                ))
+            })
+    }
+
+    /// whether each entry was signed with the key pair of the public key at the address given
+    /// with it, in the order of the items
+    /// the signatures are verified by several threads, for imports of many entries
+    pub fn verify_signatures_parallel(&self, items: Vec<(Entry, Signature, Address)>) -> Vec<bool> {
+        signatures::verify_entry_signatures(items, signatures::DEFAULT_VERIFY_WORKERS)
+    }
+
+    /// the public key of the agent, which peers verify what the instance signs with, e.g. its
+    /// DHT exports
+    pub fn public_key(&self) -> Result<PublicKey, HolochainError> {
+        self.context.keystore.public_key(&self.context.agent)
+    }
+
+    /// the public entries and the links the DHT holds, signed by the agent, to warm start
+    /// new peers with, @see Holochain::bootstrap_from()
+    pub fn export_dht(&self) -> Result<DhtExport, HolochainError> {
+        let state = self.instance.state();
+        let dna = state.nucleus().dna().ok_or(HolochainError::DnaMissing)?;
+        let key_pair = self.context.keystore.key_pair(&self.context.agent)?;
+        DhtExport::new(&self.context.agent, &key_pair, &dna, &state.dht())
+    }
+
+    /// the entries export_dht() would export, fetched and signed chunk_size addresses at a
+    /// time as the stream is read, for stores too large to export in one go
+    /// @see Holochain::import_dht_stream()
+    pub fn export_dht_stream(
+        &self,
+        chunk_size: usize,
+    ) -> Result<impl Iterator<Item = Result<ExportChunk, HolochainError>>, HolochainError> {
+        let state = self.instance.state();
+        let dna = state.nucleus().dna().ok_or(HolochainError::DnaMissing)?;
+        let key_pair = self.context.keystore.key_pair(&self.context.agent)?;
+        DhtExportStream::new(
+            &self.context.agent,
+            &key_pair,
+            &dna,
+            &state.dht(),
+            chunk_size,
+        )
+    }
+
+    /// loads the snapshot of a trusted peer's DHT in bulk instead of fetching its entries one
+    /// by one from the network; every address and signature is verified first, against the
+    /// public key of the trusted peer, and nothing is loaded if any doesn't match, failing
+    /// with InvalidDhtExport
+    /// @see Holochain::public_key()
+    pub fn bootstrap_from(
+        &mut self,
+        snapshot: &DhtExport,
+        trusted: &PublicKey,
+    ) -> Result<BootstrapReport, HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        if self.context.read_only {
+            return Err(HolochainError::ReadOnlyInstance);
+        }
+        snapshot.verify(trusted)?;
+        let mut report = BootstrapReport::default();
+        self.load_exported(snapshot.entries(), &mut report)?;
+        Ok(report)
+    }
+
+    /// loads a streamed export one chunk at a time, so only a chunk is in memory at once
+    /// every chunk is verified against the public key of the trusted peer before it is loaded,
+    /// the chunks before one failing with InvalidDhtExport stay loaded
+    /// @see Holochain::export_dht_stream()
+    pub fn import_dht_stream<I>(
+        &mut self,
+        chunks: I,
+        trusted: &PublicKey,
+    ) -> Result<BootstrapReport, HolochainError>
+    where
+        I: IntoIterator<Item = Result<ExportChunk, HolochainError>>,
+    {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        if self.context.read_only {
+            return Err(HolochainError::ReadOnlyInstance);
+        }
+        let mut report = BootstrapReport::default();
+        for chunk in chunks {
+            let chunk = chunk?;
+            chunk.verify(trusted)?;
+            self.load_exported(chunk.entries(), &mut report)?;
+        }
+        Ok(report)
+    }
+
+    /// stores the exported entries that aren't held yet, counting them in the report
+    /// fails with AddressDerivationFailed without storing any if an entry isn't addressed by
+    /// the key its content derives, @see Context::verify_address_key()
+    fn load_exported(
+        &mut self,
+        exported_entries: &[ExportedEntry],
+        report: &mut BootstrapReport,
+    ) -> Result<(), HolochainError> {
+        for exported in exported_entries {
+            self.context.verify_address_key(&exported.entry)?;
+        }
+        let storage = self.content_storage();
+        let mut entries = Vec::new();
+        for exported in exported_entries {
+            if storage.contains(&exported.address)? {
+                report.already_held += 1;
+            } else {
+                report.loaded += 1;
+                entries.push(exported.entry.clone());
+            }
+        }
+        if !entries.is_empty() {
+            self.instance
+                .dispatch_and_wait(ActionWrapper::new(Action::LoadDhtSnapshot(entries)));
+        }
+        Ok(())
+    }
+
+    /// the links the DHT holds, without the entries they link, @see Holochain::import_links()
+    pub fn export_links(&self) -> Result<LinkExport, HolochainError> {
+        LinkExport::of(&self.instance.state().dht())
+    }
+
+    /// stores the links of the export, in place of the links held with Replace or on top of
+    /// them with Merge
+    pub fn import_links(
+        &mut self,
+        export: LinkExport,
+        mode: LinkImportMode,
+    ) -> Result<(), HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        if self.context.read_only {
+            return Err(HolochainError::ReadOnlyInstance);
+        }
+        self.instance
+            .dispatch_and_wait(ActionWrapper::new(Action::ImportLinks(export, mode)));
+        Ok(())
+    }
+
+    /// marks the links whose base or target was removed as removed too, so the link graph
+    /// doesn't lead to deleted entries, @see DhtStore::orphaned_links()
+    /// only links are collected: the other meta data of removed entries, e.g. their CRUD
+    /// status, field indexes, access control lists and attached metadata, is kept
+    pub fn gc_links(&mut self) -> Result<GcReport, HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        if self.context.read_only {
+            return Err(HolochainError::ReadOnlyInstance);
+        }
+        let (links_checked, orphaned) = {
+            let dht = self.instance.state().dht();
+            (dht.links()?.len(), dht.orphaned_links()?)
+        };
+        let report = GcReport {
+            links_checked,
+            links_removed: orphaned.len(),
+        };
+        if !orphaned.is_empty() {
+            self.instance
+                .dispatch_and_wait(ActionWrapper::new(Action::RemoveLinks(orphaned)));
+        }
+        Ok(report)
+    }
+
+    /// attaches the value under the key to the entry at the address, signed by the agent
+    /// values other agents attached under the same key are kept, @see dht::metadata
+    pub fn attach_metadata(
+        &mut self,
+        address: &Address,
+        key: &str,
+        value: &str,
+    ) -> Result<EntryMetadata, HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        if self.context.read_only {
+            return Err(HolochainError::ReadOnlyInstance);
+        }
+        let key_pair = self.context.keystore.key_pair(&self.context.agent)?;
+        let metadata = EntryMetadata::new(&self.context.agent, &key_pair, address, key, value);
+        self.instance
+            .dispatch_and_wait(ActionWrapper::new(Action::AttachMetadata(metadata.clone())));
+        Ok(metadata)
+    }
+
+    /// the values attached to the entry at the address under the key, by this agent or by
+    /// peers, each with its author and signature
+    /// the author's name isn't proof of who attached a value, compare its author key with
+    /// the public key of the agent, @see EntryMetadata::signed_by()
+    pub fn get_metadata(
+        &self,
+        address: &Address,
+        key: &str,
+    ) -> Result<Vec<EntryMetadata>, HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        self.instance.state().dht().metadata(address, key)
+    }
+
+    /// the key the agent signs its next commits with and its public key, the ones it started
+    /// with unless they were rotated
+    pub fn active_key(&self) -> Result<ActiveKey, HolochainError> {
+        let public_key = self.context.keystore.public_key(&self.context.agent)?;
+        key_rotation::active_key(
+            &ActiveKey::new(&self.context.agent, &public_key),
+            &self.instance.state().agent(),
+        )
+    }
+
+    /// switches the key the agent signs its chain with to new_key by committing a KeyRotation
+    /// signed with the active key pair; the key pair of new_key is taken from the keystore of
+    /// the context, failing with UnknownKey if it has none, @see KeyStore::generate()
+    /// the headers committed before stay verifiable with the old key, e.g. in an export
+    pub fn rotate_key(&mut self, new_key: Agent) -> Result<(), HolochainError> {
+        if !self.active {
+            return Err(HolochainError::InstanceNotActive);
+        }
+        if self.paused {
+            return Err(HolochainError::InstancePaused);
+        }
+        let keystore = &self.context.keystore;
+        let active = self.active_key()?;
+        let new_key = ActiveKey::new(&new_key, &keystore.public_key(&new_key)?);
+        let rotation = KeyRotation::new(&active.key, &keystore.key_pair(&active.key)?, &new_key);
+        block_on(commit_entry(
+            rotation.to_entry(),
+            &self.context.action_channel,
+            &self.context,
+        ))
+        .map(|_| ())
+    }
+
+    /// the distinct tags of the links from the base, sorted
+    pub fn link_tags(&self, base: &Address) -> Result<Vec<String>, HolochainError> {
+        self.instance.state().dht().link_tags(base)
+    }
+
+    /// the addresses reachable from the base over up to depth links with the tag, breadth
+    /// first, and the links leading there
+    /// cycles in the link graph are walked once
+    pub fn traverse(
+        &self,
+        base: &Address,
+        tag: &str,
+        depth: usize,
+    ) -> Result<GraphResult, HolochainError> {
+        self.instance.state().dht().traverse(base, tag, depth)
+    }
+
+    /// addresses of the entries referencing the address, in their content or by a link to it,
+    /// e.g. to see what would break if it were removed
+    /// content references are the top-level fields of JSON app entries holding an address
+    pub fn referrers(&self, address: &Address) -> Result<Vec<Address>, HolochainError> {
+        self.instance.state().dht().referrers(address)
+    }
+
+    /// addresses of the entries of the given app entry type whose field has the given value
+    /// the field must be declared in the entry type's indexed_fields or unique_fields in the DNA
+    pub fn find_by_field(
+        &self,
+        entry_type: &str,
+        field_name: &str,
+        field_value: &str,
+    ) -> Result<Vec<Address>, HolochainError> {
+        self.instance.state().dht().find_by_field(
+            &EntryType::App(entry_type.to_string()),
+            field_name,
+            field_value,
+        )
+    }
+}
+
+/// calls the zome function once on a throwaway instance of the DNA run by the named agent,
+/// e.g. to try out a DNA
+/// the instance logs to stdout, persists nothing and is stopped and dropped after the call
+pub fn quick_call(
+    dna: Dna,
+    agent_name: &str,
+    zome: &str,
+    cap: &str,
+    fn_name: &str,
+    params: &str,
+) -> Result<String, HolochainError> {
+    let context = Context::new(
+        Agent::from(agent_name.to_string()),
+        Arc::new(Mutex::new(SimpleLogger {})),
+        Arc::new(Mutex::new(SimplePersister::new())),
+    );
+    let mut hc = Holochain::new(dna, Arc::new(context))?;
+    hc.start()?;
+    let result = hc.call(zome, cap, fn_name, params);
+    hc.stop()?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate holochain_agent;
+    use super::*;
+    use futures::StreamExt;
+    use holochain_cas_implementations::cas::encrypted::{StorageKey, STORAGE_KEY_LEN};
+    use holochain_core::{
+        agent::{
+            chain_export::verify_header_signature,
+            delegation::{delegation_proof, verify_chain_header_delegation, DelegationScope},
+            keys::KeyPair,
+            state::ChainRateLimit,
+        },
+        context::Context,
+        dht::{
+            dht_store::link_attribute,
+            network::{InMemoryNetwork, NetworkBackend, PublishAck},
+        },
+        nucleus::{
+            actions::validate::ValidationLevel,
+            ribosome::{callback::Callback, Defn},
+        },
+        persister::SimplePersister,
+        scheduler::PriorityClass,
+    };
+    use holochain_core_types::{
+        cas::content::Content,
+        crud_status::{CrudStatus, STATUS_NAME},
+        entry::{test_entry, test_entry_address, test_entry_b, test_entry_unique},
+        entry_type::test_entry_type,
+        error::DnaError,
+        json::{FromJson, ToJson},
+        links_entry::Link,
+    };
+    use holochain_dna::{
+        zome::{
+            capabilities::{FnParameter, ParamsEncoding},
+            entry_types::{AddressDerivation, Sharing},
+        },
+        Dna,
+    };
+    use std::{
+        collections::HashMap,
+        error::Error,
+        fmt,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            mpsc::channel,
+            Arc, Mutex,
+        },
+        time::{Duration, Instant},
+    };
+    use test_utils::{
+        create_test_cap_with_fn_name, create_test_dna_with_cap, create_test_dna_with_wat,
+        create_wasm_from_file,
+    };
+
+    // TODO: TestLogger duplicated in test_utils because:
+    //  use holochain_core::{instance::tests::TestLogger};
+    // doesn't work.
+    // @see https://github.com/holochain/holochain-rust/issues/185
+    fn test_context(agent_name: &str) -> (Arc<Context>, Arc<Mutex<test_utils::TestLogger>>) {
+        let agent = holochain_agent::Agent::from(agent_name.to_string());
+        let logger = test_utils::test_logger();
+        (
+            Arc::new(Context::new(
+                agent,
+                logger.clone(),
+                Arc::new(Mutex::new(SimplePersister::new())),
+            )),
+            logger,
+        )
+    }
+
+    #[test]
+    fn can_instantiate() {
+        let mut dna = Dna::new();
+        dna.name = "TestApp".to_string();
+        let (context, test_logger) = test_context("bob");
+        let result = Holochain::new(dna.clone(), context.clone());
+
+        match result {
+            Ok(hc) => {
+                assert_eq!(hc.instance.state().nucleus().dna(), Some(dna));
+                assert!(!hc.active);
+                assert_eq!(hc.context.agent.to_string(), "bob".to_string());
+                assert!(hc.instance.state().nucleus().has_initialized());
+                let test_logger = test_logger.lock().unwrap();
+                assert_eq!(format!("{:?}", *test_logger), "[\"TestApp instantiated\"]");
+            }
+            Err(_) => assert!(false),
+        };
+    }
+
+    #[test]
+    /// with a storage key the chain and DHT content of the instance is sealed at rest, too
+    fn instance_content_is_sealed_with_storage_key() {
+        let mut dna = Dna::new();
+        dna.name = "TestApp".to_string();
+        let (context, _) = test_context("bob");
+        let key = StorageKey::new([7; STORAGE_KEY_LEN]);
+        let mut sealing_context = (*context).clone();
+        sealing_context.set_storage_key(key).unwrap();
+        let hc = Holochain::new(dna.clone(), Arc::new(sealing_context)).unwrap();
+
+        let storage = hc.content_storage();
+        assert_eq!(Some(key), storage.storage_key());
+        assert_eq!(
+            Ok(Some(dna.to_entry())),
+            storage.fetch::<Entry>(&dna.to_entry().address())
+        );
+    }
+
+    #[test]
+    /// zomes reading the clock through the host instead of the context are refused at load
+    fn fails_instantiate_if_zome_imports_clock() {
+        let wat = |import: &str| {
+            format!(
+                r#"
+            (module
+                {}
+                (memory (;0;) 17)
+                (func (export "main") (param $p0 i32) (result i32)
+                    i32.const 4
+                )
+                (data (i32.const 0)
+                    "1337"
+                )
+                (export "memory" (memory 0))
+            )
+        "#,
+                import
+            )
+        };
+        let (context, _) = test_context("bob");
+        let clock_import = wat(r#"(import "wasi_unstable" "clock_time_get" (func $now))"#);
+        let dna = create_test_dna_with_wat("test_zome", "test_cap", Some(&clock_import));
+        assert_eq!(
+            Err(HolochainError::DnaError(DnaError::NondeterministicImports(
+                "zomes import non-deterministic host functions: \
+                 test_zome: wasi_unstable.clock_time_get"
+                    .to_string()
+            ))),
+            Holochain::new(dna, context.clone()).map(|_| ())
+        );
+
+        let api_import =
+            wat(r#"(import "env" "hc_random" (func $random (param i32) (result i32)))"#);
+        let dna = create_test_dna_with_wat("test_zome", "test_cap", Some(&api_import));
+        assert!(Holochain::new(dna, context).is_ok());
+    }
+
+    #[test]
+    fn fails_instantiate_if_genesis_fails() {
+        let dna = create_test_dna_with_wat(
+            "test_zome",
+            Callback::Genesis.capability().as_str(),
+            Some(
+                r#"
+            (module
+                (memory (;0;) 17)
+                (func (export "genesis") (param $p0 i32) (result i32)
+                    i32.const 4
+                )
+                (data (i32.const 0)
+                    "fail"
+                )
+                (export "memory" (memory 0))
+            )
+        "#,
+            ),
+        );
+
+        let (context, _test_logger) = test_context("bob");
+        let result = Holochain::new(dna.clone(), context.clone());
+
+        match result {
+            Ok(_) => assert!(false),
+            Err(err) => assert_eq!(err, HolochainError::ErrorGeneric("fail".to_string())),
+        };
+    }
+
+    #[test]
+    fn fails_instantiate_if_genesis_times_out() {
+        let dna = create_test_dna_with_wat(
+            "test_zome",
+            Callback::Genesis.capability().as_str(),
+            Some(
+                r#"
+            (module
+                (memory (;0;) 17)
+                (func (export "genesis") (param $p0 i32) (result i32)
+                    (loop (br 0))
+                    i32.const 0
+                )
+                (export "memory" (memory 0))
+            )
+        "#,
+            ),
+        );
+
+        let (context, _test_logger) = test_context("bob");
+        let result = Holochain::new(dna.clone(), context.clone());
+
+        match result {
+            Ok(_) => assert!(false),
+            Err(err) => assert_eq!(
+                err,
+                HolochainError::ErrorGeneric("Timeout while initializing".to_string())
+            ),
+        };
+    }
+
+    #[test]
+    fn fails_instantiate_if_genesis_exceeds_configured_timeout() {
+        let dna = create_test_dna_with_wat(
+            "test_zome",
+            Callback::Genesis.capability().as_str(),
+            Some(
+                r#"
+            (module
+                (memory (;0;) 17)
+                (func (export "genesis") (param $p0 i32) (result i32)
+                    (loop (br 0))
+                    i32.const 0
+                )
+                (export "memory" (memory 0))
+            )
+        "#,
+            ),
+        );
+
+        let (context, _test_logger) = test_context("bob");
+        let mut config = HolochainConfig::default();
+        config.initialization_timeout = Duration::from_millis(500);
+        let started = Instant::now();
+        let result = Holochain::new_with_config(dna.clone(), context.clone(), config);
+
+        assert_eq!(
+            result.err(),
+            Some(HolochainError::ErrorGeneric(
+                "Timeout while initializing".to_string()
+            ))
+        );
+        // much quicker than the default timeout
+        assert!(started.elapsed() < HolochainConfig::default().initialization_timeout);
+    }
+
+    /// a DNA whose genesis never returns, or fails
+    fn genesis_dna(genesis: &str) -> Dna {
+        create_test_dna_with_wat(
+            "test_zome",
+            Callback::Genesis.capability().as_str(),
+            Some(&format!(
+                r#"
+            (module
+                (memory (;0;) 17)
+                (func (export "genesis") (param $p0 i32) (result i32)
+                    {}
+                )
+                (data (i32.const 0)
+                    "fail"
+                )
+                (export "memory" (memory 0))
+            )
+        "#,
+                genesis
+            )),
+        )
+    }
+
+    #[test]
+    /// cancelling, or dropping the handle, stops genesis before the wasm of a zome runs and
+    /// ends the event loop of the instance
+    fn cancelled_genesis_ends_event_loop() {
+        // the genesis never returns, only the cancellation ends initialization
+        let (context, _) = test_context("bob");
+        let (pending, cancel_handle) = Holochain::new_cancellable(
+            genesis_dna("(loop (br 0)) i32.const 0"),
+            context,
+            HolochainConfig::default(),
+        )
+        .unwrap();
+        let instance = pending.instance.clone();
+        drop(cancel_handle);
+        assert_eq!(
+            Err(HolochainError::InitializationCancelled),
+            pending.wait().map(|_| ())
+        );
+        assert!(instance
+            .action_channel()
+            .try_send(ActionWrapper::new(Action::ClearNegativeCache))
+            .is_err());
+
+        let (context, _) = test_context("bob");
+        let (pending, cancel_handle) = Holochain::new_cancellable(
+            genesis_dna("(loop (br 0)) i32.const 0"),
+            context,
+            HolochainConfig::default(),
+        )
+        .unwrap();
+        cancel_handle.cancel();
+        assert_eq!(
+            Err(HolochainError::InitializationCancelled),
+            pending.wait().map(|_| ())
+        );
+    }
+
+    #[test]
+    /// a failed genesis ends the event loop of the instance too
+    fn failed_genesis_ends_event_loop() {
+        let (context, _) = test_context("bob");
+        let (pending, _cancel_handle) = Holochain::new_cancellable(
+            genesis_dna("i32.const 4"),
+            context,
+            HolochainConfig::default(),
+        )
+        .unwrap();
+        let instance = pending.instance.clone();
+        assert_eq!(
+            Err(HolochainError::ErrorGeneric("fail".to_string())),
+            pending.wait().map(|_| ())
+        );
+        assert!(instance
+            .action_channel()
+            .try_send(ActionWrapper::new(Action::ClearNegativeCache))
+            .is_err());
+    }
+
+    #[test]
+    fn default_config_reproduces_new() {
+        let dna = Dna::new();
+        let (context, _) = test_context("bob");
+        let hc =
+            Holochain::new_with_config(dna.clone(), context, HolochainConfig::default()).unwrap();
+
+        assert!(!hc.active());
+        assert_eq!(&HolochainConfig::default(), hc.config());
+        assert!(hc.instance.state().nucleus().has_initialized());
+    }
+
+    #[test]
+    #[cfg(feature = "unstable")]
+    /// a raw action dispatched through the instance is reduced like the API's own
+    fn can_dispatch_through_instance_mut() {
+        let (context, _) = test_context("bob");
+        let mut hc = Holochain::new(Dna::new(), context).unwrap();
+        hc.start().expect("couldn't start");
+
+        let action_wrapper = ActionWrapper::new(Action::Commit(test_entry()));
+        hc.instance_mut().dispatch_and_wait(action_wrapper.clone());
+
+        let actions = hc.instance_mut().state().agent().actions();
+        assert_eq!(
+            Some(&ActionResponse::Commit(Ok(test_entry_address()))),
+            actions.get(&action_wrapper)
+        );
+    }
+
+    #[test]
+    fn can_start_and_stop() {
+        let dna = Dna::new();
+        let (context, _) = test_context("bob");
+        let mut hc = Holochain::new(dna.clone(), context).unwrap();
+        assert!(!hc.active());
+
+        // stop when not active returns error
+        let result = hc.stop();
+        match result {
+            Err(HolochainError::InstanceNotActive) => assert!(true),
+            Ok(_) => assert!(false),
+            Err(_) => assert!(false),
+        }
+
+        let result = hc.start();
+        match result {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        assert!(hc.active());
+
+        // start when active returns error
+        let result = hc.start();
+        match result {
+            Err(HolochainError::InstanceActive) => assert!(true),
+            Ok(_) => assert!(false),
+            Err(_) => assert!(false),
+        }
+
+        let result = hc.stop();
+        match result {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        assert!(!hc.active());
+    }
+
+    #[test]
+    /// dropping an instance ends its event loop, whether it was stopped or not
+    fn drop_ends_action_loop() {
+        for stopped in vec![false, true] {
+            let (context, _) = test_context("bob");
+            let mut hc = Holochain::new(Dna::new(), context).unwrap();
+            hc.start().expect("couldn't start");
+            if stopped {
+                hc.stop().expect("couldn't stop");
+            }
+            let action_channel = hc.instance.action_channel();
+            drop(hc);
+            assert!(action_channel
+                .send(ActionWrapper::new(Action::ExpireEntries))
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn start_and_stop_fire_lifecycle_events_in_order() {
+        let dna = Dna::new();
+        let (context, _) = test_context("bob");
+        let mut hc = Holochain::new(dna.clone(), context).unwrap();
+
+        let (sender, receiver) = channel();
+        hc.on_lifecycle(move |event| sender.send(event).unwrap());
+
+        hc.start().expect("couldn't start");
+        hc.stop().expect("couldn't stop");
+
+        let timeout = Duration::from_secs(1);
+        assert_eq!(
+            Ok(LifecycleEvent::InitializationComplete),
+            receiver.recv_timeout(timeout)
+        );
+        assert_eq!(Ok(LifecycleEvent::Started), receiver.recv_timeout(timeout));
+        assert_eq!(Ok(LifecycleEvent::Stopped), receiver.recv_timeout(timeout));
+    }
+
+    #[test]
+    fn can_pause_and_resume() {
+        let dna = Dna::new();
+        let (context, _) = test_context("bob");
+        let mut hc = Holochain::new(dna.clone(), context).unwrap();
+
+        // can't pause an inactive instance
+        assert_eq!(Err(HolochainError::InstanceNotActive), hc.pause());
+        assert_eq!(Err(HolochainError::InstanceNotPaused), hc.resume());
+
+        hc.start().expect("couldn't start");
+        assert_eq!(Ok(()), hc.pause());
+        assert!(hc.paused());
+        assert_eq!(Err(HolochainError::InstancePaused), hc.pause());
+
+        // calls are rejected while paused
+        assert_eq!(
+            Err(HolochainError::InstancePaused),
+            hc.call("test_zome", "test_cap", "main", "")
+        );
+
+        assert_eq!(Ok(()), hc.resume());
+        assert!(!hc.paused());
+        assert!(hc.active());
+    }
+
+    #[test]
+    fn can_call() {
+        let wat = r#"
+(module
+ (memory 1)
+ (export "memory" (memory 0))
+ (export "main" (func $func0))
+ (func $func0 (param $p0 i32) (result i32)
+       i32.const 16
+       )
+ (data (i32.const 0)
+       "{\"holo\":\"world\"}"
+       )
+ )
+"#;
+        let dna = create_test_dna_with_wat("test_zome", "test_cap", Some(wat));
+        let (context, _) = test_context("bob");
+        let mut hc = Holochain::new(dna.clone(), context).unwrap();
+
+        let result = hc.call("test_zome", "test_cap", "main", "");
+        assert!(result.is_err());
+        assert_eq!(result.err().unwrap(), HolochainError::InstanceNotActive);
+
+        hc.start().expect("couldn't start");
+
+        // always returns not implemented error for now!
+        let result = hc.call("test_zome", "test_cap", "main", "");
+        assert!(result.is_ok(), "result = {:?}", result);
+        assert_eq!(result.ok().unwrap(), "{\"holo\":\"world\"}")
+    }
+
+    #[test]
+    fn can_get_state() {
+        let dna = Dna::new();
+        let (context, _) = test_context("bob");
+        let mut hc = Holochain::new(dna.clone(), context).unwrap();
+
+        let result = hc.state();
+        match result {
+            Ok(state) => {
+                assert_eq!(state.nucleus().dna(), Some(dna));
+            }
+            Err(_) => assert!(false),
+        };
+    }
+
+    #[test]
+    fn can_call_test() {
+        let wasm = create_wasm_from_file(
+            "wasm-test/round_trip/target/wasm32-unknown-unknown/release/round_trip.wasm",
+        );
+        let capability = create_test_cap_with_fn_name("test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let (context, _) = test_context("bob");
+        let mut hc = Holochain::new(dna.clone(), context).unwrap();
+
+        hc.start().expect("couldn't start");
+
+        // always returns not implemented error for now!
+        let result = hc.call(
+            "test_zome",
+            "test_cap",
+            "test",
+            r#"{"input_int_val":2,"input_str_val":"fish"}"#,
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        assert_eq!(
+            result.ok().unwrap(),
+            r#"{"input_int_val_plus2":4,"input_str_val_plus_dog":"fish.puppy"}"#
+        );
+    }
+
+    #[test]
+    /// quick_call gives the result of can_call_test without managing an instance
+    fn quick_call_test() {
+        let wasm = create_wasm_from_file(
+            "wasm-test/round_trip/target/wasm32-unknown-unknown/release/round_trip.wasm",
+        );
+        let capability = create_test_cap_with_fn_name("test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        assert_eq!(
+            Ok(r#"{"input_int_val_plus2":4,"input_str_val_plus_dog":"fish.puppy"}"#.to_string()),
+            quick_call(
+                dna,
+                "bob",
+                "test_zome",
+                "test_cap",
+                "test",
+                r#"{"input_int_val":2,"input_str_val":"fish"}"#,
+            )
+        );
+    }
+
+    #[test]
+    // TODO #165 - Move test to core/nucleus and use instance directly
+    fn can_call_commit() {
+        // Setup the holochain instance
+        let wasm = create_wasm_from_file(
+            "wasm-test/commit/target/wasm32-unknown-unknown/release/commit.wasm",
+        );
+        let capability = create_test_cap_with_fn_name("test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(dna.clone(), context).unwrap();
+
+        // Run the holochain instance
+        hc.start().expect("couldn't start");
+        let cursor = hc.state().unwrap().history_cursor();
+
+        // Call the exposed wasm function that calls the Commit API function
+        let result = hc.call("test_zome", "test_cap", "test", r#"{}"#);
+
+        // Expect fail because no validation function in wasm
+        assert!(result.is_ok(), "result = {:?}", result);
+        assert_ne!(
+            result.clone().ok().unwrap(),
+            "{\"Err\":\"Argument deserialization failed\"}"
+        );
+
+        // Check in holochain instance's history that the commit event has been processed
+        assert!(hc.state().unwrap().history_cursor().is_after(&cursor));
+    }
+
+    #[test]
+    fn can_replay_recorded_commit_call() {
+        let wasm = create_wasm_from_file(
+            "wasm-test/commit/target/wasm32-unknown-unknown/release/commit.wasm",
+        );
+        let capability = create_test_cap_with_fn_name("test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let (context, _) = test_context("alex");
+        let mut config = HolochainConfig::default();
+        config.record_calls = true;
+        let mut hc = Holochain::new_with_config(dna.clone(), context, config).unwrap();
+        hc.start().expect("couldn't start");
+
+        let base_state = hc.state().unwrap();
+        let result = hc.call("test_zome", "test_cap", "test", r#"{}"#);
+        assert!(result.is_ok(), "result = {:?}", result);
+        let after_call = hc.state().unwrap();
+
+        assert_eq!(1, hc.call_records().len());
+        let record = hc.call_records()[0].clone();
+        assert_eq!(&result, record.result());
+
+        // the replay commits the same entry and leaves the live instance alone
+        let replayed = hc.replay_call(&record, base_state);
+        assert_eq!(result, replayed);
+        assert_eq!(
+            after_call.history_cursor(),
+            hc.state().unwrap().history_cursor()
+        );
+        assert_eq!(
+            after_call.agent().top_chain_header(),
+            hc.state().unwrap().agent().top_chain_header()
+        );
+    }
+
+    #[test]
+    /// a replay sees the time the call was recorded at and commits to its own copy of the
+    /// storages, nothing reaches the live chain, storages or peers
+    fn replay_leaves_live_instance_alone() {
+        let (context, _) = test_context("alex");
+        let mut context = (*context).clone();
+        let clock = ManualClock::new(Duration::from_secs(1000));
+        context.clock = Arc::new(clock.clone());
+        let backend = Arc::new(InMemoryNetwork::new().unwrap());
+        let mut config = HolochainConfig::default();
+        config.record_calls = true;
+        let mut hc = Holochain::new_on_network(
+            test_commit_dna(),
+            Arc::new(context),
+            Network::new_on(backend.clone()),
+            config,
+        )
+        .unwrap();
+        hc.start().expect("couldn't start");
+        let result = hc.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        let record = hc.call_records()[0].clone();
+        assert_eq!(Duration::from_secs(1000), record.time());
+        clock.advance(Duration::from_secs(60));
+
+        // the same call committing another entry, on top of the live chain
+        let other = CallRecord::new(
+            ZomeFnCall::new(
+                "test_zome",
+                "test_cap",
+                "main",
+                &test_commit_args(&test_entry_b()),
+            ),
+            record.result().clone(),
+            record.seed(),
+            record.time(),
+        );
+        let live = hc.state().unwrap();
+        let addresses = hc.content_storage().addresses().unwrap();
+        let replayed = hc.replay_call(&other, live.clone());
+        assert!(replayed.is_ok(), "result = {:?}", replayed);
+
+        let address = test_entry_b().address();
+        assert_eq!(
+            live.agent().top_chain_header(),
+            hc.state().unwrap().agent().top_chain_header()
+        );
+        assert_eq!(Ok(addresses), hc.content_storage().addresses());
+        assert_eq!(Ok(false), hc.content_storage().contains(&address));
+        assert_eq!(None, backend.get(&address));
+        assert!(!hc
+            .state()
+            .unwrap()
+            .dht()
+            .network()
+            .published()
+            .contains(&address));
+    }
+
+    #[test]
+    fn lock_stats_count_reduces() {
+        let (context, _) = test_context("bob");
+        let hc = Holochain::new(Dna::new(), context).unwrap();
+        let stats = hc.lock_stats();
+        // genesis reduced at least one action, each taking the read and the write lock
+        assert!(stats.acquisitions >= 2);
+    }
+
+    #[test]
+    /// zome calls are counted as interactive, genesis as normal actions
+    fn scheduler_stats_count_by_class() {
+        let (context, _) = test_context("bob");
+        let mut hc = Holochain::new(test_commit_dna(), context).unwrap();
+        hc.start().expect("couldn't start");
+        let result = hc.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+
+        let stats = hc.scheduler_stats();
+        assert!(stats[&PriorityClass::Interactive].reduced >= 1);
+        assert!(stats[&PriorityClass::Normal].reduced >= 1);
+    }
+
+    #[test]
+    fn instances_with_same_commits_have_same_fingerprint() {
+        let wasm = create_wasm_from_file(
+            "wasm-test/commit/target/wasm32-unknown-unknown/release/commit.wasm",
+        );
+        let capability = create_test_cap_with_fn_name("test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let new_instance = || {
+            let (context, _) = test_context("alex");
+            let mut hc = Holochain::new(dna.clone(), context).unwrap();
+            hc.start().expect("couldn't start");
+            hc
+        };
+        let mut hc1 = new_instance();
+        let mut hc2 = new_instance();
+        let hc3 = new_instance();
+
+        for hc in vec![&mut hc1, &mut hc2] {
+            let result = hc.call("test_zome", "test_cap", "test", r#"{}"#);
+            assert!(result.is_ok(), "result = {:?}", result);
+        }
+
+        assert_eq!(hc1.state_fingerprint(), hc2.state_fingerprint());
+        assert_ne!(hc1.state_fingerprint(), hc3.state_fingerprint());
+    }
+
+    #[test]
+    fn coalesced_subscription_delivers_final_state() {
+        let wasm = create_wasm_from_file(
+            "wasm-test/commit/target/wasm32-unknown-unknown/release/commit.wasm",
+        );
+        let capability = create_test_cap_with_fn_name("test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(dna.clone(), context).unwrap();
+        hc.start().expect("couldn't start");
+
+        let (sender, receiver) = channel();
+        hc.subscribe_coalesced(Duration::from_millis(500), move |state| {
+            sender.send(state.history_cursor()).unwrap()
+        });
+        let cursor = hc.state().unwrap().history_cursor();
+
+        // the call reduces several actions in quick succession
+        let result = hc.call("test_zome", "test_cap", "test", r#"{}"#);
+        assert!(result.is_ok(), "result = {:?}", result);
+        let final_cursor = hc.state().unwrap().history_cursor();
+
+        let mut delivered = Vec::new();
+        while let Ok(delivered_cursor) = receiver.recv_timeout(Duration::from_secs(2)) {
+            delivered.push(delivered_cursor);
+        }
+        assert_eq!(Some(&final_cursor), delivered.last());
+        assert!((delivered.len() as u64) < final_cursor.actions_since(&cursor));
+    }
+
+    #[test]
+    fn commit_records_declared_content_type() {
+        let wasm = create_wasm_from_file(
+            "wasm-test/commit/target/wasm32-unknown-unknown/release/commit.wasm",
+        );
+        let capability = create_test_cap_with_fn_name("test");
+        let mut dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        dna.zomes
+            .get_mut("test_zome")
+            .and_then(|zome| zome.entry_types.get_mut("testEntryType"))
+            .expect("test DNA should define testEntryType")
+            .content_type = Some("text/plain".to_string());
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(dna.clone(), context).unwrap();
+        hc.start().expect("couldn't start");
+
+        let result = hc.call("test_zome", "test_cap", "test", r#"{}"#);
+        assert!(result.is_ok(), "result = {:?}", result);
+
+        let entry = Entry::new(
+            &EntryType::App("testEntryType".to_string()),
+            &"hello".to_string(),
+        );
+        assert_eq!(
+            Some("text/plain".to_string()),
+            hc.entry_content_type(&entry.address())
+        );
+    }
+
+    #[test]
+    fn commit_subscription_sees_commits() {
+        let wasm = create_wasm_from_file(
+            "wasm-test/commit/target/wasm32-unknown-unknown/release/commit.wasm",
+        );
+        let capability = create_test_cap_with_fn_name("test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(dna.clone(), context).unwrap();
+        hc.start().expect("couldn't start");
+
+        let (sender, receiver) = channel();
+        hc.subscribe_actions(ActionFilter::COMMIT, move |action_wrapper| {
+            sender.send(action_wrapper.action().clone()).unwrap()
+        });
+
+        let result = hc.call("test_zome", "test_cap", "test", r#"{}"#);
+        assert!(result.is_ok(), "result = {:?}", result);
+
+        let actions: Vec<Action> = receiver.try_iter().collect();
+        assert_eq!(1, actions.len());
+        match actions[0] {
+            Action::Commit(_) => (),
+            ref action => panic!("unexpected action {:?}", action),
+        }
+    }
+
+    #[test]
+    /// actions reduced after tailing started arrive on the tail in the order they were reduced
+    fn tail_streams_actions_in_order() {
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(test_commit_dna(), context).unwrap();
+        hc.start().expect("couldn't start");
+        let mut action_tail = hc.tail_actions();
+
+        let entries = vec![test_entry(), test_entry_b(), test_entry_unique()];
+        for entry in entries.iter() {
+            let result = hc.call("test_zome", "test_cap", "main", &test_commit_args(entry));
+            assert!(result.is_ok(), "result = {:?}", result);
+        }
+
+        let mut committed = Vec::new();
+        while committed.len() < entries.len() {
+            let (next, rest) = block_on(action_tail.next()).ok().unwrap();
+            action_tail = rest;
+            match next.expect("tail should stay open").action() {
+                Action::Commit(entry) | Action::CommitWithOptions(entry, _) => {
+                    committed.push(entry.clone())
+                }
+                _ => (),
+            }
+        }
+        assert_eq!(entries, committed);
+        assert_eq!(0, action_tail.dropped());
+    }
+
+    #[test]
+    /// content differing only in whitespace is committed at the address the legacy system gave it
+    fn canonicalizer_reproduces_legacy_addresses() {
+        let (context, _) = test_context("alex");
+        let mut context = (*context).clone();
+        // the legacy system collapsed runs of whitespace
+        context.set_content_canonicalizer(|_, content| {
+            content.split_whitespace().collect::<Vec<_>>().join(" ")
+        });
+        let mut hc = Holochain::new(test_commit_dna(), Arc::new(context)).unwrap();
+        hc.start().expect("couldn't start");
+
+        let entry_type = EntryType::App("testEntryType".to_string());
+        let legacy = Entry::new(&entry_type, &"legacy entry value".to_string());
+        let spaced = Entry::new(&entry_type, &" legacy  entry   value ".to_string());
+        assert_ne!(legacy.address(), spaced.address());
+
+        let result = hc.call("test_zome", "test_cap", "main", &test_commit_args(&spaced));
+        assert!(result.is_ok(), "result = {:?}", result);
+        assert_eq!(Ok(Some(legacy.clone())), hc.get_entry(&legacy.address()));
+        assert_eq!(Ok(None), hc.get_entry(&spaced.address()));
+    }
+
+    #[test]
+    /// JSON content is committed with its keys sorted, so their order doesn't change the address
+    fn json_key_order_does_not_change_address() {
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(test_commit_dna(), context).unwrap();
+        hc.start().expect("couldn't start");
+        let commit_args = |content: &str| {
+            format!(
+                r#"{{"entry_type_name":"testEntryType","entry_value":"{}","content_type":null}}"#,
+                content.replace("\"", "\\\"")
+            )
+        };
+
+        let entry_type = EntryType::App("testEntryType".to_string());
+        let title_first = r#"{"title":"hello","body":{"tags":["a"],"author":"alex"}}"#;
+        let body_first = r#"{ "body": { "author": "alex", "tags": ["a"] }, "title": "hello" }"#;
+        let sorted = Entry::new(
+            &entry_type,
+            &r#"{"body":{"author":"alex","tags":["a"]},"title":"hello"}"#.to_string(),
+        );
+        assert_ne!(
+            Entry::new(&entry_type, &title_first.to_string()).address(),
+            Entry::new(&entry_type, &body_first.to_string()).address()
+        );
+
+        let first = hc.call("test_zome", "test_cap", "main", &commit_args(title_first));
+        let second = hc.call("test_zome", "test_cap", "main", &commit_args(body_first));
+        assert!(first.is_ok(), "result = {:?}", first);
+        assert_eq!(first, second);
+        assert!(first.unwrap().contains(&sorted.address().to_string()));
+        assert_eq!(Ok(Some(sorted.clone())), hc.get_entry(&sorted.address()));
+    }
+
+    #[test]
+    /// the same value committed twice to a nonced entry type gets two unguessable addresses
+    fn nonced_entries_get_distinct_addresses() {
+        let mut dna = test_commit_dna();
+        dna.zomes
+            .get_mut("test_zome")
+            .and_then(|zome| zome.entry_types.get_mut("testEntryType"))
+            .expect("test DNA should define testEntryType")
+            .nonced = true;
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(dna, context).unwrap();
+        hc.start().expect("couldn't start");
+
+        let mut addresses = Vec::new();
+        for _ in 0..2 {
+            let result = hc.call(
+                "test_zome",
+                "test_cap",
+                "main",
+                &test_commit_args(&test_entry()),
+            );
+            assert!(result.is_ok(), "result = {:?}", result);
+            let top_header = hc.instance.state().agent().top_chain_header().unwrap();
+            addresses.push(top_header.entry_address().clone());
+        }
+        assert_ne!(addresses[0], addresses[1]);
+        for address in addresses.iter() {
+            assert_ne!(&test_entry().address(), address);
+            let entry = hc
+                .get_entry(address)
+                .unwrap()
+                .expect("nonced entry should be held");
+            assert_eq!(test_entry().value(), entry.value());
+            assert!(entry.nonce().is_some());
+        }
+    }
+
+    #[test]
+    /// entries of a type addressed by a field are got by the address of its value alone,
+    /// committing another entry with the same value updates the entry of its author
+    fn entries_are_addressed_by_field() {
+        let mut dna = test_commit_dna();
+        dna.zomes
+            .get_mut("test_zome")
+            .and_then(|zome| zome.entry_types.get_mut("testEntryType"))
+            .expect("test DNA should define testEntryType")
+            .address_derivation = AddressDerivation::ByField("name".to_string());
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(dna, context).unwrap();
+        hc.start().expect("couldn't start");
+        let commit_args = |bio: &str| {
+            format!(
+                r#"{{"entry_type_name":"testEntryType","entry_value":"{}","content_type":null}}"#,
+                format!(r#"{{\"name\":\"alice\",\"bio\":\"{}\"}}"#, bio)
+            )
+        };
+
+        let result = hc.call("test_zome", "test_cap", "main", &commit_args("first"));
+        assert!(result.is_ok(), "result = {:?}", result);
+        let address = Entry::derived_address(&test_entry_type(), "alice");
+        assert_eq!(
+            Some(&address),
+            hc.instance
+                .state()
+                .agent()
+                .top_chain_header()
+                .map(|header| header.entry_address().clone())
+                .as_ref()
+        );
+        let entry = hc
+            .get_entry(&address)
+            .unwrap()
+            .expect("entry should be held");
+        assert!(entry.value().contains("first"));
+        assert_eq!(Some(&"alice".to_string()), entry.address_key());
+
+        let result = hc.call("test_zome", "test_cap", "main", &commit_args("second"));
+        assert!(result.is_ok(), "result = {:?}", result);
+        let entry = hc
+            .get_entry(&address)
+            .unwrap()
+            .expect("entry should be held");
+        assert!(entry.value().contains("second"));
+    }
+
+    #[test]
+    fn can_flush_publishes_without_auto_publish() {
+        let wasm = create_wasm_from_file(
+            "wasm-test/commit/target/wasm32-unknown-unknown/release/commit.wasm",
+        );
+        let capability = create_test_cap_with_fn_name("test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let (context, _) = test_context("alex");
+        let mut config = HolochainConfig::default();
+        config.auto_publish = false;
+        let mut hc = Holochain::new_with_config(dna.clone(), context, config).unwrap();
+        hc.start().expect("couldn't start");
+
+        let result = hc.call("test_zome", "test_cap", "test", r#"{}"#);
+        assert!(result.is_ok(), "result = {:?}", result);
+
+        // the committed entry waits in the queue
+        assert_eq!(1, hc.state().unwrap().dht().publish_queue().len());
+
+        hc.flush_publishes().expect("couldn't flush publishes");
+        assert!(hc.state().unwrap().dht().publish_queue().is_empty());
+    }
+
+    /// DNA whose "main" function passes its parameters on to hc_commit_entry
+    fn test_commit_dna() -> Dna {
+        let wat = r#"
+(module
+    (import "env" "hc_commit_entry"
+        (func $commit_entry
+            (param i32)
+            (result i32)
+        )
+    )
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func
+        (export "main")
+        (param $allocation i32)
+        (result i32)
+
+        (call
+            $commit_entry
+            (get_local $allocation)
+        )
+    )
+
+    (func
+        (export "validate_testEntryType")
+        (param $allocation i32)
+        (result i32)
+
+        (i32.const 0)
+    )
+)
+"#;
+        create_test_dna_with_wat("test_zome", "test_cap", Some(wat))
+    }
+
+    /// parameters for the "main" function of test_commit_dna() committing the entry
+    fn test_commit_args(entry: &Entry) -> String {
+        format!(
+            r#"{{"entry_type_name":"testEntryType","entry_value":"{}","content_type":null}}"#,
+            entry.value()
+        )
+    }
+
+    /// DNA like test_commit_dna() whose entries all fail validation
+    fn test_invalid_commit_dna() -> Dna {
+        // the failure message lives far above the stack the ribosome writes parameters to
+        let offset: u32 = 4096;
+        let message = "entries are not allowed";
+        let wat = format!(
+            r#"
+(module
+    (import "env" "hc_commit_entry"
+        (func $commit_entry
+            (param i32)
+            (result i32)
+        )
+    )
+
+    (memory 1)
+    (export "memory" (memory 0))
+    (data (i32.const {}) "{}")
+
+    (func
+        (export "main")
+        (param $allocation i32)
+        (result i32)
+
+        (call
+            $commit_entry
+            (get_local $allocation)
+        )
+    )
+
+    (func
+        (export "validate_testEntryType")
+        (param $allocation i32)
+        (result i32)
+
+        (i32.const {})
+    )
+)
+"#,
+            offset,
+            message,
+            ((offset << 16) | message.len() as u32) as i32,
+        );
+        create_test_dna_with_wat("test_zome", "test_cap", Some(&wat))
+    }
+
+    /// commits test_entry() with the invalid DNA under the validation level
+    /// returns the instance and what was logged
+    fn commit_invalid_entry(level: ValidationLevel) -> (Holochain, Vec<String>) {
+        let (context, logger) = test_context("alex");
+        let mut config = HolochainConfig::default();
+        config.validation_level = level;
+        let dna = test_invalid_commit_dna();
+        let mut hc = Holochain::new_with_config(dna, context, config).unwrap();
+        hc.start().expect("couldn't start");
+        let result = hc.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        let log = logger.lock().unwrap().log.clone();
+        (hc, log)
+    }
+
+    #[test]
+    /// the same invalid entry is rejected, committed with a warning, or committed silently
+    fn validation_levels() {
+        let address = test_entry().address();
+        let is_warning = |line: &String| line.contains("despite failed validation");
+
+        let (hc, log) = commit_invalid_entry(ValidationLevel::Strict);
+        assert_eq!(Ok(None), hc.get_entry_full(&address));
+        assert!(!log.iter().any(is_warning));
+
+        let (hc, log) = commit_invalid_entry(ValidationLevel::Lenient);
+        assert!(hc.get_entry_full(&address).unwrap().is_some());
+        let warnings: Vec<&String> = log.iter().filter(|line| is_warning(line)).collect();
+        assert_eq!(1, warnings.len());
+        assert!(warnings[0].contains("entries are not allowed"));
+
+        let (hc, log) = commit_invalid_entry(ValidationLevel::Off);
+        assert!(hc.get_entry_full(&address).unwrap().is_some());
+        assert!(!log.iter().any(is_warning));
+    }
+
+    #[test]
+    /// a replica reads what the writer committed to the shared storage but can't commit
+    fn read_replica_serves_reads_and_rejects_commits() {
+        let commit_args = test_commit_args(&test_entry());
+        let dna = test_commit_dna();
+
+        let (context, _) = test_context("alex");
+        let mut writer = Holochain::new(dna.clone(), context).unwrap();
+        writer.start().expect("couldn't start");
+        let result = writer.call("test_zome", "test_cap", "main", &commit_args);
+        assert!(result.is_ok(), "result = {:?}", result);
+
+        let link = Link::new(&test_entry().address(), &test_entry_b().address(), "tag");
+        writer
+            .instance
+            .dispatch_and_wait(ActionWrapper::new(Action::AddLink(link)));
+
+        let (context, _) = test_context("alex");
+        let mut replica = Holochain::new_read_replica(
+            dna,
+            context,
+            writer.content_storage(),
+            writer.meta_storage(),
+            HolochainConfig::default(),
+        )
+        .unwrap();
+        replica.start().expect("couldn't start");
+        assert_eq!(
+            Ok(Some(test_entry())),
+            replica.get_entry(&test_entry().address())
+        );
+        assert_eq!(
+            Ok(vec!["tag".to_string()]),
+            replica.link_tags(&test_entry().address())
+        );
+
+        let result = replica.call("test_zome", "test_cap", "main", &commit_args);
+        assert!(
+            result.clone().unwrap().contains("ReadOnlyInstance"),
+            "result = {:?}",
+            result
+        );
+        assert_eq!(None, replica.state().unwrap().agent().top_chain_header());
+    }
+
+    #[test]
+    /// the constructors besides new_with_config() apply the configuration they are given too
+    fn constructors_apply_config() {
+        let mut config = HolochainConfig::default();
+        config.record_calls = true;
+        config.auto_publish = false;
+        config.validation_level = ValidationLevel::Off;
+
+        let (context, _) = test_context("alex");
+        let backend = Arc::new(InMemoryNetwork::new().unwrap());
+        let mut hc = Holochain::new_on_network(
+            test_commit_dna(),
+            context,
+            Network::new_on(backend),
+            config.clone(),
+        )
+        .unwrap();
+        hc.start().expect("couldn't start");
+        let result = hc.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        assert_eq!(1, hc.call_records().len());
+        assert!(!hc.context.auto_publish);
+        assert_eq!(ValidationLevel::Off, hc.context.validation_level);
+
+        let (context, _) = test_context("alex");
+        let replica = Holochain::new_read_replica(
+            test_commit_dna(),
+            context,
+            hc.content_storage(),
+            hc.meta_storage(),
+            config.clone(),
+        )
+        .unwrap();
+        assert_eq!(config, replica.config);
+        assert!(!replica.context.auto_publish);
+
+        let (context, _) = test_context("billie");
+        let (pending, _cancel_handle) =
+            Holochain::new_cancellable(test_commit_dna(), context, config.clone()).unwrap();
+        let cancellable = pending.wait().unwrap();
+        assert_eq!(config, cancellable.config);
+        assert_eq!(ValidationLevel::Off, cancellable.context.validation_level);
+    }
+
+    #[test]
+    /// a committed entry comes with its header, signature and status, unknown ones are None
+    fn can_get_entry_full() {
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(test_commit_dna(), context).unwrap();
+        let address = test_entry().address();
+        assert_eq!(
+            Err(HolochainError::InstanceNotActive),
+            hc.get_entry_full(&address)
+        );
+
+        hc.start().expect("couldn't start");
+        assert_eq!(Ok(None), hc.get_entry_full(&address));
+        let result = hc.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+
+        let entry_with_meta = hc
+            .get_entry_full(&address)
+            .expect("entry should be readable")
+            .expect("committed entry should be found");
+        assert_eq!(test_entry(), entry_with_meta.entry);
+        assert_eq!(
+            hc.state().unwrap().agent().top_chain_header(),
+            Some(entry_with_meta.header.clone())
+        );
+        assert_eq!(&address, entry_with_meta.header.entry_address());
+        assert_eq!(
+            entry_with_meta.header.entry_signature(),
+            &entry_with_meta.signature
+        );
+        assert_eq!(CrudStatus::LIVE, entry_with_meta.crud_status);
+
+        let unknown = Address::from("QmUnknown".to_string());
+        assert_eq!(Ok(None), hc.get_entry_full(&unknown));
+    }
+
+    #[test]
+    /// a private entry committed by one zome isn't found by the gets of another zome
+    fn private_entries_stay_in_their_zome() {
+        let wat = r#"
+(module
+    (import "env" "hc_commit_entry"
+        (func $commit_entry
+            (param i32)
+            (result i32)
+        )
+    )
+    (import "env" "hc_get_entry"
+        (func $get_entry
+            (param i32)
+            (result i32)
+        )
+    )
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func
+        (export "main")
+        (param $allocation i32)
+        (result i32)
+
+        (call
+            $commit_entry
+            (get_local $allocation)
+        )
+    )
+
+    (func
+        (export "get")
+        (param $allocation i32)
+        (result i32)
+
+        (call
+            $get_entry
+            (get_local $allocation)
+        )
+    )
+
+    (func
+        (export "validate_testEntryType")
+        (param $allocation i32)
+        (result i32)
+
+        (i32.const 0)
+    )
+)
+"#;
+        let mut dna = create_test_dna_with_wat("zome_a", "test_cap", Some(wat));
+        {
+            let zome_a = dna.zomes.get_mut("zome_a").expect("zome a should exist");
+            let mut get = FnDeclaration::new();
+            get.name = "get".to_string();
+            zome_a
+                .capabilities
+                .get_mut("test_cap")
+                .expect("test capability should exist")
+                .functions
+                .push(get);
+            zome_a
+                .entry_types
+                .get_mut("testEntryType")
+                .expect("test entry type should exist")
+                .sharing = Sharing::Private;
+        }
+        let mut zome_b = dna.zomes["zome_a"].clone();
+        zome_b.entry_types.clear();
+        dna.zomes.insert("zome_b".to_string(), zome_b);
+
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(dna, context).unwrap();
+        hc.start().expect("couldn't start");
+        let result = hc.call(
+            "zome_a",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+
+        let get_args = format!(r#"{{"address":"{}"}}"#, test_entry().address());
+        let found = hc.call("zome_a", "test_cap", "get", &get_args).unwrap();
+        assert!(found.contains(r#""status":"Found""#), "found = {}", found);
+        assert_eq!(
+            Ok(r#"{"status":"NotFound","entry":""}"#.to_string()),
+            hc.call("zome_b", "test_cap", "get", &get_args)
+        );
+    }
+
+    #[test]
+    /// an entry with a short time to live is marked as deleted once the clock passed it
+    fn entry_expires_after_ttl() {
+        let mut dna = test_commit_dna();
+        dna.zomes
+            .get_mut("test_zome")
+            .expect("test zome should exist")
+            .entry_types
+            .get_mut("testEntryType")
+            .expect("test entry type should exist")
+            .ttl = Some(60);
+        let clock = ManualClock::new(Duration::from_secs(1000));
+        let (context, _) = test_context("alex");
+        let mut context = (*context).clone();
+        context.clock = Arc::new(clock.clone());
+        let mut config = HolochainConfig::new();
+        config.expiry_sweep_interval = Duration::from_millis(10);
+        let mut hc = Holochain::new_with_config(dna, Arc::new(context), config).unwrap();
+        hc.start().expect("couldn't start");
+
+        let address = test_entry().address();
+        let result = hc.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        let crud_status =
+            |hc: &Holochain| hc.get_entry_full(&address).unwrap().unwrap().crud_status;
+        assert_eq!(CrudStatus::LIVE, crud_status(&hc));
+        // the clock didn't move, nothing expires however many sweeps run
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(Ok(vec![]), hc.expired_entries());
+
+        clock.advance(Duration::from_secs(61));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while hc.expired_entries() != Ok(vec![address.clone()]) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(Ok(vec![address.clone()]), hc.expired_entries());
+        assert!(crud_status(&hc).contains(CrudStatus::DELETED));
+        // tombstoned, not removed
+        assert_eq!(Ok(Some(test_entry())), hc.get_entry(&address));
+    }
+
+    #[test]
+    /// ephemeral entries are dropped by the sweeper once the configured time passed
+    fn ephemeral_entry_is_swept() {
+        let mut dna = test_commit_dna();
+        dna.zomes
+            .get_mut("test_zome")
+            .expect("test zome should exist")
+            .entry_types
+            .get_mut("testEntryType")
+            .expect("test entry type should exist")
+            .sharing = Sharing::Ephemeral;
+        let clock = ManualClock::new(Duration::from_secs(1000));
+        let (context, _) = test_context("alex");
+        let mut context = (*context).clone();
+        context.clock = Arc::new(clock.clone());
+        let mut config = HolochainConfig::new();
+        config.expiry_sweep_interval = Duration::from_millis(10);
+        config.ephemeral_ttl = Duration::from_secs(60);
+        let mut hc = Holochain::new_with_config(dna, Arc::new(context), config).unwrap();
+        hc.start().expect("couldn't start");
+
+        let address = test_entry().address();
+        let result = hc.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        let ephemeral = |hc: &Holochain| hc.state().unwrap().agent().ephemeral_entry(&address);
+        // the clock didn't move, nothing is dropped however many sweeps run
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(Some(test_entry()), ephemeral(&hc));
+
+        clock.advance(Duration::from_secs(60));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while ephemeral(&hc).is_some() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(None, ephemeral(&hc));
+    }
+
+    #[test]
+    /// a function scheduled every 10 seconds is called once per 10 seconds the clock moves,
+    /// not while the instance is paused
+    fn scheduled_function_is_called_every_interval() {
+        let mut dna = test_commit_dna();
+        dna.scheduled_functions.push(ScheduledFunction {
+            zome: "test_zome".to_string(),
+            capability: "test_cap".to_string(),
+            function: "main".to_string(),
+            interval: 10,
+        });
+        let clock = ManualClock::new(Duration::from_secs(1000));
+        let (context, _) = test_context("alex");
+        let mut context = (*context).clone();
+        context.clock = Arc::new(clock.clone());
+        let mut config = HolochainConfig::new();
+        config.schedule_check_interval = Duration::from_millis(10);
+        let mut hc = Holochain::new_with_config(dna, Arc::new(context), config).unwrap();
+        assert_eq!(1, hc.scheduled_functions().len());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        hc.subscribe_actions(ActionFilter::EXECUTE_ZOME_FUNCTION, move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        hc.start().expect("couldn't start");
+        let wait_for_calls = |expected: usize| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while calls.load(Ordering::SeqCst) < expected && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
+            }
+            thread::sleep(Duration::from_millis(50));
+            calls.load(Ordering::SeqCst)
+        };
+
+        // not due before the clock moved a whole interval
+        assert_eq!(0, wait_for_calls(0));
+        for expected in 1..4 {
+            clock.advance(Duration::from_secs(10));
+            assert_eq!(expected, wait_for_calls(expected));
+        }
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(3, wait_for_calls(3));
+
+        hc.pause().expect("couldn't pause");
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(3, wait_for_calls(3));
+        hc.resume().expect("couldn't resume");
+        assert_eq!(4, wait_for_calls(4));
+
+        hc.trigger_scheduled("main").ok();
+        assert_eq!(5, wait_for_calls(5));
+        assert_eq!(
+            Err(HolochainError::DnaError(DnaError::ZomeFunctionNotFound(
+                "no scheduled function unknown".to_string()
+            ))),
+            hc.trigger_scheduled("unknown")
+        );
+        hc.stop().expect("couldn't stop");
+    }
+
+    #[test]
+    /// replaying a call that uses randomness gets the same random numbers
+    fn replayed_random_call_is_identical() {
+        let wat = r#"
+(module
+    (import "env" "hc_random"
+        (func $random
+            (param i32)
+            (result i32)
+        )
+    )
+
+    (memory 1)
+    (export "memory" (memory 0))
+
+    (func
+        (export "main")
+        (param $allocation i32)
+        (result i32)
+
+        (call
+            $random
+            (get_local $allocation)
+        )
+    )
+)
+"#;
+        let dna = create_test_dna_with_wat("test_zome", "test_cap", Some(wat));
+        let (context, _) = test_context("alex");
+        let mut config = HolochainConfig::default();
+        config.record_calls = true;
+        let mut hc = Holochain::new_with_config(dna, context, config).unwrap();
+        hc.start().expect("couldn't start");
+
+        let base_state = hc.state().unwrap();
+        let first = hc.call("test_zome", "test_cap", "main", "");
+        let second = hc.call("test_zome", "test_cap", "main", "");
+        assert!(first.is_ok(), "result = {:?}", first);
+        // every call is seeded differently
+        assert_ne!(first, second);
+
+        let record = hc.call_records()[0].clone();
+        assert_eq!(first, hc.replay_call(&record, base_state.clone()));
+        assert_eq!(first, hc.replay_call(&record, base_state));
+    }
+
+    #[test]
+    /// the state before a commit doesn't hold the entry committed later
+    fn state_at_past_cursor() {
+        let (context, _) = test_context("alex");
+        let mut config = HolochainConfig::default();
+        config.record_history = true;
+        let mut hc = Holochain::new_with_config(test_commit_dna(), context, config).unwrap();
+        hc.start().expect("couldn't start");
+        let address = test_entry().address();
+        let before = hc.state().unwrap().history_cursor();
+
+        let result = hc.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        let after = hc.state().unwrap().history_cursor();
+
+        let past = hc
+            .state_at(before)
+            .expect("past state should be reconstructed");
+        assert_eq!(before, past.history_cursor());
+        assert_eq!(None, past.agent().chain_header_of(&address));
+        assert_eq!(Ok(None), hc.get_entry_at(before, &address));
+
+        let present = hc
+            .state_at(after)
+            .expect("current state should be reconstructed");
+        assert_eq!(
+            hc.state().unwrap().agent().top_chain_header(),
+            present.agent().top_chain_header()
+        );
+        assert_eq!(Ok(Some(test_entry())), hc.get_entry_at(after, &address));
+        assert_eq!(hc.state_fingerprint(), present.fingerprint());
+
+        assert_eq!(
+            Err(HolochainError::HistoryUnavailable),
+            hc.state_at(after.advance(1000))
+        );
+    }
+
+    #[test]
+    /// without record_history no actions are kept, so no past state can be reconstructed
+    fn history_is_not_kept_by_default() {
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(test_commit_dna(), context).unwrap();
+        hc.start().expect("couldn't start");
+        let before = hc.state().unwrap().history_cursor();
+        let result = hc.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        let after = hc.state().unwrap().history_cursor();
+
+        assert_eq!(Err(HolochainError::HistoryUnavailable), hc.state_at(before));
+        assert_eq!(Err(HolochainError::HistoryUnavailable), hc.state_at(after));
+    }
+
+    #[test]
+    /// replaying only the commits gives back the authored entries but not the fetched ones
+    fn replay_commits_only() {
+        let (context, _) = test_context("alex");
+        let mut config = HolochainConfig::default();
+        config.record_history = true;
+        let backend = Arc::new(InMemoryNetwork::new().unwrap());
+        let mut hc = Holochain::new_on_network(
+            test_commit_dna(),
+            context,
+            Network::new_on(backend.clone()),
+            config,
+        )
+        .unwrap();
+        hc.start().expect("couldn't start");
+        let authored = test_entry().address();
+        let result = hc.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        let fetched = test_entry_b().address();
+        backend.publish(&test_entry_b());
+        assert_eq!(Ok(Some(test_entry_b())), hc.get_entry(&fetched));
+        assert!(hc.state().unwrap().dht().cache().peek(&fetched).is_some());
+        let cursor = hc.state().unwrap().history_cursor();
+
+        let replayed = hc.replay_actions(cursor, ActionFilter::COMMIT).unwrap();
+        assert!(replayed.is_partial());
+        assert_eq!(cursor, replayed.history_cursor());
+        let content_storage = replayed.agent().chain().content_storage();
+        assert_eq!(Ok(Some(test_entry())), content_storage.fetch(&authored));
+        assert!(replayed.agent().chain_header_of(&authored).is_some());
+        assert_eq!(None, replayed.dht().cache().peek(&fetched));
+        assert_eq!(Ok(false), content_storage.contains(&fetched));
+
+        assert!(!hc.state_at(cursor).unwrap().is_partial());
+    }
+
+    #[test]
+    /// a denied function can't be called while the other functions of its capability can
+    fn denied_function_is_disabled() {
+        let wat = r#"
+(module
+    (memory (;0;) 17)
+    (func (export "main") (param $p0 i32) (result i32)
+        i32.const 4
+    )
+    (func (export "admin") (param $p0 i32) (result i32)
+        i32.const 4
+    )
+    (data (i32.const 0)
+        "1337"
+    )
+    (export "memory" (memory 0))
+)
+"#;
+        let mut dna = create_test_dna_with_wat("test_zome", "test_cap", Some(wat));
+        let admin = create_test_cap_with_fn_name("admin").functions[0].clone();
+        dna.zomes
+            .get_mut("test_zome")
+            .expect("test zome should exist")
+            .capabilities
+            .get_mut("test_cap")
+            .expect("test capability should exist")
+            .functions
+            .push(admin);
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(dna, context).unwrap();
+        hc.start().expect("couldn't start");
+        let admin_result = hc.call("test_zome", "test_cap", "admin", "");
+        assert!(admin_result.is_ok(), "result = {:?}", admin_result);
+
+        hc.set_function_policy("test_zome", "test_cap", "admin", FunctionPolicy::Deny);
+        assert_eq!(
+            Err(HolochainError::FunctionDisabled(
+                "test_zome/test_cap/admin".to_string()
+            )),
+            hc.call("test_zome", "test_cap", "admin", "")
+        );
+        let result = hc.call("test_zome", "test_cap", "main", "");
+        assert!(result.is_ok(), "result = {:?}", result);
+
+        hc.set_function_policy("test_zome", "test_cap", "admin", FunctionPolicy::Allow);
+        assert_eq!(admin_result, hc.call("test_zome", "test_cap", "admin", ""));
+    }
+
+    #[test]
+    /// only the zome defining genesis is loaded by instantiation, the others on their first call
+    fn zomes_are_loaded_on_first_call() {
+        let wat = r#"
+(module
+    (memory (;0;) 17)
+    (func (export "main") (param $p0 i32) (result i32)
+        i32.const 4
+    )
+    (data (i32.const 0)
+        "1337"
+    )
+    (export "memory" (memory 0))
+)
+"#;
+        let genesis_wat = r#"
+(module
+    (memory 1)
+    (export "memory" (memory 0))
+    (func (export "genesis") (param $p0 i32) (result i32)
+        i32.const 0
+    )
+)
+"#;
+        let mut dna = create_test_dna_with_wat("zome_0", "test_cap", Some(wat));
+        let zome = dna.zomes["zome_0"].clone();
+        for index in 1..10 {
+            dna.zomes.insert(format!("zome_{}", index), zome.clone());
+        }
+        let genesis_dna = create_test_dna_with_wat("zome_0", "test_cap", Some(genesis_wat));
+        let mut genesis_zome = zome.clone();
+        genesis_zome.code = genesis_dna.zomes["zome_0"].code.clone();
+        dna.zomes.insert("zome_genesis".to_string(), genesis_zome);
+
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(dna, context).unwrap();
+        assert_eq!(vec!["zome_genesis".to_string()], hc.loaded_zomes());
+
+        hc.start().expect("couldn't start");
+        let result = hc.call("zome_3", "test_cap", "main", "");
+        assert!(result.is_ok(), "result = {:?}", result);
+        assert_eq!(
+            vec!["zome_3".to_string(), "zome_genesis".to_string()],
+            hc.loaded_zomes()
+        );
+
+        hc.preload_zome("zome_7").unwrap();
+        assert_eq!(3, hc.loaded_zomes().len());
+        assert!(hc.preload_zome("no_such_zome").is_err());
+    }
+
+    #[test]
+    /// the chain top follows the commits, the delta is what came after an older top
+    fn chain_top_follows_commits() {
+        let (context, _) = test_context("alex");
+        let agent = context.agent.clone();
+        let mut hc = Holochain::new(test_commit_dna(), context).unwrap();
+        hc.start().expect("couldn't start");
+        let top_header_address = |hc: &Holochain| {
+            hc.instance
+                .state()
+                .agent()
+                .top_chain_header()
+                .map(|chain_header| chain_header.address())
+        };
+
+        for entry in vec![test_entry(), test_entry_b(), test_entry_unique()] {
+            let result = hc.call("test_zome", "test_cap", "main", &test_commit_args(&entry));
+            assert!(result.is_ok(), "result = {:?}", result);
+        }
+        let third_top = top_header_address(&hc).unwrap();
+        assert_eq!(Some(third_top.clone()), hc.chain_top(&agent));
+        assert_eq!(Ok(Vec::new()), hc.chain_delta(Some(&third_top)));
+
+        let fourth = test_entry_unique();
+        let result = hc.call("test_zome", "test_cap", "main", &test_commit_args(&fourth));
+        assert!(result.is_ok(), "result = {:?}", result);
+        let fourth_top = top_header_address(&hc).unwrap();
+        assert_ne!(third_top, fourth_top);
+        assert_eq!(Some(fourth_top.clone()), hc.chain_top(&agent));
+        assert_eq!(
+            Ok(vec![fourth_top.clone()]),
+            hc.chain_delta(Some(&third_top))
+        );
+        assert_eq!(Some(&fourth_top), hc.chain_delta(None).unwrap().last());
+        assert!(hc.chain_delta(Some(&test_entry().address())).is_err());
+        assert_eq!(None, hc.chain_top(&Agent::from("bob".to_string())));
+        assert_eq!(
+            Some(fourth_top),
+            hc.export_agent_chain().unwrap().chain_top()
+        );
+    }
+
+    #[test]
+    /// the top of another agent's chain is the one of its latest export learned
+    fn chain_top_of_other_agent() {
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(test_commit_dna(), context.clone()).unwrap();
+        hc.start().expect("couldn't start");
+        let (bob_context, _) = test_context("bob");
+        let bob = bob_context.agent.clone();
+        let mut bob_hc = Holochain::new(test_commit_dna(), bob_context).unwrap();
+        bob_hc.start().expect("couldn't start");
+        let bob_key = bob_hc.public_key().unwrap();
+        let result = bob_hc.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        assert_eq!(None, hc.chain_top(&bob));
+
+        let export = bob_hc.export_agent_chain().unwrap();
+        let alex_key = hc.public_key().unwrap();
+        assert!(hc.learn_chain_top(&export, &alex_key).is_err());
+        assert_eq!(None, hc.chain_top(&bob));
+        hc.learn_chain_top(&export, &bob_key).unwrap();
+        assert_eq!(bob_hc.chain_top(&bob), hc.chain_top(&bob));
+        assert!(hc.chain_top(&bob).is_some());
+
+        let own_export = hc.export_agent_chain().unwrap();
+        assert_eq!(
+            Err(HolochainError::InvalidChainExport(
+                "the chain is the instance's own".to_string()
+            )),
+            hc.learn_chain_top(&own_export, &alex_key)
+        );
+    }
+
+    /// error of QuotaMiddleware, as an extension would define it
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    struct QuotaExceeded {
+        zome: String,
+        quota: u32,
+    }
+
+    impl fmt::Display for QuotaExceeded {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{:?}", self)
+        }
+    }
+
+    impl Error for QuotaExceeded {
+        fn description(&self) -> &str {
+            "the quota of calls is used up"
+        }
+    }
+
+    /// lets through a fixed number of calls
+    struct QuotaMiddleware {
+        quota: u32,
+        calls: u32,
+    }
+
+    impl CallMiddleware for QuotaMiddleware {
+        fn before_call(&mut self, zome_call: &ZomeFnCall) -> Result<(), HolochainError> {
+            if self.calls == self.quota {
+                return Err(HolochainError::custom(QuotaExceeded {
+                    zome: zome_call.zome_name.clone(),
+                    quota: self.quota,
+                }));
+            }
+            self.calls += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    /// the caller gets the typed error a middleware rejected a call with
+    fn middleware_error_can_be_downcast() {
+        let wat = r#"
+(module
+    (memory (;0;) 17)
+    (func (export "main") (param $p0 i32) (result i32)
+        i32.const 4
+    )
+    (data (i32.const 0)
+        "1337"
+    )
+    (export "memory" (memory 0))
+)
+"#;
+        let dna = create_test_dna_with_wat("test_zome", "test_cap", Some(wat));
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(dna, context).unwrap();
+        hc.start().expect("couldn't start");
+        hc.add_call_middleware(Box::new(QuotaMiddleware { quota: 2, calls: 0 }));
+
+        for _ in 0..2 {
+            let result = hc.call("test_zome", "test_cap", "main", "");
+            assert!(result.is_ok(), "result = {:?}", result);
+        }
+        let error = hc
+            .call("test_zome", "test_cap", "main", "")
+            .expect_err("the quota should be used up");
+        assert_eq!(
+            Some(&QuotaExceeded {
+                zome: "test_zome".to_string(),
+                quota: 2,
+            }),
+            error.downcast_custom::<QuotaExceeded>()
+        );
+        assert_eq!("the quota of calls is used up", error.description());
+        assert_eq!(None, error.downcast_custom::<DnaError>());
+    }
+
+    #[test]
+    /// exhausting the limit of the write capability leaves the read capability its own calls
+    fn capability_rate_limits_are_independent() {
+        let wat = r#"
+(module
+    (memory (;0;) 17)
+    (func (export "main") (param $p0 i32) (result i32)
+        i32.const 4
+    )
+    (data (i32.const 0)
+        "1337"
+    )
+    (export "memory" (memory 0))
+)
+"#;
+        let mut dna = create_test_dna_with_wat("test_zome", "write", Some(wat));
+        {
+            let capabilities = &mut dna
+                .zomes
+                .get_mut("test_zome")
+                .expect("test zome should exist")
+                .capabilities;
+            let read = capabilities["write"].clone();
+            capabilities.insert("read".to_string(), read);
+        }
+        let clock = ManualClock::new(Duration::from_secs(1000));
+        let (context, _) = test_context("alex");
+        let mut context = (*context).clone();
+        context.clock = Arc::new(clock.clone());
+        let mut hc = Holochain::new(dna, Arc::new(context)).unwrap();
+        hc.start().expect("couldn't start");
+        let minute = Duration::from_secs(60);
+        hc.set_capability_rate_limit("test_zome", "write", CallRateLimit::new(2, minute));
+        hc.set_capability_rate_limit("test_zome", "read", CallRateLimit::new(5, minute));
+
+        for _ in 0..2 {
+            let result = hc.call("test_zome", "write", "main", "");
+            assert!(result.is_ok(), "result = {:?}", result);
+        }
+        assert_eq!(
+            Err(HolochainError::CallRateLimited(
+                "test_zome/write".to_string()
+            )),
+            hc.call("test_zome", "write", "main", "")
+        );
+        for _ in 0..5 {
+            let result = hc.call("test_zome", "read", "main", "");
+            assert!(result.is_ok(), "result = {:?}", result);
+        }
+        assert!(hc.call("test_zome", "read", "main", "").is_err());
+
+        // both windows slide on with the clock
+        clock.advance(minute);
+        assert!(hc.call("test_zome", "write", "main", "").is_ok());
+        assert!(hc.call("test_zome", "read", "main", "").is_ok());
+        hc.remove_capability_rate_limit("test_zome", "write");
+        for _ in 0..3 {
+            assert!(hc.call("test_zome", "write", "main", "").is_ok());
+        }
+    }
+
+    #[test]
+    /// a function declaring positional params gets the arguments as an array in input order
+    fn call_with_positional_params() {
+        let wat = r#"
+(module
+    (memory (;0;) 17)
+    (func (export "main") (param $allocation i32) (result i32)
+        (get_local $allocation)
+    )
+    (export "memory" (memory 0))
+)
+"#;
+        let mut dna = create_test_dna_with_wat("test_zome", "test_cap", Some(wat));
+        {
+            let main = &mut dna
+                .zomes
+                .get_mut("test_zome")
+                .expect("test zome should exist")
+                .capabilities
+                .get_mut("test_cap")
+                .expect("test capability should exist")
+                .functions[0];
+            main.params_encoding = ParamsEncoding::JsonArray;
+            for name in vec!["base", "tag"] {
+                main.inputs.push(FnParameter {
+                    name: name.to_string(),
+                    parameter_type: "string".to_string(),
+                    optional: false,
+                });
+            }
+        }
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(dna, context).unwrap();
+        hc.start().expect("couldn't start");
+
+        let params = CallParams::new().arg("tag", "follows").arg("base", "alex");
+        assert_eq!(
+            Ok(r#"["alex","follows"]"#.to_string()),
+            hc.call_with_params("test_zome", "test_cap", "main", &params)
+        );
+        assert_eq!(
+            Err(HolochainError::DnaError(DnaError::InvalidCallParams(
+                "invalid params for main: expected 2 arguments, got 3".to_string()
+            ))),
+            hc.call_with_params("test_zome", "test_cap", "main", &params.arg("depth", 2))
+        );
+    }
+
+    /// a started instance whose test capability has the api-key membrane
+    fn api_key_instance(context: Arc<Context>) -> Holochain {
+        let wat = r#"
+(module
+    (memory (;0;) 17)
+    (func (export "main") (param $p0 i32) (result i32)
+        i32.const 4
+    )
+    (data (i32.const 0)
+        "1337"
+    )
+    (export "memory" (memory 0))
+)
+"#;
+        let mut dna = create_test_dna_with_wat("test_zome", "test_cap", Some(wat));
+        dna.zomes
+            .get_mut("test_zome")
+            .expect("test zome should exist")
+            .capabilities
+            .get_mut("test_cap")
+            .expect("test capability should exist")
+            .cap_type
+            .membrane = Membrane::ApiKey;
+        let mut hc = Holochain::new(dna, context).unwrap();
+        hc.start().expect("couldn't start");
+        hc
+    }
+
+    #[test]
+    /// a grant for a single use opens the capability once and is revoked after
+    fn single_use_grant_is_used_up() {
+        let (context, _) = test_context("alex");
+        let mut hc = api_key_instance(context);
+        assert_eq!(
+            Err(HolochainError::DoesNotHaveCapabilityToken),
+            hc.call("test_zome", "test_cap", "main", "")
+        );
+
+        let options = GrantOptions {
+            max_uses: Some(1),
+            ..GrantOptions::default()
+        };
+        let token = hc
+            .grant_capability("test_zome", "test_cap", options)
+            .unwrap();
+        let result = hc.call_with_grant(&token, "test_zome", "test_cap", "main", "");
+        assert!(result.is_ok(), "result = {:?}", result);
+        assert_eq!(
+            Err(HolochainError::DoesNotHaveCapabilityToken),
+            hc.call_with_grant(&token, "test_zome", "test_cap", "main", "")
+        );
+    }
+
+    #[test]
+    /// a time-boxed grant stops opening the capability once the clock passed its expiry
+    fn grant_expires_with_clock() {
+        let clock = ManualClock::new(Duration::from_secs(1000));
+        let (context, _) = test_context("alex");
+        let mut context = (*context).clone();
+        context.clock = Arc::new(clock.clone());
+        let mut hc = api_key_instance(Arc::new(context));
+
+        let options = GrantOptions {
+            expires_at: Some(Duration::from_secs(1060)),
+            ..GrantOptions::default()
+        };
+        let token = hc
+            .grant_capability("test_zome", "test_cap", options)
+            .unwrap();
+        let result = hc.call_with_grant(&token, "test_zome", "test_cap", "main", "");
+        assert!(result.is_ok(), "result = {:?}", result);
+        let result = hc.call_with_grant(&token, "test_zome", "test_cap", "main", "");
+        assert!(result.is_ok(), "result = {:?}", result);
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(
+            Err(HolochainError::DoesNotHaveCapabilityToken),
+            hc.call_with_grant(&token, "test_zome", "test_cap", "main", "")
+        );
+    }
+
+    #[test]
+    /// a call rejected by a later check doesn't use up the grant it presented
+    fn rejected_call_keeps_grant() {
+        let clock = ManualClock::new(Duration::from_secs(1000));
+        let (context, _) = test_context("alex");
+        let mut context = (*context).clone();
+        context.clock = Arc::new(clock.clone());
+        let mut hc = api_key_instance(Arc::new(context));
+        let minute = Duration::from_secs(60);
+        hc.set_capability_rate_limit("test_zome", "test_cap", CallRateLimit::new(1, minute));
+
+        let options = GrantOptions {
+            max_uses: Some(2),
+            ..GrantOptions::default()
+        };
+        let token = hc
+            .grant_capability("test_zome", "test_cap", options)
+            .unwrap();
+        let result = hc.call_with_grant(&token, "test_zome", "test_cap", "main", "");
+        assert!(result.is_ok(), "result = {:?}", result);
+        assert_eq!(
+            Err(HolochainError::CallRateLimited(
+                "test_zome/test_cap".to_string()
+            )),
+            hc.call_with_grant(&token, "test_zome", "test_cap", "main", "")
+        );
+        assert_eq!(1, hc.capability_grants.get(&token).unwrap().uses);
+
+        clock.advance(minute);
+        let result = hc.call_with_grant(&token, "test_zome", "test_cap", "main", "");
+        assert!(result.is_ok(), "result = {:?}", result);
+        assert_eq!(None, hc.capability_grants.get(&token));
+    }
+
+    #[test]
+    /// a missed address isn't fetched again until the negative cache is cleared
+    fn clearing_negative_cache_refetches() {
+        let (context, _) = test_context("alex");
+        let mut context = (*context).clone();
+        context.negative_cache_ttl = Some(Duration::from_secs(60));
+        context.clock = Arc::new(ManualClock::new(Duration::from_secs(100)));
+        let backend = Arc::new(InMemoryNetwork::new().unwrap());
+        let mut hc = Holochain::new_on_network(
+            test_commit_dna(),
+            Arc::new(context),
+            Network::new_on(backend.clone()),
+            HolochainConfig::default(),
+        )
+        .unwrap();
+        hc.start().expect("couldn't start");
+        let address = test_entry().address();
+
+        assert_eq!(Ok(None), hc.get_entry(&address));
+        assert_eq!(
+            vec![(address.clone(), Duration::from_secs(100))],
+            hc.negative_cache_entries()
+        );
+
+        // a peer got the entry in the meantime, the negative cache still answers
+        backend.publish(&test_entry());
+        assert_eq!(Ok(None), hc.get_entry(&address));
+
+        hc.clear_negative_cache().unwrap();
+        assert!(hc.negative_cache_entries().is_empty());
+        assert_eq!(Ok(Some(test_entry())), hc.get_entry(&address));
+    }
+
+    #[test]
+    /// an authored entry is known locally, an entry got from a peer is known as cached
+    fn known_addresses_tell_local_from_cached() {
+        let (context, _) = test_context("alex");
+        let backend = Arc::new(InMemoryNetwork::new().unwrap());
+        let mut hc = Holochain::new_on_network(
+            test_commit_dna(),
+            context,
+            Network::new_on(backend.clone()),
+            config,
+        )
+        .unwrap();
+        hc.start().expect("couldn't start");
+        let authored = test_entry().address();
+        let result = hc.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        let fetched = test_entry_b().address();
+        backend.publish(&test_entry_b());
+        assert_eq!(Ok(Some(test_entry_b())), hc.get_entry(&fetched));
+
+        let known = hc.known_addresses().unwrap();
+        assert!(known.local.contains(&authored));
+        assert!(!known.local.contains(&fetched));
+        assert_eq!(vec![fetched], known.cached);
+    }
+
+    /// network acknowledging the publishes it was told to acknowledge
+    struct AckingNetwork {
+        peers: InMemoryNetwork,
+        acks: Mutex<HashMap<Address, PublishAck>>,
+    }
+
+    impl NetworkBackend for AckingNetwork {
+        fn publish(&self, entry: &Entry) {
+            self.peers.publish(entry)
+        }
+
+        fn get(&self, address: &Address) -> Option<Entry> {
+            self.peers.get(address)
+        }
+
+        fn addresses_of_type(&self, entry_type: &EntryType) -> Vec<Address> {
+            self.peers.addresses_of_type(entry_type)
+        }
+
+        fn publish_ack(&self, address: &Address) -> Option<PublishAck> {
+            self.acks.lock().unwrap().get(address).cloned()
+        }
+    }
+
+    #[test]
+    /// every published entry has the status the peers acknowledged, or Pending without an ack
+    fn publish_status_follows_acks() {
+        let (context, _) = test_context("alex");
+        let backend = Arc::new(AckingNetwork {
+            peers: InMemoryNetwork::new().unwrap(),
+            acks: Mutex::new(HashMap::new()),
+        });
+        let mut hc = Holochain::new_on_network(
+            test_commit_dna(),
+            context,
+            Network::new_on(backend.clone()),
+            HolochainConfig::default(),
+        )
+        .unwrap();
+        hc.start().expect("couldn't start");
+        let entries = vec![test_entry(), test_entry_unique(), test_entry_unique()];
+        for entry in entries.iter() {
+            let result = hc.call("test_zome", "test_cap", "main", &test_commit_args(entry));
+            assert!(result.is_ok(), "result = {:?}", result);
+            assert_eq!(PublishStatus::Pending, hc.publish_status(&entry.address()));
+        }
+
+        {
+            let mut acks = backend.acks.lock().unwrap();
+            acks.insert(entries[0].address(), PublishAck::Stored(3));
+            acks.insert(entries[1].address(), PublishAck::Rejected);
+        }
+        assert_eq!(
+            PublishStatus::Acknowledged(3),
+            hc.publish_status(&entries[0].address())
+        );
+        assert_eq!(
+            PublishStatus::Failed,
+            hc.publish_status(&entries[1].address())
+        );
+        assert_eq!(
+            PublishStatus::Pending,
+            hc.publish_status(&entries[2].address())
+        );
+        assert_eq!(
+            PublishStatus::Pending,
+            hc.publish_status(&test_entry_b().address())
+        );
+    }
+
+    /// network whose peers only take and hand out entries while its gate isn't held
+    struct GatedNetwork {
+        peers: InMemoryNetwork,
+        gate: Mutex<()>,
+    }
+
+    impl NetworkBackend for GatedNetwork {
+        fn publish(&self, entry: &Entry) {
+            let _open = self.gate.lock().unwrap();
+            self.peers.publish(entry)
+        }
+
+        fn get(&self, address: &Address) -> Option<Entry> {
+            let _open = self.gate.lock().unwrap();
+            self.peers.get(address)
+        }
+
+        fn addresses_of_type(&self, entry_type: &EntryType) -> Vec<Address> {
+            self.peers.addresses_of_type(entry_type)
+        }
+    }
+
+    #[test]
+    /// the instance keeps reducing actions while a get waits on the peers
+    fn get_does_not_block_reduce_loop() {
+        let (context, _) = test_context("alex");
+        let backend = Arc::new(GatedNetwork {
+            peers: InMemoryNetwork::new().unwrap(),
+            gate: Mutex::new(()),
+        });
+        let mut hc = Holochain::new_on_network(
+            test_commit_dna(),
+            context,
+            Network::new_on(backend.clone()),
+            HolochainConfig::default(),
+        )
+        .unwrap();
+        hc.start().expect("couldn't start");
+        backend.peers.publish(&test_entry_b());
+        let address = test_entry_b().address();
+
+        let closed = backend.gate.lock().unwrap();
+        let get = ActionWrapper::new(Action::GetEntry(address.clone()));
+        hc.instance.dispatch_and_wait(get.clone());
+        assert_eq!(None, hc.instance.state().agent().action_response(&get));
+        // the get is stuck at the gate, yet later actions are reduced
+        let result = hc.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+
+        drop(closed);
+        {
+            let answered = || hc.instance.state().agent().action_response(&get).cloned();
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while answered().is_none() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
+            }
+            assert_eq!(
+                Some(ActionResponse::GetEntry(Ok(Some(test_entry_b())))),
+                answered()
+            );
+        }
+        assert_eq!(Ok(Some(test_entry_b())), hc.get_entry(&address));
+    }
+
+    #[test]
+    /// the instance keeps reducing actions while a publish waits on the peers
+    fn publish_does_not_block_reduce_loop() {
+        let (context, _) = test_context("alex");
+        let backend = Arc::new(GatedNetwork {
+            peers: InMemoryNetwork::new().unwrap(),
+            gate: Mutex::new(()),
+        });
+        let mut hc = Holochain::new_on_network(
+            test_commit_dna(),
+            context,
+            Network::new_on(backend.clone()),
+            HolochainConfig::default(),
+        )
+        .unwrap();
+        hc.start().expect("couldn't start");
+        let address = test_entry().address();
+
+        let other = test_entry_unique();
+
+        let closed = backend.gate.lock().unwrap();
+        let result = hc.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        // the publish is stuck at the gate, yet the commit and later actions were reduced
+        let result = hc.call("test_zome", "test_cap", "main", &test_commit_args(&other));
+        assert!(result.is_ok(), "result = {:?}", result);
+        assert_eq!(Ok(Some(test_entry())), hc.get_entry(&address));
+        assert_eq!(None, backend.peers.get(&address));
+
+        drop(closed);
+        let held = || {
+            backend.peers.get(&address).is_some() && backend.peers.get(&other.address()).is_some()
+        };
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !held() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(held());
+    }
+
+    #[test]
+    /// during a partition eventual gets are served from the cache while strong ones fail
+    fn strong_get_fails_during_partition() {
+        let (context, _) = test_context("alex");
+        let backend = Arc::new(InMemoryNetwork::new().unwrap());
+        let mut hc = Holochain::new_on_network(
+            test_commit_dna(),
+            context,
+            Network::new_on(backend.clone()),
+            HolochainConfig::default(),
+        )
+        .unwrap();
+        hc.start().expect("couldn't start");
+        let address = test_entry().address();
+        backend.publish(&test_entry());
+        assert_eq!(
+            Ok(Some(test_entry())),
+            hc.get_entry_with_consistency(&address, Consistency::Strong)
+        );
+        // the eventual get caches the entry fetched from the network
+        assert_eq!(
+            Ok(Some(test_entry())),
+            hc.get_entry_with_consistency(&address, Consistency::Eventual)
+        );
+
+        backend.partition(true);
+        assert_eq!(
+            Ok(Some(test_entry())),
+            hc.get_entry_with_consistency(&address, Consistency::Eventual)
+        );
+        assert_eq!(
+            Err(HolochainError::NetworkUnreachable),
+            hc.get_entry_with_consistency(&address, Consistency::Strong)
+        );
+
+        backend.partition(false);
+        assert_eq!(
+            Ok(Some(test_entry())),
+            hc.get_entry_with_consistency(&address, Consistency::Strong)
+        );
+    }
+
+    #[test]
+    /// an entry referencing one that isn't committed yet waits until it is
+    fn deferred_entry_is_promoted_once_its_target_arrives() {
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(test_commit_dna(), context).unwrap();
+        hc.start().expect("couldn't start");
+        let target = test_entry();
+        let link = Entry::new(
+            &EntryType::App("testEntryType".to_string()),
+            &format!(r#"{{"target":"{}"}}"#, target.address()),
+        );
+
+        assert_eq!(Ok(link.address()), hc.commit_deferred(link.clone()));
+        assert_eq!(
+            vec![PendingEntry {
+                entry: link.clone(),
+                missing: vec![target.address()],
+            }],
+            hc.pending_entries().unwrap()
+        );
+        let chain_header_of = |hc: &mut Holochain, address: &Address| {
+            hc.state().unwrap().agent().chain_header_of(address)
+        };
+        assert_eq!(None, chain_header_of(&mut hc, &link.address()));
+
+        assert_eq!(Ok(target.address()), hc.commit_deferred(target.clone()));
+        assert!(hc.pending_entries().unwrap().is_empty());
+        assert!(chain_header_of(&mut hc, &target.address()).is_some());
+        assert!(chain_header_of(&mut hc, &link.address()).is_some());
+        assert_eq!(Ok(Some(link.clone())), hc.get_entry(&link.address()));
+    }
+
+    #[test]
+    /// an invalid entry committed right away fails its own commit_deferred(), one promoted by
+    /// another's is dropped and logged
+    fn commit_deferred_reports_validation_failure() {
+        let (context, logger) = test_context("alex");
+        let mut hc = Holochain::new(test_commit_dna(), context).unwrap();
+        hc.start().expect("couldn't start");
+
+        // test_commit_dna() has no testEntryTypeB, entries of it fail validation
+        match hc.commit_deferred(test_entry_b()) {
+            Err(HolochainError::ValidationFailed(_)) => (),
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(hc.pending_entries().unwrap().is_empty());
+        assert_eq!(Ok(None), hc.get_entry(&test_entry_b().address()));
+
+        let target = test_entry();
+        let invalid_link = Entry::new(
+            &EntryType::App("testEntryTypeB".to_string()),
+            &format!(r#"{{"target":"{}"}}"#, target.address()),
+        );
+        assert_eq!(
+            Ok(invalid_link.address()),
+            hc.commit_deferred(invalid_link.clone())
+        );
+        assert_eq!(Ok(target.address()), hc.commit_deferred(target.clone()));
+        assert!(hc.pending_entries().unwrap().is_empty());
+        assert_eq!(Ok(None), hc.get_entry(&invalid_link.address()));
+        let dropped = format!("dropping pending entry {}", invalid_link.address());
+        assert!(logger
+            .lock()
+            .unwrap()
+            .log
+            .iter()
+            .any(|line| line.contains(&dropped)));
+    }
+
+    #[test]
+    /// a link over the limit rolls back the commit staged before it in the same transaction
+    fn failing_link_rolls_back_transaction() {
+        let (context, _) = test_context("alex");
+        let mut context = (*context).clone();
+        context.max_links_per_base = Some(1);
+        let mut hc = Holochain::new(test_commit_dna(), Arc::new(context)).unwrap();
+        hc.start().expect("couldn't start");
+        let base = test_entry().address();
+        let link = |target: Address| Link::new(&base, &target, "tag");
+
+        let result = hc.transaction(|tx| {
+            tx.commit(test_entry())
+                .add_link(link(test_entry_b().address()))
+                .add_link(link(Address::from("other".to_string())));
+        });
+        assert_eq!(Err(HolochainError::LinkLimitExceeded), result);
+        assert_eq!(None, hc.state().unwrap().agent().chain_header_of(&base));
+        assert_eq!(Ok(None), hc.get_entry(&base));
+        assert_eq!(Ok(vec![]), hc.link_tags(&base));
+
+        let result = hc.transaction(|tx| {
+            tx.commit(test_entry())
+                .add_link(link(test_entry_b().address()));
+        });
+        assert_eq!(Ok(vec![base.clone()]), result);
+        assert!(hc.state().unwrap().agent().chain_header_of(&base).is_some());
+        assert_eq!(Ok(vec!["tag".to_string()]), hc.link_tags(&base));
+    }
+
+    #[test]
+    /// an entry failing to commit after one that would commit leaves neither on the chain,
+    /// nor its links in the DHT
+    fn failing_commit_rolls_back_transaction() {
+        let (context, _) = test_context("alex");
+        let mut context = (*context).clone();
+        context.chain_rate_limit = Some(ChainRateLimit::new(1, Duration::from_secs(60)));
+        let mut hc = Holochain::new(test_commit_dna(), Arc::new(context)).unwrap();
+        hc.start().expect("couldn't start");
+        let base = test_entry().address();
+
+        let result = hc.transaction(|tx| {
+            tx.commit(test_entry())
+                .add_link(Link::new(&base, &test_entry_b().address(), "tag"))
+                .commit(test_entry_b());
+        });
+        assert_eq!(Err(HolochainError::ChainRateLimited), result);
+        let agent = hc.state().unwrap().agent();
+        assert_eq!(None, agent.chain_header_of(&base));
+        assert_eq!(None, agent.chain_header_of(&test_entry_b().address()));
+        assert_eq!(Ok(vec![]), hc.link_tags(&base));
+        assert_eq!(1, hc.chain_growth_stats().rate_limited);
+
+        // within the limit the same entry commits
+        let result = hc.transaction(|tx| {
+            tx.commit(test_entry());
+        });
+        assert_eq!(Ok(vec![base.clone()]), result);
+    }
+
+    #[test]
+    /// the base with the most links ranks first by links, the entry got most by gets
+    fn hotspots_rank_most_linked_base_first() {
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(test_commit_dna(), context).unwrap();
+        hc.start().expect("couldn't start");
+        let anchor = test_entry().address();
+        let other = test_entry_b().address();
+        for index in 0..5 {
+            let target = Address::from(format!("target {}", index));
+            let link = Link::new(&anchor, &target, "tag");
+            hc.instance
+                .dispatch_and_wait(ActionWrapper::new(Action::AddLink(link)));
+        }
+        let link = Link::new(&other, &anchor, "tag");
+        hc.instance
+            .dispatch_and_wait(ActionWrapper::new(Action::AddLink(link)));
+        for _ in 0..3 {
+            hc.get_entry(&other).unwrap();
+        }
+        hc.get_entry(&anchor).unwrap();
+
+        let report = hc.hotspots().unwrap();
+        let by_links: Vec<(Address, usize)> = report
+            .by_links
+            .iter()
+            .map(|hotspot| (hotspot.address.clone(), hotspot.links))
+            .collect();
+        assert_eq!(vec![(anchor.clone(), 5), (other.clone(), 1)], by_links);
+        assert_eq!(other, report.by_gets[0].address);
+        assert_eq!(3, report.by_gets[0].gets);
+        assert_eq!(1, report.by_links[0].gets);
+    }
+
+    #[test]
+    /// a link query uses the index of its tag once there is one, other queries scan everything
+    fn eav_query_plan_shows_index_use() {
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(test_commit_dna(), context).unwrap();
+        hc.start().expect("couldn't start");
+        let base = test_entry().address();
+        for index in 0..5 {
+            let target = Address::from(format!("target {}", index));
+            let link = Link::new(&base, &target, "tag");
+            hc.instance
+                .dispatch_and_wait(ActionWrapper::new(Action::AddLink(link)));
+        }
+        let link_query = EavQuery {
+            entity: Some(base.clone()),
+            attribute: Some(link_attribute("tag")),
+            value: None,
+        };
+        let unindexed = hc.explain_eav_query(&link_query).unwrap();
+        assert!(unindexed.full_scan);
+        assert_eq!(None, unindexed.index);
+        assert!(unindexed.estimated_rows >= 5);
+
+        hc.add_eav_attribute_index(&link_attribute("tag")).unwrap();
+        assert_eq!(
+            Ok(QueryPlan {
+                index: Some(format!("attribute:{}", link_attribute("tag"))),
+                estimated_rows: 5,
+                full_scan: false,
+            }),
+            hc.explain_eav_query(&link_query)
+        );
+        assert_eq!(Ok(vec!["tag".to_string()]), hc.link_tags(&base));
+        let status_query = EavQuery {
+            attribute: Some(STATUS_NAME.to_string()),
+            ..Default::default()
+        };
+        assert!(hc.explain_eav_query(&status_query).unwrap().full_scan);
+    }
+
+    #[test]
+    /// the dump of an instance that made a call serializes with all its sections
+    fn diagnostic_dump_serializes() {
+        let (context, _) = test_context("alex");
+        let mut config = HolochainConfig::default();
+        config.record_calls = true;
+        let mut hc = Holochain::new_with_config(test_commit_dna(), context, config).unwrap();
+        hc.start().expect("couldn't start");
+        let commit_args = test_commit_args(&test_entry());
+        let result = hc.call("test_zome", "test_cap", "main", &commit_args);
+        assert!(result.is_ok(), "result = {:?}", result);
+
+        let bundle = hc.diagnostic_dump().unwrap();
+        assert!(bundle.dna_address.is_some());
+        assert_eq!("alex", bundle.agent);
+        assert_eq!(hc.state_fingerprint(), Ok(bundle.state_fingerprint.clone()));
+        assert!(bundle.action_stats.by_kind.get("COMMIT").is_some());
+        assert!(bundle.health.initialized && bundle.health.active);
+        assert!(bundle.lock_stats.acquisitions > 0);
+        assert_eq!(1, bundle.calls.len());
+        assert_eq!("main", bundle.calls[0].function);
+        assert_eq!(None, bundle.calls[0].error);
+        assert!(!bundle.log_lines.is_empty());
+
+        let json = bundle.to_json().unwrap();
+        for field in vec![
+            "dna_address",
+            "agent_address",
+            "state_fingerprint",
+            "dht_fingerprint",
+            "action_stats",
+            "health",
+            "lock_stats",
+            "calls",
+            "log_lines",
+        ] {
+            assert!(
+                json.contains(&format!("\"{}\":", field)),
+                "{} missing",
+                field
+            );
+        }
+        // parameters of calls stay out of the bundle
+        assert!(!json.contains(test_entry().value().as_str()));
+        assert_eq!(Ok(bundle), DiagnosticBundle::from_json(&json));
+    }
+
+    #[test]
+    /// a best effort batch commits the entries around an invalid one and reports it
+    fn best_effort_batch_skips_invalid_entry() {
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(test_commit_dna(), context).unwrap();
+        hc.start().expect("couldn't start");
+        // test_commit_dna() has no testEntryTypeB, entries of it fail validation
+        let invalid = test_entry_b();
+        let unique = test_entry_unique();
+        let entries = vec![test_entry(), invalid.clone(), unique.clone()];
+
+        let result = hc.commit_batch(entries.clone(), BatchMode::AllOrNothing);
+        match result {
+            Err(HolochainError::ValidationFailed(_)) => (),
+            _ => panic!("unexpected result {:?}", result),
+        }
+        assert_eq!(Ok(None), hc.get_entry(&test_entry().address()));
+
+        let report = hc.commit_batch(entries, BatchMode::BestEffort).unwrap();
+        assert_eq!(
+            vec![test_entry().address(), unique.address()],
+            report.committed
+        );
+        assert_eq!(1, report.failed.len());
+        match report.failed[0] {
+            (1, HolochainError::ValidationFailed(_)) => (),
+            ref failure => panic!("unexpected failure {:?}", failure),
+        }
+        assert_eq!(
+            Ok(Some(test_entry())),
+            hc.get_entry(&test_entry().address())
+        );
+        assert_eq!(Ok(Some(unique.clone())), hc.get_entry(&unique.address()));
+        assert_eq!(Ok(None), hc.get_entry(&invalid.address()));
+    }
+
+    #[test]
+    /// the chain verifies with the old key before a rotation and the new key after it
+    fn rotated_chain_verifies_both_segments() {
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(test_commit_dna(), context.clone()).unwrap();
+        hc.start().expect("couldn't start");
+        let old_key = Agent::from("alex".to_string());
+        let new_key = Agent::from("alex-rotated".to_string());
+        let commit = |hc: &mut Holochain, entry: &Entry| {
+            let result = hc.call("test_zome", "test_cap", "main", &test_commit_args(entry));
+            assert!(result.is_ok(), "result = {:?}", result);
+        };
+
+        commit(&mut hc, &test_entry());
+        assert_eq!(
+            Ok(old_key.clone()),
+            hc.active_key().map(|active| active.key)
+        );
+        assert_eq!(
+            Err(HolochainError::UnknownKey("alex-rotated".to_string())),
+            hc.rotate_key(new_key.clone())
+        );
+        context.keystore.generate(&new_key).unwrap();
+        hc.rotate_key(new_key.clone()).unwrap();
+        let active = hc.active_key().unwrap();
+        assert_eq!(new_key, active.key);
+        assert_eq!(context.keystore.public_key(&new_key), Ok(active.public_key));
+        commit(&mut hc, &test_entry_unique());
+
+        let export = hc.export_agent_chain().unwrap();
+        let public_key = context.keystore.public_key(&old_key).unwrap();
+        assert_eq!(Ok(()), export.verify(&public_key));
+        let headers = export.headers();
+        let rotation = headers
+            .iter()
+            .position(|exported| exported.entry.entry_type() == &EntryType::Key)
+            .expect("the rotation should be on the chain");
+        let new_public_key = context.keystore.public_key(&new_key).unwrap();
+        for (position, exported) in headers.iter().enumerate() {
+            let (key, other) = if position <= rotation {
+                (&public_key, &new_public_key)
+            } else {
+                (&new_public_key, &public_key)
+            };
+            assert!(verify_header_signature(
+                key,
+                &exported.header,
+                &exported.signature
+            ));
+            assert!(!verify_header_signature(
+                other,
+                &exported.header,
+                &exported.signature
+            ));
+        }
+    }
+
+    #[test]
+    /// a rotation that wasn't signed by the active key is refused, whoever commits it
+    fn rotation_not_signed_by_old_key_is_rejected() {
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(test_commit_dna(), context.clone()).unwrap();
+        hc.start().expect("couldn't start");
+        let alex = context.agent.clone();
+        let mallory_key_pair = KeyPair::generate().unwrap();
+        let mallory = ActiveKey::new(
+            &Agent::from("mallory".to_string()),
+            &mallory_key_pair.public_key(),
+        );
+
+        // signed by mallory in alex's name
+        let forged = KeyRotation::new(&alex, &mallory_key_pair, &mallory);
+        assert_eq!(
+            Err(HolochainError::InvalidKeyRotation),
+            block_on(commit_entry(
+                forged.to_entry(),
+                &context.action_channel,
+                &context
+            ))
+        );
+        assert_eq!(Ok(alex.clone()), hc.active_key().map(|active| active.key));
+        assert_eq!(
+            None,
+            hc.state()
+                .unwrap()
+                .agent()
+                .chain_header_of(&forged.to_entry().address())
+        );
+
+        // signed by alex, binding mallory's public key, fine since alex chose to
+        let alex_key_pair = context.keystore.key_pair(&alex).unwrap();
+        let rotation = KeyRotation::new(&alex, &alex_key_pair, &mallory);
+        let result = block_on(commit_entry(
+            rotation.to_entry(),
+            &context.action_channel,
+            &context,
+        ));
+        assert!(result.is_ok(), "result = {:?}", result);
+        assert_eq!(Ok(mallory), hc.active_key());
+    }
+
+    #[test]
+    /// a new peer loads the snapshot of another in bulk, unless an entry was tampered with
+    fn bootstrap_from_snapshot() {
+        let (context, _) = test_context("alex");
+        let mut alex = Holochain::new(test_commit_dna(), context).unwrap();
+        alex.start().expect("couldn't start");
+        let result = alex.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        let snapshot = alex.export_dht().unwrap();
+        assert!(snapshot
+            .entries()
+            .iter()
+            .any(|exported| exported.address == test_entry().address()));
+
+        let (context, _) = test_context("billie");
+        let mut billie = Holochain::new(test_commit_dna(), context).unwrap();
+        billie.start().expect("couldn't start");
+        assert_eq!(
+            Ok(false),
+            billie.content_storage().contains(&test_entry().address())
+        );
+        let alex_key = alex.public_key().unwrap();
+        let report = billie.bootstrap_from(&snapshot, &alex_key).unwrap();
+        assert_eq!(snapshot.entries().len(), report.loaded);
+        assert_eq!(
+            Ok(true),
+            billie.content_storage().contains(&test_entry().address())
+        );
+        assert_eq!(
+            Ok(Some(test_entry())),
+            billie.get_entry(&test_entry().address())
+        );
+
+        let corrupted = DhtExport::from_json(
+            &snapshot
+                .to_json()
+                .unwrap()
+                .replace(&test_entry().value().to_string(), "tampered value"),
+        )
+        .unwrap();
+        let (context, _) = test_context("casey");
+        let mut casey = Holochain::new(test_commit_dna(), context).unwrap();
+        casey.start().expect("couldn't start");
+        assert_eq!(
+            Err(HolochainError::InvalidDhtExport(format!(
+                "entry {} doesn't match its address",
+                test_entry().address()
+            ))),
+            casey.bootstrap_from(&corrupted, &alex_key)
+        );
+        assert_eq!(
+            Ok(false),
+            casey.content_storage().contains(&test_entry().address())
+        );
+
+        // alex's snapshot is only trusted as alex's
+        let mallory_key = KeyPair::generate().unwrap().public_key();
+        match casey.bootstrap_from(&snapshot, &mallory_key) {
+            Err(HolochainError::InvalidDhtExport(_)) => (),
+            result => panic!("the snapshot should not verify as mallory's: {:?}", result),
+        }
+        assert_eq!(
+            Ok(false),
+            casey.content_storage().contains(&test_entry().address())
+        );
+    }
+
+    #[test]
+    /// entries with an access control list are left out of a snapshot, so the peers loading
+    /// it don't hand them to anyone
+    fn restricted_entry_is_not_exported() {
+        let (context, _) = test_context("alex");
+        let mut alex = Holochain::new(test_commit_dna(), context).unwrap();
+        alex.start().expect("couldn't start");
+        let restricted = test_entry_unique();
+        let result = alex.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &format!(
+                r#"{{"entry_type_name":"testEntryType","entry_value":"{}","acl":["{}"]}}"#,
+                restricted.value(),
+                Agent::from("casey".to_string()).address()
+            ),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        let result = alex.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        let snapshot = alex.export_dht().unwrap();
+        assert!(snapshot
+            .entries()
+            .iter()
+            .all(|exported| exported.address != restricted.address()));
+
+        let (context, _) = test_context("billie");
+        let mut billie = Holochain::new(test_commit_dna(), context).unwrap();
+        billie.start().expect("couldn't start");
+        billie
+            .bootstrap_from(&snapshot, &alex.public_key().unwrap())
+            .unwrap();
+        assert_eq!(
+            Ok(Some(test_entry())),
+            billie.get_entry(&test_entry().address())
+        );
+        assert_eq!(
+            Ok(false),
+            billie.content_storage().contains(&restricted.address())
+        );
+        assert_eq!(Ok(None), billie.get_entry(&restricted.address()));
+    }
+
+    #[test]
+    /// a store is exported and imported a few entries at a time and restored in full
+    fn streamed_export_round_trip() {
+        let (context, _) = test_context("alex");
+        let mut alex = Holochain::new(test_commit_dna(), context).unwrap();
+        alex.start().expect("couldn't start");
+        let entries: Vec<Entry> = (0..25).map(|_| test_entry_unique()).collect();
+        for entry in entries.iter() {
+            let result = alex.call("test_zome", "test_cap", "main", &test_commit_args(entry));
+            assert!(result.is_ok(), "result = {:?}", result);
+        }
+        let chunk_size = 4;
+        let held = alex.content_storage().addresses().unwrap().len();
+        let alex_key = alex.public_key().unwrap();
+
+        let (context, _) = test_context("billie");
+        let mut billie = Holochain::new(test_commit_dna(), context).unwrap();
+        billie.start().expect("couldn't start");
+        let mut chunks = 0;
+        let report = {
+            let stream = alex.export_dht_stream(chunk_size).unwrap().map(|chunk| {
+                // no more than a chunk of entries is read from the store at a time
+                let chunk = chunk.unwrap();
+                assert!(chunk.entries().len() <= chunk_size);
+                chunks += 1;
+                Ok(ExportChunk::from_json(&chunk.to_json().unwrap()).unwrap())
+            });
+            billie.import_dht_stream(stream, &alex_key).unwrap()
+        };
+        assert_eq!((held + chunk_size - 1) / chunk_size, chunks);
+        assert_eq!(entries.len(), report.loaded);
+        for entry in entries {
+            assert_eq!(Ok(Some(entry.clone())), billie.get_entry(&entry.address()));
+        }
+        let exported_addresses = |hc: &Holochain| {
+            let mut addresses: Vec<Address> = hc
+                .export_dht()
+                .unwrap()
+                .entries()
+                .iter()
+                .map(|exported| exported.address.clone())
+                .collect();
+            addresses.sort();
+            addresses
+        };
+        assert_eq!(exported_addresses(&alex), exported_addresses(&billie));
+    }
+
+    #[test]
+    /// entries with an access control list are left out of streamed exports too
+    fn restricted_entry_is_not_streamed() {
+        let (context, _) = test_context("alex");
+        let mut alex = Holochain::new(test_commit_dna(), context).unwrap();
+        alex.start().expect("couldn't start");
+        let restricted = test_entry_unique();
+        let result = alex.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &format!(
+                r#"{{"entry_type_name":"testEntryType","entry_value":"{}","acl":["{}"]}}"#,
+                restricted.value(),
+                Agent::from("casey".to_string()).address()
+            ),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        let result = alex.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        let alex_key = alex.public_key().unwrap();
+
+        let (context, _) = test_context("billie");
+        let mut billie = Holochain::new(test_commit_dna(), context).unwrap();
+        billie.start().expect("couldn't start");
+        let report = {
+            let stream = alex.export_dht_stream(1).unwrap().map(|chunk| {
+                let chunk = chunk.unwrap();
+                assert!(chunk
+                    .entries()
+                    .iter()
+                    .all(|exported| exported.address != restricted.address()));
+                Ok(chunk)
+            });
+            billie.import_dht_stream(stream, &alex_key).unwrap()
+        };
+        assert_eq!(1, report.loaded);
+        assert_eq!(
+            Ok(Some(test_entry())),
+            billie.get_entry(&test_entry().address())
+        );
+        assert_eq!(
+            Ok(false),
+            billie.content_storage().contains(&restricted.address())
+        );
+        assert_eq!(Ok(None), billie.get_entry(&restricted.address()));
+    }
+
+    #[test]
+    /// the link graph is cleared and restored from an export, and two sets of links merge
+    fn link_graph_export_round_trip() {
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(test_commit_dna(), context).unwrap();
+        hc.start().expect("couldn't start");
+        let base = test_entry().address();
+        let link = |target: &str, tag: &str| Link::new(&base, &Address::from(target), tag);
+        for added in vec![
+            link("first", "tag"),
+            link("second", "tag"),
+            link("third", "other"),
+        ] {
+            hc.instance
+                .dispatch_and_wait(ActionWrapper::new(Action::AddLink(added)));
+        }
+        let export = hc.export_links().unwrap();
+        assert_eq!(3, export.links().len());
+        let file = export.to_json().unwrap();
+
+        hc.import_links(LinkExport::default(), LinkImportMode::Replace)
+            .unwrap();
+        assert_eq!(Ok(vec![]), hc.link_tags(&base));
+        assert_eq!(Ok(LinkExport::default()), hc.export_links());
+
+        let export = LinkExport::from_json(&file).unwrap();
+        hc.import_links(export.clone(), LinkImportMode::Replace)
+            .unwrap();
+        assert_eq!(Ok(export), hc.export_links());
+        assert_eq!(
+            Ok(vec!["other".to_string(), "tag".to_string()]),
+            hc.link_tags(&base)
+        );
+
+        let first = LinkExport::new(vec![link("first", "tag"), link("second", "tag")]);
+        let second = LinkExport::new(vec![link("second", "tag"), link("fourth", "new")]);
+        hc.import_links(first, LinkImportMode::Replace).unwrap();
+        hc.import_links(second, LinkImportMode::Merge).unwrap();
+        assert_eq!(
+            Ok(LinkExport::new(vec![
+                link("fourth", "new"),
+                link("first", "tag"),
+                link("second", "tag"),
+            ])),
+            hc.export_links()
         );
+    }
 
-        let (context, _test_logger) = test_context("bob");
-        let result = Holochain::new(dna.clone(), context.clone());
+    #[test]
+    /// a link to an expired entry is collected, the links between live entries are kept
+    fn gc_removes_links_to_deleted_entries() {
+        let mut dna = test_commit_dna();
+        dna.zomes
+            .get_mut("test_zome")
+            .and_then(|zome| zome.entry_types.get_mut("testEntryType"))
+            .expect("test DNA should define testEntryType")
+            .ttl = Some(60);
+        let clock = ManualClock::new(Duration::from_secs(1000));
+        let (context, _) = test_context("alex");
+        let mut context = (*context).clone();
+        context.clock = Arc::new(clock.clone());
+        let mut hc = Holochain::new(dna, Arc::new(context)).unwrap();
+        hc.start().expect("couldn't start");
+        // the target expires 20 seconds before the base
+        for entry in vec![test_entry_b(), test_entry()] {
+            let result = hc.call("test_zome", "test_cap", "main", &test_commit_args(&entry));
+            assert!(result.is_ok(), "result = {:?}", result);
+            clock.advance(Duration::from_secs(20));
+        }
+        let base = test_entry().address();
+        let target = test_entry_b().address();
+        for link in vec![
+            Link::new(&base, &target, "tag"),
+            Link::new(&base, &base, "self"),
+        ] {
+            hc.instance
+                .dispatch_and_wait(ActionWrapper::new(Action::AddLink(link)));
+        }
+        assert_eq!(
+            Ok(GcReport {
+                links_checked: 2,
+                links_removed: 0,
+            }),
+            hc.gc_links()
+        );
 
-        match result {
-            Ok(_) => assert!(false),
-            Err(err) => assert_eq!(err, HolochainError::ErrorGeneric("fail".to_string())),
-        };
+        clock.advance(Duration::from_secs(21));
+        hc.instance
+            .dispatch_and_wait(ActionWrapper::new(Action::ExpireEntries));
+        assert_eq!(Ok(vec![target.clone()]), hc.expired_entries());
+        assert_eq!(
+            Ok(GcReport {
+                links_checked: 2,
+                links_removed: 1,
+            }),
+            hc.gc_links()
+        );
+        assert_eq!(Ok(vec!["self".to_string()]), hc.link_tags(&base));
+        assert_eq!(
+            Ok(GcReport {
+                links_checked: 1,
+                links_removed: 0,
+            }),
+            hc.gc_links()
+        );
     }
 
     #[test]
-    fn fails_instantiate_if_genesis_times_out() {
-        let dna = create_test_dna_with_wat(
-            "test_zome",
-            Callback::Genesis.capability().as_str(),
-            Some(
-                r#"
-            (module
-                (memory (;0;) 17)
-                (func (export "genesis") (param $p0 i32) (result i32)
-                    (loop (br 0))
-                    i32.const 0
-                )
-                (export "memory" (memory 0))
+    /// a chain exported from one instance continues in a fresh one with its signatures
+    fn agent_chain_export_round_trip() {
+        let (original_context, _) = test_context("alex");
+        let mut original = Holochain::new(test_commit_dna(), original_context.clone()).unwrap();
+        original.start().expect("couldn't start");
+        let entries = vec![test_entry(), test_entry_b(), test_entry_unique()];
+        for entry in entries.iter() {
+            let result = original.call("test_zome", "test_cap", "main", &test_commit_args(entry));
+            assert!(result.is_ok(), "result = {:?}", result);
+        }
+        let export = original.export_agent_chain().unwrap();
+        // the file carried to the other device
+        let file = export.to_json().unwrap();
+
+        let export = AgentChainExport::from_json(&file).unwrap();
+        // without alex's key pair the signatures can't be verified on the other device
+        let (context, _) = test_context("alex");
+        assert_eq!(
+            Err(HolochainError::UnknownKey("alex".to_string())),
+            Holochain::import_agent_chain(
+                test_commit_dna(),
+                context,
+                &export,
+                HolochainConfig::default()
             )
-        "#,
-            ),
+            .map(|_| ())
         );
+        // so it moves along
+        let (context, _) = test_context("alex");
+        let key_pair = original_context
+            .keystore
+            .key_pair(&original_context.agent)
+            .unwrap();
+        context.keystore.insert(&context.agent, key_pair).unwrap();
+        let mut imported = Holochain::import_agent_chain(
+            test_commit_dna(),
+            context,
+            &export,
+            HolochainConfig::default(),
+        )
+        .unwrap();
+        imported.start().expect("couldn't start");
+        assert_eq!(Ok(export.clone()), imported.export_agent_chain());
+        for entry in entries {
+            assert_eq!(
+                Ok(Some(entry.clone())),
+                imported.get_entry(&entry.address())
+            );
+        }
 
-        let (context, _test_logger) = test_context("bob");
-        let result = Holochain::new(dna.clone(), context.clone());
+        let (context, _) = test_context("bob");
+        assert!(Holochain::import_agent_chain(
+            test_commit_dna(),
+            context,
+            &export,
+            HolochainConfig::default()
+        )
+        .is_err());
+    }
 
-        match result {
-            Ok(_) => assert!(false),
-            Err(err) => assert_eq!(
-                err,
-                HolochainError::ErrorGeneric("Timeout while initializing".to_string())
-            ),
-        };
+    /// a copy of everything in the storage
+    fn snapshot(storage: &MemoryStorage) -> MemoryStorage {
+        let mut snapshot = MemoryStorage::new().unwrap();
+        for address in storage.addresses().unwrap() {
+            let content: Content = storage.fetch(&address).unwrap().unwrap();
+            snapshot.add(&content).unwrap();
+        }
+        snapshot
     }
 
     #[test]
-    fn can_start_and_stop() {
-        let dna = Dna::new();
-        let (context, _) = test_context("bob");
-        let mut hc = Holochain::new(dna.clone(), context).unwrap();
-        assert!(!hc.active());
+    /// rolling back discards what was committed since the checkpoint
+    fn rollback_discards_commits_since_checkpoint() {
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(test_commit_dna(), context).unwrap();
+        hc.start().expect("couldn't start");
+        let top = hc.state().unwrap().agent().top_chain_header();
+        let queued = hc.state().unwrap().dht().publish_queue().clone();
+        let checkpoint = hc.checkpoint().unwrap();
 
-        // stop when not active returns error
-        let result = hc.stop();
-        match result {
-            Err(HolochainError::InstanceNotActive) => assert!(true),
-            Ok(_) => assert!(false),
-            Err(_) => assert!(false),
+        for entry in vec![test_entry(), test_entry_b()] {
+            let result = hc.call("test_zome", "test_cap", "main", &test_commit_args(&entry));
+            assert!(result.is_ok(), "result = {:?}", result);
+            assert_eq!(Ok(Some(entry.clone())), hc.get_entry(&entry.address()));
         }
+        let later = hc.checkpoint().unwrap();
 
-        let result = hc.start();
-        match result {
-            Ok(_) => assert!(true),
-            Err(_) => assert!(false),
-        }
-        assert!(hc.active());
+        hc.rollback(checkpoint).unwrap();
+        assert_eq!(Ok(None), hc.get_entry(&test_entry().address()));
+        assert_eq!(Ok(None), hc.get_entry(&test_entry_b().address()));
+        assert_eq!(top, hc.state().unwrap().agent().top_chain_header());
+        assert_eq!(&queued, hc.state().unwrap().dht().publish_queue());
+        assert_eq!(Err(HolochainError::UnknownCheckpoint), hc.rollback(later));
 
-        // start when active returns error
-        let result = hc.start();
-        match result {
-            Err(HolochainError::InstanceActive) => assert!(true),
-            Ok(_) => assert!(false),
-            Err(_) => assert!(false),
-        }
+        // the instance keeps going from the checkpoint, which can be rolled back to again
+        let result = hc.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        hc.rollback(checkpoint).unwrap();
+        assert_eq!(Ok(None), hc.get_entry(&test_entry().address()));
 
-        let result = hc.stop();
-        match result {
-            Ok(_) => assert!(true),
-            Err(_) => assert!(false),
-        }
-        assert!(!hc.active());
+        hc.discard_checkpoint(checkpoint).unwrap();
+        assert_eq!(
+            Err(HolochainError::UnknownCheckpoint),
+            hc.rollback(checkpoint)
+        );
     }
 
     #[test]
-    fn can_call() {
-        let wat = r#"
-(module
- (memory 1)
- (export "memory" (memory 0))
- (export "main" (func $func0))
- (func $func0 (param $p0 i32) (result i32)
-       i32.const 16
-       )
- (data (i32.const 0)
-       "{\"holo\":\"world\"}"
-       )
- )
-"#;
-        let dna = create_test_dna_with_wat("test_zome", "test_cap", Some(wat));
-        let (context, _) = test_context("bob");
-        let mut hc = Holochain::new(dna.clone(), context).unwrap();
-
-        let result = hc.call("test_zome", "test_cap", "main", "");
-        assert!(result.is_err());
-        assert_eq!(result.err().unwrap(), HolochainError::InstanceNotActive);
+    /// an instance keeping entries outside of its state can't take checkpoints
+    fn checkpoint_fails_outside_state() {
+        let (context, _) = test_context("alex");
+        let mut context = (*context).clone();
+        context.route_storage(test_entry().entry_type(), MemoryStorage::new().unwrap());
+        let mut hc = Holochain::new(test_commit_dna(), Arc::new(context)).unwrap();
+        hc.start().expect("couldn't start");
+        assert_eq!(
+            Err(HolochainError::CheckpointUnsupported(
+                "entries of routed types are stored outside of the state".to_string()
+            )),
+            hc.checkpoint()
+        );
 
+        let (context, _) = test_context("alex");
+        let backend = Arc::new(InMemoryNetwork::new().unwrap());
+        let mut hc = Holochain::new_on_network(
+            test_commit_dna(),
+            context,
+            Network::new_on(backend),
+            HolochainConfig::default(),
+        )
+        .unwrap();
         hc.start().expect("couldn't start");
+        assert_eq!(
+            Err(HolochainError::CheckpointUnsupported(
+                "entries handed to peers can't be taken back".to_string()
+            )),
+            hc.checkpoint()
+        );
+    }
 
-        // always returns not implemented error for now!
-        let result = hc.call("test_zome", "test_cap", "main", "");
-        assert!(result.is_ok(), "result = {:?}", result);
-        assert_eq!(result.ok().unwrap(), "{\"holo\":\"world\"}")
+    #[test]
+    /// an instance rebuilt from a snapshot of the storage has the same chain and links
+    fn can_rebuild_from_cas() {
+        let (context, _) = test_context("alex");
+        let mut original = Holochain::new(test_commit_dna(), context).unwrap();
+        original.start().expect("couldn't start");
+        for entry in vec![test_entry(), test_entry_b()] {
+            let result = original.call("test_zome", "test_cap", "main", &test_commit_args(&entry));
+            assert!(result.is_ok(), "result = {:?}", result);
+        }
+        let link = Link::new(&test_entry().address(), &test_entry_b().address(), "tag");
+        original
+            .instance
+            .dispatch_and_wait(ActionWrapper::new(Action::AddLink(link)));
+
+        let (context, _) = test_context("alex");
+        let mut rebuilt = Holochain::rebuild_from_cas(
+            test_commit_dna(),
+            context,
+            snapshot(&original.content_storage()),
+            HolochainConfig::default(),
+        )
+        .expect("instance should be rebuilt");
+        rebuilt.start().expect("couldn't start");
+
+        let original_state = original.state().unwrap();
+        let rebuilt_state = rebuilt.state().unwrap();
+        assert_eq!(
+            original_state.agent().top_chain_header(),
+            rebuilt_state.agent().top_chain_header()
+        );
+        assert_eq!(
+            Ok(vec!["tag".to_string()]),
+            rebuilt_state.dht().link_tags(&test_entry().address())
+        );
+        assert_eq!(original.state_fingerprint(), rebuilt.state_fingerprint());
+        assert!(rebuilt
+            .get_entry_full(&test_entry_b().address())
+            .unwrap()
+            .is_some());
     }
 
     #[test]
-    fn can_get_state() {
-        let dna = Dna::new();
-        let (context, _) = test_context("bob");
-        let mut hc = Holochain::new(dna.clone(), context).unwrap();
+    /// orphan entries and chains of other DNAs are reported instead of rebuilt
+    fn rebuild_from_cas_reports_inconsistencies() {
+        let (context, _) = test_context("alex");
+        let original = Holochain::new(test_commit_dna(), context).unwrap();
 
-        let result = hc.state();
-        match result {
-            Ok(state) => {
-                assert_eq!(state.nucleus().dna(), Some(dna));
+        let mut with_orphan = snapshot(&original.content_storage());
+        with_orphan.add(&test_entry()).unwrap();
+        let (context, _) = test_context("alex");
+        match Holochain::rebuild_from_cas(
+            test_commit_dna(),
+            context,
+            with_orphan,
+            HolochainConfig::default(),
+        ) {
+            Err(HolochainError::InconsistentStorage(report)) => {
+                assert!(
+                    report.contains("is not on the chain"),
+                    "report = {}",
+                    report
+                )
             }
-            Err(_) => assert!(false),
-        };
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+
+        let (context, _) = test_context("alex");
+        let cas = snapshot(&original.content_storage());
+        assert_eq!(
+            Err(HolochainError::InconsistentStorage(
+                "the chain was started with another DNA".to_string()
+            )),
+            Holochain::rebuild_from_cas(Dna::new(), context, cas, HolochainConfig::default())
+                .map(|_| ())
+        );
     }
 
     #[test]
-    fn can_call_test() {
+    fn can_pin_and_unpin_entries() {
+        let dna = create_test_dna_with_wat("test_zome", "test_cap", None);
+        let (context, _) = test_context("bob");
+        let mut hc = Holochain::new(dna, context).unwrap();
+        let address = test_entry_address();
+        assert_eq!(
+            Err(HolochainError::InstanceNotActive),
+            hc.pin_entry(&address)
+        );
+
+        hc.start().expect("couldn't start");
+        hc.pin_entry(&address).expect("couldn't pin entry");
+        assert!(hc.state().unwrap().dht().cache().is_pinned(&address));
+
+        hc.unpin_entry(&address).expect("couldn't unpin entry");
+        assert!(!hc.state().unwrap().dht().cache().is_pinned(&address));
+    }
+
+    #[test]
+    fn can_call_commit_as_delegate() {
         let wasm = create_wasm_from_file(
-            "wasm-test/round_trip/target/wasm32-unknown-unknown/release/round_trip.wasm",
+            "wasm-test/commit/target/wasm32-unknown-unknown/release/commit.wasm",
         );
         let capability = create_test_cap_with_fn_name("test");
         let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
-        let (context, _) = test_context("bob");
-        let mut hc = Holochain::new(dna.clone(), context).unwrap();
-
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(dna.clone(), context.clone()).unwrap();
         hc.start().expect("couldn't start");
 
-        // always returns not implemented error for now!
-        let result = hc.call(
+        let delegate = holochain_agent::Agent::from("bob".to_string());
+        let key_pair = context.keystore.key_pair(&context.agent).unwrap();
+        let scope = DelegationScope::new(
             "test_zome",
             "test_cap",
             "test",
-            r#"{"input_int_val":2,"input_str_val":"fish"}"#,
+            &dna.to_entry().address(),
+            context.clock.now() + Duration::from_secs(3600),
         );
+        let proof = delegation_proof(&key_pair, &delegate, &scope);
+        let delegation = Delegation::new(&delegate, &scope, &proof);
+        let result = hc.call_as(&delegation, "test_zome", "test_cap", "test", r#"{}"#);
         assert!(result.is_ok(), "result = {:?}", result);
+
+        // the committed entry is attributed to the delegate
+        let state = hc.state().unwrap();
+        let top_chain_header = state
+            .agent()
+            .top_chain_header()
+            .expect("commit should add a chain header");
         assert_eq!(
-            result.ok().unwrap(),
-            r#"{"input_int_val_plus2":4,"input_str_val_plus_dog":"fish.puppy"}"#
+            Some(delegate),
+            state.agent().delegate(&top_chain_header.address())
+        );
+        // and the header carries the scope and the proof the agent allowed it
+        let public_key = context.keystore.public_key(&context.agent).unwrap();
+        assert_eq!(
+            Some(scope.to_json().unwrap()),
+            top_chain_header.delegation_scope()
         );
+        assert_eq!(Some(proof), top_chain_header.delegation_proof());
+        assert!(verify_chain_header_delegation(
+            &public_key,
+            &top_chain_header
+        ));
     }
 
     #[test]
-    // TODO #165 - Move test to core/nucleus and use instance directly
-    fn can_call_commit() {
-        // Setup the holochain instance
+    fn call_as_rejects_invalid_delegation_proof() {
         let wasm = create_wasm_from_file(
             "wasm-test/commit/target/wasm32-unknown-unknown/release/commit.wasm",
         );
         let capability = create_test_cap_with_fn_name("test");
         let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
         let (context, _) = test_context("alex");
-        let mut hc = Holochain::new(dna.clone(), context).unwrap();
+        let mut hc = Holochain::new(dna.clone(), context.clone()).unwrap();
+        hc.start().expect("couldn't start");
 
-        // Run the holochain instance
+        let delegate = holochain_agent::Agent::from("bob".to_string());
+        let scope = DelegationScope::new(
+            "test_zome",
+            "test_cap",
+            "test",
+            &dna.to_entry().address(),
+            context.clock.now() + Duration::from_secs(3600),
+        );
+        // a proof signed by someone other than the instance's agent
+        let proof = delegation_proof(&KeyPair::generate().unwrap(), &delegate, &scope);
+        let delegation = Delegation::new(&delegate, &scope, &proof);
+        let result = hc.call_as(&delegation, "test_zome", "test_cap", "test", r#"{}"#);
+        assert_eq!(Err(HolochainError::InvalidDelegationProof), result);
+
+        // nothing but genesis was committed
+        let state = hc.state().unwrap();
+        let top_chain_header = state.agent().top_chain_header().unwrap();
+        assert_eq!(None, state.agent().delegate(&top_chain_header.address()));
+    }
+
+    #[test]
+    /// a delegation only allows calling the function of its scope, in its zome and in
+    /// instances of its DNA, until it expires
+    fn call_as_checks_delegation_scope() {
+        let wasm = create_wasm_from_file(
+            "wasm-test/commit/target/wasm32-unknown-unknown/release/commit.wasm",
+        );
+        let capability = create_test_cap_with_fn_name("test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let clock = ManualClock::new(Duration::from_secs(1000));
+        let (context, _) = test_context("alex");
+        let mut context = (*context).clone();
+        context.clock = Arc::new(clock.clone());
+        let context = Arc::new(context);
+        let mut hc = Holochain::new(dna.clone(), context.clone()).unwrap();
         hc.start().expect("couldn't start");
-        // @TODO don't use history length in tests
-        // @see https://github.com/holochain/holochain-rust/issues/195
-        assert_eq!(hc.state().unwrap().history.len(), 3);
 
-        // Call the exposed wasm function that calls the Commit API function
-        let result = hc.call("test_zome", "test_cap", "test", r#"{}"#);
+        let delegate = holochain_agent::Agent::from("bob".to_string());
+        let key_pair = context.keystore.key_pair(&context.agent).unwrap();
+        let delegation = |scope: DelegationScope| {
+            let proof = delegation_proof(&key_pair, &delegate, &scope);
+            Delegation::new(&delegate, &scope, &proof)
+        };
+        let scope = DelegationScope::new(
+            "test_zome",
+            "test_cap",
+            "test",
+            &dna.to_entry().address(),
+            Duration::from_secs(1060),
+        );
+        let out_of_scope = vec![
+            DelegationScope {
+                zome: "other_zome".to_string(),
+                ..scope.clone()
+            },
+            DelegationScope {
+                capability: "other_cap".to_string(),
+                ..scope.clone()
+            },
+            DelegationScope {
+                function: "test_fail".to_string(),
+                ..scope.clone()
+            },
+            DelegationScope {
+                dna: Address::from("another dna".to_string()),
+                ..scope.clone()
+            },
+        ];
+        for other in out_of_scope {
+            assert_eq!(
+                Err(HolochainError::DelegationOutOfScope),
+                hc.call_as(&delegation(other), "test_zome", "test_cap", "test", r#"{}"#)
+            );
+        }
 
-        // Expect fail because no validation function in wasm
+        let allowed = delegation(scope);
+        let result = hc.call_as(&allowed, "test_zome", "test_cap", "test", r#"{}"#);
         assert!(result.is_ok(), "result = {:?}", result);
-        assert_ne!(
-            result.clone().ok().unwrap(),
-            "{\"Err\":\"Argument deserialization failed\"}"
-        );
 
-        // Check in holochain instance's history that the commit event has been processed
-        // @TODO don't use history length in tests
-        // @see https://github.com/holochain/holochain-rust/issues/195
-        assert_eq!(hc.state().unwrap().history.len(), 6);
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(
+            Err(HolochainError::DelegationExpired),
+            hc.call_as(&allowed, "test_zome", "test_cap", "test", r#"{}"#)
+        );
     }
 
     #[test]
@@ -415,24 +5565,28 @@ mod tests {
 
         // Run the holochain instance
         hc.start().expect("couldn't start");
-        // @TODO don't use history length in tests
-        // @see https://github.com/holochain/holochain-rust/issues/195
-        assert_eq!(hc.state().unwrap().history.len(), 3);
+        let cursor = hc.state().unwrap().history_cursor();
 
         // Call the exposed wasm function that calls the Commit API function
         let result = hc.call("test_zome", "test_cap", "test_fail", r#"{}"#);
 
-        // Expect normal OK result with hash
-        assert!(result.is_ok(), "result = {:?}", result);
-        assert_eq!(
-            result.ok().unwrap(),
-            "{\"Err\":\"Argument deserialization failed\"}"
-        );
+        // Expect a structured error telling which function failed and why
+        match result {
+            Err(HolochainError::ZomeCallFailed(error)) => {
+                assert_eq!("test_zome", error.zome_name);
+                assert_eq!("test_fail", error.fn_name);
+                assert_eq!(None, error.trap);
+                assert_eq!("Argument deserialization failed", error.reason);
+                assert_eq!(
+                    "{\"Err\":\"Argument deserialization failed\"}",
+                    error.payload
+                );
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
 
         // Check in holochain instance's history that the commit event has been processed
-        // @TODO don't use history length in tests
-        // @see https://github.com/holochain/holochain-rust/issues/195
-        assert_eq!(hc.state().unwrap().history.len(), 5);
+        assert!(hc.state().unwrap().history_cursor().is_after(&cursor));
     }
 
     #[test]
@@ -450,9 +5604,7 @@ mod tests {
 
         // Run the holochain instance
         hc.start().expect("couldn't start");
-        // @TODO don't use history length in tests
-        // @see https://github.com/holochain/holochain-rust/issues/195
-        assert_eq!(hc.state().unwrap().history.len(), 3);
+        let cursor = hc.state().unwrap().history_cursor();
 
         // Call the exposed wasm function that calls the Commit API function
         let result = hc.call("test_zome", "test_cap", "debug_hello", r#"{}"#);
@@ -464,9 +5616,7 @@ mod tests {
             "[\"TestApp instantiated\", \"Zome Function \\\'debug_hello\\\' returned: Success\"]",
         );
         // Check in holochain instance's history that the debug event has been processed
-        // @TODO don't use history length in tests
-        // @see https://github.com/holochain/holochain-rust/issues/195
-        assert_eq!(hc.state().unwrap().history.len(), 5);
+        assert!(hc.state().unwrap().history_cursor().is_after(&cursor));
     }
 
     #[test]
@@ -484,9 +5634,7 @@ mod tests {
 
         // Run the holochain instance
         hc.start().expect("couldn't start");
-        // @TODO don't use history length in tests
-        // @see https://github.com/holochain/holochain-rust/issues/195
-        assert_eq!(hc.state().unwrap().history.len(), 3);
+        let cursor = hc.state().unwrap().history_cursor();
 
         // Call the exposed wasm function that calls the Commit API function
         let result = hc.call("test_zome", "test_cap", "debug_multiple", r#"{}"#);
@@ -502,8 +5650,90 @@ mod tests {
         );
 
         // Check in holochain instance's history that the deb event has been processed
-        // @TODO don't use history length in tests
-        // @see https://github.com/holochain/holochain-rust/issues/195
-        assert_eq!(hc.state().unwrap().history.len(), 5);
+        assert!(hc.state().unwrap().history_cursor().is_after(&cursor));
+    }
+
+    #[test]
+    /// entries valid under the installed DNA are flagged by a stricter candidate, which is
+    /// simulated without replacing the installed DNA
+    fn simulated_validation_flags_entries_failing_candidate() {
+        let (context, _) = test_context("alex");
+        let dna = test_commit_dna();
+        let mut hc = Holochain::new(dna.clone(), context).unwrap();
+        hc.start().expect("couldn't start");
+        let result = hc.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        let top_chain_header = hc.instance.state().agent().top_chain_header();
+
+        let report = hc.simulate_validation(test_invalid_commit_dna());
+
+        assert_eq!(vec![test_entry().address()], report.failed());
+        assert_eq!(
+            Some(&Err("entries are not allowed".to_string())),
+            report.results().get(&test_entry().address())
+        );
+        assert!(hc.revalidate_all().all_passed());
+        assert_eq!(Some(dna), hc.instance.state().nucleus().dna());
+        assert_eq!(
+            top_chain_header,
+            hc.instance.state().agent().top_chain_header()
+        );
+    }
+
+    #[test]
+    /// ratings two agents attach to the same entry are both read back, each signed by its
+    /// author
+    fn agents_attach_signed_metadata() {
+        let backend = Arc::new(InMemoryNetwork::new().unwrap());
+        let start = |name: &str| {
+            let (context, _) = test_context(name);
+            let network = Network::new_on(backend.clone());
+            let mut hc = Holochain::new_on_network(
+                test_commit_dna(),
+                context,
+                network,
+                HolochainConfig::default(),
+            )
+            .unwrap();
+            hc.start().expect("couldn't start");
+            hc
+        };
+        let mut alex = start("alex");
+        let mut billie = start("billie");
+        let result = alex.call(
+            "test_zome",
+            "test_cap",
+            "main",
+            &test_commit_args(&test_entry()),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        let address = test_entry().address();
+
+        let alex_rating = alex.attach_metadata(&address, "rating", "5").unwrap();
+        let billie_rating = billie.attach_metadata(&address, "rating", "3").unwrap();
+        billie.attach_metadata(&address, "flag", "spam").unwrap();
+
+        for hc in vec![&alex, &billie] {
+            let mut ratings = hc.get_metadata(&address, "rating").unwrap();
+            ratings.sort_by_key(|rating| rating.author().to_string());
+            assert_eq!(vec![alex_rating.clone(), billie_rating.clone()], ratings);
+        }
+        assert_eq!(Agent::from("alex".to_string()), alex_rating.author());
+        assert_eq!("5", alex_rating.value);
+        assert_eq!(Ok(()), alex_rating.verify());
+        assert_eq!(Agent::from("billie".to_string()), billie_rating.author());
+        assert_eq!("3", billie_rating.value);
+        assert_eq!(Ok(()), billie_rating.verify());
+        assert!(alex_rating.signed_by(&alex.public_key().unwrap()));
+        assert!(!alex_rating.signed_by(&billie.public_key().unwrap()));
+        assert!(billie_rating.signed_by(&billie.public_key().unwrap()));
+        assert_ne!(alex_rating.signature, billie_rating.signature);
+        assert_eq!(1, alex.get_metadata(&address, "flag").unwrap().len());
+        assert!(alex.get_metadata(&address, "reaction").unwrap().is_empty());
     }
 }
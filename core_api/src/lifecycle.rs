@@ -0,0 +1,168 @@
+//! Coarse lifecycle signals for containers, e.g. to notify a supervisor when an instance stops.
+//!
+//! Events are handed to a dedicated thread that calls the registered callbacks in the order
+//! the events happened, so emitting an event never blocks the instance.
+//! Events emitted before the first callback is registered (e.g. InitializationComplete during
+//! construction) are kept and delivered to that first callback, up to LIFECYCLE_BACKLOG of
+//! them: an instance nobody listens to drops the oldest ones.
+//! A panicking callback misses that event only, it keeps getting the next ones.
+
+use std::{
+    collections::VecDeque,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::mpsc::{channel, Sender},
+    thread,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LifecycleEvent {
+    /// genesis succeeded and the instance is ready to be started
+    InitializationComplete,
+    Started,
+    Stopped,
+    Paused,
+    Resumed,
+}
+
+/// how many events are kept for the first callback, @see LifecycleNotifier::register()
+pub const LIFECYCLE_BACKLOG: usize = 32;
+
+pub type LifecycleCallback = Box<Fn(LifecycleEvent) + Send>;
+
+/// calls the callback, a panic inside it doesn't end the notifier thread
+fn deliver(callback: &LifecycleCallback, event: LifecycleEvent) {
+    let _ = catch_unwind(AssertUnwindSafe(|| callback(event)));
+}
+
+enum LifecycleMessage {
+    Event(LifecycleEvent),
+    Register(LifecycleCallback),
+}
+
+/// Dispatches LifecycleEvents to callbacks on a separate thread.
+/// The thread ends when the notifier is dropped.
+pub struct LifecycleNotifier {
+    sender: Sender<LifecycleMessage>,
+}
+
+impl LifecycleNotifier {
+    pub fn new() -> Self {
+        let (sender, receiver) = channel::<LifecycleMessage>();
+
+        thread::spawn(move || {
+            let mut callbacks: Vec<LifecycleCallback> = Vec::new();
+            let mut backlog: VecDeque<LifecycleEvent> = VecDeque::new();
+            for message in receiver {
+                match message {
+                    LifecycleMessage::Register(callback) => {
+                        if callbacks.is_empty() {
+                            for event in backlog.drain(..) {
+                                deliver(&callback, event);
+                            }
+                        }
+                        callbacks.push(callback);
+                    }
+                    LifecycleMessage::Event(event) => {
+                        if callbacks.is_empty() {
+                            if backlog.len() == LIFECYCLE_BACKLOG {
+                                backlog.pop_front();
+                            }
+                            backlog.push_back(event);
+                        }
+                        for callback in callbacks.iter() {
+                            deliver(callback, event);
+                        }
+                    }
+                }
+            }
+        });
+
+        LifecycleNotifier { sender }
+    }
+
+    /// register a callback for all future events
+    /// the first callback also gets the last LIFECYCLE_BACKLOG events emitted before it
+    pub fn register(&self, callback: LifecycleCallback) {
+        // the thread only ends with the notifier, nothing to register with otherwise
+        let _ = self.sender.send(LifecycleMessage::Register(callback));
+    }
+
+    /// notify all callbacks of the event without waiting for them
+    pub fn notify(&self, event: LifecycleEvent) {
+        // the thread only ends with the notifier, nobody to notify otherwise
+        let _ = self.sender.send(LifecycleMessage::Event(event));
+    }
+}
+
+impl Default for LifecycleNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::{sync::mpsc::channel, time::Duration};
+
+    #[test]
+    /// events emitted before registration are delivered to the first callback
+    fn backlog_is_delivered_in_order() {
+        let notifier = LifecycleNotifier::new();
+        notifier.notify(LifecycleEvent::InitializationComplete);
+        notifier.notify(LifecycleEvent::Started);
+
+        let (sender, receiver) = channel();
+        notifier.register(Box::new(move |event| sender.send(event).unwrap()));
+        notifier.notify(LifecycleEvent::Stopped);
+
+        let timeout = Duration::from_secs(1);
+        for expected in vec![
+            LifecycleEvent::InitializationComplete,
+            LifecycleEvent::Started,
+            LifecycleEvent::Stopped,
+        ] {
+            assert_eq!(Ok(expected), receiver.recv_timeout(timeout));
+        }
+    }
+
+    #[test]
+    /// only the latest events are kept while no callback is registered
+    fn backlog_is_bounded() {
+        let notifier = LifecycleNotifier::new();
+        notifier.notify(LifecycleEvent::InitializationComplete);
+        for _ in 0..LIFECYCLE_BACKLOG {
+            notifier.notify(LifecycleEvent::Paused);
+        }
+        notifier.notify(LifecycleEvent::Stopped);
+
+        let (sender, receiver) = channel();
+        notifier.register(Box::new(move |event| sender.send(event).unwrap()));
+
+        let timeout = Duration::from_secs(1);
+        for _ in 1..LIFECYCLE_BACKLOG {
+            assert_eq!(Ok(LifecycleEvent::Paused), receiver.recv_timeout(timeout));
+        }
+        assert_eq!(Ok(LifecycleEvent::Stopped), receiver.recv_timeout(timeout));
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    /// a panicking callback neither ends the thread nor keeps events from the other callbacks
+    fn panicking_callback_is_contained() {
+        let notifier = LifecycleNotifier::new();
+        notifier.register(Box::new(|event| {
+            if event == LifecycleEvent::Started {
+                panic!("callback failed");
+            }
+        }));
+        let (sender, receiver) = channel();
+        notifier.register(Box::new(move |event| sender.send(event).unwrap()));
+        notifier.notify(LifecycleEvent::Started);
+        notifier.notify(LifecycleEvent::Stopped);
+
+        let timeout = Duration::from_secs(1);
+        assert_eq!(Ok(LifecycleEvent::Started), receiver.recv_timeout(timeout));
+        assert_eq!(Ok(LifecycleEvent::Stopped), receiver.recv_timeout(timeout));
+    }
+}
@@ -0,0 +1,14 @@
+//! Checks extensions add to the zome calls of an instance.
+//!
+//! Deployments sometimes need rules the DNA doesn't know about, e.g. quotas kept in an outside
+//! system. A middleware sees every call before it is dispatched and can reject it with an error
+//! of its own type through HolochainError::custom(); the caller gets that error back from
+//! call() and can downcast it with HolochainError::downcast_custom().
+
+use holochain_core::nucleus::ZomeFnCall;
+use holochain_core_types::error::HolochainError;
+
+pub trait CallMiddleware: Send {
+    /// called before the call is dispatched, an error rejects the call with it
+    fn before_call(&mut self, zome_call: &ZomeFnCall) -> Result<(), HolochainError>;
+}
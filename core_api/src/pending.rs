@@ -0,0 +1,98 @@
+//! Entries committed ahead of the entries they reference, waiting for them to arrive.
+//!
+//! Bulk imports of a graph don't necessarily come in dependency order: a link or a reply can
+//! show up before its base. Holochain::commit_deferred() parks such an entry here instead of
+//! failing, and commits it once everything it references is held.
+
+use holochain_core::dht::dht_store::publish_dependencies;
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    entry::Entry,
+    error::HolochainError,
+};
+
+/// An entry waiting in the pending area, with the addresses it references that aren't held
+#[derive(Clone, Debug, PartialEq)]
+pub struct PendingEntry {
+    pub entry: Entry,
+    pub missing: Vec<Address>,
+}
+
+/// the addresses the entry references that is_held says aren't held, in reference order
+/// fails with the first error of is_held
+/// @see publish_dependencies()
+pub fn missing_dependencies<F>(entry: &Entry, is_held: F) -> Result<Vec<Address>, HolochainError>
+where
+    F: Fn(&Address) -> Result<bool, HolochainError>,
+{
+    let mut missing = Vec::new();
+    for address in publish_dependencies(entry) {
+        if address != entry.address() && !is_held(&address)? {
+            missing.push(address);
+        }
+    }
+    Ok(missing)
+}
+
+/// The entries waiting for their dependencies, in the order they were deferred
+#[derive(Clone, Debug, Default)]
+pub struct PendingEntries {
+    entries: Vec<Entry>,
+}
+
+impl PendingEntries {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// parks the entry, unless it is waiting already
+    pub fn defer(&mut self, entry: Entry) {
+        if !self.entries.contains(&entry) {
+            self.entries.push(entry);
+        }
+    }
+
+    /// takes out the entries whose dependencies are all held, in the order they were deferred
+    /// fails with the first error of is_held, leaving every entry waiting
+    pub fn take_ready<F>(&mut self, is_held: F) -> Result<Vec<Entry>, HolochainError>
+    where
+        F: Fn(&Address) -> Result<bool, HolochainError>,
+    {
+        let mut is_ready = Vec::new();
+        for entry in self.entries.iter() {
+            is_ready.push(missing_dependencies(entry, &is_held)?.is_empty());
+        }
+        let mut ready = Vec::new();
+        let mut waiting = Vec::new();
+        for (entry, is_ready) in self.entries.drain(..).zip(is_ready) {
+            if is_ready {
+                ready.push(entry);
+            } else {
+                waiting.push(entry);
+            }
+        }
+        self.entries = waiting;
+        Ok(ready)
+    }
+
+    /// the waiting entries with what they are waiting for
+    /// fails with the first error of is_held
+    pub fn list<F>(&self, is_held: F) -> Result<Vec<PendingEntry>, HolochainError>
+    where
+        F: Fn(&Address) -> Result<bool, HolochainError>,
+    {
+        self.entries
+            .iter()
+            .map(|entry| {
+                Ok(PendingEntry {
+                    entry: entry.clone(),
+                    missing: missing_dependencies(entry, &is_held)?,
+                })
+            })
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
@@ -0,0 +1,83 @@
+//! Deployment-time overrides of which zome functions an instance exposes.
+//!
+//! The DNA's capabilities say what an app offers; an operator may still want to switch off
+//! some of it, e.g. an admin function in a shared deployment, without editing the DNA. The
+//! policies are checked before a call is dispatched, functions without one stay callable.
+
+use holochain_core::nucleus::ZomeFnCall;
+use holochain_core_types::error::HolochainError;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FunctionPolicy {
+    Allow,
+    Deny,
+}
+
+/// The policies set for single functions, by zome, capability and function name
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FunctionPolicies {
+    policies: HashMap<(String, String, String), FunctionPolicy>,
+}
+
+impl FunctionPolicies {
+    pub fn new() -> Self {
+        FunctionPolicies::default()
+    }
+
+    /// replaces the policy of the function, if any
+    pub fn set(&mut self, zome: &str, cap: &str, fn_name: &str, policy: FunctionPolicy) {
+        self.policies.insert(
+            (zome.to_string(), cap.to_string(), fn_name.to_string()),
+            policy,
+        );
+    }
+
+    /// the policy of the function, Allow unless it was set otherwise
+    pub fn get(&self, zome: &str, cap: &str, fn_name: &str) -> FunctionPolicy {
+        self.policies
+            .get(&(zome.to_string(), cap.to_string(), fn_name.to_string()))
+            .cloned()
+            .unwrap_or(FunctionPolicy::Allow)
+    }
+
+    /// fails with FunctionDisabled if the function of the call is denied
+    pub fn check(&self, zome_call: &ZomeFnCall) -> Result<(), HolochainError> {
+        match self.get(
+            &zome_call.zome_name,
+            &zome_call.cap_name,
+            &zome_call.fn_name,
+        ) {
+            FunctionPolicy::Allow => Ok(()),
+            FunctionPolicy::Deny => Err(HolochainError::FunctionDisabled(format!(
+                "{}/{}/{}",
+                zome_call.zome_name, zome_call.cap_name, zome_call.fn_name
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    /// functions are allowed unless denied, and can be allowed again
+    fn policies_default_to_allow() {
+        let mut policies = FunctionPolicies::new();
+        let call = ZomeFnCall::new("zome", "cap", "admin", "{}");
+        assert_eq!(Ok(()), policies.check(&call));
+
+        policies.set("zome", "cap", "admin", FunctionPolicy::Deny);
+        assert_eq!(
+            Err(HolochainError::FunctionDisabled(
+                "zome/cap/admin".to_string()
+            )),
+            policies.check(&call)
+        );
+        assert_eq!(FunctionPolicy::Allow, policies.get("zome", "cap", "main"));
+
+        policies.set("zome", "cap", "admin", FunctionPolicy::Allow);
+        assert_eq!(Ok(()), policies.check(&call));
+    }
+}
@@ -0,0 +1,122 @@
+//! Rate limits on the calls to single capabilities.
+//!
+//! Capabilities differ in what a call costs: a read capability can take many calls while a
+//! capability that commits should be throttled. Each limited capability counts its own calls
+//! within a sliding window of the context clock, capabilities without a limit are unlimited.
+
+use holochain_core::nucleus::ZomeFnCall;
+use holochain_core_types::error::HolochainError;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+/// Bound on how many calls a capability takes within any window of the given length
+/// calls over the limit fail with CallRateLimited
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CallRateLimit {
+    pub max_calls: usize,
+    pub window: Duration,
+}
+
+impl CallRateLimit {
+    pub fn new(max_calls: usize, window: Duration) -> Self {
+        CallRateLimit { max_calls, window }
+    }
+}
+
+/// A limited capability with the times of its calls in the current window
+#[derive(Clone, Debug, PartialEq)]
+struct LimitedCapability {
+    limit: CallRateLimit,
+    call_times: VecDeque<Duration>,
+}
+
+/// The rate limits set for single capabilities, by zome and capability name
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CapabilityRateLimits {
+    limits: HashMap<(String, String), LimitedCapability>,
+}
+
+impl CapabilityRateLimits {
+    pub fn new() -> Self {
+        CapabilityRateLimits::default()
+    }
+
+    /// replaces the limit of the capability, if any, starting a fresh window
+    pub fn set(&mut self, zome: &str, cap: &str, limit: CallRateLimit) {
+        self.limits.insert(
+            (zome.to_string(), cap.to_string()),
+            LimitedCapability {
+                limit,
+                call_times: VecDeque::new(),
+            },
+        );
+    }
+
+    /// lifts the limit of the capability
+    pub fn remove(&mut self, zome: &str, cap: &str) {
+        self.limits.remove(&(zome.to_string(), cap.to_string()));
+    }
+
+    /// the limit of the capability, None if it is unlimited
+    pub fn get(&self, zome: &str, cap: &str) -> Option<CallRateLimit> {
+        self.limits
+            .get(&(zome.to_string(), cap.to_string()))
+            .map(|limited| limited.limit)
+    }
+
+    /// counts the call at the time now against the limit of its capability
+    /// fails with CallRateLimited if the capability took as many calls as its limit allows,
+    /// calls rejected don't count
+    pub fn check(&mut self, zome_call: &ZomeFnCall, now: Duration) -> Result<(), HolochainError> {
+        let key = (zome_call.zome_name.clone(), zome_call.cap_name.clone());
+        let limited = match self.limits.get_mut(&key) {
+            Some(limited) => limited,
+            None => return Ok(()),
+        };
+        let window = limited.limit.window;
+        while limited
+            .call_times
+            .front()
+            .map_or(false, |called| now >= *called + window)
+        {
+            limited.call_times.pop_front();
+        }
+        if limited.call_times.len() >= limited.limit.max_calls {
+            return Err(HolochainError::CallRateLimited(format!(
+                "{}/{}",
+                zome_call.zome_name, zome_call.cap_name
+            )));
+        }
+        limited.call_times.push_back(now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    /// a capability takes calls again once its earliest calls left the window
+    fn calls_are_counted_in_a_sliding_window() {
+        let mut limits = CapabilityRateLimits::new();
+        let call = ZomeFnCall::new("zome", "write", "commit", "{}");
+        let second = Duration::from_secs(1);
+        limits.set("zome", "write", CallRateLimit::new(2, 10 * second));
+
+        assert_eq!(Ok(()), limits.check(&call, 100 * second));
+        assert_eq!(Ok(()), limits.check(&call, 105 * second));
+        assert_eq!(
+            Err(HolochainError::CallRateLimited("zome/write".to_string())),
+            limits.check(&call, 109 * second)
+        );
+        assert_eq!(Ok(()), limits.check(&call, 110 * second));
+        assert!(limits.check(&call, 111 * second).is_err());
+
+        limits.remove("zome", "write");
+        assert_eq!(None, limits.get("zome", "write"));
+        assert_eq!(Ok(()), limits.check(&call, 111 * second));
+    }
+}
@@ -0,0 +1,108 @@
+//! Records of zome calls, to re-run a misbehaving call against the state it saw.
+//!
+//! The time the call was made at and the seed of its random numbers are recorded, so the
+//! replay reads the same time from its clock and gets the same random numbers.
+
+use holochain_core::nucleus::ZomeFnCall;
+use holochain_core_types::error::HolochainError;
+use std::time::Duration;
+
+/// A zome call made through Holochain::call() or Holochain::call_as(), and its result
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallRecord {
+    call: ZomeFnCall,
+    result: Result<String, HolochainError>,
+    seed: u64,
+    time: Duration,
+}
+
+impl CallRecord {
+    pub fn new(
+        call: ZomeFnCall,
+        result: Result<String, HolochainError>,
+        seed: u64,
+        time: Duration,
+    ) -> Self {
+        CallRecord {
+            call,
+            result,
+            seed,
+            time,
+        }
+    }
+
+    /// the recorded call
+    pub fn call(&self) -> &ZomeFnCall {
+        &self.call
+    }
+
+    /// the result the recorded call returned
+    pub fn result(&self) -> &Result<String, HolochainError> {
+        &self.result
+    }
+
+    /// the seed of the random numbers the recorded call got
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// the time the clock of the instance read when the call was made
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// a new call with the same function, parameters and delegation as the recorded one
+    pub(crate) fn replay_call(&self) -> ZomeFnCall {
+        let call = &self.call;
+        match call.delegation {
+            Some(ref delegation) => ZomeFnCall::new_delegated(
+                delegation,
+                &call.zome_name,
+                &call.cap_name,
+                &call.fn_name,
+                &call.parameters,
+            ),
+            None => ZomeFnCall::new(
+                &call.zome_name,
+                &call.cap_name,
+                &call.fn_name,
+                &call.parameters,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate holochain_agent;
+    use super::*;
+    use holochain_core::agent::delegation::{Delegation, DelegationScope};
+    use holochain_core_types::{cas::content::Address, signature::test_signature};
+
+    #[test]
+    /// replayed calls keep everything but the call id
+    fn replay_call_matches_recorded_call() {
+        let delegate = holochain_agent::Agent::from("bob".to_string());
+        let scope = DelegationScope::new(
+            "zome",
+            "cap",
+            "fn",
+            &Address::from("dna".to_string()),
+            Duration::from_secs(20),
+        );
+        let delegation = Delegation::new(&delegate, &scope, &test_signature());
+        let call = ZomeFnCall::new_delegated(&delegation, "zome", "cap", "fn", "{}");
+        let record = CallRecord::new(
+            call.clone(),
+            Ok("result".to_string()),
+            7,
+            Duration::from_secs(10),
+        );
+
+        let replay = record.replay_call();
+        assert!(replay.same_fn_as(&call));
+        assert_eq!(call.parameters, replay.parameters);
+        assert_eq!(Some(delegation), replay.delegation);
+        assert_ne!(call, replay);
+    }
+}
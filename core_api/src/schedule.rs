@@ -0,0 +1,97 @@
+//! When the zome functions the DNA schedules are due, as told by the clock of the instance.
+//!
+//! A started instance checks the schedule on a background thread and dispatches a call of
+//! each function that is due. Runs missed while the instance was paused or the clock jumped
+//! ahead are collapsed into one, like a cron job that wasn't running.
+
+use holochain_dna::schedule::ScheduledFunction;
+use std::time::Duration;
+
+/// The scheduled functions with the time each is due next
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schedule {
+    functions: Vec<(ScheduledFunction, Duration)>,
+}
+
+impl Schedule {
+    /// each function is first due one interval after now
+    /// functions with an interval of zero are left out, they would be due all the time
+    pub fn new(functions: Vec<ScheduledFunction>, now: Duration) -> Self {
+        Schedule {
+            functions: functions
+                .into_iter()
+                .filter(|function| function.interval > 0)
+                .map(|function| {
+                    let due = now + Duration::from_secs(function.interval);
+                    (function, due)
+                })
+                .collect(),
+        }
+    }
+
+    /// the functions due at the time, once each however many runs they missed, in the order
+    /// they are declared; they are due again one interval after the last run they missed
+    pub fn take_due(&mut self, now: Duration) -> Vec<ScheduledFunction> {
+        let mut due_functions = Vec::new();
+        for (function, due) in self.functions.iter_mut() {
+            if now < *due {
+                continue;
+            }
+            let interval = Duration::from_secs(function.interval);
+            while *due <= now {
+                *due += interval;
+            }
+            due_functions.push(function.clone());
+        }
+        due_functions
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn scheduled(function: &str, interval: u64) -> ScheduledFunction {
+        ScheduledFunction {
+            zome: "zome".to_string(),
+            capability: "cap".to_string(),
+            function: function.to_string(),
+            interval,
+        }
+    }
+
+    #[test]
+    /// functions are due every interval, missed runs are collapsed
+    fn functions_are_due_every_interval() {
+        let start = Duration::from_secs(100);
+        let mut schedule = Schedule::new(
+            vec![
+                scheduled("often", 10),
+                scheduled("rarely", 30),
+                scheduled("never", 0),
+            ],
+            start,
+        );
+        let due_names = |schedule: &mut Schedule, seconds: u64| -> Vec<String> {
+            schedule
+                .take_due(start + Duration::from_secs(seconds))
+                .into_iter()
+                .map(|function| function.function)
+                .collect()
+        };
+
+        assert!(due_names(&mut schedule, 9).is_empty());
+        assert_eq!(vec!["often"], due_names(&mut schedule, 10));
+        assert!(due_names(&mut schedule, 15).is_empty());
+        assert_eq!(vec!["often", "rarely"], due_names(&mut schedule, 30));
+        // the runs of "often" at 40 and 50 were missed, it runs once and is due again at 60
+        assert_eq!(vec!["often"], due_names(&mut schedule, 55));
+        assert!(due_names(&mut schedule, 59).is_empty());
+        assert_eq!(vec!["often", "rarely"], due_names(&mut schedule, 60));
+        assert!(Schedule::new(vec![scheduled("never", 0)], start).is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+//! Coalescing of state updates for consumers slower than the instance, e.g. UIs.
+//!
+//! States are handed to a dedicated thread. The first state after a quiet period opens a
+//! window; states arriving within the window replace each other and the callback only gets
+//! the latest one when the window closes. The last state sent is therefore always delivered.
+//!
+//! Tails stream every reduced action instead, for live debugging. The event loop never waits
+//! for their consumer: actions it hasn't taken yet are buffered according to a TailPolicy.
+
+use futures::{
+    channel::mpsc::{unbounded, UnboundedReceiver},
+    never::Never,
+    task, Async, Poll, Stream,
+};
+use holochain_core::{action::ActionWrapper, state::State};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+pub type StateCallback = Box<Fn(&State) + Send>;
+
+/// Spawns the thread calling the callback at most once per window with the latest state
+/// The thread ends once the returned sender is dropped and the last state is delivered.
+pub fn coalesce(window: Duration, callback: StateCallback) -> Sender<State> {
+    let (sender, receiver) = channel::<State>();
+
+    thread::spawn(move || {
+        while let Ok(mut latest) = receiver.recv() {
+            let window_end = Instant::now() + window;
+            loop {
+                let now = Instant::now();
+                if now >= window_end {
+                    break;
+                }
+                match receiver.recv_timeout(window_end - now) {
+                    Ok(state) => latest = state,
+                    Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            callback(&latest);
+        }
+    });
+
+    sender
+}
+
+/// What happens to the actions a tail's consumer hasn't taken yet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TailPolicy {
+    /// buffer up to the given number of actions, newer ones are dropped until the consumer
+    /// catches up, @see ActionTail::dropped()
+    DropNewest(usize),
+    /// buffer all of them, however far the consumer falls behind
+    Unbounded,
+}
+
+/// Live stream of the actions reduced since it was created, in the order they were reduced
+/// @see Holochain::tail_actions()
+pub struct ActionTail {
+    receiver: UnboundedReceiver<ActionWrapper>,
+    pending: Arc<AtomicUsize>,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl ActionTail {
+    /// the number of actions left out so far because the consumer fell behind
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::SeqCst)
+    }
+}
+
+impl Stream for ActionTail {
+    type Item = ActionWrapper;
+    type Error = Never;
+
+    fn poll_next(&mut self, cx: &mut task::Context) -> Poll<Option<ActionWrapper>, Never> {
+        let next = self.receiver.poll_next(cx)?;
+        if let Async::Ready(Some(_)) = next {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+        }
+        Ok(next)
+    }
+}
+
+/// Creates a tail along with the callback feeding it, to be subscribed to reduced actions
+/// The callback never blocks. Once the tail is dropped it discards the actions.
+pub fn tail(policy: TailPolicy) -> (impl Fn(&ActionWrapper) + Send, ActionTail) {
+    let (sender, receiver) = unbounded();
+    let pending = Arc::new(AtomicUsize::new(0));
+    let dropped = Arc::new(AtomicUsize::new(0));
+    let action_tail = ActionTail {
+        receiver,
+        pending: pending.clone(),
+        dropped: dropped.clone(),
+    };
+    let feed = move |action_wrapper: &ActionWrapper| {
+        if let TailPolicy::DropNewest(capacity) = policy {
+            if pending.load(Ordering::SeqCst) >= capacity {
+                dropped.fetch_add(1, Ordering::SeqCst);
+                return;
+            }
+        }
+        pending.fetch_add(1, Ordering::SeqCst);
+        // fails only once the tail is dropped, nobody is left to miss the action then
+        let _ = sender.unbounded_send(action_wrapper.clone());
+    };
+    (feed, action_tail)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use futures::{executor::block_on, StreamExt};
+    use holochain_agent::Agent;
+    use holochain_core::{
+        action::{Action, ActionWrapper},
+        context::Context,
+        persister::SimplePersister,
+    };
+    use holochain_core_types::entry::test_entry_address;
+    use std::sync::Mutex;
+    use test_utils;
+
+    #[test]
+    /// a burst of states within the window results in a single callback with the last one
+    fn burst_is_coalesced_into_final_state() {
+        let context = Arc::new(Context::new(
+            Agent::from("bob".to_string()),
+            test_utils::test_logger(),
+            Arc::new(Mutex::new(SimplePersister::new())),
+        ));
+        let mut states = vec![State::new()];
+        for _ in 0..10 {
+            let next = states.last().unwrap().reduce(
+                context.clone(),
+                ActionWrapper::new(Action::GetEntry(test_entry_address())),
+            );
+            states.push(next);
+        }
+
+        let (sender, receiver) = channel();
+        let coalescer = coalesce(
+            Duration::from_millis(500),
+            Box::new(move |state: &State| sender.send(state.history_cursor()).unwrap()),
+        );
+        for state in states.iter() {
+            coalescer.send(state.clone()).unwrap();
+        }
+        drop(coalescer);
+
+        let delivered: Vec<_> = receiver.iter().collect();
+        assert_eq!(vec![states.last().unwrap().history_cursor()], delivered);
+    }
+
+    #[test]
+    /// a full tail drops newer actions and takes them again once the consumer caught up
+    fn full_tail_drops_newest_actions() {
+        let (feed, action_tail) = tail(TailPolicy::DropNewest(2));
+        let actions: Vec<_> = (0..3)
+            .map(|_| ActionWrapper::new(Action::GetEntry(test_entry_address())))
+            .collect();
+        for action_wrapper in actions.iter() {
+            feed(action_wrapper);
+        }
+        assert_eq!(1, action_tail.dropped());
+
+        let (first, action_tail) = block_on(action_tail.next()).ok().unwrap();
+        assert_eq!(Some(actions[0].clone()), first);
+        feed(&actions[2]);
+        assert_eq!(1, action_tail.dropped());
+
+        let (second, action_tail) = block_on(action_tail.next()).ok().unwrap();
+        let (third, _) = block_on(action_tail.next()).ok().unwrap();
+        assert_eq!(Some(actions[1].clone()), second);
+        assert_eq!(Some(actions[2].clone()), third);
+    }
+}
@@ -24,6 +24,8 @@ fn hdk_commit(mem_stack: &mut SinglePageStack, entry_type_name: &str, entry_valu
   let input = CommitEntryArgs {
     entry_type_name: entry_type_name.to_owned(),
     entry_value: entry_value.to_owned(),
+    content_type: None,
+    acl: None,
   };
   let maybe_allocation =  store_as_json(mem_stack, input);
   if let Err(return_code) = maybe_allocation {
@@ -28,6 +28,20 @@ pub struct ChainHeader {
     link_same_type: Option<Address>,
     /// ISO8601 time stamp
     timestamp: Iso8601,
+    /// MIME-like type of the entry's content, e.g. "text/markdown"
+    /// not serialized when None so headers without it keep their address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    /// agent on whose behalf the entry was committed, if any
+    /// not serialized when None so headers without it keep their address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    delegate: Option<String>,
+    /// what the author allowed the delegate, as the JSON it signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    delegation_scope: Option<String>,
+    /// the author's signature allowing the delegate to commit on its behalf
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    delegation_proof: Option<Signature>,
 }
 
 impl PartialEq for ChainHeader {
@@ -60,6 +74,33 @@ impl ChainHeader {
             link: link.to_owned(),
             link_same_type: link_same_type.to_owned(),
             timestamp: timestamp.to_owned(),
+            content_type: None,
+            delegate: None,
+            delegation_scope: None,
+            delegation_proof: None,
+        }
+    }
+
+    /// the same header declaring the content type of its entry
+    pub fn with_content_type(self, content_type: Option<String>) -> Self {
+        ChainHeader {
+            content_type,
+            ..self
+        }
+    }
+
+    /// the same header attributing its entry to the delegate, with the scope of the delegation
+    /// and the author's proof that it allowed the delegate to commit on its behalf
+    pub fn with_delegation(self, delegation: Option<(String, String, Signature)>) -> Self {
+        let (delegate, delegation_scope, delegation_proof) = match delegation {
+            Some((delegate, scope, proof)) => (Some(delegate), Some(scope), Some(proof)),
+            None => (None, None, None),
+        };
+        ChainHeader {
+            delegate,
+            delegation_scope,
+            delegation_proof,
+            ..self
         }
     }
 
@@ -96,6 +137,26 @@ impl ChainHeader {
     pub fn entry_signature(&self) -> &Signature {
         &self.entry_signature
     }
+
+    /// content_type getter
+    pub fn content_type(&self) -> Option<String> {
+        self.content_type.clone()
+    }
+
+    /// delegate getter
+    pub fn delegate(&self) -> Option<String> {
+        self.delegate.clone()
+    }
+
+    /// delegation_scope getter
+    pub fn delegation_scope(&self) -> Option<String> {
+        self.delegation_scope.clone()
+    }
+
+    /// delegation_proof getter
+    pub fn delegation_proof(&self) -> Option<Signature> {
+        self.delegation_proof.clone()
+    }
 }
 
 impl ToJson for ChainHeader {
@@ -264,6 +325,45 @@ pub mod tests {
         assert_eq!(Some(chain_header_a.address()), chain_header_b.link());
     }
 
+    #[test]
+    /// tests for header.content_type()
+    fn content_type_test() {
+        assert_eq!(None, test_chain_header().content_type());
+
+        let chain_header = test_chain_header().with_content_type(Some("text/plain".to_string()));
+        assert_eq!(Some("text/plain".to_string()), chain_header.content_type());
+        // the content type is part of the header address
+        assert_ne!(test_chain_header().address(), chain_header.address());
+        // headers without a content type keep their address
+        assert_eq!(
+            test_chain_header().address(),
+            test_chain_header().with_content_type(None).address()
+        );
+    }
+
+    #[test]
+    /// tests for header.delegate(), header.delegation_scope() and header.delegation_proof()
+    fn delegation_test() {
+        assert_eq!(None, test_chain_header().delegate());
+        assert_eq!(None, test_chain_header().delegation_scope());
+        assert_eq!(None, test_chain_header().delegation_proof());
+
+        let chain_header = test_chain_header().with_delegation(Some((
+            "bob".to_string(),
+            "{}".to_string(),
+            test_signature_b(),
+        )));
+        assert_eq!(Some("bob".to_string()), chain_header.delegate());
+        assert_eq!(Some("{}".to_string()), chain_header.delegation_scope());
+        assert_eq!(Some(test_signature_b()), chain_header.delegation_proof());
+        // the delegation is part of the header address
+        assert_ne!(test_chain_header().address(), chain_header.address());
+        assert_eq!(
+            test_chain_header().address(),
+            test_chain_header().with_delegation(None).address()
+        );
+    }
+
     #[test]
     fn entry_test() {
         assert_eq!(test_chain_header().entry_address(), &test_entry().address());
@@ -84,6 +84,36 @@ impl EntityAttributeValue {
     }
 }
 
+/// Constraints of an EAV query, None = no constraint, @see EntityAttributeValueStorage::fetch_eav
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EavQuery {
+    pub entity: Option<Entity>,
+    pub attribute: Option<Attribute>,
+    pub value: Option<Value>,
+}
+
+/// How a storage answers an EAV query, e.g. to find out why link queries are slow
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryPlan {
+    /// the index the EAVs are looked up in, None for a full scan
+    pub index: Option<String>,
+    /// number of EAVs looked at to answer the query
+    pub estimated_rows: usize,
+    /// whether every EAV in the storage is looked at
+    pub full_scan: bool,
+}
+
+impl QueryPlan {
+    /// the plan of a query looking at all of the given number of EAVs
+    pub fn full_scan(rows: usize) -> Self {
+        QueryPlan {
+            index: None,
+            estimated_rows: rows,
+            full_scan: true,
+        }
+    }
+}
+
 /// eav storage
 /// does NOT provide storage for AddressableContent
 /// use cas::storage::ContentAddressableStorage to store AddressableContent
@@ -105,6 +135,23 @@ pub trait EntityAttributeValueStorage: Clone {
         attribute: Option<Attribute>,
         value: Option<Value>,
     ) -> Result<HashSet<EntityAttributeValue>, HolochainError>;
+    /// how fetch_eav() answers the query
+    /// storages without indexes scan everything they hold
+    fn explain_eav(&self, _query: &EavQuery) -> Result<QueryPlan, HolochainError> {
+        Ok(QueryPlan::full_scan(
+            self.fetch_eav(None, None, None)?.len(),
+        ))
+    }
+    /// keeps an index of the EAVs with the attribute, so queries constrained to it don't scan
+    /// everything; storages without indexes ignore it
+    fn add_attribute_index(&mut self, _attribute: &Attribute) -> Result<(), HolochainError> {
+        Ok(())
+    }
+    /// takes the given EntityAttributeValue out of the storage again
+    /// the one exception to append only, for restoring snapshots
+    fn remove_eav(&mut self, _eav: &EntityAttributeValue) -> Result<(), HolochainError> {
+        Err(HolochainError::NotImplemented)
+    }
 }
 
 pub struct ExampleEntityAttributeValueStorageNonSync {
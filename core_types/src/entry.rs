@@ -1,11 +1,15 @@
 use cas::content::{Address, AddressableContent, Content};
+use chain_header::ChainHeader;
+use crud_status::CrudStatus;
 use entry_type::{
     test_entry_type, test_entry_type_b, test_sys_entry_type, test_unpublishable_entry_type,
     EntryType,
 };
 use error::HolochainError;
 use json::{FromJson, ToJson};
+use multihash::Hash;
 use serde_json;
+use signature::Signature;
 use snowflake;
 use std::ops::Deref;
 
@@ -15,6 +19,14 @@ use std::ops::Deref;
 pub struct Entry {
     value: String,
     entry_type: EntryType,
+    /// random data addressed along with the value, so equal values get unguessable addresses
+    /// not serialized when None so the addresses of entries without a nonce are unchanged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>,
+    /// what the address is derived from instead of the whole content, e.g. a username
+    /// @see Entry::derived_address()
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    address_key: Option<String>,
 }
 
 impl Entry {
@@ -25,6 +37,35 @@ impl Entry {
     pub fn entry_type(&self) -> &EntryType {
         &self.entry_type
     }
+
+    pub fn nonce(&self) -> Option<&String> {
+        self.nonce.as_ref()
+    }
+
+    /// the same entry addressed with the nonce
+    pub fn with_nonce(mut self, nonce: &str) -> Entry {
+        self.nonce = Some(nonce.to_string());
+        self
+    }
+
+    pub fn address_key(&self) -> Option<&String> {
+        self.address_key.as_ref()
+    }
+
+    /// the same entry addressed by the key alone instead of its whole content
+    pub fn with_address_key(mut self, address_key: &str) -> Entry {
+        self.address_key = Some(address_key.to_string());
+        self
+    }
+
+    /// the address of the entries of the type with the address key, whatever their value
+    /// namespaced by the type so keys of different types can't collide
+    pub fn derived_address(entry_type: &EntryType, address_key: &str) -> Address {
+        Address::encode_from_str(
+            &format!("{}:{}", entry_type.to_string(), address_key),
+            Hash::SHA2256,
+        )
+    }
 }
 
 pub trait ToEntry {
@@ -51,13 +92,20 @@ impl From<Entry> for String {
 }
 
 impl AddressableContent for Entry {
+    fn address(&self) -> Address {
+        match self.address_key {
+            Some(ref address_key) => Entry::derived_address(&self.entry_type, address_key),
+            None => Address::encode_from_str(&self.content(), Hash::SHA2256),
+        }
+    }
+
     fn content(&self) -> Content {
         self.to_json()
             .expect("could not convert Entry to Json Content")
     }
 
     fn from_content(content: &Content) -> Self {
-        Entry::from_json(&content.to_string()).expect("could not convert Json Content to Entry")
+        Entry::try_from_content(content).expect("could not convert Json Content to Entry")
     }
 }
 
@@ -66,8 +114,16 @@ impl Entry {
         Entry {
             entry_type: entry_type.to_owned(),
             value: value.to_owned(),
+            nonce: None,
+            address_key: None,
         }
     }
+
+    /// like from_content() but failing instead of panicking on content that isn't an entry,
+    /// e.g. content received from peers
+    pub fn try_from_content(content: &Content) -> Result<Entry, HolochainError> {
+        Entry::from_json(&content.to_string())
+    }
 }
 
 impl ToJson for Entry {
@@ -94,6 +150,16 @@ impl Deref for Entry {
     }
 }
 
+/// An entry together with its provenance: the header it was committed with, the signature of
+/// the entry and its CRUD status
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntryWithMeta {
+    pub entry: Entry,
+    pub header: ChainHeader,
+    pub signature: Signature,
+    pub crud_status: CrudStatus,
+}
+
 /// dummy entry value
 #[cfg_attr(tarpaulin, skip)]
 pub fn test_entry_value() -> String {
@@ -242,6 +308,41 @@ pub mod tests {
         );
     }
 
+    #[test]
+    /// a nonce changes the address but not the value, and survives a JSON round trip
+    fn nonce_test() {
+        let nonced = test_entry().with_nonce("1f2e3d4c");
+        assert_eq!(test_entry().value(), nonced.value());
+        assert_eq!(Some(&"1f2e3d4c".to_string()), nonced.nonce());
+        assert_ne!(test_entry().address(), nonced.address());
+        assert_ne!(
+            nonced.address(),
+            test_entry().with_nonce("5b6a7988").address()
+        );
+        assert_eq!(None, test_entry().nonce());
+        let round_tripped = Entry::from_json(&nonced.to_json().unwrap()).unwrap();
+        assert_eq!(Some(&"1f2e3d4c".to_string()), round_tripped.nonce());
+        assert_eq!(nonced.address(), round_tripped.address());
+    }
+
+    #[test]
+    /// entries with an address key are addressed by it and their type alone
+    fn address_key_test() {
+        let keyed = test_entry().with_address_key("alice");
+        let derived = Entry::derived_address(&test_entry_type(), "alice");
+        assert_eq!(derived, keyed.address());
+        let other_value = Entry::new(&test_entry_type(), &test_entry_value_b());
+        assert_eq!(derived, other_value.with_address_key("alice").address());
+        assert_ne!(
+            derived,
+            Entry::derived_address(&test_entry_type_b(), "alice")
+        );
+        assert_eq!(None, test_entry().address_key());
+        let round_tripped = Entry::from_json(&keyed.to_json().unwrap()).unwrap();
+        assert_eq!(Some(&"alice".to_string()), round_tripped.address_key());
+        assert_eq!(derived, round_tripped.address());
+    }
+
     #[test]
     /// show AddressableContent implementation
     fn addressable_content_test() {
@@ -1,10 +1,12 @@
 use self::HolochainError::*;
 use futures::channel::oneshot::Canceled as FutureCanceled;
 use json::ToJson;
-use serde_json::Error as SerdeError;
+use serde_json::{self, Error as SerdeError, Value};
 use std::{
+    any::Any,
     error::Error,
     fmt,
+    hash::{Hash, Hasher},
     io::{self, Error as IoError},
 };
 
@@ -23,6 +25,45 @@ pub enum HolochainError {
     InvalidOperationOnSysEntry,
     DoesNotHaveCapabilityToken,
     ValidationFailed(String),
+    InvalidDelegationProof,
+    DelegationOutOfScope,
+    DelegationExpired,
+    InstancePaused,
+    InstanceNotPaused,
+    LinkLimitExceeded,
+    InvalidAddress(String),
+    PinBudgetExceeded,
+    ZomeCallFailed(ZomeCallError),
+    ReadOnlyInstance,
+    InconsistentStorage(String),
+    HistoryUnavailable,
+    AccessDenied,
+    MemoryLimitExceeded,
+    InvalidChainExport(String),
+    FunctionDisabled(String),
+    NetworkUnreachable,
+    InvalidDhtExport(String),
+    UnknownCheckpoint,
+    CheckpointUnsupported(String),
+    UnknownKey(String),
+    UniquenessViolation {
+        field: String,
+        value: String,
+    },
+    ChainRateLimited,
+    CallRateLimited(String),
+    InitializationCancelled,
+    DeadlineExceeded,
+    CallTimedOut,
+    AddressDerivationFailed(String),
+    AddressTaken,
+    InvalidMetadataSignature,
+    InvalidKeyRotation,
+    StorageAlreadyRouted,
+    StorageKeyMissing,
+    /// a typed error of an extension, e.g. a call middleware
+    /// @see HolochainError::custom()
+    Custom(Box<CustomError>),
 }
 
 pub type HcResult<T> = Result<T, HolochainError>;
@@ -31,6 +72,77 @@ impl HolochainError {
     pub fn new(msg: &str) -> HolochainError {
         HolochainError::ErrorGeneric(msg.to_string())
     }
+
+    /// wraps the error of an extension, so it keeps its type up to the caller
+    pub fn custom<E: CustomError>(error: E) -> HolochainError {
+        HolochainError::Custom(Box::new(error))
+    }
+
+    /// the error of an extension this error wraps, None if it wraps none or one of another type
+    pub fn downcast_custom<E: CustomError>(&self) -> Option<&E> {
+        match self {
+            Custom(error) => (**error).as_any().downcast_ref::<E>(),
+            _ => None,
+        }
+    }
+}
+
+/// An error type extensions can return through HolochainError::Custom
+/// implemented by every error type that is Clone, PartialEq and Hash, so HolochainError keeps
+/// those traits
+pub trait CustomError: Error + Send + Sync + 'static {
+    fn clone_box(&self) -> Box<CustomError>;
+
+    fn as_any(&self) -> &Any;
+
+    fn eq_box(&self, other: &CustomError) -> bool;
+
+    fn hash_box(&self, state: &mut Hasher);
+}
+
+impl<E> CustomError for E
+where
+    E: Error + Clone + PartialEq + Hash + Send + Sync + 'static,
+{
+    fn clone_box(&self) -> Box<CustomError> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    /// errors of different types are never equal
+    fn eq_box(&self, other: &CustomError) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<E>()
+            .map_or(false, |other| self == other)
+    }
+
+    fn hash_box(&self, mut state: &mut Hasher) {
+        self.hash(&mut state)
+    }
+}
+
+impl Clone for Box<CustomError> {
+    fn clone(&self) -> Self {
+        (**self).clone_box()
+    }
+}
+
+impl PartialEq for CustomError {
+    fn eq(&self, other: &CustomError) -> bool {
+        self.eq_box(other)
+    }
+}
+
+impl Eq for CustomError {}
+
+impl Hash for CustomError {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash_box(state)
+    }
 }
 
 impl ToJson for HolochainError {
@@ -64,8 +176,89 @@ impl Error for HolochainError {
             InvalidOperationOnSysEntry => "operation cannot be done on a system entry type",
             DoesNotHaveCapabilityToken => "Caller does not have Capability to make that call",
             ValidationFailed(fail_msg) => &fail_msg,
+            InvalidDelegationProof => "the delegation proof is not valid for this agent",
+            DelegationOutOfScope => "the delegation doesn't allow this call",
+            DelegationExpired => "the delegation has expired",
+            InstancePaused => "the instance is paused",
+            InstanceNotPaused => "the instance is not paused",
+            LinkLimitExceeded => "the base already has the maximum number of links for this tag",
+            InvalidAddress(address) => &address,
+            PinBudgetExceeded => "the pin budget of the cache is used up",
+            ZomeCallFailed(error) => &error.reason,
+            ReadOnlyInstance => "the instance is a read replica and can't write",
+            InconsistentStorage(report) => &report,
+            HistoryUnavailable => "the state at this point of the history can't be reconstructed",
+            AccessDenied => "the agent is not on the access control list of the entry",
+            MemoryLimitExceeded => "the zome call used more memory than its limit",
+            InvalidChainExport(report) => &report,
+            FunctionDisabled(function) => &function,
+            NetworkUnreachable => "the network can't be reached",
+            InvalidDhtExport(report) => &report,
+            UnknownCheckpoint => "there is no checkpoint with this id",
+            CheckpointUnsupported(reason) => &reason,
+            UnknownKey(_) => "the keystore holds no key pair for this key",
+            UniquenessViolation { .. } => {
+                "another entry of this type has the same value in a unique field"
+            }
+            ChainRateLimited => "the agent committed as many entries as the rate limit allows",
+            CallRateLimited(capability) => &capability,
+            InitializationCancelled => "the initialization of the instance was cancelled",
+            DeadlineExceeded => "the action wasn't reduced by its deadline",
+            CallTimedOut => "the zome call didn't return in time",
+            AddressDerivationFailed(reason) => &reason,
+            AddressTaken => "another entry of this type has the address derived for this one",
+            InvalidMetadataSignature => "the metadata was not signed by its author",
+            InvalidKeyRotation => "the key rotation was not signed by the active key",
+            StorageAlreadyRouted => "the storage key has to be set before any storage is routed",
+            StorageKeyMissing => "the content is sealed and no storage key was given",
+            Custom(error) => error.description(),
+        }
+    }
+}
+
+/// What went wrong in a zome function call that trapped or returned an error
+#[derive(Clone, Debug, PartialEq, Hash, Eq)]
+pub struct ZomeCallError {
+    pub zome_name: String,
+    pub fn_name: String,
+    /// why the WASM execution trapped, None if the function returned an error
+    pub trap: Option<String>,
+    /// the trap, or the message of the error the function returned
+    pub reason: String,
+    /// what the function returned, or the interpreter error if it trapped
+    pub payload: String,
+}
+
+impl ZomeCallError {
+    /// the error of a call whose WASM execution trapped
+    pub fn trapped(zome_name: &str, fn_name: &str, trap: &str, payload: &str) -> Self {
+        ZomeCallError {
+            zome_name: zome_name.to_string(),
+            fn_name: fn_name.to_string(),
+            trap: Some(trap.to_string()),
+            reason: trap.to_string(),
+            payload: payload.to_string(),
         }
     }
+
+    /// the error returned by a call, if its result is a serialized Err, i.e. {"Err": reason}
+    pub fn returned(zome_name: &str, fn_name: &str, payload: &str) -> Option<Self> {
+        let reason = match serde_json::from_str(payload) {
+            Ok(Value::Object(ref fields)) if fields.len() == 1 => match fields.get("Err") {
+                Some(Value::String(reason)) => reason.clone(),
+                Some(reason) => reason.to_string(),
+                None => return None,
+            },
+            _ => return None,
+        };
+        Some(ZomeCallError {
+            zome_name: zome_name.to_string(),
+            fn_name: fn_name.to_string(),
+            trap: None,
+            reason,
+            payload: payload.to_string(),
+        })
+    }
 }
 
 /// standard strings for std io errors
@@ -100,6 +293,10 @@ pub enum DnaError {
     ZomeNotFound(String),
     CapabilityNotFound(String),
     ZomeFunctionNotFound(String),
+    /// a zome imports host functions whose results aren't reproducible, e.g. clocks
+    NondeterministicImports(String),
+    /// the arguments of a call don't fit the inputs of the function
+    InvalidCallParams(String),
 }
 
 impl Error for DnaError {
@@ -108,6 +305,8 @@ impl Error for DnaError {
             DnaError::ZomeNotFound(err_msg) => &err_msg,
             DnaError::CapabilityNotFound(err_msg) => &err_msg,
             DnaError::ZomeFunctionNotFound(err_msg) => &err_msg,
+            DnaError::NondeterministicImports(err_msg) => &err_msg,
+            DnaError::InvalidCallParams(err_msg) => &err_msg,
         }
     }
 }
@@ -200,6 +399,14 @@ mod tests {
                 HolochainError::DnaError(DnaError::ZomeFunctionNotFound(String::from("foo"))),
                 "foo",
             ),
+            (
+                HolochainError::DnaError(DnaError::NondeterministicImports(String::from("foo"))),
+                "foo",
+            ),
+            (
+                HolochainError::DnaError(DnaError::InvalidCallParams(String::from("foo"))),
+                "foo",
+            ),
             (HolochainError::IoError(String::from("foo")), "foo"),
             (
                 HolochainError::SerializationError(String::from("foo")),
@@ -213,8 +420,176 @@ mod tests {
                 HolochainError::DoesNotHaveCapabilityToken,
                 "Caller does not have Capability to make that call",
             ),
+            (
+                HolochainError::InvalidDelegationProof,
+                "the delegation proof is not valid for this agent",
+            ),
+            (
+                HolochainError::DelegationOutOfScope,
+                "the delegation doesn't allow this call",
+            ),
+            (
+                HolochainError::DelegationExpired,
+                "the delegation has expired",
+            ),
+            (HolochainError::InstancePaused, "the instance is paused"),
+            (
+                HolochainError::InstanceNotPaused,
+                "the instance is not paused",
+            ),
+            (
+                HolochainError::LinkLimitExceeded,
+                "the base already has the maximum number of links for this tag",
+            ),
+            (HolochainError::InvalidAddress("foo".to_string()), "foo"),
+            (
+                HolochainError::PinBudgetExceeded,
+                "the pin budget of the cache is used up",
+            ),
+            (
+                HolochainError::ZomeCallFailed(ZomeCallError::trapped(
+                    "zome",
+                    "fn",
+                    "unreachable",
+                    "Trap(Unreachable)",
+                )),
+                "unreachable",
+            ),
+            (
+                HolochainError::ReadOnlyInstance,
+                "the instance is a read replica and can't write",
+            ),
+            (
+                HolochainError::InconsistentStorage("foo".to_string()),
+                "foo",
+            ),
+            (
+                HolochainError::HistoryUnavailable,
+                "the state at this point of the history can't be reconstructed",
+            ),
+            (
+                HolochainError::AccessDenied,
+                "the agent is not on the access control list of the entry",
+            ),
+            (
+                HolochainError::MemoryLimitExceeded,
+                "the zome call used more memory than its limit",
+            ),
+            (HolochainError::InvalidChainExport("foo".to_string()), "foo"),
+            (HolochainError::FunctionDisabled("foo".to_string()), "foo"),
+            (
+                HolochainError::NetworkUnreachable,
+                "the network can't be reached",
+            ),
+            (HolochainError::InvalidDhtExport("foo".to_string()), "foo"),
+            (
+                HolochainError::UnknownCheckpoint,
+                "there is no checkpoint with this id",
+            ),
+            (
+                HolochainError::CheckpointUnsupported("foo".to_string()),
+                "foo",
+            ),
+            (
+                HolochainError::UnknownKey("foo".to_string()),
+                "the keystore holds no key pair for this key",
+            ),
+            (
+                HolochainError::UniquenessViolation {
+                    field: "name".to_string(),
+                    value: "alex".to_string(),
+                },
+                "another entry of this type has the same value in a unique field",
+            ),
+            (
+                HolochainError::ChainRateLimited,
+                "the agent committed as many entries as the rate limit allows",
+            ),
+            (HolochainError::CallRateLimited("foo".to_string()), "foo"),
+            (
+                HolochainError::InitializationCancelled,
+                "the initialization of the instance was cancelled",
+            ),
+            (
+                HolochainError::DeadlineExceeded,
+                "the action wasn't reduced by its deadline",
+            ),
+            (
+                HolochainError::CallTimedOut,
+                "the zome call didn't return in time",
+            ),
+            (
+                HolochainError::AddressDerivationFailed("foo".to_string()),
+                "foo",
+            ),
+            (
+                HolochainError::AddressTaken,
+                "another entry of this type has the address derived for this one",
+            ),
+            (
+                HolochainError::InvalidMetadataSignature,
+                "the metadata was not signed by its author",
+            ),
+            (
+                HolochainError::InvalidKeyRotation,
+                "the key rotation was not signed by the active key",
+            ),
+            (
+                HolochainError::StorageAlreadyRouted,
+                "the storage key has to be set before any storage is routed",
+            ),
+            (
+                HolochainError::StorageKeyMissing,
+                "the content is sealed and no storage key was given",
+            ),
+            (
+                HolochainError::custom(DnaError::ZomeNotFound("foo".to_string())),
+                "foo",
+            ),
         ] {
             assert_eq!(output, input.description());
         }
     }
+
+    #[test]
+    /// a custom error is only equal to and downcast as errors of its own type
+    fn custom_error_test() {
+        let error = HolochainError::custom(DnaError::ZomeNotFound("foo".to_string()));
+        assert_eq!(error, error.clone());
+        assert_eq!(
+            Some(&DnaError::ZomeNotFound("foo".to_string())),
+            error.downcast_custom::<DnaError>()
+        );
+        assert_eq!(None, error.downcast_custom::<HolochainError>());
+        assert_eq!(
+            None,
+            HolochainError::DnaMissing.downcast_custom::<DnaError>()
+        );
+        assert_ne!(error, HolochainError::custom(HolochainError::new("foo")));
+        assert_ne!(
+            error,
+            HolochainError::DnaError(DnaError::ZomeNotFound("foo".to_string()))
+        );
+    }
+
+    #[test]
+    /// only results that are a serialized Err are returned errors
+    fn zome_call_error_returned_test() {
+        let payload = "{\"Err\":\"Argument deserialization failed\"}";
+        assert_eq!(
+            Some(ZomeCallError {
+                zome_name: "zome".to_string(),
+                fn_name: "fn".to_string(),
+                trap: None,
+                reason: "Argument deserialization failed".to_string(),
+                payload: payload.to_string(),
+            }),
+            ZomeCallError::returned("zome", "fn", payload)
+        );
+        assert_eq!(
+            None,
+            ZomeCallError::returned("zome", "fn", "{\"Ok\":\"foo\"}")
+        );
+        assert_eq!(None, ZomeCallError::returned("zome", "fn", "not json"));
+    }
 }
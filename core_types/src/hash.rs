@@ -1,8 +1,20 @@
-use multihash::{encode, Hash};
-use rust_base58::ToBase58;
+use base64;
+use error::HolochainError;
+use hex;
+use multihash::{decode, encode, Hash};
+use rust_base58::{FromBase58, ToBase58};
 use serde::Serialize;
 use serde_json;
-use std::fmt;
+use std::{fmt, str::FromStr};
+
+/// Encodings a HashString can be presented in
+/// HashStrings are stored and displayed as base58, the others are for tools
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HashEncoding {
+    Base58,
+    Base64,
+    Hex,
+}
 
 // HashString newtype for String
 #[derive(PartialOrd, PartialEq, Eq, Ord, Clone, Debug, Serialize, Deserialize, Default, Hash)]
@@ -51,6 +63,42 @@ impl HashString {
     pub fn encode_from_serializable<S: Serialize>(s: S, hash_type: Hash) -> HashString {
         HashString::encode_from_str(&serde_json::to_string(&s).unwrap(), hash_type)
     }
+
+    /// parse a multihash presented in the given encoding
+    /// fails if the string is not a valid multihash in that encoding
+    pub fn decode_from(s: &str, encoding: HashEncoding) -> Result<HashString, HolochainError> {
+        let invalid = || HolochainError::InvalidAddress(format!("invalid address: {}", s));
+        let bytes = match encoding {
+            HashEncoding::Base58 => s.from_base58().map_err(|_| invalid())?,
+            HashEncoding::Base64 => base64::decode(s).map_err(|_| invalid())?,
+            HashEncoding::Hex => hex::decode(s).map_err(|_| invalid())?,
+        };
+        decode(&bytes).map_err(|_| invalid())?;
+        Ok(HashString(bytes.to_base58()))
+    }
+
+    /// present the multihash in the given encoding
+    /// fails if this HashString is not a valid base58 multihash
+    pub fn encode_to(&self, encoding: HashEncoding) -> Result<String, HolochainError> {
+        let bytes = self
+            .0
+            .from_base58()
+            .map_err(|_| HolochainError::InvalidAddress(format!("invalid address: {}", self)))?;
+        Ok(match encoding {
+            HashEncoding::Base58 => self.0.clone(),
+            HashEncoding::Base64 => base64::encode(&bytes),
+            HashEncoding::Hex => hex::encode(&bytes),
+        })
+    }
+}
+
+/// validated parsing of base58 multihashes, unlike From<String>
+impl FromStr for HashString {
+    type Err = HolochainError;
+
+    fn from_str(s: &str) -> Result<HashString, HolochainError> {
+        HashString::decode_from(s, HashEncoding::Base58)
+    }
 }
 
 #[cfg(test)]
@@ -91,6 +139,44 @@ pub mod tests {
         assert_eq!(test_hash(), HashString::from(test_entry().address()),);
     }
 
+    #[test]
+    /// show FromStr implementation
+    fn parse_test() {
+        assert_eq!(
+            Ok(test_hash()),
+            test_hash().to_string().parse::<HashString>()
+        );
+        assert!("xxxxxxxxx".parse::<HashString>().is_err());
+        assert!("".parse::<HashString>().is_err());
+        // valid base58 but not a multihash
+        assert!("Qm".parse::<HashString>().is_err());
+    }
+
+    #[test]
+    /// addresses round trip through every encoding
+    fn encoding_round_trip_test() {
+        for encoding in vec![
+            HashEncoding::Base58,
+            HashEncoding::Base64,
+            HashEncoding::Hex,
+        ] {
+            let encoded = test_hash()
+                .encode_to(encoding)
+                .expect("test hash should be a valid multihash");
+            assert_eq!(Ok(test_hash()), HashString::decode_from(&encoded, encoding));
+        }
+        assert_eq!(
+            Ok("1220".to_string()),
+            test_hash()
+                .encode_to(HashEncoding::Hex)
+                .map(|hex| hex[..4].to_string())
+        );
+        assert!(HashString::decode_from("not hex", HashEncoding::Hex).is_err());
+        assert!(HashString::from("xxxxxxxxx")
+            .encode_to(HashEncoding::Hex)
+            .is_err());
+    }
+
     #[test]
     /// mimics tests from legacy golang holochain core hashing bytes
     fn bytes_to_b58_known_golang() {
@@ -1,4 +1,5 @@
 use error::HolochainError;
+use serde_json::{self, Value};
 
 pub trait ToJson {
     /// serialize self to a canonical JSON string
@@ -14,3 +15,55 @@ where
 }
 
 pub trait RoundTripJson: ToJson + FromJson {}
+
+/// the JSON object or array in the content, compact and with the keys of all its objects sorted
+/// so logically identical content is the same bytes, and address, whatever order its keys were
+/// put in; content that isn't a JSON object or array is returned as it is
+pub fn sort_json_keys(content: &str) -> String {
+    let trimmed = content.trim();
+    if !trimmed.starts_with('{') && !trimmed.starts_with('[') {
+        return content.to_string();
+    }
+    serde_json::from_str::<Value>(content)
+        .ok()
+        .and_then(|value| serde_json::to_string(&sorted_keys(value)).ok())
+        .unwrap_or_else(|| content.to_string())
+}
+
+fn sorted_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut fields: Vec<(String, Value)> = map.into_iter().collect();
+            fields.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| (key, sorted_keys(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sorted_keys).collect()),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    /// keys are sorted at every level, other content is left alone
+    fn sort_json_keys_test() {
+        assert_eq!(
+            r#"{"a":[{"x":1,"y":2}],"b":{"c":true,"d":null}}"#,
+            sort_json_keys(r#"{ "b": {"d": null, "c": true}, "a": [{"y": 2, "x": 1}] }"#)
+        );
+        assert_eq!(
+            r#"[2,{"a":1,"b":0}]"#,
+            sort_json_keys(r#"[2, {"b": 0, "a": 1}]"#)
+        );
+        assert_eq!("plain text", sort_json_keys("plain text"));
+        assert_eq!("{not json", sort_json_keys("{not json"));
+        assert_eq!("42", sort_json_keys("42"));
+    }
+}
@@ -8,7 +8,9 @@
 //! out into their separate crate as well since those are generic and not
 //! necessarily bound to Holochain.
 
+extern crate base64;
 extern crate futures;
+extern crate hex;
 extern crate multihash;
 extern crate rust_base58;
 extern crate serde;
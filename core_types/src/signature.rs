@@ -1,4 +1,4 @@
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Signature(String);
 
 impl From<&'static str> for Signature {
@@ -7,6 +7,18 @@ impl From<&'static str> for Signature {
     }
 }
 
+impl From<String> for Signature {
+    fn from(s: String) -> Signature {
+        Signature(s)
+    }
+}
+
+impl ToString for Signature {
+    fn to_string(&self) -> String {
+        self.0.clone()
+    }
+}
+
 pub fn test_signature() -> Signature {
     Signature::from("fake-signature")
 }
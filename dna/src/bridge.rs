@@ -0,0 +1,53 @@
+//! holochain_dna::bridge holds the declarations of the other instances a DNA calls into.
+
+/// Represents an individual object in the top-level "bridges" array.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash)]
+pub struct Bridge {
+    /// The name zomes use to call through this bridge.
+    #[serde(default)]
+    pub handle: String,
+
+    /// The id of the instance calls through this bridge go to, as registered in the container.
+    #[serde(default)]
+    pub target: String,
+}
+
+impl Default for Bridge {
+    /// Provide defaults for a "bridges" object.
+    fn default() -> Self {
+        Bridge {
+            handle: String::new(),
+            target: String::new(),
+        }
+    }
+}
+
+impl Bridge {
+    /// Allow sane defaults for `Bridge::new()`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn build_and_compare() {
+        let fixture: Bridge = serde_json::from_str(
+            r#"{
+                "handle": "accounts",
+                "target": "accounts-instance"
+            }"#,
+        )
+        .unwrap();
+
+        let mut bridge = Bridge::new();
+        bridge.handle = String::from("accounts");
+        bridge.target = String::from("accounts-instance");
+
+        assert_eq!(fixture, bridge);
+    }
+}
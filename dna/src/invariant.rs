@@ -0,0 +1,73 @@
+//! holochain_dna::invariant holds the declarations of the rules spanning several entries that
+//! every commit has to keep, e.g. "the balances add up to zero".
+
+/// Represents an individual object in the top-level "invariants" array.
+/// The function is called after each commit of the entry types it watches, with the address
+/// of the committed entry. It reads what it needs from the store and returns nothing if the
+/// invariant holds, or why it doesn't, in which case the commit is rolled back.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash)]
+pub struct Invariant {
+    /// The zome the function is in.
+    #[serde(default)]
+    pub zome: String,
+
+    /// The name of the function checking the invariant.
+    #[serde(default)]
+    pub function: String,
+
+    /// The entry types whose commits the invariant is checked after, all app entry types
+    /// if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entry_types: Vec<String>,
+}
+
+impl Default for Invariant {
+    /// Provide defaults for an "invariants" object.
+    fn default() -> Self {
+        Invariant {
+            zome: String::new(),
+            function: String::new(),
+            entry_types: Vec::new(),
+        }
+    }
+}
+
+impl Invariant {
+    /// Allow sane defaults for `Invariant::new()`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// true if the invariant is checked after commits of the entry type
+    pub fn watches(&self, entry_type: &str) -> bool {
+        self.entry_types.is_empty() || self.entry_types.iter().any(|watched| watched == entry_type)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn build_and_compare() {
+        let fixture: Invariant = serde_json::from_str(
+            r#"{
+                "zome": "ledger",
+                "function": "balances_add_up",
+                "entry_types": ["transfer"]
+            }"#,
+        )
+        .unwrap();
+
+        let mut invariant = Invariant::new();
+        invariant.zome = String::from("ledger");
+        invariant.function = String::from("balances_add_up");
+        invariant.entry_types = vec![String::from("transfer")];
+
+        assert_eq!(fixture, invariant);
+        assert!(invariant.watches("transfer"));
+        assert!(!invariant.watches("account"));
+        assert!(Invariant::new().watches("account"));
+    }
+}
@@ -31,15 +31,21 @@ extern crate uuid;
 use serde_json::Value;
 use std::hash::{Hash, Hasher};
 
+pub mod bridge;
+pub mod invariant;
+pub mod schedule;
 pub mod wasm;
 pub mod zome;
 
+use bridge::Bridge;
 use holochain_core_types::{
     cas::content::AddressableContent,
     entry::{Entry, ToEntry},
     entry_type::EntryType,
     error::DnaError,
 };
+use invariant::Invariant;
+use schedule::ScheduledFunction;
 use std::collections::HashMap;
 use uuid::Uuid;
 use zome::{capabilities::Capability, entry_types::EntryTypeDef};
@@ -84,6 +90,21 @@ pub struct Dna {
     /// An array of zomes associated with your holochain application.
     #[serde(default)]
     pub zomes: HashMap<String, zome::Zome>,
+
+    /// Other instances the zomes of this application call into.
+    /// not serialized when empty so existing DNA JSON (and its address) is unchanged
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bridges: Vec<Bridge>,
+
+    /// Rules spanning several entries that every commit has to keep.
+    /// not serialized when empty so existing DNA JSON (and its address) is unchanged
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub invariants: Vec<Invariant>,
+
+    /// Zome functions a running instance calls periodically.
+    /// not serialized when empty so existing DNA JSON (and its address) is unchanged
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scheduled_functions: Vec<ScheduledFunction>,
 }
 
 impl Default for Dna {
@@ -97,6 +118,9 @@ impl Default for Dna {
             dna_spec_version: String::from("2.0"),
             properties: empty_object(),
             zomes: HashMap::new(),
+            bridges: Vec::new(),
+            invariants: Vec::new(),
+            scheduled_functions: Vec::new(),
         }
     }
 }
@@ -241,6 +265,31 @@ impl Dna {
         }
         None
     }
+
+    /// checks that no zome imports host functions whose results differ between runs,
+    /// @see wasm::NONDETERMINISTIC_IMPORTS
+    /// fails with NondeterministicImports listing the offending imports by zome
+    pub fn validate_determinism(&self) -> Result<(), DnaError> {
+        let mut zome_names: Vec<&String> = self.zomes.keys().collect();
+        zome_names.sort();
+        let offending: Vec<String> = zome_names
+            .into_iter()
+            .flat_map(|zome_name| {
+                self.zomes[zome_name]
+                    .code
+                    .nondeterministic_imports()
+                    .into_iter()
+                    .map(move |(module, field)| format!("{}: {}.{}", zome_name, module, field))
+            })
+            .collect();
+        if offending.is_empty() {
+            return Ok(());
+        }
+        Err(DnaError::NondeterministicImports(format!(
+            "zomes import non-deterministic host functions: {}",
+            offending.join(", ")
+        )))
+    }
 }
 
 impl Hash for Dna {
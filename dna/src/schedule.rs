@@ -0,0 +1,68 @@
+//! holochain_dna::schedule holds the declarations of the zome functions an instance calls
+//! periodically, e.g. for cleanup or aggregation.
+
+/// Represents an individual object in the top-level "scheduled_functions" array.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash)]
+pub struct ScheduledFunction {
+    /// The zome the function is in.
+    #[serde(default)]
+    pub zome: String,
+
+    /// The capability the function is called through.
+    #[serde(default)]
+    pub capability: String,
+
+    /// The name of the function, called without parameters.
+    #[serde(default)]
+    pub function: String,
+
+    /// Seconds between two calls, as told by the clock of the instance.
+    #[serde(default)]
+    pub interval: u64,
+}
+
+impl Default for ScheduledFunction {
+    /// Provide defaults for a "scheduled_functions" object.
+    fn default() -> Self {
+        ScheduledFunction {
+            zome: String::new(),
+            capability: String::new(),
+            function: String::new(),
+            interval: 0,
+        }
+    }
+}
+
+impl ScheduledFunction {
+    /// Allow sane defaults for `ScheduledFunction::new()`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn build_and_compare() {
+        let fixture: ScheduledFunction = serde_json::from_str(
+            r#"{
+                "zome": "posts",
+                "capability": "maintenance",
+                "function": "prune_drafts",
+                "interval": 3600
+            }"#,
+        )
+        .unwrap();
+
+        let mut scheduled = ScheduledFunction::new();
+        scheduled.zome = String::from("posts");
+        scheduled.capability = String::from("maintenance");
+        scheduled.function = String::from("prune_drafts");
+        scheduled.interval = 3600;
+
+        assert_eq!(fixture, scheduled);
+    }
+}
@@ -76,3 +76,192 @@ impl DnaWasm {
         Default::default()
     }
 }
+
+/// Host functions whose results differ between runs, by the name zomes import them with.
+/// Zomes get the time and random numbers through the context, e.g. hc_random, instead.
+pub const NONDETERMINISTIC_IMPORTS: &[&str] = &[
+    // WASI
+    "clock_time_get",
+    "clock_res_get",
+    "random_get",
+    // emscripten
+    "_emscripten_get_now",
+    "_clock",
+    "_clock_gettime",
+    "_gettimeofday",
+    "_time",
+    // AssemblyScript
+    "Date.now",
+    "seed",
+];
+
+/// id of the import section of a WebAssembly module
+const IMPORT_SECTION: u8 = 2;
+
+/// id of the export section of a WebAssembly module
+const EXPORT_SECTION: u8 = 7;
+
+/// reads an unsigned LEB128 number at the position and moves past it
+fn read_leb(code: &[u8], position: &mut usize) -> Option<u32> {
+    let mut value: u32 = 0;
+    for shift in (0..5).map(|byte| byte * 7) {
+        let byte = *code.get(*position)?;
+        *position += 1;
+        value |= u32::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// reads a length prefixed UTF-8 name at the position and moves past it
+fn read_name(code: &[u8], position: &mut usize) -> Option<String> {
+    let length = read_leb(code, position)? as usize;
+    let name = code.get(*position..*position + length)?;
+    *position += length;
+    String::from_utf8(name.to_vec()).ok()
+}
+
+/// moves past the limits of a table or memory import
+fn skip_limits(code: &[u8], position: &mut usize) -> Option<()> {
+    let has_maximum = read_leb(code, position)? & 1 == 1;
+    read_leb(code, position)?;
+    if has_maximum {
+        read_leb(code, position)?;
+    }
+    Some(())
+}
+
+/// the position of the content of the section with the id, None inside if there is none
+/// None if the code isn't a WebAssembly module
+fn find_section(code: &[u8], section_id: u8) -> Option<Option<usize>> {
+    if code.get(0..4)? != b"\0asm" {
+        return None;
+    }
+    let mut position = 8;
+    while position < code.len() {
+        let id = code[position];
+        position += 1;
+        let size = read_leb(code, &mut position)? as usize;
+        if id == section_id {
+            return Some(Some(position));
+        }
+        position += size;
+    }
+    Some(None)
+}
+
+impl DnaWasm {
+    /// the module and field name of every import of the module, in order
+    /// None if the code isn't a WebAssembly module
+    pub fn imports(&self) -> Option<Vec<(String, String)>> {
+        let code = &self.code;
+        let mut position = match find_section(code, IMPORT_SECTION)? {
+            Some(position) => position,
+            None => return Some(Vec::new()),
+        };
+        let mut imports = Vec::new();
+        for _ in 0..read_leb(code, &mut position)? {
+            let module = read_name(code, &mut position)?;
+            let field = read_name(code, &mut position)?;
+            let kind = *code.get(position)?;
+            position += 1;
+            match kind {
+                // function: type index
+                0 => {
+                    read_leb(code, &mut position)?;
+                }
+                // table: element type and limits
+                1 => {
+                    position += 1;
+                    skip_limits(code, &mut position)?;
+                }
+                // memory: limits
+                2 => skip_limits(code, &mut position)?,
+                // global: value type and mutability
+                3 => position += 2,
+                _ => return None,
+            }
+            imports.push((module, field));
+        }
+        Some(imports)
+    }
+
+    /// the name of every export of the module, e.g. its functions, in order
+    /// None if the code isn't a WebAssembly module
+    pub fn exports(&self) -> Option<Vec<String>> {
+        let code = &self.code;
+        let mut position = match find_section(code, EXPORT_SECTION)? {
+            Some(position) => position,
+            None => return Some(Vec::new()),
+        };
+        let mut exports = Vec::new();
+        for _ in 0..read_leb(code, &mut position)? {
+            exports.push(read_name(code, &mut position)?);
+            // kind and index
+            position += 1;
+            read_leb(code, &mut position)?;
+        }
+        Some(exports)
+    }
+
+    /// the imports of the module that are in NONDETERMINISTIC_IMPORTS
+    pub fn nondeterministic_imports(&self) -> Vec<(String, String)> {
+        self.imports()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(_, field)| NONDETERMINISTIC_IMPORTS.contains(&field.as_str()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// a module with a type section and an import of env.seed
+    fn seeding_module() -> DnaWasm {
+        let mut code = b"\0asm".to_vec();
+        code.extend_from_slice(&[1, 0, 0, 0]);
+        // type section: one function type without parameters and results
+        code.extend_from_slice(&[1, 4, 1, 0x60, 0, 0]);
+        // import section: env.seed as a function of type 0
+        code.extend_from_slice(&[2, 12, 1, 3]);
+        code.extend_from_slice(b"env");
+        code.push(4);
+        code.extend_from_slice(b"seed");
+        code.extend_from_slice(&[0, 0]);
+        DnaWasm { code }
+    }
+
+    #[test]
+    /// imports are read from the import section, other sections are skipped
+    fn imports_test() {
+        let wasm = seeding_module();
+        let seed = ("env".to_string(), "seed".to_string());
+        assert_eq!(Some(vec![seed.clone()]), wasm.imports());
+        assert_eq!(vec![seed], wasm.nondeterministic_imports());
+
+        assert_eq!(None, DnaWasm::new().imports());
+        assert!(DnaWasm::new().nondeterministic_imports().is_empty());
+    }
+
+    #[test]
+    /// exports are read from the export section, after the sections before it
+    fn exports_test() {
+        let mut wasm = seeding_module();
+        assert_eq!(Some(Vec::new()), wasm.exports());
+        // export section: the imported function as "genesis" and memory 0 as "memory"
+        wasm.code.extend_from_slice(&[7, 20, 2, 7]);
+        wasm.code.extend_from_slice(b"genesis");
+        wasm.code.extend_from_slice(&[0, 0, 6]);
+        wasm.code.extend_from_slice(b"memory");
+        wasm.code.extend_from_slice(&[2, 0]);
+        assert_eq!(
+            Some(vec!["genesis".to_string(), "memory".to_string()]),
+            wasm.exports()
+        );
+        assert_eq!(None, DnaWasm::new().exports());
+    }
+}
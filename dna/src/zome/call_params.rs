@@ -0,0 +1,156 @@
+//! holochain_dna::zome::call_params builds the params string of a zome call the way the
+//! called fn declares it wants its arguments, @see capabilities::ParamsEncoding
+
+use holochain_core_types::error::DnaError;
+use serde_json::{self, Map, Value};
+use zome::capabilities::{FnDeclaration, ParamsEncoding};
+
+/// The named arguments of a zome call, in the order they were given.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CallParams {
+    args: Vec<(String, Value)>,
+}
+
+impl CallParams {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// adds the argument for the input with the given name
+    pub fn arg<V: Into<Value>>(mut self, name: &str, value: V) -> Self {
+        self.args.push((name.to_string(), value.into()));
+        self
+    }
+
+    fn get(&self, name: &str) -> Option<&Value> {
+        self.args
+            .iter()
+            .find(|(arg_name, _)| arg_name == name)
+            .map(|(_, value)| value)
+    }
+
+    /// the params string to call the declared fn with
+    /// fns declaring no inputs take whatever arguments are given, others need an argument per
+    /// required input and may get one per optional input; fails with InvalidCallParams
+    /// otherwise
+    pub fn encode(&self, fn_declaration: &FnDeclaration) -> Result<String, DnaError> {
+        let invalid = |reason: String| {
+            Err(DnaError::InvalidCallParams(format!(
+                "invalid params for {}: {}",
+                fn_declaration.name, reason
+            )))
+        };
+        let inputs = &fn_declaration.inputs;
+        if !inputs.is_empty() {
+            let required = inputs.iter().filter(|input| !input.optional).count();
+            if self.args.len() < required || self.args.len() > inputs.len() {
+                let expected = if required == inputs.len() {
+                    required.to_string()
+                } else {
+                    format!("{} to {}", required, inputs.len())
+                };
+                return invalid(format!(
+                    "expected {} arguments, got {}",
+                    expected,
+                    self.args.len()
+                ));
+            }
+            if let Some(input) = inputs
+                .iter()
+                .find(|input| !input.optional && self.get(&input.name).is_none())
+            {
+                return invalid(format!("missing argument {}", input.name));
+            }
+            if let Some((name, _)) = self
+                .args
+                .iter()
+                .find(|(name, _)| !inputs.iter().any(|input| &input.name == name))
+            {
+                return invalid(format!("unknown argument {}", name));
+            }
+        }
+        // optional inputs left out are passed as null where arguments are positional
+        let ordered_values: Vec<Value> = if inputs.is_empty() {
+            self.args.iter().map(|(_, value)| value.clone()).collect()
+        } else {
+            inputs
+                .iter()
+                .map(|input| self.get(&input.name).cloned().unwrap_or(Value::Null))
+                .collect()
+        };
+        match fn_declaration.params_encoding {
+            ParamsEncoding::JsonObject => {
+                let fields: Map<String, Value> = self.args.iter().cloned().collect();
+                Ok(Value::Object(fields).to_string())
+            }
+            ParamsEncoding::JsonArray => Ok(Value::Array(ordered_values).to_string()),
+            ParamsEncoding::Raw => match ordered_values.as_slice() {
+                [Value::String(raw)] => Ok(raw.clone()),
+                [value] => Ok(serde_json::to_string(value).expect("JSON values serialize")),
+                _ => invalid(format!(
+                    "raw params take a single argument, got {}",
+                    ordered_values.len()
+                )),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use zome::capabilities::FnParameter;
+
+    fn declaration(encoding: ParamsEncoding, inputs: &[&str]) -> FnDeclaration {
+        let mut fn_declaration = FnDeclaration::new();
+        fn_declaration.name = "add".to_string();
+        fn_declaration.params_encoding = encoding;
+        fn_declaration.inputs = inputs
+            .iter()
+            .map(|name| FnParameter {
+                name: name.to_string(),
+                parameter_type: "number".to_string(),
+                optional: false,
+            })
+            .collect();
+        fn_declaration
+    }
+
+    #[test]
+    /// arguments are encoded as declared, positional ones in the order of the inputs
+    fn encode_test() {
+        let params = CallParams::new().arg("b", 2).arg("a", 1);
+        let json_object = declaration(ParamsEncoding::JsonObject, &["a", "b"]);
+        assert_eq!(
+            Ok(r#"{"b":2,"a":1}"#.to_string()),
+            params.encode(&json_object)
+        );
+        let json_array = declaration(ParamsEncoding::JsonArray, &["a", "b"]);
+        assert_eq!(Ok("[1,2]".to_string()), params.encode(&json_array));
+
+        let raw = declaration(ParamsEncoding::Raw, &["text"]);
+        let text = CallParams::new().arg("text", "hello");
+        assert_eq!(Ok("hello".to_string()), text.encode(&raw));
+    }
+
+    #[test]
+    /// the arguments must match the declared inputs
+    fn encode_checks_arity() {
+        let json_array = declaration(ParamsEncoding::JsonArray, &["a", "b"]);
+        assert_eq!(
+            Err(DnaError::InvalidCallParams(
+                "invalid params for add: expected 2 arguments, got 1".to_string()
+            )),
+            CallParams::new().arg("a", 1).encode(&json_array)
+        );
+        assert_eq!(
+            Err(DnaError::InvalidCallParams(
+                "invalid params for add: missing argument b".to_string()
+            )),
+            CallParams::new()
+                .arg("a", 1)
+                .arg("c", 3)
+                .encode(&json_array)
+        );
+    }
+}
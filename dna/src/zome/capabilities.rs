@@ -96,6 +96,14 @@ pub struct FnParameter {
     #[serde(rename = "type")]
     pub parameter_type: String,
     pub name: String,
+    /// Whether calls may leave this parameter out.
+    /// Left out when false, so the addresses of existing DNAs don't change.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub optional: bool,
+}
+
+pub(crate) fn is_false(b: &bool) -> bool {
+    !*b
 }
 
 impl FnParameter {
@@ -104,10 +112,39 @@ impl FnParameter {
         FnParameter {
             name: n.into(),
             parameter_type: t.into(),
+            optional: false,
         }
     }
 }
 
+/// Enum for the "params_encoding" property of a "fn_declarations" object:
+/// how the arguments of a call are passed to the function.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ParamsEncoding {
+    /// a JSON object with a field per input
+    #[serde(rename = "json-object")]
+    JsonObject,
+    /// a JSON array with the inputs in the order they are declared in
+    #[serde(rename = "json-array")]
+    JsonArray,
+    /// the single input as is, without any encoding
+    #[serde(rename = "raw")]
+    Raw,
+}
+
+impl Default for ParamsEncoding {
+    /// Default params encoding is a JSON object
+    fn default() -> Self {
+        ParamsEncoding::JsonObject
+    }
+}
+
+impl ParamsEncoding {
+    pub fn is_default(&self) -> bool {
+        *self == ParamsEncoding::default()
+    }
+}
+
 /// Represents a zome "fn_declarations" object.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash)]
 pub struct FnDeclaration {
@@ -116,6 +153,10 @@ pub struct FnDeclaration {
     pub name: String,
     pub inputs: Vec<FnParameter>,
     pub outputs: Vec<FnParameter>,
+    /// How the arguments of a call are passed to this fn.
+    /// Left out when it is the default, so the addresses of existing DNAs don't change.
+    #[serde(default, skip_serializing_if = "ParamsEncoding::is_default")]
+    pub params_encoding: ParamsEncoding,
 }
 
 impl Default for FnDeclaration {
@@ -125,15 +166,106 @@ impl Default for FnDeclaration {
             name: String::new(),
             inputs: Vec::new(),
             outputs: Vec::new(),
+            params_encoding: ParamsEncoding::default(),
         }
     }
 }
 
+/// How a fn declaration changed from an earlier version of it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompatibilityResult {
+    /// Nothing changed.
+    Identical,
+    /// Existing callers keep working, with the changes made.
+    Compatible(Vec<String>),
+    /// Existing callers break, with the changes that break them.
+    Breaking(Vec<String>),
+}
+
 impl FnDeclaration {
     /// Allow sane defaults for `FnDecrlaration::new()`.
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Classifies how this declaration changed from the earlier declaration `other`.
+    /// Adding optional inputs or any outputs is compatible. Removing, renaming or retyping
+    /// inputs or outputs, adding required inputs, making inputs required and changing how
+    /// the params are encoded is breaking.
+    pub fn compatible_with(&self, other: &FnDeclaration) -> CompatibilityResult {
+        let mut compatible = Vec::new();
+        let mut breaking = Vec::new();
+        let find =
+            |params: &[FnParameter], name: &str| params.iter().position(|param| param.name == name);
+
+        if self.name != other.name {
+            breaking.push(format!("renamed from {} to {}", other.name, self.name));
+        }
+        if self.params_encoding != other.params_encoding {
+            breaking.push(format!(
+                "params encoding changed from {:?} to {:?}",
+                other.params_encoding, self.params_encoding
+            ));
+        }
+        for (old_position, old) in other.inputs.iter().enumerate() {
+            let position = match find(&self.inputs, &old.name) {
+                Some(position) => position,
+                None => {
+                    breaking.push(format!("input {} removed", old.name));
+                    continue;
+                }
+            };
+            let new = &self.inputs[position];
+            if new.parameter_type != old.parameter_type {
+                breaking.push(format!(
+                    "input {} retyped from {} to {}",
+                    old.name, old.parameter_type, new.parameter_type
+                ));
+            }
+            if old.optional && !new.optional {
+                breaking.push(format!("input {} made required", old.name));
+            } else if !old.optional && new.optional {
+                compatible.push(format!("input {} made optional", old.name));
+            }
+            if position != old_position && self.params_encoding == ParamsEncoding::JsonArray {
+                breaking.push(format!("input {} moved", old.name));
+            }
+        }
+        for new in self.inputs.iter() {
+            if find(&other.inputs, &new.name).is_none() {
+                if new.optional {
+                    compatible.push(format!("optional input {} added", new.name));
+                } else {
+                    breaking.push(format!("required input {} added", new.name));
+                }
+            }
+        }
+        for old in other.outputs.iter() {
+            match find(&self.outputs, &old.name) {
+                None => breaking.push(format!("output {} removed", old.name)),
+                Some(position) if self.outputs[position].parameter_type != old.parameter_type => {
+                    breaking.push(format!(
+                        "output {} retyped from {} to {}",
+                        old.name, old.parameter_type, self.outputs[position].parameter_type
+                    ))
+                }
+                Some(_) => (),
+            }
+        }
+        for new in self.outputs.iter() {
+            if find(&other.outputs, &new.name).is_none() {
+                compatible.push(format!("output {} added", new.name));
+            }
+        }
+
+        if !breaking.is_empty() {
+            CompatibilityResult::Breaking(breaking)
+        } else if !compatible.is_empty() {
+            CompatibilityResult::Compatible(compatible)
+        } else {
+            CompatibilityResult::Identical
+        }
+    }
 }
 
 /// Represents an individual object in the "zome" "capabilities" array.
@@ -235,4 +367,44 @@ mod tests {
 
         assert_eq!(fixture, cap);
     }
+
+    fn add_declaration() -> FnDeclaration {
+        let mut fn_dec = FnDeclaration::new();
+        fn_dec.name = String::from("add");
+        fn_dec.inputs.push(FnParameter::new("a", "number"));
+        fn_dec.inputs.push(FnParameter::new("b", "number"));
+        fn_dec.outputs.push(FnParameter::new("sum", "number"));
+        fn_dec
+    }
+
+    #[test]
+    /// an added optional input keeps existing callers working
+    fn added_optional_input_is_compatible() {
+        let old = add_declaration();
+        assert_eq!(CompatibilityResult::Identical, old.compatible_with(&old));
+
+        let mut new = add_declaration();
+        let mut c = FnParameter::new("c", "number");
+        c.optional = true;
+        new.inputs.push(c);
+        assert_eq!(
+            CompatibilityResult::Compatible(vec!["optional input c added".to_string()]),
+            new.compatible_with(&old)
+        );
+    }
+
+    #[test]
+    /// renaming a required input breaks callers passing it by its old name
+    fn renamed_required_input_is_breaking() {
+        let old = add_declaration();
+        let mut new = add_declaration();
+        new.inputs[1].name = String::from("addend");
+        assert_eq!(
+            CompatibilityResult::Breaking(vec![
+                "input b removed".to_string(),
+                "required input addend added".to_string(),
+            ]),
+            new.compatible_with(&old)
+        );
+    }
 }
@@ -1,5 +1,7 @@
 //! File holding all the structs for handling entry types defined by DNA.
 
+use zome::capabilities::is_false;
+
 /// Enum for Zome EntryType "sharing" property.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash)]
 pub enum Sharing {
@@ -9,6 +11,10 @@ pub enum Sharing {
     Private,
     #[serde(rename = "encrypted")]
     Encrypted,
+    /// transient data, e.g. presence: kept in memory by the agent only, never put on the
+    /// source chain, published or persisted
+    #[serde(rename = "ephemeral")]
+    Ephemeral,
 }
 
 impl Sharing {
@@ -18,6 +24,7 @@ impl Sharing {
            Sharing::Public    => true,
            Sharing::Private   => false,
            Sharing::Encrypted => true,
+           Sharing::Ephemeral => false,
        }
     }
 }
@@ -29,6 +36,34 @@ impl Default for Sharing {
     }
 }
 
+/// How the entries of a type get their addresses
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash)]
+pub enum AddressDerivation {
+    /// hashed from the whole content, like any other entry
+    #[serde(rename = "whole_content")]
+    WholeContent,
+    /// derived from the value of the named top-level field alone, so the entry is found by
+    /// that value without an index; no two entries of the type can have the same value
+    #[serde(rename = "by_field")]
+    ByField(String),
+    /// derived from the key the address deriver the instance registered under the name
+    /// makes of the content, @see Context::set_address_deriver()
+    #[serde(rename = "custom")]
+    Custom(String),
+}
+
+impl AddressDerivation {
+    pub fn is_whole_content(&self) -> bool {
+        *self == AddressDerivation::WholeContent
+    }
+}
+
+impl Default for AddressDerivation {
+    fn default() -> Self {
+        AddressDerivation::WholeContent
+    }
+}
+
 /// An individual object in a "links_to" array.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash)]
 pub struct LinksTo {
@@ -106,6 +141,42 @@ pub struct EntryTypeDef {
     /// An array of link definitions for links pointing to entries of this type
     #[serde(default)]
     pub linked_from: Vec<LinkedFrom>,
+
+    /// Names of top-level fields of the entry's JSON value that get a secondary index,
+    /// so entries can be found by the value of these fields
+    /// not serialized when empty so existing DNA JSON (and its address) is unchanged
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub indexed_fields: Vec<String>,
+
+    /// Names of top-level fields no two entries of this type may have the same value in,
+    /// e.g. a username; they get a secondary index like the indexed fields
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unique_fields: Vec<String>,
+
+    /// MIME-like type of the content of entries of this type, e.g. "application/json"
+    /// recorded in the chain header of commits that don't give their own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+
+    /// Name of a function in the same zome called with the address of every committed entry
+    /// of this type, after the commit is on the chain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_commit: Option<String>,
+
+    /// Seconds after which committed entries of this type expire and are marked as deleted
+    /// None for entries that don't expire
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u64>,
+
+    /// Whether committed entries of this type get a random nonce in their addressed content,
+    /// so entries with the same low-entropy value, e.g. a yes/no vote, can't be enumerated
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub nonced: bool,
+
+    /// How committed entries of this type are addressed, from their whole content unless
+    /// declared otherwise
+    #[serde(default, skip_serializing_if = "AddressDerivation::is_whole_content")]
+    pub address_derivation: AddressDerivation,
 }
 
 impl Default for EntryTypeDef {
@@ -116,6 +187,13 @@ impl Default for EntryTypeDef {
             sharing: Sharing::Public,
             links_to: Vec::new(),
             linked_from: Vec::new(),
+            indexed_fields: Vec::new(),
+            unique_fields: Vec::new(),
+            content_type: None,
+            post_commit: None,
+            ttl: None,
+            nonced: false,
+            address_derivation: AddressDerivation::WholeContent,
         }
     }
 }
@@ -125,6 +203,17 @@ impl EntryTypeDef {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// the fields entries of this type get a secondary index for, unique fields included
+    pub fn index_fields(&self) -> Vec<String> {
+        let mut fields = self.indexed_fields.clone();
+        for field in self.unique_fields.iter() {
+            if !fields.contains(field) {
+                fields.push(field.clone());
+            }
+        }
+        fields
+    }
 }
 
 #[cfg(test)]
@@ -136,6 +225,7 @@ mod tests {
     fn can_publish() {
         assert!(Sharing::Public.can_publish());
         assert!(!Sharing::Private.can_publish());
+        assert!(!Sharing::Ephemeral.can_publish());
     }
 
     #[test]
@@ -155,7 +245,10 @@ mod tests {
                         "base_type": "HcSysAgentKeyHash",
                         "tag": "authored_posts"
                     }
-                ]
+                ],
+                "indexed_fields": ["author"],
+                "post_commit": "update_author_index",
+                "address_derivation": {"by_field": "slug"}
             }"#,
         ).unwrap();
 
@@ -173,6 +266,10 @@ mod tests {
         linked.tag = String::from("authored_posts");
         entry.linked_from.push(linked);
 
+        entry.indexed_fields.push(String::from("author"));
+        entry.post_commit = Some(String::from("update_author_index"));
+        entry.address_derivation = AddressDerivation::ByField(String::from("slug"));
+
         assert_eq!(fixture, entry);
     }
 }
@@ -1,5 +1,6 @@
 //! holochain_dna::zome is a set of structs for working with holochain dna.
 
+pub mod call_params;
 pub mod capabilities;
 pub mod entry_types;
 
@@ -232,6 +232,8 @@ pub fn commit_entry(
     let input = CommitEntryArgs {
         entry_type_name: entry_type_name.to_string(),
         entry_value: entry_content.to_string(),
+        content_type: None,
+        acl: None,
     };
     let maybe_allocation_of_input = store_as_json(&mut mem_stack, input);
     if let Err(err_code) = maybe_allocation_of_input {
@@ -140,6 +140,11 @@ impl Logger for TestLogger {
     fn log(&mut self, msg: String) {
         self.log.push(msg);
     }
+
+    fn recent_lines(&self, count: usize) -> Vec<String> {
+        let start = self.log.len().saturating_sub(count);
+        self.log[start..].to_vec()
+    }
 }
 
 // trying to get a way to print out what has been logged for tests without a read function.
@@ -5,6 +5,12 @@ use holochain_core_types::{cas::content::Address, hash::HashString};
 pub struct CommitEntryArgs {
     pub entry_type_name: String,
     pub entry_value: String,
+    /// MIME-like type of entry_value, defaults to the one declared in the DNA
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// agents allowed to get the entry besides its author, everyone if None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acl: Option<Vec<Address>>,
 }
 #[derive(Deserialize, Serialize, Default, Debug)]
 pub struct CommitEntryResult {